@@ -0,0 +1,46 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use pflow_metamodel::analysis::{explore, Limits};
+use pflow_metamodel::generators::{philosophers, pipeline};
+use pflow_metamodel::vasm::{StateMachine, Vasm};
+
+fn transform(c: &mut Criterion) {
+    let sm = StateMachine::from_model(&mut philosophers(100));
+    let state = sm.initial_vector();
+    c.bench_function("transform philosophers(100)", |b| {
+        b.iter(|| sm.transform(black_box(&state), "eat50", 1))
+    });
+}
+
+fn from_model(c: &mut Criterion) {
+    let net = philosophers(100);
+    c.bench_function("from_model philosophers(100)", |b| {
+        b.iter(|| StateMachine::from_model(&mut black_box(&net).clone()))
+    });
+}
+
+fn reachability(c: &mut Criterion) {
+    let mut group = c.benchmark_group("explore");
+    group.sample_size(10);
+    let sm = StateMachine::from_model(&mut philosophers(8));
+    group.bench_function("philosophers(8)", |b| {
+        b.iter(|| explore(black_box(&sm), Limits::default()))
+    });
+    let sm = StateMachine::from_model(&mut pipeline(8, 4));
+    group.bench_function("pipeline(8, 4)", |b| {
+        b.iter(|| explore(black_box(&sm), Limits::default()))
+    });
+    group.finish();
+}
+
+fn compression(c: &mut Criterion) {
+    let net = philosophers(100);
+    let zblob = net.to_zblob();
+    let mut group = c.benchmark_group("zblob philosophers(100)");
+    group.bench_function("compress", |b| b.iter(|| black_box(&net).to_zblob()));
+    group.bench_function("decompress", |b| b.iter(|| black_box(&zblob).to_net()));
+    group.finish();
+}
+
+criterion_group!(benches, transform, from_model, reachability, compression);
+criterion_main!(benches);
@@ -0,0 +1,26 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use pflow_metamodel::binary::{from_cbor, from_msgpack, to_cbor, to_msgpack};
+use pflow_metamodel::fixtures::DINING_PHILOSOPHERS;
+use pflow_metamodel::petri_net::PetriNet;
+
+fn encoding(c: &mut Criterion) {
+    let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+    let zblob = net.to_zblob();
+    let cbor = to_cbor(&net).unwrap();
+    let msgpack = to_msgpack(&net).unwrap();
+    let mut group = c.benchmark_group("encode");
+    group.bench_function("zblob", |b| b.iter(|| black_box(&net).to_zblob()));
+    group.bench_function("cbor", |b| b.iter(|| to_cbor(black_box(&net)).unwrap()));
+    group.bench_function("msgpack", |b| b.iter(|| to_msgpack(black_box(&net)).unwrap()));
+    group.finish();
+
+    let mut group = c.benchmark_group("decode");
+    group.bench_function("zblob", |b| b.iter(|| black_box(&zblob).to_net()));
+    group.bench_function("cbor", |b| b.iter(|| from_cbor::<PetriNet>(black_box(&cbor)).unwrap()));
+    group.bench_function("msgpack", |b| b.iter(|| from_msgpack::<PetriNet>(black_box(&msgpack)).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, encoding);
+criterion_main!(benches);
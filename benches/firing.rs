@@ -0,0 +1,109 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use pflow_metamodel::petri_net::PetriNet;
+use pflow_metamodel::vasm::{StateMachine, Vasm};
+
+/// A wide net where every transition is guarded by an inhibitor arc on each of the other lanes.
+fn guarded_lanes(lanes: usize) -> StateMachine {
+    let mut net = PetriNet::new();
+    for i in 0..lanes {
+        net.add_place(&format!("ready{}", i), 2 * i as i32, Some(1), None, 0, 0);
+        net.add_place(&format!("done{}", i), 2 * i as i32 + 1, None, None, 0, 0);
+        net.add_transition(&format!("run{}", i), "default", 0, 0);
+        net.add_arc(
+            &format!("ready{}", i),
+            &format!("run{}", i),
+            Some(1),
+            None,
+            None,
+            None,
+            None,
+        );
+        net.add_arc(
+            &format!("run{}", i),
+            &format!("done{}", i),
+            Some(1),
+            None,
+            None,
+            None,
+            None,
+        );
+    }
+    for i in 0..lanes {
+        for j in (0..lanes).filter(|j| *j != i) {
+            net.add_arc(
+                &format!("done{}", j),
+                &format!("run{}", i),
+                Some(1),
+                None,
+                None,
+                Some(true),
+                None,
+            );
+        }
+    }
+    StateMachine::from_model(&mut net)
+}
+
+/// A long chain of places, each transition moves the token one place further.
+fn chain(places: usize) -> StateMachine {
+    let mut net = PetriNet::new();
+    for i in 0..places {
+        net.add_place(&format!("p{}", i), i as i32, Some((i == 0) as i32), None, 0, 0);
+    }
+    for i in 1..places {
+        net.add_transition(&format!("t{}", i), "default", 0, 0);
+        net.add_arc(
+            &format!("p{}", i - 1),
+            &format!("t{}", i),
+            Some(1),
+            None,
+            None,
+            None,
+            None,
+        );
+        net.add_arc(&format!("t{}", i), &format!("p{}", i), Some(1), None, None, None, None);
+    }
+    StateMachine::from_model(&mut net)
+}
+
+fn firing(c: &mut Criterion) {
+    let sm = guarded_lanes(32);
+    let mut blocked = sm.initial_vector();
+    blocked[1] = 1;
+
+    let mut group = c.benchmark_group("firing");
+    group.bench_function("transform enabled", |b| {
+        b.iter(|| sm.transform(black_box(&sm.initial_vector()), "run0", 1))
+    });
+    let compiled = sm.compile();
+    let run0 = compiled.id("run0").unwrap();
+    group.bench_function("compiled fire enabled", |b| {
+        b.iter(|| compiled.fire(black_box(&sm.initial_vector()), run0, 1))
+    });
+    group.bench_function("transform inhibited", |b| {
+        b.iter(|| sm.transform(black_box(&blocked), "run3", 1))
+    });
+    group.bench_function("is_enabled inhibited", |b| {
+        b.iter(|| sm.is_enabled(black_box(&blocked), "run3", 1))
+    });
+    group.bench_function("enabled set", |b| {
+        b.iter(|| {
+            sm.transitions
+                .keys()
+                .filter(|t| sm.is_enabled(black_box(&blocked), t, 1))
+                .count()
+        })
+    });
+    group.finish();
+
+    // Thousands of places make vector_add the hot path, compare with `--features simd`.
+    let wide = chain(4096);
+    let state = wide.initial_vector();
+    c.bench_function("vector_add wide", |b| {
+        b.iter(|| wide.transform(black_box(&state), "t1", 1))
+    });
+}
+
+criterion_group!(benches, firing);
+criterion_main!(benches);
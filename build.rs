@@ -0,0 +1,42 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc::compile();
+}
+
+/// Generates the `ModelService` server and client from the messages declared by hand in `src/proto.rs`,
+/// so building does not need `protoc`. Keep in sync with the service in `proto/pflow.proto`.
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    const METHODS: [(&str, &str, &str, &str); 5] = [
+        ("store_model", "StoreModel", "StoreModelRequest", "StoreModelResponse"),
+        ("get_model", "GetModel", "GetModelRequest", "GetModelResponse"),
+        ("fire_transition", "FireTransition", "FireTransitionRequest", "Transaction"),
+        (
+            "get_enabled_transitions",
+            "GetEnabledTransitions",
+            "GetEnabledTransitionsRequest",
+            "GetEnabledTransitionsResponse",
+        ),
+        ("analyze_model", "AnalyzeModel", "AnalyzeModelRequest", "AnalyzeModelResponse"),
+    ];
+
+    pub fn compile() {
+        let service = METHODS.iter().fold(
+            Service::builder().name("ModelService").package("pflow.metamodel.v0"),
+            |service, (name, route, input, output)| {
+                service.method(
+                    Method::builder()
+                        .name(name)
+                        .route_name(route)
+                        .input_type(format!("crate::proto::{}", input))
+                        .output_type(format!("crate::proto::{}", output))
+                        .codec_path("tonic::codec::ProstCodec")
+                        .build(),
+                )
+            },
+        );
+        Builder::new().compile(&[service.build()]);
+    }
+}
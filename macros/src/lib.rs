@@ -0,0 +1,209 @@
+//! > **Procedural macros for pflow-metamodel**
+//!
+//! - `petri_net!` declares a Petri-net at compile time and expands to `FlowDsl` calls.
+
+use std::collections::HashMap;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{braced, parenthesized, parse_macro_input, Error, Ident, LitInt, Token};
+
+mod kw {
+    syn::custom_keyword!(places);
+    syn::custom_keyword!(transitions);
+    syn::custom_keyword!(arcs);
+    syn::custom_keyword!(role);
+}
+
+/// A place declared as `name`, `name(initial)` or `name(initial, capacity)`.
+struct PlaceDecl {
+    name: Ident,
+    initial: Option<LitInt>,
+    capacity: Option<LitInt>,
+}
+
+impl Parse for PlaceDecl {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: Ident = input.parse()?;
+        let mut initial = None;
+        let mut capacity = None;
+        if input.peek(syn::token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            initial = Some(content.parse()?);
+            if content.parse::<Option<Token![,]>>()?.is_some() {
+                capacity = Some(content.parse()?);
+            }
+        }
+        Ok(Self { name, initial, capacity })
+    }
+}
+
+/// A transition declared as `name` or `name: role(r)`.
+struct TransitionDecl {
+    name: Ident,
+    role: Option<Ident>,
+}
+
+impl Parse for TransitionDecl {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: Ident = input.parse()?;
+        let mut role = None;
+        if input.parse::<Option<Token![:]>>()?.is_some() {
+            input.parse::<kw::role>()?;
+            let content;
+            parenthesized!(content in input);
+            role = Some(content.parse()?);
+        }
+        Ok(Self { name, role })
+    }
+}
+
+/// A chain of arcs such as `p1 -1-> t1 -> p2`, each link is stored with its weight.
+struct ArcChain {
+    nodes: Vec<Ident>,
+    weights: Vec<LitInt>,
+}
+
+impl Parse for ArcChain {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut nodes = vec![input.parse::<Ident>()?];
+        let mut weights = Vec::new();
+        loop {
+            let weight = if input.peek(Token![->]) {
+                input.parse::<Token![->]>()?;
+                LitInt::new("1", input.span())
+            } else if input.peek(Token![-]) {
+                input.parse::<Token![-]>()?;
+                let weight: LitInt = input.parse()?;
+                input.parse::<Token![->]>()?;
+                weight
+            } else {
+                break;
+            };
+            weights.push(weight);
+            nodes.push(input.parse()?);
+        }
+        if weights.is_empty() {
+            return Err(input.error("expected `->` or `-N->`"));
+        }
+        Ok(Self { nodes, weights })
+    }
+}
+
+struct NetDecl {
+    places: Punctuated<PlaceDecl, Token![,]>,
+    transitions: Punctuated<TransitionDecl, Token![,]>,
+    arcs: Punctuated<ArcChain, Token![,]>,
+}
+
+impl Parse for NetDecl {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<kw::places>()?;
+        let content;
+        braced!(content in input);
+        let places = content.parse_terminated(PlaceDecl::parse, Token![,])?;
+
+        input.parse::<kw::transitions>()?;
+        let content;
+        braced!(content in input);
+        let transitions = content.parse_terminated(TransitionDecl::parse, Token![,])?;
+
+        input.parse::<kw::arcs>()?;
+        let content;
+        braced!(content in input);
+        let arcs = content.parse_terminated(ArcChain::parse, Token![,])?;
+        Ok(Self { places, transitions, arcs })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Node {
+    Place,
+    Transition,
+}
+
+/// Declares a Petri-net at compile time.
+///
+/// The macro expands to a closure that can be passed wherever a `fn(&mut dyn FlowDsl)` declaration is expected.
+/// Arcs must alternate between declared places and transitions, which is checked at compile time.
+///
+/// ```ignore
+/// let sm = StateMachine::new(petri_net! {
+///     places { p1(1), p2 }
+///     transitions { t1: role(user) }
+///     arcs { p1 -1-> t1 -> p2 }
+/// });
+/// ```
+#[proc_macro]
+pub fn petri_net(input: TokenStream) -> TokenStream {
+    let net = parse_macro_input!(input as NetDecl);
+    match expand(&net) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(net: &NetDecl) -> syn::Result<proc_macro2::TokenStream> {
+    let mut nodes: HashMap<String, Node> = HashMap::new();
+    let mut statements = Vec::new();
+
+    for place in &net.places {
+        let label = place.name.to_string();
+        if nodes.insert(label.clone(), Node::Place).is_some() {
+            return Err(Error::new(place.name.span(), format!("duplicate node `{}`", label)));
+        }
+        let initial = option_tokens(&place.initial);
+        let capacity = option_tokens(&place.capacity);
+        statements.push(quote! { p.cell(#label, #initial, #capacity, 0, 0); });
+    }
+
+    for transition in &net.transitions {
+        let label = transition.name.to_string();
+        if nodes.insert(label.clone(), Node::Transition).is_some() {
+            return Err(Error::new(transition.name.span(), format!("duplicate node `{}`", label)));
+        }
+        let role = transition
+            .role
+            .as_ref()
+            .map_or("default".to_string(), |r| r.to_string());
+        statements.push(quote! { p.func(#label, #role, 0, 0); });
+    }
+
+    for chain in &net.arcs {
+        for (i, weight) in chain.weights.iter().enumerate() {
+            let (source, target) = (&chain.nodes[i], &chain.nodes[i + 1]);
+            let kind = |node: &Ident| {
+                nodes.get(&node.to_string()).copied().ok_or_else(|| {
+                    Error::new(node.span(), format!("undeclared node `{}`", node))
+                })
+            };
+            if kind(source)? == kind(target)? {
+                return Err(Error::new(
+                    target.span(),
+                    "arcs must connect a place and a transition",
+                ));
+            }
+            if weight.base10_parse::<i32>()? <= 0 {
+                return Err(Error::new(weight.span(), "weight must be positive"));
+            }
+            let (source, target) = (source.to_string(), target.to_string());
+            statements.push(quote! { p.arrow(#source, #target, #weight); });
+        }
+    }
+
+    Ok(quote! {
+        |p: &mut dyn ::pflow_metamodel::dsl::FlowDsl| {
+            #(#statements)*
+        }
+    })
+}
+
+fn option_tokens(value: &Option<LitInt>) -> proc_macro2::TokenStream {
+    match value {
+        Some(v) => quote! { ::std::option::Option::Some(#v) },
+        None => quote! { ::std::option::Option::None },
+    }
+}
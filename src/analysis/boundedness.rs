@@ -0,0 +1,268 @@
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+#[cfg(feature = "parallel")]
+use crate::analysis::reachability::{expand_level, with_workers};
+use crate::analysis::reachability::{sorted_actions, Limits};
+#[cfg(feature = "parallel")]
+use crate::analysis::store::SharedStore;
+use crate::analysis::store::{StateId, StateStore};
+use crate::capacity::Capacity;
+use crate::vasm::{ModelType, StateMachine, Vasm, Vector};
+use crate::vector;
+
+/// `Boundedness` is the result of checking whether the places of a net can hold arbitrarily many tokens.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum Boundedness {
+    /// No place ever holds more than `k` tokens, and `k` is the smallest such bound.
+    Bounded { k: i32 },
+    /// Firing `pump` after `prefix` strictly increases `place` without decreasing any other place,
+    /// so it can be repeated forever.
+    Unbounded {
+        place: String,
+        prefix: Vec<String>,
+        pump: Vec<String>,
+    },
+    /// The limits were reached before a bound or a pumping sequence was found,
+    /// `k` is the largest number of tokens seen in a place so far.
+    Unknown { k: i32 },
+}
+
+impl Boundedness {
+    /// Checks if the net is k-bounded for the given `k`.
+    pub fn is_k_bounded(&self, k: i32) -> bool {
+        matches!(self, Boundedness::Bounded { k: bound } if *bound <= k)
+    }
+
+    /// Checks if the net is safe (1-bounded).
+    pub fn is_safe(&self) -> bool {
+        self.is_k_bounded(1)
+    }
+}
+
+/// Checks the boundedness of the state machine exploring at most `Limits::default()` states.
+pub fn boundedness(sm: &StateMachine) -> Boundedness {
+    boundedness_within(sm, Limits::default())
+}
+
+/// Checks the boundedness of the state machine exploring at most `limits.max_states` states.
+///
+/// A reached state strictly covering one of its ancestors proves the net unbounded (Karp-Miller).
+/// Elementary and workflow models are checked as plain place/transition nets without their
+/// implicit capacity of one, so the result tells whether the net really is safe.
+/// Inhibitor arcs break monotonicity, so pumping sequences are replayed once before they are reported.
+pub fn boundedness_within(sm: &StateMachine, limits: Limits) -> Boundedness {
+    let sm = &as_place_transition_net(sm);
+    let actions = sorted_actions(sm);
+    let mut states = StateStore::from_iter([sm.initial_vector()]);
+    let mut parents: Vec<Option<(StateId, &String)>> = vec![None];
+    let mut queue = VecDeque::from([0]);
+    let mut complete = true;
+
+    while let Some(i) = queue.pop_front() {
+        for action in &actions {
+            let res = sm.transform(&states[i], action, 1);
+            if res.is_err() || states.get(&res.output).is_some() {
+                continue;
+            }
+            if states.len() >= limits.max_states {
+                complete = false;
+                continue;
+            }
+            let (j, _) = states.intern(res.output);
+            parents.push(Some((i, action)));
+            if let Some(unbounded) = find_pump(sm, &states, &parents, j) {
+                return unbounded;
+            }
+            queue.push_back(j);
+        }
+    }
+
+    let k = states.iter().flatten().copied().max().unwrap_or(0);
+    if complete {
+        Boundedness::Bounded { k }
+    } else {
+        Boundedness::Unknown { k }
+    }
+}
+
+/// Checks the boundedness like `boundedness_within`, expanding each breadth-first level on `workers` threads,
+/// or on the global rayon pool if `workers` is zero, see `explore_parallel`.
+///
+/// New states are checked in the order of the single-threaded search, so the same bound or pumping sequence is found.
+#[cfg(feature = "parallel")]
+pub fn boundedness_parallel(sm: &StateMachine, limits: Limits, workers: usize) -> Boundedness {
+    let sm = &as_place_transition_net(sm);
+    with_workers(workers, || {
+        let actions = sorted_actions(sm);
+        let visited = SharedStore::new(sm.initial_vector());
+        let mut states = StateStore::from_iter([sm.initial_vector()]);
+        let mut parents: Vec<Option<(StateId, &String)>> = vec![None];
+        let mut level = 0..1;
+        let mut complete = true;
+
+        while !level.is_empty() {
+            let room = limits.max_states.saturating_sub(states.len());
+            let expanded = expand_level(sm, &actions, &states[level.clone()], &visited, states.len(), room);
+            complete &= expanded.successors.iter().flatten().all(|(_, target)| target.is_some());
+            let next = states.len();
+            for (offset, action, output) in expanded.found {
+                let (j, _) = states.intern(output);
+                parents.push(Some((level.start + offset, action)));
+                if let Some(unbounded) = find_pump(sm, &states, &parents, j) {
+                    return unbounded;
+                }
+            }
+            level = next..states.len();
+        }
+
+        let k = states.iter().flatten().copied().max().unwrap_or(0);
+        if complete {
+            Boundedness::Bounded { k }
+        } else {
+            Boundedness::Unknown { k }
+        }
+    })
+}
+
+fn as_place_transition_net(sm: &StateMachine) -> StateMachine {
+    let mut net = sm.clone();
+    if !sm.semantics_config().is_concurrent() || sm.semantics_config().place_capacity.is_some() {
+        net.model_type = ModelType::PetriNet;
+        net.capacity = vec![Capacity::Unbounded; sm.places.len()];
+    }
+    net.semantics = None;
+    net
+}
+
+/// Returns the actions leading from the initial state to the state at index `i`.
+fn path_to(parents: &[Option<(usize, &String)>], mut i: usize) -> Vec<String> {
+    let mut path = Vec::new();
+    while let Some((parent, action)) = parents[i] {
+        path.push(action.clone());
+        i = parent;
+    }
+    path.reverse();
+    path
+}
+
+fn find_pump(
+    sm: &StateMachine,
+    states: &[Vector],
+    parents: &[Option<(usize, &String)>],
+    j: usize,
+) -> Option<Boundedness> {
+    let state = &states[j];
+    let mut ancestor = parents[j].map(|(p, _)| p);
+    while let Some(a) = ancestor {
+        let covered = &states[a];
+        if vector::ge(state, covered) {
+            let path = path_to(parents, j);
+            let prefix = path_to(parents, a);
+            let pump = path[prefix.len()..].to_vec();
+            let place = state.iter().zip(covered).position(|(s, c)| s > c)?;
+            if replays(sm, state, &pump) {
+                return Some(Boundedness::Unbounded {
+                    place: sm.places[place].clone(),
+                    prefix,
+                    pump,
+                });
+            }
+        }
+        ancestor = parents[a].map(|(p, _)| p);
+    }
+    None
+}
+
+fn replays(sm: &StateMachine, state: &Vector, pump: &[String]) -> bool {
+    let mut current = state.clone();
+    for action in pump {
+        let res = sm.transform(&current, action, 1);
+        if res.is_err() {
+            return false;
+        }
+        current = res.output;
+    }
+    vector::compare(&current, state) == Some(Ordering::Greater)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::petri_net::PetriNet;
+
+    use super::*;
+
+    #[test]
+    fn test_philosophers_are_safe() {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let sm = StateMachine::from_model(&mut net);
+        let res = boundedness(&sm);
+        assert_eq!(res, Boundedness::Bounded { k: 1 });
+        assert!(res.is_safe());
+        #[cfg(feature = "parallel")]
+        assert_eq!(boundedness_parallel(&sm, Limits::new(10), 2), boundedness_within(&sm, Limits::new(10)));
+    }
+
+    #[test]
+    fn test_k_bounded() {
+        let sm = StateMachine::new(|p| {
+            p.cell("a", Option::from(3), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.func("move", "default", 0, 0);
+            p.arrow("a", "move", 1);
+            p.arrow("move", "b", 1);
+        });
+        let res = boundedness(&sm);
+        assert!(res.is_k_bounded(3));
+        assert!(!res.is_k_bounded(2));
+    }
+
+    #[test]
+    fn test_unbounded_generator() {
+        let sm = StateMachine::new(|p| {
+            p.model_type("workflow");
+            p.cell("start", Option::from(1), None, 0, 0);
+            p.cell("ready", None, None, 0, 0);
+            p.cell("items", None, None, 0, 0);
+            p.cell("back", None, None, 0, 0);
+            p.func("begin", "default", 0, 0);
+            p.func("produce", "default", 0, 0);
+            p.func("ret", "default", 0, 0);
+            p.arrow("start", "begin", 1);
+            p.arrow("begin", "ready", 1);
+            p.arrow("ready", "produce", 1);
+            p.arrow("produce", "back", 1);
+            p.arrow("produce", "items", 1);
+            p.arrow("back", "ret", 1);
+            p.arrow("ret", "ready", 1);
+        });
+        assert_eq!(
+            boundedness(&sm),
+            Boundedness::Unbounded {
+                place: "items".to_string(),
+                prefix: vec!["begin".to_string()],
+                pump: vec!["produce".to_string(), "ret".to_string()],
+            }
+        );
+        #[cfg(feature = "parallel")]
+        for workers in [0, 1, 3] {
+            assert_eq!(boundedness_parallel(&sm, Limits::default(), workers), boundedness(&sm));
+        }
+    }
+
+    #[test]
+    fn test_truncated_exploration_is_unknown() {
+        let sm = StateMachine::new(|p| {
+            p.cell("a", Option::from(5), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.func("move", "default", 0, 0);
+            p.arrow("a", "move", 1);
+            p.arrow("move", "b", 1);
+        });
+        assert_eq!(boundedness_within(&sm, Limits::new(3)), Boundedness::Unknown { k: 5 });
+    }
+}
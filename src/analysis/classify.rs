@@ -0,0 +1,175 @@
+use serde::Serialize;
+
+use crate::analysis::structure::NetStructure;
+use crate::petri_net::PetriNet;
+
+/// `NetClass` is the most specific structural subclass a petri-net belongs to.
+///
+/// The subclasses only apply to ordinary nets, so nets with arc weights other than one
+/// or with inhibitor and read arcs are always `General`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NetClass {
+    /// Every transition has exactly one input and one output place.
+    StateMachine,
+    /// Every place has exactly one input and one output transition.
+    MarkedGraph,
+    /// Every arc from a place to a transition is either the only output of the place or the only input of the transition.
+    FreeChoice,
+    /// Places sharing an output transition share all their output transitions.
+    ExtendedFreeChoice,
+    /// None of the above.
+    General,
+}
+
+impl NetClass {
+    /// Checks if the class is contained in the free-choice nets.
+    pub fn is_free_choice(&self) -> bool {
+        matches!(self, NetClass::StateMachine | NetClass::MarkedGraph | NetClass::FreeChoice)
+    }
+
+    /// Checks if the class is contained in the extended free-choice nets.
+    pub fn is_extended_free_choice(&self) -> bool {
+        self.is_free_choice() || *self == NetClass::ExtendedFreeChoice
+    }
+}
+
+/// Checks if every transition has exactly one input and one output place.
+pub fn is_state_machine(s: &NetStructure) -> bool {
+    s.transitions.iter().all(|t| s.pre[t].len() == 1 && s.post[t].len() == 1)
+}
+
+/// Checks if every place has exactly one input and one output transition.
+pub fn is_marked_graph(s: &NetStructure) -> bool {
+    s.places.iter().all(|p| s.inputs[p].len() == 1 && s.outputs[p].len() == 1)
+}
+
+/// Checks if for every arc (p, t) either p• = {t} or •t = {p}.
+pub fn is_free_choice(s: &NetStructure) -> bool {
+    s.places.iter().all(|p| {
+        s.outputs[p].len() <= 1 || s.outputs[p].keys().all(|t| s.pre[t].len() == 1)
+    })
+}
+
+/// Checks if any two places with a common output transition have the same output transitions.
+pub fn is_extended_free_choice(s: &NetStructure) -> bool {
+    s.transitions.iter().all(|t| {
+        let mut inputs = s.pre[t].keys();
+        match inputs.next() {
+            Some(first) => inputs.all(|p| s.outputs[p].keys().eq(s.outputs[first].keys())),
+            None => true,
+        }
+    })
+}
+
+/// Classifies the net into the most specific structural subclass it belongs to.
+///
+/// A net that is both a state machine and a marked graph is reported as a `StateMachine`.
+pub fn classify(net: &PetriNet) -> NetClass {
+    let s = NetStructure::from_net(net);
+    if !s.is_ordinary() || !s.guarded.is_empty() {
+        NetClass::General
+    } else if is_state_machine(&s) {
+        NetClass::StateMachine
+    } else if is_marked_graph(&s) {
+        NetClass::MarkedGraph
+    } else if is_free_choice(&s) {
+        NetClass::FreeChoice
+    } else if is_extended_free_choice(&s) {
+        NetClass::ExtendedFreeChoice
+    } else {
+        NetClass::General
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    fn choice(p: &mut dyn FlowDsl) {
+        p.cell("a", Option::from(1), None, 0, 0);
+        p.cell("b", Option::from(1), None, 0, 0);
+        p.cell("c", None, None, 0, 0);
+        p.func("t0", "default", 0, 0);
+        p.func("t1", "default", 0, 0);
+        p.arrow("a", "t0", 1);
+        p.arrow("b", "t0", 1);
+        p.arrow("a", "t1", 1);
+        p.arrow("t0", "c", 1);
+        p.arrow("t1", "c", 1);
+    }
+
+    #[test]
+    fn test_cycle_is_state_machine() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.func("t0", "default", 0, 0);
+            p.func("t1", "default", 0, 0);
+            p.arrow("a", "t0", 1);
+            p.arrow("t0", "b", 1);
+            p.arrow("b", "t1", 1);
+            p.arrow("t1", "a", 1);
+        });
+        let s = NetStructure::from_net(&net);
+        assert!(is_marked_graph(&s));
+        assert_eq!(classify(&net), NetClass::StateMachine);
+    }
+
+    #[test]
+    fn test_fork_join_is_marked_graph() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.cell("b", Option::from(1), None, 0, 0);
+            p.func("t0", "default", 0, 0);
+            p.arrow("a", "t0", 1);
+            p.arrow("b", "t0", 1);
+            p.arrow("t0", "a", 1);
+            p.arrow("t0", "b", 1);
+        });
+        assert_eq!(classify(&net), NetClass::MarkedGraph);
+    }
+
+    #[test]
+    fn test_confusion_is_general() {
+        let mut net = PetriNet::new();
+        net.declare(choice);
+        assert_eq!(classify(&net), NetClass::General);
+
+        net.add_arc("b", "t1", Some(1), None, None, None, None);
+        net.populate_arc_attributes();
+        assert_eq!(classify(&net), NetClass::ExtendedFreeChoice);
+        assert!(!classify(&net).is_free_choice());
+    }
+
+    #[test]
+    fn test_guarded_and_weighted_nets_are_general() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.func("t0", "default", 0, 0);
+            p.guard("a", "t0", 1);
+        });
+        assert_eq!(classify(&net), NetClass::General);
+
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        assert_eq!(classify(&net), NetClass::General);
+
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.func("t0", "default", 0, 0);
+            p.arrow("a", "t0", 1);
+            p.arrow("t0", "b", 1);
+        });
+        assert_eq!(classify(&net), NetClass::StateMachine);
+        net.declare(|p| p.reset("b", "t0"));
+        assert_eq!(classify(&net), NetClass::General);
+    }
+}
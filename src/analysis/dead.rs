@@ -0,0 +1,137 @@
+use std::collections::BTreeSet;
+
+use crate::analysis::reachability::{explore, sorted_actions, Limits};
+use crate::vasm::StateMachine;
+
+/// Lists the transitions that can never be enabled because an input place can never receive tokens.
+///
+/// A place is markable if it is initially marked or is an output of a transition whose inputs are all markable.
+/// Inhibitor arcs are ignored, so the check never reports a transition that could fire.
+pub fn structurally_dead_transitions(sm: &StateMachine) -> Vec<String> {
+    let mut markable: Vec<bool> = sm.initial.iter().map(|i| *i > 0).collect();
+    let mut live: BTreeSet<&String> = BTreeSet::new();
+
+    loop {
+        let mut changed = false;
+        for action in sorted_actions(sm) {
+            if live.contains(action) {
+                continue;
+            }
+            let t = &sm.transitions[action];
+            let consumes_unmarkable = t.delta.iter().enumerate().any(|(i, d)| *d < 0 && !markable[i]);
+            let reads_unmarkable = t.guards.values().filter(|g| g.read).any(|g| {
+                g.delta.iter().enumerate().any(|(i, d)| *d < 0 && !markable[i])
+            });
+            if consumes_unmarkable || reads_unmarkable {
+                continue;
+            }
+            live.insert(action);
+            t.delta.iter().enumerate().filter(|(_, d)| **d > 0).for_each(|(i, _)| markable[i] = true);
+            for transfer in &t.transfers {
+                if let (Some(to), true) = (transfer.to, markable[transfer.from]) {
+                    markable[to] = true;
+                }
+            }
+            for arc in &t.marking_arcs {
+                if !arc.consume && markable[arc.of] {
+                    markable[arc.place] = true;
+                }
+            }
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    sorted_actions(sm)
+        .into_iter()
+        .filter(|a| !live.contains(a))
+        .cloned()
+        .collect()
+}
+
+/// Lists the transitions that are not enabled in any reachable state.
+///
+/// Structurally dead transitions are reported without exploring the state space.
+/// If the exploration is truncated by `limits`, a transition that did not fire may
+/// still be enabled beyond the explored states, so only the structurally dead ones are reported.
+pub fn dead_transitions(sm: &StateMachine, limits: Limits) -> Vec<String> {
+    let mut dead: BTreeSet<String> = structurally_dead_transitions(sm).into_iter().collect();
+    if dead.len() == sm.transitions.len() {
+        return dead.into_iter().collect();
+    }
+
+    let graph = explore(sm, limits);
+    if !graph.complete {
+        return dead.into_iter().collect();
+    }
+    let mut fired: BTreeSet<&String> = BTreeSet::new();
+    graph.successors.iter().flatten().for_each(|(action, _)| {
+        fired.insert(action);
+    });
+    sorted_actions(sm)
+        .into_iter()
+        .filter(|a| !fired.contains(a))
+        .for_each(|a| {
+            dead.insert(a.clone());
+        });
+    dead.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::petri_net::PetriNet;
+
+    use super::*;
+
+    fn dead_model(p: &mut dyn FlowDsl) {
+        p.model_type("petriNet");
+        p.cell("start", Option::from(1), None, 0, 0);
+        p.cell("orphan", None, None, 0, 0);
+        p.cell("done", None, None, 0, 0);
+        p.func("go", "default", 0, 0);
+        p.func("never", "default", 0, 0);
+        p.func("twice", "default", 0, 0);
+        p.arrow("start", "go", 1);
+        p.arrow("go", "done", 1);
+        p.arrow("orphan", "never", 1);
+        p.arrow("start", "twice", 2);
+    }
+
+    #[test]
+    fn test_structurally_dead() {
+        let sm = StateMachine::new(dead_model);
+        assert_eq!(structurally_dead_transitions(&sm), vec!["never"]);
+    }
+
+    #[test]
+    fn test_dead_transitions() {
+        let sm = StateMachine::new(dead_model);
+        assert_eq!(dead_transitions(&sm, Limits::default()), vec!["never", "twice"]);
+    }
+
+    #[test]
+    fn test_truncated_exploration_reports_only_proven_dead() {
+        let sm = StateMachine::new(|p| {
+            p.cell("count", Option::from(0), None, 0, 0);
+            p.cell("orphan", None, None, 0, 0);
+            p.func("inc", "default", 0, 0);
+            p.func("late", "default", 0, 0);
+            p.func("never", "default", 0, 0);
+            p.arrow("inc", "count", 1);
+            p.arrow("count", "late", 5);
+            p.arrow("orphan", "never", 1);
+        });
+        assert_eq!(dead_transitions(&sm, Limits::new(3)), vec!["never"]);
+    }
+
+    #[test]
+    fn test_no_dead_philosophers() {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let sm = StateMachine::from_model(&mut net);
+        assert!(dead_transitions(&sm, Limits::default()).is_empty());
+    }
+}
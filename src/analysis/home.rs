@@ -0,0 +1,108 @@
+use crate::analysis::reachability::ReachabilityGraph;
+
+/// Groups the states of the reachability graph into strongly connected components.
+///
+/// Components are listed in reverse topological order, so the components reached last come first,
+/// and the states of each component are sorted by index.
+pub fn strongly_connected_components(graph: &ReachabilityGraph) -> Vec<Vec<usize>> {
+    let component = graph.component_ids();
+    let mut components: Vec<Vec<usize>> = vec![Vec::new(); component.iter().max().map_or(0, |c| c + 1)];
+    component.iter().enumerate().for_each(|(state, c)| components[*c].push(state));
+    components
+}
+
+/// Lists the components without firings leaving them, once entered they are never left.
+///
+/// On a truncated graph the states whose firings were cut off may look terminal.
+pub fn terminal_components(graph: &ReachabilityGraph) -> Vec<Vec<usize>> {
+    let component = graph.component_ids();
+    strongly_connected_components(graph)
+        .into_iter()
+        .filter(|states| {
+            states.iter().all(|i| {
+                graph.successors[*i].iter().all(|(_, j)| component[*j] == component[*i])
+            })
+        })
+        .collect()
+}
+
+/// Lists the home states, the states that can be reached again from every reachable state.
+///
+/// They exist only if there is a single terminal component, which then holds all of them.
+/// Returns None if the exploration of the graph was truncated, since the missing states may not reach them.
+pub fn home_states(graph: &ReachabilityGraph) -> Option<Vec<usize>> {
+    if !graph.complete {
+        return None;
+    }
+    let mut terminal = terminal_components(graph);
+    match terminal.len() {
+        1 => Some(terminal.remove(0)),
+        _ => Some(Vec::new()),
+    }
+}
+
+/// Checks if the initial state can always be recovered, that is the initial state is a home state.
+/// Returns None if the exploration of the graph was truncated.
+pub fn is_reversible(graph: &ReachabilityGraph) -> Option<bool> {
+    Some(home_states(graph)?.contains(&0))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::reachability::{explore, Limits};
+    use crate::dsl::FlowDsl;
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::petri_net::PetriNet;
+    use crate::vasm::StateMachine;
+
+    use super::*;
+
+    fn retry(p: &mut dyn FlowDsl) {
+        p.model_type("workflow");
+        p.cell("start", Option::from(1), None, 0, 0);
+        p.cell("pending", None, None, 0, 0);
+        p.cell("failed", None, None, 0, 0);
+        p.cell("done", None, None, 0, 0);
+        p.func("submit", "default", 0, 0);
+        p.func("fail", "default", 0, 0);
+        p.func("retry", "default", 0, 0);
+        p.func("succeed", "default", 0, 0);
+        p.arrow("start", "submit", 1);
+        p.arrow("submit", "pending", 1);
+        p.arrow("pending", "fail", 1);
+        p.arrow("fail", "failed", 1);
+        p.arrow("failed", "retry", 1);
+        p.arrow("retry", "pending", 1);
+        p.arrow("pending", "succeed", 1);
+        p.arrow("succeed", "done", 1);
+    }
+
+    #[test]
+    fn test_retry_loop_components() {
+        let sm = StateMachine::new(retry);
+        let graph = explore(&sm, Limits::default());
+        let components = strongly_connected_components(&graph);
+        assert_eq!(components.len(), 3);
+        assert!(components.contains(&vec![1, 2]));
+
+        let done = graph.index_of(&vec![0, 0, 0, 1]).unwrap();
+        assert_eq!(terminal_components(&graph), vec![vec![done]]);
+        assert_eq!(home_states(&graph), Some(vec![done]));
+        assert_eq!(is_reversible(&graph), Some(false));
+    }
+
+    #[test]
+    fn test_philosophers_are_reversible() {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let sm = StateMachine::from_model(&mut net);
+        let graph = explore(&sm, Limits::default());
+        assert_eq!(strongly_connected_components(&graph).len(), 1);
+        assert_eq!(home_states(&graph).unwrap().len(), graph.states.len());
+        assert_eq!(is_reversible(&graph), Some(true));
+
+        let truncated = explore(&sm, Limits::new(5));
+        assert!(!truncated.complete);
+        assert_eq!(home_states(&truncated), None);
+        assert_eq!(is_reversible(&truncated), None);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use crate::analysis::structure::NetStructure;
+use crate::petri_net::PetriNet;
+
+/// Invariant is a type alias for the non-zero weights of a place invariant by place label.
+pub type Invariant = BTreeMap<String, i32>;
+
+/// The number of intermediate rows after which the elimination gives up, it grows exponentially on some nets.
+const MAX_ROWS: usize = 10_000;
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Computes the minimal-support place invariants (P-semiflows) of the net with the Farkas algorithm.
+///
+/// The weighted token sum of the places of an invariant is the same in every reachable marking.
+/// Inhibitor and read arcs do not move tokens and are ignored. Places attached to reset, transfer or
+/// marking-weighted arcs lose or gain tokens in proportion to the marking, so no invariant containing them
+/// is reported.
+/// Returns the invariants found so far if the elimination exceeds its internal row limit.
+pub fn place_invariants(net: &PetriNet) -> Vec<Invariant> {
+    let s = NetStructure::from_net(net);
+    let places: Vec<&String> = s.places.iter().collect();
+    let transitions: Vec<&String> = s.transitions.iter().collect();
+
+    // Each row pairs the incidence of a combination of places with the combination itself.
+    let mut rows: Vec<(Vec<i64>, Vec<i64>)> = places
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let incidence = transitions
+                .iter()
+                .map(|t| {
+                    let produced = *s.post[*t].get(*p).unwrap_or(&0) as i64;
+                    let consumed = *s.pre[*t].get(*p).unwrap_or(&0) as i64;
+                    produced - consumed
+                })
+                .collect();
+            let mut unit = vec![0; places.len()];
+            unit[i] = 1;
+            (incidence, unit)
+        })
+        .collect();
+
+    for j in 0..transitions.len() {
+        let mut next: Vec<(Vec<i64>, Vec<i64>)> = rows.iter().filter(|(c, _)| c[j] == 0).cloned().collect();
+        for (a, ya) in rows.iter().filter(|(c, _)| c[j] > 0) {
+            for (b, yb) in rows.iter().filter(|(c, _)| c[j] < 0) {
+                let (fa, fb) = (-b[j], a[j]);
+                let c: Vec<i64> = a.iter().zip(b).map(|(x, y)| fa * x + fb * y).collect();
+                let y: Vec<i64> = ya.iter().zip(yb).map(|(x, y)| fa * x + fb * y).collect();
+                let g = c.iter().chain(&y).fold(0, |g, v| gcd(g, *v)).max(1);
+                next.push((c.iter().map(|v| v / g).collect(), y.iter().map(|v| v / g).collect()));
+            }
+        }
+        next.sort_by(|a, b| a.1.cmp(&b.1));
+        next.dedup_by(|a, b| a.1 == b.1);
+        rows = next;
+        if rows.len() > MAX_ROWS {
+            break;
+        }
+    }
+
+    let support = |y: &Vec<i64>| -> Vec<usize> { (0..y.len()).filter(|i| y[*i] != 0).collect() };
+    let candidates: Vec<&Vec<i64>> = rows
+        .iter()
+        .filter(|(c, _)| c.iter().all(|v| *v == 0))
+        .map(|(_, y)| y)
+        .collect();
+    let minimal = candidates.iter().filter(|y| support(y).iter().all(|i| !s.dependent.contains(places[*i])));
+    let minimal = minimal.filter(|y| {
+        let sy = support(y);
+        !candidates.iter().any(|other| {
+            let so = support(other);
+            so.len() < sy.len() && so.iter().all(|i| sy.contains(i))
+        })
+    });
+    let mut invariants: Vec<Invariant> = minimal
+        .map(|y| {
+            y.iter()
+                .enumerate()
+                .filter(|(_, w)| **w != 0)
+                .map(|(i, w)| (places[i].clone(), *w as i32))
+                .collect()
+        })
+        .collect();
+    invariants.sort();
+    invariants.dedup();
+    invariants
+}
+
+/// Returns the weighted token sum of the invariant in the initial marking of the net.
+pub fn invariant_value(net: &PetriNet, invariant: &Invariant) -> i32 {
+    invariant
+        .iter()
+        .map(|(p, w)| w * net.places[p].initial.unwrap_or(0))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    #[test]
+    fn test_cycle_invariant() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.cell("c", None, None, 0, 0);
+            p.func("t0", "default", 0, 0);
+            p.func("t1", "default", 0, 0);
+            p.arrow("a", "t0", 2);
+            p.arrow("t0", "b", 1);
+            p.arrow("b", "t1", 1);
+            p.arrow("t1", "a", 2);
+            p.arrow("t1", "c", 1);
+        });
+        let invariants = place_invariants(&net);
+        assert_eq!(
+            invariants,
+            vec![Invariant::from([("a".to_string(), 1), ("b".to_string(), 2)])]
+        );
+        assert_eq!(invariant_value(&net, &invariants[0]), 1);
+    }
+
+    #[test]
+    fn test_philosophers_conserve_chopsticks() {
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let invariants = place_invariants(&net);
+        assert!(!invariants.is_empty());
+        assert!(invariants.iter().all(|i| invariant_value(&net, i) > 0));
+        assert!(invariants.iter().any(|i| i.contains_key("chopstick1")));
+    }
+
+    #[test]
+    fn test_reset_arc_breaks_invariant() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.cell("c", Option::from(1), None, 0, 0);
+            p.cell("d", None, None, 0, 0);
+            p.func("t0", "default", 0, 0);
+            p.func("t1", "default", 0, 0);
+            p.func("flip", "default", 0, 0);
+            p.arrow("a", "t0", 1);
+            p.arrow("t0", "b", 1);
+            p.arrow("b", "t1", 1);
+            p.arrow("t1", "a", 1);
+            p.arrow("c", "flip", 1);
+            p.arrow("flip", "d", 1);
+        });
+        assert_eq!(place_invariants(&net).len(), 2);
+
+        net.declare(|p| p.reset("b", "t0"));
+        let invariants = place_invariants(&net);
+        assert_eq!(
+            invariants,
+            vec![Invariant::from([("c".to_string(), 1), ("d".to_string(), 1)])]
+        );
+    }
+
+    #[test]
+    fn test_marking_weight_breaks_invariant() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", Option::from(4), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.func("t0", "default", 0, 0);
+            p.marking_arrow("a", "t0", "half");
+            p.arrow("t0", "b", 1);
+        });
+        assert!(place_invariants(&net).is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::analysis::reachability::{explore, sorted_actions, Limits, ReachabilityGraph};
+use crate::vasm::StateMachine;
+
+/// `LivenessLevel` is the classic liveness hierarchy of a transition, each level implies the ones below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum LivenessLevel {
+    /// The transition never fires (dead).
+    L0,
+    /// The transition fires at least once in some firing sequence.
+    L1,
+    /// The transition fires at least k times in some firing sequence, for any k.
+    L2,
+    /// The transition fires infinitely often in some firing sequence.
+    L3,
+    /// The transition can eventually fire again from every reachable state (live).
+    L4,
+}
+
+/// `LivenessReport` holds the liveness level of every transition of a state machine.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LivenessReport {
+    /// The liveness level by transition label.
+    pub levels: BTreeMap<String, LivenessLevel>,
+    /// False if the reachability graph was truncated, in which case the levels are only estimates.
+    pub complete: bool,
+}
+
+impl LivenessReport {
+    /// Returns the transitions with the given liveness level.
+    pub fn at_level(&self, level: LivenessLevel) -> Vec<&String> {
+        self.levels.iter().filter(|(_, l)| **l == level).map(|(t, _)| t).collect()
+    }
+
+    /// Checks if every transition is live (L4).
+    pub fn is_live(&self) -> bool {
+        self.levels.values().all(|l| *l == LivenessLevel::L4)
+    }
+}
+
+/// Computes the liveness level of every transition over the given reachability graph.
+///
+/// The state space is finite, so a transition firing arbitrarily often must lie on a cycle
+/// and L2 always coincides with L3, in which case L3 is reported.
+pub fn liveness_levels(sm: &StateMachine, graph: &ReachabilityGraph) -> LivenessReport {
+    let component = graph.component_ids();
+    let components = component.iter().max().map_or(0, |c| c + 1);
+
+    let mut fired: BTreeSet<&String> = BTreeSet::new();
+    let mut cyclic: BTreeSet<&String> = BTreeSet::new();
+    let mut terminal = vec![true; components];
+    let mut fired_in: Vec<BTreeSet<&String>> = vec![BTreeSet::new(); components];
+
+    for (i, successors) in graph.successors.iter().enumerate() {
+        for (action, j) in successors {
+            fired.insert(action);
+            if component[i] == component[*j] {
+                cyclic.insert(action);
+                fired_in[component[i]].insert(action);
+            } else {
+                terminal[component[i]] = false;
+            }
+        }
+    }
+
+    let levels = sorted_actions(sm)
+        .into_iter()
+        .map(|action| {
+            let live = (0..components)
+                .filter(|c| terminal[*c])
+                .all(|c| fired_in[c].contains(action));
+            let level = if !fired.contains(action) {
+                LivenessLevel::L0
+            } else if live {
+                LivenessLevel::L4
+            } else if cyclic.contains(action) {
+                LivenessLevel::L3
+            } else {
+                LivenessLevel::L1
+            };
+            (action.clone(), level)
+        })
+        .collect();
+
+    LivenessReport {
+        levels,
+        complete: graph.complete,
+    }
+}
+
+/// Explores the state space within `limits` and computes the liveness level of every transition.
+pub fn liveness(sm: &StateMachine, limits: Limits) -> LivenessReport {
+    liveness_levels(sm, &explore(sm, limits))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::petri_net::PetriNet;
+
+    use super::*;
+
+    fn levels(p: &mut dyn FlowDsl) {
+        p.model_type("petriNet");
+        p.cell("start", Option::from(1), None, 0, 0);
+        p.cell("loop", None, None, 0, 0);
+        p.cell("back", None, None, 0, 0);
+        p.cell("end", None, None, 0, 0);
+        p.cell("orphan", None, None, 0, 0);
+        p.func("enter", "default", 0, 0);
+        p.func("spin", "default", 0, 0);
+        p.func("ret", "default", 0, 0);
+        p.func("exit", "default", 0, 0);
+        p.func("never", "default", 0, 0);
+        p.arrow("start", "enter", 1);
+        p.arrow("enter", "loop", 1);
+        p.arrow("loop", "spin", 1);
+        p.arrow("spin", "back", 1);
+        p.arrow("back", "ret", 1);
+        p.arrow("ret", "loop", 1);
+        p.arrow("loop", "exit", 1);
+        p.arrow("exit", "end", 1);
+        p.arrow("orphan", "never", 1);
+    }
+
+    #[test]
+    fn test_liveness_levels() {
+        let sm = StateMachine::new(levels);
+        let report = liveness(&sm, Limits::default());
+        assert!(report.complete);
+        assert!(!report.is_live());
+        assert_eq!(report.levels["never"], LivenessLevel::L0);
+        assert_eq!(report.levels["enter"], LivenessLevel::L1);
+        assert_eq!(report.levels["exit"], LivenessLevel::L1);
+        assert_eq!(report.levels["spin"], LivenessLevel::L3);
+    }
+
+    #[test]
+    fn test_philosophers_are_live() {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let sm = StateMachine::from_model(&mut net);
+        let report = liveness(&sm, Limits::default());
+        assert!(report.is_live());
+        assert_eq!(report.at_level(LivenessLevel::L4).len(), 10);
+    }
+}
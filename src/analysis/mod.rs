@@ -0,0 +1,56 @@
+/// The `structure` module exposes the bipartite graph of a petri-net used by the structural analyses.
+pub mod structure;
+
+/// The `reduction` module contains the Murata reduction rules used to shrink nets before analysis.
+pub mod reduction;
+
+/// The `reachability` module explores the state space of a `StateMachine` within configurable limits.
+pub mod reachability;
+
+/// The `dead` module detects transitions that can never fire.
+pub mod dead;
+
+/// The `boundedness` module checks whether a net is k-bounded or finds a pumping sequence.
+pub mod boundedness;
+
+/// The `classify` module detects the structural subclass of a petri-net.
+pub mod classify;
+
+/// The `home` module decomposes the reachability graph into components and finds home states.
+pub mod home;
+
+/// The `liveness` module computes the liveness level of each transition.
+pub mod liveness;
+
+/// The `statistics` module summarizes the size of the reachable state space.
+pub mod statistics;
+
+/// The `siphon` module enumerates minimal siphons and traps and checks the siphon-trap property.
+pub mod siphon;
+
+/// The `soundness` module checks that workflow nets always complete properly.
+pub mod soundness;
+
+/// The `invariants` module computes the place invariants of a petri-net.
+pub mod invariants;
+
+/// The `store` module interns the states visited by the analyses.
+pub mod store;
+
+#[cfg(feature = "parallel")]
+pub use boundedness::boundedness_parallel;
+pub use boundedness::{boundedness, Boundedness};
+pub use classify::{classify, NetClass};
+pub use dead::{dead_transitions, structurally_dead_transitions};
+pub use home::{home_states, is_reversible, strongly_connected_components};
+pub use invariants::{invariant_value, place_invariants, Invariant};
+pub use liveness::{liveness, LivenessLevel, LivenessReport};
+#[cfg(feature = "parallel")]
+pub use reachability::explore_parallel;
+pub use reachability::{can_cover, can_reach, explore, explore_compact, CompactGraph, Limits, ReachabilityGraph};
+pub use reduction::{reduce, Reduction, ReductionLog};
+pub use siphon::{minimal_siphons, minimal_traps, siphon_trap_property, siphon_trap_property_within, PlaceSet};
+pub use soundness::{soundness, workflow_shape, SoundnessReport, WorkflowShape};
+pub use statistics::{statistics, Statistics};
+pub use store::{StateId, StateStore};
+pub use structure::NetStructure;
@@ -0,0 +1,563 @@
+use std::collections::{HashMap, VecDeque};
+#[cfg(feature = "parallel")]
+use std::mem;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "parallel")]
+use crate::analysis::store::{Rank, SharedStore, Visit};
+use crate::analysis::store::{StateId, StateStore};
+use crate::bitset::BitState;
+use crate::vasm::{Marking, StateMachine, Vasm, Vector};
+use crate::vector;
+
+/// `Limits` bounds the size of a state space exploration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Limits {
+    /// The maximum number of distinct states to visit before the exploration is truncated.
+    pub max_states: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self { max_states: 100_000 }
+    }
+}
+
+impl Limits {
+    /// Creates new `Limits` allowing at most `max_states` distinct states.
+    pub fn new(max_states: usize) -> Self {
+        Self { max_states }
+    }
+}
+
+/// `ReachabilityGraph` holds the states reachable from the initial marking and the firings between them.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReachabilityGraph {
+    /// The reachable states interned by id, the initial state is always id zero.
+    pub states: StateStore,
+    /// The outgoing firings of each state as (action, target state id) pairs.
+    pub successors: Vec<Vec<(String, StateId)>>,
+    /// False if the exploration stopped because the limits were reached.
+    pub complete: bool,
+}
+
+impl ReachabilityGraph {
+    /// Returns the index of the given state if it was reached.
+    pub fn index_of(&self, state: &Vector) -> Option<StateId> {
+        self.states.get(state)
+    }
+
+    /// Returns the total number of firings recorded in the graph.
+    pub fn edge_count(&self) -> usize {
+        self.successors.iter().map(|s| s.len()).sum()
+    }
+
+    /// Assigns each state the index of its strongly connected component.
+    ///
+    /// Components are numbered in reverse topological order, so a component only
+    /// has firings into components with a lower or equal index.
+    pub(crate) fn component_ids(&self) -> Vec<usize> {
+        const UNVISITED: usize = usize::MAX;
+        let n = self.states.len();
+        let mut order = vec![UNVISITED; n];
+        let mut low = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut component = vec![UNVISITED; n];
+        let mut stack: Vec<usize> = Vec::new();
+        let mut next_order = 0;
+        let mut next_component = 0;
+
+        for root in 0..n {
+            if order[root] != UNVISITED {
+                continue;
+            }
+            // each frame holds a state and the position of the next successor to visit
+            let mut frames: Vec<(usize, usize)> = vec![(root, 0)];
+            order[root] = next_order;
+            low[root] = next_order;
+            next_order += 1;
+            stack.push(root);
+            on_stack[root] = true;
+
+            while let Some((v, pos)) = frames.last_mut() {
+                let v = *v;
+                if let Some((_, w)) = self.successors[v].get(*pos) {
+                    let w = *w;
+                    *pos += 1;
+                    if order[w] == UNVISITED {
+                        order[w] = next_order;
+                        low[w] = next_order;
+                        next_order += 1;
+                        stack.push(w);
+                        on_stack[w] = true;
+                        frames.push((w, 0));
+                    } else if on_stack[w] {
+                        low[v] = low[v].min(order[w]);
+                    }
+                    continue;
+                }
+                frames.pop();
+                if let Some((parent, _)) = frames.last() {
+                    low[*parent] = low[*parent].min(low[v]);
+                }
+                if low[v] == order[v] {
+                    while let Some(w) = stack.pop() {
+                        on_stack[w] = false;
+                        component[w] = next_component;
+                        if w == v {
+                            break;
+                        }
+                    }
+                    next_component += 1;
+                }
+            }
+        }
+        component
+    }
+
+    fn insert(&mut self, state: Vector) -> StateId {
+        let (i, _) = self.states.intern(state);
+        self.successors.push(Vec::new());
+        i
+    }
+}
+
+/// `CompactGraph` is the reachability graph of a 1-safe net with its states packed into bits, see `explore_compact`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompactGraph {
+    /// The number of places of the state machine, the length of every unpacked state.
+    pub places: usize,
+    /// The reachable states, the initial state is always at index zero.
+    pub states: Vec<BitState>,
+    /// The outgoing firings of each state as (action, target state index) pairs.
+    pub successors: Vec<Vec<(String, usize)>>,
+    /// False if the exploration stopped because the limits were reached.
+    pub complete: bool,
+    #[serde(skip)]
+    index: HashMap<BitState, usize>,
+}
+
+impl CompactGraph {
+    /// Returns the index of the given state if it was reached.
+    pub fn index_of(&self, state: &Vector) -> Option<usize> {
+        self.index.get(&BitState::pack(state)?).copied()
+    }
+
+    /// Returns the state at the given index as a state vector.
+    pub fn state(&self, i: usize) -> Vector {
+        self.states[i].unpack(self.places)
+    }
+
+    /// Returns the total number of firings recorded in the graph.
+    pub fn edge_count(&self) -> usize {
+        self.successors.iter().map(|s| s.len()).sum()
+    }
+
+    /// Unpacks the graph for the analyses working on a `ReachabilityGraph`, the state indices are kept.
+    pub fn to_graph(&self) -> ReachabilityGraph {
+        ReachabilityGraph {
+            states: (0..self.states.len()).map(|i| self.state(i)).collect(),
+            successors: self.successors.clone(),
+            complete: self.complete,
+        }
+    }
+
+    fn insert(&mut self, state: BitState) -> usize {
+        let i = self.states.len();
+        self.index.insert(state.clone(), i);
+        self.states.push(state);
+        self.successors.push(Vec::new());
+        i
+    }
+}
+
+/// Returns the transition labels of the state machine in a stable order.
+pub(crate) fn sorted_actions(sm: &StateMachine) -> Vec<&String> {
+    let mut actions: Vec<&String> = sm.transitions.keys().collect();
+    actions.sort();
+    actions
+}
+
+/// Searches breadth-first for a reachable state matching `found` and returns the actions leading to it.
+fn shortest_path<F>(sm: &StateMachine, limits: Limits, found: F) -> Option<Vec<String>>
+where
+    F: Fn(&Vector) -> bool,
+{
+    let actions = sorted_actions(sm);
+    let mut states = StateStore::from_iter([sm.initial_vector()]);
+    let mut parents: Vec<Option<(StateId, &String)>> = vec![None];
+    let mut queue = VecDeque::from([0]);
+
+    let mut reached = found(&states[0]).then_some(0);
+    while let (None, Some(i)) = (reached, queue.pop_front()) {
+        for action in &actions {
+            let res = sm.transform(&states[i], action, 1);
+            if res.is_err() || states.get(&res.output).is_some() || states.len() >= limits.max_states {
+                continue;
+            }
+            let (j, _) = states.intern(res.output);
+            parents.push(Some((i, action)));
+            if found(&states[j]) {
+                reached = Some(j);
+                break;
+            }
+            queue.push_back(j);
+        }
+    }
+
+    let mut path = Vec::new();
+    let mut i = reached?;
+    while let Some((parent, action)) = parents[i] {
+        path.push(action.clone());
+        i = parent;
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// Returns a shortest firing sequence from the initial state to the target marking.
+///
+/// `None` means the target is unreachable, or was not found within the limits.
+pub fn can_reach(sm: &StateMachine, target: &Marking, limits: Limits) -> Option<Vec<String>> {
+    shortest_path(sm, limits, |state| state == target.as_vector())
+}
+
+/// Returns a shortest firing sequence from the initial state to a marking with at least
+/// as many tokens as the target in every place.
+pub fn can_cover(sm: &StateMachine, target: &Marking, limits: Limits) -> Option<Vec<String>> {
+    shortest_path(sm, limits, |state| vector::ge(state, target.as_vector()))
+}
+
+/// Explores the reachable state space of the state machine breadth-first, firing each transition with a multiple of one.
+///
+/// When every place fires with a capacity of at most one token, as in elementary and workflow models, the states
+/// are visited as bits with `explore_compact` and unpacked once the exploration is done.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(
+    state_hash = crate::tracing::state_hash(&sm.initial_vector()),
+    max_states = limits.max_states,
+)))]
+pub fn explore(sm: &StateMachine, limits: Limits) -> ReachabilityGraph {
+    let one_safe = (0..sm.places.len()).all(|i| sm.place_capacity(i).limit().is_some_and(|limit| limit <= 1));
+    let graph = match one_safe.then(|| explore_compact(sm, limits)).flatten() {
+        Some(compact) => compact.to_graph(),
+        None => explore_vectors(sm, limits),
+    };
+    #[cfg(feature = "tracing")]
+    tracing::debug!(states = graph.states.len(), complete = graph.complete, "explored");
+    graph
+}
+
+fn explore_vectors(sm: &StateMachine, limits: Limits) -> ReachabilityGraph {
+    let compiled = sm.compile();
+    let mut graph = ReachabilityGraph {
+        complete: true,
+        ..Default::default()
+    };
+    let mut queue = VecDeque::from([graph.insert(sm.initial_vector())]);
+
+    while let Some(i) = queue.pop_front() {
+        for (id, action) in compiled.labels().iter().enumerate() {
+            let res = compiled.fire(&graph.states[i], id, 1);
+            if res.is_err() {
+                continue;
+            }
+            let target = match graph.index_of(&res.output) {
+                Some(j) => j,
+                None if graph.states.len() < limits.max_states => {
+                    let j = graph.insert(res.output);
+                    queue.push_back(j);
+                    j
+                }
+                None => {
+                    graph.complete = false;
+                    continue;
+                }
+            };
+            graph.successors[i].push((action.to_string(), target));
+        }
+    }
+    graph
+}
+
+/// Explores the reachable state space like `explore`, storing each state as a bit per place.
+///
+/// Meant for elementary and workflow models, or any other net known to be 1-safe. Returns None as soon as
+/// a reachable state holds more than one token in a place, use `explore` for such nets.
+pub fn explore_compact(sm: &StateMachine, limits: Limits) -> Option<CompactGraph> {
+    let compiled = sm.compile();
+    let mut graph = CompactGraph {
+        places: sm.places.len(),
+        complete: true,
+        ..Default::default()
+    };
+    let mut queue = VecDeque::from([graph.insert(BitState::pack(&sm.initial_vector())?)]);
+
+    while let Some(i) = queue.pop_front() {
+        let state = graph.state(i);
+        for (id, action) in compiled.labels().iter().enumerate() {
+            let res = compiled.fire(&state, id, 1);
+            if res.is_err() {
+                continue;
+            }
+            let output = BitState::pack(&res.output)?;
+            let target = match graph.index.get(&output) {
+                Some(j) => *j,
+                None if graph.states.len() < limits.max_states => {
+                    let j = graph.insert(output);
+                    queue.push_back(j);
+                    j
+                }
+                None => {
+                    graph.complete = false;
+                    continue;
+                }
+            };
+            graph.successors[i].push((action.to_string(), target));
+        }
+    }
+    Some(graph)
+}
+
+/// Explores the reachable state space like `explore`, expanding each breadth-first level on `workers` threads,
+/// or on the global rayon pool if `workers` is zero.
+///
+/// Workers fire the actions and deduplicate the outputs against a visited set they share, see `expand_level`.
+/// The new states of a level are then numbered in the order `explore` would have found them, so the resulting
+/// graph is identical to the single-threaded one, including its numbering and where the limits truncate it.
+#[cfg(feature = "parallel")]
+pub fn explore_parallel(sm: &StateMachine, limits: Limits, workers: usize) -> ReachabilityGraph {
+    with_workers(workers, || {
+        let actions = sorted_actions(sm);
+        let visited = SharedStore::new(sm.initial_vector());
+        let mut states = vec![sm.initial_vector()];
+        let mut successors: Vec<Vec<(String, StateId)>> = Vec::new();
+        let mut complete = true;
+        let mut level = 0..states.len();
+
+        while !level.is_empty() {
+            let room = limits.max_states.saturating_sub(states.len());
+            let expanded = expand_level(sm, &actions, &states[level.clone()], &visited, states.len(), room);
+            for targets in expanded.successors {
+                complete &= targets.iter().all(|(_, target)| target.is_some());
+                let targets = targets.into_iter().filter_map(|(action, target)| Some((action.to_string(), target?)));
+                successors.push(targets.collect());
+            }
+            let next = states.len();
+            states.extend(expanded.found.into_iter().map(|(_, _, state)| state));
+            level = next..states.len();
+        }
+        drop(visited);
+        ReachabilityGraph {
+            states: states.into_iter().collect(),
+            successors,
+            complete,
+        }
+    })
+}
+
+/// `Level` is a breadth-first level expanded by `expand_level`.
+#[cfg(feature = "parallel")]
+pub(crate) struct Level<'a> {
+    /// The successors of each state of the level in the order of the actions, the target is None
+    /// if it is a new state that did not fit.
+    pub successors: Vec<Vec<(&'a String, Option<StateId>)>>,
+    /// The new states in id order, each with the offset in the level of the state it was first reached
+    /// from and the action that reached it.
+    pub found: Vec<(usize, &'a String, Vector)>,
+}
+
+/// A successor computed by a worker, either a state numbered before the level or a new output state,
+/// which the worker may have been the first to add.
+#[cfg(feature = "parallel")]
+enum Successor {
+    Known(StateId),
+    First(Vector),
+    New(Vector),
+}
+
+/// Fires every action in every state of the level in parallel, deduplicating the outputs with the shared
+/// visited set, then numbers the new states from `next` in the order of the single-threaded search.
+///
+/// At most `room` new states are numbered, the others are forgotten.
+#[cfg(feature = "parallel")]
+pub(crate) fn expand_level<'a>(
+    sm: &StateMachine,
+    actions: &[&'a String],
+    level: &[Vector],
+    visited: &SharedStore,
+    next: StateId,
+    room: usize,
+) -> Level<'a> {
+    let mut fired: Vec<Vec<(usize, Successor)>> = level
+        .par_iter()
+        .enumerate()
+        .map(|(offset, state)| {
+            actions
+                .iter()
+                .enumerate()
+                .filter_map(|(a, action)| {
+                    let res = sm.transform(state, action, 1);
+                    if res.is_err() {
+                        return None;
+                    }
+                    let successor = match visited.visit(&res.output, (offset, a)) {
+                        Visit::Visited(j) => Successor::Known(j),
+                        Visit::First => Successor::First(res.output),
+                        Visit::Again => Successor::New(res.output),
+                    };
+                    Some((a, successor))
+                })
+                .collect()
+        })
+        .collect();
+
+    // each new state was added by exactly one worker, which finds its earliest rank once the level is done
+    let mut first: Vec<(Rank, usize, usize)> = fired
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(offset, successors)| {
+            successors.iter().enumerate().filter_map(move |(i, (_, successor))| match successor {
+                Successor::First(output) => Some((visited.rank(output)?, offset, i)),
+                _ => None,
+            })
+        })
+        .collect();
+    first.par_sort_unstable();
+    first.par_iter().enumerate().for_each(|(k, (_, offset, i))| {
+        if let Successor::First(output) = &fired[*offset][*i].1 {
+            visited.number(output, (k < room).then_some(next + k));
+        }
+    });
+
+    let successors = fired
+        .par_iter()
+        .map(|successors| {
+            successors
+                .iter()
+                .map(|(a, successor)| match successor {
+                    Successor::Known(j) => (actions[*a], Some(*j)),
+                    Successor::First(output) | Successor::New(output) => (actions[*a], visited.get(output)),
+                })
+                .collect()
+        })
+        .collect();
+    let found = first
+        .into_iter()
+        .take(room)
+        .filter_map(|((parent, a), offset, i)| match mem::replace(&mut fired[offset][i].1, Successor::Known(0)) {
+            Successor::First(output) => Some((parent, actions[a], output)),
+            _ => None,
+        })
+        .collect();
+    Level { successors, found }
+}
+
+/// Runs `f` on a dedicated pool of `workers` threads, or on the global rayon pool if `workers` is zero
+/// or the pool cannot be built.
+#[cfg(feature = "parallel")]
+pub(crate) fn with_workers<T: Send>(workers: usize, f: impl FnOnce() -> T + Send) -> T {
+    if workers == 0 {
+        return f();
+    }
+    match rayon::ThreadPoolBuilder::new().num_threads(workers).build() {
+        Ok(pool) => pool.install(f),
+        Err(_) => f(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::petri_net::PetriNet;
+    use crate::vasm::SemanticsConfig;
+
+    use super::*;
+
+    fn philosophers() -> StateMachine {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        StateMachine::from_model(&mut net)
+    }
+
+    fn marking(sm: &StateMachine, places: &[&str]) -> Marking {
+        let state = sm.places.iter().map(|p| places.contains(&p.as_str()) as i32).collect();
+        Marking::from_vector(sm, state).unwrap()
+    }
+
+    #[test]
+    fn test_can_reach_target() {
+        let sm = philosophers();
+        let initial = Marking::for_machine(&sm);
+        assert_eq!(can_reach(&sm, &initial, Limits::default()), Some(vec![]));
+
+        let eating = marking(&sm, &["left1", "right1", "chopstick2", "chopstick3", "chopstick4"]);
+        assert_eq!(can_reach(&sm, &eating, Limits::default()), Some(vec!["eat1".to_string()]));
+
+        let impossible = marking(&sm, &["left1", "right1", "left2", "right2"]);
+        assert_eq!(can_reach(&sm, &impossible, Limits::default()), None);
+    }
+
+    #[test]
+    fn test_can_cover_submarking() {
+        let sm = philosophers();
+        let two_eating = marking(&sm, &["left1", "left3"]);
+        let path = can_cover(&sm, &two_eating, Limits::default()).unwrap();
+        assert_eq!(path.len(), 2);
+        assert!(can_cover(&sm, &marking(&sm, &["left1", "left2"]), Limits::default()).is_none());
+    }
+
+    #[test]
+    fn test_compact_exploration_matches_explore() {
+        let sm = philosophers();
+        for limits in [Limits::default(), Limits::new(7)] {
+            let expected = explore(&sm, limits);
+            let compact = explore_compact(&sm, limits).unwrap();
+            assert_eq!(compact.to_graph().states, expected.states);
+            assert_eq!(compact.successors, expected.successors);
+            assert_eq!(compact.complete, expected.complete);
+            assert_eq!(compact.index_of(&expected.states[3]), Some(3));
+        }
+
+        let counter = StateMachine::new(|p| {
+            p.cell("count", None, Option::from(2), 0, 0);
+            p.func("inc", "default", 0, 0);
+            p.arrow("inc", "count", 1);
+        });
+        assert!(explore_compact(&counter, Limits::default()).is_none());
+    }
+
+    #[test]
+    fn test_explore_packs_one_safe_models() {
+        let mut sm = philosophers();
+        sm.set_semantics(SemanticsConfig {
+            single_active_place: false,
+            ..SemanticsConfig::ELEMENTARY
+        });
+        for limits in [Limits::default(), Limits::new(7)] {
+            let expected = explore_vectors(&sm, limits);
+            let graph = explore(&sm, limits);
+            assert_eq!(graph.states, expected.states);
+            assert_eq!(graph.successors, expected.successors);
+            assert_eq!(graph.complete, expected.complete);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_parallel_exploration_is_deterministic() {
+        let sm = philosophers();
+        for limits in [Limits::default(), Limits::new(7)] {
+            let expected = explore(&sm, limits);
+            for workers in [0, 1, 2, 4] {
+                let graph = explore_parallel(&sm, limits, workers);
+                assert_eq!(graph.states, expected.states);
+                assert_eq!(graph.successors, expected.successors);
+                assert_eq!(graph.complete, expected.complete);
+                assert_eq!(graph.index_of(&sm.initial_vector()), Some(0));
+            }
+        }
+    }
+}
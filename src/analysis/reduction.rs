@@ -0,0 +1,397 @@
+use serde::Serialize;
+
+use crate::analysis::structure::{Adjacency, NetStructure};
+use crate::petri_net::{Arrow, PetriNet};
+use crate::vasm::{model_type_from_string, SemanticsConfig};
+
+/// `Reduction` records a single application of one of the Murata reduction rules.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "rule")]
+pub enum Reduction {
+    /// `place` was merged into `into` and the `transition` between them was removed.
+    FusionOfSeriesPlaces { transition: String, place: String, into: String },
+    /// `transition` was merged into `into` and the `place` between them was removed.
+    FusionOfSeriesTransitions { place: String, transition: String, into: String },
+    /// `place` had the same pre- and post-set as `into` and was removed.
+    FusionOfParallelPlaces { place: String, into: String },
+    /// `transition` had the same pre- and post-set and role as `into` and was removed.
+    FusionOfParallelTransitions { transition: String, into: String },
+    /// `place` was marked and only connected to a single transition in a self-loop.
+    EliminationOfSelfLoopPlace { place: String },
+    /// `transition` consumed and produced the same tokens from a single place.
+    EliminationOfSelfLoopTransition { transition: String },
+}
+
+/// `ReductionLog` lists the reductions applied by `reduce` in the order they were applied.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReductionLog {
+    pub steps: Vec<Reduction>,
+}
+
+impl ReductionLog {
+    /// Returns the number of reductions applied.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Checks if the net was left unchanged.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+/// Applies the behavior-preserving reduction rules of Murata until none applies.
+///
+/// The rules preserve liveness, safeness and boundedness of place/transition nets.
+/// Only ordinary arcs (weight 1) are reduced, and places with a capacity as well as
+/// nodes attached to inhibitor, read, reset, transfer or marking-weighted arcs are never touched. Series places of elementary
+/// and workflow models are only fused while their tokens fit in a single place.
+///
+/// # Returns
+///
+/// * The reduced `PetriNet` and the `ReductionLog` of the applied rules.
+///
+pub fn reduce(net: &PetriNet) -> (PetriNet, ReductionLog) {
+    let mut reduced = net.clone();
+    reduced.populate_arc_attributes();
+    let mut log = ReductionLog::default();
+
+    loop {
+        let s = NetStructure::from_net(&reduced);
+        let step = fuse_series_places(&reduced, &s)
+            .or_else(|| fuse_series_transitions(&reduced, &s))
+            .or_else(|| fuse_parallel_places(&reduced, &s))
+            .or_else(|| fuse_parallel_transitions(&reduced, &s))
+            .or_else(|| eliminate_self_loop_place(&reduced, &s))
+            .or_else(|| eliminate_self_loop_transition(&s));
+        match step {
+            Some(step) => {
+                apply(&mut reduced, &step);
+                log.steps.push(step);
+            }
+            None => break,
+        }
+    }
+    reduced.reindex_offsets();
+    (reduced, log)
+}
+
+fn tokens(net: &PetriNet, place: &str) -> i32 {
+    net.places[place].initial.unwrap_or(0)
+}
+
+fn unbounded(net: &PetriNet, place: &str) -> bool {
+    !net.places[place].capacity.unwrap_or_default().is_bounded()
+}
+
+/// Checks if the tokens of both places fit in one, which the implicit capacity of elementary and workflow
+/// models may forbid.
+fn fits_fused(net: &PetriNet, place: &str, into: &str) -> bool {
+    let forced = SemanticsConfig::preset(&model_type_from_string(&net.model_type)).place_capacity;
+    forced.map_or(true, |capacity| capacity.allows(tokens(net, place) + tokens(net, into)))
+}
+
+fn single(adj: &Adjacency) -> Option<&String> {
+    match adj.iter().next() {
+        Some((label, 1)) if adj.len() == 1 => Some(label),
+        _ => None,
+    }
+}
+
+fn reducible_place(net: &PetriNet, s: &NetStructure, place: &str) -> bool {
+    !s.is_irregular(place) && unbounded(net, place)
+}
+
+fn fuse_series_places(net: &PetriNet, s: &NetStructure) -> Option<Reduction> {
+    s.transitions.iter().filter(|t| !s.is_irregular(t)).find_map(|t| {
+        let place = single(&s.pre[t])?;
+        let into = single(&s.post[t])?;
+        let only_consumer = single(&s.outputs[place]).is_some_and(|c| c == t);
+        if place == into
+            || !only_consumer
+            || !reducible_place(net, s, place)
+            || !reducible_place(net, s, into)
+            || !fits_fused(net, place, into)
+        {
+            return None;
+        }
+        Some(Reduction::FusionOfSeriesPlaces {
+            transition: t.clone(),
+            place: place.clone(),
+            into: into.clone(),
+        })
+    })
+}
+
+fn fuse_series_transitions(net: &PetriNet, s: &NetStructure) -> Option<Reduction> {
+    s.places.iter().filter(|p| reducible_place(net, s, p)).find_map(|p| {
+        let into = single(&s.inputs[p])?;
+        let transition = single(&s.outputs[p])?;
+        if into == transition
+            || tokens(net, p) != 0
+            || single(&s.pre[transition]) != Some(p)
+            || s.is_irregular(into)
+            || s.is_irregular(transition)
+            || net.transitions[into].role != net.transitions[transition].role
+        {
+            return None;
+        }
+        Some(Reduction::FusionOfSeriesTransitions {
+            place: p.clone(),
+            transition: transition.clone(),
+            into: into.clone(),
+        })
+    })
+}
+
+fn fuse_parallel_places(net: &PetriNet, s: &NetStructure) -> Option<Reduction> {
+    let candidates: Vec<&String> = s
+        .places
+        .iter()
+        .filter(|p| reducible_place(net, s, p))
+        .filter(|p| !s.inputs[*p].is_empty() || !s.outputs[*p].is_empty())
+        .collect();
+    for (i, into) in candidates.iter().enumerate() {
+        for place in &candidates[i + 1..] {
+            if s.inputs[*into] == s.inputs[*place]
+                && s.outputs[*into] == s.outputs[*place]
+                && tokens(net, into) == tokens(net, place)
+            {
+                return Some(Reduction::FusionOfParallelPlaces {
+                    place: (*place).clone(),
+                    into: (*into).clone(),
+                });
+            }
+        }
+    }
+    None
+}
+
+fn fuse_parallel_transitions(net: &PetriNet, s: &NetStructure) -> Option<Reduction> {
+    let candidates: Vec<&String> = s
+        .transitions
+        .iter()
+        .filter(|t| !s.is_irregular(t))
+        .filter(|t| !s.pre[*t].is_empty() || !s.post[*t].is_empty())
+        .collect();
+    for (i, into) in candidates.iter().enumerate() {
+        for transition in &candidates[i + 1..] {
+            if s.pre[*into] == s.pre[*transition]
+                && s.post[*into] == s.post[*transition]
+                && net.transitions[*into].role == net.transitions[*transition].role
+            {
+                return Some(Reduction::FusionOfParallelTransitions {
+                    transition: (*transition).clone(),
+                    into: (*into).clone(),
+                });
+            }
+        }
+    }
+    None
+}
+
+fn eliminate_self_loop_place(net: &PetriNet, s: &NetStructure) -> Option<Reduction> {
+    s.places.iter().filter(|p| reducible_place(net, s, p)).find_map(|p| {
+        let t = single(&s.inputs[p])?;
+        if single(&s.outputs[p]) != Some(t) || tokens(net, p) < 1 {
+            return None;
+        }
+        Some(Reduction::EliminationOfSelfLoopPlace { place: p.clone() })
+    })
+}
+
+fn eliminate_self_loop_transition(s: &NetStructure) -> Option<Reduction> {
+    s.transitions.iter().filter(|t| !s.is_irregular(t)).find_map(|t| {
+        let p = single(&s.pre[t])?;
+        if single(&s.post[t]) != Some(p) || s.is_irregular(p) {
+            return None;
+        }
+        Some(Reduction::EliminationOfSelfLoopTransition { transition: t.clone() })
+    })
+}
+
+fn apply(net: &mut PetriNet, step: &Reduction) {
+    match step {
+        Reduction::FusionOfSeriesPlaces { transition, place, into } => {
+            remove_node(net, transition);
+            let initial = tokens(net, place) + tokens(net, into);
+            net.places.get_mut(into).unwrap().initial = Some(initial);
+            redirect(net, place, into);
+            net.places.remove(place);
+        }
+        Reduction::FusionOfSeriesTransitions { place, transition, into } => {
+            remove_node(net, place);
+            redirect(net, transition, into);
+            net.transitions.remove(transition);
+        }
+        Reduction::FusionOfParallelPlaces { place, .. } => remove_node(net, place),
+        Reduction::FusionOfParallelTransitions { transition, .. } => remove_node(net, transition),
+        Reduction::EliminationOfSelfLoopPlace { place } => remove_node(net, place),
+        Reduction::EliminationOfSelfLoopTransition { transition } => remove_node(net, transition),
+    }
+    merge_arcs(net);
+}
+
+fn remove_node(net: &mut PetriNet, label: &str) {
+    net.places.remove(label);
+    net.transitions.remove(label);
+    net.arcs.retain(|a| a.source != label && a.target != label);
+}
+
+fn redirect(net: &mut PetriNet, from: &str, to: &str) {
+    for arc in &mut net.arcs {
+        if arc.source == from {
+            arc.source = to.to_string();
+        }
+        if arc.target == from {
+            arc.target = to.to_string();
+        }
+    }
+}
+
+/// Merges parallel flow arcs created by a fusion into a single arc with the summed weight.
+fn merge_arcs(net: &mut PetriNet) {
+    let mut merged: Vec<Arrow> = Vec::with_capacity(net.arcs.len());
+    for arc in net.arcs.drain(..) {
+        let existing = merged.iter_mut().find(|a| {
+            a.source == arc.source
+                && a.target == arc.target
+                && !a.inhibit.unwrap_or(false)
+                && !arc.inhibit.unwrap_or(false)
+        });
+        match existing {
+            Some(a) => a.weight = Some(a.weight.unwrap_or(1) + arc.weight.unwrap_or(1)),
+            None => merged.push(arc),
+        }
+    }
+    net.arcs = merged;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::vasm::StateMachine;
+
+    use super::*;
+
+    #[test]
+    fn test_series_pipeline_collapses() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("p0", Option::from(1), None, 0, 0);
+            p.cell("p1", None, None, 0, 0);
+            p.cell("p2", None, None, 0, 0);
+            p.func("t0", "default", 0, 0);
+            p.func("t1", "default", 0, 0);
+            p.arrow("p0", "t0", 1);
+            p.arrow("t0", "p1", 1);
+            p.arrow("p1", "t1", 1);
+            p.arrow("t1", "p2", 1);
+        });
+
+        let (reduced, log) = reduce(&net);
+        assert_eq!(log.len(), 2);
+        assert_eq!(reduced.transitions.len(), 0);
+        assert_eq!(reduced.places.len(), 1);
+        assert_eq!(reduced.places["p2"].initial, Some(1));
+        assert_eq!(reduced.places["p2"].offset, 0);
+    }
+
+    #[test]
+    fn test_series_places_respect_implicit_capacity() {
+        let series = |p: &mut dyn FlowDsl| {
+            p.cell("p0", Option::from(1), None, 0, 0);
+            p.cell("p1", Option::from(1), None, 0, 0);
+            p.func("t0", "default", 0, 0);
+            p.arrow("p0", "t0", 1);
+            p.arrow("t0", "p1", 1);
+        };
+        let mut net = PetriNet::new();
+        net.declare(series);
+        let (reduced, _) = reduce(&net);
+        assert_eq!(reduced.places["p1"].initial, Some(2));
+
+        for model_type in ["elementary", "workflow"] {
+            let mut net = PetriNet::new();
+            net.declare(series);
+            net.model_type = model_type.to_string();
+            let (reduced, log) = reduce(&net);
+            assert!(log.is_empty(), "{}", model_type);
+            assert_eq!(reduced.places.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_self_loop_and_parallel_transitions() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("lock", Option::from(1), None, 0, 0);
+            p.cell("out", None, None, 0, 0);
+            p.func("a", "default", 0, 0);
+            p.func("b", "default", 0, 0);
+            p.arrow("lock", "a", 1);
+            p.arrow("a", "lock", 1);
+            p.arrow("a", "out", 1);
+            p.arrow("b", "out", 1);
+        });
+
+        let (reduced, log) = reduce(&net);
+        assert!(!reduced.places.contains_key("lock"));
+        assert_eq!(reduced.transitions.len(), 1);
+        assert!(log.steps.contains(&Reduction::EliminationOfSelfLoopPlace { place: "lock".to_string() }));
+        assert!(log.steps.contains(&Reduction::FusionOfParallelTransitions {
+            transition: "b".to_string(),
+            into: "a".to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_guarded_nodes_are_preserved() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("foo", Option::from(1), None, 0, 0);
+            p.cell("bar", None, None, 0, 0);
+            p.func("t", "default", 0, 0);
+            p.func("u", "default", 0, 0);
+            p.arrow("foo", "t", 1);
+            p.arrow("t", "bar", 1);
+            p.guard("bar", "u", 1);
+        });
+
+        let (reduced, log) = reduce(&net);
+        assert!(log.is_empty());
+        assert_eq!(reduced.places.len(), 2);
+    }
+
+    #[test]
+    fn test_reset_and_transfer_nodes_are_preserved() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("p0", Option::from(1), None, 0, 0);
+            p.cell("p1", None, None, 0, 0);
+            p.cell("p2", None, None, 0, 0);
+            p.cell("p3", None, None, 0, 0);
+            p.func("t0", "default", 0, 0);
+            p.func("t1", "default", 0, 0);
+            p.arrow("p0", "t0", 1);
+            p.arrow("t0", "p1", 1);
+            p.arrow("p1", "t1", 1);
+            p.arrow("t1", "p2", 1);
+            p.reset("p0", "t1");
+            p.transfer("p2", "t0", "p3", 1);
+        });
+
+        let (reduced, log) = reduce(&net);
+        assert!(log.is_empty());
+        assert_eq!(reduced.places.len(), 4);
+    }
+
+    #[test]
+    fn test_reduced_net_builds_state_machine() {
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let (mut reduced, _) = reduce(&net);
+        let sm = StateMachine::from_model(&mut reduced);
+        assert_eq!(sm.places.len(), reduced.places.len());
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeSet;
+
+use crate::analysis::reachability::Limits;
+use crate::analysis::structure::NetStructure;
+use crate::petri_net::PetriNet;
+
+/// PlaceSet is a type alias for an ordered set of place labels.
+pub type PlaceSet = BTreeSet<String>;
+
+/// Checks if every transition producing into `set` also consumes from it (•S ⊆ S•).
+pub fn is_siphon(s: &NetStructure, set: &PlaceSet) -> bool {
+    first_unguarded_producer(s, set).is_none()
+}
+
+/// Checks if every transition consuming from `set` also produces into it (S• ⊆ •S).
+pub fn is_trap(s: &NetStructure, set: &PlaceSet) -> bool {
+    first_unguarded_consumer(s, set).is_none()
+}
+
+/// Returns a transition in •S with no input place in S.
+fn first_unguarded_producer<'a>(s: &'a NetStructure, set: &PlaceSet) -> Option<&'a String> {
+    s.transitions.iter().find(|t| {
+        s.post[*t].keys().any(|p| set.contains(p)) && !s.pre[*t].keys().any(|p| set.contains(p))
+    })
+}
+
+/// Returns a transition in S• with no output place in S.
+fn first_unguarded_consumer<'a>(s: &'a NetStructure, set: &PlaceSet) -> Option<&'a String> {
+    s.transitions.iter().find(|t| {
+        s.pre[*t].keys().any(|p| set.contains(p)) && !s.post[*t].keys().any(|p| set.contains(p))
+    })
+}
+
+/// Enumerates the non-empty siphons or traps reachable by growing single places.
+///
+/// A set violating the property is extended by each place of the violating transition in turn,
+/// so every minimal set is found and non-minimal results are filtered afterwards.
+/// Returns None once more than `max_sets` candidate sets were visited, as their number can grow exponentially.
+fn enumerate_minimal(s: &NetStructure, siphon: bool, max_sets: usize) -> Option<Vec<PlaceSet>> {
+    let mut visited: BTreeSet<PlaceSet> = BTreeSet::new();
+    let mut found: BTreeSet<PlaceSet> = BTreeSet::new();
+    let mut stack: Vec<PlaceSet> = s.places.iter().map(|p| PlaceSet::from([p.clone()])).collect();
+
+    while let Some(set) = stack.pop() {
+        if visited.contains(&set) || found.iter().any(|f| f.is_subset(&set)) {
+            continue;
+        }
+        if visited.len() == max_sets {
+            return None;
+        }
+        visited.insert(set.clone());
+        let violation = if siphon {
+            first_unguarded_producer(s, &set).map(|t| &s.pre[t])
+        } else {
+            first_unguarded_consumer(s, &set).map(|t| &s.post[t])
+        };
+        match violation {
+            None => {
+                found.retain(|f| !set.is_subset(f));
+                found.insert(set);
+            }
+            Some(candidates) => candidates.keys().for_each(|p| {
+                let mut next = set.clone();
+                next.insert(p.clone());
+                stack.push(next);
+            }),
+        }
+    }
+    Some(found.into_iter().collect())
+}
+
+/// Lists the minimal non-empty siphons of the net.
+///
+/// Once emptied a siphon stays empty, so transitions consuming from it are dead from then on.
+/// Arc weights as well as inhibitor and read arcs are ignored.
+pub fn minimal_siphons(s: &NetStructure) -> Vec<PlaceSet> {
+    enumerate_minimal(s, true, usize::MAX).unwrap_or_default()
+}
+
+/// Lists the minimal non-empty traps of the net.
+///
+/// Once marked a trap stays marked. Arc weights as well as inhibitor and read arcs are ignored.
+pub fn minimal_traps(s: &NetStructure) -> Vec<PlaceSet> {
+    enumerate_minimal(s, false, usize::MAX).unwrap_or_default()
+}
+
+/// Returns the largest trap contained in `set`, which is empty if there is none.
+pub fn maximal_trap(s: &NetStructure, set: &PlaceSet) -> PlaceSet {
+    let mut trap = set.clone();
+    while let Some(t) = first_unguarded_consumer(s, &trap) {
+        trap.retain(|p| !s.pre[t].contains_key(p));
+    }
+    trap
+}
+
+/// Lists the minimal siphons that do not contain a trap marked in the initial marking of the net.
+pub fn unmarked_siphons(net: &PetriNet) -> Vec<PlaceSet> {
+    unmarked_siphons_within(net, usize::MAX).unwrap_or_default()
+}
+
+fn unmarked_siphons_within(net: &PetriNet, max_sets: usize) -> Option<Vec<PlaceSet>> {
+    let s = NetStructure::from_net(net);
+    let siphons = enumerate_minimal(&s, true, max_sets)?;
+    Some(
+        siphons
+            .into_iter()
+            .filter(|siphon| {
+                !maximal_trap(&s, siphon)
+                    .iter()
+                    .any(|p| net.places[p].initial.unwrap_or(0) > 0)
+            })
+            .collect(),
+    )
+}
+
+/// Checks the siphon-trap property: every minimal siphon contains an initially marked trap.
+///
+/// For ordinary nets the property guarantees the absence of deadlocks,
+/// and for free-choice nets it is equivalent to liveness (Commoner's theorem).
+/// The guarantees do not hold with reset, transfer or marking-weighted arcs, which move tokens in proportion
+/// to the marking, so such nets do not have the property.
+pub fn siphon_trap_property(net: &PetriNet) -> bool {
+    NetStructure::from_net(net).dependent.is_empty() && unmarked_siphons(net).is_empty()
+}
+
+/// Like `siphon_trap_property`, visiting at most `limits.max_states` candidate siphons,
+/// returns None if the enumeration was cut short or the net has reset, transfer or marking-weighted arcs.
+pub fn siphon_trap_property_within(net: &PetriNet, limits: Limits) -> Option<bool> {
+    if !NetStructure::from_net(net).dependent.is_empty() {
+        return None;
+    }
+    unmarked_siphons_within(net, limits.max_states).map(|siphons| siphons.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    fn set(labels: &[&str]) -> PlaceSet {
+        labels.iter().map(|l| l.to_string()).collect()
+    }
+
+    fn cycle() -> PetriNet {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.cell("sink", None, None, 0, 0);
+            p.func("t0", "default", 0, 0);
+            p.func("t1", "default", 0, 0);
+            p.func("drain", "default", 0, 0);
+            p.arrow("a", "t0", 1);
+            p.arrow("t0", "b", 1);
+            p.arrow("b", "t1", 1);
+            p.arrow("t1", "a", 1);
+            p.arrow("b", "drain", 1);
+            p.arrow("drain", "sink", 1);
+        });
+        net
+    }
+
+    #[test]
+    fn test_minimal_siphons_and_traps() {
+        let net = cycle();
+        let s = NetStructure::from_net(&net);
+        assert_eq!(minimal_siphons(&s), vec![set(&["a", "b"])]);
+        assert_eq!(minimal_traps(&s), vec![set(&["sink"])]);
+        assert!(is_siphon(&s, &set(&["a", "b", "sink"])));
+        assert!(!is_trap(&s, &set(&["a", "b"])));
+    }
+
+    #[test]
+    fn test_draining_cycle_violates_property() {
+        let net = cycle();
+        assert_eq!(unmarked_siphons(&net), vec![set(&["a", "b"])]);
+        assert!(!siphon_trap_property(&net));
+    }
+
+    #[test]
+    fn test_marked_cycle_satisfies_property() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.func("t0", "default", 0, 0);
+            p.func("t1", "default", 0, 0);
+            p.arrow("a", "t0", 1);
+            p.arrow("t0", "b", 1);
+            p.arrow("b", "t1", 1);
+            p.arrow("t1", "a", 1);
+        });
+        let s = NetStructure::from_net(&net);
+        assert_eq!(maximal_trap(&s, &set(&["a", "b"])), set(&["a", "b"]));
+        assert!(siphon_trap_property(&net));
+    }
+
+    #[test]
+    fn test_philosophers_are_deadlock_free() {
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let s = NetStructure::from_net(&net);
+        let siphons = minimal_siphons(&s);
+        assert_eq!(siphons.len(), 20);
+        assert!(siphons.iter().all(|siphon| is_siphon(&s, siphon) && is_trap(&s, siphon)));
+        assert!(siphon_trap_property(&net));
+        assert_eq!(siphon_trap_property_within(&net, Limits::default()), Some(true));
+        assert_eq!(siphon_trap_property_within(&net, Limits::new(10)), None);
+    }
+
+    #[test]
+    fn test_reset_arcs_void_the_property() {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        net.declare(|p| p.reset("chopstick1", "eat2"));
+        assert!(!siphon_trap_property(&net));
+        assert_eq!(siphon_trap_property_within(&net, Limits::default()), None);
+    }
+}
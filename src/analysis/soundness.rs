@@ -0,0 +1,174 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::reachability::{explore, Limits};
+use crate::analysis::structure::NetStructure;
+use crate::petri_net::PetriNet;
+use crate::vasm::StateMachine;
+
+/// `WorkflowShape` names the unique source and sink places of a workflow net.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkflowShape {
+    pub source: String,
+    pub sink: String,
+}
+
+/// Checks that the net has exactly one place without inputs and one place without outputs,
+/// and that every node lies on a path from the former to the latter.
+///
+/// Returns the reason the net is not a workflow net otherwise.
+pub fn workflow_shape(net: &PetriNet) -> Result<WorkflowShape, String> {
+    let s = NetStructure::from_net(net);
+    let sources: Vec<&String> = s.places.iter().filter(|p| s.inputs[*p].is_empty()).collect();
+    let sinks: Vec<&String> = s.places.iter().filter(|p| s.outputs[*p].is_empty()).collect();
+    let (source, sink) = match (sources.as_slice(), sinks.as_slice()) {
+        ([source], [sink]) => (*source, *sink),
+        _ => {
+            return Err(format!(
+                "expected one source and one sink place, found {} and {}",
+                sources.len(),
+                sinks.len()
+            ))
+        }
+    };
+
+    let reach = |start: &String, forward: bool| -> BTreeSet<String> {
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![start.clone()];
+        while let Some(node) = stack.pop() {
+            if !seen.insert(node.clone()) {
+                continue;
+            }
+            let next = match (forward, s.places.contains(&node)) {
+                (true, true) => &s.outputs[&node],
+                (true, false) => &s.post[&node],
+                (false, true) => &s.inputs[&node],
+                (false, false) => &s.pre[&node],
+            };
+            stack.extend(next.keys().cloned());
+        }
+        seen
+    };
+    let from_source = reach(source, true);
+    let to_sink = reach(sink, false);
+    if let Some(node) = s.places.iter().chain(s.transitions.iter()).find(|n| !from_source.contains(*n) || !to_sink.contains(*n)) {
+        return Err(format!("{} is not on a path from {} to {}", node, source, sink));
+    }
+    Ok(WorkflowShape {
+        source: source.clone(),
+        sink: sink.clone(),
+    })
+}
+
+/// `SoundnessReport` holds the three conditions of classical workflow net soundness.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SoundnessReport {
+    pub shape: WorkflowShape,
+    /// The final marking, one token in the sink, is reachable from every reachable state.
+    pub option_to_complete: bool,
+    /// The final marking is the only reachable state marking the sink.
+    pub proper_completion: bool,
+    /// The transitions that fire in no reachable state.
+    pub dead_transitions: Vec<String>,
+    /// False if the exploration was truncated, in which case the report is not conclusive.
+    pub complete: bool,
+}
+
+impl SoundnessReport {
+    /// Checks if the net is sound: all three conditions hold on the full state space.
+    pub fn is_sound(&self) -> bool {
+        self.complete && self.option_to_complete && self.proper_completion && self.dead_transitions.is_empty()
+    }
+}
+
+/// Checks the soundness of a workflow net started with a single token in its source place.
+///
+/// Returns the reason the net is not a workflow net if `workflow_shape` rejects it.
+pub fn soundness(net: &PetriNet, limits: Limits) -> Result<SoundnessReport, String> {
+    let shape = workflow_shape(net)?;
+    let mut started = net.clone();
+    for (label, place) in started.places.iter_mut() {
+        place.initial = Some(i32::from(*label == shape.source));
+    }
+    let sm = StateMachine::from_model(&mut started);
+    let graph = explore(&sm, limits);
+    let sink = sm.places.iter().position(|p| *p == shape.sink).unwrap();
+    let is_final = |state: &Vec<i32>| state[sink] == 1 && state.iter().sum::<i32>() == 1;
+
+    let mut can_complete: Vec<bool> = graph.states.iter().map(is_final).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 0..graph.states.len() {
+            if !can_complete[i] && graph.successors[i].iter().any(|(_, j)| can_complete[*j]) {
+                can_complete[i] = true;
+                changed = true;
+            }
+        }
+    }
+
+    let fired: BTreeSet<&String> = graph.successors.iter().flatten().map(|(action, _)| action).collect();
+    let mut dead_transitions: Vec<String> = sm.transitions.keys().filter(|t| !fired.contains(t)).cloned().collect();
+    dead_transitions.sort();
+
+    Ok(SoundnessReport {
+        shape,
+        option_to_complete: can_complete.iter().all(|c| *c),
+        proper_completion: graph.states.iter().all(|s| s[sink] == 0 || is_final(s)),
+        dead_transitions,
+        complete: graph.complete,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn choice(p: &mut dyn crate::dsl::FlowDsl) {
+        p.model_type("workflow");
+        p.cell("in", Option::from(1), None, 0, 0);
+        p.cell("mid", None, None, 0, 0);
+        p.cell("out", None, None, 0, 0);
+        p.func("a", "default", 0, 0);
+        p.func("b", "default", 0, 0);
+        p.func("c", "default", 0, 0);
+        p.arrow("in", "a", 1);
+        p.arrow("in", "b", 1);
+        p.arrow("a", "mid", 1);
+        p.arrow("b", "mid", 1);
+        p.arrow("mid", "c", 1);
+        p.arrow("c", "out", 1);
+    }
+
+    #[test]
+    fn test_sound_workflow() {
+        let mut net = PetriNet::new();
+        net.declare(choice);
+        let report = soundness(&net, Limits::default()).unwrap();
+        assert_eq!(report.shape, WorkflowShape { source: "in".to_string(), sink: "out".to_string() });
+        assert!(report.is_sound(), "{:?}", report);
+    }
+
+    #[test]
+    fn test_unsound_workflows() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            choice(p);
+            p.cell("extra", None, None, 0, 0);
+            p.func("d", "default", 0, 0);
+            p.arrow("in", "d", 1);
+            p.arrow("d", "extra", 1);
+            p.arrow("extra", "c", 1);
+        });
+        let report = soundness(&net, Limits::default()).unwrap();
+        assert!(!report.option_to_complete);
+        assert!(report.proper_completion);
+
+        net.remove_transition("d");
+        assert_eq!(
+            workflow_shape(&net).unwrap_err(),
+            "expected one source and one sink place, found 2 and 1"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::analysis::reachability::{explore, Limits};
+use crate::vasm::StateMachine;
+
+/// `Statistics` summarizes the size of the reachable state space of a state machine.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Statistics {
+    /// The number of distinct reachable states.
+    pub states: usize,
+    /// The number of firings between reachable states.
+    pub arcs: usize,
+    /// The largest number of tokens seen in each place.
+    pub max_tokens: BTreeMap<String, i32>,
+    /// The number of reachable states in which no transition is enabled.
+    pub dead_states: usize,
+    /// The time spent exploring the state space in microseconds.
+    pub elapsed_micros: u64,
+    /// False if the exploration stopped because the limits were reached.
+    pub complete: bool,
+}
+
+/// Explores the state space within `limits` and collects its statistics.
+pub fn statistics(sm: &StateMachine, limits: Limits) -> Statistics {
+    let started = Instant::now();
+    let graph = explore(sm, limits);
+    let elapsed_micros = started.elapsed().as_micros() as u64;
+
+    let max_tokens = sm
+        .places
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (p.clone(), graph.states.iter().map(|s| s[i]).max().unwrap_or(0)))
+        .collect();
+
+    Statistics {
+        states: graph.states.len(),
+        arcs: graph.edge_count(),
+        max_tokens,
+        dead_states: graph.successors.iter().filter(|s| s.is_empty()).count(),
+        elapsed_micros,
+        complete: graph.complete,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::petri_net::PetriNet;
+
+    use super::*;
+
+    #[test]
+    fn test_philosopher_statistics() {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let sm = StateMachine::from_model(&mut net);
+        let stats = statistics(&sm, Limits::default());
+        assert!(stats.complete);
+        assert_eq!(stats.states, 11);
+        assert_eq!(stats.arcs, 30);
+        assert_eq!(stats.dead_states, 0);
+        assert_eq!(stats.max_tokens["chopstick1"], 1);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["states"], 11);
+        assert!(json.get("elapsedMicros").is_some());
+    }
+
+    #[test]
+    fn test_dead_states_are_counted() {
+        let sm = StateMachine::new(|p| {
+            p.cell("a", Option::from(2), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.func("move", "default", 0, 0);
+            p.arrow("a", "move", 1);
+            p.arrow("move", "b", 1);
+        });
+        let stats = statistics(&sm, Limits::default());
+        assert_eq!(stats.states, 3);
+        assert_eq!(stats.dead_states, 1);
+        assert_eq!(stats.max_tokens["b"], 2);
+    }
+}
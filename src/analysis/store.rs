@@ -0,0 +1,268 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::ops::Deref;
+#[cfg(feature = "parallel")]
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use serde::{Serialize, Serializer};
+
+use crate::vasm::Vector;
+
+/// `StateId` identifies a state interned in a `StateStore`, ids are assigned in insertion order from zero.
+pub type StateId = usize;
+
+/// `StateStore` interns the states visited by an analysis, so each marking is stored exactly once
+/// and graphs and traces refer to it by its `StateId`.
+///
+/// States are looked up by their hash, only the rare states whose hash collides with an earlier one
+/// are kept in a second map keyed by the full marking. The store dereferences to the slice of states
+/// in id order.
+#[derive(Debug, Clone, Default)]
+pub struct StateStore {
+    states: Vec<Vector>,
+    hashes: HashMap<u64, StateId>,
+    collisions: HashMap<Vector, StateId>,
+    hasher: RandomState,
+}
+
+impl StateStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the id of the state, adding it to the store if it was not interned yet.
+    /// The flag is true if the state was added.
+    pub fn intern(&mut self, state: Vector) -> (StateId, bool) {
+        let hash = self.hasher.hash_one(&state);
+        let id = self.states.len();
+        match self.hashes.get(&hash) {
+            Some(i) if self.states[*i] == state => return (*i, false),
+            Some(_) => match self.collisions.get(&state) {
+                Some(i) => return (*i, false),
+                None => self.collisions.insert(state.clone(), id),
+            },
+            None => self.hashes.insert(hash, id),
+        };
+        self.states.push(state);
+        (id, true)
+    }
+
+    /// Returns the id of the state if it was interned.
+    pub fn get(&self, state: &Vector) -> Option<StateId> {
+        match self.hashes.get(&self.hasher.hash_one(state)) {
+            Some(i) if self.states[*i] == *state => Some(*i),
+            Some(_) => self.collisions.get(state).copied(),
+            None => None,
+        }
+    }
+
+    /// Resolves an id back to its marking.
+    pub fn resolve(&self, id: StateId) -> Option<&Vector> {
+        self.states.get(id)
+    }
+
+    /// Consumes the store and returns the states in id order.
+    pub fn into_states(self) -> Vec<Vector> {
+        self.states
+    }
+}
+
+impl Deref for StateStore {
+    type Target = [Vector];
+
+    fn deref(&self) -> &Self::Target {
+        &self.states
+    }
+}
+
+impl PartialEq for StateStore {
+    fn eq(&self, other: &Self) -> bool {
+        self.states == other.states
+    }
+}
+
+impl PartialEq<Vec<Vector>> for StateStore {
+    fn eq(&self, other: &Vec<Vector>) -> bool {
+        self.states == *other
+    }
+}
+
+impl FromIterator<Vector> for StateStore {
+    fn from_iter<I: IntoIterator<Item = Vector>>(iter: I) -> Self {
+        let mut store = Self::new();
+        for state in iter {
+            store.intern(state);
+        }
+        store
+    }
+}
+
+impl Serialize for StateStore {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.states.serialize(serializer)
+    }
+}
+
+/// The number of locks a `SharedStore` spreads its states over.
+#[cfg(feature = "parallel")]
+const SHARDS: usize = 64;
+
+/// `Rank` is the position a state is reached at during a breadth-first level, the offset in the level
+/// of the state fired and the index of the action, in the order of the single-threaded search.
+#[cfg(feature = "parallel")]
+pub(crate) type Rank = (usize, usize);
+
+#[cfg(feature = "parallel")]
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Visited(StateId),
+    Found(Rank),
+}
+
+/// `Visit` tells what a `SharedStore` knew of a state when a worker reached it.
+#[cfg(feature = "parallel")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Visit {
+    /// The state was numbered by an earlier level.
+    Visited(StateId),
+    /// The state is new and this worker added it.
+    First,
+    /// The state is new but was already added during the level.
+    Again,
+}
+
+/// `SharedStore` is the visited set of a parallel search, which the workers query and extend concurrently.
+///
+/// States are spread over shards by hash, each behind its own lock, so workers rarely wait for each other.
+/// A state found during the current level keeps the earliest rank it was reached at, until the level is
+/// done and `number` assigns it an id.
+#[cfg(feature = "parallel")]
+#[derive(Debug)]
+pub(crate) struct SharedStore {
+    shards: Box<[Mutex<HashMap<Vector, Slot>>]>,
+    hasher: RandomState,
+}
+
+#[cfg(feature = "parallel")]
+impl SharedStore {
+    /// Creates a store holding the initial state with id zero.
+    pub(crate) fn new(initial: Vector) -> Self {
+        let store = Self {
+            shards: (0..SHARDS).map(|_| Mutex::default()).collect(),
+            hasher: RandomState::new(),
+        };
+        store.shard(&initial).insert(initial, Slot::Visited(0));
+        store
+    }
+
+    fn shard(&self, state: &Vector) -> MutexGuard<'_, HashMap<Vector, Slot>> {
+        let shard = &self.shards[self.hasher.hash_one(state) as usize % SHARDS];
+        shard.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Looks the state up, adding it with the rank if it is new, or lowering its rank if it was found
+    /// during the level.
+    pub(crate) fn visit(&self, state: &Vector, rank: Rank) -> Visit {
+        let mut shard = self.shard(state);
+        match shard.get_mut(state) {
+            Some(Slot::Visited(id)) => Visit::Visited(*id),
+            Some(Slot::Found(found)) => {
+                *found = rank.min(*found);
+                Visit::Again
+            }
+            None => {
+                shard.insert(state.clone(), Slot::Found(rank));
+                Visit::First
+            }
+        }
+    }
+
+    /// Returns the earliest rank a state found during the level was reached at.
+    pub(crate) fn rank(&self, state: &Vector) -> Option<Rank> {
+        match self.shard(state).get(state) {
+            Some(Slot::Found(rank)) => Some(*rank),
+            _ => None,
+        }
+    }
+
+    /// Assigns the id to a state found during the level, or forgets the state if there is no id.
+    pub(crate) fn number(&self, state: &Vector, id: Option<StateId>) {
+        let mut shard = self.shard(state);
+        match (id, shard.get_mut(state)) {
+            (Some(id), Some(slot)) => *slot = Slot::Visited(id),
+            (Some(id), None) => {
+                shard.insert(state.clone(), Slot::Visited(id));
+            }
+            (None, _) => {
+                shard.remove(state);
+            }
+        }
+    }
+
+    /// Returns the id of the state if it was numbered.
+    pub(crate) fn get(&self, state: &Vector) -> Option<StateId> {
+        match self.shard(state).get(state) {
+            Some(Slot::Visited(id)) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_and_resolve() {
+        let mut store = StateStore::new();
+        assert_eq!(store.intern(vec![1, 0]), (0, true));
+        assert_eq!(store.intern(vec![0, 1]), (1, true));
+        assert_eq!(store.intern(vec![1, 0]), (0, false));
+        assert_eq!(store.get(&vec![0, 1]), Some(1));
+        assert_eq!(store.get(&vec![1, 1]), None);
+        assert_eq!(store.resolve(1), Some(&vec![0, 1]));
+        assert_eq!(store.resolve(2), None);
+        assert_eq!(store.len(), 2);
+        assert_eq!(serde_json::to_string(&store).unwrap(), "[[1,0],[0,1]]");
+    }
+
+    #[test]
+    fn test_hash_collisions() {
+        let mut store = StateStore::new();
+        // Force every state into the collision map by planting its hash on another id.
+        let states: Vec<Vector> = (0..50).map(|i| vec![i, i % 7]).collect();
+        store.intern(vec![-1]);
+        for state in &states {
+            store.hashes.insert(store.hasher.hash_one(state), 0);
+        }
+        for (i, state) in states.iter().enumerate() {
+            assert_eq!(store.intern(state.clone()), (i + 1, true));
+        }
+        for (i, state) in states.iter().enumerate() {
+            assert_eq!(store.get(state), Some(i + 1));
+            assert_eq!(store.intern(state.clone()), (i + 1, false));
+        }
+        assert_eq!(store.get(&vec![-1]), Some(0));
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_shared_store_keeps_the_earliest_rank() {
+        let store = SharedStore::new(vec![1, 0]);
+        assert_eq!(store.visit(&vec![1, 0], (0, 0)), Visit::Visited(0));
+        assert_eq!(store.visit(&vec![0, 1], (2, 1)), Visit::First);
+        assert_eq!(store.visit(&vec![0, 1], (1, 3)), Visit::Again);
+        assert_eq!(store.visit(&vec![0, 1], (2, 0)), Visit::Again);
+        assert_eq!(store.visit(&vec![1, 1], (3, 0)), Visit::First);
+        assert_eq!(store.rank(&vec![0, 1]), Some((1, 3)));
+        assert_eq!(store.get(&vec![0, 1]), None);
+
+        store.number(&vec![0, 1], Some(1));
+        store.number(&vec![1, 1], None);
+        assert_eq!(store.get(&vec![0, 1]), Some(1));
+        assert_eq!(store.rank(&vec![0, 1]), None);
+        assert_eq!(store.visit(&vec![1, 1], (0, 0)), Visit::First);
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::petri_net::PetriNet;
+
+/// Adjacency is a type alias for a BTreeMap that maps a neighbouring node to the arc weight.
+pub type Adjacency = BTreeMap<String, i32>;
+
+/// `NetStructure` is the bipartite graph of a `PetriNet` indexed by node label.
+/// Inhibitor and read arcs do not move tokens, so they are kept apart from the flow relation.
+/// Reset, transfer and marking-weighted arcs move a number of tokens computed from the marking, they are
+/// part of the flow relation with a weight of one and their nodes are listed as `dependent`.
+#[derive(Debug, Clone, Default)]
+pub struct NetStructure {
+    /// The labels of all places in the net.
+    pub places: BTreeSet<String>,
+    /// The labels of all transitions in the net.
+    pub transitions: BTreeSet<String>,
+    /// The input places of each transition (•t) with the consumed weight.
+    pub pre: BTreeMap<String, Adjacency>,
+    /// The output places of each transition (t•) with the produced weight.
+    pub post: BTreeMap<String, Adjacency>,
+    /// The transitions producing into each place (•p).
+    pub inputs: BTreeMap<String, Adjacency>,
+    /// The transitions consuming from each place (p•).
+    pub outputs: BTreeMap<String, Adjacency>,
+    /// Nodes attached to an inhibitor or read arc.
+    pub guarded: BTreeSet<String>,
+    /// Nodes attached to an arc moving a number of tokens that depends on the marking, such as reset, transfer
+    /// and marking-weighted arcs, whose weights in the flow relation do not tell how many tokens move.
+    pub dependent: BTreeSet<String>,
+}
+
+impl NetStructure {
+    /// Builds the structure of the given `PetriNet`, arcs with unknown endpoints are ignored.
+    pub fn from_net(net: &PetriNet) -> Self {
+        let mut s = Self::default();
+        for p in net.places.keys() {
+            s.places.insert(p.clone());
+            s.inputs.insert(p.clone(), Adjacency::new());
+            s.outputs.insert(p.clone(), Adjacency::new());
+        }
+        for t in net.transitions.keys() {
+            s.transitions.insert(t.clone());
+            s.pre.insert(t.clone(), Adjacency::new());
+            s.post.insert(t.clone(), Adjacency::new());
+        }
+
+        for arc in &net.arcs {
+            let weight = arc.weight.unwrap_or(1);
+            if arc.reset.unwrap_or(false) || arc.transfer.is_some() {
+                s.add_dependent(&arc.source, &arc.target, true);
+                if let Some(into) = &arc.transfer {
+                    s.add_dependent(into, &arc.target, false);
+                }
+            } else if arc.marking_weight.is_some() && !arc.inhibit.unwrap_or(false) {
+                s.add_dependent(&arc.source, &arc.target, true);
+                s.add_dependent(&arc.target, &arc.source, false);
+            } else if arc.inhibit.unwrap_or(false) {
+                s.guarded.insert(arc.source.clone());
+                s.guarded.insert(arc.target.clone());
+            } else if s.places.contains(&arc.source) && s.transitions.contains(&arc.target) {
+                *s.pre.get_mut(&arc.target).unwrap().entry(arc.source.clone()).or_insert(0) += weight;
+                *s.outputs.get_mut(&arc.source).unwrap().entry(arc.target.clone()).or_insert(0) += weight;
+            } else if s.transitions.contains(&arc.source) && s.places.contains(&arc.target) {
+                *s.post.get_mut(&arc.source).unwrap().entry(arc.target.clone()).or_insert(0) += weight;
+                *s.inputs.get_mut(&arc.target).unwrap().entry(arc.source.clone()).or_insert(0) += weight;
+            }
+        }
+        s
+    }
+
+    /// Adds a marking-dependent arc between the place and the transition to the flow relation.
+    fn add_dependent(&mut self, place: &str, transition: &str, consume: bool) {
+        if !self.places.contains(place) || !self.transitions.contains(transition) {
+            return;
+        }
+        let (flow, places) = if consume {
+            (&mut self.pre, &mut self.outputs)
+        } else {
+            (&mut self.post, &mut self.inputs)
+        };
+        flow.get_mut(transition).unwrap().entry(place.to_string()).or_insert(1);
+        places.get_mut(place).unwrap().entry(transition.to_string()).or_insert(1);
+        self.dependent.insert(place.to_string());
+        self.dependent.insert(transition.to_string());
+    }
+
+    /// Returns true if every flow arc in the net has a weight of one and moves a fixed number of tokens.
+    pub fn is_ordinary(&self) -> bool {
+        self.dependent.is_empty()
+            && self.pre.values().chain(self.post.values()).all(|adj| adj.values().all(|w| *w == 1))
+    }
+
+    /// Checks if the node is attached to an inhibitor or read arc, or to a marking-dependent arc,
+    /// which the behavior-preserving rules do not account for.
+    pub fn is_irregular(&self, node: &str) -> bool {
+        self.guarded.contains(node) || self.dependent.contains(node)
+    }
+}
@@ -0,0 +1,230 @@
+use serde::{Deserialize, Serialize};
+
+use crate::oid::Oid;
+use crate::vasm::{StateMachine, Transaction, Vector};
+
+/// `AuditRecord` is an entry of an `AuditLog`, one per fire attempt whether it succeeded or not.
+///
+/// `hash` is the CID of the record without it, and covers `previous_hash`, so altering, removing or
+/// reordering any record breaks the chain from that record on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+    /// The position of the record in the log, starting at zero.
+    pub sequence: u64,
+    /// The role the action was fired on behalf of.
+    pub role: String,
+    pub action: String,
+    pub multiple: i32,
+    pub ok: bool,
+    pub inhibited: bool,
+    pub overflow: bool,
+    pub underflow: bool,
+    /// The reason the attempt was rejected before any arithmetic, see `TransformError`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The hash of the marking the action was fired in, see `state_hash`.
+    pub prior_state: String,
+    /// The hash of the marking after the attempt, equal to `prior_state` if it failed.
+    pub next_state: String,
+    /// The hash of the previous record, empty for the first one.
+    pub previous_hash: String,
+    pub hash: String,
+}
+
+impl AuditRecord {
+    /// Computes the hash of the record from every other field.
+    pub fn compute_hash(&self) -> String {
+        let unsealed = AuditRecord {
+            hash: String::new(),
+            ..self.clone()
+        };
+        Oid::new(serde_json::to_string(&unsealed).unwrap().as_bytes())
+            .unwrap()
+            .to_string()
+    }
+}
+
+/// Returns the CID of the marking, which keeps records small on nets with many places.
+pub fn state_hash(state: &Vector) -> String {
+    Oid::new(serde_json::to_string(state).unwrap().as_bytes())
+        .unwrap()
+        .to_string()
+}
+
+/// `AuditLog` is a tamper-evident log of fire attempts, each record chained to the previous one by its hash.
+///
+/// The chain cannot tell a log cut short at the tail from one that ended there, so `head` must be kept
+/// outside the log, for instance published or signed, and checked with `verify_head`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditLog {
+    records: Vec<AuditRecord>,
+}
+
+impl AuditLog {
+    /// Creates an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the records in the order they were appended.
+    pub fn records(&self) -> &[AuditRecord] {
+        &self.records
+    }
+
+    /// Returns the hash of the last record, empty if there is none.
+    pub fn head(&self) -> &str {
+        self.records.last().map(|r| r.hash.as_str()).unwrap_or("")
+    }
+
+    /// Fires the action on behalf of `role` like `StateMachine::transform_as` and records the attempt.
+    pub fn fire(&mut self, sm: &StateMachine, state: &Vector, action: &str, multiple: i32, role: &str) -> Transaction {
+        let tx = sm.transform_as(state, action, multiple, role);
+        self.record(role, action, multiple, state, &tx);
+        tx
+    }
+
+    /// Appends the record of a fire attempt made elsewhere, in the state `prior` with the transaction `tx`.
+    pub fn record(
+        &mut self,
+        role: &str,
+        action: &str,
+        multiple: i32,
+        prior: &Vector,
+        tx: &Transaction,
+    ) -> &AuditRecord {
+        let prior_state = state_hash(prior);
+        let next_state = if tx.is_ok() {
+            state_hash(&tx.output)
+        } else {
+            prior_state.clone()
+        };
+        let mut record = AuditRecord {
+            sequence: self.records.len() as u64,
+            role: role.to_string(),
+            action: action.to_string(),
+            multiple,
+            ok: tx.ok,
+            inhibited: tx.inhibited,
+            overflow: tx.overflow,
+            underflow: tx.underflow,
+            error: tx.error.as_ref().map(|err| err.to_string()),
+            prior_state,
+            next_state,
+            previous_hash: self.head().to_string(),
+            hash: String::new(),
+        };
+        record.hash = record.compute_hash();
+        self.records.push(record);
+        self.records.last().unwrap()
+    }
+
+    /// Checks the chain, returns the sequence number of the first record that was altered,
+    /// removed or reordered, or None if the log is intact.
+    ///
+    /// Records removed from the tail go unnoticed, see `verify_head`.
+    pub fn verify(&self) -> Option<usize> {
+        let mut previous_hash = "";
+        for (i, record) in self.records.iter().enumerate() {
+            if record.sequence != i as u64
+                || record.previous_hash != previous_hash
+                || record.hash != record.compute_hash()
+            {
+                return Some(i);
+            }
+            previous_hash = &record.hash;
+        }
+        None
+    }
+
+    /// Checks the chain like `verify` and that it ends at the expected head, a hash returned by `head`
+    /// and kept elsewhere. Returns the length of the log if it is intact but does not end there.
+    pub fn verify_head(&self, head: &str) -> Option<usize> {
+        self.verify().or_else(|| (self.head() != head).then_some(self.records.len()))
+    }
+
+    /// Exports the log as JSON lines, one record per line.
+    pub fn to_jsonl(&self) -> String {
+        self.records
+            .iter()
+            .map(|record| serde_json::to_string(record).unwrap() + "\n")
+            .collect()
+    }
+
+    /// Reads a log exported by `to_jsonl`, blank lines are skipped. Use `verify` to check the chain.
+    pub fn from_jsonl(jsonl: &str) -> Result<Self, serde_json::Error> {
+        let records = jsonl
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { records })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+    use crate::vasm::Vasm;
+
+    use super::*;
+
+    fn payment(p: &mut dyn FlowDsl) {
+        p.cell("pending", Option::from(1), None, 0, 0);
+        p.cell("paid", None, None, 0, 0);
+        p.func("pay", "clerk", 0, 0);
+        p.arrow("pending", "pay", 1);
+        p.arrow("pay", "paid", 1);
+    }
+
+    fn audited() -> AuditLog {
+        let sm = StateMachine::new(payment);
+        let mut log = AuditLog::new();
+        let state = sm.initial_vector();
+        assert!(log.fire(&sm, &state, "pay", 1, "guest").is_err());
+        let paid = log.fire(&sm, &state, "pay", 1, "clerk").output;
+        assert!(log.fire(&sm, &paid, "pay", 1, "clerk").is_err());
+        log
+    }
+
+    #[test]
+    fn test_records_every_attempt() {
+        let log = audited();
+        let records = log.records();
+        assert_eq!(records.len(), 3);
+        assert_eq!((records[0].role.as_str(), records[0].ok), ("guest", false));
+        assert!(records[0].error.as_deref().unwrap().contains("clerk"));
+        assert_eq!(records[0].prior_state, records[0].next_state);
+        assert!(records[1].ok);
+        assert_eq!(records[1].next_state, state_hash(&vec![0, 1]));
+        assert_eq!(records[2].prior_state, records[1].next_state);
+        assert!(records[2].underflow);
+        assert_eq!(records[1].previous_hash, records[0].hash);
+        assert_eq!(log.head(), records[2].hash);
+        assert_eq!(log.verify(), None);
+    }
+
+    #[test]
+    fn test_jsonl_round_trip_and_tampering() {
+        let log = audited();
+        let jsonl = log.to_jsonl();
+        assert_eq!(jsonl.lines().count(), 3);
+        assert!(jsonl.lines().next().unwrap().contains("\"priorState\""));
+        let read = AuditLog::from_jsonl(&jsonl).unwrap();
+        assert_eq!(read, log);
+
+        let altered = AuditLog::from_jsonl(&jsonl.replacen("\"guest\"", "\"clerk\"", 1)).unwrap();
+        assert_eq!(altered.verify(), Some(0));
+
+        let mut removed = log.clone();
+        removed.records.remove(1);
+        assert_eq!(removed.verify(), Some(1));
+
+        let mut truncated = log.clone();
+        truncated.records.pop();
+        assert_eq!(truncated.verify(), None);
+        assert_eq!(truncated.verify_head(log.head()), Some(2));
+        assert_eq!(log.verify_head(log.head()), None);
+        assert_eq!(removed.verify_head(log.head()), Some(1));
+    }
+}
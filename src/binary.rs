@@ -0,0 +1,68 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Encodes a `PetriNet`, `StateMachine`, `Transaction` or any other model type as CBOR.
+#[cfg(feature = "cbor")]
+pub fn to_cbor<T: Serialize>(value: &T) -> Result<Vec<u8>, ciborium::ser::Error<std::io::Error>> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decodes a value encoded by `to_cbor`.
+#[cfg(feature = "cbor")]
+pub fn from_cbor<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ciborium::de::Error<std::io::Error>> {
+    ciborium::from_reader(bytes)
+}
+
+/// Encodes a `PetriNet`, `StateMachine`, `Transaction` or any other model type as MessagePack.
+///
+/// Structs are written as maps, since the optional fields skipped when empty would shift positional fields.
+#[cfg(feature = "msgpack")]
+pub fn to_msgpack<T: Serialize>(value: &T) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    rmp_serde::to_vec_named(value)
+}
+
+/// Decodes a value encoded by `to_msgpack`.
+#[cfg(feature = "msgpack")]
+pub fn from_msgpack<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, rmp_serde::decode::Error> {
+    rmp_serde::from_slice(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::petri_net::PetriNet;
+    use crate::vasm::{StateMachine, Transaction, Vasm};
+
+    use super::*;
+
+    fn fixtures() -> (PetriNet, StateMachine, Transaction) {
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let sm = StateMachine::from_model(&mut net.clone());
+        let missing = sm.transform(&sm.initial_vector(), "missing", 1);
+        (net, sm, missing)
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_round_trip() {
+        let (net, sm, tx) = fixtures();
+        let bytes = to_cbor(&net).unwrap();
+        assert!(bytes.len() < net.to_json().unwrap().len());
+        assert_eq!(from_cbor::<PetriNet>(&bytes).unwrap().to_json().unwrap(), net.to_json().unwrap());
+        assert_eq!(from_cbor::<StateMachine>(&to_cbor(&sm).unwrap()).unwrap().places, sm.places);
+        assert_eq!(from_cbor::<Transaction>(&to_cbor(&tx).unwrap()).unwrap().error, tx.error);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_round_trip() {
+        let (net, sm, tx) = fixtures();
+        let bytes = to_msgpack(&net).unwrap();
+        assert!(bytes.len() < net.to_json().unwrap().len());
+        assert_eq!(from_msgpack::<PetriNet>(&bytes).unwrap().to_json().unwrap(), net.to_json().unwrap());
+        assert_eq!(from_msgpack::<StateMachine>(&to_msgpack(&sm).unwrap()).unwrap().places, sm.places);
+        assert_eq!(from_msgpack::<Transaction>(&to_msgpack(&tx).unwrap()).unwrap().error, tx.error);
+    }
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+
+use crate::vasm::Vector;
+
+const BITS: usize = u64::BITS as usize;
+
+/// `BitState` is the marking of a 1-safe net packed into 64-bit words, one bit per place.
+///
+/// Elementary and workflow models never hold more than one token in a place, so their markings take
+/// a bit per place instead of the 32 of a `Vector`. The number of places is not stored, it is known
+/// from the state machine the marking belongs to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BitState {
+    words: Box<[u64]>,
+}
+
+impl BitState {
+    /// Packs the state vector, or returns None if a place holds anything other than zero or one token.
+    pub fn pack(state: &Vector) -> Option<Self> {
+        let mut words = vec![0u64; state.len().div_ceil(BITS)];
+        for (i, tokens) in state.iter().enumerate() {
+            match tokens {
+                0 => {}
+                1 => words[i / BITS] |= 1 << (i % BITS),
+                _ => return None,
+            }
+        }
+        Some(Self { words: words.into() })
+    }
+
+    /// Unpacks the marking into a state vector with one entry per place.
+    pub fn unpack(&self, places: usize) -> Vector {
+        (0..places).map(|i| self.get(i) as i32).collect()
+    }
+
+    /// Checks if the place at the given offset is marked.
+    pub fn get(&self, place: usize) -> bool {
+        self.words.get(place / BITS).is_some_and(|w| w >> (place % BITS) & 1 == 1)
+    }
+
+    /// Returns the number of marked places.
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Returns the offsets of the marked places in increasing order.
+    pub fn marked(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.words.len() * BITS).filter(|i| self.get(*i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_round_trip() {
+        for len in [0, 1, 63, 64, 65, 130] {
+            let state: Vector = (0..len).map(|i| (i % 3 == 0) as i32).collect();
+            let bits = BitState::pack(&state).unwrap();
+            assert_eq!(bits.unpack(len), state);
+            assert_eq!(bits.count(), state.iter().sum::<i32>() as usize);
+            assert_eq!(bits.marked().collect::<Vec<_>>(), (0..len).step_by(3).collect::<Vec<_>>());
+        }
+        assert!(BitState::pack(&vec![0, 2, 1]).is_none());
+        assert!(BitState::pack(&vec![-1]).is_none());
+        assert_ne!(BitState::pack(&vec![1, 0]), BitState::pack(&vec![0, 1]));
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::capacity::Capacity;
+use crate::engine::GuardKind;
+use crate::roles::RoleRegistry;
+use crate::vasm::{
+    Guard, GuardMap, ModelType, ReentryPolicy, RoleMap, SemanticsConfig, StateMachine, Transition, TransitionMap,
+    Vector,
+};
+
+/// `BuildError` is returned by `StateMachineBuilder::build` when the vectors do not describe a state machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// Two places share the label.
+    DuplicatePlace { place: String },
+    /// Two transitions share the label.
+    DuplicateTransition { transition: String },
+    /// The initial tokens of the place are negative.
+    NegativeInitial { place: String, initial: i32 },
+    /// The delta or a guard of the transition does not have one entry per place.
+    DimensionMismatch {
+        transition: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// A guard of the transition does not test a single place against a positive weight.
+    MalformedGuard { transition: String },
+    /// The transition has two guards on the place.
+    DuplicateGuard { transition: String, place: String },
+    /// A guard or reentry refers to a transition that was not added.
+    UnknownTransition { transition: String },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::DuplicatePlace { place } => write!(f, "place {} is declared twice", place),
+            BuildError::DuplicateTransition { transition } => {
+                write!(f, "transition {} is declared twice", transition)
+            }
+            BuildError::NegativeInitial { place, initial } => {
+                write!(f, "place {} starts with {} tokens", place, initial)
+            }
+            BuildError::DimensionMismatch {
+                transition,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "transition {} has a vector of {} entries, expected {}",
+                transition, actual, expected
+            ),
+            BuildError::MalformedGuard { transition } => {
+                write!(f, "a guard of transition {} must test exactly one place", transition)
+            }
+            BuildError::DuplicateGuard { transition, place } => {
+                write!(f, "transition {} has two guards on place {}", transition, place)
+            }
+            BuildError::UnknownTransition { transition } => write!(f, "transition {} is not declared", transition),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// `StateMachineBuilder` assembles a `StateMachine` directly from vectors, without a `PetriNet` graph.
+///
+/// It suits callers that already hold matrix data, such as the columns of an incidence matrix. Places are
+/// numbered in the order they are added and every delta or guard has one entry per place, which `build`
+/// checks along with the labels. Elementary and workflow models hold at most one token per place, so their
+/// capacities and initial tokens are clamped to one like `StateMachine::from_model` does.
+#[derive(Debug, Clone)]
+pub struct StateMachineBuilder {
+    model_type: ModelType,
+    reentry: ReentryPolicy,
+    places: Vec<(String, i32, Capacity)>,
+    transitions: Vec<(String, String, Vector)>,
+    guards: Vec<(String, Vector, GuardKind)>,
+    reentrant: Vec<String>,
+}
+
+impl StateMachineBuilder {
+    /// Starts a state machine of the given model type without places or transitions.
+    pub fn new(model_type: ModelType) -> Self {
+        Self {
+            model_type,
+            reentry: ReentryPolicy::default(),
+            places: Vec::new(),
+            transitions: Vec::new(),
+            guards: Vec::new(),
+            reentrant: Vec::new(),
+        }
+    }
+
+    /// Adds a place at the next offset.
+    pub fn place(mut self, label: &str, initial: i32, capacity: Capacity) -> Self {
+        self.places.push((label.to_string(), initial, capacity));
+        self
+    }
+
+    /// Adds a transition of the role changing each place by the entry of `delta` at its offset.
+    pub fn transition(mut self, label: &str, role: &str, delta: Vector) -> Self {
+        self.transitions.push((label.to_string(), role.to_string(), delta));
+        self
+    }
+
+    /// Adds a guard to the transition, given as the negated weight at the offset of the guarded place and zero
+    /// elsewhere, as `Guard::delta` returns it.
+    pub fn guard(mut self, transition: &str, delta: Vector, kind: GuardKind) -> Self {
+        self.guards.push((transition.to_string(), delta, kind));
+        self
+    }
+
+    /// Lets the transition fire into the marked place of a workflow, see `ReentryPolicy`.
+    pub fn allow_reentry(mut self, transition: &str) -> Self {
+        self.reentrant.push(transition.to_string());
+        self
+    }
+
+    /// Sets the reentry policy of the state machine.
+    pub fn with_reentry(mut self, reentry: ReentryPolicy) -> Self {
+        self.reentry = reentry;
+        self
+    }
+
+    /// Checks the labels and vectors and assembles the state machine.
+    pub fn build(self) -> Result<StateMachine, BuildError> {
+        let size = self.places.len();
+        let mut labels = HashSet::new();
+        for (place, initial, _) in &self.places {
+            if !labels.insert(place.as_str()) {
+                return Err(BuildError::DuplicatePlace { place: place.clone() });
+            }
+            if *initial < 0 {
+                return Err(BuildError::NegativeInitial {
+                    place: place.clone(),
+                    initial: *initial,
+                });
+            }
+        }
+
+        let mut transitions = TransitionMap::new();
+        let mut roles = RoleMap::new();
+        for (label, role, delta) in self.transitions {
+            check_size(&label, &delta, size)?;
+            if transitions.contains_key(&label) {
+                return Err(BuildError::DuplicateTransition { transition: label });
+            }
+            roles.insert(role.clone(), true);
+            let transition = Transition {
+                label: label.clone(),
+                role,
+                delta,
+                guards: GuardMap::new(),
+                ..Default::default()
+            };
+            transitions.insert(label, transition);
+        }
+        for (label, delta, kind) in self.guards {
+            check_size(&label, &delta, size)?;
+            let Some(transition) = transitions.get_mut(&label) else {
+                return Err(BuildError::UnknownTransition { transition: label });
+            };
+            let mut entries = delta.iter().enumerate().filter(|(_, d)| **d != 0);
+            let offset = match (entries.next(), entries.next()) {
+                (Some((offset, weight)), None) if *weight < 0 => offset,
+                _ => return Err(BuildError::MalformedGuard { transition: label }),
+            };
+            let place = self.places[offset].0.clone();
+            if transition.guards.contains_key(&place) {
+                return Err(BuildError::DuplicateGuard {
+                    transition: label,
+                    place,
+                });
+            }
+            transition
+                .guards
+                .insert(place, Guard::new(delta, kind == GuardKind::Read));
+        }
+        for label in self.reentrant {
+            match transitions.get_mut(&label) {
+                Some(transition) => transition.allow_reentry = true,
+                None => return Err(BuildError::UnknownTransition { transition: label }),
+            }
+        }
+
+        let forced = SemanticsConfig::preset(&self.model_type).place_capacity;
+        let (mut places, mut initial, mut capacity) = (Vec::new(), Vector::new(), Vec::new());
+        for (label, tokens, cap) in self.places {
+            let cap = forced.unwrap_or(cap);
+            places.push(label);
+            initial.push(cap.limit().map_or(tokens, |limit| tokens.min(limit)));
+            capacity.push(cap);
+        }
+        Ok(StateMachine {
+            model_type: self.model_type,
+            initial,
+            capacity,
+            places,
+            transitions,
+            roles,
+            registry: RoleRegistry::default(),
+            units: vec![None; size],
+            reentry: self.reentry,
+            attributes: HashMap::new(),
+            place_attributes: vec![HashMap::new(); size],
+            semantics: None,
+            firing: None,
+        })
+    }
+}
+
+fn check_size(transition: &str, vector: &Vector, expected: usize) -> Result<(), BuildError> {
+    if vector.len() != expected {
+        return Err(BuildError::DimensionMismatch {
+            transition: transition.to_string(),
+            expected,
+            actual: vector.len(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vasm::Vasm;
+
+    use super::*;
+
+    fn counter() -> StateMachineBuilder {
+        StateMachineBuilder::new(ModelType::PetriNet)
+            .place("count", 1, Capacity::Bounded(3))
+            .place("paused", 0, Capacity::Unbounded)
+            .transition("inc", "default", vec![1, 0])
+            .transition("dec", "default", vec![-1, 0])
+            .guard("inc", vec![0, -1], GuardKind::Inhibit)
+    }
+
+    #[test]
+    fn test_build_matches_declared_net() {
+        let built = counter().build().unwrap();
+        let declared = StateMachine::new(|p| {
+            p.cell("count", Option::from(1), Option::from(3), 0, 0);
+            p.cell("paused", None, None, 0, 0);
+            p.func("inc", "default", 0, 0);
+            p.func("dec", "default", 0, 0);
+            p.arrow("inc", "count", 1);
+            p.arrow("count", "dec", 1);
+            p.guard("paused", "inc", 1);
+        });
+        for state in [vec![0, 0], vec![1, 0], vec![3, 0], vec![1, 1]] {
+            for action in ["inc", "dec"] {
+                let (b, d) = (
+                    built.transform(&state, action, 1),
+                    declared.transform(&state, action, 1),
+                );
+                assert_eq!((b.ok, b.inhibited, b.output), (d.ok, d.inhibited, d.output));
+            }
+        }
+        assert_eq!(built.places, declared.places);
+        assert_eq!(built.roles, declared.roles);
+    }
+
+    #[test]
+    fn test_build_validates_vectors() {
+        let short = counter().transition("reset", "default", vec![-1]).build();
+        assert!(matches!(
+            short,
+            Err(BuildError::DimensionMismatch {
+                expected: 2,
+                actual: 1,
+                ..
+            })
+        ));
+        let wide = counter().guard("dec", vec![-1, -1], GuardKind::Read).build();
+        assert_eq!(
+            wide.unwrap_err(),
+            BuildError::MalformedGuard {
+                transition: "dec".to_string()
+            }
+        );
+        let twice = counter().guard("inc", vec![0, -2], GuardKind::Read).build();
+        assert!(matches!(twice, Err(BuildError::DuplicateGuard { .. })));
+        let unknown = counter().allow_reentry("undo").build();
+        assert_eq!(unknown.unwrap_err().to_string(), "transition undo is not declared");
+        let dup = counter().place("count", 0, Capacity::Unbounded).build();
+        assert!(matches!(dup, Err(BuildError::DuplicatePlace { .. })));
+    }
+
+    #[test]
+    fn test_workflow_places_hold_one_token() {
+        let sm = StateMachineBuilder::new(ModelType::Workflow)
+            .place("a", 2, Capacity::Unbounded)
+            .place("b", 0, Capacity::Unbounded)
+            .transition("ab", "default", vec![-1, 1])
+            .transition("restart", "default", vec![1, 0])
+            .allow_reentry("restart")
+            .build()
+            .unwrap();
+        assert_eq!(sm.initial_vector(), vec![1, 0]);
+        assert_eq!(sm.capacity, vec![Capacity::Bounded(1); 2]);
+        assert!(sm.transform(&sm.initial_vector(), "restart", 1).is_ok());
+        assert!(sm.transform(&sm.initial_vector(), "ab", 1).is_ok());
+    }
+}
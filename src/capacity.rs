@@ -0,0 +1,140 @@
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// `Capacity` is the largest number of tokens a place may hold.
+///
+/// Models used to store capacities as integers where zero meant unbounded, which made it impossible
+/// to forbid tokens in a place. Integers are still accepted and produced wherever they are unambiguous:
+/// `Unbounded` serializes as `0` and `Bounded(n)` as `n`, except `Bounded(0)`, which serializes as
+/// `{"bounded": 0}`. The string `"unbounded"` is accepted as well.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Capacity {
+    /// The place may hold any number of tokens.
+    #[default]
+    Unbounded,
+    /// The place may hold at most this many tokens, zero forbids tokens altogether.
+    ///
+    /// Token counts are `i32`, so bounds above `i32::MAX` are rejected when deserializing and act as
+    /// `i32::MAX` otherwise.
+    Bounded(u32),
+}
+
+impl Capacity {
+    /// Converts an integer capacity with the legacy semantics, where zero or less means unbounded.
+    pub fn from_legacy(capacity: i32) -> Self {
+        if capacity > 0 {
+            Capacity::Bounded(capacity as u32)
+        } else {
+            Capacity::Unbounded
+        }
+    }
+
+    /// Returns the largest number of tokens the place may hold, or None if it is unbounded.
+    pub fn limit(&self) -> Option<i32> {
+        match self {
+            Capacity::Unbounded => None,
+            Capacity::Bounded(n) => Some(i32::try_from(*n).unwrap_or(i32::MAX)),
+        }
+    }
+
+    /// Checks if the place has a bound.
+    pub fn is_bounded(&self) -> bool {
+        matches!(self, Capacity::Bounded(_))
+    }
+
+    /// Checks if the place may hold the given number of tokens.
+    pub fn allows(&self, tokens: i32) -> bool {
+        self.limit().map_or(true, |limit| tokens <= limit)
+    }
+}
+
+impl From<i32> for Capacity {
+    /// Converts with the legacy semantics, see `Capacity::from_legacy`.
+    fn from(capacity: i32) -> Self {
+        Capacity::from_legacy(capacity)
+    }
+}
+
+impl fmt::Display for Capacity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Capacity::Unbounded => write!(f, "unbounded"),
+            Capacity::Bounded(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Repr {
+    Legacy(i64),
+    Keyword(String),
+    Explicit { bounded: u32 },
+}
+
+impl Serialize for Capacity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Capacity::Unbounded => Repr::Legacy(0),
+            Capacity::Bounded(0) => Repr::Explicit { bounded: 0 },
+            Capacity::Bounded(n) => Repr::Legacy(*n as i64),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Capacity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Repr::deserialize(deserializer)? {
+            Repr::Legacy(n) if n <= 0 => Ok(Capacity::Unbounded),
+            Repr::Legacy(n) => bounded(n),
+            Repr::Keyword(k) if k == "unbounded" => Ok(Capacity::Unbounded),
+            Repr::Keyword(k) => Err(serde::de::Error::custom(format!("unknown capacity {:?}", k))),
+            Repr::Explicit { bounded: n } => bounded(n.into()),
+        }
+    }
+}
+
+fn bounded<E: serde::de::Error>(n: i64) -> Result<Capacity, E> {
+    match i32::try_from(n) {
+        Ok(n) => Ok(Capacity::Bounded(n as u32)),
+        Err(_) => Err(E::custom(format!("capacity {} is too large", n))),
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serde_back_compat() {
+        let parse = |json: &str| serde_json::from_str::<Capacity>(json).unwrap();
+        assert_eq!(parse("0"), Capacity::Unbounded);
+        assert_eq!(parse("3"), Capacity::Bounded(3));
+        assert_eq!(parse("\"unbounded\""), Capacity::Unbounded);
+        assert_eq!(parse("{\"bounded\": 0}"), Capacity::Bounded(0));
+        assert!(serde_json::from_str::<Capacity>("\"full\"").is_err());
+        assert!(serde_json::from_str::<Capacity>("2147483648").is_err());
+        assert!(serde_json::from_str::<Capacity>("{\"bounded\": 4294967295}").is_err());
+        assert_eq!(parse("2147483647"), Capacity::Bounded(i32::MAX as u32));
+
+        for capacity in [Capacity::Unbounded, Capacity::Bounded(0), Capacity::Bounded(5)] {
+            assert_eq!(parse(&serde_json::to_string(&capacity).unwrap()), capacity);
+        }
+        assert_eq!(serde_json::to_string(&Capacity::Unbounded).unwrap(), "0");
+        assert_eq!(serde_json::to_string(&Capacity::Bounded(0)).unwrap(), "{\"bounded\":0}");
+    }
+
+    #[test]
+    fn test_allows() {
+        assert!(Capacity::Unbounded.allows(i32::MAX));
+        assert!(Capacity::Bounded(2).allows(2));
+        assert!(!Capacity::Bounded(2).allows(3));
+        assert!(!Capacity::Bounded(0).allows(1));
+        assert_eq!(Capacity::Bounded(u32::MAX).limit(), Some(i32::MAX));
+        assert!(Capacity::Bounded(u32::MAX).allows(i32::MAX));
+    }
+}
@@ -0,0 +1,413 @@
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use serde_json::Value;
+
+use crate::expr::Variables;
+use crate::quota::QuotaPolicy;
+use crate::simulation::timers::{TimeoutEvent, Timers};
+use crate::vasm::{Marking, StateMachine, Transaction, Vector};
+
+/// CaseId is a type alias for the identifier of a case.
+pub type CaseId = String;
+
+/// `CaseStore` persists the markings of the cases of a `CaseManager`.
+pub trait CaseStore {
+    /// Stores the marking of the case, replacing the previous one.
+    fn save(&mut self, id: &str, marking: &Vector) -> Result<(), String>;
+    /// Removes the case, removing an unknown case is not an error.
+    fn delete(&mut self, id: &str) -> Result<(), String>;
+    /// Returns the marking of every stored case.
+    fn load(&self) -> Result<Vec<(CaseId, Vector)>, String>;
+}
+
+/// `MemoryCaseStore` keeps the markings in memory, for tests and single-process services.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryCaseStore {
+    cases: BTreeMap<CaseId, Vector>,
+}
+
+impl CaseStore for MemoryCaseStore {
+    fn save(&mut self, id: &str, marking: &Vector) -> Result<(), String> {
+        self.cases.insert(id.to_string(), marking.clone());
+        Ok(())
+    }
+
+    fn delete(&mut self, id: &str) -> Result<(), String> {
+        self.cases.remove(id);
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Vec<(CaseId, Vector)>, String> {
+        Ok(self.cases.iter().map(|(id, marking)| (id.clone(), marking.clone())).collect())
+    }
+}
+
+static EMPTY: Variables = Variables::new();
+
+/// `CaseManager` keeps the current marking of many running instances (cases) of a single `StateMachine`.
+#[derive(Debug, Clone)]
+pub struct CaseManager {
+    pub sm: StateMachine,
+    cases: BTreeMap<CaseId, Marking>,
+    variables: BTreeMap<CaseId, Variables>,
+    quotas: QuotaPolicy,
+    timers: Timers,
+}
+
+impl CaseManager {
+    /// Creates a new `CaseManager` without any cases.
+    pub fn new(sm: StateMachine) -> Self {
+        Self {
+            sm,
+            cases: BTreeMap::new(),
+            variables: BTreeMap::new(),
+            quotas: QuotaPolicy::new(),
+            timers: Timers::new(),
+        }
+    }
+
+    /// Replaces the quotas limiting how many times a role may fire a transition, none by default.
+    pub fn set_quotas(&mut self, quotas: QuotaPolicy) {
+        self.quotas = quotas;
+    }
+
+    /// Creates a case in the initial state, an existing case with the same id is reset along with its per-case quotas.
+    pub fn create(&mut self, id: &str) -> &Marking {
+        self.cases.insert(id.to_string(), Marking::for_machine(&self.sm));
+        self.variables.remove(id);
+        self.quotas.forget(id);
+        self.timers.forget(id);
+        self.timers.update(&self.sm, id, self.cases[id].as_vector(), None);
+        &self.cases[id]
+    }
+
+    /// Loads the cases of the store, failing if a stored marking does not fit the state machine.
+    pub fn load<S: CaseStore>(sm: StateMachine, store: &S) -> Result<Self, String> {
+        let mut manager = Self::new(sm);
+        for (id, marking) in store.load()? {
+            let marking = Marking::from_vector(&manager.sm, marking).map_err(|err| format!("case {}: {}", id, err))?;
+            manager.timers.update(&manager.sm, &id, marking.as_vector(), None);
+            manager.cases.insert(id, marking);
+        }
+        Ok(manager)
+    }
+
+    /// Saves the marking of every case to the store.
+    pub fn save<S: CaseStore>(&self, store: &mut S) -> Result<(), String> {
+        self.cases.iter().try_for_each(|(id, marking)| store.save(id, marking.as_vector()))
+    }
+
+    /// Returns the current marking of a case.
+    pub fn state(&self, id: &str) -> Option<&Marking> {
+        self.cases.get(id)
+    }
+
+    /// Returns the variables of a case, the data its transition conditions are evaluated over.
+    pub fn variables(&self, id: &str) -> Option<&Variables> {
+        self.cases.get(id).map(|_| self.variables.get(id).unwrap_or(&EMPTY))
+    }
+
+    /// Sets a variable of a case, returns false if there is no such case.
+    pub fn set_variable(&mut self, id: &str, name: &str, value: Value) -> bool {
+        if !self.cases.contains_key(id) {
+            return false;
+        }
+        self.variables.entry(id.to_string()).or_default().insert(name.to_string(), value);
+        true
+    }
+
+    /// Removes a case, returns its last marking or None if there was no such case.
+    pub fn remove(&mut self, id: &str) -> Option<Marking> {
+        self.variables.remove(id);
+        self.quotas.forget(id);
+        self.timers.forget(id);
+        self.cases.remove(id)
+    }
+
+    /// Lists the ids of the cases whose state matches the filter in id order.
+    pub fn find<F>(&self, filter: F) -> Vec<&CaseId>
+    where
+        F: Fn(&CaseId, &Vector) -> bool,
+    {
+        self.cases.iter().filter(|(id, marking)| filter(id, marking.as_vector())).map(|(id, _)| id).collect()
+    }
+
+    /// Fires the action on a single case, which moves to its new state only if the transformation succeeds.
+    ///
+    /// The action is fired on behalf of the role of its transition, so its quotas apply, see `fire_as`.
+    ///
+    /// # Returns
+    ///
+    /// * The `Transaction`, or None if there is no such case.
+    ///
+    pub fn fire(&mut self, id: &str, action: &str, multiple: i32) -> Option<Transaction> {
+        let role = self.sm.transitions.get(action).map(|t| t.role.clone()).unwrap_or_default();
+        self.fire_as(id, action, multiple, &role)
+    }
+
+    /// Fires the action on a single case on behalf of the role with the variables of the case like
+    /// `StateMachine::transform_with`, rejecting it with `TransformError::QuotaExceeded` if the role used up
+    /// one of its quotas for the transition.
+    ///
+    /// # Returns
+    ///
+    /// * The `Transaction`, or None if there is no such case.
+    ///
+    pub fn fire_as(&mut self, id: &str, action: &str, multiple: i32, role: &str) -> Option<Transaction> {
+        let marking = self.cases.get_mut(id)?;
+        let now = Instant::now();
+        if let Err(error) = self.quotas.check(id, role, action, now) {
+            let state = marking.as_vector();
+            return Some(Transaction::rejected(state, role, error).with_action(state, action, multiple));
+        }
+        let variables = self.variables.get(id).unwrap_or(&EMPTY);
+        let res = marking.apply_with(&self.sm, action, multiple, role, variables);
+        if res.is_ok() {
+            self.quotas.record(id, role, action, now);
+            self.timers.update(&self.sm, id, &res.output, Some(action));
+        }
+        Some(res)
+    }
+
+    /// Returns the number of cases.
+    pub fn len(&self) -> usize {
+        self.cases.len()
+    }
+
+    /// Checks if there are no cases.
+    pub fn is_empty(&self) -> bool {
+        self.cases.is_empty()
+    }
+
+    /// Fires the action with a multiple of one on every case whose state matches the filter.
+    ///
+    /// Cases are fired in parallel, each case moves to its new state only if its own transformation succeeds.
+    /// With quotas the cases are fired one after the other in id order instead, so each firing counts against them.
+    ///
+    /// # Returns
+    ///
+    /// * The `Transaction` of every matching case by case id.
+    ///
+    pub fn fire_all<F>(&mut self, filter: F, action: &str) -> BTreeMap<CaseId, Transaction>
+    where
+        F: Fn(&CaseId, &Vector) -> bool + Sync,
+    {
+        if !self.quotas.is_empty() {
+            let ids: Vec<CaseId> = self.find(|id, state| filter(id, state)).into_iter().cloned().collect();
+            return ids
+                .into_iter()
+                .filter_map(|id| self.fire(&id, action, 1).map(|tx| (id, tx)))
+                .collect();
+        }
+        let (sm, variables) = (&self.sm, &self.variables);
+        let role = sm.transitions.get(action).map(|t| t.role.clone()).unwrap_or_default();
+        #[cfg(feature = "parallel")]
+        let cases = self.cases.par_iter_mut();
+        #[cfg(not(feature = "parallel"))]
+        let cases = self.cases.iter_mut();
+        let fired: BTreeMap<CaseId, Transaction> = cases
+            .filter(|(id, marking)| filter(id, marking.as_vector()))
+            .map(|(id, marking)| {
+                let variables = variables.get(id).unwrap_or(&EMPTY);
+                (id.clone(), marking.apply_with(sm, action, 1, &role, variables))
+            })
+            .collect();
+        for (id, tx) in fired.iter().filter(|(_, tx)| tx.is_ok()) {
+            self.timers.update(&self.sm, id, &tx.output, Some(action));
+        }
+        fired
+    }
+
+    /// Returns the current tick of the clock driving the timeouts of the transitions, see `advance`.
+    pub fn now(&self) -> u64 {
+        self.timers.now()
+    }
+
+    /// Advances the clock by the given number of ticks, one per time unit of the model.
+    ///
+    /// A transition enabled in a case for longer than its `Timeout` times out once, firing its escalation
+    /// transition on that case if it declares one, see `simulation::timers::Timers`.
+    pub fn advance(&mut self, ticks: u64) -> Vec<TimeoutEvent> {
+        let mut events = Vec::new();
+        for _ in 0..ticks {
+            for (case, action) in self.timers.tick() {
+                let escalate = self.sm.transitions[&action].timeout().and_then(|t| t.escalate.clone());
+                let transaction = escalate.as_deref().and_then(|e| self.fire(&case, e, 1));
+                events.push(TimeoutEvent {
+                    case,
+                    action,
+                    at: self.timers.now(),
+                    escalate,
+                    transaction,
+                });
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::dsl::FlowDsl;
+    use crate::quota::QuotaWindow;
+    use crate::vasm::TransformError;
+
+    use super::*;
+
+    fn order(p: &mut dyn FlowDsl) {
+        p.model_type("workflow");
+        p.cell("open", Option::from(1), None, 0, 0);
+        p.cell("stuck", None, None, 0, 0);
+        p.cell("cancelled", None, None, 0, 0);
+        p.func("hang", "default", 0, 0);
+        p.func("cancel", "admin", 0, 0);
+        p.arrow("open", "hang", 1);
+        p.arrow("hang", "stuck", 1);
+        p.arrow("stuck", "cancel", 1);
+        p.arrow("cancel", "cancelled", 1);
+    }
+
+    #[test]
+    fn test_fire_all_matching_cases() {
+        let mut cases = CaseManager::new(StateMachine::new(order));
+        for i in 0..10 {
+            cases.create(&format!("order-{}", i));
+        }
+        let hung = cases.fire_all(|id, _| id.ends_with('3') || id.ends_with('7'), "hang");
+        assert_eq!(hung.len(), 2);
+        assert!(hung.values().all(|t| t.is_ok()));
+
+        let stuck = cases.sm.places.iter().position(|p| p == "stuck").unwrap();
+        let cancelled = cases.fire_all(|_, state| state[stuck] > 0, "cancel");
+        assert_eq!(cancelled.keys().collect::<Vec<_>>(), vec!["order-3", "order-7"]);
+        assert_eq!(cases.state("order-3").unwrap().as_vector(), &vec![0, 0, 1]);
+        assert_eq!(cases.state("order-4").unwrap().as_vector(), &vec![1, 0, 0]);
+    }
+
+    #[test]
+    fn test_fire_find_and_persist() {
+        let mut cases = CaseManager::new(StateMachine::new(order));
+        cases.create("a");
+        cases.create("b");
+        assert!(cases.fire("a", "hang", 1).unwrap().is_ok());
+        assert!(cases.fire("b", "cancel", 1).unwrap().is_err());
+        assert!(cases.fire("c", "hang", 1).is_none());
+        assert_eq!(cases.find(|_, state| state[0] > 0), vec!["b"]);
+
+        let mut store = MemoryCaseStore::default();
+        cases.save(&mut store).unwrap();
+        assert_eq!(cases.remove("b").unwrap().as_vector(), &vec![1, 0, 0]);
+        store.delete("b").unwrap();
+        let loaded = CaseManager::load(StateMachine::new(order), &store).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded.state("a").unwrap().as_vector(), &vec![0, 1, 0]);
+
+        store.save("broken", &vec![1]).unwrap();
+        let err = CaseManager::load(StateMachine::new(order), &store).unwrap_err();
+        assert!(err.starts_with("case broken:"), "{}", err);
+    }
+
+    #[test]
+    fn test_quotas_per_case_and_window() {
+        let mut cases = CaseManager::new(StateMachine::new(order));
+        cases.set_quotas(
+            QuotaPolicy::new()
+                .with_quota("admin", "cancel", 1, QuotaWindow::PerCase)
+                .with_quota("default", "hang", 2, QuotaWindow::Every(Duration::from_secs(3600))),
+        );
+        for id in ["a", "b", "c"] {
+            cases.create(id);
+        }
+        let hung = cases.fire_all(|_, _| true, "hang");
+        assert!(hung["a"].is_ok() && hung["b"].is_ok());
+        assert!(matches!(hung["c"].error, Some(TransformError::QuotaExceeded { limit: 2, .. })));
+        assert_eq!(cases.state("c").unwrap().as_vector(), &vec![1, 0, 0]);
+
+        assert!(cases.fire_as("a", "cancel", 1, "admin").unwrap().is_ok());
+        assert!(cases.fire_as("b", "cancel", 1, "admin").unwrap().is_ok());
+        let tx = cases.fire_as("a", "cancel", 1, "admin").unwrap();
+        assert!(matches!(tx.error, Some(TransformError::QuotaExceeded { .. })));
+        assert_eq!(cases.state("a").unwrap().as_vector(), &vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn test_recreated_case_resets_quotas() {
+        let mut cases = CaseManager::new(StateMachine::new(order));
+        cases.set_quotas(QuotaPolicy::new().with_quota("default", "hang", 1, QuotaWindow::PerCase));
+        cases.create("a");
+        assert!(cases.fire("a", "hang", 1).unwrap().is_ok());
+        cases.create("a");
+        assert!(cases.fire("a", "hang", 1).unwrap().is_ok());
+        cases.create("a");
+        cases.create("b");
+        let hung = cases.fire_all(|_, _| true, "hang");
+        assert!(hung.values().all(|tx| tx.is_ok()));
+        assert!(matches!(
+            cases.fire("a", "hang", 1).unwrap().error,
+            Some(TransformError::QuotaExceeded { limit: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_timeouts_escalate_each_case() {
+        let mut cases = CaseManager::new(StateMachine::new(|p| {
+            order(p);
+            p.timeout("cancel", 24, None);
+            p.timeout("hang", 48, Some("hang"));
+        }));
+        cases.create("a");
+        cases.create("b");
+        cases.advance(12);
+        assert!(cases.fire("a", "hang", 1).unwrap().is_ok());
+        assert_eq!(cases.timers.deadline("a", "cancel"), Some(36));
+
+        let events = cases.advance(36);
+        let timeouts: Vec<(&str, &str, u64)> =
+            events.iter().map(|e| (e.case.as_str(), e.action.as_str(), e.at)).collect();
+        assert_eq!(timeouts, vec![("a", "cancel", 36), ("b", "hang", 48)]);
+        assert!(events[1].transaction.as_ref().unwrap().is_ok());
+        assert_eq!(cases.state("b").unwrap().as_vector(), &vec![0, 1, 0]);
+        assert_eq!(cases.timers.deadline("b", "cancel"), Some(72));
+
+        cases.remove("b");
+        assert!(cases.advance(100).is_empty());
+    }
+
+    #[test]
+    fn test_conditions_over_case_variables() {
+        let mut cases = CaseManager::new(StateMachine::new(|p| {
+            order(p);
+            p.condition("hang", "amount <= 1000");
+        }));
+        cases.create("small");
+        cases.create("large");
+        assert!(cases.set_variable("small", "amount", serde_json::json!(200)));
+        assert!(cases.set_variable("large", "amount", serde_json::json!(5000)));
+        assert!(!cases.set_variable("missing", "amount", serde_json::json!(1)));
+        assert_eq!(cases.variables("large").unwrap()["amount"], 5000);
+
+        let hung = cases.fire_all(|_, _| true, "hang");
+        assert!(hung["small"].is_ok());
+        assert!(hung["large"].inhibited);
+
+        cases.set_variable("large", "amount", serde_json::json!(900));
+        assert!(cases.fire("large", "hang", 1).unwrap().is_ok());
+        cases.create("large");
+        assert!(cases.variables("large").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_failed_fire_keeps_state() {
+        let mut cases = CaseManager::new(StateMachine::new(order));
+        cases.create("a");
+        let res = cases.fire_all(|_, _| true, "cancel");
+        assert!(res["a"].is_err());
+        assert_eq!(cases.state("a").unwrap().as_vector(), &vec![1, 0, 0]);
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use serde_json::Value;
+
+use crate::capacity::Capacity;
+use crate::petri_net::PetriNet;
+
+fn option(value: Option<i32>) -> String {
+    match value {
+        Some(v) => format!("Option::from({})", v),
+        None => "None".to_string(),
+    }
+}
+
+fn options(
+    src: &mut String,
+    label: &str,
+    display: (&Option<String>, &HashMap<String, String>),
+    description: &Option<String>,
+    attributes: &HashMap<String, Value>,
+) {
+    if let Some(display) = display.0 {
+        writeln!(src, "    p.label({:?}, {:?});", label, display).unwrap();
+    }
+    let mut locales: Vec<&String> = display.1.keys().collect();
+    locales.sort();
+    for locale in locales {
+        writeln!(src, "    p.localize({:?}, {:?}, {:?});", label, locale, display.1[locale]).unwrap();
+    }
+    if let Some(description) = description {
+        writeln!(src, "    p.describe({:?}, {:?});", label, description).unwrap();
+    }
+    let mut keys: Vec<&String> = attributes.keys().collect();
+    keys.sort();
+    for key in keys {
+        let json = attributes[key].to_string();
+        writeln!(src, "    p.attribute({:?}, {:?}, serde_json::from_str({:?}).unwrap());", label, key, json).unwrap();
+    }
+}
+
+/// Generates the Rust source of a `FlowDsl` declaration reproducing the net.
+///
+/// Places are declared in offset order and transitions in label order so the output is stable.
+pub fn to_dsl_source(net: &PetriNet) -> String {
+    let mut src = String::new();
+    let forbidden = |capacity: &Option<Capacity>| *capacity == Some(Capacity::Bounded(0));
+    if net.places.values().any(|p| forbidden(&p.capacity)) {
+        src.push_str("use pflow_metamodel::capacity::Capacity;\n");
+    }
+    src.push_str("use pflow_metamodel::dsl::FlowDsl;\n\n");
+    src.push_str("pub fn model(p: &mut dyn FlowDsl) {\n");
+    writeln!(src, "    p.model_type({:?});", net.model_type).unwrap();
+    let mut roles: Vec<_> = net.roles.iter().collect();
+    roles.sort_by_key(|(name, _)| *name);
+    for (name, role) in roles {
+        writeln!(
+            src,
+            "    p.role({:?}, {}, &{:?});",
+            name,
+            role.description.as_ref().map_or("None".to_string(), |d| format!("Some({:?})", d)),
+            role.implies
+        )
+        .unwrap();
+    }
+    if let Some(role) = &net.default_role {
+        writeln!(src, "    p.default_role({:?});", role).unwrap();
+    }
+
+    let mut places: Vec<_> = net.places.iter().collect();
+    places.sort_by_key(|(label, place)| (place.offset, *label));
+    if !places.is_empty() {
+        src.push('\n');
+    }
+    for (label, place) in &places {
+        writeln!(
+            src,
+            "    p.cell({:?}, {}, {}, {}, {});",
+            label,
+            option(place.initial),
+            option(place.capacity.map(|c| c.limit().unwrap_or(0)).filter(|_| !forbidden(&place.capacity))),
+            place.x,
+            place.y
+        )
+        .unwrap();
+    }
+    for (label, place) in &places {
+        if forbidden(&place.capacity) {
+            writeln!(src, "    p.capacity({:?}, Capacity::Bounded(0));", label).unwrap();
+        }
+        if let Some(unit) = &place.unit {
+            writeln!(src, "    p.unit({:?}, {:?});", label, unit).unwrap();
+        }
+        options(&mut src, label, (&place.label, &place.labels), &place.description, &place.attributes);
+    }
+
+    let mut transitions: Vec<_> = net.transitions.iter().collect();
+    transitions.sort_by_key(|(label, _)| *label);
+    if !transitions.is_empty() {
+        src.push('\n');
+    }
+    for (label, transition) in &transitions {
+        let role = transition.role.as_deref().unwrap_or(net.default_role());
+        writeln!(src, "    p.func({:?}, {:?}, {}, {});", label, role, transition.x, transition.y).unwrap();
+    }
+    for (label, transition) in &transitions {
+        if transition.allow_reentry {
+            writeln!(src, "    p.allow_reentry({:?});", label).unwrap();
+        }
+        if let Some(priority) = transition.priority {
+            writeln!(src, "    p.priority({:?}, {});", label, priority).unwrap();
+        }
+        if let Some(rate) = transition.rate {
+            writeln!(src, "    p.rate({:?}, {});", label, rate).unwrap();
+        }
+        if let Some(delay) = transition.delay {
+            writeln!(src, "    p.delay({:?}, {});", label, delay).unwrap();
+        }
+        if let Some(timeout) = &transition.timeout {
+            let escalate = timeout.escalate.as_deref();
+            writeln!(src, "    p.timeout({:?}, {}, {:?});", label, timeout.after, escalate).unwrap();
+        }
+        if let Some(condition) = &transition.condition {
+            writeln!(src, "    p.condition({:?}, {:?});", label, condition).unwrap();
+        }
+        options(
+            &mut src,
+            label,
+            (&transition.label, &transition.labels),
+            &transition.description,
+            &transition.attributes,
+        );
+    }
+
+    if !net.arcs.is_empty() {
+        src.push('\n');
+    }
+    for arc in &net.arcs {
+        if arc.reset.unwrap_or(false) {
+            writeln!(src, "    p.reset({:?}, {:?});", arc.source, arc.target).unwrap();
+            continue;
+        }
+        if let Some(weight) = &arc.marking_weight {
+            writeln!(src, "    p.marking_arrow({:?}, {:?}, {:?});", arc.source, arc.target, weight).unwrap();
+            continue;
+        }
+        if let Some(to) = &arc.transfer {
+            let weight = arc.weight.unwrap_or(1);
+            writeln!(src, "    p.transfer({:?}, {:?}, {:?}, {});", arc.source, arc.target, to, weight).unwrap();
+            continue;
+        }
+        let method = if arc.inhibit.unwrap_or(false) { "guard" } else { "arrow" };
+        writeln!(
+            src,
+            "    p.{}({:?}, {:?}, {});",
+            method,
+            arc.source,
+            arc.target,
+            arc.weight.unwrap_or(1)
+        )
+        .unwrap();
+    }
+    src.push_str("}\n");
+    src
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    #[test]
+    fn test_generated_source() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("b", None, Option::from(3), 10, 20);
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.unit("a", "items");
+            p.func("t", "user", 5, 5);
+            p.priority("t", 1);
+            p.localize("t", "fr", "prendre");
+            p.describe("t", "take");
+            p.attribute("t", "sla", serde_json::json!({"hours": 4}));
+            p.arrow("a", "t", 2);
+            p.arrow("t", "b", 1);
+            p.guard("b", "t", 3);
+        });
+        assert_eq!(
+            to_dsl_source(&net),
+            r#"use pflow_metamodel::dsl::FlowDsl;
+
+pub fn model(p: &mut dyn FlowDsl) {
+    p.model_type("petriNet");
+
+    p.cell("b", None, Option::from(3), 10, 20);
+    p.cell("a", Option::from(1), None, 0, 0);
+    p.unit("a", "items");
+
+    p.func("t", "user", 5, 5);
+    p.priority("t", 1);
+    p.localize("t", "fr", "prendre");
+    p.describe("t", "take");
+    p.attribute("t", "sla", serde_json::from_str("{\"hours\":4}").unwrap());
+
+    p.arrow("a", "t", 2);
+    p.arrow("t", "b", 1);
+    p.guard("b", "t", 3);
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_zero_capacity_source() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", None, Option::from(2), 0, 0);
+            p.cell("b", None, None, 0, 0);
+        });
+        net.set_capacity("b", Capacity::Bounded(0));
+        let src = to_dsl_source(&net);
+        assert!(src.starts_with("use pflow_metamodel::capacity::Capacity;\n"));
+        assert!(src.contains("    p.cell(\"a\", None, Option::from(2), 0, 0);\n"));
+        assert!(src.contains("    p.cell(\"b\", None, None, 0, 0);\n    p.capacity(\"b\", Capacity::Bounded(0));\n"));
+    }
+
+    #[test]
+    fn test_timeout_source() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.func("review", "reviewer", 0, 0);
+            p.func("escalate", "system", 0, 0);
+            p.timeout("review", 48, Some("escalate"));
+            p.timeout("escalate", 24, None);
+            p.condition("review", "amount > 1000");
+        });
+        net.declare(|p| {
+            p.cell("inbox", None, None, 0, 0);
+            p.cell("archive", None, None, 0, 0);
+            p.reset("inbox", "escalate");
+            p.transfer("inbox", "review", "archive", 2);
+            p.marking_arrow("inbox", "escalate", "half max 10");
+        });
+        let src = to_dsl_source(&net);
+        assert!(src.contains("    p.timeout(\"review\", 48, Some(\"escalate\"));\n"));
+        assert!(src.contains("    p.timeout(\"escalate\", 24, None);\n"));
+        assert!(src.contains("    p.condition(\"review\", \"amount > 1000\");\n"));
+        assert!(src.contains("    p.reset(\"inbox\", \"escalate\");\n"));
+        assert!(src.contains("    p.transfer(\"inbox\", \"review\", \"archive\", 2);\n"));
+        assert!(src.contains("    p.marking_arrow(\"inbox\", \"escalate\", \"half max 10\");\n"));
+    }
+
+    #[test]
+    fn test_role_source() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.role("manager", Some("approves refunds"), &["clerk"]);
+            p.role("clerk", None, &[]);
+            p.default_role("clerk");
+        });
+        net.add_transition("refund", "clerk", 0, 0);
+        net.transitions.get_mut("refund").unwrap().role = None;
+        let src = to_dsl_source(&net);
+        assert!(src.contains(
+            "    p.role(\"clerk\", None, &[]);\n    p.role(\"manager\", Some(\"approves refunds\"), &[\"clerk\"]);\n"
+        ));
+        assert!(src.contains("    p.default_role(\"clerk\");\n"));
+        assert!(src.contains("    p.func(\"refund\", \"clerk\", 0, 0);\n"));
+    }
+
+    #[test]
+    fn test_generated_source_is_stable() {
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let src = net.to_dsl_source();
+        assert_eq!(src, net.clone().to_dsl_source());
+        assert_eq!(src.matches("p.cell(").count(), 15);
+        assert_eq!(src.matches("p.func(").count(), 10);
+        assert_eq!(src.matches("p.arrow(").count() + src.matches("p.guard(").count(), 40);
+    }
+}
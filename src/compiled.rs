@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::vasm::{StateMachine, Transaction, TransformError, Transition, Vector};
+
+/// `TransitionId` is the dense index of a transition in a `CompiledVasm`, transitions are numbered in label order.
+pub type TransitionId = usize;
+
+/// `Firing` is the result of firing a transition of a `CompiledVasm`.
+///
+/// It carries the same information as a `Transaction`, but the role is shared with the compiled
+/// state machine instead of being cloned on every firing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Firing {
+    pub ok: bool,
+    pub output: Vector,
+    pub role: Arc<str>,
+    pub inhibited: bool,
+    pub overflow: bool,
+    pub underflow: bool,
+    pub error: Option<TransformError>,
+}
+
+impl Firing {
+    fn rejected(state: &Vector, role: Arc<str>, error: TransformError) -> Self {
+        Self {
+            ok: false,
+            output: state.clone(),
+            role,
+            inhibited: false,
+            overflow: false,
+            underflow: false,
+            error: Some(error),
+        }
+    }
+
+    /// Checks if the firing was successful.
+    pub fn is_ok(&self) -> bool {
+        self.ok
+    }
+
+    pub fn is_err(&self) -> bool {
+        !self.ok
+    }
+
+    /// Converts the firing into the `Transaction` that `Vasm::transform` would have returned, without the action,
+    /// which `Transaction::with_action` records.
+    pub fn into_transaction(self) -> Transaction {
+        Transaction {
+            ok: self.ok,
+            output: self.output,
+            role: self.role.to_string(),
+            inhibited: self.inhibited,
+            overflow: self.overflow,
+            underflow: self.underflow,
+            error: self.error,
+            action: String::new(),
+            multiple: 0,
+            net_consumed: Vector::new(),
+            net_produced: Vector::new(),
+        }
+    }
+}
+
+/// `CompiledVasm` addresses the transitions of a `StateMachine` by dense indices for hot loops.
+///
+/// Labels are resolved once with `id`, after which firing does no string lookups and no allocation
+/// besides the output state. The results are the same as those of `Vasm::transform`.
+#[derive(Debug, Clone)]
+pub struct CompiledVasm<'a> {
+    sm: &'a StateMachine,
+    labels: Vec<&'a str>,
+    transitions: Vec<&'a Transition>,
+    roles: Vec<Arc<str>>,
+    index: HashMap<&'a str, TransitionId>,
+}
+
+impl<'a> CompiledVasm<'a> {
+    /// Compiles the state machine, numbering its transitions in label order.
+    pub fn new(sm: &'a StateMachine) -> Self {
+        let mut labels: Vec<&'a str> = sm.transitions.keys().map(|l| l.as_str()).collect();
+        labels.sort();
+        let transitions: Vec<&'a Transition> = labels.iter().map(|l| &sm.transitions[*l]).collect();
+        Self {
+            sm,
+            roles: transitions.iter().map(|t| Arc::from(t.role())).collect(),
+            index: labels.iter().enumerate().map(|(i, l)| (*l, i)).collect(),
+            labels,
+            transitions,
+        }
+    }
+
+    /// Returns the compiled state machine.
+    pub fn machine(&self) -> &'a StateMachine {
+        self.sm
+    }
+
+    /// Returns the number of transitions.
+    pub fn len(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Checks if the state machine has no transitions.
+    pub fn is_empty(&self) -> bool {
+        self.transitions.is_empty()
+    }
+
+    /// Resolves the label of a transition to its id.
+    pub fn id(&self, action: &str) -> Option<TransitionId> {
+        self.index.get(action).copied()
+    }
+
+    /// Returns the label of the transition, panics if the id is out of range.
+    pub fn label(&self, id: TransitionId) -> &'a str {
+        self.labels[id]
+    }
+
+    /// Returns the labels of all transitions indexed by id.
+    pub fn labels(&self) -> &[&'a str] {
+        &self.labels
+    }
+
+    /// Checks if the transition can fire in the given state, see `StateMachine::is_enabled`.
+    pub fn is_enabled(&self, state: &Vector, id: TransitionId, multiple: i32) -> bool {
+        match self.transitions.get(id) {
+            Some(transition) => self.sm.transition_enabled(state, transition, multiple),
+            None => false,
+        }
+    }
+
+    /// Returns the ids of the transitions enabled in the given state in increasing order.
+    pub fn enabled<'s>(&'s self, state: &'s Vector) -> impl Iterator<Item = TransitionId> + 's {
+        (0..self.len()).filter(move |id| self.is_enabled(state, *id, 1))
+    }
+
+    /// Fires the transition, rejecting unknown ids, models without places and states of the wrong dimension
+    /// like `Vasm::transform`.
+    pub fn fire(&self, state: &Vector, id: TransitionId, multiple: i32) -> Firing {
+        let Some(transition) = self.transitions.get(id) else {
+            let error = TransformError::UnknownAction {
+                action: format!("#{}", id),
+            };
+            return Firing::rejected(state, Arc::from(""), error);
+        };
+        let role = self.roles[id].clone();
+        if self.sm.is_empty() {
+            return Firing::rejected(state, role, TransformError::EmptyModel);
+        }
+        if state.len() != self.sm.places.len() {
+            let error = TransformError::DimensionMismatch {
+                expected: self.sm.places.len(),
+                actual: state.len(),
+            };
+            return Firing::rejected(state, role, error);
+        }
+        if let Some(semantics) = self.sm.firing_semantics() {
+            let tx = semantics.fire(self.sm, state, transition, multiple);
+            return Firing {
+                ok: tx.ok,
+                output: tx.output,
+                role,
+                inhibited: tx.inhibited,
+                overflow: tx.overflow,
+                underflow: tx.underflow,
+                error: tx.error,
+            };
+        }
+        let outcome = self.sm.outcome(state, transition, multiple);
+        Firing {
+            ok: outcome.ok,
+            output: outcome.output,
+            role,
+            inhibited: outcome.inhibited,
+            overflow: outcome.overflow,
+            underflow: outcome.underflow,
+            error: None,
+        }
+    }
+}
+
+impl StateMachine {
+    /// Compiles the state machine for firing transitions by index, see `CompiledVasm`.
+    pub fn compile(&self) -> CompiledVasm<'_> {
+        CompiledVasm::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::petri_net::PetriNet;
+    use crate::vasm::Vasm;
+
+    use super::*;
+
+    #[test]
+    fn test_matches_transform() {
+        let sm = StateMachine::from_model(&mut PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap());
+        let compiled = sm.compile();
+        assert_eq!(compiled.len(), 10);
+        assert_eq!(compiled.label(compiled.id("eat1").unwrap()), "eat1");
+        assert_eq!(compiled.id("missing"), None);
+
+        let state = sm.initial_vector();
+        for (id, action) in compiled.labels().iter().enumerate() {
+            for multiple in 1..=2 {
+                let firing = compiled.fire(&state, id, multiple);
+                let transaction = sm.transform(&state, action, multiple);
+                assert_eq!(firing.ok, transaction.ok, "{}", action);
+                assert_eq!(firing.output, transaction.output, "{}", action);
+                assert_eq!(&*firing.role, transaction.role, "{}", action);
+                assert_eq!(compiled.is_enabled(&state, id, multiple), transaction.ok, "{}", action);
+            }
+        }
+        let enabled: Vec<&str> = compiled.enabled(&state).map(|id| compiled.label(id)).collect();
+        assert_eq!(enabled, vec!["eat1", "eat2", "eat3", "eat4", "eat5"]);
+
+        assert_eq!(
+            compiled.fire(&state, 99, 1).error,
+            Some(TransformError::UnknownAction {
+                action: "#99".to_string()
+            })
+        );
+        assert_eq!(
+            compiled.fire(&vec![1], 0, 1).into_transaction().error,
+            Some(TransformError::DimensionMismatch {
+                expected: 15,
+                actual: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+
+use crate::expr::MarkingWeight;
+use crate::petri_net::{Arrow, PetriNet};
+
+/// `FusionSpec` lists the nodes shared by two nets being composed, as pairs of (left, right) labels.
+///
+/// Fused places let the components communicate asynchronously through shared tokens,
+/// fused transitions make both components fire together.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FusionSpec {
+    pub places: Vec<(String, String)>,
+    pub transitions: Vec<(String, String)>,
+}
+
+impl FusionSpec {
+    /// Creates an empty `FusionSpec`, composing the nets side by side.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fuses the place `left` of the first net with the place `right` of the second.
+    pub fn place(mut self, left: &str, right: &str) -> Self {
+        self.places.push((left.to_string(), right.to_string()));
+        self
+    }
+
+    /// Fuses the transition `left` of the first net with the transition `right` of the second.
+    pub fn transition(mut self, left: &str, right: &str) -> Self {
+        self.transitions.push((left.to_string(), right.to_string()));
+        self
+    }
+}
+
+/// Picks `label` or the first of `label_2`, `label_3`, ... not used in `net`.
+fn free_label(net: &PetriNet, label: &str) -> String {
+    let taken = |l: &str| net.places.contains_key(l) || net.transitions.contains_key(l);
+    if !taken(label) {
+        return label.to_string();
+    }
+    (2..)
+        .map(|i| format!("{}_{}", label, i))
+        .find(|l| !taken(l))
+        .unwrap()
+}
+
+/// Composes two nets, see `PetriNet::compose`.
+pub fn compose(left: &PetriNet, right: &PetriNet, fusion: &FusionSpec) -> PetriNet {
+    let mut net = left.clone();
+    let mut renamed: HashMap<&str, String> = HashMap::new();
+    for (name, role) in &right.roles {
+        net.roles.entry(name.clone()).or_insert_with(|| role.clone());
+    }
+
+    for (l, r) in &fusion.places {
+        assert!(left.places.contains_key(l), "unknown place {} in left net", l);
+        assert!(right.places.contains_key(r), "unknown place {} in right net", r);
+        renamed.insert(r, l.clone());
+    }
+    for (l, r) in &fusion.transitions {
+        assert!(left.transitions.contains_key(l), "unknown transition {} in left net", l);
+        assert!(right.transitions.contains_key(r), "unknown transition {} in right net", r);
+        renamed.insert(r, l.clone());
+    }
+
+    let mut places: Vec<_> = right.places.iter().collect();
+    places.sort_by_key(|(label, place)| (place.offset, *label));
+    for (label, place) in places {
+        if renamed.contains_key(label.as_str()) {
+            continue;
+        }
+        let name = free_label(&net, label);
+        let mut place = place.clone();
+        place.offset = net.places.len() as i32;
+        net.places.insert(name.clone(), place);
+        renamed.insert(label, name);
+    }
+
+    let mut transitions: Vec<_> = right.transitions.iter().collect();
+    transitions.sort_by_key(|(label, _)| *label);
+    for (label, transition) in transitions {
+        if renamed.contains_key(label.as_str()) {
+            continue;
+        }
+        let name = free_label(&net, label);
+        let mut transition = transition.clone();
+        if transition.role.is_none() {
+            transition.role = Some(right.default_role().to_string());
+        }
+        net.transitions.insert(name.clone(), transition);
+        renamed.insert(label, name);
+    }
+
+    for arc in &right.arcs {
+        let arc = Arrow {
+            source: renamed[arc.source.as_str()].clone(),
+            target: renamed[arc.target.as_str()].clone(),
+            transfer: arc.transfer.as_ref().map(|t| renamed[t.as_str()].clone()),
+            marking_weight: arc
+                .marking_weight
+                .as_deref()
+                .map(|w| MarkingWeight::rename_place(w, |p| renamed[p].clone())),
+            ..arc.clone()
+        };
+        let duplicate = net.arcs.iter().any(|a| arc_key(a) == arc_key(&arc));
+        if !duplicate {
+            net.arcs.push(arc);
+        }
+    }
+    net
+}
+
+/// Identifies an arc by its nodes, weight and kind, so fused arcs are only merged when they are the same arc.
+fn arc_key(arc: &Arrow) -> (&str, &str, i32, bool, bool, bool, Option<&str>, Option<&str>) {
+    (
+        &arc.source,
+        &arc.target,
+        arc.weight.unwrap_or(1),
+        arc.inhibit.unwrap_or(false),
+        arc.read.unwrap_or(false),
+        arc.reset.unwrap_or(false),
+        arc.transfer.as_deref(),
+        arc.marking_weight.as_deref(),
+    )
+}
+
+impl PetriNet {
+    /// Composes this net with `other`, merging the nodes paired in `fusion`.
+    ///
+    /// Fused nodes keep the label and attributes of this net. Other nodes of `other` whose label is
+    /// already taken are renamed `label_2`, `label_3`, ... and its places are appended after ours.
+    /// An arc already present between two fused nodes with the same weight and kind is kept once, and roles of `other` are declared
+    /// unless this net already declares a role of the same name.
+    ///
+    /// Panics if `fusion` names a node missing from either net.
+    pub fn compose(&self, other: &PetriNet, fusion: &FusionSpec) -> PetriNet {
+        compose(self, other, fusion)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vasm::{StateMachine, Vasm};
+
+    use super::*;
+
+    fn producer() -> PetriNet {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("idle", Option::from(1), None, 0, 0);
+            p.cell("buffer", None, None, 0, 0);
+            p.func("work", "default", 0, 0);
+            p.arrow("idle", "work", 1);
+            p.arrow("work", "idle", 1);
+            p.arrow("work", "buffer", 1);
+        });
+        net
+    }
+
+    fn consumer() -> PetriNet {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("idle", Option::from(1), None, 0, 0);
+            p.cell("input", None, None, 0, 0);
+            p.func("work", "default", 0, 0);
+            p.arrow("input", "work", 1);
+            p.arrow("idle", "work", 1);
+            p.arrow("work", "idle", 1);
+        });
+        net
+    }
+
+    #[test]
+    fn test_place_fusion_renames_collisions() {
+        let net = producer().compose(&consumer(), &FusionSpec::new().place("buffer", "input"));
+        assert_eq!(net.places.len(), 3);
+        assert_eq!(net.transitions.len(), 2);
+        assert_eq!(net.places["idle_2"].offset, 2);
+        assert!(net.transitions.contains_key("work_2"));
+        assert!(net.arcs.iter().any(|a| a.source == "buffer" && a.target == "work_2"));
+
+        let sm = StateMachine::from_model(&mut net.clone());
+        let res = sm.transform(&sm.initial_vector(), "work_2", 1);
+        assert!(res.is_err());
+        let res = sm.transform(&sm.transform(&sm.initial_vector(), "work", 1).output, "work_2", 1);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_transition_fusion_synchronizes() {
+        let fusion = FusionSpec::new().place("idle", "idle").transition("work", "work");
+        let net = producer().compose(&consumer(), &fusion);
+        assert_eq!(net.places.len(), 3);
+        assert_eq!(net.transitions.len(), 1);
+        assert_eq!(net.arcs.len(), 4);
+
+        let sm = StateMachine::from_model(&mut net.clone());
+        assert!(sm.transform(&sm.initial_vector(), "work", 1).is_err());
+
+        let mut heavy = consumer();
+        heavy.arcs.iter_mut().find(|a| a.source == "idle").unwrap().weight = Some(2);
+        let net = producer().compose(&heavy, &fusion);
+        assert_eq!(net.arcs.len(), 5);
+        assert!(net.arcs.iter().any(|a| a.source == "idle" && a.weight == Some(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown place missing in right net")]
+    fn test_unknown_fused_node_panics() {
+        producer().compose(&consumer(), &FusionSpec::new().place("buffer", "missing"));
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+use std::io;
 use std::io::Cursor;
 use std::io::Read;
 use std::io::Write;
@@ -5,37 +7,275 @@ use base64::{Engine as _, engine::general_purpose};
 
 
 use brotli::CompressorWriter;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
 
+/// The buffer size used by the brotli streams.
+const BUFFER_SIZE: usize = 4096;
 
-pub fn decompress_brotli_decode(encoded_data: &str) -> Option<String> {
-    let decoded = general_purpose::STANDARD.decode(encoded_data);
-    if !decoded.is_ok() {
-        return None
+/// `Codec` is the compression applied to a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// No compression.
+    Stored,
+    /// Deflate with a level from 0 (fastest) to 9 (smallest).
+    Deflate { level: u32 },
+    /// Brotli with a quality from 0 (fastest) to 11 (smallest).
+    Brotli { quality: u32 },
+}
+
+impl Default for Codec {
+    /// Brotli at quality 5, the codec of zblobs and share URLs.
+    fn default() -> Self {
+        Codec::Brotli { quality: 5 }
+    }
+}
+
+/// `DecodeLimits` bounds the output of a decoder, to defend against untrusted payloads that inflate
+/// to huge sizes or unpack into huge numbers of entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// The maximum number of decompressed bytes.
+    pub max_size: u64,
+    /// The maximum number of entries of a bundle.
+    pub max_entries: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_size: 16 * 1024 * 1024,
+            max_entries: 64,
+        }
+    }
+}
+
+/// `DecodeError` describes why an encoded payload could not be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The URL has no `z` parameter.
+    MissingPayload,
+    /// The payload is not valid base64.
+    InvalidBase64,
+    /// The payload could not be decompressed.
+    InvalidData(String),
+    /// The decompressed payload is not UTF-8.
+    NotUtf8,
+    /// The payload decompresses to more than the allowed number of bytes.
+    TooLarge { limit: u64 },
+    /// The payload unpacks into more than the allowed number of entries.
+    TooManyEntries { limit: usize },
+    /// The payload is encrypted and must be decrypted first.
+    Encrypted,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::MissingPayload => write!(f, "no z parameter in the url"),
+            DecodeError::InvalidBase64 => write!(f, "payload is not valid base64"),
+            DecodeError::InvalidData(reason) => write!(f, "payload could not be decompressed: {}", reason),
+            DecodeError::NotUtf8 => write!(f, "payload is not utf-8"),
+            DecodeError::TooLarge { limit } => write!(f, "payload decompresses to more than {} bytes", limit),
+            DecodeError::TooManyEntries { limit } => write!(f, "payload holds more than {} entries", limit),
+            DecodeError::Encrypted => write!(f, "payload is encrypted"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<io::Error> for DecodeError {
+    fn from(err: io::Error) -> Self {
+        match err.get_ref().and_then(|e| e.downcast_ref::<DecodeError>()) {
+            Some(decode_error) => decode_error.clone(),
+            None => DecodeError::InvalidData(err.to_string()),
+        }
+    }
+}
+
+/// Wraps `writer` in a stream compressing everything written to it.
+///
+/// Call `Encoder::finish` to complete the stream, dropping the encoder completes it too but loses write errors.
+pub fn encoder<W: Write>(writer: W, codec: Codec) -> Encoder<W> {
+    match codec {
+        Codec::Stored => Encoder(Inner::Stored(writer)),
+        Codec::Deflate { level } => {
+            Encoder(Inner::Deflate(DeflateEncoder::new(writer, Compression::new(level.min(9)))))
+        }
+        Codec::Brotli { quality } => {
+            let writer = Recording { inner: writer, error: None };
+            Encoder(Inner::Brotli(Box::new(CompressorWriter::new(writer, BUFFER_SIZE, quality.min(11), 22))))
+        }
+    }
+}
+
+/// `Encoder` is a stream compressing everything written to it with a `Codec`, see `encoder`.
+pub struct Encoder<W: Write>(Inner<W>);
+
+enum Inner<W: Write> {
+    Stored(W),
+    Deflate(DeflateEncoder<W>),
+    Brotli(Box<CompressorWriter<Recording<W>>>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Writes the end of the compressed stream and returns the underlying writer.
+    pub fn finish(self) -> io::Result<W> {
+        match self.0 {
+            Inner::Stored(mut writer) => writer.flush().map(|_| writer),
+            Inner::Deflate(encoder) => encoder.finish(),
+            Inner::Brotli(encoder) => {
+                // `into_inner` swallows the errors of the final writes, the recording writer keeps them.
+                let writer = encoder.into_inner();
+                match writer.error {
+                    Some(error) => Err(error),
+                    None => Ok(writer.inner),
+                }
+            }
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.0 {
+            Inner::Stored(writer) => writer.write(buf),
+            Inner::Deflate(encoder) => encoder.write(buf),
+            Inner::Brotli(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.0 {
+            Inner::Stored(writer) => writer.flush(),
+            Inner::Deflate(encoder) => encoder.flush(),
+            Inner::Brotli(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// A writer keeping the first error of the writer it wraps.
+struct Recording<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Write for Recording<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf).map_err(|error| self.record(error))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().map_err(|error| self.record(error))
     }
+}
+
+impl<W> Recording<W> {
+    fn record(&mut self, error: io::Error) -> io::Error {
+        let copy = io::Error::new(error.kind(), error.to_string());
+        self.error.get_or_insert(error);
+        copy
+    }
+}
+
+/// Wraps `reader` in a stream decompressing what is read from it, failing with `InvalidData`
+/// once more than `limits.max_size` bytes were decompressed. The error converts into `DecodeError::TooLarge`.
+pub fn decoder<'a, R: Read + 'a>(reader: R, codec: Codec, limits: DecodeLimits) -> Box<dyn Read + 'a> {
+    let inner: Box<dyn Read + 'a> = match codec {
+        Codec::Stored => Box::new(reader),
+        Codec::Deflate { .. } => Box::new(DeflateDecoder::new(reader)),
+        Codec::Brotli { .. } => Box::new(brotli::Decompressor::new(reader, BUFFER_SIZE)),
+    };
+    Box::new(Limited {
+        inner,
+        limit: limits.max_size,
+        remaining: limits.max_size,
+    })
+}
+
+/// A reader failing once it produced more than the allowed number of bytes.
+struct Limited<R> {
+    inner: R,
+    limit: u64,
+    remaining: u64,
+}
+
+impl<R: Read> Read for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Reading one byte past the limit tells a stream of exactly the limit from a longer one.
+        let len = buf.len().min(self.remaining.saturating_add(1).try_into().unwrap_or(usize::MAX));
+        let n = self.inner.read(&mut buf[..len])?;
+        if n as u64 > self.remaining {
+            let error = DecodeError::TooLarge { limit: self.limit };
+            return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Compresses everything from `reader` into `writer`, returns the number of uncompressed bytes.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(codec = ?codec), ret, err))]
+pub fn compress_stream<R: Read, W: Write>(mut reader: R, writer: W, codec: Codec) -> io::Result<u64> {
+    let mut encoder = encoder(writer, codec);
+    let n = io::copy(&mut reader, &mut encoder)?;
+    encoder.finish()?;
+    Ok(n)
+}
+
+/// Decompresses everything from `reader` into `writer`, returns the number of decompressed bytes.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    level = "debug",
+    skip_all,
+    fields(codec = ?codec, max_size = limits.max_size),
+    ret,
+    err
+))]
+pub fn decompress_stream<R: Read, W: Write>(
+    reader: R,
+    mut writer: W,
+    codec: Codec,
+    limits: DecodeLimits,
+) -> io::Result<u64> {
+    io::copy(&mut decoder(reader, codec, limits), &mut writer)
+}
+
+/// Decodes a base64 brotli payload, such as the `z` parameter of a share URL, within the limits.
+pub fn decode_within(encoded_data: &str, limits: DecodeLimits) -> Result<String, DecodeError> {
+    let decoded = general_purpose::STANDARD
+        .decode(encoded_data)
+        .map_err(|_| DecodeError::InvalidBase64)?;
     let mut decompressed_data = Vec::new();
-    let mut decompressor = brotli::Decompressor::new(Cursor::new(decoded.unwrap()), 4096);
-    decompressor.read_to_end(&mut decompressed_data).unwrap();
-    Option::from(String::from_utf8(decompressed_data).unwrap())
+    decompress_stream(Cursor::new(decoded), &mut decompressed_data, Codec::default(), limits)?;
+    String::from_utf8(decompressed_data).map_err(|_| DecodeError::NotUtf8)
 }
 
-/// Decodes the base64 string from the given URL into a zip file and then extracts the file with the given filename from the zip file.
-pub fn decompress_encoded_url(url: &str) -> Option<String> {
-    let query_string = url.split('?').collect::<Vec<&str>>()[1];
+/// Decodes a base64 brotli payload within `DecodeLimits::default()`, see `decode_within`.
+pub fn decompress_brotli_decode(encoded_data: &str) -> Option<String> {
+    decode_within(encoded_data, DecodeLimits::default()).ok()
+}
+
+/// Decodes the `z` parameter of the given URL within the limits.
+pub fn decode_url_within(url: &str, limits: DecodeLimits) -> Result<String, DecodeError> {
+    let (_, query_string) = url.split_once('?').ok_or(DecodeError::MissingPayload)?;
     let z = query_string
         .split('&')
-        .find(|&param| param.starts_with("z="))?;
-    let z = &z[2..];
+        .find_map(|param| param.strip_prefix("z="))
+        .ok_or(DecodeError::MissingPayload)?;
+    decode_within(z, limits)
+}
 
-    decompress_brotli_decode(z)
+/// Decodes the `z` parameter of the given URL within `DecodeLimits::default()`, see `decode_url_within`.
+pub fn decompress_encoded_url(url: &str) -> Option<String> {
+    decode_url_within(url, DecodeLimits::default()).ok()
 }
 
+/// Compresses the data with the default codec and encodes it as base64, the inverse of `decompress_brotli_decode`.
 pub fn compress_brotli_encode(data: &str) -> String {
     let mut compressed_data = Vec::new();
-    {
-        let mut compressor = CompressorWriter::new(&mut compressed_data, 4096, 5, 22); // 4096 is the buffer size, 5 is quality, 22 is lgwin
-        compressor.write_all(data.as_bytes()).unwrap();
-    } // CompressorWriter is flushed and finished when it goes out of scope
-
+    compress_stream(data.as_bytes(), &mut compressed_data, Codec::default()).unwrap();
     general_purpose::STANDARD.encode(compressed_data)
 }
 
@@ -54,6 +294,81 @@ mod tests {
         assert_eq!(decoded, DINING_PHILOSOPHERS);
     }
 
+    #[test]
+    fn test_streaming_codecs() {
+        let data = DINING_PHILOSOPHERS.repeat(20);
+        let codecs = [Codec::Stored, Codec::Deflate { level: 9 }, Codec::Brotli { quality: 11 }, Codec::default()];
+        for codec in codecs {
+            let mut compressed = Vec::new();
+            assert_eq!(compress_stream(data.as_bytes(), &mut compressed, codec).unwrap(), data.len() as u64);
+            if codec != Codec::Stored {
+                assert!(compressed.len() < data.len() / 10, "{:?}", codec);
+            }
+            let mut decompressed = Vec::new();
+            let n = decompress_stream(&compressed[..], &mut decompressed, codec, DecodeLimits::default()).unwrap();
+            assert_eq!((n, decompressed), (data.len() as u64, data.as_bytes().to_vec()));
+        }
+    }
+
+    #[test]
+    fn test_compress_stream_reports_write_errors() {
+        struct Full(usize);
+        impl Write for Full {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if buf.len() > self.0 {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, "full"));
+                }
+                self.0 -= buf.len();
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        for codec in [Codec::Stored, Codec::Deflate { level: 9 }, Codec::default()] {
+            let err = compress_stream(DINING_PHILOSOPHERS.as_bytes(), Full(8), codec).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::WriteZero, "{:?}", codec);
+        }
+    }
+
+    #[test]
+    fn test_decode_size_limit() {
+        let bomb = vec![0u8; 1 << 20];
+        let mut compressed = Vec::new();
+        compress_stream(&bomb[..], &mut compressed, Codec::Deflate { level: 9 }).unwrap();
+        let limits = DecodeLimits {
+            max_size: 1 << 16,
+            ..Default::default()
+        };
+        let err = decompress_stream(&compressed[..], io::sink(), Codec::Deflate { level: 9 }, limits).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(DecodeError::from(err), DecodeError::TooLarge { limit: 1 << 16 });
+
+        let exact = DecodeLimits {
+            max_size: 1 << 20,
+            ..Default::default()
+        };
+        assert!(decompress_stream(&compressed[..], io::sink(), Codec::Deflate { level: 9 }, exact).is_ok());
+
+        let encoded = general_purpose::STANDARD.encode(compress_stream_to_vec(&vec![b'a'; 32 << 20]));
+        assert!(decompress_brotli_decode(&encoded).is_none());
+        assert_eq!(
+            decode_within(&encoded, DecodeLimits::default()),
+            Err(DecodeError::TooLarge { limit: 16 << 20 })
+        );
+        assert_eq!(decode_within("not base64!", DecodeLimits::default()), Err(DecodeError::InvalidBase64));
+        assert_eq!(
+            decode_url_within("http://localhost:3000/", DecodeLimits::default()),
+            Err(DecodeError::MissingPayload)
+        );
+    }
+
+    fn compress_stream_to_vec(data: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        compress_stream(data, &mut compressed, Codec::default()).unwrap();
+        compressed
+    }
+
     #[test]
     fn test_unzip_url() {
         let url = "http://localhost:3000/?z=GzkCIBwHdqMPWUYyo7XgaT/B09w+1fHywu1u31IMRQwiCxaRsTAxQRT6UodF4e9vcmthITygLrPfojnB4nxsskw21O/iE3GRG82+n/aPgzT++TW8fY5765PjEAvRHLk1fa0Atw8uCVzrgniE9AOCxwJt0eNbZxX3GlCwKSXlDBVIj2qWMSpoWCuQ0SZF4WJKQu7IYz8DzVzPNGg5hqbWWqtzXBixNz9qkiODzShUClkETwDocbjtBJp9Wh5QW8T8PXrgq9nCDI3qaA==";
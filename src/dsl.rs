@@ -1,4 +1,8 @@
-use crate::petri_net::PetriNet;
+use serde_json::Value;
+
+use crate::capacity::Capacity;
+use crate::expr::{Condition, MarkingWeight};
+use crate::petri_net::{is_integral, PetriNet};
 use crate::vasm::StateMachine;
 
 /// `FlowDsl` is a trait that provides a domain-specific language (DSL) for defining Petri nets.
@@ -11,6 +15,19 @@ use crate::vasm::StateMachine;
 /// * `func` - Adds a function (transition) to the Petri net.
 /// * `arrow` - Adds an arrow (arc) from a source to a target in the Petri net.
 /// * `guard` - Adds a guard (inhibitor arc) from a source to a target in the Petri net.
+/// * `unit` - Sets the unit of the tokens held by a cell.
+/// * `capacity` - Sets the capacity of a cell, the only way to declare a cell that holds no tokens.
+/// * `allow_reentry` - Lets a workflow func fire into the cell that is already marked.
+/// * `priority` / `rate` / `delay` - Set the conflict priority, stochastic rate and firing delay of a func.
+/// * `label` / `localize` - Set the default and the per-locale display names of a cell or func,
+///   which keep being referred to by their identifiers.
+/// * `describe` / `attribute` - Attach a description or custom key-value data to a cell or func.
+/// * `place` / `transition` - Like `cell` and `func`, but return typed handles.
+/// * `consume` / `produce` / `inhibit` / `require` - Connect typed handles, so arcs between two
+///   places or two transitions are compile errors.
+///
+/// Only the first five methods are required, the others do nothing unless an implementation overrides them,
+/// so implementations that cannot represent a feature still compile.
 ///
 /// # Example
 ///
@@ -34,6 +51,14 @@ use crate::vasm::StateMachine;
 pub trait FlowDsl {
     /// Sets the model type of the Petri net.
     fn model_type(&mut self, model_type: &str);
+    /// Declares a role with the roles it implies, see `PetriNet::validate_roles`.
+    fn role(&mut self, name: &str, description: Option<&str>, implies: &[&str]) {
+        let _ = (name, description, implies);
+    }
+    /// Sets the role of the functions (transitions) stored without one.
+    fn default_role(&mut self, role: &str) {
+        let _ = role;
+    }
     /// Adds a cell (place) to the Petri net.
     fn cell<'a>(
         &mut self,
@@ -49,6 +74,123 @@ pub trait FlowDsl {
     fn arrow(&mut self, source: &str, target: &str, weight: i32);
     /// Adds a guard (inhibitor arc) from a source to a target in the Petri net.
     fn guard(&mut self, source: &str, target: &str, weight: i32);
+    /// Adds a reset arc emptying a cell (place) when a function (transition) fires.
+    fn reset(&mut self, cell: &str, func: &str) {
+        let _ = (cell, func);
+    }
+    /// Adds a transfer arc moving the whole content of a cell to another, `weight` times as many tokens,
+    /// when a function fires. The content is taken after the ordinary arrows of the function.
+    fn transfer(&mut self, source: &str, func: &str, target: &str, weight: i32) {
+        let _ = (source, func, target, weight);
+    }
+    /// Adds an arrow whose weight is computed from the marking when the function fires, such as `all`
+    /// or `half of dock max 10`, see `expr::MarkingWeight`.
+    fn marking_arrow(&mut self, source: &str, target: &str, weight: &str) {
+        let _ = (source, target, weight);
+    }
+    /// Sets the unit of the tokens held by a cell (place).
+    fn unit(&mut self, cell: &str, unit: &str) {
+        let _ = (cell, unit);
+    }
+    /// Sets the capacity of a cell, unlike the `capacity` argument of `cell` a bound of zero forbids tokens.
+    fn capacity(&mut self, cell: &str, capacity: Capacity) {
+        let _ = (cell, capacity);
+    }
+    /// Lets a workflow function (transition) fire into the cell that is already marked.
+    fn allow_reentry(&mut self, func: &str) {
+        let _ = func;
+    }
+    /// Sets the priority of a function (transition), higher fires first in a conflict.
+    fn priority(&mut self, func: &str, priority: i32) {
+        let _ = (func, priority);
+    }
+    /// Sets the firing rate of a function (transition).
+    fn rate(&mut self, func: &str, rate: i32) {
+        let _ = (func, rate);
+    }
+    /// Sets the firing delay of a function (transition).
+    fn delay(&mut self, func: &str, delay: i32) {
+        let _ = (func, delay);
+    }
+    /// Sets the time units a function (transition) may stay enabled without firing, and the function
+    /// fired automatically once they pass.
+    fn timeout(&mut self, func: &str, after: i32, escalate: Option<&str>) {
+        let _ = (func, after, escalate);
+    }
+    /// Requires a condition over the case variables for a function (transition) to fire, see `expr::Expr`.
+    fn condition(&mut self, func: &str, expression: &str) {
+        let _ = (func, expression);
+    }
+    /// Sets the name shown for a cell or function instead of its identifier.
+    fn label(&mut self, node: &str, label: &str) {
+        let _ = (node, label);
+    }
+    /// Sets the name shown for a cell or function in the given locale.
+    fn localize(&mut self, node: &str, locale: &str, label: &str) {
+        let _ = (node, locale, label);
+    }
+    /// Sets the description of a cell or function.
+    fn describe(&mut self, node: &str, description: &str) {
+        let _ = (node, description);
+    }
+    /// Attaches a custom key-value attribute to a cell or function.
+    fn attribute(&mut self, node: &str, key: &str, value: Value) {
+        let _ = (node, key, value);
+    }
+
+    /// Adds a place to the Petri net and returns a typed handle to it.
+    fn place<'a>(
+        &mut self,
+        label: &'a str,
+        initial: Option<i32>,
+        capacity: Option<i32>,
+        x: i32,
+        y: i32,
+    ) -> PlaceRef<'a> {
+        PlaceRef(self.cell(label, initial, capacity, x, y))
+    }
+    /// Adds a transition to the Petri net and returns a typed handle to it.
+    fn transition<'a>(&mut self, label: &'a str, role: &str, x: i32, y: i32) -> TransitionRef<'a> {
+        TransitionRef(self.func(label, role, x, y))
+    }
+    /// Adds an arc consuming `weight` tokens from the place when the transition fires.
+    fn consume(&mut self, place: PlaceRef, transition: TransitionRef, weight: i32) {
+        self.arrow(place.0, transition.0, weight);
+    }
+    /// Adds an arc producing `weight` tokens into the place when the transition fires.
+    fn produce(&mut self, transition: TransitionRef, place: PlaceRef, weight: i32) {
+        self.arrow(transition.0, place.0, weight);
+    }
+    /// Adds an inhibitor arc disabling the transition while the place holds `weight` tokens or more.
+    fn inhibit(&mut self, place: PlaceRef, transition: TransitionRef, weight: i32) {
+        self.guard(place.0, transition.0, weight);
+    }
+    /// Adds a read arc enabling the transition only while the place holds `weight` tokens or more.
+    fn require(&mut self, transition: TransitionRef, place: PlaceRef, weight: i32) {
+        self.guard(transition.0, place.0, weight);
+    }
+}
+
+/// `PlaceRef` is a handle to a place declared with `FlowDsl::place`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlaceRef<'a>(&'a str);
+
+impl<'a> PlaceRef<'a> {
+    /// Returns the label of the place.
+    pub fn label(&self) -> &'a str {
+        self.0
+    }
+}
+
+/// `TransitionRef` is a handle to a transition declared with `FlowDsl::transition`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransitionRef<'a>(&'a str);
+
+impl<'a> TransitionRef<'a> {
+    /// Returns the label of the transition.
+    pub fn label(&self) -> &'a str {
+        self.0
+    }
 }
 
 /// `Builder` is a struct that implements the `FlowDsl` trait and is used to build a Petri net.
@@ -94,6 +236,14 @@ impl<'a> FlowDsl for Builder<'a> {
         self.net.model_type = model_type.to_string();
     }
 
+    fn role(&mut self, name: &str, description: Option<&str>, implies: &[&str]) {
+        self.net.add_role(name, description, implies);
+    }
+
+    fn default_role(&mut self, role: &str) {
+        self.net.set_default_role(role);
+    }
+
     fn cell<'b>(
         &mut self,
         label: &'b str,
@@ -104,12 +254,12 @@ impl<'a> FlowDsl for Builder<'a> {
     ) -> &'b str {
         let offset = self.net.places.len() as i32;
         self.net.add_place(label, offset, initial, capacity, x, y);
-        return label;
+        label
     }
 
     fn func<'b>(&mut self, label: &'b str, role: &str, x: i32, y: i32) -> &'b str {
         self.net.add_transition(label, role, x, y);
-        return label;
+        label
     }
 
     fn arrow(&mut self, source: &str, target: &str, weight: i32) {
@@ -121,6 +271,77 @@ impl<'a> FlowDsl for Builder<'a> {
         assert!(weight > 0, "weight must be positive");
         self.net.add_arc(source, target, Some(weight), Some(true), None, Some(true), None);
     }
+
+    fn reset(&mut self, cell: &str, func: &str) {
+        self.net.add_reset_arc(cell, func);
+    }
+
+    fn transfer(&mut self, source: &str, func: &str, target: &str, weight: i32) {
+        assert!(weight > 0, "weight must be positive");
+        self.net.add_transfer_arc(source, func, target, weight);
+    }
+
+    fn marking_arrow(&mut self, source: &str, target: &str, weight: &str) {
+        if let Err(err) = MarkingWeight::parse(weight) {
+            panic!("invalid marking weight of arrow {} -> {}: {}", source, target, err);
+        }
+        self.net.add_marking_arc(source, target, weight);
+    }
+
+    fn unit(&mut self, cell: &str, unit: &str) {
+        assert!(self.net.set_unit(cell, unit), "unit declared for unknown cell {}", cell);
+    }
+
+    fn capacity(&mut self, cell: &str, capacity: Capacity) {
+        assert!(self.net.set_capacity(cell, capacity), "capacity declared for unknown cell {}", cell);
+    }
+
+    fn allow_reentry(&mut self, func: &str) {
+        assert!(self.net.set_allow_reentry(func, true), "reentry allowed for unknown func {}", func);
+    }
+
+    fn priority(&mut self, func: &str, priority: i32) {
+        assert!(self.net.set_priority(func, priority), "priority declared for unknown func {}", func);
+    }
+
+    fn rate(&mut self, func: &str, rate: i32) {
+        assert!(rate >= 0, "rate must not be negative");
+        assert!(self.net.set_rate(func, rate), "rate declared for unknown func {}", func);
+    }
+
+    fn delay(&mut self, func: &str, delay: i32) {
+        assert!(delay >= 0, "delay must not be negative");
+        assert!(self.net.set_delay(func, delay), "delay declared for unknown func {}", func);
+    }
+
+    fn timeout(&mut self, func: &str, after: i32, escalate: Option<&str>) {
+        assert!(after >= 0, "timeout must not be negative");
+        assert!(self.net.set_timeout(func, after, escalate), "timeout declared for unknown func {}", func);
+    }
+
+    fn condition(&mut self, func: &str, expression: &str) {
+        if let Err(err) = Condition::parse(expression) {
+            panic!("invalid condition of func {}: {}", func, err);
+        }
+        assert!(self.net.set_condition(func, expression), "condition declared for unknown func {}", func);
+    }
+
+    fn label(&mut self, node: &str, label: &str) {
+        assert!(self.net.set_label(node, None, label), "label declared for unknown node {}", node);
+    }
+
+    fn localize(&mut self, node: &str, locale: &str, label: &str) {
+        assert!(self.net.set_label(node, Some(locale), label), "label declared for unknown node {}", node);
+    }
+
+    fn describe(&mut self, node: &str, description: &str) {
+        assert!(self.net.set_description(node, description), "description declared for unknown node {}", node);
+    }
+
+    fn attribute(&mut self, node: &str, key: &str, value: Value) {
+        assert!(is_integral(&value), "attribute {} of {} is not an integer", key, node);
+        assert!(self.net.set_attribute(node, key, value), "attribute declared for unknown node {}", node);
+    }
 }
 
 #[cfg(test)]
@@ -136,6 +357,7 @@ mod tests {
     }
 
     impl TestModel {
+        #[cfg(feature = "zblob")]
         fn to_link(&self) -> String {
             format!("{}{}", "https://pflow.dev/p/?z=", self.model.net.to_zblob().base64_zipped.replace(" ", "+"))
         }
@@ -197,11 +419,104 @@ mod tests {
         p.guard(foo, baz, 1);
     }
 
+    fn typed_model(p: &mut dyn FlowDsl) {
+        p.model_type("petriNet");
+        let foo = p.place("foo", Option::from(1), Option::from(3), 707, 364);
+        let bar = p.transition("bar", "default", 560, 480);
+        let baz = p.transition("baz", "default", 850, 480);
+        let inc = p.transition("inc", "default", 560, 240);
+        let dec = p.transition("dec", "default", 850, 240);
+
+        p.produce(inc, foo, 1);
+        p.consume(foo, dec, 1);
+        p.require(bar, foo, 3);
+        p.inhibit(foo, baz, 1);
+    }
+
+    #[test]
+    fn test_declaring_options() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            let r = "default";
+            p.cell("queue", None, Option::from(10), 0, 0);
+            p.func("serve", r, 0, 0);
+            p.priority("serve", 2);
+            p.rate("serve", 3);
+            p.delay("serve", 30);
+            p.timeout("serve", 60, Some("serve"));
+            p.describe("queue", "customers waiting");
+            p.label("serve", "Serve customer");
+            p.localize("serve", "es", "Atender al cliente");
+            p.attribute("serve", "owner", serde_json::json!("support"));
+        });
+        let serve = &net.transitions["serve"];
+        assert_eq!((serve.priority, serve.rate, serve.delay), (Some(2), Some(3), Some(30)));
+        assert_eq!(serve.attributes["owner"], "support");
+        assert_eq!(net.display_label("serve", Some("es")), Some("Atender al cliente"));
+        assert_eq!(net.places["queue"].description.as_deref(), Some("customers waiting"));
+
+        let json = net.to_json().unwrap();
+        assert!(json.contains("\"priority\":2"));
+        let restored = PetriNet::from_json(json).unwrap();
+        assert_eq!(restored.transitions["serve"].delay, Some(30));
+        let timeout = restored.transitions["serve"].timeout.as_ref().unwrap();
+        assert_eq!((timeout.after, timeout.escalate.as_deref()), (60, Some("serve")));
+        assert_eq!(restored.transitions["serve"].attributes["owner"], "support");
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let sm = StateMachine::new(|p| {
+            p.cell("closed", None, Option::from(0), 0, 0);
+            p.cell("open", None, Option::from(0), 0, 0);
+            p.func("fill", "default", 0, 0);
+            p.func("leak", "default", 0, 0);
+            p.arrow("fill", "open", 1);
+            p.arrow("leak", "closed", 1);
+            p.capacity("closed", Capacity::Bounded(0));
+        });
+        assert_eq!(sm.capacity, vec![Capacity::Bounded(0), Capacity::Unbounded]);
+        assert!(sm.transform(&sm.initial_vector(), "fill", 1).is_ok());
+        assert!(sm.transform(&sm.initial_vector(), "leak", 1).overflow);
+    }
+
+    #[test]
+    fn test_typed_handles() {
+        let typed = StateMachine::new(typed_model);
+        let untyped = StateMachine::new(model_test_code);
+        assert_eq!(typed.places, untyped.places);
+        assert_eq!(typed.capacity, untyped.capacity);
+        for (label, t) in &untyped.transitions {
+            assert_eq!(typed.transitions[label].delta, t.delta);
+            assert_eq!(typed.transitions[label].guards.len(), t.guards.len());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "macros")]
+    fn test_petri_net_macro() {
+        let sm = StateMachine::new(crate::petri_net! {
+            places { p1(2), p2, p3(0, 1) }
+            transitions { t1: role(user), t2 }
+            arcs { p1 -2-> t1 -> p2, p2 -> t2 -> p3 }
+        });
+        assert_eq!(sm.places, vec!["p1", "p2", "p3"]);
+        assert_eq!(sm.initial, vec![2, 0, 0]);
+        assert_eq!(sm.capacity, vec![Capacity::Unbounded, Capacity::Unbounded, Capacity::Bounded(1)]);
+        assert_eq!(sm.transitions["t1"].role, "user");
+        assert_eq!(sm.transitions["t1"].delta, vec![-2, 1, 0]);
+        assert!(sm.roles.contains_key("default"));
+
+        let res = sm.transform(&sm.initial, "t1", 1);
+        assert_eq!(sm.transform(&res.output, "t2", 1).output, vec![0, 0, 1]);
+    }
+
     #[test]
     fn test_loading_dsl() {
         let m = &mut TestModel::new();
         assert_eq!(m.state, vec![1]);
 
+        #[cfg(feature = "zblob")]
         println!("link: {}", m.to_link()); // compare w/ GUI
 
         m.assert_inhibited("bar");
@@ -216,4 +531,30 @@ mod tests {
         m.assert_underflow("dec");
         m.assert_pass("baz"); // enabled
     }
+
+    #[test]
+    fn test_minimal_implementation() {
+        #[derive(Default)]
+        struct Counter(usize);
+
+        impl FlowDsl for Counter {
+            fn model_type(&mut self, _: &str) {}
+            fn cell<'a>(&mut self, label: &'a str, _: Option<i32>, _: Option<i32>, _: i32, _: i32) -> &'a str {
+                self.0 += 1;
+                label
+            }
+            fn func<'a>(&mut self, label: &'a str, _: &str, _: i32, _: i32) -> &'a str {
+                self.0 += 1;
+                label
+            }
+            fn arrow(&mut self, _: &str, _: &str, _: i32) {}
+            fn guard(&mut self, _: &str, _: &str, _: i32) {}
+        }
+
+        let mut counter = Counter::default();
+        model_test_code(&mut counter);
+        counter.unit("foo", "kg");
+        counter.reset("foo", "dec");
+        assert_eq!(counter.0, 5);
+    }
 }
@@ -0,0 +1,230 @@
+use std::fmt;
+
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+
+use crate::zblob::Zblob;
+
+/// The algorithm encrypting both the payload and the wrapped content key.
+pub const ALGORITHM: &str = "xchacha20poly1305";
+
+/// The length of an XChaCha20-Poly1305 nonce in bytes.
+const NONCE_LEN: usize = 24;
+
+/// `Key` is a 256-bit secret shared with whoever may read the encrypted zblobs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Key([u8; 32]);
+
+impl Key {
+    /// Generates a random key.
+    pub fn generate() -> Self {
+        Self(XChaCha20Poly1305::generate_key(&mut OsRng).into())
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Parses a key encoded with `to_base64`.
+    pub fn from_base64(encoded: &str) -> Option<Self> {
+        let bytes = general_purpose::STANDARD.decode(encoded).ok()?;
+        Some(Self(bytes.try_into().ok()?))
+    }
+
+    pub fn to_base64(&self) -> String {
+        general_purpose::STANDARD.encode(self.0)
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(&self.0.into())
+    }
+}
+
+impl fmt::Debug for Key {
+    /// Keeps the secret out of logs.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Key(..)")
+    }
+}
+
+/// `KeyWrap` is the metadata stored as JSON in `Zblob::encryption`.
+///
+/// The payload is encrypted with a random content key, which is itself encrypted ("wrapped") with the shared `Key`,
+/// so the shared key never touches the payload and can be rotated by rewrapping the content key only.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyWrap {
+    pub algorithm: String,
+    /// The base64 nonce used to wrap the content key.
+    pub nonce: String,
+    /// The base64 content key, encrypted with the shared key.
+    pub wrapped_key: String,
+}
+
+/// `EncryptionError` describes why a zblob could not be encrypted or decrypted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncryptionError {
+    /// The zblob to encrypt is already encrypted.
+    AlreadyEncrypted,
+    /// The zblob to decrypt is not encrypted.
+    NotEncrypted,
+    /// The key-wrapping metadata is malformed or names an unknown algorithm.
+    InvalidMetadata,
+    /// The key does not unwrap the content key.
+    WrongKey,
+    /// The payload is not valid base64 or was tampered with.
+    InvalidPayload,
+}
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionError::AlreadyEncrypted => write!(f, "zblob is already encrypted"),
+            EncryptionError::NotEncrypted => write!(f, "zblob is not encrypted"),
+            EncryptionError::InvalidMetadata => write!(f, "invalid key-wrapping metadata"),
+            EncryptionError::WrongKey => write!(f, "key does not decrypt the zblob"),
+            EncryptionError::InvalidPayload => write!(f, "encrypted payload is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for EncryptionError {}
+
+/// Encrypts the data with the cipher under a random nonce, returns the nonce and the ciphertext.
+fn seal(cipher: &XChaCha20Poly1305, data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    (nonce.to_vec(), cipher.encrypt(&nonce, data).unwrap())
+}
+
+fn decode(encoded: &str) -> Option<Vec<u8>> {
+    general_purpose::STANDARD.decode(encoded).ok()
+}
+
+impl Zblob {
+    /// Returns true if the payload is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        !self.encryption.is_empty()
+    }
+
+    /// Parses the key-wrapping metadata of an encrypted zblob.
+    pub fn key_wrap(&self) -> Option<KeyWrap> {
+        serde_json::from_str(&self.encryption).ok()
+    }
+
+    /// Encrypts the compressed payload with a random content key wrapped with `key`.
+    ///
+    /// The title, description and other metadata stay readable, the CID is the CID of the encrypted payload.
+    pub fn encrypt(&self, key: &Key) -> Result<Zblob, EncryptionError> {
+        if self.is_encrypted() {
+            return Err(EncryptionError::AlreadyEncrypted);
+        }
+        let payload = decode(&self.base64_zipped).ok_or(EncryptionError::InvalidPayload)?;
+        let content_key = Key::generate();
+        let (nonce, ciphertext) = seal(&content_key.cipher(), &payload);
+        let (wrap_nonce, wrapped_key) = seal(&key.cipher(), content_key.as_bytes());
+        let key_wrap = KeyWrap {
+            algorithm: ALGORITHM.to_string(),
+            nonce: general_purpose::STANDARD.encode(wrap_nonce),
+            wrapped_key: general_purpose::STANDARD.encode(wrapped_key),
+        };
+        let mut encrypted = Zblob {
+            base64_zipped: general_purpose::STANDARD.encode([nonce, ciphertext].concat()),
+            encryption: serde_json::to_string(&key_wrap).unwrap(),
+            ..self.clone()
+        };
+        encrypted.ipfs_cid = encrypted.content_cid();
+        Ok(encrypted)
+    }
+
+    /// Decrypts the payload, returning the plain zblob with the CID it had before `encrypt`.
+    pub fn decrypt(&self, key: &Key) -> Result<Zblob, EncryptionError> {
+        if !self.is_encrypted() {
+            return Err(EncryptionError::NotEncrypted);
+        }
+        let key_wrap = self.key_wrap().ok_or(EncryptionError::InvalidMetadata)?;
+        let wrap_nonce = decode(&key_wrap.nonce).filter(|n| n.len() == NONCE_LEN);
+        let wrapped_key = decode(&key_wrap.wrapped_key);
+        let (wrap_nonce, wrapped_key) = match (wrap_nonce, wrapped_key) {
+            (Some(nonce), Some(wrapped_key)) if key_wrap.algorithm == ALGORITHM => (nonce, wrapped_key),
+            _ => return Err(EncryptionError::InvalidMetadata),
+        };
+        let content_key = key
+            .cipher()
+            .decrypt(XNonce::from_slice(&wrap_nonce), wrapped_key.as_slice())
+            .map_err(|_| EncryptionError::WrongKey)?;
+        let content_key = Key(content_key.try_into().map_err(|_| EncryptionError::InvalidMetadata)?);
+
+        let sealed = decode(&self.base64_zipped)
+            .filter(|s| s.len() >= NONCE_LEN)
+            .ok_or(EncryptionError::InvalidPayload)?;
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let payload = content_key
+            .cipher()
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| EncryptionError::InvalidPayload)?;
+        let mut decrypted = Zblob {
+            base64_zipped: general_purpose::STANDARD.encode(payload),
+            encryption: "".to_string(),
+            ..self.clone()
+        };
+        decrypted.ipfs_cid = decrypted.content_cid();
+        Ok(decrypted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::petri_net::PetriNet;
+
+    use super::*;
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let plain = Zblob {
+            title: "philosophers".to_string(),
+            ..net.to_zblob()
+        };
+        let key = Key::generate();
+        let encrypted = plain.encrypt(&key).unwrap();
+        assert!(encrypted.is_encrypted() && !plain.is_encrypted());
+        assert_ne!(encrypted.ipfs_cid, plain.ipfs_cid);
+        assert_eq!(encrypted.title, "philosophers");
+        assert_eq!(encrypted.key_wrap().unwrap().algorithm, ALGORITHM);
+        assert!(encrypted.bundle().is_none() && encrypted.try_to_net().is_none());
+        assert_ne!(encrypted.base64_zipped, plain.encrypt(&key).unwrap().base64_zipped);
+
+        let decrypted = encrypted.decrypt(&key).unwrap();
+        assert_eq!(decrypted.ipfs_cid, plain.ipfs_cid);
+        assert_eq!(decrypted.to_net().to_json().unwrap(), net.to_json().unwrap());
+        assert_eq!(Key::from_base64(&key.to_base64()), Some(key.clone()));
+        assert_eq!(format!("{:?}", key), "Key(..)");
+    }
+
+    #[test]
+    fn test_decrypt_errors() {
+        let plain = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap().to_zblob();
+        let key = Key::generate();
+        let encrypted = plain.encrypt(&key).unwrap();
+        assert_eq!(plain.decrypt(&key).unwrap_err(), EncryptionError::NotEncrypted);
+        assert_eq!(encrypted.encrypt(&key).unwrap_err(), EncryptionError::AlreadyEncrypted);
+        assert_eq!(
+            encrypted.decrypt(&Key::generate()).unwrap_err(),
+            EncryptionError::WrongKey
+        );
+
+        let mut tampered = encrypted.clone();
+        tampered.base64_zipped = general_purpose::STANDARD.encode(vec![0u8; 64]);
+        assert_eq!(tampered.decrypt(&key).unwrap_err(), EncryptionError::InvalidPayload);
+
+        let mut unknown = encrypted.clone();
+        unknown.encryption = unknown.encryption.replace(ALGORITHM, "rot13");
+        assert_eq!(unknown.decrypt(&key).unwrap_err(), EncryptionError::InvalidMetadata);
+    }
+}
@@ -0,0 +1,718 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::capacity::Capacity;
+
+/// Vector is a type alias for a vector of 32-bit integers.
+/// It is used to represent the state of a state machine and the delta of each transition or inhibitor.
+pub type Vector = Vec<i32>;
+
+/// ModelType is an enum that represents the type of model.
+/// It is used to determine the type of state machine to use.
+/// The possible values are `PetriNet`, `Elementary`, and `Workflow`.
+/// The default value is `PetriNet`.
+/// The `Elementary` model is a simplified version of the `PetriNet` model.
+/// The `Workflow` model is a simplified version of the `Elementary` model.
+/// The `PetriNet` model is the most complex and general model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ModelType {
+    PetriNet,
+    Elementary,
+    Workflow,
+}
+
+/// ReentryPolicy decides when a workflow transition may fire into the place that is already marked.
+///
+/// Workflow models hold a single token, so firing into the marked place overflows its implicit capacity of one.
+/// When reentry is allowed such an overflow is accepted and the marking is clamped back to one token,
+/// which lets a task be retried or restarted without an explicit reset transition.
+/// Other model types never reenter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReentryPolicy {
+    /// Reentry is never allowed.
+    Never,
+    /// Reentry is allowed for the transitions that declare it.
+    #[default]
+    PerTransition,
+    /// Reentry is allowed for every transition.
+    Always,
+}
+
+impl ReentryPolicy {
+    /// Checks if a transition declaring `allow_reentry` may fire into the marked place under this policy.
+    pub fn allows(&self, allow_reentry: bool) -> bool {
+        match self {
+            ReentryPolicy::Never => false,
+            ReentryPolicy::PerTransition => allow_reentry,
+            ReentryPolicy::Always => true,
+        }
+    }
+}
+
+/// `SemanticsConfig` composes the firing rules that each model type presets.
+///
+/// The presets are available as `SemanticsConfig::PETRI_NET`, `ELEMENTARY` and `WORKFLOW`, or by the name
+/// of their model type with `SemanticsConfig::named`. Mixing the rules gives hybrid models, such as
+/// multi-token places that may still be retried, or a single active place holding any number of tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticsConfig {
+    /// The capacity of every place, replacing the declared ones, or None to keep them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub place_capacity: Option<Capacity>,
+    /// Requires every firing to leave exactly one place marked.
+    #[serde(default)]
+    pub single_active_place: bool,
+    /// Accepts a reentering firing that overflows a place by clamping each place back between zero
+    /// and its capacity, when the reentry policy allows it.
+    #[serde(default)]
+    pub retry: bool,
+    /// Decides which transitions may retry.
+    #[serde(default)]
+    pub reentry: ReentryPolicy,
+}
+
+impl SemanticsConfig {
+    /// Places hold any number of tokens up to their capacity.
+    pub const PETRI_NET: Self = Self {
+        place_capacity: None,
+        single_active_place: false,
+        retry: false,
+        reentry: ReentryPolicy::PerTransition,
+    };
+
+    /// Places hold at most one token and exactly one place is marked.
+    pub const ELEMENTARY: Self = Self {
+        place_capacity: Some(Capacity::Bounded(1)),
+        single_active_place: true,
+        ..Self::PETRI_NET
+    };
+
+    /// Like `ELEMENTARY`, with the transitions declaring reentry allowed to retry.
+    pub const WORKFLOW: Self = Self {
+        retry: true,
+        ..Self::ELEMENTARY
+    };
+
+    /// Returns the preset of the model type.
+    pub fn preset(model_type: &ModelType) -> Self {
+        match model_type {
+            ModelType::PetriNet => Self::PETRI_NET,
+            ModelType::Elementary => Self::ELEMENTARY,
+            ModelType::Workflow => Self::WORKFLOW,
+        }
+    }
+
+    /// Returns the preset named like a model type in a model file, such as `"workflow"`.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "petriNet" => Some(Self::PETRI_NET),
+            "elementary" => Some(Self::ELEMENTARY),
+            "workflow" => Some(Self::WORKFLOW),
+            _ => None,
+        }
+    }
+
+    /// Returns the config with the given reentry policy.
+    pub fn with_reentry(self, reentry: ReentryPolicy) -> Self {
+        Self { reentry, ..self }
+    }
+
+    /// Checks if a firing is plain vector addition, so that transitions may fire concurrently.
+    pub fn is_concurrent(&self) -> bool {
+        !self.single_active_place && !self.retry
+    }
+}
+
+/// `GuardKind` tells whether a guard enables or blocks a transition once its threshold is reached.
+///
+/// A guard tests a single place against a threshold of `weight * multiple` tokens and never moves tokens.
+///
+/// | kind    | tokens < threshold | tokens >= threshold |
+/// |---------|--------------------|---------------------|
+/// | read    | blocks             | allows              |
+/// | inhibit | allows             | blocks              |
+///
+/// Capacities play no part in guard evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GuardKind {
+    /// The transition is enabled only once the place holds at least the threshold.
+    Read,
+    /// The transition is blocked once the place holds at least the threshold.
+    Inhibit,
+}
+
+impl GuardKind {
+    /// Checks if a guard of this kind blocks the transition when the place holds `tokens`.
+    ///
+    /// The threshold is wider than a token count, as `weight * multiple` may not fit in an i32.
+    pub fn blocks(&self, tokens: i32, threshold: i64) -> bool {
+        let reached = tokens as i64 >= threshold;
+        match self {
+            GuardKind::Read => !reached,
+            GuardKind::Inhibit => reached,
+        }
+    }
+}
+
+/// `Threshold` is a guard resolved to the offset of its place, as held by a `Machine`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Threshold {
+    pub place: usize,
+    pub weight: i32,
+    pub kind: GuardKind,
+}
+
+impl Threshold {
+    /// Checks if the guard blocks the transition in the given state.
+    pub fn blocks(&self, state: &[i32], multiple: i32) -> bool {
+        let tokens = state.get(self.place).copied().unwrap_or(0);
+        self.kind.blocks(tokens, self.weight as i64 * multiple as i64)
+    }
+}
+
+/// `Transfer` is a reset or transfer arc resolved to the offsets of its places.
+///
+/// When its transition fires, the tokens left in `from` by the ordinary arcs are removed, and a transfer arc
+/// adds `weight` times as many to `to`. A reset arc has no `to`. Transfers apply in order, so a later
+/// transfer from the same place finds it empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transfer {
+    pub from: usize,
+    pub to: Option<usize>,
+    pub weight: i32,
+}
+
+/// `Overflow` is the error of a change of a place that does not fit in an i32.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow;
+
+/// Returns the change of each place when a transition fires `multiple` times in the state, transfers included.
+pub fn transfer_delta(
+    state: &[i32],
+    delta: &Vector,
+    multiple: i32,
+    transfers: &[Transfer],
+) -> Result<Vector, Overflow> {
+    let mut total: Vec<i64> = (0..state.len()).map(|i| *delta.get(i).unwrap_or(&0) as i64 * multiple as i64).collect();
+    for transfer in transfers {
+        let Some(tokens) = state.get(transfer.from) else {
+            continue;
+        };
+        let left = (*tokens as i64 + total[transfer.from]).max(0);
+        total[transfer.from] -= left;
+        if let Some(to) = transfer.to.filter(|to| *to < total.len()) {
+            total[to] = left
+                .checked_mul(transfer.weight as i64)
+                .and_then(|moved| total[to].checked_add(moved))
+                .ok_or(Overflow)?;
+        }
+    }
+    total.into_iter().map(|d| i32::try_from(d).map_err(|_| Overflow)).collect()
+}
+
+/// `MarkingArc` is an arc with a weight computed from the marking, resolved to the offsets of its places.
+///
+/// Its weight is `numerator / denominator` of the tokens of `of` before firing, rounded down and capped by `max`,
+/// so it never exceeds those tokens. A consuming arc removes the weight from `place`, otherwise it adds it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkingArc {
+    pub place: usize,
+    pub consume: bool,
+    pub of: usize,
+    pub numerator: i32,
+    pub denominator: i32,
+    pub max: Option<i32>,
+}
+
+impl MarkingArc {
+    /// Returns the weight of the arc in the state.
+    ///
+    /// A weight that does not fit in an i32 saturates.
+    pub fn weight(&self, state: &[i32]) -> i32 {
+        self.exact_weight(state).clamp(i32::MIN as i64, i32::MAX as i64) as i32
+    }
+
+    fn exact_weight(&self, state: &[i32]) -> i64 {
+        let tokens = state.get(self.of).copied().unwrap_or(0).max(0) as i64;
+        let weight = tokens * self.numerator as i64 / self.denominator.max(1) as i64;
+        self.max.map_or(weight, |max| weight.min(max as i64))
+    }
+}
+
+/// Returns the delta to add `multiple` times to the state with the marking arcs and transfers folded in, along
+/// with that multiple, or None if there are neither and the delta applies as is.
+///
+/// Marking arcs are weighted once per firing and repeat with the multiple like ordinary arcs,
+/// transfers then take the content left after all the firings, see `transfer_delta`.
+/// Fails if the change of a place does not fit in an i32.
+pub fn marking_delta(
+    state: &[i32],
+    delta: &Vector,
+    multiple: i32,
+    arcs: &[MarkingArc],
+    transfers: &[Transfer],
+) -> Result<Option<(Vector, i32)>, Overflow> {
+    if arcs.is_empty() && transfers.is_empty() {
+        return Ok(None);
+    }
+    let mut delta = delta.clone();
+    delta.resize(delta.len().max(state.len()), 0);
+    for arc in arcs {
+        let weight = arc.exact_weight(state);
+        if let Some(d) = delta.get_mut(arc.place) {
+            let sum = *d as i64 + if arc.consume { -weight } else { weight };
+            *d = i32::try_from(sum).map_err(|_| Overflow)?;
+        }
+    }
+    if transfers.is_empty() {
+        return Ok(Some((delta, multiple)));
+    }
+    Ok(Some((transfer_delta(state, &delta, multiple, transfers)?, 1)))
+}
+
+/// `Transition` is a transition of a `Machine`, addressed by its index.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transition {
+    pub label: String,
+    pub delta: Vector,
+    pub guards: Vec<Threshold>,
+    pub allow_reentry: bool,
+    #[serde(default)]
+    pub transfers: Vec<Transfer>,
+    #[serde(default)]
+    pub marking_arcs: Vec<MarkingArc>,
+}
+
+/// `Machine` is the executable form of a state machine without roles, attributes or labels of places.
+///
+/// It builds without the standard library, so firmware can fire the transitions of a model exported
+/// by `StateMachine::to_machine` and shipped in any serde format. Transitions are numbered in label order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Machine {
+    pub model_type: ModelType,
+    pub initial: Vector,
+    pub capacity: Vec<Capacity>,
+    pub transitions: Vec<Transition>,
+    #[serde(default)]
+    pub reentry: ReentryPolicy,
+    /// The semantics replacing those of the model type and the reentry policy, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub semantics: Option<SemanticsConfig>,
+}
+
+impl Machine {
+    /// Resolves the label of a transition to its index.
+    ///
+    /// A deserialized machine may list its transitions in any order, so the lookup does not rely on label order.
+    pub fn id(&self, label: &str) -> Option<usize> {
+        self.transitions.iter().position(|t| t.label == label)
+    }
+
+    /// Fires the transition, returns None if the index is out of range or the state does not have one entry per place.
+    pub fn fire(&self, state: &Vector, id: usize, multiple: i32) -> Option<Outcome> {
+        let transition = self.transitions.get(id)?;
+        if state.len() != self.capacity.len() {
+            return None;
+        }
+        if transition.guards.iter().any(|g| g.blocks(state, multiple)) {
+            return Some(Outcome::inhibited(state));
+        }
+        let arcs = &transition.marking_arcs;
+        let Ok(folded) = marking_delta(state, &transition.delta, multiple, arcs, &transition.transfers) else {
+            return Some(Outcome::overflowed(state));
+        };
+        let (delta, multiple) = match &folded {
+            Some((delta, multiple)) => (delta, *multiple),
+            None => (&transition.delta, multiple),
+        };
+        if let Some(config) = &self.semantics {
+            let allow_reentry = config.reentry.allows(transition.allow_reentry);
+            return Some(configured_outcome(config, &self.capacity, state, delta, multiple, allow_reentry));
+        }
+        let allow_reentry = self.reentry.allows(transition.allow_reentry);
+        Some(outcome(&self.model_type, &self.capacity, state, delta, multiple, allow_reentry))
+    }
+
+    /// Checks if the transition can fire in the given state.
+    pub fn is_enabled(&self, state: &Vector, id: usize, multiple: i32) -> bool {
+        self.fire(state, id, multiple).is_some_and(|o| o.ok)
+    }
+}
+
+/// `Outcome` is the result of firing a transition, without the role that fired it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub output: Vector,
+    pub ok: bool,
+    pub inhibited: bool,
+    pub overflow: bool,
+    pub underflow: bool,
+}
+
+impl Outcome {
+    /// The outcome of an inhibited transition, which returns the input state unchanged.
+    pub fn inhibited(state: &Vector) -> Self {
+        Self {
+            output: state.clone(),
+            ok: false,
+            inhibited: true,
+            overflow: false,
+            underflow: false,
+        }
+    }
+
+    /// The outcome of a transition whose change of a place does not fit in an i32, which returns the input
+    /// state unchanged.
+    pub fn overflowed(state: &Vector) -> Self {
+        Self {
+            output: state.clone(),
+            ok: false,
+            inhibited: false,
+            overflow: true,
+            underflow: false,
+        }
+    }
+}
+
+/// Adds `multiple` times `delta` to `state` and checks the result against the capacities, see `vector::add`.
+pub use crate::vector::add as vector_add;
+
+/// Fires an unguarded transition according to the model type, `allow_reentry` only applies to workflows.
+pub fn outcome(
+    model_type: &ModelType,
+    capacity: &[Capacity],
+    state: &Vector,
+    delta: &Vector,
+    multiple: i32,
+    allow_reentry: bool,
+) -> Outcome {
+    match model_type {
+        ModelType::PetriNet => petri_net_outcome(capacity, state, delta, multiple),
+        ModelType::Elementary => elementary_outcome(capacity, state, delta, multiple),
+        ModelType::Workflow => workflow_outcome(capacity, state, delta, multiple, allow_reentry),
+    }
+}
+
+/// Fires an unguarded transition according to a composed semantics, see `SemanticsConfig`.
+///
+/// `allow_reentry` tells whether the reentry policy of the config lets the transition retry. A place capacity
+/// forced by the config replaces the given capacities.
+pub fn configured_outcome(
+    config: &SemanticsConfig,
+    capacity: &[Capacity],
+    state: &Vector,
+    delta: &Vector,
+    multiple: i32,
+    allow_reentry: bool,
+) -> Outcome {
+    let forced;
+    let capacity = match config.place_capacity {
+        Some(place_capacity) => {
+            forced = vec![place_capacity; state.len()];
+            &forced[..]
+        }
+        None => capacity,
+    };
+    let (output, ok, overflow, underflow) = vector_add(capacity, state, delta, multiple);
+    let single = |output: &Vector| !config.single_active_place || output.iter().filter(|&x| *x > 0).count() == 1;
+    if config.retry && overflow && allow_reentry {
+        let retried: Vector = output
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let limit = capacity.get(i).and_then(Capacity::limit).unwrap_or(i32::MAX);
+                (*x).clamp(0, limit)
+            })
+            .collect();
+        if single(&retried) {
+            return Outcome {
+                output: retried,
+                ok: true,
+                inhibited: false,
+                overflow: false,
+                underflow,
+            };
+        }
+    }
+    let ok = ok && single(&output);
+    Outcome {
+        output,
+        ok,
+        inhibited: false,
+        overflow,
+        underflow,
+    }
+}
+
+/// Fires a petri-net transition, valid as long as no place underflows or exceeds its capacity.
+pub fn petri_net_outcome(capacity: &[Capacity], state: &Vector, delta: &Vector, multiple: i32) -> Outcome {
+    let (output, ok, overflow, underflow) = vector_add(capacity, state, delta, multiple);
+    Outcome {
+        output,
+        ok,
+        inhibited: false,
+        overflow,
+        underflow,
+    }
+}
+
+/// Fires an elementary transition, which must also leave exactly one place marked.
+pub fn elementary_outcome(capacity: &[Capacity], state: &Vector, delta: &Vector, multiple: i32) -> Outcome {
+    let (output, ok, overflow, underflow) = vector_add(capacity, state, delta, multiple);
+    let output_state_count = output.iter().filter(|&x| *x > 0).count();
+    let elementary_ok = ok && output_state_count == 1;
+    Outcome {
+        output,
+        ok: elementary_ok,
+        inhibited: false,
+        overflow,
+        underflow,
+    }
+}
+
+/// Fires a workflow transition, which behaves like an elementary one unless it reenters.
+///
+/// A reentering firing overflows the marked place, it is accepted when it leaves exactly one place
+/// marked once every count is clamped to zero or one, and `allow_reentry` is set.
+pub fn workflow_outcome(
+    capacity: &[Capacity],
+    state: &Vector,
+    delta: &Vector,
+    multiple: i32,
+    allow_reentry: bool,
+) -> Outcome {
+    let (output, ok, overflow, underflow) = vector_add(capacity, state, delta, multiple);
+    let workflow_output = output
+        .iter()
+        .map(|x| {
+            match x {
+                -1 => 0, // allow retry / reentry
+                0 => 0,
+                1 => 1,
+                2 => 1, // allow reentry
+                _ => 1, // no other values allowed
+            }
+        })
+        .collect::<Vec<i32>>();
+    let output_state_count = workflow_output.iter().filter(|&x| *x > 0).count();
+    if overflow && output_state_count == 1 && allow_reentry {
+        return Outcome {
+            output: workflow_output,
+            ok: true,
+            inhibited: false,
+            overflow: false,
+            underflow,
+        };
+    }
+    let workflow_ok = ok && output_state_count == 1;
+
+    Outcome {
+        output,
+        ok: workflow_ok,
+        inhibited: false,
+        overflow,
+        underflow,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    use super::*;
+
+    fn machine(model_type: ModelType) -> Machine {
+        let transition = |label: &str, delta: Vector| Transition {
+            label: label.to_string(),
+            delta,
+            ..Default::default()
+        };
+        Machine {
+            model_type,
+            initial: vec![1, 0],
+            capacity: vec![Capacity::Unbounded, Capacity::Bounded(1)],
+            transitions: vec![
+                Transition {
+                    guards: vec![Threshold {
+                        place: 1,
+                        weight: 1,
+                        kind: GuardKind::Inhibit,
+                    }],
+                    ..transition("start", vec![-1, 1])
+                },
+                transition("undo", vec![1, -1]),
+            ],
+            reentry: ReentryPolicy::default(),
+            semantics: None,
+        }
+    }
+
+    #[test]
+    fn test_machine_fire() {
+        let m = machine(ModelType::PetriNet);
+        assert_eq!((m.id("start"), m.id("undo"), m.id("stop")), (Some(0), Some(1), None));
+        let mut unsorted = m.clone();
+        unsorted.transitions.reverse();
+        assert_eq!((unsorted.id("start"), unsorted.id("undo")), (Some(1), Some(0)));
+
+        let fired = m.fire(&m.initial, 0, 1).unwrap();
+        assert!(fired.ok);
+        assert_eq!(fired.output, vec![0, 1]);
+        assert!(m.fire(&fired.output, 0, 1).unwrap().inhibited);
+        assert!(m.fire(&m.initial, 1, 1).unwrap().underflow);
+        assert!(!m.is_enabled(&m.initial, 0, 2));
+        assert_eq!(m.fire(&m.initial, 2, 1), None);
+        assert_eq!(m.fire(&vec![1], 0, 1), None);
+    }
+
+    #[test]
+    fn test_transfers() {
+        let transfers = [
+            Transfer {
+                from: 0,
+                to: Some(2),
+                weight: 2,
+            },
+            Transfer {
+                from: 1,
+                to: None,
+                weight: 1,
+            },
+        ];
+        assert_eq!(transfer_delta(&[5, 3, 1], &vec![-1, 1, 0], 1, &transfers), Ok(vec![-5, -3, 8]));
+        assert_eq!(transfer_delta(&[5, 3, 1], &vec![-1, 1, 0], 2, &transfers), Ok(vec![-5, -3, 6]));
+        assert_eq!(transfer_delta(&[0, 0, 0], &vec![-1, 0, 0], 1, &transfers), Ok(vec![-1, 0, 0]));
+        assert_eq!(transfer_delta(&[i32::MAX, 0, 0], &vec![0, 0, 0], 1, &transfers), Err(Overflow));
+        assert_eq!(transfer_delta(&[0, 0, 0], &vec![0, 0, i32::MAX], 2, &transfers), Err(Overflow));
+
+        let mut m = machine(ModelType::PetriNet);
+        m.transitions[1].transfers = transfers[1..].to_vec();
+        assert_eq!(m.fire(&vec![0, 3], 1, 1).unwrap().output, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_marking_arcs() {
+        let half = MarkingArc {
+            place: 0,
+            consume: true,
+            of: 0,
+            numerator: 1,
+            denominator: 2,
+            max: None,
+        };
+        let batch = MarkingArc {
+            place: 1,
+            consume: false,
+            max: Some(3),
+            ..half
+        };
+        assert_eq!(half.weight(&[7, 0]), 3);
+        assert_eq!(batch.weight(&[7, 0]), 3);
+        assert_eq!(batch.weight(&[-2, 0]), 0);
+        assert_eq!(marking_delta(&[7, 0], &vec![0, 0], 1, &[], &[]), Ok(None));
+        assert_eq!(marking_delta(&[7, 0], &vec![0, 0], 2, &[half, batch], &[]), Ok(Some((vec![-3, 3], 2))));
+        let reset = Transfer {
+            from: 0,
+            to: None,
+            weight: 1,
+        };
+        assert_eq!(marking_delta(&[7, 0], &vec![0, 0], 1, &[half], &[reset]), Ok(Some((vec![-7, 0], 1))));
+        let double = MarkingArc {
+            numerator: 2,
+            denominator: 1,
+            max: None,
+            ..batch
+        };
+        assert_eq!(double.weight(&[i32::MAX, 0]), i32::MAX);
+        assert_eq!(marking_delta(&[i32::MAX, 0], &vec![0, 0], 1, &[double], &[]), Err(Overflow));
+
+        let mut m = machine(ModelType::PetriNet);
+        m.capacity = vec![Capacity::Unbounded; 2];
+        m.transitions[1].delta = vec![0, 0];
+        m.transitions[1].marking_arcs = vec![MarkingArc {
+            place: 1,
+            of: 1,
+            max: None,
+            ..half
+        }];
+        assert_eq!(m.fire(&vec![0, 5], 1, 1).unwrap().output, vec![0, 3]);
+        assert!(!m.is_enabled(&vec![0, 5], 1, 3));
+        m.transitions[1].marking_arcs[0].numerator = 4;
+        let overflowed = m.fire(&vec![0, i32::MAX], 1, 1).unwrap();
+        assert!(overflowed.overflow && !overflowed.ok);
+        assert_eq!(overflowed.output, vec![0, i32::MAX]);
+    }
+
+    #[test]
+    fn test_model_types() {
+        let capacity = [Capacity::Unbounded; 2];
+        let state = vec![1, 0];
+        assert!(petri_net_outcome(&capacity, &state, &vec![1, 1], 1).ok);
+        assert!(!elementary_outcome(&capacity, &state, &vec![1, 1], 1).ok);
+        assert!(elementary_outcome(&capacity, &state, &vec![-1, 1], 1).ok);
+
+        let bounded = [Capacity::Bounded(1); 2];
+        assert!(!workflow_outcome(&bounded, &state, &vec![1, 0], 1, false).ok);
+        let reentered = workflow_outcome(&bounded, &state, &vec![1, 0], 1, true);
+        assert_eq!((reentered.ok, reentered.output), (true, vec![1, 0]));
+        assert!(ReentryPolicy::Always.allows(false) && !ReentryPolicy::Never.allows(true));
+    }
+
+    #[test]
+    fn test_semantics_presets() {
+        let bounded = [Capacity::Bounded(1); 3];
+        let presets = [ModelType::PetriNet, ModelType::Elementary, ModelType::Workflow];
+        let states = [vec![1, 0, 0], vec![0, 1, 0], vec![1, 1, 0]];
+        let deltas = [vec![-1, 1, 0], vec![1, 0, 0], vec![0, -1, 1], vec![1, -1, 1]];
+        for model_type in &presets {
+            let config = SemanticsConfig::preset(model_type);
+            for state in &states {
+                for delta in &deltas {
+                    for allow_reentry in [false, true] {
+                        assert_eq!(
+                            configured_outcome(&config, &bounded, state, delta, 1, allow_reentry),
+                            outcome(model_type, &bounded, state, delta, 1, allow_reentry),
+                        );
+                    }
+                }
+            }
+        }
+        assert_eq!(SemanticsConfig::named("workflow"), Some(SemanticsConfig::WORKFLOW));
+        assert_eq!(SemanticsConfig::named("colored"), None);
+    }
+
+    #[test]
+    fn test_hybrid_semantics() {
+        // Multi-token places which may still be retried past their capacity.
+        let retrying = SemanticsConfig {
+            retry: true,
+            ..SemanticsConfig::PETRI_NET
+        };
+        let capacity = [Capacity::Bounded(3), Capacity::Unbounded];
+        let retried = configured_outcome(&retrying, &capacity, &vec![3, 2], &vec![1, 1], 1, true);
+        assert_eq!((retried.ok, retried.output), (true, vec![3, 3]));
+        assert!(!configured_outcome(&retrying, &capacity, &vec![3, 2], &vec![1, 1], 1, false).ok);
+        assert!(!retrying.is_concurrent());
+
+        // A single active place holding any number of tokens.
+        let single = SemanticsConfig {
+            single_active_place: true,
+            ..SemanticsConfig::PETRI_NET
+        };
+        let unbounded = [Capacity::Unbounded; 2];
+        assert!(configured_outcome(&single, &unbounded, &vec![0, 2], &vec![3, -2], 1, false).ok);
+        assert!(!configured_outcome(&single, &unbounded, &vec![0, 2], &vec![3, -1], 1, false).ok);
+
+        let mut m = machine(ModelType::PetriNet);
+        m.semantics = Some(single);
+        assert!(m.fire(&m.initial, 0, 1).unwrap().ok);
+        assert!(!m.is_enabled(&vec![2, 0], 0, 1));
+    }
+}
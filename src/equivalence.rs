@@ -0,0 +1,340 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::analysis::{explore, Limits};
+use crate::petri_net::PetriNet;
+use crate::vasm::StateMachine;
+
+/// `Renaming` maps the labels of one net onto the labels of an isomorphic net.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Renaming {
+    pub places: BTreeMap<String, String>,
+    pub transitions: BTreeMap<String, String>,
+}
+
+/// `Equivalence` is the strongest relation found between two nets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Equivalence {
+    /// The nets are the same up to the renaming of their nodes, layout and display names are ignored.
+    Isomorphic(Renaming),
+    /// The nets differ structurally, but their reachability graphs are strongly bisimilar
+    /// with transitions matched by label.
+    Bisimilar,
+    /// The nets are neither isomorphic nor, if checked, bisimilar.
+    Different,
+    /// The nets are not isomorphic and the limits were reached before bisimilarity was decided.
+    Unknown,
+}
+
+/// An arc as seen from one of its ends: (weight, inhibit, read).
+type Kind = (i32, bool, bool);
+
+/// The bipartite graph of a net with nodes indexed places first, then transitions, both in sorted order.
+struct Graph {
+    labels: Vec<String>,
+    places: usize,
+    colors: Vec<String>,
+    edges: HashMap<(usize, usize), Vec<Kind>>,
+    neighbours: Vec<BTreeSet<usize>>,
+}
+
+impl Graph {
+    fn new(net: &PetriNet) -> Self {
+        let mut places: Vec<&String> = net.places.keys().collect();
+        places.sort();
+        let mut transitions: Vec<&String> = net.transitions.keys().collect();
+        transitions.sort();
+        let labels: Vec<String> = places.iter().chain(&transitions).map(|l| l.to_string()).collect();
+        let index: HashMap<&String, usize> = labels.iter().enumerate().map(|(i, l)| (l, i)).collect();
+
+        let colors = places
+            .iter()
+            .map(|p| {
+                let place = &net.places[*p];
+                format!(
+                    "p{:?}{:?}{:?}",
+                    place.initial.unwrap_or(0),
+                    place.capacity.unwrap_or_default(),
+                    place.unit
+                )
+            })
+            .chain(transitions.iter().map(|t| format!("t{:?}", net.transitions[*t].role)))
+            .collect();
+
+        let mut edges: HashMap<(usize, usize), Vec<Kind>> = HashMap::new();
+        let mut neighbours = vec![BTreeSet::new(); labels.len()];
+        for arc in &net.arcs {
+            // an arc to a node that does not exist takes no part in the structure
+            let (Some(&s), Some(&t)) = (index.get(&arc.source), index.get(&arc.target)) else {
+                continue;
+            };
+            let kind = (
+                arc.weight.unwrap_or(1),
+                arc.inhibit.unwrap_or(false),
+                arc.read.unwrap_or(false),
+            );
+            edges.entry((s, t)).or_default().push(kind);
+            neighbours[s].insert(t);
+            neighbours[t].insert(s);
+        }
+        edges.values_mut().for_each(|kinds| kinds.sort());
+        Self {
+            labels,
+            places: places.len(),
+            colors,
+            edges,
+            neighbours,
+        }
+    }
+
+    fn between(&self, s: usize, t: usize) -> &[Kind] {
+        self.edges.get(&(s, t)).map_or(&[], Vec::as_slice)
+    }
+
+    fn signature(&self, node: usize, colors: &[usize]) -> (usize, Vec<(bool, usize, Vec<Kind>)>) {
+        let mut adjacent: Vec<(bool, usize, Vec<Kind>)> = self.neighbours[node]
+            .iter()
+            .flat_map(|n| {
+                [
+                    (true, colors[*n], self.between(node, *n).to_vec()),
+                    (false, colors[*n], self.between(*n, node).to_vec()),
+                ]
+            })
+            .collect();
+        adjacent.sort();
+        (colors[node], adjacent)
+    }
+}
+
+/// Refines the node colors of both graphs together until the number of colors is stable.
+fn refine(a: &Graph, b: &Graph) -> (Vec<usize>, Vec<usize>) {
+    let mut ids: BTreeMap<&String, usize> = BTreeMap::new();
+    for color in a.colors.iter().chain(&b.colors) {
+        let next = ids.len();
+        ids.entry(color).or_insert(next);
+    }
+    let mut ca: Vec<usize> = a.colors.iter().map(|c| ids[c]).collect();
+    let mut cb: Vec<usize> = b.colors.iter().map(|c| ids[c]).collect();
+    let mut count = ids.len();
+    loop {
+        let sa: Vec<_> = (0..ca.len()).map(|n| a.signature(n, &ca)).collect();
+        let sb: Vec<_> = (0..cb.len()).map(|n| b.signature(n, &cb)).collect();
+        let mut ids = BTreeMap::new();
+        for s in sa.iter().chain(&sb) {
+            let next = ids.len();
+            ids.entry(s).or_insert(next);
+        }
+        ca = sa.iter().map(|s| ids[s]).collect();
+        cb = sb.iter().map(|s| ids[s]).collect();
+        if ids.len() == count {
+            return (ca, cb);
+        }
+        count = ids.len();
+    }
+}
+
+/// Extends the partial mapping from the nodes of `a` to the nodes of `b` by backtracking.
+fn extend(
+    a: &Graph,
+    b: &Graph,
+    colors: &(Vec<usize>, Vec<usize>),
+    mapping: &mut Vec<usize>,
+    used: &mut [bool],
+) -> bool {
+    let x = mapping.len();
+    if x == a.labels.len() {
+        return true;
+    }
+    for y in 0..b.labels.len() {
+        if used[y] || colors.0[x] != colors.1[y] {
+            continue;
+        }
+        let consistent = a.between(x, x) == b.between(y, y)
+            && mapping
+                .iter()
+                .enumerate()
+                .all(|(u, v)| a.between(x, u) == b.between(y, *v) && a.between(u, x) == b.between(*v, y));
+        if consistent {
+            mapping.push(y);
+            used[y] = true;
+            if extend(a, b, colors, mapping, used) {
+                return true;
+            }
+            mapping.pop();
+            used[y] = false;
+        }
+    }
+    false
+}
+
+/// Finds a renaming of the places and transitions of `a` making it equal to `b`, ignoring layout.
+///
+/// Initial markings, capacities, units, roles and arc weights and kinds must match.
+pub fn isomorphism(a: &PetriNet, b: &PetriNet) -> Option<Renaming> {
+    if a.model_type != b.model_type
+        || a.places.len() != b.places.len()
+        || a.transitions.len() != b.transitions.len()
+        || a.arcs.len() != b.arcs.len()
+    {
+        return None;
+    }
+    let (mut a, mut b) = (a.clone(), b.clone());
+    a.populate_arc_attributes();
+    b.populate_arc_attributes();
+    let (ga, gb) = (Graph::new(&a), Graph::new(&b));
+    let colors = refine(&ga, &gb);
+    let (mut ha, mut hb) = (colors.0.clone(), colors.1.clone());
+    ha.sort();
+    hb.sort();
+    if ha != hb {
+        return None;
+    }
+
+    let mut mapping = Vec::with_capacity(ga.labels.len());
+    if !extend(&ga, &gb, &colors, &mut mapping, &mut vec![false; gb.labels.len()]) {
+        return None;
+    }
+    let mut renaming = Renaming::default();
+    for (x, y) in mapping.into_iter().enumerate() {
+        let pair = (ga.labels[x].clone(), gb.labels[y].clone());
+        if x < ga.places {
+            renaming.places.insert(pair.0, pair.1);
+        } else {
+            renaming.transitions.insert(pair.0, pair.1);
+        }
+    }
+    Some(renaming)
+}
+
+/// Checks if the reachability graphs of the nets are strongly bisimilar, with transitions matched by label.
+/// Returns None if either exploration was truncated by the limits.
+pub fn bisimilar(a: &PetriNet, b: &PetriNet, limits: Limits) -> Option<bool> {
+    let ga = explore(&StateMachine::from_model(&mut a.clone()), limits);
+    let gb = explore(&StateMachine::from_model(&mut b.clone()), limits);
+    if !ga.complete || !gb.complete {
+        return None;
+    }
+    // Both graphs are refined as a single transition system, the states of b follow the states of a.
+    let offset = ga.states.len();
+    let successors: Vec<Vec<(&String, usize)>> = ga
+        .successors
+        .iter()
+        .map(|s| s.iter().map(|(action, j)| (action, *j)).collect())
+        .chain(
+            gb.successors
+                .iter()
+                .map(|s| s.iter().map(|(action, j)| (action, j + offset)).collect()),
+        )
+        .collect();
+
+    let mut block = vec![0; successors.len()];
+    let mut count = 1;
+    loop {
+        let signatures: Vec<(usize, BTreeSet<(&String, usize)>)> = successors
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (block[i], s.iter().map(|(action, j)| (*action, block[*j])).collect()))
+            .collect();
+        let mut ids = BTreeMap::new();
+        for s in &signatures {
+            let next = ids.len();
+            ids.entry(s).or_insert(next);
+        }
+        block = signatures.iter().map(|s| ids[s]).collect();
+        if ids.len() == count {
+            return Some(block[0] == block[offset]);
+        }
+        count = ids.len();
+    }
+}
+
+/// Checks if the nets are structurally isomorphic up to renaming.
+pub fn equivalent(a: &PetriNet, b: &PetriNet) -> Equivalence {
+    match isomorphism(a, b) {
+        Some(renaming) => Equivalence::Isomorphic(renaming),
+        None => Equivalence::Different,
+    }
+}
+
+/// Checks if the nets are structurally isomorphic up to renaming, or else behaviorally equivalent
+/// exploring at most `limits.max_states` states of each net.
+pub fn equivalent_within(a: &PetriNet, b: &PetriNet, limits: Limits) -> Equivalence {
+    match (isomorphism(a, b), bisimilar(a, b, limits)) {
+        (Some(renaming), _) => Equivalence::Isomorphic(renaming),
+        (None, Some(true)) => Equivalence::Bisimilar,
+        (None, Some(false)) => Equivalence::Different,
+        (None, None) => Equivalence::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    fn renamed(net: &PetriNet) -> PetriNet {
+        let mut copy = net.clone();
+        let mut places: Vec<String> = copy.places.keys().cloned().collect();
+        places.sort();
+        for (i, p) in places.iter().enumerate() {
+            assert!(copy.rename_place(p, &format!("place{}", i)));
+            copy.places.get_mut(&format!("place{}", i)).unwrap().x += 40;
+        }
+        let mut transitions: Vec<String> = copy.transitions.keys().cloned().collect();
+        transitions.sort();
+        for (i, t) in transitions.iter().rev().enumerate() {
+            assert!(copy.rename_transition(t, &format!("action{}", i)));
+        }
+        copy.arcs.reverse();
+        copy
+    }
+
+    #[test]
+    fn test_isomorphic_up_to_renaming() {
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let copy = renamed(&net);
+        let Equivalence::Isomorphic(renaming) = equivalent(&net, &copy) else {
+            panic!("expected an isomorphism")
+        };
+        assert_eq!(renaming.places.len(), 15);
+        assert_eq!(renaming.transitions.len(), 10);
+
+        let mut different = copy.clone();
+        different.arcs[0].weight = Some(2);
+        assert_eq!(equivalent(&net, &different), Equivalence::Different);
+
+        let mut dangling = copy.clone();
+        dangling.add_arc("place0", "missing", Some(1), None, None, None, None);
+        assert_eq!(equivalent(&net, &dangling), Equivalence::Different);
+        assert!(matches!(equivalent(&dangling, &dangling), Equivalence::Isomorphic(_)));
+    }
+
+    #[test]
+    fn test_bisimilar_nets() {
+        fn toggle(p: &mut dyn crate::dsl::FlowDsl) {
+            p.cell("on", Option::from(1), None, 0, 0);
+            p.cell("off", None, None, 0, 0);
+            p.func("tick", "default", 0, 0);
+            p.func("tock", "default", 0, 0);
+            p.arrow("on", "tick", 1);
+            p.arrow("tick", "off", 1);
+            p.arrow("off", "tock", 1);
+            p.arrow("tock", "on", 1);
+        }
+        let mut one = PetriNet::new();
+        one.declare(toggle);
+        // The extra place always holds its token, so the read arc never blocks tick.
+        let mut two = one.clone();
+        two.add_place("power", 2, Some(1), None, 0, 0);
+        two.add_arc("tick", "power", Some(1), None, None, Some(true), None);
+        assert_eq!(equivalent(&one, &two), Equivalence::Different);
+        assert_eq!(equivalent_within(&one, &two, Limits::default()), Equivalence::Bisimilar);
+
+        let mut three = one.clone();
+        three.arcs.pop();
+        assert_eq!(
+            equivalent_within(&one, &three, Limits::default()),
+            Equivalence::Different
+        );
+    }
+}
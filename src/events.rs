@@ -0,0 +1,207 @@
+use std::future::Future;
+
+use serde::{Deserialize, Serialize};
+
+use crate::vasm::{Transaction, Vector};
+
+/// The version of the `TransactionEvent` schema, raised on every incompatible change.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The Avro schema of `TransactionEvent`, for brokers backed by a schema registry.
+pub const AVRO_SCHEMA: &str = r#"{
+  "type": "record",
+  "name": "TransactionEvent",
+  "namespace": "dev.pflow.metamodel",
+  "fields": [
+    { "name": "schemaVersion", "type": "int" },
+    { "name": "modelCid", "type": "string" },
+    { "name": "caseId", "type": "string" },
+    { "name": "action", "type": "string" },
+    { "name": "multiple", "type": "int" },
+    { "name": "role", "type": "string" },
+    { "name": "output", "type": { "type": "array", "items": "int" } }
+  ]
+}"#;
+
+/// `TransactionEvent` is published for every successful firing of a case, see `AVRO_SCHEMA`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionEvent {
+    pub schema_version: u32,
+    /// The CID of the zblob of the model the case runs.
+    pub model_cid: String,
+    pub case_id: String,
+    pub action: String,
+    pub multiple: i32,
+    pub role: String,
+    /// The marking of the case after the firing.
+    pub output: Vector,
+}
+
+impl TransactionEvent {
+    /// Encodes the event as JSON.
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap()
+    }
+}
+
+/// `Broker` is a message broker the events are published to.
+///
+/// NATS is supported behind the `nats` feature. Other brokers, such as a Kafka producer,
+/// are supported by implementing `publish` with their client.
+pub trait Broker {
+    type Error;
+
+    /// Publishes the payload to the topic, `key` is the case id so the events of a case stay in order.
+    fn publish(&self, topic: &str, key: &str, payload: Vec<u8>)
+        -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
+
+/// `TransactionPublisher` publishes the successful transactions of the cases of a model to a topic.
+#[derive(Debug, Clone)]
+pub struct TransactionPublisher<B> {
+    broker: B,
+    topic: String,
+    model_cid: String,
+}
+
+impl<B: Broker> TransactionPublisher<B> {
+    pub fn new(broker: B, topic: &str, model_cid: &str) -> Self {
+        Self {
+            broker,
+            topic: topic.to_string(),
+            model_cid: model_cid.to_string(),
+        }
+    }
+
+    pub fn broker(&self) -> &B {
+        &self.broker
+    }
+
+    /// Returns the event of a successful transaction, or None if the transaction failed.
+    pub fn event(&self, case_id: &str, action: &str, multiple: i32, tx: &Transaction) -> Option<TransactionEvent> {
+        tx.is_ok().then(|| TransactionEvent {
+            schema_version: SCHEMA_VERSION,
+            model_cid: self.model_cid.clone(),
+            case_id: case_id.to_string(),
+            action: action.to_string(),
+            multiple,
+            role: tx.role.clone(),
+            output: tx.output.clone(),
+        })
+    }
+
+    /// Publishes the transaction as JSON if it succeeded, returns true if an event was published.
+    pub async fn publish(
+        &self,
+        case_id: &str,
+        action: &str,
+        multiple: i32,
+        tx: &Transaction,
+    ) -> Result<bool, B::Error> {
+        match self.event(case_id, action, multiple, tx) {
+            Some(event) => {
+                self.broker.publish(&self.topic, case_id, event.to_json()).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Publishes to the subject named by the topic, with the case id in the `Pflow-Case-Id` header.
+#[cfg(feature = "nats")]
+impl Broker for async_nats::Client {
+    type Error = async_nats::PublishError;
+
+    async fn publish(&self, topic: &str, key: &str, payload: Vec<u8>) -> Result<(), Self::Error> {
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert("Pflow-Case-Id", key);
+        self.publish_with_headers(topic.to_string(), headers, payload.into())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Wake, Waker};
+
+    use crate::cases::CaseManager;
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::petri_net::PetriNet;
+    use crate::vasm::StateMachine;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        messages: Mutex<Vec<(String, String, Vec<u8>)>>,
+    }
+
+    impl Broker for &Recorder {
+        type Error = ();
+
+        async fn publish(&self, topic: &str, key: &str, payload: Vec<u8>) -> Result<(), ()> {
+            self.messages
+                .lock()
+                .unwrap()
+                .push((topic.to_string(), key.to_string(), payload));
+            Ok(())
+        }
+    }
+
+    struct Noop;
+
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    /// Runs a future that never waits.
+    fn ready<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(Noop));
+        match pin!(future).poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future is pending"),
+        }
+    }
+
+    #[test]
+    fn test_publish_successful_transactions() {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let cid = "zb2rhbJgSpkiifamgPLnyfEDxRKRBjPru2ojyYSBMitPNjXTx";
+        let mut cases = CaseManager::new(StateMachine::from_model(&mut net));
+        cases.create("order-1");
+        let recorder = Recorder::default();
+        let publisher = TransactionPublisher::new(&recorder, "pflow.transactions", cid);
+
+        let fired = cases.fire_all(|_, _| true, "eat1");
+        assert!(ready(publisher.publish("order-1", "eat1", 1, &fired["order-1"])).unwrap());
+        let fired = cases.fire_all(|_, _| true, "eat1");
+        assert!(!ready(publisher.publish("order-1", "eat1", 1, &fired["order-1"])).unwrap());
+
+        let messages = recorder.messages.lock().unwrap();
+        assert_eq!(messages.len(), 1);
+        let (topic, key, payload) = &messages[0];
+        assert_eq!((topic.as_str(), key.as_str()), ("pflow.transactions", "order-1"));
+        let event: TransactionEvent = serde_json::from_slice(payload).unwrap();
+        assert_eq!(event.schema_version, SCHEMA_VERSION);
+        assert_eq!(
+            (event.model_cid.as_str(), event.action.as_str()),
+            (cid, "eat1")
+        );
+        assert_eq!(&event.output, cases.state("order-1").unwrap().as_vector());
+
+        let schema: serde_json::Value = serde_json::from_str(AVRO_SCHEMA).unwrap();
+        let fields: Vec<&str> = schema["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["name"].as_str().unwrap())
+            .collect();
+        let json: serde_json::Value = serde_json::from_slice(payload).unwrap();
+        assert_eq!(fields.len(), json.as_object().unwrap().len());
+        assert!(fields.iter().all(|f| json.get(f).is_some()));
+    }
+}
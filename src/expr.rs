@@ -0,0 +1,742 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Variables is a type alias for the named data of a case, carried alongside its marking.
+pub type Variables = BTreeMap<String, Value>;
+
+/// `ExprError` reports a syntax error in an expression with its 1-based column, or a value of the wrong type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprError {
+    Syntax { column: usize, message: String },
+    Type { message: String },
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::Syntax { column, message } => write!(f, "{}: {}", column, message),
+            ExprError::Type { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+/// `UnaryOp` is the operator of a unary expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Not,
+    Neg,
+}
+
+/// `BinaryOp` is the operator of a binary expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Or => "||",
+            BinaryOp::And => "&&",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+        }
+    }
+}
+
+/// `Expr` is a parsed expression over the variables of a case.
+///
+/// The language has numbers, strings in single or double quotes, `true`, `false` and `null`, variables
+/// with dotted paths into objects such as `customer.tier`, the arithmetic operators `+ - * / %`,
+/// the comparisons `== != < <= > >=` and the logical operators `! && ||`, with the usual precedence.
+/// A variable that is not set evaluates to `null`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    Variable(Vec<String>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Parses an expression.
+    pub fn parse(source: &str) -> Result<Self, ExprError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+            depth: 0,
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            (Token::End, _) => Ok(expr),
+            (token, column) => Err(ExprError::Syntax {
+                column: *column,
+                message: format!("unexpected {}", token),
+            }),
+        }
+    }
+
+    /// Evaluates the expression over the variables.
+    pub fn evaluate(&self, variables: &Variables) -> Result<Value, ExprError> {
+        match self {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Variable(path) => {
+                let mut value = variables.get(&path[0]);
+                for key in &path[1..] {
+                    value = value.and_then(|v| v.get(key));
+                }
+                Ok(value.cloned().unwrap_or(Value::Null))
+            }
+            Expr::Unary(UnaryOp::Not, operand) => Ok(Value::Bool(!boolean(&operand.evaluate(variables)?, "!")?)),
+            Expr::Unary(UnaryOp::Neg, operand) => Ok(Value::from(-number(&operand.evaluate(variables)?, "-")?)),
+            Expr::Binary(op @ (BinaryOp::And | BinaryOp::Or), left, right) => {
+                let left = boolean(&left.evaluate(variables)?, op.symbol())?;
+                if left == (*op == BinaryOp::Or) {
+                    return Ok(Value::Bool(left));
+                }
+                Ok(Value::Bool(boolean(&right.evaluate(variables)?, op.symbol())?))
+            }
+            Expr::Binary(op, left, right) => binary(*op, &left.evaluate(variables)?, &right.evaluate(variables)?),
+        }
+    }
+}
+
+fn boolean(value: &Value, op: &str) -> Result<bool, ExprError> {
+    value.as_bool().ok_or_else(|| ExprError::Type {
+        message: format!("`{}` expects a boolean, got {}", op, value),
+    })
+}
+
+fn number(value: &Value, op: &str) -> Result<f64, ExprError> {
+    value.as_f64().ok_or_else(|| ExprError::Type {
+        message: format!("`{}` expects a number, got {}", op, value),
+    })
+}
+
+fn binary(op: BinaryOp, left: &Value, right: &Value) -> Result<Value, ExprError> {
+    let equal = match (left.as_f64(), right.as_f64()) {
+        (Some(l), Some(r)) => l == r,
+        _ => left == right,
+    };
+    let ordering = || match (left, right) {
+        (Value::String(l), Value::String(r)) => Ok(l.cmp(r)),
+        _ => {
+            let (l, r) = (number(left, op.symbol())?, number(right, op.symbol())?);
+            l.partial_cmp(&r).ok_or_else(|| ExprError::Type {
+                message: format!("cannot compare {} and {}", left, right),
+            })
+        }
+    };
+    let arithmetic = |f: fn(f64, f64) -> f64| -> Result<Value, ExprError> {
+        Ok(Value::from(f(number(left, op.symbol())?, number(right, op.symbol())?)))
+    };
+    match op {
+        BinaryOp::Eq => Ok(Value::Bool(equal)),
+        BinaryOp::Ne => Ok(Value::Bool(!equal)),
+        BinaryOp::Lt => Ok(Value::Bool(ordering()?.is_lt())),
+        BinaryOp::Le => Ok(Value::Bool(ordering()?.is_le())),
+        BinaryOp::Gt => Ok(Value::Bool(ordering()?.is_gt())),
+        BinaryOp::Ge => Ok(Value::Bool(ordering()?.is_ge())),
+        BinaryOp::Add => match (left, right) {
+            (Value::String(l), Value::String(r)) => Ok(Value::String(format!("{}{}", l, r))),
+            _ => arithmetic(|l, r| l + r),
+        },
+        BinaryOp::Sub => arithmetic(|l, r| l - r),
+        BinaryOp::Mul => arithmetic(|l, r| l * r),
+        BinaryOp::Div => arithmetic(|l, r| l / r),
+        BinaryOp::Rem => arithmetic(|l, r| l % r),
+        BinaryOp::And | BinaryOp::Or => unreachable!("logical operators short-circuit"),
+    }
+}
+
+/// `Condition` is the predicate over the case variables a transition requires to fire, kept with its source.
+///
+/// It is serialized as its source, so state machines holding conditions round-trip through JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+impl Condition {
+    /// Parses the condition.
+    pub fn parse(source: &str) -> Result<Self, ExprError> {
+        Ok(Self {
+            source: source.to_string(),
+            expr: Expr::parse(source)?,
+        })
+    }
+
+    /// Returns the source of the condition.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the parsed expression.
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// Evaluates the condition over the variables, failing if it is not a boolean.
+    pub fn evaluate(&self, variables: &Variables) -> Result<bool, ExprError> {
+        let value = self.expr.evaluate(variables)?;
+        value.as_bool().ok_or_else(|| ExprError::Type {
+            message: format!("condition `{}` is not a boolean, got {}", self.source, value),
+        })
+    }
+
+    /// Checks if the condition holds over the variables, a condition that fails to evaluate does not hold.
+    pub fn holds(&self, variables: &Variables) -> bool {
+        self.evaluate(variables).unwrap_or(false)
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = ExprError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Self::parse(&source)
+    }
+}
+
+impl From<Condition> for String {
+    fn from(condition: Condition) -> Self {
+        condition.source
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// `MarkingWeight` is the weight of an arc computed from the marking when its transition fires, such as
+/// "consume all" or "consume half, rounded down".
+///
+/// The expression set is restricted to a fraction of the tokens of a single place, rounded down and
+/// optionally capped, so the weight never exceeds the tokens of that place and analyses can still bound it:
+///
+/// ```text
+/// all | half | <numerator>/<denominator> [of <place>] [max <limit>]
+/// ```
+///
+/// Without `of` the place is the one the arc connects. It is serialized as its canonical form.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MarkingWeight {
+    pub numerator: i32,
+    pub denominator: i32,
+    /// The place whose tokens the weight is a fraction of, the place of the arc if None.
+    pub of: Option<String>,
+    /// The largest weight, such as the size of a batch.
+    pub max: Option<i32>,
+}
+
+impl MarkingWeight {
+    /// Parses the weight.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pflow_metamodel::expr::MarkingWeight;
+    ///
+    /// let batch = MarkingWeight::parse("half of dock max 10").unwrap();
+    /// assert_eq!(batch.weight(7), 3);
+    /// assert_eq!(batch.weight(50), 10);
+    /// assert_eq!(batch.of.as_deref(), Some("dock"));
+    /// ```
+    pub fn parse(source: &str) -> Result<Self, ExprError> {
+        let mut words = source
+            .char_indices()
+            .filter(|(i, c)| !c.is_whitespace() && (*i == 0 || source[..*i].ends_with(char::is_whitespace)))
+            .map(|(i, _)| {
+                let word = source[i..].split_whitespace().next().unwrap();
+                (source[..i].chars().count() + 1, word)
+            })
+            .peekable();
+        let syntax = |column: usize, message: String| ExprError::Syntax { column, message };
+        let end = source.chars().count() + 1;
+
+        let (column, fraction) = words.next().ok_or_else(|| syntax(end, "expected a fraction".to_string()))?;
+        let (numerator, denominator) = match fraction {
+            "all" => (1, 1),
+            "half" => (1, 2),
+            _ => {
+                let parsed = fraction
+                    .split_once('/')
+                    .and_then(|(n, d)| Some((n.parse::<i32>().ok()?, d.parse::<i32>().ok()?)));
+                match parsed {
+                    Some((n, d)) if d > 0 && (0..=d).contains(&n) => (n, d),
+                    _ => return Err(syntax(column, format!("expected all, half or a fraction up to one, got {}", fraction))),
+                }
+            }
+        };
+        let mut weight = Self {
+            numerator,
+            denominator,
+            of: None,
+            max: None,
+        };
+        if words.peek().is_some_and(|(_, word)| *word == "of") {
+            words.next();
+            let (_, place) = words.next().ok_or_else(|| syntax(end, "expected a place after of".to_string()))?;
+            weight.of = Some(place.to_string());
+        }
+        if words.peek().is_some_and(|(_, word)| *word == "max") {
+            words.next();
+            let (column, limit) = words.next().ok_or_else(|| syntax(end, "expected a limit after max".to_string()))?;
+            match limit.parse::<i32>() {
+                Ok(limit) if limit >= 0 => weight.max = Some(limit),
+                _ => return Err(syntax(column, format!("expected a non-negative limit, got {}", limit))),
+            }
+        }
+        match words.next() {
+            Some((column, word)) => Err(syntax(column, format!("unexpected {}", word))),
+            None => Ok(weight),
+        }
+    }
+
+    /// Returns the weight when the place holds `tokens`, between zero and the tokens.
+    pub fn weight(&self, tokens: i32) -> i32 {
+        let weight = (tokens.max(0) as i64 * self.numerator as i64 / self.denominator as i64) as i32;
+        self.max.map_or(weight, |max| weight.min(max))
+    }
+
+    /// Rewrites the place named by `of` in the source of a weight, which is returned unchanged if it does not parse.
+    pub fn rename_place<F>(source: &str, rename: F) -> String
+    where
+        F: FnOnce(&str) -> String,
+    {
+        match Self::parse(source) {
+            Ok(mut weight) => {
+                weight.of = weight.of.as_deref().map(rename);
+                weight.to_string()
+            }
+            Err(_) => source.to_string(),
+        }
+    }
+}
+
+impl TryFrom<String> for MarkingWeight {
+    type Error = ExprError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Self::parse(&source)
+    }
+}
+
+impl From<MarkingWeight> for String {
+    fn from(weight: MarkingWeight) -> Self {
+        weight.to_string()
+    }
+}
+
+impl fmt::Display for MarkingWeight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.numerator, self.denominator) {
+            (n, d) if n == d => f.write_str("all")?,
+            (n, d) if 2 * n == d => f.write_str("half")?,
+            (n, d) => write!(f, "{}/{}", n, d)?,
+        }
+        if let Some(of) = &self.of {
+            write!(f, " of {}", of)?;
+        }
+        if let Some(max) = self.max {
+            write!(f, " max {}", max)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Str(String),
+    Punct(&'static str),
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(s) => write!(f, "`{}`", s),
+            Token::Number(n) => write!(f, "`{}`", n),
+            Token::Str(s) => write!(f, "{:?}", s),
+            Token::Punct(p) => write!(f, "`{}`", p),
+            Token::End => write!(f, "end of input"),
+        }
+    }
+}
+
+const PUNCTS: [&str; 17] = [
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "+", "-", "*", "/", "%", "(", ")", ".",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ExprError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let token = if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            Token::Ident(chars[start..i].iter().collect())
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let digits: String = chars[start..i].iter().collect();
+            Token::Number(digits.parse().map_err(|_| ExprError::Syntax {
+                column,
+                message: format!("invalid number `{}`", digits),
+            })?)
+        } else if c == '"' || c == '\'' {
+            let mut s = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => {
+                        return Err(ExprError::Syntax {
+                            column,
+                            message: "unterminated string".to_string(),
+                        })
+                    }
+                    Some(q) if *q == c => break,
+                    Some('\\') if i + 1 < chars.len() => {
+                        s.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(ch) => {
+                        s.push(*ch);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            Token::Str(s)
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let punct = PUNCTS
+                .iter()
+                .find(|p| rest.starts_with(**p))
+                .ok_or_else(|| ExprError::Syntax {
+                    column,
+                    message: format!("unexpected character `{}`", c),
+                })?;
+            i += punct.len();
+            Token::Punct(punct)
+        };
+        tokens.push((token, column));
+    }
+    tokens.push((Token::End, chars.len() + 1));
+    Ok(tokens)
+}
+
+/// How deeply operators and parentheses may nest before parsing gives up, bounding the recursion.
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &(Token, usize) {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> (Token, usize) {
+        let t = self.tokens[self.pos].clone();
+        if t.0 != Token::End {
+            self.pos += 1;
+        }
+        t
+    }
+
+    fn eat(&mut self, punct: &'static str) -> bool {
+        if self.peek().0 == Token::Punct(punct) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn binary<F>(&mut self, ops: &[(&'static str, BinaryOp)], operand: F) -> Result<Expr, ExprError>
+    where
+        F: Fn(&mut Self) -> Result<Expr, ExprError>,
+    {
+        let depth = self.depth;
+        let mut left = operand(self)?;
+        while let Some((_, op)) = ops.iter().find(|(punct, _)| self.peek().0 == Token::Punct(punct)) {
+            let column = self.peek().1;
+            self.pos += 1;
+            self.enter(column)?;
+            left = Expr::Binary(*op, Box::new(left), Box::new(operand(self)?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&[("||", BinaryOp::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&[("&&", BinaryOp::And)], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ExprError> {
+        let ops = [
+            ("==", BinaryOp::Eq),
+            ("!=", BinaryOp::Ne),
+            ("<=", BinaryOp::Le),
+            (">=", BinaryOp::Ge),
+            ("<", BinaryOp::Lt),
+            (">", BinaryOp::Gt),
+        ];
+        self.binary(&ops, Self::sum)
+    }
+
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&[("+", BinaryOp::Add), ("-", BinaryOp::Sub)], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        let ops = [("*", BinaryOp::Mul), ("/", BinaryOp::Div), ("%", BinaryOp::Rem)];
+        self.binary(&ops, Self::unary)
+    }
+
+    fn enter(&mut self, column: usize) -> Result<(), ExprError> {
+        if self.depth == MAX_DEPTH {
+            return Err(ExprError::Syntax {
+                column,
+                message: format!("nested deeper than {} levels", MAX_DEPTH),
+            });
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn nested<F>(&mut self, column: usize, parse: F) -> Result<Expr, ExprError>
+    where
+        F: FnOnce(&mut Self) -> Result<Expr, ExprError>,
+    {
+        self.enter(column)?;
+        let expr = parse(self)?;
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        let column = self.peek().1;
+        if self.eat("!") {
+            let operand = self.nested(column, Self::unary)?;
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(operand)));
+        }
+        if self.eat("-") {
+            let operand = self.nested(column, Self::unary)?;
+            return Ok(Expr::Unary(UnaryOp::Neg, Box::new(operand)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ExprError> {
+        let (token, column) = self.next();
+        match token {
+            Token::Number(n) => Ok(Expr::Literal(Value::from(n))),
+            Token::Str(s) => Ok(Expr::Literal(Value::String(s))),
+            Token::Ident(s) if s == "true" || s == "false" => Ok(Expr::Literal(Value::Bool(s == "true"))),
+            Token::Ident(s) if s == "null" => Ok(Expr::Literal(Value::Null)),
+            Token::Ident(s) => {
+                let mut path = vec![s];
+                while self.eat(".") {
+                    match self.next() {
+                        (Token::Ident(key), _) => path.push(key),
+                        (other, column) => {
+                            return Err(ExprError::Syntax {
+                                column,
+                                message: format!("expected a name, found {}", other),
+                            })
+                        }
+                    }
+                }
+                Ok(Expr::Variable(path))
+            }
+            Token::Punct("(") => {
+                let expr = self.nested(column, Self::or)?;
+                if !self.eat(")") {
+                    let (token, column) = self.peek().clone();
+                    return Err(ExprError::Syntax {
+                        column,
+                        message: format!("expected `)`, found {}", token),
+                    });
+                }
+                Ok(expr)
+            }
+            other => Err(ExprError::Syntax {
+                column,
+                message: format!("expected a value, found {}", other),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn variables(value: Value) -> Variables {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let vars = variables(json!({"amount": 1500, "currency": "EUR", "customer": {"tier": "gold"}, "vip": false}));
+        for (source, expected) in [
+            ("amount > 1000", json!(true)),
+            ("amount * 2 - 1 == 2999", json!(true)),
+            ("-amount < 0 && !vip", json!(true)),
+            ("vip || amount % 1000 == 500", json!(true)),
+            ("currency == 'EUR' && customer.tier != \"silver\"", json!(true)),
+            ("currency + \"/USD\"", json!("EUR/USD")),
+            ("1 + 2 * 3", json!(7.0)),
+            ("(1 + 2) * 3", json!(9.0)),
+            ("approved == null", json!(true)),
+            ("customer.name", Value::Null),
+            ("'b' > 'a'", json!(true)),
+        ] {
+            assert_eq!(Expr::parse(source).unwrap().evaluate(&vars), Ok(expected), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_short_circuit_and_type_errors() {
+        let vars = variables(json!({"amount": 10}));
+        assert_eq!(Expr::parse("true || amount").unwrap().evaluate(&vars), Ok(json!(true)));
+        assert!(matches!(
+            Expr::parse("amount && true").unwrap().evaluate(&vars),
+            Err(ExprError::Type { .. })
+        ));
+        assert!(matches!(
+            Expr::parse("missing > 1").unwrap().evaluate(&vars),
+            Err(ExprError::Type { .. })
+        ));
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        for (source, column) in [
+            ("amount >", 9),
+            ("(amount", 8),
+            ("amount $ 1", 8),
+            ("a.1", 3),
+            ("'open", 1),
+        ] {
+            match Expr::parse(source) {
+                Err(ExprError::Syntax { column: c, .. }) => assert_eq!(c, column, "{}", source),
+                other => panic!("{}: {:?}", source, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        assert!(Expr::parse(&format!("{}true", "!".repeat(MAX_DEPTH))).is_ok());
+        assert!(Expr::parse(&format!("{}1{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH))).is_ok());
+        for source in [
+            format!("{}true", "!".repeat(100_000)),
+            format!("{}1", "(".repeat(100_000)),
+            format!("{}1", "-(".repeat(50_000)),
+        ] {
+            match Expr::parse(&source) {
+                Err(ExprError::Syntax { column, .. }) => assert_eq!(column, MAX_DEPTH + 1),
+                other => panic!("{:?}", other),
+            }
+        }
+        assert!(matches!(
+            Expr::parse(&format!("1{}", " + 1".repeat(100_000))),
+            Err(ExprError::Syntax { .. })
+        ));
+    }
+
+    #[test]
+    fn test_condition() {
+        let condition = Condition::parse("amount <= 1000").unwrap();
+        assert!(condition.holds(&variables(json!({"amount": 1000}))));
+        assert!(!condition.holds(&variables(json!({"amount": 1001}))));
+        assert!(!condition.holds(&Variables::new()));
+        assert!(Condition::parse("amount + 1")
+            .unwrap()
+            .evaluate(&Variables::new())
+            .is_err());
+
+        let json = serde_json::to_string(&condition).unwrap();
+        assert_eq!(json, "\"amount <= 1000\"");
+        assert_eq!(serde_json::from_str::<Condition>(&json).unwrap(), condition);
+        assert!(serde_json::from_str::<Condition>("\"amount <=\"").is_err());
+    }
+
+    #[test]
+    fn test_marking_weight() {
+        let all = MarkingWeight::parse("all").unwrap();
+        assert_eq!((all.weight(7), all.weight(-1)), (7, 0));
+        assert_eq!(MarkingWeight::parse("half").unwrap().weight(7), 3);
+        assert_eq!(MarkingWeight::parse(" 2/3  max 4 ").unwrap().weight(9), 4);
+        for source in ["all", "half of dock", "1/3 max 5", "0/1 of a max 0"] {
+            let weight = MarkingWeight::parse(source).unwrap();
+            assert_eq!(weight.to_string(), source);
+            let json = serde_json::to_string(&weight).unwrap();
+            assert_eq!(serde_json::from_str::<MarkingWeight>(&json).unwrap(), weight);
+        }
+        assert_eq!(MarkingWeight::parse("4/8").unwrap().to_string(), "half");
+        assert_eq!(MarkingWeight::rename_place("all of dock max 3", |p| format!("a.{}", p)), "all of a.dock max 3");
+
+        for (source, column) in [("", 1), ("twice", 1), ("3/2", 1), ("1/0", 1), ("all of", 7), ("half max -1", 10), ("all dock", 5)] {
+            match MarkingWeight::parse(source) {
+                Err(ExprError::Syntax { column: c, .. }) => assert_eq!(c, column, "{}", source),
+                other => panic!("{} parsed as {:?}", source, other),
+            }
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use crate::petri_net::PetriNet;
+
 /// This is a petri net model that represents the dining philosophers problem using chopsticks and philosophers.
-pub const DINING_PHILOSOPHERS: &'static str = r#"
+pub const DINING_PHILOSOPHERS: &str = r#"
 {
     "modelType": "petriNet",
     "version": "v0",
@@ -75,3 +77,33 @@ pub const DINING_PHILOSOPHERS: &'static str = r#"
         { "source": "left2", "target": "think2" }
     ]
 }"#;
+
+/// The bundled models by name, in the order `all` lists them.
+const REGISTRY: &[(&str, &str)] = &[("dining_philosophers", DINING_PHILOSOPHERS)];
+
+/// Lists the (name, JSON source) pairs of all bundled models.
+pub fn all() -> impl Iterator<Item = (&'static str, &'static str)> {
+    REGISTRY.iter().copied()
+}
+
+/// Loads the bundled model with the given name, or returns None if there is no such model.
+pub fn load(name: &str) -> Option<PetriNet> {
+    let (_, source) = REGISTRY.iter().find(|(n, _)| *n == name)?;
+    PetriNet::from_json(source.to_string()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        for (name, source) in all() {
+            let net = load(name).unwrap();
+            assert_eq!(net.to_json().unwrap(), PetriNet::from_json(source.to_string()).unwrap().to_json().unwrap());
+        }
+        assert_eq!(all().map(|(name, _)| name).collect::<Vec<_>>(), vec!["dining_philosophers"]);
+        assert_eq!(load("dining_philosophers").unwrap().places.len(), 15);
+        assert!(load("missing").is_none());
+    }
+}
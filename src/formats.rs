@@ -0,0 +1,82 @@
+use serde_json::Value;
+
+use crate::petri_net::PetriNet;
+
+/// Converts the net to a JSON value with sorted keys and without null fields,
+/// so the text formats get the same stable ordering as the canonical JSON.
+fn canonical_value(net: &PetriNet) -> Value {
+    fn strip_nulls(value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                map.retain(|_, v| !v.is_null());
+                map.values_mut().for_each(strip_nulls);
+            }
+            Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+            _ => {}
+        }
+    }
+    let mut value = serde_json::to_value(net).expect("a PetriNet is always representable as JSON");
+    strip_nulls(&mut value);
+    value
+}
+
+#[cfg(feature = "yaml")]
+impl PetriNet {
+    /// Creates a new `PetriNet` object from the given YAML string.
+    pub fn from_yaml(contents: &str) -> Result<Self, serde_yaml::Error> {
+        let mut petri_net: PetriNet = serde_yaml::from_str(contents)?;
+        petri_net.populate_arc_attributes();
+        Ok(petri_net)
+    }
+
+    /// Converts the `PetriNet` to a YAML string with sorted keys.
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(&canonical_value(self))
+    }
+}
+
+#[cfg(feature = "toml")]
+impl PetriNet {
+    /// Creates a new `PetriNet` object from the given TOML string.
+    pub fn from_toml(contents: &str) -> Result<Self, toml::de::Error> {
+        let mut petri_net: PetriNet = toml::from_str(contents)?;
+        petri_net.populate_arc_attributes();
+        Ok(petri_net)
+    }
+
+    /// Converts the `PetriNet` to a TOML string with sorted keys.
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(&canonical_value(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml_round_trip() {
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let yaml = net.to_yaml().unwrap();
+        assert!(yaml.starts_with("arcs:"));
+        let back = PetriNet::from_yaml(&yaml).unwrap();
+        assert_eq!(back.to_yaml().unwrap(), yaml);
+        assert_eq!(back.to_json().unwrap(), net.to_json().unwrap());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml_round_trip() {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        net.set_unit("right2", "forks");
+        let toml = net.to_toml().unwrap();
+        let back = PetriNet::from_toml(&toml).unwrap();
+        assert_eq!(back.to_toml().unwrap(), toml);
+        assert_eq!(back.places["right2"].unit.as_deref(), Some("forks"));
+        #[cfg(feature = "zblob")]
+        assert_eq!(back.to_zblob().ipfs_cid, net.to_zblob().ipfs_cid);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::layout;
+use crate::petri_net::PetriNet;
+
+/// Generates the dining philosophers problem for `n` philosophers sharing `n` chopsticks.
+///
+/// Places and transitions are named like the `DINING_PHILOSOPHERS` fixture, which is the net for five
+/// philosophers: philosopher `i` takes `chopstick{i}` and the chopstick on its other side to fire `eat{i}`,
+/// holds them in `left{i}` and `right{i}`, and puts them back with `think{i}`. A lone philosopher has only
+/// its own chopstick to take, so `philosophers(1)` connects it once.
+pub fn philosophers(n: usize) -> PetriNet {
+    let mut net = PetriNet::new();
+    for i in 1..=n {
+        let offset = net.places.len() as i32;
+        net.add_place(&format!("chopstick{}", i), offset, Some(1), None, 0, 0);
+        net.add_place(&format!("left{}", i), offset + 1, None, None, 0, 0);
+        net.add_place(&format!("right{}", i), offset + 2, None, None, 0, 0);
+    }
+    for i in 1..=n {
+        let (eat, think) = (format!("eat{}", i), format!("think{}", i));
+        let other = format!("chopstick{}", if i == 1 { n } else { i - 1 });
+        let own = format!("chopstick{}", i);
+        net.add_transition(&eat, "default", 0, 0);
+        net.add_transition(&think, "default", 0, 0);
+        let chopsticks = if n == 1 { vec![&own] } else { vec![&own, &other] };
+        for chopstick in chopsticks {
+            net.add_arc(chopstick, &eat, Some(1), None, None, None, None);
+            net.add_arc(&think, chopstick, Some(1), None, None, None, None);
+        }
+        for hand in [format!("left{}", i), format!("right{}", i)] {
+            net.add_arc(&eat, &hand, Some(1), None, None, None, None);
+            net.add_arc(&hand, &think, Some(1), None, None, None, None);
+        }
+    }
+    layout::auto(&mut net);
+    net
+}
+
+/// Generates a pipeline of `stages` steps moving `items` tokens from `stage0` to the last stage.
+///
+/// Step `k` moves a token from `stage{k-1}` to `stage{k}`, every stage in between holds at most one token.
+pub fn pipeline(stages: usize, items: i32) -> PetriNet {
+    let mut net = PetriNet::new();
+    for k in 0..=stages {
+        let capacity = (k > 0 && k < stages).then_some(1);
+        net.add_place(
+            &format!("stage{}", k),
+            k as i32,
+            (k == 0).then_some(items),
+            capacity,
+            0,
+            0,
+        );
+    }
+    for k in 1..=stages {
+        let step = format!("step{}", k);
+        net.add_transition(&step, "default", 0, 0);
+        net.add_arc(&format!("stage{}", k - 1), &step, Some(1), None, None, None, None);
+        net.add_arc(&step, &format!("stage{}", k), Some(1), None, None, None, None);
+    }
+    layout::auto(&mut net);
+    net
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{explore, Limits};
+    use crate::equivalence::{equivalent, Equivalence};
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::vasm::StateMachine;
+
+    use super::*;
+
+    #[test]
+    fn test_philosophers_match_fixture() {
+        let mut fixture = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        fixture.transitions.values_mut().for_each(|t| t.role = Some("default".to_string()));
+        assert!(matches!(
+            equivalent(&philosophers(5), &fixture),
+            Equivalence::Isomorphic(_)
+        ));
+        let net = philosophers(8);
+        assert_eq!((net.places.len(), net.transitions.len(), net.arcs.len()), (24, 16, 64));
+        let lone = philosophers(1);
+        assert_eq!((lone.places.len(), lone.transitions.len(), lone.arcs.len()), (3, 2, 6));
+        let pairs = lone.arcs.iter().map(|a| (&a.source, &a.target)).collect::<std::collections::HashSet<_>>();
+        assert_eq!(pairs.len(), lone.arcs.len());
+    }
+
+    #[test]
+    fn test_pipeline_state_space() {
+        let sm = StateMachine::from_model(&mut pipeline(3, 2));
+        let graph = explore(&sm, Limits::default());
+        assert!(graph.complete);
+        assert!(graph.index_of(&vec![0, 0, 0, 2]).is_some());
+        assert!(graph.states.iter().all(|s| s[1] <= 1 && s[2] <= 1));
+    }
+}
@@ -0,0 +1,374 @@
+// Every RPC fails with a `tonic::Status`, however large it is.
+#![allow(clippy::result_large_err)]
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+use tonic::{Request, Response, Status};
+
+use crate::analysis::{dead_transitions, Boundedness, Limits};
+use crate::petri_net::PetriNet;
+use crate::proto;
+use crate::report::{generate, AnalysisResults};
+use crate::vasm::{StateMachine, Vasm, Vector};
+use crate::zblob::Zblob;
+
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/pflow.metamodel.v0.ModelService.rs"));
+}
+
+pub use generated::model_service_client::ModelServiceClient;
+pub use generated::model_service_server::{ModelService, ModelServiceServer};
+
+/// The most states an analysis explores, and candidate siphons it enumerates, whatever `max_states` the client asks for.
+pub const MAX_ANALYSIS_STATES: usize = 100_000;
+
+/// The most times a transition is fired at once.
+pub const MAX_MULTIPLE: i32 = 1_000;
+
+/// The most models a store keeps unless created `with_capacity`.
+pub const MAX_MODELS: usize = 10_000;
+
+/// A stored model with the state machine its firings are computed by.
+struct Entry {
+    zblob: Zblob,
+    net: PetriNet,
+    sm: StateMachine,
+}
+
+/// `ModelStore` implements the `ModelService` RPCs over models kept in memory, keyed by the CID of their zblob.
+pub struct ModelStore {
+    models: RwLock<HashMap<String, Arc<Entry>>>,
+    capacity: usize,
+}
+
+impl Default for ModelStore {
+    fn default() -> Self {
+        Self::with_capacity(MAX_MODELS)
+    }
+}
+
+impl ModelStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a store keeping at most `capacity` models, once full new models are refused.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            models: RwLock::default(),
+            capacity,
+        }
+    }
+
+    /// Returns the stored model, which stays usable once the lock on the store is released.
+    fn entry(&self, cid: &str) -> Result<Arc<Entry>, Status> {
+        let models = self.models.read().unwrap();
+        models
+            .get(cid)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("no model with cid {}", cid)))
+    }
+
+    fn with_model<T>(&self, cid: &str, f: impl FnOnce(&Entry) -> Result<T, Status>) -> Result<T, Status> {
+        let entry = self.entry(cid)?;
+        f(&entry)
+    }
+}
+
+/// Returns the state of the request, the initial state if it is empty.
+fn state_or_initial(sm: &StateMachine, state: Vector) -> Result<Vector, Status> {
+    match state.len() {
+        0 => Ok(sm.initial_vector()),
+        n if n == sm.places.len() => Ok(state),
+        n => Err(Status::invalid_argument(format!(
+            "state has {} places, the model has {}",
+            n,
+            sm.places.len()
+        ))),
+    }
+}
+
+#[tonic::async_trait]
+impl ModelService for ModelStore {
+    async fn store_model(
+        &self,
+        request: Request<proto::StoreModelRequest>,
+    ) -> Result<Response<proto::StoreModelResponse>, Status> {
+        let request = request.into_inner();
+        let mut net: PetriNet = request
+            .net
+            .ok_or_else(|| Status::invalid_argument("missing net"))?
+            .into();
+        let sm = StateMachine::try_from_model(&mut net).map_err(|err| Status::invalid_argument(err.to_string()))?;
+        let mut zblob = Zblob {
+            title: request.title,
+            description: request.description,
+            keywords: request.keywords,
+            ..net.to_zblob()
+        };
+        zblob.normalize();
+        zblob
+            .validate()
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+        let cid = zblob.ipfs_cid.clone();
+        let mut models = self.models.write().unwrap();
+        if models.len() >= self.capacity && !models.contains_key(&cid) {
+            return Err(Status::resource_exhausted(format!("the store is full with {} models", models.len())));
+        }
+        models.insert(cid.clone(), Arc::new(Entry { zblob, net, sm }));
+        Ok(Response::new(proto::StoreModelResponse { cid }))
+    }
+
+    async fn get_model(
+        &self,
+        request: Request<proto::GetModelRequest>,
+    ) -> Result<Response<proto::GetModelResponse>, Status> {
+        self.with_model(&request.into_inner().cid, |entry| {
+            Ok(Response::new(proto::GetModelResponse {
+                net: Some(proto::PetriNet::from(&entry.net)),
+                title: entry.zblob.title.clone(),
+                description: entry.zblob.description.clone(),
+                keywords: entry.zblob.keywords.clone(),
+            }))
+        })
+    }
+
+    async fn fire_transition(
+        &self,
+        request: Request<proto::FireTransitionRequest>,
+    ) -> Result<Response<proto::Transaction>, Status> {
+        let request = request.into_inner();
+        self.with_model(&request.cid, |entry| {
+            let state = state_or_initial(&entry.sm, request.state)?;
+            let multiple = match request.multiple {
+                0 => 1,
+                n @ 1..=MAX_MULTIPLE => n,
+                n => {
+                    return Err(Status::invalid_argument(format!(
+                        "multiple {} is not between 1 and {}",
+                        n, MAX_MULTIPLE
+                    )))
+                }
+            };
+            let tx = entry.sm.transform(&state, &request.action, multiple);
+            Ok(Response::new(proto::Transaction::from(&tx)))
+        })
+    }
+
+    async fn get_enabled_transitions(
+        &self,
+        request: Request<proto::GetEnabledTransitionsRequest>,
+    ) -> Result<Response<proto::GetEnabledTransitionsResponse>, Status> {
+        let request = request.into_inner();
+        self.with_model(&request.cid, |entry| {
+            let state = state_or_initial(&entry.sm, request.state)?;
+            let mut actions: Vec<String> = entry
+                .sm
+                .transitions
+                .keys()
+                .filter(|action| entry.sm.is_enabled(&state, action, 1))
+                .cloned()
+                .collect();
+            actions.sort();
+            Ok(Response::new(proto::GetEnabledTransitionsResponse { actions }))
+        })
+    }
+
+    async fn analyze_model(
+        &self,
+        request: Request<proto::AnalyzeModelRequest>,
+    ) -> Result<Response<proto::AnalyzeModelResponse>, Status> {
+        let request = request.into_inner();
+        let limits = match request.max_states {
+            0 => Limits::default(),
+            n => Limits::new(usize::try_from(n).unwrap_or(usize::MAX).min(MAX_ANALYSIS_STATES)),
+        };
+        let entry = self.entry(&request.cid)?;
+        // The analysis explores the state space, so it runs off the async workers and without the lock.
+        let response = tokio::task::spawn_blocking(move || {
+            let results = AnalysisResults::compute(&entry.net, limits);
+            let statistics = results.statistics.clone().unwrap_or_default();
+            let class = serde_json::to_value(results.class).unwrap();
+            proto::AnalyzeModelResponse {
+                class: class.as_str().unwrap_or_default().to_string(),
+                states: statistics.states as u64,
+                complete: statistics.complete,
+                bound: match results.boundedness {
+                    Some(Boundedness::Bounded { k }) => Some(k),
+                    _ => None,
+                },
+                dead_transitions: dead_transitions(&entry.sm, limits),
+                report: generate(&entry.net, &results),
+            }
+        })
+        .await
+        .map_err(|err| Status::internal(err.to_string()))?;
+        Ok(Response::new(response))
+    }
+}
+
+/// Serves the `ModelService` of the store on the address until the server fails.
+pub async fn serve(addr: SocketAddr, store: ModelStore) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(ModelServiceServer::new(store))
+        .serve(addr)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::transport::server::TcpIncoming;
+    use tonic::transport::Server;
+
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    fn philosophers() -> proto::StoreModelRequest {
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        proto::StoreModelRequest {
+            net: Some(proto::PetriNet::from(&net)),
+            title: " Dining philosophers ".to_string(),
+            description: "five philosophers, five chopsticks".to_string(),
+            keywords: "Classic, deadlock".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_model_store() {
+        let store = ModelStore::new();
+        let cid = store
+            .store_model(Request::new(philosophers()))
+            .await
+            .unwrap()
+            .into_inner()
+            .cid;
+        let expected = PetriNet::from_json(DINING_PHILOSOPHERS.to_string())
+            .unwrap()
+            .to_zblob()
+            .ipfs_cid;
+        assert_eq!(cid, expected);
+
+        let model = store
+            .get_model(Request::new(proto::GetModelRequest { cid: cid.clone() }))
+            .await
+            .unwrap();
+        let model = model.into_inner();
+        assert_eq!(
+            (model.title.as_str(), model.keywords.as_str()),
+            ("Dining philosophers", "classic,deadlock")
+        );
+        assert_eq!(PetriNet::from(model.net.unwrap()).to_zblob().ipfs_cid, cid);
+
+        let enabled = proto::GetEnabledTransitionsRequest {
+            cid: cid.clone(),
+            state: vec![],
+        };
+        let actions = store
+            .get_enabled_transitions(Request::new(enabled))
+            .await
+            .unwrap()
+            .into_inner()
+            .actions;
+        assert_eq!(actions.len(), 5);
+        assert!(actions.windows(2).all(|w| w[0] < w[1]));
+
+        let fire = proto::FireTransitionRequest {
+            cid: cid.clone(),
+            state: vec![],
+            action: actions[0].clone(),
+            multiple: 0,
+        };
+        let tx = store
+            .fire_transition(Request::new(fire.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(tx.ok);
+        let unknown = proto::FireTransitionRequest {
+            action: "missing".to_string(),
+            ..fire.clone()
+        };
+        let tx = store.fire_transition(Request::new(unknown)).await.unwrap().into_inner();
+        assert!(!tx.ok && tx.error.is_some());
+        let wrong_state = proto::FireTransitionRequest {
+            state: vec![1],
+            ..fire.clone()
+        };
+        let status = store.fire_transition(Request::new(wrong_state)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        for multiple in [-1, MAX_MULTIPLE + 1, i32::MAX] {
+            let excessive = proto::FireTransitionRequest {
+                multiple,
+                ..fire.clone()
+            };
+            let status = store.fire_transition(Request::new(excessive)).await.unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+
+        let missing = proto::GetModelRequest {
+            cid: "missing".to_string(),
+        };
+        assert_eq!(
+            store.get_model(Request::new(missing)).await.unwrap_err().code(),
+            tonic::Code::NotFound
+        );
+        let empty = proto::StoreModelRequest {
+            net: None,
+            ..philosophers()
+        };
+        let status = store.store_model(Request::new(empty)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let mut dangling = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        dangling.add_arc("eat1", "nowhere", None, None, None, None, None);
+        let invalid = proto::StoreModelRequest {
+            net: Some(proto::PetriNet::from(&dangling)),
+            ..philosophers()
+        };
+        let status = store.store_model(Request::new(invalid)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(status.message(), "arc eat1 -> nowhere does not connect a place and a transition");
+    }
+
+    #[tokio::test]
+    async fn test_store_capacity() {
+        let store = ModelStore::with_capacity(1);
+        store.store_model(Request::new(philosophers())).await.unwrap();
+        store.store_model(Request::new(philosophers())).await.unwrap();
+        let mut other = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        other.set_description("eat1", "pasta");
+        let request = proto::StoreModelRequest {
+            net: Some(proto::PetriNet::from(&other)),
+            ..philosophers()
+        };
+        let status = store.store_model(Request::new(request)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_over_network() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(ModelServiceServer::new(ModelStore::new()))
+                .serve_with_incoming(incoming),
+        );
+
+        let mut client = ModelServiceClient::connect(format!("http://{}", addr)).await.unwrap();
+        let cid = client.store_model(philosophers()).await.unwrap().into_inner().cid;
+        let analysis = client
+            .analyze_model(proto::AnalyzeModelRequest { cid, max_states: 0 })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(analysis.complete && analysis.states > 1);
+        assert_eq!(analysis.bound, Some(1));
+        assert!(analysis.dead_transitions.is_empty());
+        assert!(analysis.report.starts_with('#'));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::vasm::{Guard, GuardMap, Vector};
+
+pub use crate::engine::GuardKind;
+
+impl Guard {
+    /// Returns whether the guard is a read or an inhibitor arc.
+    pub fn kind(&self) -> GuardKind {
+        if self.read {
+            GuardKind::Read
+        } else {
+            GuardKind::Inhibit
+        }
+    }
+
+    fn cached(&self) -> (Option<usize>, i32) {
+        *self.cached.get_or_init(|| {
+            let place = self.delta.iter().position(|d| *d != 0);
+            let weight = self.delta.iter().map(|d| d.abs()).max().unwrap_or(0);
+            (place, weight)
+        })
+    }
+
+    /// Returns the offset of the guarded place.
+    pub fn place(&self) -> Option<usize> {
+        self.cached().0
+    }
+
+    /// Returns the arc weight of the guard.
+    pub fn weight(&self) -> i32 {
+        self.cached().1
+    }
+
+    /// Returns the number of tokens the guarded place is compared against when firing with the given multiple.
+    pub fn threshold(&self, multiple: i32) -> i64 {
+        self.weight() as i64 * multiple as i64
+    }
+
+    /// Checks if the guard blocks the transition in the given state.
+    pub fn blocks(&self, state: &Vector, multiple: i32) -> bool {
+        let tokens = self.place().and_then(|i| state.get(i)).copied().unwrap_or(0);
+        self.kind().blocks(tokens, self.threshold(multiple))
+    }
+}
+
+/// Checks if any of the guards blocks the transition in the given state.
+///
+/// All read arcs must be satisfied and no inhibitor arc may reach its threshold for the transition to fire.
+pub fn guards_block(guards: &GuardMap, state: &Vector, multiple: i32) -> bool {
+    guards.values().any(|g| g.blocks(state, multiple))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+    use crate::vasm::{StateMachine, Vasm};
+
+    use super::*;
+
+    fn guard(kind: GuardKind, weight: i32) -> Guard {
+        Guard::new(vec![0, -weight], kind == GuardKind::Read)
+    }
+
+    #[test]
+    fn test_threshold_truth_table() {
+        for weight in 1..=3 {
+            for multiple in 1..=3 {
+                for tokens in 0..=9 {
+                    let state = vec![0, tokens];
+                    let reached = tokens >= weight * multiple;
+                    assert_eq!(
+                        guard(GuardKind::Read, weight).blocks(&state, multiple),
+                        !reached,
+                        "read w{} x{} with {}",
+                        weight,
+                        multiple,
+                        tokens
+                    );
+                    assert_eq!(
+                        guard(GuardKind::Inhibit, weight).blocks(&state, multiple),
+                        reached,
+                        "inhibit w{} x{} with {}",
+                        weight,
+                        multiple,
+                        tokens
+                    );
+                }
+            }
+        }
+    }
+
+    fn guarded(p: &mut dyn FlowDsl) {
+        p.cell("ready", None, None, 0, 0);
+        p.cell("stop", None, None, 0, 0);
+        p.cell("alarm", None, None, 0, 0);
+        p.func("run", "default", 0, 0);
+        p.guard("run", "ready", 2);
+        p.guard("stop", "run", 1);
+        p.guard("alarm", "run", 1);
+    }
+
+    #[test]
+    fn test_every_guard_is_checked() {
+        let sm = StateMachine::new(guarded);
+        let run = &sm.transitions["run"];
+        assert_eq!(run.guards.len(), 3);
+        assert_eq!(run.guards["ready"].kind(), GuardKind::Read);
+        assert_eq!(run.guards["stop"].kind(), GuardKind::Inhibit);
+
+        assert!(sm.transform(&vec![1, 0, 0], "run", 1).inhibited);
+        assert!(sm.transform(&vec![2, 0, 0], "run", 1).is_ok());
+        assert!(sm.transform(&vec![3, 0, 0], "run", 2).inhibited);
+        assert!(sm.transform(&vec![2, 1, 0], "run", 1).inhibited);
+        assert!(sm.transform(&vec![2, 0, 1], "run", 1).inhibited);
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::expr::MarkingWeight;
+use crate::petri_net::{Arrow, PetriNet};
+
+/// `Subnet` is the child page of a substitution transition.
+///
+/// `ports` maps port places of the child net to socket places of the parent net, the two are merged
+/// when the hierarchy is flattened.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Subnet {
+    pub net: PetriNet,
+    pub ports: HashMap<String, String>,
+}
+
+impl Subnet {
+    /// Creates a subnet from the child net and its (port, socket) pairs.
+    pub fn new(net: PetriNet, ports: &[(&str, &str)]) -> Self {
+        Self {
+            net,
+            ports: ports.iter().map(|(p, s)| (p.to_string(), s.to_string())).collect(),
+        }
+    }
+}
+
+impl PetriNet {
+    /// Turns a transition into a substitution transition refining into `subnet`,
+    /// returns false if there is no such transition.
+    pub fn set_subnet(&mut self, label: &str, subnet: Subnet) -> bool {
+        match self.transitions.get_mut(label) {
+            Some(transition) => {
+                transition.subnet = Some(Box::new(subnet));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Checks if any transition of the net is a substitution transition.
+    pub fn has_subnets(&self) -> bool {
+        self.transitions.values().any(|t| t.subnet.is_some())
+    }
+
+    /// Flattens the hierarchy into a plain net.
+    ///
+    /// Each substitution transition and its arcs are replaced by the nodes of its (flattened) subnet,
+    /// labelled `transition.label`. Port places are merged into their sockets and the other child
+    /// places are appended after the places of the parent.
+    ///
+    /// Panics if a port is not a place of the subnet or a socket is not a place of the parent.
+    pub fn flatten(&self) -> PetriNet {
+        let mut net = self.clone();
+        let mut substituted: Vec<_> = self
+            .transitions
+            .iter()
+            .filter_map(|(label, t)| t.subnet.as_ref().map(|s| (label, s)))
+            .collect();
+        substituted.sort_by_key(|(label, _)| *label);
+
+        for (parent, subnet) in substituted {
+            net.transitions.remove(parent);
+            net.arcs.retain(|a| &a.source != parent && &a.target != parent);
+
+            let child = subnet.net.flatten();
+            let rename = |label: &str| match subnet.ports.get(label) {
+                Some(socket) => socket.clone(),
+                None => format!("{}.{}", parent, label),
+            };
+            for (name, role) in &child.roles {
+                net.roles.entry(name.clone()).or_insert_with(|| role.clone());
+            }
+            for (port, socket) in &subnet.ports {
+                assert!(child.places.contains_key(port), "unknown port {} in subnet {}", port, parent);
+                assert!(self.places.contains_key(socket), "unknown socket {} for subnet {}", socket, parent);
+            }
+
+            let mut places: Vec<_> = child.places.iter().filter(|(l, _)| !subnet.ports.contains_key(*l)).collect();
+            places.sort_by_key(|(label, place)| (place.offset, *label));
+            for (label, place) in places {
+                let mut place = place.clone();
+                place.offset = net.places.len() as i32;
+                net.places.insert(rename(label), place);
+            }
+            for (label, transition) in &child.transitions {
+                let mut transition = transition.clone();
+                if let Some(timeout) = &mut transition.timeout {
+                    timeout.escalate = timeout.escalate.as_deref().map(rename);
+                }
+                if transition.role.is_none() {
+                    transition.role = Some(child.default_role().to_string());
+                }
+                net.transitions.insert(rename(label), transition);
+            }
+            for arc in &child.arcs {
+                net.arcs.push(Arrow {
+                    source: rename(&arc.source),
+                    target: rename(&arc.target),
+                    transfer: arc.transfer.as_deref().map(rename),
+                    marking_weight: arc.marking_weight.as_deref().map(|w| MarkingWeight::rename_place(w, rename)),
+                    ..arc.clone()
+                });
+            }
+        }
+        net.reindex_offsets();
+        net
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vasm::{StateMachine, Vasm};
+
+    use super::*;
+
+    fn review() -> PetriNet {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("in", None, None, 0, 0);
+            p.cell("reviewing", None, None, 0, 0);
+            p.cell("out", None, None, 0, 0);
+            p.func("start", "reviewer", 0, 0);
+            p.func("finish", "reviewer", 0, 0);
+            p.arrow("in", "start", 1);
+            p.arrow("start", "reviewing", 1);
+            p.arrow("reviewing", "finish", 1);
+            p.arrow("finish", "out", 1);
+        });
+        net
+    }
+
+    fn document() -> PetriNet {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("draft", Option::from(1), None, 0, 0);
+            p.cell("approved", None, None, 0, 0);
+            p.func("review", "default", 0, 0);
+            p.arrow("draft", "review", 1);
+            p.arrow("review", "approved", 1);
+        });
+        assert!(net.set_subnet("review", Subnet::new(review(), &[("in", "draft"), ("out", "approved")])));
+        net
+    }
+
+    #[test]
+    fn test_flatten_substitutes_transition() {
+        let net = document();
+        assert!(net.has_subnets());
+        let flat = net.flatten();
+        assert!(!flat.has_subnets());
+        assert_eq!(flat.places.len(), 3);
+        assert_eq!(flat.places["review.reviewing"].offset, 2);
+        let mut labels: Vec<&String> = flat.transitions.keys().collect();
+        labels.sort();
+        assert_eq!(labels, vec!["review.finish", "review.start"]);
+        assert_eq!(flat.arcs.len(), 4);
+        assert!(flat.arcs.iter().any(|a| a.source == "draft" && a.target == "review.start"));
+    }
+
+    #[test]
+    fn test_removed_socket_is_unmerged() {
+        let mut net = document();
+        assert!(net.remove_place("approved"));
+        assert_eq!(net.transitions["review"].subnet.as_ref().unwrap().ports.len(), 1);
+        let flat = net.flatten();
+        assert!(flat.places.contains_key("review.out") && !flat.places.contains_key("approved"));
+    }
+
+    #[test]
+    fn test_nested_subnets_and_vasm() {
+        let mut outer = PetriNet::new();
+        outer.declare(|p| {
+            p.cell("todo", Option::from(1), None, 0, 0);
+            p.cell("done", None, None, 0, 0);
+            p.func("process", "default", 0, 0);
+        });
+        outer.set_subnet("process", Subnet::new(document(), &[("draft", "todo"), ("approved", "done")]));
+
+        let sm = StateMachine::from_model(&mut outer.clone());
+        assert!(sm.transitions.contains_key("process.review.start"));
+        let state = sm.transform(&sm.initial_vector(), "process.review.start", 1).output;
+        let res = sm.transform(&state, "process.review.finish", 1);
+        assert!(res.is_ok());
+        assert_eq!(res.output[sm.places.iter().position(|p| p == "done").unwrap()], 1);
+    }
+
+    #[test]
+    fn test_subnet_round_trips_json() {
+        let net = PetriNet::from_json(document().to_json().unwrap()).unwrap();
+        assert!(net.has_subnets());
+        assert!(PetriNet::from_json(PetriNet::new().to_json().unwrap()).unwrap().transitions.is_empty());
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::analysis::{soundness, Limits, NetStructure};
+use crate::interchange::{parse_error, xml_escape, xml_id, InterchangeError};
+use crate::petri_net::PetriNet;
+
+/// `BpmnImport` is a workflow net imported from BPMN along with the constructs that were skipped.
+///
+/// The net has the `workflow` model type, unless the process forks concurrent branches, see `import`.
+#[derive(Debug, Clone)]
+pub struct BpmnImport {
+    pub net: PetriNet,
+    /// A description of each element that has no Petri-net mapping, its sequence flows are dropped.
+    pub unsupported: Vec<String>,
+}
+
+/// How a BPMN flow node is represented in the net.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    /// Start events, end events and exclusive gateways hold the token themselves.
+    Place,
+    /// Tasks, intermediate events and parallel gateways move tokens between flows.
+    Transition,
+}
+
+fn shape(element: &str) -> Option<Shape> {
+    match element {
+        "startEvent" | "endEvent" | "exclusiveGateway" => Some(Shape::Place),
+        "task" | "userTask" | "serviceTask" | "scriptTask" | "manualTask" | "sendTask" | "receiveTask"
+        | "businessRuleTask" | "callActivity" | "intermediateCatchEvent" | "intermediateThrowEvent"
+        | "parallelGateway" => Some(Shape::Transition),
+        _ => None,
+    }
+}
+
+/// Elements of a process that are neither flow nodes nor sequence flows but carry no behaviour.
+const IGNORED: &[&str] = &[
+    "sequenceFlow",
+    "laneSet",
+    "documentation",
+    "extensionElements",
+    "textAnnotation",
+    "association",
+    "dataObject",
+    "dataObjectReference",
+    "dataStoreReference",
+    "incoming",
+    "outgoing",
+];
+
+/// Imports the processes of a BPMN 2.0 document as a `workflow` net.
+///
+/// Workflow models hold a single token, so a process with a transition that forks or joins concurrent
+/// branches is imported with the `petriNet` model type instead, the structure of the net is the same.
+///
+/// Sequence flows between two transitions become places named after the flow, exclusive gateways
+/// become a single conflict place and parallel gateways a transition consuming from every incoming
+/// and producing to every outgoing flow. A task reached by several flows gets a `<id>.merge` place,
+/// since BPMN activities merge without synchronizing. Labels are element ids, roles are lane names
+/// and coordinates come from the diagram shapes when present.
+pub fn import(source: &str) -> Result<BpmnImport, InterchangeError> {
+    let doc = roxmltree::Document::parse(source)
+        .map_err(|e| parse_error(e.pos().row as usize, e.to_string()))?;
+    let mut net = PetriNet::new();
+    net.model_type = "workflow".to_string();
+    let mut unsupported = Vec::new();
+
+    let mut nodes: BTreeMap<String, (Shape, &str)> = BTreeMap::new();
+    let mut flows: Vec<(String, String, String)> = Vec::new();
+    let mut roles: HashMap<String, String> = HashMap::new();
+    let mut bounds: HashMap<String, (i32, i32)> = HashMap::new();
+
+    for node in doc.descendants().filter(|n| n.is_element()) {
+        match node.tag_name().name() {
+            "lane" => {
+                let name = node.attribute("name").or(node.attribute("id")).unwrap_or("default");
+                for r in node.children().filter(|c| c.tag_name().name() == "flowNodeRef") {
+                    roles.insert(r.text().unwrap_or("").trim().to_string(), name.to_string());
+                }
+            }
+            "BPMNShape" => {
+                let b = node.children().find(|c| c.tag_name().name() == "Bounds");
+                if let (Some(id), Some(b)) = (node.attribute("bpmnElement"), b) {
+                    let coordinate = |a: &str| b.attribute(a).and_then(|v| v.parse::<f64>().ok()).unwrap_or(0.0);
+                    let x = coordinate("x") + coordinate("width") / 2.0;
+                    let y = coordinate("y") + coordinate("height") / 2.0;
+                    bounds.insert(id.to_string(), (x as i32, y as i32));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for process in doc.descendants().filter(|n| n.tag_name().name() == "process") {
+        for node in process.children().filter(|n| n.is_element()) {
+            let element = node.tag_name().name();
+            let Some(id) = node.attribute("id") else { continue };
+            if element == "sequenceFlow" {
+                let (Some(s), Some(t)) = (node.attribute("sourceRef"), node.attribute("targetRef")) else {
+                    return Err(parse_error(node_line(&doc, node), format!("sequence flow {} without endpoints", id)));
+                };
+                flows.push((id.to_string(), s.to_string(), t.to_string()));
+            } else if let Some(shape) = shape(element) {
+                nodes.insert(id.to_string(), (shape, element));
+            } else if !IGNORED.contains(&element) {
+                unsupported.push(format!("{} {}", element, id));
+            }
+        }
+    }
+
+    let position = |id: &str| bounds.get(id).copied().unwrap_or((0, 0));
+    let role = |id: &str| roles.get(id).map_or("default", String::as_str).to_string();
+    let add_place = |net: &mut PetriNet, label: &str, initial: i32, at: (i32, i32)| {
+        let offset = net.places.len() as i32;
+        net.add_place(label, offset, Some(initial), None, at.0, at.1);
+    };
+
+    for (id, (shape, element)) in &nodes {
+        match shape {
+            Shape::Place => add_place(&mut net, id, i32::from(*element == "startEvent"), position(id)),
+            Shape::Transition => net.add_transition(id, &role(id), position(id).0, position(id).1),
+        }
+    }
+
+    let incoming = |id: &str| flows.iter().filter(|(_, _, t)| t == id).count();
+    let mut merges: BTreeMap<&String, String> = BTreeMap::new();
+    for (id, (shape, element)) in &nodes {
+        if *shape == Shape::Transition && *element != "parallelGateway" && incoming(id) > 1 {
+            let merge = format!("{}.merge", id);
+            add_place(&mut net, &merge, 0, position(id));
+            net.add_arc(&merge, id, Some(1), None, None, None, None);
+            merges.insert(id, merge);
+        }
+    }
+
+    for (id, source, target) in &flows {
+        let (Some((s, _)), Some((t, _))) = (nodes.get(source), nodes.get(target)) else {
+            unsupported.push(format!("sequenceFlow {} touching an unsupported element", id));
+            continue;
+        };
+        let (target, t) = match merges.get(target) {
+            Some(merge) => (merge, &Shape::Place),
+            None => (target, t),
+        };
+        match (s, t) {
+            (Shape::Transition, Shape::Transition) => {
+                add_place(&mut net, id, 0, (0, 0));
+                net.add_arc(source, id, Some(1), None, None, None, None);
+                net.add_arc(id, target, Some(1), None, None, None, None);
+            }
+            (Shape::Place, Shape::Place) => {
+                net.add_transition(id, &role(source), 0, 0);
+                net.add_arc(source, id, Some(1), None, None, None, None);
+                net.add_arc(id, target, Some(1), None, None, None, None);
+            }
+            _ => net.add_arc(source, target, Some(1), None, None, None, None),
+        }
+    }
+    net.populate_arc_attributes();
+    let s = NetStructure::from_net(&net);
+    if s.transitions.iter().any(|t| s.pre[t].len() > 1 || s.post[t].len() > 1) {
+        net.model_type = "petriNet".to_string();
+    }
+    Ok(BpmnImport { net, unsupported })
+}
+
+fn node_line(doc: &roxmltree::Document, node: roxmltree::Node) -> usize {
+    doc.text_pos_at(node.range().start).row as usize
+}
+
+/// Exports a sound workflow net as a BPMN 2.0 process.
+///
+/// The net must use the `workflow` model type, or the `petriNet` type for processes with concurrent
+/// branches, and have the shape checked by `workflow_shape`.
+///
+/// Transitions become tasks, in a lane per role. A place with one producer and one consumer becomes
+/// a sequence flow, any other place an exclusive gateway, and a transition with several input or
+/// output places is wrapped in parallel join and split gateways. The source and sink places become
+/// the start and end events.
+pub fn export(net: &PetriNet) -> Result<String, InterchangeError> {
+    if net.model_type != "workflow" && net.model_type != "petriNet" {
+        return Err(InterchangeError::Unsupported(format!("BPMN export needs a workflow net, not {}", net.model_type)));
+    }
+    if let Some(arc) = net.arcs.iter().find(|a| a.inhibit.unwrap_or(false) || a.weight.unwrap_or(1) != 1) {
+        return Err(InterchangeError::Unsupported(format!(
+            "BPMN has no weighted or guard arcs ({} -> {})",
+            arc.source, arc.target
+        )));
+    }
+    let report = soundness(net, Limits::default()).map_err(InterchangeError::Unsupported)?;
+    if !report.is_sound() {
+        return Err(InterchangeError::Unsupported("BPMN export needs a sound workflow net".to_string()));
+    }
+    let (source, sink) = (&report.shape.source, &report.shape.sink);
+    let s = NetStructure::from_net(net);
+
+    let mut elements: Vec<(String, String, String)> = Vec::new();
+    let mut flows: Vec<(String, String)> = Vec::new();
+    let mut lanes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    let join = |t: &String| if s.pre[t].len() > 1 { format!("{}.join", xml_id(t)) } else { xml_id(t) };
+    let split = |t: &String| if s.post[t].len() > 1 { format!("{}.split", xml_id(t)) } else { xml_id(t) };
+    let collapsed = |p: &String| p != source && p != sink && s.inputs[p].len() == 1 && s.outputs[p].len() == 1;
+    let place_in = |p: &String| xml_id(p);
+    let place_out = |p: &String| {
+        if p == source && s.outputs[p].len() > 1 {
+            format!("{}.choice", xml_id(p))
+        } else {
+            xml_id(p)
+        }
+    };
+
+    for t in &s.transitions {
+        let role = net.transitions[t].role.clone().unwrap_or_else(|| "default".to_string());
+        elements.push(("task".to_string(), xml_id(t), t.clone()));
+        lanes.entry(role).or_default().push(xml_id(t));
+        if s.pre[t].len() > 1 {
+            elements.push(("parallelGateway".to_string(), join(t), String::new()));
+            flows.push((join(t), xml_id(t)));
+        }
+        if s.post[t].len() > 1 {
+            elements.push(("parallelGateway".to_string(), split(t), String::new()));
+            flows.push((xml_id(t), split(t)));
+        }
+    }
+    for p in &s.places {
+        if collapsed(p) {
+            let producer = s.inputs[p].keys().next().unwrap();
+            let consumer = s.outputs[p].keys().next().unwrap();
+            flows.push((split(producer), join(consumer)));
+            continue;
+        }
+        let element = if p == source {
+            "startEvent"
+        } else if p == sink {
+            "endEvent"
+        } else {
+            "exclusiveGateway"
+        };
+        elements.push((element.to_string(), xml_id(p), p.clone()));
+        if place_out(p) != place_in(p) {
+            elements.push(("exclusiveGateway".to_string(), place_out(p), String::new()));
+            flows.push((place_in(p), place_out(p)));
+        }
+        for t in s.inputs[p].keys() {
+            flows.push((split(t), place_in(p)));
+        }
+        for t in s.outputs[p].keys() {
+            flows.push((place_out(p), join(t)));
+        }
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<bpmn:definitions xmlns:bpmn=\"http://www.omg.org/spec/BPMN/20100524/MODEL\" id=\"definitions\" targetNamespace=\"https://pflow.dev/bpmn\">\n");
+    xml.push_str("  <bpmn:process id=\"process\" isExecutable=\"false\">\n");
+    xml.push_str("    <bpmn:laneSet id=\"lanes\">\n");
+    for (i, (role, tasks)) in lanes.iter().enumerate() {
+        xml.push_str(&format!("      <bpmn:lane id=\"lane{}\" name=\"{}\">\n", i, xml_escape(role)));
+        for task in tasks {
+            xml.push_str(&format!("        <bpmn:flowNodeRef>{}</bpmn:flowNodeRef>\n", task));
+        }
+        xml.push_str("      </bpmn:lane>\n");
+    }
+    xml.push_str("    </bpmn:laneSet>\n");
+    for (element, id, name) in &elements {
+        if name.is_empty() {
+            xml.push_str(&format!("    <bpmn:{} id=\"{}\" />\n", element, id));
+        } else {
+            xml.push_str(&format!("    <bpmn:{} id=\"{}\" name=\"{}\" />\n", element, id, xml_escape(name)));
+        }
+    }
+    for (i, (source, target)) in flows.iter().enumerate() {
+        xml.push_str(&format!(
+            "    <bpmn:sequenceFlow id=\"flow{}\" sourceRef=\"{}\" targetRef=\"{}\" />\n",
+            i, source, target
+        ));
+    }
+    xml.push_str("  </bpmn:process>\n</bpmn:definitions>\n");
+    Ok(xml)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{explore, statistics};
+    use crate::vasm::StateMachine;
+
+    use super::*;
+
+    pub(crate) const ORDER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<bpmn:definitions xmlns:bpmn="http://www.omg.org/spec/BPMN/20100524/MODEL">
+  <bpmn:process id="order" isExecutable="false">
+    <bpmn:laneSet>
+      <bpmn:lane id="l1" name="clerk">
+        <bpmn:flowNodeRef>check</bpmn:flowNodeRef>
+      </bpmn:lane>
+    </bpmn:laneSet>
+    <bpmn:startEvent id="start" />
+    <bpmn:userTask id="check" name="Check order" />
+    <bpmn:exclusiveGateway id="ok" />
+    <bpmn:parallelGateway id="split" />
+    <bpmn:serviceTask id="ship" />
+    <bpmn:serviceTask id="bill" />
+    <bpmn:parallelGateway id="join" />
+    <bpmn:task id="reject" />
+    <bpmn:endEvent id="end" />
+    <bpmn:inclusiveGateway id="odd" />
+    <bpmn:sequenceFlow id="f1" sourceRef="start" targetRef="check" />
+    <bpmn:sequenceFlow id="f2" sourceRef="check" targetRef="ok" />
+    <bpmn:sequenceFlow id="f3" sourceRef="ok" targetRef="split" />
+    <bpmn:sequenceFlow id="f4" sourceRef="ok" targetRef="reject" />
+    <bpmn:sequenceFlow id="f5" sourceRef="split" targetRef="ship" />
+    <bpmn:sequenceFlow id="f6" sourceRef="split" targetRef="bill" />
+    <bpmn:sequenceFlow id="f7" sourceRef="ship" targetRef="join" />
+    <bpmn:sequenceFlow id="f8" sourceRef="bill" targetRef="join" />
+    <bpmn:sequenceFlow id="f9" sourceRef="join" targetRef="end" />
+    <bpmn:sequenceFlow id="f10" sourceRef="reject" targetRef="end" />
+    <bpmn:sequenceFlow id="f11" sourceRef="reject" targetRef="odd" />
+  </bpmn:process>
+</bpmn:definitions>"#;
+
+    #[test]
+    fn test_import_maps_gateways() {
+        let import = import(ORDER).unwrap();
+        let net = &import.net;
+        assert_eq!(import.unsupported, vec!["inclusiveGateway odd", "sequenceFlow f11 touching an unsupported element"]);
+        assert_eq!(net.model_type, "petriNet");
+        assert_eq!(net.places["start"].initial, Some(1));
+        assert_eq!(net.transitions["check"].role.as_deref(), Some("clerk"));
+        assert!(net.places.contains_key("ok"));
+        for flow in ["f5", "f6", "f7", "f8"] {
+            assert!(net.places.contains_key(flow));
+        }
+        assert!(net.arcs.iter().any(|a| a.source == "join" && a.target == "end"));
+
+        let sm = StateMachine::from_model(&mut net.clone());
+        let graph = explore(&sm, Limits::default());
+        let end = sm.places.iter().position(|p| p == "end").unwrap();
+        let finals: Vec<usize> = (0..graph.states.len()).filter(|i| graph.states[*i][end] == 1).collect();
+        assert_eq!(finals.len(), 1);
+        assert_eq!(graph.states[finals[0]].iter().sum::<i32>(), 1);
+        assert!(graph.successors[finals[0]].is_empty());
+    }
+
+    #[test]
+    fn test_implicit_merge_and_errors() {
+        let xml = r#"<definitions><process id="p">
+            <startEvent id="s" /><exclusiveGateway id="x" /><task id="t" /><endEvent id="e" />
+            <sequenceFlow id="a" sourceRef="s" targetRef="x" />
+            <sequenceFlow id="b" sourceRef="x" targetRef="t" />
+            <sequenceFlow id="c" sourceRef="s" targetRef="t" />
+            <sequenceFlow id="d" sourceRef="t" targetRef="e" />
+        </process></definitions>"#;
+        let net = import(xml).unwrap().net;
+        assert_eq!(net.model_type, "workflow");
+        assert!(net.transitions.contains_key("a"));
+        assert!(net.arcs.iter().any(|a| a.source == "x" && a.target == "b"));
+        assert!(net.arcs.iter().any(|a| a.source == "b" && a.target == "t.merge"));
+        assert!(net.arcs.iter().any(|a| a.source == "c" && a.target == "t.merge"));
+        assert!(net.arcs.iter().any(|a| a.source == "t.merge" && a.target == "t"));
+
+        let e = import("<definitions>\n<process>").unwrap_err();
+        assert!(matches!(e, InterchangeError::Parse(_)));
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let net = import(ORDER).unwrap().net;
+        let xml = export(&net).unwrap();
+        assert!(xml.contains("<bpmn:parallelGateway id=\"split.split\" />"));
+        assert!(xml.contains("<bpmn:exclusiveGateway id=\"ok\" name=\"ok\" />"));
+        assert!(xml.contains("<bpmn:lane id=\"lane0\" name=\"clerk\">"));
+
+        let back = import(&xml).unwrap();
+        assert!(back.unsupported.is_empty());
+        let states = |n: &PetriNet| statistics(&StateMachine::from_model(&mut n.clone()), Limits::default()).states;
+        assert_eq!(states(&back.net), states(&net) + 2);
+        assert!(soundness(&back.net, Limits::default()).unwrap().is_sound());
+    }
+
+    #[test]
+    fn test_export_rejects_unsound_nets() {
+        let net = import(ORDER).unwrap().net;
+        assert!(export(&net).is_ok());
+
+        let mut elementary = net.clone();
+        elementary.model_type = "elementary".to_string();
+        assert!(matches!(export(&elementary), Err(InterchangeError::Unsupported(_))));
+
+        let mut broken = net.clone();
+        broken.remove_transition("bill");
+        assert!(matches!(export(&broken), Err(InterchangeError::Unsupported(_))));
+
+        let mut deadlocked = net;
+        deadlocked.add_arc("f6", "ship", Some(1), None, None, None, None);
+        deadlocked.populate_arc_attributes();
+        let e = export(&deadlocked).unwrap_err();
+        assert_eq!(e.to_string(), "unsupported construct: BPMN export needs a sound workflow net");
+    }
+}
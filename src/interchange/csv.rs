@@ -0,0 +1,407 @@
+use crate::builder::StateMachineBuilder;
+use crate::capacity::Capacity;
+use crate::interchange::{parse_error, InterchangeError};
+use crate::petri_net::DEFAULT_ROLE;
+use crate::vasm::{ModelType, StateMachine, Vector};
+
+/// Quotes a field if it holds a separator, a quote or surrounding spaces, doubling its quotes.
+fn field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) || text.trim() != text {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn row<'a>(fields: impl IntoIterator<Item = &'a str>) -> String {
+    let fields: Vec<String> = fields.into_iter().map(field).collect();
+    fields.join(",") + "\n"
+}
+
+/// Splits a line into fields, trimming the unquoted ones and unquoting the quoted ones, which keep their spaces.
+fn fields(line: &str, line_no: usize) -> Result<Vec<String>, InterchangeError> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        let mut current = String::new();
+        while chars.next_if(|c| *c != ',' && c.is_whitespace()).is_some() {}
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        current.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => current.push(c),
+                    None => return Err(parse_error(line_no, "unterminated quoted field".to_string())),
+                }
+            }
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                if !c.is_whitespace() {
+                    return Err(parse_error(line_no, "unexpected text after a quoted field".to_string()));
+                }
+            }
+            fields.push(current);
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                current.push(c);
+            }
+            fields.push(current.trim().to_string());
+        }
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+/// Exports the state machine as a CSV incidence matrix with one column per place in offset order.
+///
+/// The header row names the places, followed by an `initial` row with their tokens, a `capacity` row with
+/// their bounds, left empty when unbounded, and one row per transition in label order with its role and the
+/// change of each place when it fires.
+///
+/// ```csv
+/// transition,role,waiting,served
+/// initial,,3,0
+/// capacity,,,2
+/// serve,clerk,-1,1
+/// ```
+///
+/// A matrix has no room for read, inhibitor, reset, transfer or marking weighted arcs, state machines
+/// using them are rejected, as are labels and roles holding line breaks, which would split their row.
+/// The matrix is read back as a petri-net, so other model types, reentrant transitions and custom
+/// semantics are rejected too.
+pub fn to_csv(sm: &StateMachine) -> Result<String, InterchangeError> {
+    if !matches!(sm.model_type, ModelType::PetriNet) || sm.semantics.is_some() {
+        return Err(InterchangeError::Unsupported(
+            "CSV matrices only hold petri-nets with the default semantics".to_string(),
+        ));
+    }
+    let mut labels: Vec<&String> = sm.transitions.keys().collect();
+    labels.sort();
+    for label in &labels {
+        let t = &sm.transitions[*label];
+        if !t.guards().is_empty() || !t.transfers().is_empty() || !t.marking_arcs().is_empty() {
+            return Err(InterchangeError::Unsupported(format!(
+                "CSV matrices only hold arc weights ({})",
+                label
+            )));
+        }
+        if t.allow_reentry() {
+            return Err(InterchangeError::Unsupported(format!(
+                "CSV matrices cannot mark reentrant transitions ({})",
+                label
+            )));
+        }
+    }
+    let transitions = labels.iter().flat_map(|label| [label.as_str(), sm.transitions[*label].role()]);
+    let mut texts = sm.places.iter().map(String::as_str).chain(transitions);
+    if let Some(text) = texts.find(|text| text.contains(['\n', '\r'])) {
+        return Err(InterchangeError::Unsupported(format!(
+            "CSV rows cannot hold line breaks ({:?})",
+            text
+        )));
+    }
+
+    let mut out = row(["transition", "role"]
+        .into_iter()
+        .chain(sm.places.iter().map(String::as_str)));
+    let initial: Vec<String> = sm.initial.iter().map(ToString::to_string).collect();
+    out.push_str(&row(["initial", ""]
+        .into_iter()
+        .chain(initial.iter().map(String::as_str))));
+    let capacity: Vec<String> = sm
+        .capacity
+        .iter()
+        .map(|c| c.limit().map(|l| l.to_string()).unwrap_or_default())
+        .collect();
+    out.push_str(&row(["capacity", ""]
+        .into_iter()
+        .chain(capacity.iter().map(String::as_str))));
+    for label in labels {
+        let t = &sm.transitions[label];
+        let delta: Vec<String> = (0..sm.places.len())
+            .map(|i| t.delta().get(i).copied().unwrap_or(0).to_string())
+            .collect();
+        out.push_str(&row([label.as_str(), t.role()]
+            .into_iter()
+            .chain(delta.iter().map(String::as_str))));
+    }
+    Ok(out)
+}
+
+/// Imports a petri-net state machine from a CSV incidence matrix in the layout written by `to_csv`.
+///
+/// Empty cells of the matrix count as zero, an empty role as the default role, and blank lines are skipped.
+pub fn from_csv(source: &str) -> Result<StateMachine, InterchangeError> {
+    let mut lines = source
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty());
+    let (header_no, header) = lines
+        .next()
+        .ok_or_else(|| parse_error(1, "missing header row".to_string()))?;
+    let mut places = fields(header, header_no)?;
+    if places.len() < 2 {
+        return Err(parse_error(
+            header_no,
+            "expected the transition and role columns".to_string(),
+        ));
+    }
+    let places = places.split_off(2);
+    let mut cells = || -> Result<Option<(usize, Vec<String>)>, InterchangeError> {
+        let Some((line_no, line)) = lines.next() else {
+            return Ok(None);
+        };
+        let row = fields(line, line_no)?;
+        if row.len() != places.len() + 2 {
+            let message = format!("expected {} fields, found {}", places.len() + 2, row.len());
+            return Err(parse_error(line_no, message));
+        }
+        Ok(Some((line_no, row)))
+    };
+    let number = |cell: &str, line_no: usize| -> Result<i32, InterchangeError> {
+        match cell {
+            "" => Ok(0),
+            n => n
+                .parse()
+                .map_err(|_| parse_error(line_no, format!("invalid number `{}`", n))),
+        }
+    };
+
+    let missing = |name: &str| parse_error(header_no, format!("missing {} row", name));
+    let (initial_no, initial) = cells()?.ok_or_else(|| missing("initial"))?;
+    let (capacity_no, capacity) = cells()?.ok_or_else(|| missing("capacity"))?;
+    for (line_no, row, name) in [(initial_no, &initial, "initial"), (capacity_no, &capacity, "capacity")] {
+        if row[0] != name {
+            return Err(parse_error(line_no, format!("expected the {} row", name)));
+        }
+    }
+    let mut builder = StateMachineBuilder::new(ModelType::PetriNet);
+    for (i, place) in places.iter().enumerate() {
+        let tokens = number(&initial[i + 2], initial_no)?;
+        let limit = number(&capacity[i + 2], capacity_no)?;
+        let capacity = match capacity[i + 2].as_str() {
+            "" => Capacity::Unbounded,
+            _ if limit >= 0 => Capacity::Bounded(limit as u32),
+            _ => return Err(parse_error(capacity_no, format!("negative capacity of {}", place))),
+        };
+        builder = builder.place(place, tokens, capacity);
+    }
+    while let Some((line_no, row)) = cells()? {
+        let delta = row[2..]
+            .iter()
+            .map(|cell| number(cell, line_no))
+            .collect::<Result<Vector, _>>()?;
+        let role = if row[1].is_empty() {
+            DEFAULT_ROLE
+        } else {
+            row[1].as_str()
+        };
+        builder = builder.transition(&row[0], role, delta);
+    }
+    builder.build().map_err(|e| parse_error(header_no, e.to_string()))
+}
+
+/// Exports markings as CSV, with a header row naming the places in offset order and one row per marking.
+///
+/// Place labels holding line breaks are quoted but cannot be read back by `markings_from_csv`.
+pub fn markings_to_csv(sm: &StateMachine, markings: &[Vector]) -> String {
+    let mut out = row(sm.places.iter().map(String::as_str));
+    for marking in markings {
+        let tokens: Vec<String> = marking.iter().map(ToString::to_string).collect();
+        out.push_str(&row(tokens.iter().map(String::as_str)));
+    }
+    out
+}
+
+/// Imports markings of the state machine from CSV, with a header row naming places in any order and one
+/// row per marking, places left out of the header holding no tokens.
+pub fn markings_from_csv(sm: &StateMachine, source: &str) -> Result<Vec<Vector>, InterchangeError> {
+    let mut lines = source
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty());
+    let Some((header_no, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let offsets = fields(header, header_no)?
+        .iter()
+        .map(|label| {
+            let offset = sm.places.iter().position(|p| p == label);
+            offset.ok_or_else(|| parse_error(header_no, format!("unknown place `{}`", label)))
+        })
+        .collect::<Result<Vec<usize>, _>>()?;
+    let mut markings = Vec::new();
+    for (line_no, line) in lines {
+        let row = fields(line, line_no)?;
+        if row.len() != offsets.len() {
+            let message = format!("expected {} fields, found {}", offsets.len(), row.len());
+            return Err(parse_error(line_no, message));
+        }
+        let mut marking = vec![0; sm.places.len()];
+        for (offset, cell) in offsets.iter().zip(&row) {
+            marking[*offset] = match cell.as_str() {
+                "" => 0,
+                n => n
+                    .parse()
+                    .map_err(|_| parse_error(line_no, format!("invalid number `{}`", n)))?,
+            };
+        }
+        markings.push(marking);
+    }
+    Ok(markings)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::petri_net::PetriNet;
+    use crate::vasm::Vasm;
+
+    use super::*;
+
+    fn queue() -> StateMachine {
+        StateMachine::new(|p| {
+            p.cell("waiting", Option::from(3), None, 0, 0);
+            p.cell("served, today", None, Option::from(2), 0, 0);
+            p.func("serve", "clerk", 0, 0);
+            p.func("arrive", "default", 0, 0);
+            p.arrow("waiting", "serve", 1);
+            p.arrow("serve", "served, today", 1);
+            p.arrow("arrive", "waiting", 1);
+        })
+    }
+
+    #[test]
+    fn test_to_csv() {
+        assert_eq!(
+            to_csv(&queue()).unwrap(),
+            [
+                "transition,role,waiting,\"served, today\"",
+                "initial,,3,0",
+                "capacity,,,2",
+                "arrive,default,1,0",
+                "serve,clerk,-1,1",
+                "",
+            ]
+            .join("\n")
+        );
+        let mut guarded = StateMachine::new(|p| {
+            p.cell("a", None, None, 0, 0);
+            p.func("t", "default", 0, 0);
+            p.guard("a", "t", 1);
+        });
+        assert!(matches!(to_csv(&guarded), Err(InterchangeError::Unsupported(_))));
+        guarded.transitions.clear();
+        assert!(to_csv(&guarded).is_ok());
+        guarded.places[0] = "a\nb".to_string();
+        assert_eq!(
+            to_csv(&guarded),
+            Err(InterchangeError::Unsupported("CSV rows cannot hold line breaks (\"a\\nb\")".to_string()))
+        );
+        let mut queue = queue();
+        queue.transitions.get_mut("serve").unwrap().role = "front\rdesk".to_string();
+        assert!(matches!(to_csv(&queue), Err(InterchangeError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_csv_rejects_what_it_cannot_read_back() {
+        let workflow = StateMachine::new(|p| {
+            p.model_type("workflow");
+            p.cell("open", Option::from(1), None, 0, 0);
+            p.func("close", "default", 0, 0);
+            p.arrow("open", "close", 1);
+        });
+        assert!(matches!(to_csv(&workflow), Err(InterchangeError::Unsupported(_))));
+
+        let mut reentrant = queue();
+        reentrant.transitions.get_mut("serve").unwrap().allow_reentry = true;
+        assert_eq!(
+            to_csv(&reentrant),
+            Err(InterchangeError::Unsupported(
+                "CSV matrices cannot mark reentrant transitions (serve)".to_string()
+            ))
+        );
+
+        let sm = queue();
+        let back = from_csv(&to_csv(&sm).unwrap()).unwrap();
+        assert!(matches!(back.model_type, ModelType::PetriNet));
+        assert!(back.transitions.values().all(|t| !t.allow_reentry()));
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let philosophers = StateMachine::from_model(&mut PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap());
+        for sm in [queue(), philosophers] {
+            let back = from_csv(&to_csv(&sm).unwrap()).unwrap();
+            assert_eq!(
+                (&back.places, &back.initial, &back.capacity),
+                (&sm.places, &sm.initial, &sm.capacity)
+            );
+            assert_eq!(back.transitions.len(), sm.transitions.len());
+            for (label, t) in &sm.transitions {
+                assert_eq!(back.transitions[label].delta(), t.delta());
+                assert_eq!(back.transitions[label].role(), t.role());
+            }
+            assert_eq!(to_csv(&back).unwrap(), to_csv(&sm).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_csv_keeps_quoted_spaces() {
+        let sm = StateMachineBuilder::new(ModelType::PetriNet)
+            .place(" a", 1, Capacity::Unbounded)
+            .place("b ", 0, Capacity::Unbounded)
+            .transition(" t ", " clerk", vec![-1, 1])
+            .build()
+            .unwrap();
+        let csv = to_csv(&sm).unwrap();
+        assert!(csv.starts_with("transition,role,\" a\",\"b \"\n"));
+        let back = from_csv(&csv).unwrap();
+        assert_eq!(back.places, sm.places);
+        assert_eq!(back.transitions[" t "].role(), " clerk");
+
+        let sm = from_csv("transition,role, \" a\" ,b\ninitial,, 1 ,\ncapacity,,,\n").unwrap();
+        assert_eq!(sm.places, vec![" a", "b"]);
+        assert_eq!(sm.initial_vector(), vec![1, 0]);
+        assert!(from_csv("transition,role,\"a\"b\ninitial,,1\ncapacity,,\n").is_err());
+    }
+
+    #[test]
+    fn test_from_csv_errors() {
+        let sm = from_csv("transition,role,a,b\n\ninitial,,1,\ncapacity,,,\nt,,-1,1\n").unwrap();
+        assert_eq!(sm.initial_vector(), vec![1, 0]);
+        assert_eq!(sm.transitions["t"].role(), DEFAULT_ROLE);
+
+        let error = |source: &str| match from_csv(source) {
+            Err(InterchangeError::Parse(e)) => (e.line, e.message),
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(error("transition,role,a\ninitial,,1\n").1, "missing capacity row");
+        assert_eq!(
+            error("transition,role,a\ninitial,,x\ncapacity,,\n"),
+            (2, "invalid number `x`".to_string())
+        );
+        assert_eq!(error("transition,role,a\ncapacity,,\ninitial,,1\n").0, 2);
+        assert_eq!(error("transition,role,a\ninitial,,1\ncapacity,,\nt,,1,2\n").0, 4);
+        assert_eq!(error("transition,role,a\ninitial,,1\ncapacity,,-1\n").0, 3);
+        assert!(error("transition,role,a,a\ninitial,,1,1\ncapacity,,,\n")
+            .1
+            .contains("declared twice"));
+    }
+
+    #[test]
+    fn test_markings_csv() {
+        let sm = queue();
+        let csv = markings_to_csv(&sm, &[vec![3, 0], vec![2, 1]]);
+        assert_eq!(csv, "waiting,\"served, today\"\n3,0\n2,1\n");
+        assert_eq!(markings_from_csv(&sm, &csv).unwrap(), vec![vec![3, 0], vec![2, 1]]);
+        let reordered = markings_from_csv(&sm, "\"served, today\"\n1\n").unwrap();
+        assert_eq!(reordered, vec![vec![0, 1]]);
+        assert!(markings_from_csv(&sm, "waiting,queue\n1,2\n").is_err());
+    }
+}
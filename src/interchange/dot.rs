@@ -0,0 +1,422 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::interchange::{parse_error, InterchangeError};
+use crate::layout;
+use crate::petri_net::PetriNet;
+
+/// `DotImport` is a net imported from GraphViz DOT along with the notes about what was guessed or skipped.
+#[derive(Debug, Clone)]
+pub struct DotImport {
+    pub net: PetriNet,
+    pub warnings: Vec<String>,
+}
+
+type Attributes = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Id(String),
+    Punct(&'static str),
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, InterchangeError> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = source.chars().collect();
+    let (mut i, mut line) = (0, 1);
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '#' || (c == '/' && next == Some('/')) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                line += usize::from(chars[i] == '\n');
+                i += 1;
+            }
+            i += 2;
+        } else if c == '"' {
+            let start = line;
+            let mut id = String::new();
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                if chars[i] == '\\' && chars.get(i + 1) == Some(&'"') {
+                    i += 1;
+                }
+                line += usize::from(chars[i] == '\n');
+                id.push(chars[i]);
+                i += 1;
+            }
+            if i == chars.len() {
+                return Err(parse_error(start, "unterminated string".to_string()));
+            }
+            i += 1;
+            tokens.push((start, Token::Id(id)));
+        } else if c == '<' {
+            let (start, mut depth) = (line, 0);
+            let mut id = String::new();
+            while i < chars.len() {
+                depth += i32::from(chars[i] == '<') - i32::from(chars[i] == '>');
+                line += usize::from(chars[i] == '\n');
+                id.push(chars[i]);
+                i += 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            tokens.push((start, Token::Id(id)));
+        } else if c == '-' && (next == Some('>') || next == Some('-')) {
+            tokens.push((line, Token::Punct("->")));
+            i += 2;
+        } else if let Some(p) = ["{", "}", "[", "]", "=", ";", ",", ":"].iter().find(|p| p.starts_with(c)) {
+            tokens.push((line, Token::Punct(p)));
+            i += 1;
+        } else if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' {
+            let mut id = String::new();
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.' || chars[i] == '-') {
+                if chars[i] == '-' && matches!(chars.get(i + 1), Some('>') | Some('-')) {
+                    break;
+                }
+                id.push(chars[i]);
+                i += 1;
+            }
+            tokens.push((line, Token::Id(id)));
+        } else {
+            return Err(parse_error(line, format!("unexpected character `{}`", c)));
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<(String, Attributes)>,
+    edges: Vec<(String, String, Attributes)>,
+    node_defaults: Attributes,
+    edge_defaults: Attributes,
+}
+
+impl Graph {
+    fn node(&mut self, id: &str, attrs: &Attributes) {
+        match self.nodes.iter_mut().find(|(n, _)| n == id) {
+            Some((_, existing)) => existing.extend(attrs.clone()),
+            None => {
+                let mut all = self.node_defaults.clone();
+                all.extend(attrs.clone());
+                self.nodes.push((id.to_string(), all));
+            }
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos).or(self.tokens.last()).map_or(1, |(l, _)| *l)
+    }
+
+    fn eat(&mut self, punct: &'static str) -> bool {
+        if self.peek() == Some(&Token::Punct(punct)) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn id(&mut self) -> Result<String, InterchangeError> {
+        match self.tokens.get(self.pos) {
+            Some((_, Token::Id(id))) => {
+                self.pos += 1;
+                Ok(id.clone())
+            }
+            Some((line, Token::Punct(p))) => Err(parse_error(*line, format!("expected an identifier, found `{}`", p))),
+            None => Err(parse_error(self.line(), "unexpected end of input".to_string())),
+        }
+    }
+
+    fn expect(&mut self, punct: &'static str) -> Result<(), InterchangeError> {
+        if self.eat(punct) {
+            return Ok(());
+        }
+        Err(parse_error(self.line(), format!("expected `{}`", punct)))
+    }
+
+    fn attributes(&mut self) -> Result<Attributes, InterchangeError> {
+        let mut attrs = Attributes::new();
+        while self.eat("[") {
+            while !self.eat("]") {
+                let key = self.id()?;
+                self.expect("=")?;
+                attrs.insert(key.to_lowercase(), self.id()?);
+                let _ = self.eat(",") || self.eat(";");
+            }
+        }
+        Ok(attrs)
+    }
+
+    /// Parses a node id, dropping any `:port:compass` suffix.
+    fn node_id(&mut self) -> Result<String, InterchangeError> {
+        let id = self.id()?;
+        while self.eat(":") {
+            self.id()?;
+        }
+        Ok(id)
+    }
+
+    fn statements(&mut self, graph: &mut Graph) -> Result<(), InterchangeError> {
+        while !self.eat("}") {
+            if self.peek().is_none() {
+                return Err(parse_error(self.line(), "expected `}`".to_string()));
+            }
+            if self.eat(";") {
+                continue;
+            }
+            let first = self.id()?;
+            match first.to_lowercase().as_str() {
+                "node" if self.peek() == Some(&Token::Punct("[")) => {
+                    let attrs = self.attributes()?;
+                    graph.node_defaults.extend(attrs);
+                    continue;
+                }
+                "edge" if self.peek() == Some(&Token::Punct("[")) => {
+                    let attrs = self.attributes()?;
+                    graph.edge_defaults.extend(attrs);
+                    continue;
+                }
+                "graph" if self.peek() == Some(&Token::Punct("[")) => {
+                    self.attributes()?;
+                    continue;
+                }
+                "subgraph" => {
+                    if self.peek() != Some(&Token::Punct("{")) {
+                        self.id()?;
+                    }
+                    self.expect("{")?;
+                    // node and edge defaults set inside the braces only apply to the subgraph
+                    let defaults = (graph.node_defaults.clone(), graph.edge_defaults.clone());
+                    self.statements(graph)?;
+                    (graph.node_defaults, graph.edge_defaults) = defaults;
+                    continue;
+                }
+                _ => {}
+            }
+            if self.eat("=") {
+                self.id()?;
+                continue;
+            }
+            while self.eat(":") {
+                self.id()?;
+            }
+            let mut chain = vec![first];
+            while self.eat("->") {
+                chain.push(self.node_id()?);
+            }
+            let attrs = self.attributes()?;
+            if chain.len() == 1 {
+                graph.node(&chain[0], &attrs);
+            } else {
+                for pair in chain.windows(2) {
+                    graph.node(&pair[0], &Attributes::new());
+                    graph.node(&pair[1], &Attributes::new());
+                    let mut all = graph.edge_defaults.clone();
+                    all.extend(attrs.clone());
+                    graph.edges.push((pair[0].clone(), pair[1].clone(), all));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Place,
+    Transition,
+}
+
+fn kind_of_shape(shape: &str) -> Option<Kind> {
+    match shape {
+        "circle" | "ellipse" | "oval" | "doublecircle" | "point" => Some(Kind::Place),
+        "box" | "rect" | "rectangle" | "square" | "record" | "box3d" => Some(Kind::Transition),
+        _ => None,
+    }
+}
+
+fn number(attrs: &Attributes, keys: &[&str]) -> Option<i32> {
+    keys.iter().find_map(|k| attrs.get(*k)).and_then(|v| v.trim().parse().ok())
+}
+
+/// Imports a GraphViz graph drawn with Petri-net conventions.
+///
+/// Circles and ellipses are places and boxes are transitions. Nodes without a known shape take the kind
+/// opposite to their neighbours, falling back to places. The `tokens` (or `marking`) and `capacity`
+/// node attributes set the marking, the `weight` (or a numeric `label`) edge attribute the arc weight,
+/// and an `odot` arrowhead makes an inhibitor arc. Nodes are placed from their `pos` attribute,
+/// or laid out automatically when no node has one.
+pub fn import(source: &str) -> Result<DotImport, InterchangeError> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        pos: 0,
+    };
+    let mut header = parser.id()?;
+    if header.eq_ignore_ascii_case("strict") {
+        header = parser.id()?;
+    }
+    if !header.eq_ignore_ascii_case("digraph") && !header.eq_ignore_ascii_case("graph") {
+        return Err(parse_error(1, format!("expected `graph` or `digraph`, found `{}`", header)));
+    }
+    if parser.peek() != Some(&Token::Punct("{")) {
+        parser.id()?;
+    }
+    parser.expect("{")?;
+    let mut graph = Graph::default();
+    parser.statements(&mut graph)?;
+
+    let mut warnings = Vec::new();
+    let mut kinds: HashMap<&String, Kind> = HashMap::new();
+    for (id, attrs) in &graph.nodes {
+        if let Some(kind) = attrs.get("shape").and_then(|s| kind_of_shape(s)) {
+            kinds.insert(id, kind);
+        }
+    }
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (a, b, _) in &graph.edges {
+            for (known, other) in [(a, b), (b, a)] {
+                if let (Some(kind), false) = (kinds.get(known).copied(), kinds.contains_key(other)) {
+                    let opposite = if kind == Kind::Place { Kind::Transition } else { Kind::Place };
+                    kinds.insert(other, opposite);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    let mut net = PetriNet::new();
+    let mut placed = false;
+    for (id, attrs) in &graph.nodes {
+        let (x, y) = match attrs.get("pos").and_then(|p| p.trim_end_matches('!').split_once(',')) {
+            Some((x, y)) => {
+                placed = true;
+                (x.trim().parse::<f64>().unwrap_or(0.0) as i32, y.trim().parse::<f64>().unwrap_or(0.0) as i32)
+            }
+            None => (0, 0),
+        };
+        let kind = match kinds.get(id) {
+            Some(kind) => *kind,
+            None => {
+                warnings.push(format!("{} has no shape or typed neighbour, assumed to be a place", id));
+                Kind::Place
+            }
+        };
+        match kind {
+            Kind::Place => {
+                let offset = net.places.len() as i32;
+                let initial = number(attrs, &["tokens", "marking"]).unwrap_or(0);
+                net.add_place(id, offset, Some(initial), number(attrs, &["capacity"]), x, y);
+            }
+            Kind::Transition => {
+                let role = attrs.get("role").map_or("default", String::as_str);
+                net.add_transition(id, role, x, y);
+            }
+        }
+    }
+
+    for (source, target, attrs) in &graph.edges {
+        let source_is_place = net.places.contains_key(source);
+        if source_is_place == net.places.contains_key(target) {
+            warnings.push(format!("skipped the edge {} -> {} between two nodes of the same kind", source, target));
+            continue;
+        }
+        let weight = number(attrs, &["weight", "label"]).filter(|w| *w > 0).unwrap_or(1);
+        if attrs.get("arrowhead").is_some_and(|a| a == "odot") {
+            net.add_arc(source, target, Some(weight), Some(true), None, Some(true), None);
+        } else {
+            net.add_arc(source, target, Some(weight), None, None, None, None);
+        }
+    }
+    net.populate_arc_attributes();
+    if !placed {
+        layout::auto(&mut net);
+    }
+    Ok(DotImport { net, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::capacity::Capacity;
+
+    use super::*;
+
+    #[test]
+    fn test_import_conventions() {
+        let import = import(
+            r#"
+            // order handling
+            digraph "order" {
+                rankdir=LR;
+                node [shape=circle];
+                new [tokens=2, pos="10,20!"];
+                paid [capacity=1];
+                pay [shape=box, role=customer];
+                cancel [shape=box];
+                new -> pay -> paid [weight=2];
+                new -> cancel;
+                cancel -> cancelled;
+                paid -> cancel [arrowhead=odot];
+                new -> paid;
+            }"#,
+        )
+        .unwrap();
+        let net = &import.net;
+        assert_eq!(import.warnings, vec!["skipped the edge new -> paid between two nodes of the same kind"]);
+        assert_eq!(net.places["new"].initial, Some(2));
+        assert_eq!((net.places["new"].x, net.places["new"].y), (10, 20));
+        assert_eq!(net.places["paid"].capacity, Some(Capacity::Bounded(1)));
+        assert_eq!(net.transitions["pay"].role.as_deref(), Some("customer"));
+        assert!(net.places.contains_key("cancelled"));
+        assert_eq!(net.arcs.len(), 5);
+        assert_eq!(net.arcs[1].weight, Some(2));
+        assert_eq!(net.arcs[4].inhibit, Some(true));
+    }
+
+    #[test]
+    fn test_infers_kinds_from_neighbours() {
+        let import = import("digraph { a [shape=box]; p -> a -> q; q -> b; z }").unwrap();
+        let net = &import.net;
+        assert!(net.transitions.contains_key("a"));
+        assert!(net.places.contains_key("p") && net.places.contains_key("q"));
+        assert!(net.transitions.contains_key("b"));
+        assert_eq!(import.warnings, vec!["z has no shape or typed neighbour, assumed to be a place"]);
+        assert!(!layout::is_unplaced(net));
+
+        let source = "digraph { subgraph s { node [shape=box]; edge [weight=2]; p [shape=circle]; p -> t } u; t -> q }";
+        let scoped = super::import(source).unwrap();
+        assert!(scoped.net.transitions.contains_key("t"));
+        assert_eq!(scoped.warnings, vec!["u has no shape or typed neighbour, assumed to be a place"]);
+        assert_eq!(scoped.net.arcs.iter().map(|a| a.weight).collect::<Vec<_>>(), vec![Some(2), Some(1)]);
+
+        assert!(matches!(import_err("digraph { a -> }"), InterchangeError::Parse(_)));
+        assert!(matches!(import_err("digraph {\n a [label=\"x]\n}"), InterchangeError::Parse(p) if p.line == 2));
+    }
+
+    fn import_err(source: &str) -> InterchangeError {
+        import(source).unwrap_err()
+    }
+}
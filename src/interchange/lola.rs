@@ -0,0 +1,279 @@
+use crate::interchange::{flows, parse_error, places_in_order, InterchangeError};
+use crate::petri_net::PetriNet;
+
+const KEYWORDS: &[&str] = &["PLACE", "SAFE", "MARKING", "TRANSITION", "CONSUME", "PRODUCE"];
+
+/// Writes a LoLA identifier, which cannot be quoted, so each byte of a delimiter, a space, a brace
+/// or a `%` is written as `%` and two hex digits, as is the first letter of a name that is a keyword.
+fn name(label: &str) -> String {
+    let keyword = KEYWORDS.contains(&label);
+    let mut out = String::new();
+    for (i, c) in label.chars().enumerate() {
+        if (keyword && i == 0) || c.is_whitespace() || ",;:(){}%".contains(c) {
+            let mut bytes = [0; 4];
+            c.encode_utf8(&mut bytes).bytes().for_each(|b| out.push_str(&format!("%{:02X}", b)));
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Reads back an identifier written by `name`.
+fn unescape(token: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut rest = token.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let hex = tail.get(..2).and_then(|h| u8::from_str_radix(std::str::from_utf8(h).ok()?, 16).ok());
+        match (b, hex) {
+            (b'%', Some(decoded)) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn entries(side: &std::collections::BTreeMap<i32, (String, i32)>) -> String {
+    side.values().map(|(p, w)| format!("{}: {}", name(p), w)).collect::<Vec<_>>().join(", ")
+}
+
+/// Exports the net in the LoLA format, capacities become `SAFE` place groups.
+///
+/// LoLA has no inhibitor or read arcs, nets using them are rejected. Names are escaped, see `name`.
+pub fn export(net: &PetriNet) -> Result<String, InterchangeError> {
+    if let Some(arc) = net.arcs.iter().find(|a| a.inhibit.unwrap_or(false)) {
+        return Err(InterchangeError::Unsupported(format!(
+            "LoLA has no inhibitor or read arcs ({} -> {})",
+            arc.source, arc.target
+        )));
+    }
+    let places = places_in_order(net);
+
+    let mut out = String::from("PLACE\n");
+    let mut groups: Vec<(i32, Vec<&String>)> = Vec::new();
+    for p in &places {
+        let capacity = match net.places[*p].capacity.unwrap_or_default().limit() {
+            Some(0) => return Err(InterchangeError::Unsupported(format!("LoLA has no zero capacities ({})", p))),
+            limit => limit.unwrap_or(0),
+        };
+        match groups.last_mut() {
+            Some((c, group)) if *c == capacity => group.push(p),
+            _ => groups.push((capacity, vec![p])),
+        }
+    }
+    for (capacity, group) in groups {
+        let names: Vec<String> = group.iter().map(|p| name(p)).collect();
+        if capacity > 0 {
+            out.push_str(&format!("  SAFE {}: {};\n", capacity, names.join(", ")));
+        } else {
+            out.push_str(&format!("  {};\n", names.join(", ")));
+        }
+    }
+
+    let marking: Vec<String> = places
+        .iter()
+        .filter_map(|p| net.places[*p].initial.filter(|m| *m > 0).map(|m| format!("{}: {}", name(p), m)))
+        .collect();
+    out.push_str(&format!("\nMARKING\n  {};\n", marking.join(", ")));
+
+    for (t, flow) in flows(net) {
+        out.push_str(&format!("\nTRANSITION {}\n", name(t)));
+        out.push_str(&format!("  CONSUME {};\n", entries(&flow.consume)));
+        out.push_str(&format!("  PRODUCE {};\n", entries(&flow.produce)));
+    }
+    Ok(out)
+}
+
+/// Splits a LoLA document into (line, token) pairs, dropping `{ ... }` comments.
+fn tokenize(source: &str) -> Vec<(usize, String)> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut comment = false;
+    let mut line = 1;
+    for c in source.chars() {
+        if comment {
+            comment = c != '}';
+        } else if c == '{' || c.is_whitespace() || ",;:".contains(c) {
+            if !current.is_empty() {
+                tokens.push((line, std::mem::take(&mut current)));
+            }
+            comment = c == '{';
+            if ",;:".contains(c) {
+                tokens.push((line, c.to_string()));
+            }
+        } else {
+            current.push(c);
+        }
+        if c == '\n' {
+            line += 1;
+        }
+    }
+    if !current.is_empty() {
+        tokens.push((line, current));
+    }
+    tokens
+}
+
+struct Tokens {
+    tokens: Vec<(usize, String)>,
+    pos: usize,
+}
+
+impl Tokens {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|(_, t)| t.as_str())
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos).or(self.tokens.last()).map_or(1, |(l, _)| *l)
+    }
+
+    fn next(&mut self) -> Result<String, InterchangeError> {
+        let token = self.tokens.get(self.pos).map(|(_, t)| t.clone());
+        self.pos += 1;
+        token.ok_or_else(|| parse_error(self.line(), "unexpected end of input".to_string()))
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), InterchangeError> {
+        let line = self.line();
+        let found = self.next()?;
+        if found != token {
+            return Err(parse_error(line, format!("expected `{}`, found `{}`", token, found)));
+        }
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<i32, InterchangeError> {
+        let line = self.line();
+        let found = self.next()?;
+        found.parse().map_err(|_| parse_error(line, format!("expected a number, found `{}`", found)))
+    }
+
+    /// Parses `name: weight, ... ;` into pairs.
+    fn weighted_list(&mut self) -> Result<Vec<(String, i32)>, InterchangeError> {
+        let mut list = Vec::new();
+        while self.peek() != Some(";") {
+            let name = unescape(&self.next()?);
+            self.expect(":")?;
+            list.push((name, self.number()?));
+            if self.peek() == Some(",") {
+                self.pos += 1;
+            }
+        }
+        self.expect(";")?;
+        Ok(list)
+    }
+}
+
+/// Imports a net in the LoLA format, transitions get the `default` role and `%` escapes in names are decoded.
+pub fn import(source: &str) -> Result<PetriNet, InterchangeError> {
+    let mut tokens = Tokens {
+        tokens: tokenize(source),
+        pos: 0,
+    };
+    let mut net = PetriNet::new();
+
+    tokens.expect("PLACE")?;
+    while tokens.peek().is_some_and(|t| t != "MARKING") {
+        let mut capacity = None;
+        if tokens.peek() == Some("SAFE") {
+            tokens.pos += 1;
+            capacity = Some(tokens.number()?);
+            tokens.expect(":")?;
+        }
+        loop {
+            let name = unescape(&tokens.next()?);
+            let offset = net.places.len() as i32;
+            net.add_place(&name, offset, Some(0), capacity, 0, 0);
+            match tokens.next()?.as_str() {
+                "," => continue,
+                ";" => break,
+                other => return Err(parse_error(tokens.line(), format!("expected `,` or `;`, found `{}`", other))),
+            }
+        }
+    }
+
+    tokens.expect("MARKING")?;
+    for (place, count) in tokens.weighted_list()? {
+        match net.places.get_mut(&place) {
+            Some(p) => p.initial = Some(count),
+            None => return Err(parse_error(tokens.line(), format!("marking of undeclared place `{}`", place))),
+        }
+    }
+
+    while tokens.peek().is_some() {
+        tokens.expect("TRANSITION")?;
+        let name = unescape(&tokens.next()?);
+        net.add_transition(&name, "default", 0, 0);
+        for (keyword, consume) in [("CONSUME", true), ("PRODUCE", false)] {
+            tokens.expect(keyword)?;
+            for (place, weight) in tokens.weighted_list()? {
+                if !net.places.contains_key(&place) {
+                    return Err(parse_error(tokens.line(), format!("arc of undeclared place `{}`", place)));
+                }
+                let (source, target) = if consume { (&place, &name) } else { (&name, &place) };
+                net.add_arc(source, target, Some(weight), None, None, None, None);
+            }
+        }
+    }
+    net.populate_arc_attributes();
+    Ok(net)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interchange::tests::{assert_same_structure, fixtures};
+
+    use super::*;
+
+    #[test]
+    fn test_export_format() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.cell("b", None, Option::from(1), 0, 0);
+            p.func("t", "default", 0, 0);
+            p.arrow("a", "t", 1);
+            p.arrow("t", "b", 1);
+        });
+        assert_eq!(
+            export(&net).unwrap(),
+            "PLACE\n  a;\n  SAFE 1: b;\n\nMARKING\n  a: 1;\n\nTRANSITION t\n  CONSUME a: 1;\n  PRODUCE b: 1;\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let philosophers = &fixtures()[0];
+        let net = import(&export(philosophers).unwrap()).unwrap();
+        assert_same_structure(philosophers, &net);
+
+        let mut odd = PetriNet::new();
+        odd.declare(|p| {
+            p.cell("in queue", Option::from(1), None, 0, 0);
+            p.cell("a,b:c;{d}%", None, None, 0, 0);
+            p.cell("SAFE", None, None, 0, 0);
+            p.func("TRANSITION", "default", 0, 0);
+            p.arrow("in queue", "TRANSITION", 1);
+            p.arrow("TRANSITION", "a,b:c;{d}%", 1);
+            p.arrow("TRANSITION", "SAFE", 1);
+        });
+        let lola = export(&odd).unwrap();
+        assert!(lola.contains("TRANSITION %54RANSITION\n  CONSUME in%20queue: 1;"), "{}", lola);
+        assert!(lola.contains("a%2Cb%3Ac%3B%7Bd%7D%25"), "{}", lola);
+        assert_same_structure(&odd, &import(&lola).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_guards_and_bad_input() {
+        assert!(matches!(export(&fixtures()[1]), Err(InterchangeError::Unsupported(_))));
+        let e = import("PLACE a;\nMARKING b: 1;").unwrap_err();
+        assert!(matches!(e, InterchangeError::Parse(ref p) if p.line == 2), "{}", e);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::petri_net::PetriNet;
+use crate::text_dsl::ParseError;
+
+/// The `bpmn` module converts between BPMN 2.0 processes and workflow nets behind the `bpmn` feature,
+/// processes with concurrent branches use the `petriNet` model type.
+#[cfg(feature = "bpmn")]
+pub mod bpmn;
+/// The `csv` module reads and writes state machines as incidence matrices and markings as CSV.
+pub mod csv;
+/// The `dot` module imports nets sketched as GraphViz graphs.
+pub mod dot;
+/// The `lola` module reads and writes the LoLA `.lola` net format.
+pub mod lola;
+/// The `scxml` module exports elementary nets as SCXML statecharts.
+pub mod scxml;
+/// The `tina` module reads and writes the TINA `.net` net format.
+pub mod tina;
+
+pub use csv::{from_csv, to_csv};
+
+/// `InterchangeError` is returned when a net cannot be exported to or imported from another tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterchangeError {
+    /// The net uses a construct the target format cannot express.
+    Unsupported(String),
+    /// The document is not valid in the source format.
+    Parse(ParseError),
+}
+
+impl fmt::Display for InterchangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterchangeError::Unsupported(what) => write!(f, "unsupported construct: {}", what),
+            InterchangeError::Parse(e) => write!(f, "parse error at {}", e),
+        }
+    }
+}
+
+impl std::error::Error for InterchangeError {}
+
+impl From<ParseError> for InterchangeError {
+    fn from(e: ParseError) -> Self {
+        InterchangeError::Parse(e)
+    }
+}
+
+fn parse_error(line: usize, message: String) -> InterchangeError {
+    InterchangeError::Parse(ParseError { line, column: 1, message })
+}
+
+/// Turns a label into an XML id, replacing characters not allowed in an `NCName` by `_`.
+pub(crate) fn xml_id(label: &str) -> String {
+    let id: String = label
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "_-.".contains(c) { c } else { '_' })
+        .collect();
+    if id.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        id
+    } else {
+        format!("_{}", id)
+    }
+}
+
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The flow arcs of a transition with the weights of parallel arcs summed, in place order.
+#[derive(Debug, Default)]
+struct Flow {
+    consume: BTreeMap<i32, (String, i32)>,
+    produce: BTreeMap<i32, (String, i32)>,
+    read: BTreeMap<i32, (String, i32)>,
+    inhibit: BTreeMap<i32, (String, i32)>,
+}
+
+/// Lists the places in offset order.
+fn places_in_order(net: &PetriNet) -> Vec<&String> {
+    let mut places: Vec<&String> = net.places.keys().collect();
+    places.sort_by_key(|p| (net.places[*p].offset, *p));
+    places
+}
+
+/// Groups the arcs of the net by transition, with transitions in label order.
+fn flows(net: &PetriNet) -> BTreeMap<&String, Flow> {
+    let mut flows: BTreeMap<&String, Flow> = net.transitions.keys().map(|t| (t, Flow::default())).collect();
+    for arc in &net.arcs {
+        let weight = arc.weight.unwrap_or(1);
+        let inhibit = arc.inhibit.unwrap_or(false);
+        let (place, transition, input) = if net.transitions.contains_key(&arc.target) {
+            (&arc.source, &arc.target, true)
+        } else if net.transitions.contains_key(&arc.source) {
+            (&arc.target, &arc.source, false)
+        } else {
+            continue;
+        };
+        let Some(p) = net.places.get(place) else { continue };
+        let flow = flows.get_mut(transition).unwrap();
+        let side = match (input, inhibit) {
+            (true, false) => &mut flow.consume,
+            (true, true) => &mut flow.inhibit,
+            (false, false) => &mut flow.produce,
+            (false, true) => &mut flow.read,
+        };
+        side.entry(p.offset).or_insert_with(|| (place.clone(), 0)).1 += weight;
+    }
+    flows
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    /// Compares the parts of two nets both formats preserve: places with their order and marking,
+    /// transitions, and arcs with their weights and kinds.
+    pub(crate) fn assert_same_structure(a: &PetriNet, b: &PetriNet) {
+        let places = |n: &PetriNet| -> Vec<(String, i32)> {
+            places_in_order(n).into_iter().map(|p| (p.clone(), n.places[p].initial.unwrap_or(0))).collect()
+        };
+        let arcs = |n: &PetriNet| -> Vec<(String, String, i32, bool)> {
+            let mut arcs: Vec<_> = n
+                .arcs
+                .iter()
+                .map(|a| (a.source.clone(), a.target.clone(), a.weight.unwrap_or(1), a.inhibit.unwrap_or(false)))
+                .collect();
+            arcs.sort();
+            arcs
+        };
+        assert_eq!(places(a), places(b));
+        assert_eq!(a.transitions.keys().collect::<std::collections::BTreeSet<_>>(), b.transitions.keys().collect());
+        assert_eq!(arcs(a), arcs(b));
+    }
+
+    pub(crate) fn fixtures() -> Vec<PetriNet> {
+        let mut guarded = PetriNet::new();
+        guarded.declare(|p| {
+            p.cell("b", Option::from(2), None, 0, 0);
+            p.cell("a", None, None, 0, 0);
+            p.cell("lock", None, None, 0, 0);
+            p.func("t", "default", 0, 0);
+            p.arrow("b", "t", 2);
+            p.arrow("t", "a", 1);
+            p.guard("lock", "t", 1);
+            p.guard("t", "b", 1);
+        });
+        vec![PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap(), guarded]
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeSet;
+
+use crate::analysis::{explore, Limits};
+use crate::interchange::{xml_escape, xml_id, InterchangeError};
+use crate::petri_net::PetriNet;
+use crate::vasm::{StateMachine, Vector};
+
+/// Names a reachable marking after its marked places, or `s<index>` if several places hold tokens.
+fn state_id(sm: &StateMachine, state: &Vector, index: usize) -> String {
+    let marked: Vec<&String> = sm.places.iter().zip(state).filter(|(_, t)| **t > 0).map(|(p, _)| p).collect();
+    match marked.as_slice() {
+        [place] => xml_id(place),
+        [] => "empty".to_string(),
+        _ => format!("s{}", index),
+    }
+}
+
+/// Exports an `elementary` or `workflow` net as an SCXML document.
+///
+/// Each reachable marking becomes a state and each firing a transition on the event named after the action.
+/// Markings without successors become final states.
+pub fn export(net: &PetriNet) -> Result<String, InterchangeError> {
+    if net.model_type != "elementary" && net.model_type != "workflow" {
+        return Err(InterchangeError::Unsupported(format!(
+            "SCXML export needs an elementary or workflow net, not {}",
+            net.model_type
+        )));
+    }
+    let sm = StateMachine::from_model(&mut net.clone());
+    let graph = explore(&sm, Limits::default());
+    if !graph.complete {
+        return Err(InterchangeError::Unsupported("the state space exceeds the exploration limits".to_string()));
+    }
+
+    let mut ids: Vec<String> = graph.states.iter().enumerate().map(|(i, s)| state_id(&sm, s, i)).collect();
+    let mut taken: BTreeSet<String> = ids.iter().cloned().collect();
+    let mut seen = BTreeSet::new();
+    for (i, id) in ids.iter_mut().enumerate() {
+        if !seen.insert(id.clone()) {
+            let unused = (i..).map(|n| format!("{}_{}", id, n)).find(|c| !taken.contains(c)).unwrap();
+            taken.insert(unused.clone());
+            *id = unused;
+        }
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<scxml xmlns=\"http://www.w3.org/2005/07/scxml\" version=\"1.0\" initial=\"{}\">\n",
+        ids[0]
+    ));
+    for (i, successors) in graph.successors.iter().enumerate() {
+        if successors.is_empty() {
+            xml.push_str(&format!("  <final id=\"{}\" />\n", ids[i]));
+            continue;
+        }
+        xml.push_str(&format!("  <state id=\"{}\">\n", ids[i]));
+        let mut successors = successors.clone();
+        successors.sort();
+        for (action, target) in successors {
+            xml.push_str(&format!(
+                "    <transition event=\"{}\" target=\"{}\" />\n",
+                xml_escape(&action),
+                ids[target]
+            ));
+        }
+        xml.push_str("  </state>\n");
+    }
+    xml.push_str("</scxml>\n");
+    Ok(xml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_traffic_light() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.model_type("elementary");
+            p.cell("red", Option::from(1), None, 0, 0);
+            p.cell("green", None, None, 0, 0);
+            p.cell("off", None, None, 0, 0);
+            p.func("go", "default", 0, 0);
+            p.func("stop", "default", 0, 0);
+            p.func("shutdown", "default", 0, 0);
+            p.arrow("red", "go", 1);
+            p.arrow("go", "green", 1);
+            p.arrow("green", "stop", 1);
+            p.arrow("stop", "red", 1);
+            p.arrow("red", "shutdown", 1);
+            p.arrow("shutdown", "off", 1);
+        });
+        assert_eq!(
+            export(&net).unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<scxml xmlns="http://www.w3.org/2005/07/scxml" version="1.0" initial="red">
+  <state id="red">
+    <transition event="go" target="green" />
+    <transition event="shutdown" target="off" />
+  </state>
+  <state id="green">
+    <transition event="stop" target="red" />
+  </state>
+  <final id="off" />
+</scxml>
+"#
+        );
+
+        net.model_type = "petriNet".to_string();
+        assert!(matches!(export(&net), Err(InterchangeError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_state_ids_are_unique() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.model_type("elementary");
+            p.cell("a b", Option::from(1), None, 0, 0);
+            p.cell("a_b", None, None, 0, 0);
+            p.cell("a_b_1", None, None, 0, 0);
+            p.func("first", "default", 0, 0);
+            p.func("second", "default", 0, 0);
+            p.arrow("a b", "first", 1);
+            p.arrow("first", "a_b", 1);
+            p.arrow("a_b", "second", 1);
+            p.arrow("second", "a_b_1", 1);
+        });
+        let xml = export(&net).unwrap();
+        let ids: Vec<&str> = xml.split(" id=\"").skip(1).map(|s| &s[..s.find('"').unwrap()]).collect();
+        assert_eq!(ids, vec!["a_b", "a_b_2", "a_b_1"]);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::interchange::{flows, parse_error, places_in_order, InterchangeError};
+use crate::petri_net::PetriNet;
+
+/// Writes a TINA identifier, enclosing it in braces unless it only has letters, digits, `_` and `'`.
+fn name(label: &str) -> String {
+    if !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '\'') {
+        label.to_string()
+    } else {
+        format!("{{{}}}", label.replace('\\', "\\\\").replace('}', "\\}"))
+    }
+}
+
+fn arc(place: &str, kind: &str, weight: i32) -> String {
+    match (kind, weight) {
+        ("", 1) => name(place),
+        ("", w) => format!("{}*{}", name(place), w),
+        (k, w) => format!("{}{}{}", name(place), k, w),
+    }
+}
+
+/// Exports the net in the TINA `.net` format, read arcs become test arcs (`?w`)
+/// and inhibitor arcs become inhibitor arcs (`?-w`).
+///
+/// TINA nets have no place capacities, nets using them are rejected.
+pub fn export(net: &PetriNet) -> Result<String, InterchangeError> {
+    let places = places_in_order(net);
+    if let Some(p) = places.iter().find(|p| net.places[**p].capacity.unwrap_or_default().is_bounded()) {
+        return Err(InterchangeError::Unsupported(format!("TINA has no place capacities ({})", p)));
+    }
+
+    let mut out = String::from("net model\n");
+    for p in &places {
+        match net.places[*p].initial.unwrap_or(0) {
+            0 => out.push_str(&format!("pl {}\n", name(p))),
+            m => out.push_str(&format!("pl {} ({})\n", name(p), m)),
+        }
+    }
+    for (t, flow) in flows(net) {
+        let inputs = flow
+            .consume
+            .values()
+            .map(|(p, w)| arc(p, "", *w))
+            .chain(flow.read.values().map(|(p, w)| arc(p, "?", *w)))
+            .chain(flow.inhibit.values().map(|(p, w)| arc(p, "?-", *w)));
+        let outputs = flow.produce.values().map(|(p, w)| arc(p, "", *w));
+        let mut line = format!("tr {}", name(t));
+        inputs.for_each(|a| line.push_str(&format!(" {}", a)));
+        line.push_str(" ->");
+        outputs.for_each(|a| line.push_str(&format!(" {}", a)));
+        out.push_str(&line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Splits a line into words, keeping `{...}` identifiers (with `\` escapes) together with their suffix.
+fn words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                current.push('{');
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => current.extend(chars.next()),
+                        '}' => break,
+                        c => current.push(c),
+                    }
+                }
+                current.push('}');
+            }
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Splits a word into its identifier and the rest, removing the braces of quoted identifiers.
+fn split_name(word: &str) -> (String, &str) {
+    if let Some(quoted) = word.strip_prefix('{') {
+        let end = quoted.find('}').unwrap_or(quoted.len());
+        (quoted[..end].to_string(), quoted.get(end + 1..).unwrap_or(""))
+    } else {
+        let end = word.find(['*', '?', '(']).unwrap_or(word.len());
+        (word[..end].to_string(), &word[end..])
+    }
+}
+
+fn declare(net: &mut PetriNet, place: &str) {
+    if !net.places.contains_key(place) {
+        let offset = net.places.len() as i32;
+        net.add_place(place, offset, Some(0), None, 0, 0);
+    }
+}
+
+/// Imports a net in the TINA `.net` format, ignoring time intervals, labels and priorities.
+///
+/// Transitions get the `default` role and places used before their `pl` declaration are added unmarked.
+pub fn import(source: &str) -> Result<PetriNet, InterchangeError> {
+    let mut net = PetriNet::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.split('#').next().unwrap_or("");
+        let words = words(line);
+        let Some(keyword) = words.first() else { continue };
+        match keyword.as_str() {
+            "net" | "lb" | "pr" | "nt" | "note" => {}
+            "pl" => {
+                let (place, rest) = split_name(words.get(1).ok_or_else(|| parse_error(line_no, "missing place name".to_string()))?);
+                declare(&mut net, &place);
+                let marking = words.get(2).map(String::as_str).unwrap_or(rest);
+                if let Some(m) = marking.strip_prefix('(').and_then(|m| m.strip_suffix(')')) {
+                    let m = m.parse().map_err(|_| parse_error(line_no, format!("invalid marking `{}`", m)))?;
+                    net.places.get_mut(&place).unwrap().initial = Some(m);
+                }
+            }
+            "tr" => {
+                let (t, _) = split_name(words.get(1).ok_or_else(|| parse_error(line_no, "missing transition name".to_string()))?);
+                net.add_transition(&t, "default", 0, 0);
+                let mut input = true;
+                for word in &words[2..] {
+                    if word == "->" {
+                        input = false;
+                        continue;
+                    }
+                    if word.starts_with(['[', ']', ':']) {
+                        continue;
+                    }
+                    let (place, suffix) = split_name(word);
+                    let (kind, weight) = if let Some(w) = suffix.strip_prefix("?-") {
+                        ("inhibit", w)
+                    } else if let Some(w) = suffix.strip_prefix('?') {
+                        ("read", w)
+                    } else {
+                        ("flow", suffix.strip_prefix('*').unwrap_or("1"))
+                    };
+                    let weight: i32 =
+                        weight.parse().map_err(|_| parse_error(line_no, format!("invalid arc weight in `{}`", word)))?;
+                    declare(&mut net, &place);
+                    match (kind, input) {
+                        ("flow", true) => net.add_arc(&place, &t, Some(weight), None, None, None, None),
+                        ("flow", false) => net.add_arc(&t, &place, Some(weight), None, None, None, None),
+                        ("read", true) => net.add_arc(&t, &place, Some(weight), Some(true), None, Some(true), None),
+                        ("inhibit", true) => net.add_arc(&place, &t, Some(weight), Some(true), None, Some(true), None),
+                        _ => return Err(parse_error(line_no, format!("test and inhibitor arcs must be inputs: `{}`", word))),
+                    }
+                }
+            }
+            other => return Err(parse_error(line_no, format!("unknown declaration `{}`", other))),
+        }
+    }
+    net.populate_arc_attributes();
+    Ok(net)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interchange::tests::{assert_same_structure, fixtures};
+
+    use super::*;
+
+    #[test]
+    fn test_export_format() {
+        assert_eq!(
+            export(&fixtures()[1]).unwrap(),
+            "net model\npl b (2)\npl a\npl lock\ntr t b*2 b?1 lock?-1 -> a\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for fixture in fixtures() {
+            assert_same_structure(&fixture, &import(&export(&fixture).unwrap()).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_quoted_names_and_errors() {
+        let net = import("net n\npl {order.new} (1)\ntr {pay now} [0,2] {order.new}*2 -> done\n").unwrap();
+        assert_eq!(net.places["order.new"].initial, Some(1));
+        assert_eq!(net.places["done"].offset, 1);
+        assert_eq!(net.arcs[0].weight, Some(2));
+        assert!(net.transitions.contains_key("pay now"));
+        assert_eq!(name("pay now"), "{pay now}");
+
+        let e = import("net n\npl a\ntr t a*x ->").unwrap_err();
+        assert!(matches!(e, InterchangeError::Parse(ref p) if p.line == 3), "{}", e);
+    }
+}
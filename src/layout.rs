@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::analysis::NetStructure;
+use crate::petri_net::PetriNet;
+
+/// Horizontal distance between two layers.
+pub const LAYER_SPACING: i32 = 120;
+/// Vertical distance between two nodes of a layer.
+pub const NODE_SPACING: i32 = 80;
+/// Coordinate of the first layer and of the first node in each layer.
+pub const MARGIN: i32 = 100;
+
+/// Checks if every node of the net sits at the same position, as for nets declared without coordinates.
+pub fn is_unplaced(net: &PetriNet) -> bool {
+    let mut positions = net
+        .places
+        .values()
+        .map(|p| (p.x, p.y))
+        .chain(net.transitions.values().map(|t| (t.x, t.y)));
+    match positions.next() {
+        Some(first) => positions.all(|p| p == first),
+        None => true,
+    }
+}
+
+/// Assigns coordinates to every node of the net with a layered layout flowing left to right.
+///
+/// Layers are the breadth-first distance along flow arcs from the marked places and the nodes
+/// without inputs, so cycles flow back to the left. Nodes out of reach start a layering of their own.
+/// Within a layer nodes are ordered by the mean position of their neighbours in the previous layer.
+pub fn auto(net: &mut PetriNet) {
+    let s = NetStructure::from_net(net);
+    let successors = |node: &String| -> Vec<&String> {
+        match s.post.get(node) {
+            Some(post) => post.keys().collect(),
+            None => s.outputs[node].keys().collect(),
+        }
+    };
+    let predecessors = |node: &String| -> Vec<&String> {
+        match s.pre.get(node) {
+            Some(pre) => pre.keys().collect(),
+            None => s.inputs[node].keys().collect(),
+        }
+    };
+
+    let mut places: Vec<&String> = s.places.iter().collect();
+    places.sort_by_key(|p| (net.places[*p].offset, *p));
+    let nodes: Vec<&String> = places.into_iter().chain(s.transitions.iter()).collect();
+
+    let mut rank: BTreeMap<&String, usize> = BTreeMap::new();
+    let is_root = |n: &String| {
+        predecessors(n).is_empty() || net.places.get(n).is_some_and(|p| p.initial.unwrap_or(0) > 0)
+    };
+    let roots: Vec<&String> = nodes.iter().copied().filter(|n| is_root(n)).collect();
+    for start in roots.into_iter().chain(nodes.iter().copied()) {
+        if rank.contains_key(start) {
+            continue;
+        }
+        let base = if is_root(start) { 0 } else { rank.values().max().map_or(0, |r| r + 1) };
+        let mut queue = VecDeque::from([(start, base)]);
+        rank.insert(start, base);
+        while let Some((node, r)) = queue.pop_front() {
+            for next in successors(node) {
+                if !rank.contains_key(next) {
+                    rank.insert(next, r + 1);
+                    queue.push_back((next, r + 1));
+                }
+            }
+        }
+    }
+
+    let mut layers: BTreeMap<usize, Vec<&String>> = BTreeMap::new();
+    for node in &nodes {
+        layers.entry(rank[node]).or_default().push(node);
+    }
+    let mut row: BTreeMap<&String, f64> = BTreeMap::new();
+    for layer in layers.values_mut() {
+        let key = |n: &String| {
+            let placed: Vec<f64> = predecessors(n).iter().filter_map(|p| row.get(p).copied()).collect();
+            if placed.is_empty() {
+                f64::MAX
+            } else {
+                placed.iter().sum::<f64>() / placed.len() as f64
+            }
+        };
+        let mut keyed: Vec<(f64, usize, &String)> = layer.iter().enumerate().map(|(i, n)| (key(n), i, *n)).collect();
+        keyed.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        *layer = keyed.into_iter().map(|(_, _, n)| n).collect();
+        for (i, n) in layer.iter().enumerate() {
+            row.insert(n, i as f64);
+        }
+    }
+
+    let mut positions: Vec<(String, i32, i32)> = Vec::new();
+    for (r, layer) in &layers {
+        for (i, n) in layer.iter().enumerate() {
+            positions.push(((*n).clone(), MARGIN + *r as i32 * LAYER_SPACING, MARGIN + i as i32 * NODE_SPACING));
+        }
+    }
+    for (label, x, y) in positions {
+        if let Some(p) = net.places.get_mut(&label) {
+            (p.x, p.y) = (x, y);
+        } else if let Some(t) = net.transitions.get_mut(&label) {
+            (t.x, t.y) = (x, y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    #[test]
+    fn test_layers_follow_flow() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.cell("c", None, None, 0, 0);
+            p.func("t0", "default", 0, 0);
+            p.func("t1", "default", 0, 0);
+            p.func("t2", "default", 0, 0);
+            p.arrow("a", "t0", 1);
+            p.arrow("t0", "b", 1);
+            p.arrow("t0", "c", 1);
+            p.arrow("b", "t1", 1);
+            p.arrow("t1", "a", 1);
+            p.arrow("c", "t2", 1);
+        });
+        assert!(is_unplaced(&net));
+        auto(&mut net);
+        assert!(!is_unplaced(&net));
+        assert_eq!((net.places["a"].x, net.places["a"].y), (100, 100));
+        assert_eq!((net.transitions["t0"].x, net.transitions["t0"].y), (220, 100));
+        assert_eq!((net.places["b"].x, net.places["b"].y), (340, 100));
+        assert_eq!((net.places["c"].x, net.places["c"].y), (340, 180));
+        assert_eq!((net.transitions["t2"].x, net.transitions["t2"].y), (460, 180));
+    }
+
+    #[test]
+    fn test_every_node_gets_a_distinct_position() {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        auto(&mut net);
+        let positions: HashSet<(i32, i32)> = net
+            .places
+            .values()
+            .map(|p| (p.x, p.y))
+            .chain(net.transitions.values().map(|t| (t.x, t.y)))
+            .collect();
+        assert_eq!(positions.len(), 25);
+    }
+}
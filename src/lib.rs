@@ -3,27 +3,222 @@
 //! - Provides a DSL-driven framework for modeling and simulating Petri-nets, wf-nets, and DFAs.
 //! - State machine data types are executed as a [Vector Addition State Machine (VASM)](https://en.wikipedia.org/wiki/Vector_addition_system).
 //! - Data models are viewable / shareable in browsers by using [https://pflow-dev.github.io/pflow-js/p/](https://pflow-dev.github.io/pflow-js/p/)
+//! - Without the default `std` feature only the `engine`, `vector` and `capacity` modules are built, on `no_std` targets with `alloc`.
+//! - Without the default `zblob` feature the compression stack is left out, build with
+//!   `--no-default-features --features std --profile wasm` for a lean `wasm32-unknown-unknown` bundle.
+//! - The default `macros`, `parallel` and `random` features add the `petri_net!` macro, the rayon-backed
+//!   parallel analyses and the random simulation policies and experiments, which such a bundle leaves out.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+extern crate self as pflow_metamodel;
+
+/// The `petri_net!` macro declares a Petri-net at compile time, see the `pflow_metamodel_macros` crate.
+#[cfg(feature = "macros")]
+pub use pflow_metamodel_macros::petri_net;
 
 /// The `petri_net` module contains the definition and implementation of the `PetriNet` struct.
+#[cfg(feature = "std")]
 pub mod petri_net;
 
 /// The `oid` module is used to generate CID's for the zipped blobs.
+#[cfg(feature = "zblob")]
 pub mod oid;
 
 /// The `compression` module contains functions for zipping/unzipping models as sharable base64 blobs.
+#[cfg(feature = "zblob")]
 pub mod compression;
 
 /// The `vasm` module contains the implementation of a Vector Addition State Machine (VASM).
+#[cfg(feature = "std")]
 pub mod vasm;
 
 /// The `dsl` module contains `FlowDsl` and `Builder` traits for defining Petri-nets.
+#[cfg(feature = "std")]
 pub mod dsl;
 
+/// The `formats` module (de)serializes models as YAML or TOML behind the `yaml` and `toml` features.
+#[cfg(any(feature = "yaml", feature = "toml"))]
+pub mod formats;
+
+/// The `binary` module encodes models as CBOR or MessagePack behind the `cbor` and `msgpack` features.
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+pub mod binary;
+
+/// The `proto` module mirrors `proto/pflow.proto` and encodes models as protobuf behind the `protobuf` feature.
+#[cfg(feature = "protobuf")]
+pub mod proto;
+
+/// The `schema` module describes the JSON file format and upgrades documents from older versions.
+#[cfg(feature = "std")]
+pub mod schema;
+
+/// The `layout` module assigns coordinates to nets declared without positions.
+#[cfg(feature = "std")]
+pub mod layout;
+
+/// The `slice` module extracts the fragment of a net relevant to chosen places, transitions or roles.
+#[cfg(feature = "std")]
+pub mod slice;
+
+/// The `hierarchy` module refines substitution transitions into subnets and flattens them.
+#[cfg(feature = "std")]
+pub mod hierarchy;
+
+/// The `compose` module merges independently maintained nets by fusing places and transitions.
+#[cfg(feature = "std")]
+pub mod compose;
+
+/// The `template` module declares reusable net fragments instantiated under a name prefix.
+#[cfg(feature = "std")]
+pub mod template;
+
+/// The `text_dsl` module parses models written in the textual `.pflow` format.
+#[cfg(feature = "std")]
+pub mod text_dsl;
+
+/// The `codegen` module generates Rust `FlowDsl` source code from petri-nets.
+#[cfg(feature = "std")]
+pub mod codegen;
+
 /// The `fixtures` module contains test fixtures for the project (visible only in the test environment).
+#[cfg(feature = "std")]
 pub mod fixtures;
 
 /// The `zblob` contains utilities to facilitate loading zipped blob data as petri-nets.
+#[cfg(feature = "zblob")]
 pub mod zblob;
 
 /// The `model` encapsulates the `PetriNet` and `Vasm` objects into a single `Model` object.
+#[cfg(feature = "std")]
 pub mod model;
+
+/// The `analysis` module contains structural and behavioral analyses of petri-nets.
+#[cfg(feature = "std")]
+pub mod analysis;
+
+/// The `cases` module manages many running instances of a single state machine.
+#[cfg(feature = "std")]
+pub mod cases;
+
+/// The `projection` module provides named read-only views of a subset of a marking.
+#[cfg(feature = "std")]
+pub mod projection;
+
+/// The `simulation` module runs state machines step by step and publishes their marking updates.
+#[cfg(feature = "std")]
+pub mod simulation;
+
+/// The `guard` module defines when read arcs enable and inhibitor arcs block a transition.
+#[cfg(feature = "std")]
+pub mod guard;
+
+/// The `step` module fires sets of concurrently enabled transitions in a single step.
+#[cfg(feature = "std")]
+pub mod step;
+
+/// The `interchange` module reads and writes the net formats of other Petri-net tools.
+#[cfg(feature = "std")]
+pub mod interchange;
+
+/// The `report` module renders analysis results as Markdown or HTML documents.
+#[cfg(feature = "std")]
+pub mod report;
+
+/// The `render` module draws nets as plain text for terminal debugging or as SVG images.
+#[cfg(feature = "std")]
+pub mod render;
+
+/// The `equivalence` module checks nets for isomorphism and bisimilarity.
+#[cfg(feature = "std")]
+pub mod equivalence;
+
+/// The `capacity` module defines the token capacity of places.
+pub mod capacity;
+
+/// The `engine` module fires transitions on plain vectors and builds without the standard library.
+pub mod engine;
+
+/// The `vector` module adds and compares state vectors with the arithmetic the engine fires transitions with.
+pub mod vector;
+
+/// The `simd` module adds state vectors in fixed-width chunks behind the `simd` feature.
+#[cfg(feature = "simd")]
+pub(crate) mod simd;
+
+/// The `bitset` module packs the markings of 1-safe nets into bits.
+#[cfg(feature = "std")]
+pub mod bitset;
+
+/// The `compiled` module addresses transitions by dense indices for hot loops.
+#[cfg(feature = "std")]
+pub mod compiled;
+
+/// The `generators` module builds synthetic large nets for benchmarks behind the `bench` feature.
+#[cfg(feature = "bench")]
+pub mod generators;
+
+/// The `testkit` module generates random nets, markings and firing sequences behind the `arbitrary` feature.
+#[cfg(feature = "arbitrary")]
+pub mod testkit;
+
+/// The `storage` module keeps zblobs in SQLite with keyword and full-text search behind the `storage` feature.
+#[cfg(feature = "storage")]
+pub mod storage;
+
+/// The `encryption` module encrypts zblob payloads with a shared key behind the `encryption` feature.
+#[cfg(feature = "encryption")]
+pub mod encryption;
+
+/// The `grpc` module serves model storage and firing over gRPC behind the `grpc` feature.
+#[cfg(feature = "grpc")]
+pub mod grpc;
+
+/// The `server` module provides axum handlers for sharing and firing models over REST behind the `server` feature.
+#[cfg(feature = "server")]
+pub mod server;
+
+/// The `events` module publishes successful transactions to message brokers such as NATS or Kafka.
+#[cfg(feature = "std")]
+pub mod events;
+
+/// The `metrics` module records firings, simulation steps and server requests behind the `metrics` feature.
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+/// The `tracing` module hashes states and models for the spans recorded behind the `tracing` feature.
+#[cfg(feature = "tracing")]
+pub mod tracing;
+
+/// The `audit` module keeps a hash-chained log of fire attempts behind the `zblob` feature, which provides the hashes.
+#[cfg(feature = "zblob")]
+pub mod audit;
+
+/// The `quota` module limits how many times a role may fire a transition per case or per time window.
+#[cfg(feature = "std")]
+pub mod quota;
+
+/// The `expr` module parses and evaluates the conditions of transitions over case variables.
+#[cfg(feature = "std")]
+pub mod expr;
+
+/// The `lint` module checks models against a configurable set of rules, such as unconnected nodes or missing roles.
+#[cfg(feature = "std")]
+pub mod lint;
+
+/// The `roles` module resolves the roles declared by a net and the roles they imply.
+#[cfg(feature = "std")]
+pub mod roles;
+
+/// The `semantics` module lets users fire the transitions of a state machine with their own rules.
+#[cfg(feature = "std")]
+pub mod semantics;
+
+/// The `preview` module tells whether an action would fire and why not, without firing it.
+#[cfg(feature = "std")]
+pub mod preview;
+
+/// The `builder` module assembles state machines directly from vectors, without a `PetriNet` graph.
+#[cfg(feature = "std")]
+pub mod builder;
@@ -0,0 +1,486 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::capacity::Capacity;
+use crate::petri_net::PetriNet;
+
+/// `Severity` is how much a `Lint` matters, a CI job typically fails on errors only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// `Rule` is a check of the linter, identified by a stable machine-readable code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    /// An arc whose endpoints are unknown, or two places or two transitions.
+    DanglingArc,
+    /// A place or transition without any arc.
+    UnconnectedNode,
+    /// A transition without a role, or with an empty one.
+    MissingRole,
+    /// A transition or implied role referencing a role missing from the declared roles, see `PetriNet::validate_roles`.
+    UndeclaredRole,
+    /// A place no transition consumes from or is guarded by, so its tokens are never used.
+    UnreadPlace,
+    /// A label that does not follow the configured `Naming` convention.
+    LabelNaming,
+    /// A place or transition without a description.
+    MissingDescription,
+    /// A place whose initial tokens are negative or exceed its capacity.
+    InitialExceedsCapacity,
+}
+
+impl Rule {
+    /// Every rule in the order the linter reports them.
+    pub const ALL: [Rule; 8] = [
+        Rule::DanglingArc,
+        Rule::UnconnectedNode,
+        Rule::MissingRole,
+        Rule::UndeclaredRole,
+        Rule::UnreadPlace,
+        Rule::LabelNaming,
+        Rule::MissingDescription,
+        Rule::InitialExceedsCapacity,
+    ];
+
+    /// Returns the code of the rule, such as `unconnected-node`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Rule::DanglingArc => "dangling-arc",
+            Rule::UnconnectedNode => "unconnected-node",
+            Rule::MissingRole => "missing-role",
+            Rule::UndeclaredRole => "undeclared-role",
+            Rule::UnreadPlace => "unread-place",
+            Rule::LabelNaming => "label-naming",
+            Rule::MissingDescription => "missing-description",
+            Rule::InitialExceedsCapacity => "initial-exceeds-capacity",
+        }
+    }
+
+    /// Returns the severity of the rule unless configured otherwise.
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            Rule::DanglingArc | Rule::UndeclaredRole | Rule::InitialExceedsCapacity => Severity::Error,
+            Rule::UnconnectedNode | Rule::MissingRole | Rule::LabelNaming => Severity::Warning,
+            Rule::UnreadPlace | Rule::MissingDescription => Severity::Info,
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// `Naming` is the convention the labels of places and transitions must follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Naming {
+    /// Lowercase letters, digits and underscores, starting with a letter, such as `check_stock`.
+    #[default]
+    SnakeCase,
+    /// Letters and digits starting with a lowercase letter, such as `checkStock`.
+    CamelCase,
+    /// Letters and digits starting with an uppercase letter, such as `CheckStock`.
+    PascalCase,
+}
+
+impl Naming {
+    /// Checks if the label follows the convention.
+    pub fn matches(&self, label: &str) -> bool {
+        let mut chars = label.chars();
+        let Some(first) = chars.next() else {
+            return false;
+        };
+        let rest = chars.as_str();
+        match self {
+            Naming::SnakeCase => {
+                first.is_ascii_lowercase()
+                    && rest
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            }
+            Naming::CamelCase => first.is_ascii_lowercase() && rest.chars().all(|c| c.is_ascii_alphanumeric()),
+            Naming::PascalCase => first.is_ascii_uppercase() && rest.chars().all(|c| c.is_ascii_alphanumeric()),
+        }
+    }
+}
+
+/// `LintConfig` selects the rules to run and their severity.
+///
+/// Every rule runs at its default severity unless disabled or overridden.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LintConfig {
+    /// The severity of the rules that do not run at their default one, None disables the rule.
+    #[serde(default)]
+    pub severities: BTreeMap<Rule, Option<Severity>>,
+    #[serde(default)]
+    pub naming: Naming,
+}
+
+impl LintConfig {
+    /// Creates a configuration running every rule at its default severity.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reports the rule at the given severity.
+    pub fn with_severity(mut self, rule: Rule, severity: Severity) -> Self {
+        self.severities.insert(rule, Some(severity));
+        self
+    }
+
+    /// Disables the rule.
+    pub fn without(mut self, rule: Rule) -> Self {
+        self.severities.insert(rule, None);
+        self
+    }
+
+    /// Sets the naming convention of labels.
+    pub fn with_naming(mut self, naming: Naming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Returns the severity the rule is reported at, or None if it is disabled.
+    pub fn severity(&self, rule: Rule) -> Option<Severity> {
+        match self.severities.get(&rule) {
+            Some(severity) => *severity,
+            None => Some(rule.default_severity()),
+        }
+    }
+}
+
+/// `Lint` is a finding of the linter on a node or arc of the net.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lint {
+    pub rule: Rule,
+    /// The code of the rule, see `Rule::code`.
+    pub code: String,
+    pub severity: Severity,
+    /// The label of the place or transition, or `source -> target` for an arc.
+    pub subject: String,
+    pub message: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}] {}: {}", self.severity, self.code, self.subject, self.message)
+    }
+}
+
+/// Checks the net against the rules enabled in the configuration.
+///
+/// Lints are grouped by rule in the order of `Rule::ALL`, then sorted by subject, so the output is stable.
+///
+/// # Example
+///
+/// ```
+/// use pflow_metamodel::lint::{check, LintConfig, Rule, Severity};
+/// use pflow_metamodel::petri_net::PetriNet;
+///
+/// let net = PetriNet::from_dsl_str("places { p1(1), orphan } transitions { t1: role(user) } arcs { p1 -> t1 }").unwrap();
+/// let config = LintConfig::new().without(Rule::MissingDescription);
+/// let lints = check(&net, &config);
+/// assert_eq!(lints[0].code, "unconnected-node");
+/// assert!(lints.iter().all(|lint| lint.severity < Severity::Error));
+/// ```
+pub fn check(net: &PetriNet, config: &LintConfig) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    for rule in Rule::ALL {
+        let Some(severity) = config.severity(rule) else {
+            continue;
+        };
+        let mut found: Vec<(String, String)> = match rule {
+            Rule::DanglingArc => dangling_arcs(net),
+            Rule::UnconnectedNode => unconnected_nodes(net),
+            Rule::MissingRole => missing_roles(net),
+            Rule::UndeclaredRole => undeclared_roles(net),
+            Rule::UnreadPlace => unread_places(net),
+            Rule::LabelNaming => misnamed_labels(net, config.naming),
+            Rule::MissingDescription => missing_descriptions(net),
+            Rule::InitialExceedsCapacity => inconsistent_initials(net),
+        };
+        found.sort();
+        lints.extend(found.into_iter().map(|(subject, message)| Lint {
+            rule,
+            code: rule.code().to_string(),
+            severity,
+            subject,
+            message,
+        }));
+    }
+    lints
+}
+
+fn dangling_arcs(net: &PetriNet) -> Vec<(String, String)> {
+    let kind = |label: &str| {
+        if net.places.contains_key(label) {
+            Some("place")
+        } else if net.transitions.contains_key(label) {
+            Some("transition")
+        } else {
+            None
+        }
+    };
+    net.arcs
+        .iter()
+        .filter_map(|arc| {
+            let message = match (kind(&arc.source), kind(&arc.target)) {
+                (None, _) => format!("unknown source {}", arc.source),
+                (_, None) => format!("unknown target {}", arc.target),
+                (Some(source), Some(target)) if source == target => format!("connects two nodes of kind {}", source),
+                _ => return None,
+            };
+            Some((format!("{} -> {}", arc.source, arc.target), message))
+        })
+        .collect()
+}
+
+fn unconnected_nodes(net: &PetriNet) -> Vec<(String, String)> {
+    let connected: BTreeSet<&str> = net
+        .arcs
+        .iter()
+        .flat_map(|arc| {
+            [arc.source.as_str(), arc.target.as_str()]
+                .into_iter()
+                .chain(arc.transfer.as_deref())
+        })
+        .collect();
+    let places = net.places.keys().map(|label| (label, "place"));
+    let transitions = net.transitions.keys().map(|label| (label, "transition"));
+    places
+        .chain(transitions)
+        .filter(|(label, _)| !connected.contains(label.as_str()))
+        .map(|(label, kind)| (label.clone(), format!("{} has no arcs", kind)))
+        .collect()
+}
+
+fn missing_roles(net: &PetriNet) -> Vec<(String, String)> {
+    net.transitions
+        .iter()
+        .filter(|(_, t)| t.role.as_deref().map_or(true, |role| role.trim().is_empty()))
+        .map(|(label, _)| (label.clone(), "transition has no role".to_string()))
+        .collect()
+}
+
+fn undeclared_roles(net: &PetriNet) -> Vec<(String, String)> {
+    if net.roles.is_empty() {
+        return Vec::new();
+    }
+    let transitions = net.transitions.keys().filter_map(|label| {
+        let role = net.transition_role(label).unwrap();
+        (!role.trim().is_empty() && !net.roles.contains_key(role))
+            .then(|| (label.clone(), format!("role {} is not declared", role)))
+    });
+    let implied = net.roles.iter().flat_map(|(name, role)| {
+        role.implies
+            .iter()
+            .filter(|implied| !net.roles.contains_key(*implied))
+            .map(move |implied| (name.clone(), format!("implied role {} is not declared", implied)))
+    });
+    transitions.chain(implied).collect()
+}
+
+fn unread_places(net: &PetriNet) -> Vec<(String, String)> {
+    let read: BTreeSet<&str> = net
+        .arcs
+        .iter()
+        .flat_map(|arc| {
+            let guarded = arc.inhibit.unwrap_or(false).then_some(arc.target.as_str());
+            [arc.source.as_str()].into_iter().chain(guarded)
+        })
+        .collect();
+    net.places
+        .keys()
+        .filter(|label| !read.contains(label.as_str()))
+        .map(|label| {
+            (
+                label.clone(),
+                "no transition consumes from or is guarded by the place".to_string(),
+            )
+        })
+        .collect()
+}
+
+fn misnamed_labels(net: &PetriNet, naming: Naming) -> Vec<(String, String)> {
+    net.places
+        .keys()
+        .chain(net.transitions.keys())
+        .filter(|label| !naming.matches(label))
+        .map(|label| {
+            (
+                label.clone(),
+                format!("label does not follow the {:?} convention", naming),
+            )
+        })
+        .collect()
+}
+
+fn missing_descriptions(net: &PetriNet) -> Vec<(String, String)> {
+    let places = net.places.iter().map(|(label, p)| (label, "place", &p.description));
+    let transitions = net
+        .transitions
+        .iter()
+        .map(|(label, t)| (label, "transition", &t.description));
+    places
+        .chain(transitions)
+        .filter(|(_, _, description)| description.as_deref().map_or(true, |d| d.trim().is_empty()))
+        .map(|(label, kind, _)| (label.clone(), format!("{} has no description", kind)))
+        .collect()
+}
+
+fn inconsistent_initials(net: &PetriNet) -> Vec<(String, String)> {
+    net.places
+        .iter()
+        .filter_map(|(label, place)| {
+            let initial = place.initial.unwrap_or(0);
+            let capacity = place.capacity.unwrap_or(Capacity::Unbounded);
+            let message = if initial < 0 {
+                format!("initial tokens {} are negative", initial)
+            } else if !capacity.allows(initial) {
+                format!(
+                    "initial tokens {} exceed the capacity {}",
+                    initial,
+                    capacity.limit().unwrap_or(0)
+                )
+            } else {
+                return None;
+            };
+            Some((label.clone(), message))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    fn order(p: &mut dyn FlowDsl) {
+        p.cell("placed", Option::from(1), None, 0, 0);
+        p.cell("Shipped", None, None, 0, 0);
+        p.cell("archive", Option::from(3), Option::from(2), 0, 0);
+        p.func("ship", "warehouse", 0, 0);
+        p.func("cancel", "", 0, 0);
+        p.arrow("placed", "ship", 1);
+        p.arrow("ship", "Shipped", 1);
+        p.describe("ship", "hands the order to the carrier");
+    }
+
+    fn codes(lints: &[Lint]) -> Vec<(&str, &str)> {
+        lints.iter().map(|l| (l.code.as_str(), l.subject.as_str())).collect()
+    }
+
+    #[test]
+    fn test_rules() {
+        let mut net = PetriNet::new();
+        net.declare(order);
+        net.add_arc("placed", "archive", None, None, None, None, None);
+        let lints = check(&net, &LintConfig::new());
+        assert_eq!(
+            codes(&lints),
+            vec![
+                ("dangling-arc", "placed -> archive"),
+                ("unconnected-node", "cancel"),
+                ("missing-role", "cancel"),
+                ("unread-place", "Shipped"),
+                ("unread-place", "archive"),
+                ("label-naming", "Shipped"),
+                ("missing-description", "Shipped"),
+                ("missing-description", "archive"),
+                ("missing-description", "cancel"),
+                ("missing-description", "placed"),
+                ("initial-exceeds-capacity", "archive"),
+            ]
+        );
+        assert_eq!(lints[0].severity, Severity::Error);
+        assert_eq!(
+            lints.last().unwrap().to_string(),
+            "error[initial-exceeds-capacity] archive: initial tokens 3 exceed the capacity 2"
+        );
+    }
+
+    #[test]
+    fn test_config() {
+        let mut net = PetriNet::new();
+        net.declare(order);
+        let config = LintConfig::new()
+            .without(Rule::MissingDescription)
+            .without(Rule::UnreadPlace)
+            .with_severity(Rule::MissingRole, Severity::Error)
+            .with_naming(Naming::PascalCase);
+        let lints = check(&net, &config);
+        assert_eq!(lints.iter().filter(|l| l.rule == Rule::LabelNaming).count(), 4);
+        assert_eq!(
+            lints.iter().find(|l| l.rule == Rule::MissingRole).unwrap().severity,
+            Severity::Error
+        );
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(json.contains("\"missing-description\":null"));
+        assert_eq!(serde_json::from_str::<LintConfig>(&json).unwrap(), config);
+        assert!(serde_json::to_string(&lints[0])
+            .unwrap()
+            .contains("\"severity\":\"warning\""));
+    }
+
+    #[test]
+    fn test_fixture_is_clean_of_errors() {
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let lints = check(&net, &LintConfig::new());
+        assert!(lints.iter().all(|l| l.severity < Severity::Error));
+        assert_eq!(lints.iter().filter(|l| l.rule == Rule::MissingRole).count(), 10);
+        assert!(lints.iter().all(|l| l.rule != Rule::UnconnectedNode));
+    }
+
+    #[test]
+    fn test_undeclared_roles() {
+        let mut net = PetriNet::new();
+        net.declare(order);
+        assert!(check(&net, &LintConfig::new())
+            .iter()
+            .all(|l| l.rule != Rule::UndeclaredRole));
+        net.add_role("warehouse", None, &["auditor"]);
+        net.add_transition("refund", "clerk", 0, 0);
+        let lints: Vec<Lint> = check(&net, &LintConfig::new())
+            .into_iter()
+            .filter(|l| l.rule == Rule::UndeclaredRole)
+            .collect();
+        assert_eq!(
+            codes(&lints),
+            vec![("undeclared-role", "refund"), ("undeclared-role", "warehouse")]
+        );
+        assert_eq!(lints[0].message, "role clerk is not declared");
+    }
+
+    #[test]
+    fn test_naming() {
+        assert!(Naming::SnakeCase.matches("check_stock2"));
+        assert!(!Naming::SnakeCase.matches("checkStock"));
+        assert!(Naming::CamelCase.matches("checkStock"));
+        assert!(!Naming::CamelCase.matches("check_stock"));
+        assert!(Naming::PascalCase.matches("CheckStock"));
+        assert!(!Naming::PascalCase.matches(""));
+    }
+}
@@ -0,0 +1,224 @@
+use std::time::Duration;
+
+use ::metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit};
+
+use crate::vasm::{StateMachine, Transaction, TransformError};
+
+/// Counts the successful firings, labelled by `model` and `action`.
+pub const FIRES: &str = "pflow_fires_total";
+/// Counts the rejected firings, labelled by `model`, `action` and `reason`, see `rejection_reason`.
+pub const REJECTIONS: &str = "pflow_rejections_total";
+/// The tokens in each place after the last successful firing, labelled by `model` and `place`, and by `unit`
+/// for places whose tokens have one.
+pub const TOKENS: &str = "pflow_tokens";
+/// The time taken by each step of a simulation run, in seconds.
+pub const STEP_SECONDS: &str = "pflow_simulation_step_seconds";
+/// Counts the requests handled by the server, labelled by `route` and `status`.
+pub const REQUESTS: &str = "pflow_http_requests_total";
+/// The time taken to handle each request of the server, in seconds, labelled by `route`.
+pub const REQUEST_SECONDS: &str = "pflow_http_request_seconds";
+
+/// Describes the metrics to the installed recorder, call it once after installing the recorder.
+pub fn describe() {
+    describe_counter!(FIRES, "Successful firings by transition");
+    describe_counter!(REJECTIONS, "Rejected firings by transition and reason");
+    describe_gauge!(TOKENS, "Tokens per place after the last successful firing");
+    describe_histogram!(STEP_SECONDS, Unit::Seconds, "Duration of simulation steps");
+    describe_counter!(REQUESTS, "Server requests by route and status");
+    describe_histogram!(REQUEST_SECONDS, Unit::Seconds, "Duration of server requests by route");
+}
+
+/// Returns why the transaction was rejected: `unknown_action`, `empty_model`, `dimension_mismatch`,
+/// `role_mismatch`, `quota_exceeded`, `inhibited`, `overflow`, `underflow` or `not_enabled` for the other
+/// refusals, such as a condition that does not hold, or None if it succeeded.
+pub fn rejection_reason(tx: &Transaction) -> Option<&'static str> {
+    if tx.is_ok() {
+        return None;
+    }
+    Some(match &tx.error {
+        Some(TransformError::UnknownAction { .. }) => "unknown_action",
+        Some(TransformError::EmptyModel) => "empty_model",
+        Some(TransformError::DimensionMismatch { .. }) => "dimension_mismatch",
+        Some(TransformError::RoleMismatch { .. }) => "role_mismatch",
+        Some(TransformError::QuotaExceeded { .. }) => "quota_exceeded",
+        None if tx.inhibited => "inhibited",
+        None if tx.overflow => "overflow",
+        None if tx.underflow => "underflow",
+        None => "not_enabled",
+    })
+}
+
+/// Records the firing of the action of the model with the given CID: a fire and the tokens of every place
+/// if it succeeded, a rejection otherwise.
+pub fn record_transaction(model: &str, sm: &StateMachine, action: &str, tx: &Transaction) {
+    let model = model.to_string();
+    match rejection_reason(tx) {
+        None => {
+            counter!(FIRES, "model" => model.clone(), "action" => action.to_string()).increment(1);
+            for (offset, (place, tokens)) in sm.places.iter().zip(&tx.output).enumerate() {
+                let mut labels = vec![("model", model.clone()), ("place", place.clone())];
+                if let Some(unit) = sm.units.get(offset).and_then(|u| u.clone()) {
+                    labels.push(("unit", unit));
+                }
+                gauge!(TOKENS, &labels).set(*tokens as f64);
+            }
+        }
+        Some(reason) => {
+            counter!(REJECTIONS, "model" => model, "action" => action.to_string(), "reason" => reason).increment(1);
+        }
+    }
+}
+
+/// Records the duration of a simulation step.
+pub fn record_step(elapsed: Duration) {
+    histogram!(STEP_SECONDS).record(elapsed.as_secs_f64());
+}
+
+/// Records a request handled by the server.
+pub fn record_request(route: &str, status: u16, elapsed: Duration) {
+    counter!(REQUESTS, "route" => route.to_string(), "status" => status.to_string()).increment(1);
+    histogram!(REQUEST_SECONDS, "route" => route.to_string()).record(elapsed.as_secs_f64());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use ::metrics::{Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString};
+
+    use crate::dsl::FlowDsl;
+    use crate::simulation::snapshot::model_cid;
+    use crate::simulation::Simulator;
+    use crate::vasm::Vasm;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Samples(Mutex<Vec<f64>>);
+
+    impl HistogramFn for Samples {
+        fn record(&self, value: f64) {
+            self.0.lock().unwrap().push(value);
+        }
+    }
+
+    /// Keeps every metric by its name and labels, such as `pflow_fires_total{model=zb2...,action=move}`.
+    #[derive(Default)]
+    struct TestRecorder {
+        values: Mutex<BTreeMap<String, Arc<AtomicU64>>>,
+        samples: Mutex<BTreeMap<String, Arc<Samples>>>,
+    }
+
+    fn name(key: &Key) -> String {
+        let labels: Vec<String> = key.labels().map(|l| format!("{}={}", l.key(), l.value())).collect();
+        format!("{}{{{}}}", key.name(), labels.join(","))
+    }
+
+    impl TestRecorder {
+        fn value(&self, key: &str) -> Option<u64> {
+            self.values.lock().unwrap().get(key).map(|v| v.load(Ordering::Relaxed))
+        }
+
+        fn atomic(&self, key: &Key) -> Arc<AtomicU64> {
+            self.values.lock().unwrap().entry(name(key)).or_default().clone()
+        }
+    }
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.atomic(key))
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::from_arc(self.atomic(key))
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::from_arc(self.samples.lock().unwrap().entry(name(key)).or_default().clone())
+        }
+    }
+
+    fn counter_net(p: &mut dyn FlowDsl) {
+        p.cell("foo", Option::from(2), None, 0, 0);
+        p.cell("bar", None, Option::from(1), 0, 0);
+        p.unit("foo", "kg");
+        p.func("move", "default", 0, 0);
+        p.arrow("foo", "move", 1);
+        p.arrow("move", "bar", 1);
+    }
+
+    #[test]
+    fn test_simulator_metrics() {
+        let recorder = TestRecorder::default();
+        let model = model_cid(&StateMachine::new(counter_net));
+        ::metrics::with_local_recorder(&recorder, || {
+            describe();
+            let mut sim = Simulator::new(StateMachine::new(counter_net));
+            sim.fire("missing", 1);
+            sim.run(10);
+            record_request("/models/:cid", 404, Duration::from_millis(3));
+        });
+        assert_eq!(recorder.value(&format!("pflow_fires_total{{model={},action=move}}", model)), Some(1));
+        assert_eq!(
+            recorder.value(&format!(
+                "pflow_rejections_total{{model={},action=missing,reason=unknown_action}}",
+                model
+            )),
+            Some(1)
+        );
+        assert_eq!(
+            recorder.value(&format!("pflow_rejections_total{{model={},action=move,reason=overflow}}", model)),
+            None
+        );
+        let tokens = recorder.value(&format!("pflow_tokens{{model={},place=bar}}", model)).unwrap();
+        assert_eq!(f64::from_bits(tokens), 1.0);
+        let tokens = recorder.value(&format!("pflow_tokens{{model={},place=foo,unit=kg}}", model)).unwrap();
+        assert_eq!(f64::from_bits(tokens), 1.0);
+        let steps = recorder.samples.lock().unwrap()["pflow_simulation_step_seconds{}"].clone();
+        assert_eq!(steps.0.lock().unwrap().len(), 1);
+        assert_eq!(
+            recorder.value("pflow_http_requests_total{route=/models/:cid,status=404}"),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_rejection_reasons() {
+        let sm = StateMachine::new(counter_net);
+        let state = sm.initial_vector();
+        assert_eq!(rejection_reason(&sm.transform(&state, "move", 1)), None);
+        let refused = Transaction {
+            ok: false,
+            ..sm.transform(&state, "move", 1)
+        };
+        assert_eq!(rejection_reason(&refused), Some("not_enabled"));
+        assert_eq!(rejection_reason(&sm.transform(&state, "move", 2)), Some("overflow"));
+        assert_eq!(
+            rejection_reason(&sm.transform(&vec![0, 0], "move", 1)),
+            Some("underflow")
+        );
+        assert_eq!(
+            rejection_reason(&sm.transform(&vec![0], "move", 1)),
+            Some("dimension_mismatch")
+        );
+        assert_eq!(
+            rejection_reason(&sm.transform_as(&state, "move", 1, "guest")),
+            Some("role_mismatch")
+        );
+        let error = TransformError::QuotaExceeded {
+            role: "default".to_string(),
+            action: "move".to_string(),
+            limit: 1,
+        };
+        assert_eq!(
+            rejection_reason(&Transaction::rejected(&state, "default", error)),
+            Some("quota_exceeded")
+        );
+    }
+}
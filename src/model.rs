@@ -21,7 +21,7 @@ impl Model {
 
     pub fn declare(&mut self, func: fn(&mut dyn FlowDsl)) -> &mut Model {
         self.declaration.push(func);
-        self.vm = Box::new(self.net.declare(func).as_vasm());
+        *self.vm = self.net.declare(func).as_vasm();
         self
     }
 }
@@ -45,9 +45,9 @@ mod tests {
         });
 
         assert_eq!(model.net.model_type, "petriNet");
-        let zblob = model.net.to_zblob();
+        #[cfg(feature = "zblob")]
         assert_eq!(
-            zblob.ipfs_cid,
+            model.net.to_zblob().ipfs_cid,
             "zb2rhXz6Zi73pN9tyWzNGCLUCd9MLvAkupcBKXpCvrV87Rch4"
         );
 
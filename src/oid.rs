@@ -1,3 +1,5 @@
+use std::fmt;
+
 use libipld::cid::Cid;
 use libipld::multihash::{Code, MultihashDigest};
 
@@ -24,19 +26,6 @@ impl Oid {
         Ok(Self { cid })
     }
 
-    /// Converts the `Oid` to a string.
-    ///
-    /// # Returns
-    ///
-    /// * A string that represents the `Oid`.
-    ///
-    pub fn to_string(&self) -> String {
-        return self
-            .cid
-            .to_string_of_base(multibase::Base::Base58Btc)
-            .unwrap();
-    }
-
     /// Converts the `Oid` to a byte vector.
     ///
     /// # Returns
@@ -48,12 +37,24 @@ impl Oid {
     }
 }
 
+impl fmt::Display for Oid {
+    /// Formats the `Oid` as a base58btc encoded string.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encoded = self
+            .cid
+            .to_string_of_base(multibase::Base::Base58Btc)
+            .map_err(|_| fmt::Error)?;
+        write!(f, "{}", encoded)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::fixtures::DINING_PHILOSOPHERS;
 
     use super::*;
 
+    #[allow(dead_code)]
     #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
     struct TestStruct {
         field1: u32,
@@ -1,13 +1,34 @@
 use std::collections::HashMap;
+use std::fmt;
 
 use serde::{Deserialize, Serialize};
-use serde_json::Error;
+use serde_json::{Error, Value};
 
+use crate::capacity::Capacity;
+use crate::codegen;
 use crate::dsl::{Builder, FlowDsl};
+use crate::expr::MarkingWeight;
+pub use crate::equivalence::{equivalent, equivalent_within, Equivalence};
+use crate::hierarchy::Subnet;
+use crate::text_dsl::{self, ParseError};
+#[cfg(feature = "zblob")]
 use crate::zblob::Zblob;
 
+/// The role of the transitions without one, unless the net declares another with `PetriNet::default_role`.
+pub const DEFAULT_ROLE: &str = "default";
+
+/// Returns true if every number within the value is an integer, as attributes of a net must be.
+pub fn is_integral(value: &Value) -> bool {
+    match value {
+        Value::Number(n) => !n.is_f64(),
+        Value::Array(items) => items.iter().all(is_integral),
+        Value::Object(fields) => fields.values().all(is_integral),
+        _ => true,
+    }
+}
+
 /// PetriNet stores petri-net elements used during the construction of a petri-net.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PetriNet {
     pub model_type: String,
@@ -15,6 +36,16 @@ pub struct PetriNet {
     pub places: HashMap<String, Place>,
     pub transitions: HashMap<String, Transition>,
     pub arcs: Vec<Arrow>,
+    /// Custom key-value data attached to the net by applications, such as owners, SLAs or URLs.
+    /// Numbers must be integers, the canonical JSON of the net has no floating point values.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub attributes: HashMap<String, Value>,
+    /// The roles transitions may belong to by name, once declared every role in use must be, see `validate_roles`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub roles: HashMap<String, Role>,
+    /// The role of the transitions without one, `DEFAULT_ROLE` if None.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_role: Option<String>,
 }
 
 impl Default for PetriNet {
@@ -25,28 +56,83 @@ impl Default for PetriNet {
             places: HashMap::new(),
             transitions: HashMap::new(),
             arcs: Vec::new(),
+            attributes: HashMap::new(),
+            roles: HashMap::new(),
+            default_role: None,
+        }
+    }
+}
+
+/// Role is a role declared in the `roles` section of a net.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Role {
+    /// A human readable description of the role.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The roles whose transitions this role may fire as well, such as a manager implying a clerk.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub implies: Vec<String>,
+}
+
+/// `RoleError` is returned by `PetriNet::validate_roles` when a net uses a role it does not declare.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoleError {
+    /// The transition belongs to a role missing from the `roles` section.
+    UndeclaredRole { transition: String, role: String },
+    /// The declared role implies a role missing from the `roles` section.
+    UnknownImpliedRole { role: String, implied: String },
+}
+
+impl fmt::Display for RoleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoleError::UndeclaredRole { transition, role } => {
+                write!(f, "transition {} belongs to undeclared role {}", transition, role)
+            }
+            RoleError::UnknownImpliedRole { role, implied } => {
+                write!(f, "role {} implies undeclared role {}", role, implied)
+            }
         }
     }
 }
 
+impl std::error::Error for RoleError {}
+
 impl PetriNet {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub(crate) fn declare(&mut self, func: fn(&mut dyn FlowDsl)) -> Builder {
+    pub(crate) fn declare(&mut self, func: fn(&mut dyn FlowDsl)) -> Builder<'_> {
         let mut flow_builder = Builder::new(self);
         func(&mut flow_builder);
-        return flow_builder;
+        flow_builder
     }
 
     /// Creates a new `PetriNet` object from the given JSON string.
     pub fn from_json(contents: String) -> Result<Self, Error> {
-        let mut petri_net: PetriNet = serde_json::from_str(&*contents)?;
+        let mut petri_net: PetriNet = serde_json::from_str(&contents)?;
+        if let Some((label, key)) = petri_net.non_integer_attribute() {
+            return Err(serde::de::Error::custom(format!(
+                "attribute {} of {} is not an integer",
+                key,
+                if label.is_empty() { "the net" } else { label }
+            )));
+        }
         petri_net.populate_arc_attributes();
         Ok(petri_net)
     }
 
+    /// Creates a new `PetriNet` object from a model written in the textual `.pflow` format.
+    pub fn from_dsl_str(source: &str) -> Result<Self, ParseError> {
+        text_dsl::parse(source)
+    }
+
+    /// Generates the Rust source of a `FlowDsl` declaration reproducing the net.
+    pub fn to_dsl_source(&self) -> String {
+        codegen::to_dsl_source(self)
+    }
+
     /// Converts the `PetriNet` to a canonical JSON string.
     pub fn to_json(&self) -> Result<String, cjson::Error> {
         let res: serde_json::Value = serde_json::to_value(self)?;
@@ -54,19 +140,49 @@ impl PetriNet {
     }
 
     /// Converts the `PetriNet` to a `Zblob` object.
+    #[cfg(feature = "zblob")]
     pub fn to_zblob(&self) -> Zblob {
         Zblob::from_net(self)
     }
 }
 
+/// Picks the label for the locale, falling back to the default label and then to the identifier.
+pub(crate) fn localized<'a>(
+    id: &'a str,
+    default: &'a Option<String>,
+    labels: &'a HashMap<String, String>,
+    locale: Option<&str>,
+) -> &'a str {
+    locale
+        .and_then(|l| labels.get(l))
+        .or(default.as_ref())
+        .map_or(id, String::as_str)
+}
+
 /// Place is a struct that represents a place (cell in FLowDsl).
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Place {
     pub offset: i32,
     pub initial: Option<i32>,
-    pub capacity: Option<i32>,
+    /// The largest number of tokens the place may hold, None is unbounded as well.
+    pub capacity: Option<Capacity>,
     pub x: i32,
     pub y: i32,
+    /// The unit of the tokens held by the place, such as "items" or "€ cents".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// The name shown for the place instead of its identifier, the key in `PetriNet::places`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The translated names of the place by locale, such as "de" or "pt-BR".
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub labels: HashMap<String, String>,
+    /// A human readable description of the place.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Custom key-value data attached to the place by applications, see `PetriNet::attributes`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub attributes: HashMap<String, Value>,
 }
 
 impl Default for Place {
@@ -74,9 +190,14 @@ impl Default for Place {
         Self {
             offset: 0,
             initial: Option::from(0),
-            capacity: Option::from(0),
+            capacity: Option::from(Capacity::Unbounded),
             x: 0,
             y: 0,
+            unit: None,
+            label: None,
+            labels: HashMap::new(),
+            description: None,
+            attributes: HashMap::new(),
         }
     }
 }
@@ -87,6 +208,39 @@ pub struct Transition {
     pub role: Option<String>,
     pub x: i32,
     pub y: i32,
+    /// The child net refining a substitution transition, see `PetriNet::flatten`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subnet: Option<Box<Subnet>>,
+    /// Lets a workflow transition fire into the place that is already marked, see `ReentryPolicy`.
+    #[serde(default, rename = "allowReentry", skip_serializing_if = "std::ops::Not::not")]
+    pub allow_reentry: bool,
+    /// The priority used to resolve conflicts between enabled transitions, higher fires first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+    /// The firing rate of the transition relative to the others in a stochastic interpretation of the net.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate: Option<i32>,
+    /// The time units the transition takes to fire in a timed interpretation of the net.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay: Option<i32>,
+    /// The deadline of the transition once it is enabled, see `simulation::timers`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<Timeout>,
+    /// The predicate over the case variables the transition requires to fire, see `expr::Condition`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    /// The name shown for the transition instead of its identifier, the key in `PetriNet::transitions`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The translated names of the transition by locale, such as "de" or "pt-BR".
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub labels: HashMap<String, String>,
+    /// A human readable description of the transition.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Custom key-value data attached to the transition by applications, see `PetriNet::attributes`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub attributes: HashMap<String, Value>,
 }
 
 impl Default for Transition {
@@ -95,12 +249,32 @@ impl Default for Transition {
             role: Option::from("default".to_string()),
             x: 0,
             y: 0,
+            subnet: None,
+            allow_reentry: false,
+            priority: None,
+            rate: None,
+            delay: None,
+            timeout: None,
+            condition: None,
+            label: None,
+            labels: HashMap::new(),
+            description: None,
+            attributes: HashMap::new(),
         }
     }
 }
 
+/// Timeout is the deadline of a transition: the time units it may stay enabled without firing.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Timeout {
+    pub after: i32,
+    /// The transition fired automatically once the deadline passes, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalate: Option<String>,
+}
+
 /// Arrow is a struct that represents an arrow (arc in FlowDsl).
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Arrow {
     pub source: String,
     pub target: String,
@@ -109,6 +283,15 @@ pub struct Arrow {
     pub produce: Option<bool>,
     pub inhibit: Option<bool>,
     pub read: Option<bool>,
+    /// Empties the source place when the target transition fires, see `engine::Transfer`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset: Option<bool>,
+    /// The place receiving `weight` times the tokens left in the source place when the target transition fires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transfer: Option<String>,
+    /// The weight computed from the marking in place of `weight`, see `expr::MarkingWeight`.
+    #[serde(default, rename = "markingWeight", skip_serializing_if = "Option::is_none")]
+    pub marking_weight: Option<String>,
 }
 
 impl PetriNet {
@@ -132,7 +315,78 @@ impl PetriNet {
         }
     }
 
-    /// Adds a place to the petri-net.
+    /// Reassigns place offsets to a dense range starting at zero while keeping their relative order.
+    pub(crate) fn reindex_offsets(&mut self) {
+        let mut labels: Vec<(i32, String)> = self
+            .places
+            .iter()
+            .map(|(k, v)| (v.offset, k.clone()))
+            .collect();
+        labels.sort();
+        for (i, (_, label)) in labels.iter().enumerate() {
+            self.places.get_mut(label).unwrap().offset = i as i32;
+        }
+    }
+
+    /// Declares a role, replacing any previous declaration of the same name.
+    pub fn add_role(&mut self, name: &str, description: Option<&str>, implies: &[&str]) {
+        self.roles.insert(
+            name.to_string(),
+            Role {
+                description: description.map(str::to_string),
+                implies: implies.iter().map(|r| r.to_string()).collect(),
+            },
+        );
+    }
+
+    /// Sets the role of the transitions without one.
+    pub fn set_default_role(&mut self, role: &str) {
+        self.default_role = Some(role.to_string());
+    }
+
+    /// Returns the role of the transitions without one.
+    pub fn default_role(&self) -> &str {
+        self.default_role.as_deref().unwrap_or(DEFAULT_ROLE)
+    }
+
+    /// Returns the role of the transition, the default role if it has none, or None if there is no such transition.
+    pub fn transition_role(&self, label: &str) -> Option<&str> {
+        let transition = self.transitions.get(label)?;
+        Some(transition.role.as_deref().unwrap_or(self.default_role()))
+    }
+
+    /// Checks that every transition and implied role references a declared role, in label order.
+    ///
+    /// Nets without a `roles` section declare nothing and accept any role.
+    pub fn validate_roles(&self) -> Result<(), RoleError> {
+        if self.roles.is_empty() {
+            return Ok(());
+        }
+        let mut labels: Vec<&String> = self.transitions.keys().collect();
+        labels.sort();
+        for label in labels {
+            let role = self.transition_role(label).unwrap();
+            if !self.roles.contains_key(role) {
+                return Err(RoleError::UndeclaredRole {
+                    transition: label.clone(),
+                    role: role.to_string(),
+                });
+            }
+        }
+        let mut names: Vec<&String> = self.roles.keys().collect();
+        names.sort();
+        for name in names {
+            if let Some(implied) = self.roles[name].implies.iter().find(|r| !self.roles.contains_key(*r)) {
+                return Err(RoleError::UnknownImpliedRole {
+                    role: name.clone(),
+                    implied: implied.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a place to the petri-net, `capacity` is read with the legacy semantics of `Capacity::from_legacy`.
     pub fn add_place(
         &mut self,
         label: &str,
@@ -147,12 +401,161 @@ impl PetriNet {
             Place {
                 offset,
                 initial,
-                capacity,
+                capacity: capacity.map(Capacity::from_legacy),
                 x,
                 y,
+                ..Place::default()
             },
         );
-        return;
+    }
+
+    /// Sets the capacity of a place, returns false if there is no such place.
+    pub fn set_capacity(&mut self, label: &str, capacity: Capacity) -> bool {
+        self.places.get_mut(label).map(|p| p.capacity = Some(capacity)).is_some()
+    }
+
+    /// Sets the unit of the tokens held by a place, returns false if there is no such place.
+    pub fn set_unit(&mut self, label: &str, unit: &str) -> bool {
+        match self.places.get_mut(label) {
+            Some(place) => {
+                place.unit = Some(unit.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets whether a workflow transition may fire into the place that is already marked,
+    /// returns false if there is no such transition.
+    pub fn set_allow_reentry(&mut self, label: &str, allow: bool) -> bool {
+        self.transitions.get_mut(label).map(|t| t.allow_reentry = allow).is_some()
+    }
+
+    /// Sets the priority of a transition, returns false if there is no such transition.
+    pub fn set_priority(&mut self, label: &str, priority: i32) -> bool {
+        self.transitions.get_mut(label).map(|t| t.priority = Some(priority)).is_some()
+    }
+
+    /// Sets the firing rate of a transition, returns false if there is no such transition.
+    pub fn set_rate(&mut self, label: &str, rate: i32) -> bool {
+        self.transitions.get_mut(label).map(|t| t.rate = Some(rate)).is_some()
+    }
+
+    /// Sets the firing delay of a transition, returns false if there is no such transition.
+    pub fn set_delay(&mut self, label: &str, delay: i32) -> bool {
+        self.transitions.get_mut(label).map(|t| t.delay = Some(delay)).is_some()
+    }
+
+    /// Sets the deadline of a transition and the transition escalating it, returns false if there is no such transition.
+    pub fn set_timeout(&mut self, label: &str, after: i32, escalate: Option<&str>) -> bool {
+        let timeout = Timeout {
+            after,
+            escalate: escalate.map(str::to_string),
+        };
+        self.transitions.get_mut(label).map(|t| t.timeout = Some(timeout)).is_some()
+    }
+
+    /// Sets the condition over the case variables of a transition, returns false if there is no such transition.
+    pub fn set_condition(&mut self, label: &str, condition: &str) -> bool {
+        self.transitions.get_mut(label).map(|t| t.condition = Some(condition.to_string())).is_some()
+    }
+
+    /// Sets the description of a place or transition, returns false if there is no such node.
+    pub fn set_description(&mut self, label: &str, description: &str) -> bool {
+        if let Some(place) = self.places.get_mut(label) {
+            place.description = Some(description.to_string());
+        } else if let Some(transition) = self.transitions.get_mut(label) {
+            transition.description = Some(description.to_string());
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Sets a custom attribute of a place or transition, or of the net itself if `label` is empty,
+    /// returns false if there is no such node or the value holds a number that is not an integer.
+    pub fn set_attribute(&mut self, label: &str, key: &str, value: Value) -> bool {
+        if !is_integral(&value) {
+            return false;
+        }
+        let attributes = match (self.places.get_mut(label), self.transitions.get_mut(label)) {
+            _ if label.is_empty() => &mut self.attributes,
+            (Some(place), _) => &mut place.attributes,
+            (None, Some(transition)) => &mut transition.attributes,
+            (None, None) => return false,
+        };
+        attributes.insert(key.to_string(), value);
+        true
+    }
+
+    /// Sets the display label of a place or transition, for the given locale or as the default if `locale` is None,
+    /// returns false if there is no such node.
+    pub fn set_label(&mut self, id: &str, locale: Option<&str>, label: &str) -> bool {
+        let (default, labels) = match (self.places.get_mut(id), self.transitions.get_mut(id)) {
+            (Some(place), _) => (&mut place.label, &mut place.labels),
+            (None, Some(transition)) => (&mut transition.label, &mut transition.labels),
+            (None, None) => return false,
+        };
+        match locale {
+            Some(locale) => labels.insert(locale.to_string(), label.to_string()),
+            None => default.replace(label.to_string()),
+        };
+        true
+    }
+
+    /// Returns the name to show for a place or transition: its label for the locale, else its default label,
+    /// else its identifier. Returns None if there is no such node.
+    pub fn display_label<'a>(&'a self, id: &'a str, locale: Option<&str>) -> Option<&'a str> {
+        let (default, labels) = match (self.places.get(id), self.transitions.get(id)) {
+            (Some(place), _) => (&place.label, &place.labels),
+            (None, Some(transition)) => (&transition.label, &transition.labels),
+            (None, None) => return None,
+        };
+        Some(localized(id, default, labels, locale))
+    }
+
+    /// Returns the label of the node, empty for the net itself, and the key of an attribute holding a number
+    /// that is not an integer, which the canonical JSON of the net cannot encode.
+    pub fn non_integer_attribute(&self) -> Option<(&str, &str)> {
+        let nodes = self
+            .places
+            .iter()
+            .map(|(label, place)| (label.as_str(), &place.attributes))
+            .chain(self.transitions.iter().map(|(label, t)| (label.as_str(), &t.attributes)));
+        std::iter::once(("", &self.attributes))
+            .chain(nodes)
+            .find_map(|(label, attributes)| {
+                attributes
+                    .iter()
+                    .find(|(_, value)| !is_integral(value))
+                    .map(|(key, _)| (label, key.as_str()))
+            })
+    }
+
+    /// Replaces the attributes holding numbers that are not integers by their JSON text, so the net can be
+    /// hashed even where it was edited without `set_attribute`.
+    #[cfg(any(feature = "tracing", feature = "zblob"))]
+    pub(crate) fn stringify_non_integer_attributes(&mut self) {
+        let bags = std::iter::once(&mut self.attributes)
+            .chain(self.places.values_mut().map(|place| &mut place.attributes))
+            .chain(self.transitions.values_mut().map(|t| &mut t.attributes));
+        for value in bags.flat_map(|attributes| attributes.values_mut()) {
+            if !is_integral(value) {
+                *value = Value::String(value.to_string());
+            }
+        }
+    }
+
+    /// Returns a custom attribute of a place or transition, or of the net itself if `label` is empty.
+    pub fn attribute(&self, label: &str, key: &str) -> Option<&Value> {
+        if label.is_empty() {
+            return self.attributes.get(key);
+        }
+        match (self.places.get(label), self.transitions.get(label)) {
+            (Some(place), _) => place.attributes.get(key),
+            (None, Some(transition)) => transition.attributes.get(key),
+            (None, None) => None,
+        }
     }
 
     /// Adds a transition to the petri-net.
@@ -163,12 +566,13 @@ impl PetriNet {
                 role: Option::from(role.to_string()),
                 x,
                 y,
+                ..Transition::default()
             },
         );
-        return;
     }
 
     /// Adds an arc to the petri-net.
+    #[allow(clippy::too_many_arguments)]
     pub fn add_arc(
         &mut self,
         source: &str,
@@ -187,14 +591,120 @@ impl PetriNet {
             produce,
             inhibit,
             read,
+            reset: None,
+            transfer: None,
+            marking_weight: None,
         });
-        return;
+    }
+
+    /// Adds a reset arc emptying the place when the transition fires.
+    pub fn add_reset_arc(&mut self, place: &str, transition: &str) {
+        self.add_arc(place, transition, None, Some(true), Some(false), None, Some(false));
+        self.arcs.last_mut().unwrap().reset = Some(true);
+    }
+
+    /// Adds a transfer arc moving the tokens left in `source` to `target`, `weight` times as many,
+    /// when the transition fires.
+    pub fn add_transfer_arc(&mut self, source: &str, transition: &str, target: &str, weight: i32) {
+        self.add_arc(source, transition, Some(weight), Some(true), Some(false), None, Some(false));
+        self.arcs.last_mut().unwrap().transfer = Some(target.to_string());
+    }
+
+    /// Adds an arc whose weight is computed from the marking when the transition fires, such as `all` or `half`.
+    pub fn add_marking_arc(&mut self, source: &str, target: &str, weight: &str) {
+        let consume = self.places.contains_key(source);
+        self.add_arc(source, target, None, Some(consume), Some(!consume), None, Some(false));
+        self.arcs.last_mut().unwrap().marking_weight = Some(weight.to_string());
+    }
+
+    fn is_node(&self, label: &str) -> bool {
+        self.places.contains_key(label) || self.transitions.contains_key(label)
+    }
+
+    fn rename_arcs(&mut self, from: &str, to: &str) {
+        for arc in &mut self.arcs {
+            if arc.source == from {
+                arc.source = to.to_string();
+            }
+            if arc.target == from {
+                arc.target = to.to_string();
+            }
+        }
+    }
+
+    /// Renames a place along with its arcs, transfers into it, arcs weighted by its marking and the subnet
+    /// sockets bound to it, returns false if there is no such place or the new label is taken.
+    pub fn rename_place(&mut self, from: &str, to: &str) -> bool {
+        if !self.places.contains_key(from) || self.is_node(to) {
+            return false;
+        }
+        let place = self.places.remove(from).unwrap();
+        self.places.insert(to.to_string(), place);
+        self.rename_arcs(from, to);
+        for arc in &mut self.arcs {
+            if arc.transfer.as_deref() == Some(from) {
+                arc.transfer = Some(to.to_string());
+            }
+            let weight = arc.marking_weight.as_deref().and_then(|w| MarkingWeight::parse(w).ok());
+            if weight.and_then(|w| w.of).is_some_and(|of| of == from) {
+                let source = arc.marking_weight.as_deref().unwrap();
+                arc.marking_weight = Some(MarkingWeight::rename_place(source, |_| to.to_string()));
+            }
+        }
+        for subnet in self.transitions.values_mut().filter_map(|t| t.subnet.as_mut()) {
+            subnet.ports.values_mut().filter(|s| *s == from).for_each(|s| *s = to.to_string());
+        }
+        true
+    }
+
+    /// Renames a transition along with its arcs, returns false if there is no such transition or the new label is taken.
+    pub fn rename_transition(&mut self, from: &str, to: &str) -> bool {
+        if !self.transitions.contains_key(from) || self.is_node(to) {
+            return false;
+        }
+        let transition = self.transitions.remove(from).unwrap();
+        self.transitions.insert(to.to_string(), transition);
+        self.rename_arcs(from, to);
+        true
+    }
+
+    /// Removes a place with its arcs, including guards, transfers into it and arcs weighted by its marking,
+    /// and shifts the offsets of the following places. Subnet ports merged into the place become places of
+    /// their subnet. Returns false if there is no such place.
+    pub fn remove_place(&mut self, label: &str) -> bool {
+        if self.places.remove(label).is_none() {
+            return false;
+        }
+        self.arcs.retain(|a| {
+            let weighted_by = || {
+                let weight = a.marking_weight.as_deref().and_then(|w| MarkingWeight::parse(w).ok());
+                weight.and_then(|w| w.of).is_some_and(|of| of == label)
+            };
+            a.source != label && a.target != label && a.transfer.as_deref() != Some(label) && !weighted_by()
+        });
+        for transition in self.transitions.values_mut() {
+            if let Some(subnet) = &mut transition.subnet {
+                subnet.ports.retain(|_, socket| socket != label);
+            }
+        }
+        self.reindex_offsets();
+        true
+    }
+
+    /// Removes a transition with its arcs, including guards, returns false if there is no such transition.
+    pub fn remove_transition(&mut self, label: &str) -> bool {
+        if self.transitions.remove(label).is_none() {
+            return false;
+        }
+        self.arcs.retain(|a| a.source != label && a.target != label);
+        true
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::vasm::{StateMachine, Vasm};
 
     use super::*;
 
@@ -215,6 +725,149 @@ mod tests {
     }
 
     #[test]
+    fn test_place_unit_round_trip() {
+        let mut petri_net = PetriNet::new();
+        petri_net.add_place("stock", 0, Some(3), None, 0, 0);
+        assert!(petri_net.set_unit("stock", "items"));
+        assert!(!petri_net.set_unit("missing", "items"));
+        let net = PetriNet::from_json(petri_net.to_json().unwrap()).unwrap();
+        assert_eq!(net.places["stock"].unit.as_deref(), Some("items"));
+    }
+
+    fn editable() -> PetriNet {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.cell("c", None, None, 0, 0);
+            p.func("t", "default", 0, 0);
+            p.arrow("a", "t", 1);
+            p.arrow("t", "c", 1);
+            p.guard("b", "t", 1);
+        });
+        net
+    }
+
+    #[test]
+    fn test_rename_nodes() {
+        let mut net = editable();
+        assert!(net.rename_place("a", "start"));
+        assert!(net.rename_transition("t", "go"));
+        assert!(!net.rename_place("b", "c"));
+        assert!(!net.rename_place("missing", "x"));
+        assert!(!net.rename_transition("go", "start"));
+        assert_eq!(net.places["start"].offset, 0);
+        assert!(net.arcs.iter().all(|a| a.source != "a" && a.target != "t"));
+
+        let sm = StateMachine::from_model(&mut net);
+        assert!(sm.transform(&sm.initial_vector(), "go", 1).is_ok());
+
+        let mut net = editable();
+        net.add_transfer_arc("a", "t", "b", 1);
+        net.add_marking_arc("c", "t", "half of b");
+        assert!(net.rename_place("b", "bin"));
+        assert_eq!(net.arcs[3].transfer.as_deref(), Some("bin"));
+        assert_eq!(net.arcs[4].marking_weight.as_deref(), Some("half of bin"));
+        assert!(StateMachine::try_from_model(&mut net).is_ok());
+    }
+
+    #[test]
+    fn test_remove_nodes() {
+        let mut net = editable();
+        net.add_transfer_arc("a", "t", "b", 1);
+        net.add_marking_arc("a", "t", "half of b");
+        assert!(net.remove_place("b"));
+        assert!(!net.remove_place("b"));
+        assert_eq!(net.places["c"].offset, 1);
+        assert_eq!(net.arcs.len(), 2);
+
+        let sm = StateMachine::from_model(&mut net.clone());
+        assert_eq!(sm.transform(&sm.initial_vector(), "t", 1).output, vec![0, 1]);
+
+        assert!(net.remove_transition("t"));
+        assert!(net.arcs.is_empty());
+    }
+
+    #[test]
+    fn test_attributes() {
+        let mut net = editable();
+        assert!(net.set_attribute("", "owner", serde_json::json!("ops")));
+        assert!(net.set_attribute("b", "url", serde_json::json!("https://pflow.dev")));
+        assert!(net.set_attribute("t", "sla", serde_json::json!({"hours": 4})));
+        assert!(!net.set_attribute("missing", "sla", serde_json::json!(1)));
+        assert!(!net.set_attribute("t", "sla", serde_json::json!({"hours": 4.5})));
+        assert_eq!(net.attribute("t", "sla").unwrap()["hours"], 4);
+        assert_eq!(net.non_integer_attribute(), None);
+
+        let restored = PetriNet::from_json(net.to_json().unwrap()).unwrap();
+        assert_eq!(restored.attribute("", "owner").unwrap(), "ops");
+
+        let sm = StateMachine::from_model(&mut net);
+        assert_eq!(sm.attributes["owner"], "ops");
+        assert_eq!(sm.place_attributes[net.places["b"].offset as usize]["url"], "https://pflow.dev");
+        assert_eq!(sm.transitions["t"].attributes()["sla"]["hours"], 4);
+
+        net.transitions.get_mut("t").unwrap().attributes.insert("cost".into(), serde_json::json!([1, 2.5]));
+        assert_eq!(net.non_integer_attribute(), Some(("t", "cost")));
+        assert!(net.to_json().is_err());
+        let json = serde_json::to_string(&net).unwrap();
+        assert!(PetriNet::from_json(json).unwrap_err().to_string().starts_with("attribute cost of t is not an integer"));
+        assert_eq!(
+            StateMachine::try_from_model(&mut net).unwrap_err().to_string(),
+            "attribute cost of t is not an integer"
+        );
+    }
+
+    #[test]
+    fn test_role_registry() {
+        let mut net = editable();
+        net.transitions.get_mut("t").unwrap().role = None;
+        assert_eq!(net.transition_role("t"), Some(DEFAULT_ROLE));
+        assert_eq!(net.validate_roles(), Ok(()));
+
+        net.add_role("clerk", Some("handles requests"), &[]);
+        net.add_role("manager", None, &["clerk", "auditor"]);
+        assert_eq!(
+            net.validate_roles(),
+            Err(RoleError::UndeclaredRole {
+                transition: "t".to_string(),
+                role: DEFAULT_ROLE.to_string()
+            })
+        );
+        net.set_default_role("clerk");
+        assert_eq!(net.transition_role("t"), Some("clerk"));
+        assert_eq!(
+            net.validate_roles().unwrap_err().to_string(),
+            "role manager implies undeclared role auditor"
+        );
+        net.add_role("auditor", None, &[]);
+        assert_eq!(net.validate_roles(), Ok(()));
+
+        let json = net.to_json().unwrap();
+        assert!(json.contains("\"defaultRole\":\"clerk\""));
+        let restored = PetriNet::from_json(json).unwrap();
+        assert_eq!(restored.roles["manager"].implies, vec!["clerk", "auditor"]);
+        assert_eq!(restored.roles["clerk"].description.as_deref(), Some("handles requests"));
+    }
+
+    #[test]
+    fn test_display_labels() {
+        let mut net = editable();
+        assert!(net.set_label("t", None, "Transfer"));
+        assert!(net.set_label("t", Some("de"), "Überweisen"));
+        assert!(!net.set_label("missing", None, "Missing"));
+        assert_eq!(net.display_label("t", Some("de")), Some("Überweisen"));
+        assert_eq!(net.display_label("t", Some("fr")), Some("Transfer"));
+        assert_eq!(net.display_label("b", Some("de")), Some("b"));
+        assert_eq!(net.display_label("missing", None), None);
+
+        let sm = StateMachine::from_model(&mut net);
+        assert_eq!(sm.transitions["t"].display_label(Some("de")), "Überweisen");
+        assert!(sm.transform(&sm.initial_vector(), "t", 1).is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "zblob")]
     fn test_zblob() {
         let petri_net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
         let zblob = petri_net.to_zblob();
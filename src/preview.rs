@@ -0,0 +1,163 @@
+use serde::Serialize;
+
+use crate::vasm::{StateMachine, TransformError, Vasm, Vector};
+use crate::vector;
+
+/// `FirePreview` tells whether an action would fire and why not, without firing it.
+///
+/// Places are listed by label in offset order, borrowed from the state machine.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FirePreview<'a> {
+    /// Whether the action would fire.
+    pub enabled: bool,
+    /// The places whose read or inhibitor arcs block the action.
+    pub blocking_guards: Vec<&'a str>,
+    /// The places that would exceed their capacity.
+    pub over_capacity: Vec<&'a str>,
+    /// The places that would hold fewer than zero tokens.
+    pub short_of_tokens: Vec<&'a str>,
+    /// The reason the action could not be evaluated, as `Vasm::transform` reports it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<TransformError>,
+}
+
+impl StateMachine {
+    /// Tells what firing the action `multiple` times in the state would do, without firing it.
+    ///
+    /// For petri-nets and other concurrent semantics the token counts are checked place by place without
+    /// building the output state, even when a guard blocks the action, so every reason is reported at once,
+    /// and nothing is allocated when the action is enabled. Other semantics, and transitions with reset,
+    /// transfer or marking weighted arcs, fall back to a full transformation, which stops at the guards.
+    pub fn preview(&self, state: &Vector, action: &str, multiple: i32) -> FirePreview<'_> {
+        let Some(transition) = self.transitions.get(action) else {
+            let error = TransformError::UnknownAction {
+                action: action.to_string(),
+            };
+            return FirePreview::rejected(error);
+        };
+        if self.is_empty() {
+            return FirePreview::rejected(TransformError::EmptyModel);
+        }
+        if state.len() != self.places.len() {
+            return FirePreview::rejected(TransformError::DimensionMismatch {
+                expected: self.places.len(),
+                actual: state.len(),
+            });
+        }
+
+        let mut preview = FirePreview::default();
+        let mut guards: Vec<(usize, &str)> = transition
+            .guards
+            .iter()
+            .filter(|(_, g)| g.blocks(state, multiple))
+            .map(|(label, g)| (g.place().unwrap_or(usize::MAX), label.as_str()))
+            .collect();
+        guards.sort();
+        preview.blocking_guards = guards.into_iter().map(|(_, label)| label).collect();
+
+        let plain = transition.transfers().is_empty() && transition.marking_arcs().is_empty();
+        if plain && self.firing_semantics().is_none() && self.semantics_config().is_concurrent() {
+            for (i, tokens) in state.iter().enumerate() {
+                let output = vector::fired(*tokens, *transition.delta.get(i).unwrap_or(&0), multiple);
+                self.check_place(&mut preview, i, output);
+            }
+            preview.enabled = preview.is_clear();
+            return preview;
+        }
+
+        let tx = self.transform(state, action, multiple);
+        for (i, output) in tx.output.iter().enumerate() {
+            self.check_place(&mut preview, i, *output as i64);
+        }
+        preview.enabled = tx.is_ok();
+        preview
+    }
+
+    fn check_place<'a>(&'a self, preview: &mut FirePreview<'a>, offset: usize, output: i64) {
+        let capacity = self.place_capacity(offset);
+        if output < 0 {
+            preview.short_of_tokens.push(&self.places[offset]);
+        } else if !i32::try_from(output).is_ok_and(|output| capacity.allows(output)) {
+            preview.over_capacity.push(&self.places[offset]);
+        }
+    }
+}
+
+impl FirePreview<'_> {
+    fn rejected(error: TransformError) -> Self {
+        Self {
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+
+    /// Checks if nothing blocks the action.
+    fn is_clear(&self) -> bool {
+        self.blocking_guards.is_empty() && self.over_capacity.is_empty() && self.short_of_tokens.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+    use crate::vasm::SemanticsConfig;
+
+    use super::*;
+
+    fn vending(p: &mut dyn FlowDsl) {
+        p.cell("coins", Option::from(1), None, 0, 0);
+        p.cell("cans", Option::from(2), None, 0, 0);
+        p.cell("tray", None, Option::from(1), 0, 0);
+        p.cell("jammed", None, None, 0, 0);
+        p.func("vend", "default", 0, 0);
+        p.arrow("coins", "vend", 2);
+        p.arrow("cans", "vend", 1);
+        p.arrow("vend", "tray", 1);
+        p.guard("jammed", "vend", 1);
+    }
+
+    #[test]
+    fn test_preview_reports_every_reason() {
+        let sm = StateMachine::new(vending);
+        let preview = sm.preview(&vec![2, 1, 0, 0], "vend", 1);
+        assert!(preview.enabled);
+        assert_eq!(
+            preview,
+            FirePreview {
+                enabled: true,
+                ..Default::default()
+            }
+        );
+
+        let preview = sm.preview(&vec![1, 1, 1, 1], "vend", 1);
+        assert!(!preview.enabled);
+        assert_eq!(preview.blocking_guards, vec!["jammed"]);
+        assert_eq!(preview.over_capacity, vec!["tray"]);
+        assert_eq!(preview.short_of_tokens, vec!["coins"]);
+
+        assert!(matches!(
+            sm.preview(&vec![0], "vend", 1).error,
+            Some(TransformError::DimensionMismatch { .. })
+        ));
+        assert!(sm.preview(&vec![0; 4], "refund", 1).error.is_some());
+    }
+
+    #[test]
+    fn test_preview_agrees_with_transform() {
+        let sm = StateMachine::new(vending);
+        for state in [vec![4, 2, 0, 0], vec![2, 1, 1, 0], vec![2, 2, 0, 1], vec![0, 0, 0, 0]] {
+            for multiple in 1..=2 {
+                let tx = sm.transform(&state, "vend", multiple);
+                let preview = sm.preview(&state, "vend", multiple);
+                assert_eq!(preview.enabled, tx.is_ok(), "{:?} x{}", state, multiple);
+                assert_eq!(preview.blocking_guards.is_empty(), !tx.inhibited);
+            }
+        }
+
+        let mut elementary = StateMachine::new(vending);
+        elementary.set_semantics(SemanticsConfig::ELEMENTARY);
+        let preview = elementary.preview(&vec![1, 1, 0, 0], "vend", 1);
+        assert!(!preview.enabled);
+        assert_eq!(preview.short_of_tokens, vec!["coins"]);
+    }
+}
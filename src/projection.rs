@@ -0,0 +1,88 @@
+use serde::Serialize;
+
+use crate::vasm::{StateMachine, Vector};
+
+/// `Projection` is a named subset of a marking in the order the places were requested.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Projection {
+    /// The requested place labels.
+    pub places: Vec<String>,
+    /// The token count of each requested place, `None` for labels that are not places of the state machine.
+    pub values: Vec<Option<i32>>,
+}
+
+impl Projection {
+    /// Returns the token count of the given place if it is part of the projection.
+    pub fn get(&self, place: &str) -> Option<i32> {
+        self.get_entry(place).flatten()
+    }
+
+    /// Lists the places whose token count differs from the previous projection.
+    ///
+    /// Places that are missing from the previous projection are always reported as changed.
+    pub fn changes<'a>(&'a self, previous: &Projection) -> Vec<&'a str> {
+        self.places
+            .iter()
+            .zip(&self.values)
+            .filter(|(place, value)| previous.get_entry(place) != Some(**value))
+            .map(|(place, _)| place.as_str())
+            .collect()
+    }
+
+    /// Checks if any place differs from the previous projection.
+    pub fn has_changed(&self, previous: &Projection) -> bool {
+        !self.changes(previous).is_empty()
+    }
+
+    fn get_entry(&self, place: &str) -> Option<Option<i32>> {
+        let i = self.places.iter().position(|p| p == place)?;
+        Some(self.values[i])
+    }
+}
+
+impl StateMachine {
+    /// Projects the state onto the given places, keeping their order so widgets can bind by position.
+    pub fn project(&self, state: &Vector, places: &[&str]) -> Projection {
+        Projection {
+            places: places.iter().map(|p| p.to_string()).collect(),
+            values: places
+                .iter()
+                .map(|p| {
+                    let i = self.places.iter().position(|label| label == p)?;
+                    state.get(i).copied()
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vasm::Vasm;
+
+    use super::*;
+
+    #[test]
+    fn test_project_and_detect_changes() {
+        let sm = StateMachine::new(|p| {
+            p.cell("foo", Option::from(1), None, 0, 0);
+            p.cell("bar", None, None, 0, 0);
+            p.cell("baz", Option::from(2), None, 0, 0);
+            p.func("move", "default", 0, 0);
+            p.arrow("foo", "move", 1);
+            p.arrow("move", "bar", 1);
+        });
+        let before = sm.project(&sm.initial_vector(), &["baz", "foo", "missing"]);
+        assert_eq!(before.values, vec![Some(2), Some(1), None]);
+        assert_eq!(before.get("foo"), Some(1));
+        assert_eq!(before.get("missing"), None);
+
+        let state = sm.transform(&sm.initial_vector(), "move", 1).output;
+        let after = sm.project(&state, &["baz", "foo", "missing"]);
+        assert_eq!(after.changes(&before), vec!["foo"]);
+        assert!(!after.has_changed(&after));
+
+        let wider = sm.project(&state, &["foo", "bar"]);
+        assert_eq!(wider.changes(&before), vec!["foo", "bar"]);
+    }
+}
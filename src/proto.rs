@@ -0,0 +1,705 @@
+use std::collections::HashMap;
+
+use prost::Message;
+
+use crate::capacity::Capacity;
+use crate::hierarchy;
+use crate::petri_net;
+use crate::vasm;
+
+#[derive(Clone, PartialEq, Message)]
+pub struct PetriNet {
+    #[prost(string, tag = "1")]
+    pub model_type: String,
+    #[prost(string, tag = "2")]
+    pub version: String,
+    #[prost(map = "string, message", tag = "3")]
+    pub places: HashMap<String, Place>,
+    #[prost(map = "string, message", tag = "4")]
+    pub transitions: HashMap<String, Transition>,
+    #[prost(message, repeated, tag = "5")]
+    pub arcs: Vec<Arrow>,
+    /// The attribute values encoded as JSON.
+    #[prost(map = "string, string", tag = "6")]
+    pub attributes: HashMap<String, String>,
+    #[prost(map = "string, message", tag = "7")]
+    pub roles: HashMap<String, Role>,
+    #[prost(string, optional, tag = "8")]
+    pub default_role: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Role {
+    #[prost(string, optional, tag = "1")]
+    pub description: Option<String>,
+    #[prost(string, repeated, tag = "2")]
+    pub implies: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Place {
+    #[prost(int32, tag = "1")]
+    pub offset: i32,
+    #[prost(int32, optional, tag = "2")]
+    pub initial: Option<i32>,
+    #[prost(int32, optional, tag = "3")]
+    pub capacity: Option<i32>,
+    #[prost(int32, tag = "4")]
+    pub x: i32,
+    #[prost(int32, tag = "5")]
+    pub y: i32,
+    #[prost(string, optional, tag = "6")]
+    pub unit: Option<String>,
+    #[prost(string, optional, tag = "7")]
+    pub description: Option<String>,
+    /// The attribute values encoded as JSON.
+    #[prost(map = "string, string", tag = "8")]
+    pub attributes: HashMap<String, String>,
+    #[prost(string, optional, tag = "9")]
+    pub label: Option<String>,
+    #[prost(map = "string, string", tag = "10")]
+    pub labels: HashMap<String, String>,
+    /// Set when `capacity` is an explicit bound, so a capacity of zero forbids tokens instead of meaning unbounded.
+    #[prost(bool, tag = "11")]
+    pub bounded: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Transition {
+    #[prost(string, optional, tag = "1")]
+    pub role: Option<String>,
+    #[prost(int32, tag = "2")]
+    pub x: i32,
+    #[prost(int32, tag = "3")]
+    pub y: i32,
+    #[prost(message, optional, boxed, tag = "4")]
+    pub subnet: Option<Box<Subnet>>,
+    #[prost(int32, optional, tag = "5")]
+    pub priority: Option<i32>,
+    #[prost(int32, optional, tag = "6")]
+    pub rate: Option<i32>,
+    #[prost(int32, optional, tag = "7")]
+    pub delay: Option<i32>,
+    #[prost(string, optional, tag = "8")]
+    pub description: Option<String>,
+    /// The attribute values encoded as JSON.
+    #[prost(map = "string, string", tag = "9")]
+    pub attributes: HashMap<String, String>,
+    #[prost(string, optional, tag = "10")]
+    pub label: Option<String>,
+    #[prost(map = "string, string", tag = "11")]
+    pub labels: HashMap<String, String>,
+    #[prost(bool, tag = "12")]
+    pub allow_reentry: bool,
+    #[prost(message, optional, tag = "13")]
+    pub timeout: Option<Timeout>,
+    #[prost(string, optional, tag = "14")]
+    pub condition: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Timeout {
+    #[prost(int32, tag = "1")]
+    pub after: i32,
+    #[prost(string, optional, tag = "2")]
+    pub escalate: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Subnet {
+    #[prost(message, optional, tag = "1")]
+    pub net: Option<PetriNet>,
+    #[prost(map = "string, string", tag = "2")]
+    pub ports: HashMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Arrow {
+    #[prost(string, tag = "1")]
+    pub source: String,
+    #[prost(string, tag = "2")]
+    pub target: String,
+    #[prost(int32, optional, tag = "3")]
+    pub weight: Option<i32>,
+    #[prost(bool, optional, tag = "4")]
+    pub consume: Option<bool>,
+    #[prost(bool, optional, tag = "5")]
+    pub produce: Option<bool>,
+    #[prost(bool, optional, tag = "6")]
+    pub inhibit: Option<bool>,
+    #[prost(bool, optional, tag = "7")]
+    pub read: Option<bool>,
+    #[prost(bool, optional, tag = "8")]
+    pub reset: Option<bool>,
+    #[prost(string, optional, tag = "9")]
+    pub transfer: Option<String>,
+    #[prost(string, optional, tag = "10")]
+    pub marking_weight: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Transaction {
+    #[prost(bool, tag = "1")]
+    pub ok: bool,
+    #[prost(int32, repeated, tag = "2")]
+    pub output: Vec<i32>,
+    #[prost(string, tag = "3")]
+    pub role: String,
+    #[prost(bool, tag = "4")]
+    pub inhibited: bool,
+    #[prost(bool, tag = "5")]
+    pub overflow: bool,
+    #[prost(bool, tag = "6")]
+    pub underflow: bool,
+    #[prost(message, optional, tag = "7")]
+    pub error: Option<TransformError>,
+    #[prost(string, tag = "8")]
+    pub action: String,
+    #[prost(int32, tag = "9")]
+    pub multiple: i32,
+    #[prost(int32, repeated, tag = "10")]
+    pub net_consumed: Vec<i32>,
+    #[prost(int32, repeated, tag = "11")]
+    pub net_produced: Vec<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TransformError {
+    #[prost(oneof = "transform_error::Kind", tags = "1, 2, 3, 4, 5")]
+    pub kind: Option<transform_error::Kind>,
+}
+
+pub mod transform_error {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct UnknownAction {
+        #[prost(string, tag = "1")]
+        pub action: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct EmptyModel {}
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DimensionMismatch {
+        #[prost(uint64, tag = "1")]
+        pub expected: u64,
+        #[prost(uint64, tag = "2")]
+        pub actual: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RoleMismatch {
+        #[prost(string, tag = "1")]
+        pub expected: String,
+        #[prost(string, tag = "2")]
+        pub actual: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct QuotaExceeded {
+        #[prost(string, tag = "1")]
+        pub role: String,
+        #[prost(string, tag = "2")]
+        pub action: String,
+        #[prost(uint32, tag = "3")]
+        pub limit: u32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Kind {
+        #[prost(message, tag = "1")]
+        UnknownAction(UnknownAction),
+        #[prost(message, tag = "2")]
+        EmptyModel(EmptyModel),
+        #[prost(message, tag = "3")]
+        DimensionMismatch(DimensionMismatch),
+        #[prost(message, tag = "4")]
+        RoleMismatch(RoleMismatch),
+        #[prost(message, tag = "5")]
+        QuotaExceeded(QuotaExceeded),
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StoreModelRequest {
+    #[prost(message, optional, tag = "1")]
+    pub net: Option<PetriNet>,
+    #[prost(string, tag = "2")]
+    pub title: String,
+    #[prost(string, tag = "3")]
+    pub description: String,
+    #[prost(string, tag = "4")]
+    pub keywords: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StoreModelResponse {
+    #[prost(string, tag = "1")]
+    pub cid: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetModelRequest {
+    #[prost(string, tag = "1")]
+    pub cid: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetModelResponse {
+    #[prost(message, optional, tag = "1")]
+    pub net: Option<PetriNet>,
+    #[prost(string, tag = "2")]
+    pub title: String,
+    #[prost(string, tag = "3")]
+    pub description: String,
+    #[prost(string, tag = "4")]
+    pub keywords: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FireTransitionRequest {
+    #[prost(string, tag = "1")]
+    pub cid: String,
+    /// The marking to fire in, the initial marking if empty.
+    #[prost(int32, repeated, tag = "2")]
+    pub state: Vec<i32>,
+    #[prost(string, tag = "3")]
+    pub action: String,
+    /// The number of times to fire, once if zero.
+    #[prost(int32, tag = "4")]
+    pub multiple: i32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetEnabledTransitionsRequest {
+    #[prost(string, tag = "1")]
+    pub cid: String,
+    /// The marking to check, the initial marking if empty.
+    #[prost(int32, repeated, tag = "2")]
+    pub state: Vec<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetEnabledTransitionsResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub actions: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct AnalyzeModelRequest {
+    #[prost(string, tag = "1")]
+    pub cid: String,
+    /// The maximum number of states to explore, the default limit if zero.
+    #[prost(uint64, tag = "2")]
+    pub max_states: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct AnalyzeModelResponse {
+    #[prost(string, tag = "1")]
+    pub class: String,
+    #[prost(uint64, tag = "2")]
+    pub states: u64,
+    #[prost(bool, tag = "3")]
+    pub complete: bool,
+    /// The bound of the places, unset if the net is unbounded or the bound is unknown.
+    #[prost(int32, optional, tag = "4")]
+    pub bound: Option<i32>,
+    /// The transitions proven dead, only the structurally dead ones if the exploration is not complete.
+    #[prost(string, repeated, tag = "5")]
+    pub dead_transitions: Vec<String>,
+    /// The analysis report in Markdown.
+    #[prost(string, tag = "6")]
+    pub report: String,
+}
+
+fn encode_attributes(attributes: &HashMap<String, serde_json::Value>) -> HashMap<String, String> {
+    attributes.iter().map(|(k, v)| (k.clone(), v.to_string())).collect()
+}
+
+/// Values that are not valid JSON, or hold numbers that are not integers, are kept as plain strings.
+fn decode_attributes(attributes: HashMap<String, String>) -> HashMap<String, serde_json::Value> {
+    attributes
+        .into_iter()
+        .map(|(k, v)| {
+            let value = serde_json::from_str(&v)
+                .ok()
+                .filter(petri_net::is_integral)
+                .unwrap_or(serde_json::Value::String(v));
+            (k, value)
+        })
+        .collect()
+}
+
+impl From<&petri_net::PetriNet> for PetriNet {
+    fn from(net: &petri_net::PetriNet) -> Self {
+        Self {
+            model_type: net.model_type.clone(),
+            version: net.version.clone(),
+            places: net
+                .places
+                .iter()
+                .map(|(label, p)| {
+                    let place = Place {
+                        offset: p.offset,
+                        initial: p.initial,
+                        capacity: p.capacity.map(|c| c.limit().unwrap_or(0)),
+                        x: p.x,
+                        y: p.y,
+                        unit: p.unit.clone(),
+                        description: p.description.clone(),
+                        attributes: encode_attributes(&p.attributes),
+                        label: p.label.clone(),
+                        labels: p.labels.clone(),
+                        bounded: p.capacity == Some(Capacity::Bounded(0)),
+                    };
+                    (label.clone(), place)
+                })
+                .collect(),
+            transitions: net
+                .transitions
+                .iter()
+                .map(|(label, t)| {
+                    let transition = Transition {
+                        role: t.role.clone(),
+                        x: t.x,
+                        y: t.y,
+                        subnet: t.subnet.as_ref().map(|s| {
+                            Box::new(Subnet {
+                                net: Some(PetriNet::from(&s.net)),
+                                ports: s.ports.clone(),
+                            })
+                        }),
+                        allow_reentry: t.allow_reentry,
+                        priority: t.priority,
+                        rate: t.rate,
+                        delay: t.delay,
+                        timeout: t.timeout.as_ref().map(|timeout| Timeout {
+                            after: timeout.after,
+                            escalate: timeout.escalate.clone(),
+                        }),
+                        condition: t.condition.clone(),
+                        description: t.description.clone(),
+                        attributes: encode_attributes(&t.attributes),
+                        label: t.label.clone(),
+                        labels: t.labels.clone(),
+                    };
+                    (label.clone(), transition)
+                })
+                .collect(),
+            arcs: net
+                .arcs
+                .iter()
+                .map(|a| Arrow {
+                    source: a.source.clone(),
+                    target: a.target.clone(),
+                    weight: a.weight,
+                    consume: a.consume,
+                    produce: a.produce,
+                    inhibit: a.inhibit,
+                    read: a.read,
+                    reset: a.reset,
+                    transfer: a.transfer.clone(),
+                    marking_weight: a.marking_weight.clone(),
+                })
+                .collect(),
+            attributes: encode_attributes(&net.attributes),
+            roles: net
+                .roles
+                .iter()
+                .map(|(name, r)| {
+                    let role = Role {
+                        description: r.description.clone(),
+                        implies: r.implies.clone(),
+                    };
+                    (name.clone(), role)
+                })
+                .collect(),
+            default_role: net.default_role.clone(),
+        }
+    }
+}
+
+impl From<PetriNet> for petri_net::PetriNet {
+    fn from(net: PetriNet) -> Self {
+        let mut petri_net = Self {
+            model_type: net.model_type,
+            version: net.version,
+            places: net
+                .places
+                .into_iter()
+                .map(|(label, p)| {
+                    let place = petri_net::Place {
+                        offset: p.offset,
+                        initial: p.initial,
+                        capacity: p.capacity.map(|c| match p.bounded {
+                            true => Capacity::Bounded(c.max(0) as u32),
+                            false => Capacity::from_legacy(c),
+                        }),
+                        x: p.x,
+                        y: p.y,
+                        unit: p.unit,
+                        description: p.description,
+                        attributes: decode_attributes(p.attributes),
+                        label: p.label,
+                        labels: p.labels,
+                    };
+                    (label, place)
+                })
+                .collect(),
+            transitions: net
+                .transitions
+                .into_iter()
+                .map(|(label, t)| {
+                    let transition = petri_net::Transition {
+                        role: t.role,
+                        x: t.x,
+                        y: t.y,
+                        subnet: t.subnet.map(|s| {
+                            Box::new(hierarchy::Subnet {
+                                net: s.net.map(Into::into).unwrap_or_default(),
+                                ports: s.ports,
+                            })
+                        }),
+                        allow_reentry: t.allow_reentry,
+                        priority: t.priority,
+                        rate: t.rate,
+                        delay: t.delay,
+                        timeout: t.timeout.map(|timeout| petri_net::Timeout {
+                            after: timeout.after,
+                            escalate: timeout.escalate,
+                        }),
+                        condition: t.condition,
+                        description: t.description,
+                        attributes: decode_attributes(t.attributes),
+                        label: t.label,
+                        labels: t.labels,
+                    };
+                    (label, transition)
+                })
+                .collect(),
+            arcs: net
+                .arcs
+                .into_iter()
+                .map(|a| petri_net::Arrow {
+                    source: a.source,
+                    target: a.target,
+                    weight: a.weight,
+                    consume: a.consume,
+                    produce: a.produce,
+                    inhibit: a.inhibit,
+                    read: a.read,
+                    reset: a.reset,
+                    transfer: a.transfer,
+                    marking_weight: a.marking_weight,
+                })
+                .collect(),
+            attributes: decode_attributes(net.attributes),
+            roles: net
+                .roles
+                .into_iter()
+                .map(|(name, r)| {
+                    let role = petri_net::Role {
+                        description: r.description,
+                        implies: r.implies,
+                    };
+                    (name, role)
+                })
+                .collect(),
+            default_role: net.default_role,
+        };
+        petri_net.populate_arc_attributes();
+        petri_net
+    }
+}
+
+impl From<&vasm::TransformError> for TransformError {
+    fn from(e: &vasm::TransformError) -> Self {
+        use transform_error::Kind;
+        let kind = match e {
+            vasm::TransformError::UnknownAction { action } => Kind::UnknownAction(transform_error::UnknownAction {
+                action: action.clone(),
+            }),
+            vasm::TransformError::EmptyModel => Kind::EmptyModel(transform_error::EmptyModel {}),
+            vasm::TransformError::DimensionMismatch { expected, actual } => {
+                Kind::DimensionMismatch(transform_error::DimensionMismatch {
+                    expected: *expected as u64,
+                    actual: *actual as u64,
+                })
+            }
+            vasm::TransformError::RoleMismatch { expected, actual } => {
+                Kind::RoleMismatch(transform_error::RoleMismatch {
+                    expected: expected.clone(),
+                    actual: actual.clone(),
+                })
+            }
+            vasm::TransformError::QuotaExceeded { role, action, limit } => {
+                Kind::QuotaExceeded(transform_error::QuotaExceeded {
+                    role: role.clone(),
+                    action: action.clone(),
+                    limit: *limit,
+                })
+            }
+        };
+        Self { kind: Some(kind) }
+    }
+}
+
+impl From<&vasm::Transaction> for Transaction {
+    fn from(tx: &vasm::Transaction) -> Self {
+        Self {
+            ok: tx.ok,
+            output: tx.output.clone(),
+            role: tx.role.clone(),
+            inhibited: tx.inhibited,
+            overflow: tx.overflow,
+            underflow: tx.underflow,
+            error: tx.error.as_ref().map(Into::into),
+            action: tx.action.clone(),
+            multiple: tx.multiple,
+            net_consumed: tx.net_consumed.clone(),
+            net_produced: tx.net_produced.clone(),
+        }
+    }
+}
+
+impl From<Transaction> for vasm::Transaction {
+    fn from(tx: Transaction) -> Self {
+        use transform_error::Kind;
+        Self {
+            ok: tx.ok,
+            output: tx.output,
+            role: tx.role,
+            inhibited: tx.inhibited,
+            overflow: tx.overflow,
+            underflow: tx.underflow,
+            error: tx.error.and_then(|e| e.kind).map(|kind| match kind {
+                Kind::UnknownAction(e) => vasm::TransformError::UnknownAction { action: e.action },
+                Kind::EmptyModel(_) => vasm::TransformError::EmptyModel,
+                Kind::DimensionMismatch(e) => vasm::TransformError::DimensionMismatch {
+                    expected: e.expected as usize,
+                    actual: e.actual as usize,
+                },
+                Kind::RoleMismatch(e) => vasm::TransformError::RoleMismatch {
+                    expected: e.expected,
+                    actual: e.actual,
+                },
+                Kind::QuotaExceeded(e) => vasm::TransformError::QuotaExceeded {
+                    role: e.role,
+                    action: e.action,
+                    limit: e.limit,
+                },
+            }),
+            action: tx.action,
+            multiple: tx.multiple,
+            net_consumed: tx.net_consumed,
+            net_produced: tx.net_produced,
+        }
+    }
+}
+
+impl petri_net::PetriNet {
+    /// Encodes the `PetriNet` as a `pflow.metamodel.v0.PetriNet` protobuf message.
+    pub fn to_protobuf(&self) -> Vec<u8> {
+        PetriNet::from(self).encode_to_vec()
+    }
+
+    /// Decodes a `pflow.metamodel.v0.PetriNet` protobuf message.
+    pub fn from_protobuf(bytes: &[u8]) -> Result<Self, prost::DecodeError> {
+        Ok(PetriNet::decode(bytes)?.into())
+    }
+}
+
+impl vasm::Transaction {
+    /// Encodes the `Transaction` as a `pflow.metamodel.v0.Transaction` protobuf message.
+    pub fn to_protobuf(&self) -> Vec<u8> {
+        Transaction::from(self).encode_to_vec()
+    }
+
+    /// Decodes a `pflow.metamodel.v0.Transaction` protobuf message.
+    pub fn from_protobuf(bytes: &[u8]) -> Result<Self, prost::DecodeError> {
+        Ok(Transaction::decode(bytes)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::vasm::{StateMachine, Vasm};
+
+    use super::*;
+
+    #[test]
+    fn test_petri_net_round_trip() {
+        let net = petri_net::PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let bytes = net.to_protobuf();
+        let back = petri_net::PetriNet::from_protobuf(&bytes).unwrap();
+        #[cfg(feature = "zblob")]
+        assert_eq!(back.to_zblob().ipfs_cid, net.to_zblob().ipfs_cid);
+        assert!(petri_net::PetriNet::from_protobuf(&[0xff]).is_err());
+    }
+
+    #[test]
+    fn test_options_round_trip() {
+        let mut net = petri_net::PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        net.set_rate("eat1", 2);
+        net.set_capacity("right2", Capacity::Bounded(0));
+        net.set_allow_reentry("eat1", true);
+        net.set_timeout("eat1", 30, Some("think1"));
+        net.set_condition("eat1", "hungry");
+        net.add_transfer_arc("right2", "eat1", "left1", 2);
+        net.add_marking_arc("eat1", "left2", "half of right1 max 3");
+        net.set_description("right2", "fork");
+        net.set_label("eat1", Some("it"), "mangiare");
+        net.set_attribute("eat1", "owner", serde_json::json!({"team": "kitchen"}));
+        net.set_attribute("", "url", serde_json::json!("https://pflow.dev"));
+        net.add_role("chef", Some("cooks"), &["default"]);
+        net.add_role("default", None, &[]);
+        net.set_default_role("chef");
+        let back = petri_net::PetriNet::from_protobuf(&net.to_protobuf()).unwrap();
+        assert_eq!(back.transitions["eat1"].rate, Some(2));
+        assert!(back.transitions["eat1"].allow_reentry);
+        assert_eq!(back.transitions["eat1"].timeout, net.transitions["eat1"].timeout);
+        assert_eq!(back.transitions["eat1"].condition.as_deref(), Some("hungry"));
+        assert_eq!(back.arcs[back.arcs.len() - 2].transfer.as_deref(), Some("left1"));
+        assert_eq!(back.arcs.last().unwrap().marking_weight.as_deref(), Some("half of right1 max 3"));
+        assert_eq!(back.places["right2"].description.as_deref(), Some("fork"));
+        assert_eq!(back.places["right2"].capacity, Some(Capacity::Bounded(0)));
+        assert_eq!(back.attributes["url"], "https://pflow.dev");
+        assert_eq!(back.roles["chef"], net.roles["chef"]);
+        assert_eq!(back.default_role(), "chef");
+        assert_eq!(back.display_label("eat1", Some("it")), Some("mangiare"));
+        #[cfg(feature = "zblob")]
+        assert_eq!(back.to_zblob().ipfs_cid, net.to_zblob().ipfs_cid);
+
+        let mut message = PetriNet::from(&net);
+        message.attributes.insert("ratio".to_string(), "1.5".to_string());
+        assert_eq!(petri_net::PetriNet::from(message).attributes["ratio"], "1.5");
+    }
+
+    #[test]
+    fn test_transaction_round_trip() {
+        let sm = StateMachine::from_model(&mut petri_net::PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap());
+        for tx in [
+            sm.transform(&sm.initial_vector(), "missing", 1),
+            sm.transform(&vec![0], "missing", 1),
+            sm.transform_as(&sm.initial_vector(), "eat1", 1, "guest"),
+            sm.fire_maximal_step(&sm.initial_vector()).transaction,
+            vasm::Transaction::rejected(
+                &sm.initial_vector(),
+                "default",
+                vasm::TransformError::QuotaExceeded {
+                    role: "default".to_string(),
+                    action: "eat1".to_string(),
+                    limit: 3,
+                },
+            ),
+        ] {
+            let back = vasm::Transaction::from_protobuf(&tx.to_protobuf()).unwrap();
+            assert_eq!(back.error, tx.error);
+            assert_eq!(back.output, tx.output);
+            assert_eq!((back.action, back.multiple), (tx.action, tx.multiple));
+            assert_eq!((back.net_consumed, back.net_produced), (tx.net_consumed, tx.net_produced));
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::vasm::TransformError;
+
+/// `QuotaWindow` is the span over which the firings counted by a `Quota` are limited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaWindow {
+    /// Counts the firings of each case separately, for its whole life.
+    PerCase,
+    /// Counts the firings of every case in a sliding window of the given duration.
+    Every(Duration),
+}
+
+/// `Quota` limits how many times a role may fire a transition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quota {
+    pub role: String,
+    pub action: String,
+    pub limit: u32,
+    pub window: QuotaWindow,
+}
+
+/// `QuotaPolicy` enforces quotas on the firings of a `Simulator` or a `CaseManager`.
+///
+/// Only successful firings count against a quota, and a firing is rejected with
+/// `TransformError::QuotaExceeded` as soon as any quota of its role and transition is used up.
+#[derive(Debug, Clone, Default)]
+pub struct QuotaPolicy {
+    quotas: Vec<Quota>,
+    per_case: BTreeMap<(String, String, String), u32>,
+    recent: BTreeMap<(String, String), VecDeque<Instant>>,
+}
+
+impl QuotaPolicy {
+    /// Creates a policy without any quota, which admits every firing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a quota of `limit` firings of the action by the role per window.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use pflow_metamodel::quota::{QuotaPolicy, QuotaWindow};
+    ///
+    /// let quotas = QuotaPolicy::new()
+    ///     .with_quota("clerk", "refund", 1, QuotaWindow::PerCase)
+    ///     .with_quota("clerk", "refund", 100, QuotaWindow::Every(Duration::from_secs(3600)));
+    /// assert_eq!(quotas.quotas().len(), 2);
+    /// ```
+    pub fn with_quota(mut self, role: &str, action: &str, limit: u32, window: QuotaWindow) -> Self {
+        self.quotas.push(Quota {
+            role: role.to_string(),
+            action: action.to_string(),
+            limit,
+            window,
+        });
+        self
+    }
+
+    /// Returns the quotas in the order they were added.
+    pub fn quotas(&self) -> &[Quota] {
+        &self.quotas
+    }
+
+    /// Checks if the policy has no quota.
+    pub fn is_empty(&self) -> bool {
+        self.quotas.is_empty()
+    }
+
+    /// Checks if the role may fire the action on the case at the given time.
+    pub fn check(&self, case: &str, role: &str, action: &str, now: Instant) -> Result<(), TransformError> {
+        let exceeded = self
+            .quotas
+            .iter()
+            .filter(|q| q.role == role && q.action == action)
+            .find(|q| self.used(q, case, now) >= q.limit);
+        match exceeded {
+            Some(q) => Err(TransformError::QuotaExceeded {
+                role: q.role.clone(),
+                action: q.action.clone(),
+                limit: q.limit,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Counts a successful firing of the action by the role on the case at the given time.
+    pub fn record(&mut self, case: &str, role: &str, action: &str, now: Instant) {
+        let matching = self.quotas.iter().filter(|q| q.role == role && q.action == action);
+        let mut longest = None;
+        let mut per_case = false;
+        for q in matching {
+            match q.window {
+                QuotaWindow::PerCase => per_case = true,
+                QuotaWindow::Every(window) => longest = longest.max(Some(window)),
+            }
+        }
+        if per_case {
+            *self
+                .per_case
+                .entry((case.to_string(), role.to_string(), action.to_string()))
+                .or_default() += 1;
+        }
+        if let Some(window) = longest {
+            let times = self.recent.entry((role.to_string(), action.to_string())).or_default();
+            while times.front().is_some_and(|t| now.duration_since(*t) >= window) {
+                times.pop_front();
+            }
+            times.push_back(now);
+        }
+    }
+
+    /// Forgets the per-case usage of a case, once it is removed or reset.
+    pub fn forget(&mut self, case: &str) {
+        self.per_case.retain(|(c, _, _), _| c != case);
+    }
+
+    fn used(&self, quota: &Quota, case: &str, now: Instant) -> u32 {
+        match quota.window {
+            QuotaWindow::PerCase => self
+                .per_case
+                .get(&(case.to_string(), quota.role.clone(), quota.action.clone()))
+                .copied()
+                .unwrap_or(0),
+            QuotaWindow::Every(window) => self
+                .recent
+                .get(&(quota.role.clone(), quota.action.clone()))
+                .map_or(0, |times| {
+                    times.iter().filter(|t| now.duration_since(**t) < window).count() as u32
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_case_quota() {
+        let mut quotas = QuotaPolicy::new().with_quota("clerk", "refund", 2, QuotaWindow::PerCase);
+        let now = Instant::now();
+        for _ in 0..2 {
+            assert!(quotas.check("a", "clerk", "refund", now).is_ok());
+            quotas.record("a", "clerk", "refund", now);
+        }
+        assert_eq!(
+            quotas.check("a", "clerk", "refund", now),
+            Err(TransformError::QuotaExceeded {
+                role: "clerk".to_string(),
+                action: "refund".to_string(),
+                limit: 2
+            })
+        );
+        assert!(quotas.check("b", "clerk", "refund", now).is_ok());
+        assert!(quotas.check("a", "clerk", "approve", now).is_ok());
+        quotas.forget("a");
+        assert!(quotas.check("a", "clerk", "refund", now).is_ok());
+    }
+
+    #[test]
+    fn test_windowed_quota() {
+        let window = Duration::from_secs(60);
+        let mut quotas = QuotaPolicy::new().with_quota("clerk", "refund", 2, QuotaWindow::Every(window));
+        let start = Instant::now();
+        quotas.record("a", "clerk", "refund", start);
+        quotas.record("b", "clerk", "refund", start + Duration::from_secs(30));
+        assert!(quotas
+            .check("c", "clerk", "refund", start + Duration::from_secs(59))
+            .is_err());
+        assert!(quotas.check("c", "clerk", "refund", start + window).is_ok());
+        assert!(quotas
+            .check("c", "clerk", "refund", start + Duration::from_secs(90))
+            .is_ok());
+    }
+}
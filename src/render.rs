@@ -0,0 +1,306 @@
+use std::fmt::Write;
+
+use crate::interchange::xml_escape;
+use crate::layout;
+use crate::petri_net::PetriNet;
+use crate::vasm::Vector;
+
+/// The radius of a place in an SVG drawing.
+const PLACE_RADIUS: i32 = 16;
+/// The half width of a transition in an SVG drawing.
+const TRANSITION_SIZE: i32 = 15;
+/// The space around the nodes of an SVG drawing, leaving room for the labels.
+const SVG_MARGIN: i32 = 40;
+
+/// Renders a plain-text depiction of the net for logs and test failure output.
+///
+/// Every place is listed with its tokens, taken from `marking` indexed by place offset or from the
+/// initial marking, its capacity and the unit of its tokens, if any. Every transition is listed with the places it consumes from
+/// and produces to, followed by its guards: `unless p>=w` for inhibitor arcs and `if p>=w` for read arcs.
+///
+/// ```text
+/// petriNet
+///   places
+///     (1)     start
+///     (0/1)   paid
+///     (2 kg)  stock
+///   transitions
+///     [pay]   start -> paid
+///     [ship]  2*paid -> end  unless end>=1
+/// ```
+pub fn to_ascii(net: &PetriNet, marking: Option<&Vector>) -> String {
+    let mut places: Vec<&String> = net.places.keys().collect();
+    places.sort_by_key(|p| net.places[*p].offset);
+    let mut transitions: Vec<&String> = net.transitions.keys().collect();
+    transitions.sort();
+
+    let tokens: Vec<String> = places
+        .iter()
+        .map(|p| {
+            let place = &net.places[*p];
+            let count = marking
+                .and_then(|m| m.get(place.offset as usize).copied())
+                .unwrap_or_else(|| place.initial.unwrap_or(0));
+            let count = match place.capacity.and_then(|c| c.limit()) {
+                Some(c) => format!("{}/{}", count, c),
+                _ => count.to_string(),
+            };
+            match &place.unit {
+                Some(unit) => format!("({} {})", count, unit),
+                None => format!("({})", count),
+            }
+        })
+        .collect();
+    let names: Vec<String> = transitions.iter().map(|t| format!("[{}]", t)).collect();
+
+    let mut out = String::new();
+    writeln!(out, "{}", net.model_type).unwrap();
+    writeln!(out, "  places").unwrap();
+    let width = tokens.iter().map(|t| t.len()).max().unwrap_or(0);
+    for (p, t) in places.iter().zip(&tokens) {
+        writeln!(out, "    {:width$}  {}", t, p, width = width).unwrap();
+    }
+    writeln!(out, "  transitions").unwrap();
+    let width = names.iter().map(|t| t.len()).max().unwrap_or(0);
+    for (t, name) in transitions.iter().zip(&names) {
+        let (mut inputs, mut outputs, mut guards) = (vec![], vec![], vec![]);
+        for arc in &net.arcs {
+            let weight = arc.weight.unwrap_or(1);
+            let term = |place: &String| if weight == 1 { place.clone() } else { format!("{}*{}", weight, place) };
+            match (arc.inhibit.unwrap_or(false), arc.source == **t, arc.target == **t) {
+                (false, false, true) => inputs.push(term(&arc.source)),
+                (false, true, false) => outputs.push(term(&arc.target)),
+                (true, false, true) => guards.push(format!("unless {}>={}", arc.source, weight)),
+                (true, true, false) => guards.push(format!("if {}>={}", arc.target, weight)),
+                _ => {}
+            }
+        }
+        let side = |terms: Vec<String>| if terms.is_empty() { ".".to_string() } else { terms.join(", ") };
+        let mut line = format!("    {:width$}  {} -> {}", name, side(inputs), side(outputs), width = width);
+        for guard in guards {
+            write!(line, "  {}", guard).unwrap();
+        }
+        writeln!(out, "{}", line).unwrap();
+    }
+    out
+}
+
+/// Draws the net as a standalone SVG image, at the positions of its nodes or with `layout::auto` if it has none.
+///
+/// Places are circles showing their tokens with their unit, taken from `marking` or the initial marking,
+/// transitions are squares,
+/// and arcs are arrows labelled with their weight when it is not one. Inhibitor arcs end in a circle.
+pub fn to_svg(net: &PetriNet, marking: Option<&Vector>) -> String {
+    let mut placed;
+    let net = if layout::is_unplaced(net) {
+        placed = net.clone();
+        layout::auto(&mut placed);
+        &placed
+    } else {
+        net
+    };
+    let position = |label: &str| {
+        net.places
+            .get(label)
+            .map(|p| (p.x, p.y))
+            .or_else(|| net.transitions.get(label).map(|t| (t.x, t.y)))
+    };
+    let points: Vec<(i32, i32)> = net
+        .places
+        .values()
+        .map(|p| (p.x, p.y))
+        .chain(net.transitions.values().map(|t| (t.x, t.y)))
+        .collect();
+    let min_x = points.iter().map(|p| p.0).min().unwrap_or(0) - SVG_MARGIN;
+    let min_y = points.iter().map(|p| p.1).min().unwrap_or(0) - SVG_MARGIN;
+    let max_x = points.iter().map(|p| p.0).max().unwrap_or(0) + SVG_MARGIN;
+    let max_y = points.iter().map(|p| p.1).max().unwrap_or(0) + SVG_MARGIN;
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" font-family="sans-serif" font-size="12">"#,
+        min_x,
+        min_y,
+        max_x - min_x,
+        max_y - min_y
+    )
+    .unwrap();
+    writeln!(
+        out,
+        concat!(
+            r#"  <defs><marker id="arrow" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="8" markerHeight="8" "#,
+            r#"orient="auto"><path d="M 0 0 L 10 5 L 0 10 z"/></marker>"#,
+            r#"<marker id="inhibit" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="8" markerHeight="8">"#,
+            r#"<circle cx="5" cy="5" r="4" fill="white" stroke="black"/></marker></defs>"#
+        )
+    )
+    .unwrap();
+
+    for arc in &net.arcs {
+        let (Some((x1, y1)), Some((x2, y2))) = (position(&arc.source), position(&arc.target)) else {
+            continue;
+        };
+        // Stop the arrow at the border of the target node.
+        let (dx, dy) = ((x2 - x1) as f64, (y2 - y1) as f64);
+        let length = dx.hypot(dy).max(1.0);
+        let inset = if net.places.contains_key(&arc.target) {
+            PLACE_RADIUS
+        } else {
+            TRANSITION_SIZE
+        } as f64;
+        let (ex, ey) = (x2 as f64 - dx * inset / length, y2 as f64 - dy * inset / length);
+        let marker = if arc.inhibit.unwrap_or(false) { "inhibit" } else { "arrow" };
+        writeln!(
+            out,
+            r#"  <line x1="{}" y1="{}" x2="{:.1}" y2="{:.1}" stroke="black" marker-end="url(#{})"/>"#,
+            x1, y1, ex, ey, marker
+        )
+        .unwrap();
+        let weight = arc.weight.unwrap_or(1);
+        if weight != 1 {
+            writeln!(
+                out,
+                r#"  <text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+                (x1 + x2) / 2,
+                (y1 + y2) / 2 - 4,
+                weight
+            )
+            .unwrap();
+        }
+    }
+
+    let mut places: Vec<(&String, _)> = net.places.iter().collect();
+    places.sort_by_key(|(_, p)| p.offset);
+    for (label, place) in places {
+        let tokens = marking
+            .and_then(|m| m.get(place.offset as usize).copied())
+            .unwrap_or_else(|| place.initial.unwrap_or(0));
+        writeln!(
+            out,
+            r#"  <circle cx="{}" cy="{}" r="{}" fill="white" stroke="black"/>"#,
+            place.x, place.y, PLACE_RADIUS
+        )
+        .unwrap();
+        if tokens != 0 {
+            let tokens = match &place.unit {
+                Some(unit) => format!("{} {}", tokens, xml_escape(unit)),
+                None => tokens.to_string(),
+            };
+            writeln!(
+                out,
+                r#"  <text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+                place.x,
+                place.y + 4,
+                tokens
+            )
+            .unwrap();
+        }
+        writeln!(
+            out,
+            r#"  <text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+            place.x,
+            place.y + PLACE_RADIUS + 14,
+            xml_escape(label)
+        )
+        .unwrap();
+    }
+    let mut transitions: Vec<&String> = net.transitions.keys().collect();
+    transitions.sort();
+    for label in transitions {
+        let transition = &net.transitions[label];
+        writeln!(
+            out,
+            r#"  <rect x="{}" y="{}" width="{}" height="{}" fill="white" stroke="black"/>"#,
+            transition.x - TRANSITION_SIZE,
+            transition.y - TRANSITION_SIZE,
+            2 * TRANSITION_SIZE,
+            2 * TRANSITION_SIZE
+        )
+        .unwrap();
+        writeln!(
+            out,
+            r#"  <text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+            transition.x,
+            transition.y + TRANSITION_SIZE + 14,
+            xml_escape(label)
+        )
+        .unwrap();
+    }
+    writeln!(out, "</svg>").unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+
+    use super::*;
+
+    fn order(p: &mut dyn FlowDsl) {
+        p.model_type("petriNet");
+        p.cell("start", Option::from(1), None, 0, 0);
+        p.cell("paid", None, Option::from(1), 0, 0);
+        p.cell("end", None, None, 0, 0);
+        p.func("pay", "default", 0, 0);
+        p.func("ship", "default", 0, 0);
+        p.func("refill", "default", 0, 0);
+        p.arrow("start", "pay", 1);
+        p.arrow("pay", "paid", 1);
+        p.arrow("paid", "ship", 2);
+        p.arrow("ship", "end", 1);
+        p.guard("end", "ship", 1);
+        p.guard("refill", "end", 1);
+    }
+
+    #[test]
+    fn test_to_ascii() {
+        let mut net = PetriNet::new();
+        net.declare(order);
+        let expected = concat!(
+            "petriNet\n",
+            "  places\n",
+            "    (1)    start\n",
+            "    (0/1)  paid\n",
+            "    (0)    end\n",
+            "  transitions\n",
+            "    [pay]     start -> paid\n",
+            "    [refill]  . -> .  if end>=1\n",
+            "    [ship]    2*paid -> end  unless end>=1\n",
+        );
+        assert_eq!(to_ascii(&net, None), expected);
+    }
+
+    #[test]
+    fn test_to_ascii_with_marking() {
+        let mut net = PetriNet::new();
+        net.declare(order);
+        let text = to_ascii(&net, Some(&vec![0, 1, 3]));
+        assert!(text.contains("    (0)    start\n    (1/1)  paid\n    (3)    end\n"));
+
+        net.set_unit("paid", "orders");
+        net.set_unit("end", "parcels");
+        let text = to_ascii(&net, Some(&vec![0, 1, 3]));
+        assert!(text.contains("    (0)           start\n    (1/1 orders)  paid\n    (3 parcels)   end\n"));
+        assert!(to_svg(&net, Some(&vec![0, 1, 3])).contains(">3 parcels</text>"));
+    }
+
+    #[test]
+    fn test_to_svg() {
+        let mut net = PetriNet::new();
+        net.declare(order);
+        let svg = to_svg(&net, Some(&vec![0, 1, 3]));
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches(r#"r="16""#).count(), 3);
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert_eq!(svg.matches("<line").count(), 6);
+        assert_eq!(svg.matches("url(#inhibit)").count(), 2);
+        assert!(svg.contains(">3</text>") && svg.contains(">2</text>"));
+        assert!(svg.contains(">refill</text>"));
+
+        net.places.get_mut("start").unwrap().x = 10;
+        let svg = to_svg(&net, None);
+        assert!(svg.contains(r#"<circle cx="10" cy="0""#));
+    }
+}
@@ -0,0 +1,429 @@
+use std::fmt::Write;
+
+use crate::analysis::boundedness::boundedness_within;
+use crate::analysis::{
+    classify, invariant_value, liveness, place_invariants, siphon_trap_property_within, soundness, statistics,
+    Boundedness, Invariant, Limits, LivenessReport, NetClass, SoundnessReport, Statistics,
+};
+use crate::capacity::Capacity;
+use crate::interchange::xml_escape;
+use crate::petri_net::PetriNet;
+use crate::vasm::StateMachine;
+
+/// `AnalysisResults` collects the outcome of the analyses summarized by a report.
+///
+/// Every field is optional so a report can be generated from the analyses that were actually run.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisResults {
+    /// The structural subclass of the net.
+    pub class: Option<NetClass>,
+    /// The size of the reachable state space.
+    pub statistics: Option<Statistics>,
+    /// Whether the number of tokens in the places is bounded.
+    pub boundedness: Option<Boundedness>,
+    /// The liveness level of every transition.
+    pub liveness: Option<LivenessReport>,
+    /// The minimal-support place invariants.
+    pub invariants: Option<Vec<Invariant>>,
+    /// Whether every siphon contains an initially marked trap, None as well if there were too many siphons to tell.
+    pub siphon_trap_property: Option<bool>,
+    /// The soundness report, or the reason the net is not a workflow net.
+    pub soundness: Option<Result<SoundnessReport, String>>,
+}
+
+impl AnalysisResults {
+    /// Runs every analysis of the report on the net, exploring at most `limits.max_states` states
+    /// and as many candidate siphons.
+    pub fn compute(net: &PetriNet, limits: Limits) -> Self {
+        let mut populated = net.clone();
+        let sm = StateMachine::from_model(&mut populated);
+        Self {
+            class: Some(classify(&populated)),
+            statistics: Some(statistics(&sm, limits)),
+            boundedness: Some(boundedness_within(&sm, limits)),
+            liveness: Some(liveness(&sm, limits)),
+            invariants: Some(place_invariants(&populated)),
+            siphon_trap_property: siphon_trap_property_within(&populated, limits),
+            soundness: Some(soundness(&populated, limits)),
+        }
+    }
+}
+
+/// `Format` is the markup a report is rendered to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Markdown,
+    /// A standalone HTML page, the diagram is rendered by Mermaid loaded from a CDN.
+    Html,
+}
+
+enum Block {
+    Heading(String),
+    Paragraph(String),
+    Table(Vec<&'static str>, Vec<Vec<String>>),
+    Diagram(String),
+}
+
+/// Generates a Markdown report of the net and its analysis results.
+pub fn generate(net: &PetriNet, results: &AnalysisResults) -> String {
+    generate_as(net, results, Format::Markdown)
+}
+
+/// Generates a report of the net and its analysis results in the given format.
+///
+/// The report holds a model summary, a Mermaid diagram of the net and a section for every analysis
+/// present in `results`.
+pub fn generate_as(net: &PetriNet, results: &AnalysisResults, format: Format) -> String {
+    let blocks = blocks(net, results);
+    match format {
+        Format::Markdown => to_markdown(&blocks),
+        Format::Html => to_html(&blocks),
+    }
+}
+
+fn blocks(net: &PetriNet, results: &AnalysisResults) -> Vec<Block> {
+    let mut places: Vec<&String> = net.places.keys().collect();
+    places.sort_by_key(|p| net.places[*p].offset);
+    let mut transitions: Vec<&String> = net.transitions.keys().collect();
+    transitions.sort();
+    let mut roles: Vec<&str> = net.transitions.values().filter_map(|t| t.role.as_deref()).collect();
+    roles.sort();
+    roles.dedup();
+
+    let mut summary = vec![
+        vec!["Model type".to_string(), net.model_type.clone()],
+        vec!["Places".to_string(), places.len().to_string()],
+        vec!["Transitions".to_string(), transitions.len().to_string()],
+        vec!["Arcs".to_string(), net.arcs.len().to_string()],
+        vec!["Roles".to_string(), roles.join(", ")],
+    ];
+    if let Some(class) = results.class {
+        summary.push(vec!["Class".to_string(), format!("{:?}", class)]);
+    }
+
+    let mut blocks = vec![
+        Block::Heading("Model summary".to_string()),
+        Block::Table(vec!["Property", "Value"], summary),
+        places_table(net, &places),
+        Block::Heading("Diagram".to_string()),
+        Block::Diagram(mermaid(net, &places, &transitions)),
+    ];
+
+    if let Some(invariants) = &results.invariants {
+        blocks.push(Block::Heading("Place invariants".to_string()));
+        if invariants.is_empty() {
+            blocks.push(Block::Paragraph("The net has no place invariants.".to_string()));
+        } else {
+            blocks.push(Block::Table(
+                vec!["Invariant", "Value"],
+                invariants
+                    .iter()
+                    .map(|i| {
+                        let terms: Vec<String> = i
+                            .iter()
+                            .map(|(p, w)| if *w == 1 { p.clone() } else { format!("{}·{}", w, p) })
+                            .collect();
+                        vec![terms.join(" + "), invariant_value(net, i).to_string()]
+                    })
+                    .collect(),
+            ));
+        }
+    }
+
+    if let Some(b) = &results.boundedness {
+        blocks.push(Block::Heading("Boundedness".to_string()));
+        blocks.push(Block::Paragraph(match b {
+            Boundedness::Bounded { k } => format!("The net is {}-bounded{}.", k, if *k <= 1 { " (safe)" } else { "" }),
+            Boundedness::Unbounded { place, prefix, pump } => format!(
+                "The net is unbounded: firing {} after {} increases `{}` forever.",
+                sequence(pump),
+                sequence(prefix),
+                place
+            ),
+            Boundedness::Unknown { k } => {
+                format!("Unknown: the limits were reached with up to {} tokens in a place.", k)
+            }
+        }));
+    }
+
+    if let Some(l) = &results.liveness {
+        blocks.push(Block::Heading("Liveness".to_string()));
+        blocks.push(Block::Paragraph(format!(
+            "The net is {}live{}.",
+            if l.is_live() { "" } else { "not " },
+            incomplete(l.complete)
+        )));
+        blocks.push(Block::Table(
+            vec!["Transition", "Level"],
+            l.levels
+                .iter()
+                .map(|(t, level)| vec![t.clone(), format!("{:?}", level)])
+                .collect(),
+        ));
+    }
+
+    if let Some(property) = results.siphon_trap_property {
+        blocks.push(Block::Heading("Siphons and traps".to_string()));
+        blocks.push(Block::Paragraph(if property {
+            "Every siphon contains an initially marked trap.".to_string()
+        } else {
+            "Some siphon contains no initially marked trap.".to_string()
+        }));
+    }
+
+    if let Some(s) = &results.soundness {
+        blocks.push(Block::Heading("Soundness".to_string()));
+        match s {
+            Ok(report) => {
+                blocks.push(Block::Paragraph(format!(
+                    "The workflow net from `{}` to `{}` is {}sound{}.",
+                    report.shape.source,
+                    report.shape.sink,
+                    if report.is_sound() { "" } else { "not " },
+                    incomplete(report.complete)
+                )));
+                blocks.push(Block::Table(
+                    vec!["Condition", "Holds"],
+                    vec![
+                        vec!["Option to complete".to_string(), yes_no(report.option_to_complete)],
+                        vec!["Proper completion".to_string(), yes_no(report.proper_completion)],
+                        vec![
+                            "No dead transitions".to_string(),
+                            yes_no(report.dead_transitions.is_empty()),
+                        ],
+                    ],
+                ));
+            }
+            Err(reason) => blocks.push(Block::Paragraph(format!("Not a workflow net: {}.", reason))),
+        }
+    }
+
+    if let Some(s) = &results.statistics {
+        blocks.push(Block::Heading("State space".to_string()));
+        blocks.push(Block::Table(
+            vec!["Property", "Value"],
+            vec![
+                vec![
+                    "States".to_string(),
+                    format!("{}{}", s.states, if s.complete { "" } else { "+" }),
+                ],
+                vec!["Arcs".to_string(), s.arcs.to_string()],
+                vec!["Dead states".to_string(), s.dead_states.to_string()],
+            ],
+        ));
+    }
+    blocks
+}
+
+/// Lists the initial tokens and capacity of the places, with the unit of their tokens if any place declares one.
+fn places_table(net: &PetriNet, places: &[&String]) -> Block {
+    let units = places.iter().any(|p| net.places[*p].unit.is_some());
+    let mut header = vec!["Place", "Initial", "Capacity"];
+    if units {
+        header.push("Unit");
+    }
+    let rows = places
+        .iter()
+        .map(|p| {
+            let place = &net.places[*p];
+            let mut row = vec![
+                (*p).clone(),
+                place.initial.unwrap_or(0).to_string(),
+                capacity(place.capacity),
+            ];
+            if units {
+                row.push(place.unit.clone().unwrap_or_default());
+            }
+            row
+        })
+        .collect();
+    Block::Table(header, rows)
+}
+
+fn capacity(capacity: Option<Capacity>) -> String {
+    match capacity.and_then(|c| c.limit()) {
+        Some(c) => c.to_string(),
+        None => "∞".to_string(),
+    }
+}
+
+fn sequence(actions: &[String]) -> String {
+    if actions.is_empty() {
+        "nothing".to_string()
+    } else {
+        format!("`{}`", actions.join(" "))
+    }
+}
+
+fn incomplete(complete: bool) -> &'static str {
+    if complete {
+        ""
+    } else {
+        " (the state space was truncated, the result is not conclusive)"
+    }
+}
+
+fn yes_no(b: bool) -> String {
+    if b { "yes" } else { "no" }.to_string()
+}
+
+fn mermaid(net: &PetriNet, places: &[&String], transitions: &[&String]) -> String {
+    let id = |label: &String| -> String {
+        match places.iter().position(|p| *p == label) {
+            Some(i) => format!("p{}", i),
+            None => format!("t{}", transitions.iter().position(|t| *t == label).unwrap_or(0)),
+        }
+    };
+    let mut out = String::from("flowchart LR\n");
+    for (i, p) in places.iter().enumerate() {
+        let tokens = net.places[*p].initial.unwrap_or(0);
+        let label = if tokens > 0 {
+            format!("{} ({})", p, tokens)
+        } else {
+            (*p).clone()
+        };
+        writeln!(out, "    p{}((\"{}\"))", i, label.replace('"', "#quot;")).unwrap();
+    }
+    for (i, t) in transitions.iter().enumerate() {
+        writeln!(out, "    t{}[\"{}\"]", i, t.replace('"', "#quot;")).unwrap();
+    }
+    for arc in &net.arcs {
+        let weight = arc.weight.unwrap_or(1);
+        let link = match (arc.inhibit.unwrap_or(false), weight) {
+            (true, _) => "--o".to_string(),
+            (false, 1) => "-->".to_string(),
+            (false, w) => format!("-->|{}|", w),
+        };
+        writeln!(out, "    {} {} {}", id(&arc.source), link, id(&arc.target)).unwrap();
+    }
+    out
+}
+
+fn to_markdown(blocks: &[Block]) -> String {
+    let mut out = String::from("# Model report\n");
+    for block in blocks {
+        out.push('\n');
+        match block {
+            Block::Heading(text) => writeln!(out, "## {}", text).unwrap(),
+            Block::Paragraph(text) => writeln!(out, "{}", text).unwrap(),
+            Block::Table(header, rows) => {
+                writeln!(out, "| {} |", header.join(" | ")).unwrap();
+                writeln!(out, "|{}", " --- |".repeat(header.len())).unwrap();
+                for row in rows {
+                    let cells: Vec<String> = row.iter().map(|c| c.replace('|', "\\|")).collect();
+                    writeln!(out, "| {} |", cells.join(" | ")).unwrap();
+                }
+            }
+            Block::Diagram(source) => write!(out, "```mermaid\n{}```\n", source).unwrap(),
+        }
+    }
+    out
+}
+
+fn to_html(blocks: &[Block]) -> String {
+    let inline = |text: &str| -> String {
+        // Backticks mark code spans in paragraphs, as they do in Markdown.
+        xml_escape(text)
+            .split('`')
+            .enumerate()
+            .map(|(i, part)| {
+                if i % 2 == 1 {
+                    format!("<code>{}</code>", part)
+                } else {
+                    part.to_string()
+                }
+            })
+            .collect()
+    };
+    let mut out = String::from(concat!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Model report</title>\n",
+        "<script type=\"module\">import mermaid from ",
+        "'https://cdn.jsdelivr.net/npm/mermaid@10/dist/mermaid.esm.min.mjs'; mermaid.initialize({ startOnLoad: true });</script>\n",
+        "</head>\n<body>\n<h1>Model report</h1>\n"
+    ));
+    for block in blocks {
+        match block {
+            Block::Heading(text) => writeln!(out, "<h2>{}</h2>", xml_escape(text)).unwrap(),
+            Block::Paragraph(text) => writeln!(out, "<p>{}</p>", inline(text)).unwrap(),
+            Block::Table(header, rows) => {
+                out.push_str("<table>\n<tr>");
+                for h in header {
+                    write!(out, "<th>{}</th>", xml_escape(h)).unwrap();
+                }
+                out.push_str("</tr>\n");
+                for row in rows {
+                    out.push_str("<tr>");
+                    for c in row {
+                        write!(out, "<td>{}</td>", xml_escape(c)).unwrap();
+                    }
+                    out.push_str("</tr>\n");
+                }
+                out.push_str("</table>\n");
+            }
+            Block::Diagram(source) => writeln!(out, "<pre class=\"mermaid\">\n{}</pre>", xml_escape(source)).unwrap(),
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    fn order(p: &mut dyn crate::dsl::FlowDsl) {
+        p.model_type("workflow");
+        p.cell("start", Option::from(1), None, 0, 0);
+        p.cell("paid", None, None, 0, 0);
+        p.cell("end", None, None, 0, 0);
+        p.func("pay", "customer", 0, 0);
+        p.func("ship", "clerk", 0, 0);
+        p.arrow("start", "pay", 1);
+        p.arrow("pay", "paid", 1);
+        p.arrow("paid", "ship", 1);
+        p.arrow("ship", "end", 1);
+    }
+
+    #[test]
+    fn test_markdown_report() {
+        let mut net = PetriNet::new();
+        net.declare(order);
+        let report = generate(&net, &AnalysisResults::compute(&net, Limits::default()));
+        assert!(report.starts_with("# Model report\n"));
+        assert!(report.contains("| Roles | clerk, customer |"));
+        assert!(report.contains("```mermaid\nflowchart LR\n    p0((\"start (1)\"))\n"));
+        assert!(report.contains("    p0 --> t0\n"));
+        assert!(report.contains("| end + paid + start | 1 |"));
+        assert!(report.contains("The net is 1-bounded (safe)."));
+        assert!(report.contains("The workflow net from `start` to `end` is sound."));
+        assert!(report.contains("| States | 3 |"));
+        assert!(report.contains("Some siphon contains no initially marked trap."));
+        assert!(!report.contains("| Unit |"));
+        assert_eq!(report, generate(&net, &AnalysisResults::compute(&net, Limits::default())));
+
+        net.set_unit("paid", "€ cents");
+        let report = generate(&net, &AnalysisResults::default());
+        assert!(report.contains("| Place | Initial | Capacity | Unit |"));
+        assert!(report.contains("| start | 1 | ∞ |  |"));
+        assert!(report.contains("| paid | 0 | ∞ | € cents |"));
+    }
+
+    #[test]
+    fn test_partial_html_report() {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let sm = StateMachine::from_model(&mut net);
+        let results = AnalysisResults {
+            liveness: Some(liveness(&sm, Limits::default())),
+            ..Default::default()
+        };
+        let report = generate_as(&net, &results, Format::Html);
+        assert!(report.starts_with("<!DOCTYPE html>"));
+        assert!(report.contains("<pre class=\"mermaid\">\nflowchart LR\n"));
+        assert!(report.contains("<p>The net is live.</p>"));
+        assert!(!report.contains("Boundedness"));
+        assert!(!report.contains("Soundness"));
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::petri_net::{PetriNet, Role, DEFAULT_ROLE};
+
+/// `RoleRegistry` is the `roles` section of a net as carried by a `StateMachine`, with its default role.
+///
+/// A role may fire the transitions of the roles it implies, directly or through other roles.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleRegistry {
+    roles: BTreeMap<String, Role>,
+    default_role: String,
+}
+
+impl Default for RoleRegistry {
+    fn default() -> Self {
+        Self {
+            roles: BTreeMap::new(),
+            default_role: DEFAULT_ROLE.to_string(),
+        }
+    }
+}
+
+impl RoleRegistry {
+    /// Collects the roles declared by the net.
+    pub fn from_net(net: &PetriNet) -> Self {
+        Self {
+            roles: net
+                .roles
+                .iter()
+                .map(|(name, role)| (name.clone(), role.clone()))
+                .collect(),
+            default_role: net.default_role().to_string(),
+        }
+    }
+
+    /// Returns the role of the transitions declared without one.
+    pub fn default_role(&self) -> &str {
+        &self.default_role
+    }
+
+    /// Returns the declared roles by name.
+    pub fn roles(&self) -> &BTreeMap<String, Role> {
+        &self.roles
+    }
+
+    /// Returns the declaration of the role, if any.
+    pub fn get(&self, name: &str) -> Option<&Role> {
+        self.roles.get(name)
+    }
+
+    /// Checks if no role is declared.
+    pub fn is_empty(&self) -> bool {
+        self.roles.is_empty()
+    }
+
+    /// Returns the role and every role it implies, directly or not.
+    pub fn implied(&self, name: &str) -> BTreeSet<String> {
+        let mut implied = BTreeSet::from([name.to_string()]);
+        let mut pending = vec![name];
+        while let Some(role) = pending.pop() {
+            for next in self.roles.get(role).map_or(&[][..], |r| &r.implies) {
+                if implied.insert(next.clone()) {
+                    pending.push(next);
+                }
+            }
+        }
+        implied
+    }
+
+    /// Checks if `actor` may fire the transitions of `role`, because it is that role or implies it.
+    pub fn may_fire(&self, actor: &str, role: &str) -> bool {
+        actor == role || self.implied(actor).contains(role)
+    }
+
+    /// Writes the registry back to the `roles` section and default role of the net.
+    pub(crate) fn write_to(&self, net: &mut PetriNet) {
+        net.roles = self
+            .roles
+            .iter()
+            .map(|(name, role)| (name.clone(), role.clone()))
+            .collect();
+        net.default_role = (self.default_role != DEFAULT_ROLE).then(|| self.default_role.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bank() -> PetriNet {
+        let mut net = PetriNet::new();
+        net.add_role("teller", Some("serves customers"), &[]);
+        net.add_role("auditor", None, &[]);
+        net.add_role("manager", None, &["teller"]);
+        net.add_role("director", None, &["manager", "auditor"]);
+        net.set_default_role("teller");
+        net
+    }
+
+    #[test]
+    fn test_implied_roles() {
+        let registry = RoleRegistry::from_net(&bank());
+        assert_eq!(registry.default_role(), "teller");
+        assert_eq!(
+            registry.get("teller").unwrap().description.as_deref(),
+            Some("serves customers")
+        );
+        assert_eq!(
+            registry.implied("director").into_iter().collect::<Vec<_>>(),
+            vec!["auditor", "director", "manager", "teller"]
+        );
+        assert!(registry.may_fire("manager", "teller"));
+        assert!(!registry.may_fire("teller", "manager"));
+        assert!(!registry.may_fire("manager", "auditor"));
+        assert!(registry.may_fire("guest", "guest"));
+    }
+
+    #[test]
+    fn test_cyclic_implications_terminate() {
+        let mut net = PetriNet::new();
+        net.add_role("a", None, &["b"]);
+        net.add_role("b", None, &["a"]);
+        let registry = RoleRegistry::from_net(&net);
+        assert_eq!(registry.implied("a").len(), 2);
+        assert_eq!(registry.default_role(), DEFAULT_ROLE);
+    }
+}
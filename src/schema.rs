@@ -0,0 +1,566 @@
+use std::cell::RefCell;
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde_json::{json, Map, Number, Value};
+
+use crate::petri_net::PetriNet;
+
+/// The version written by this crate and expected by `PetriNet::from_json`.
+pub const CURRENT_VERSION: &str = "v0";
+
+/// `SchemaError` is returned when a document cannot be migrated to the current version.
+#[derive(Debug)]
+pub enum SchemaError {
+    /// The document is not valid JSON or does not match the current structure after migration.
+    Json(serde_json::Error),
+    /// The document is not a JSON object.
+    NotAnObject,
+    /// The document declares a version this crate does not know.
+    UnsupportedVersion(String),
+    /// The document does not match `json_schema`, see `read_petri_net_from_string_strict`.
+    Invalid(Vec<Violation>),
+}
+
+/// `Violation` is a part of a document that does not match the schema, located by its JSON Pointer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The JSON Pointer (RFC 6901) of the offending value or key, such as `/arcs/3/weight`, empty for the root.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Json(e) => write!(f, "invalid model: {}", e),
+            SchemaError::NotAnObject => write!(f, "model must be a JSON object"),
+            SchemaError::UnsupportedVersion(v) => write!(f, "unsupported model version {}", v),
+            SchemaError::Invalid(violations) => {
+                let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                write!(f, "invalid model: {}", violations.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<serde_json::Error> for SchemaError {
+    fn from(e: serde_json::Error) -> Self {
+        SchemaError::Json(e)
+    }
+}
+
+/// Returns the JSON Schema (draft 2020-12) of the `PetriNet` file format.
+pub fn json_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": "https://pflow.dev/schema/petri-net.json",
+        "title": "PetriNet",
+        "type": "object",
+        "required": ["modelType", "version", "places", "transitions", "arcs"],
+        "properties": {
+            "modelType": { "enum": ["petriNet", "elementary", "workflow"] },
+            "version": { "const": CURRENT_VERSION },
+            "places": {
+                "type": "object",
+                "additionalProperties": {
+                    "type": "object",
+                    "required": ["offset", "x", "y"],
+                    "properties": {
+                        "offset": { "type": "integer", "minimum": 0 },
+                        "initial": { "type": ["integer", "null"], "minimum": 0 },
+                        "capacity": {
+                            "oneOf": [
+                                { "type": ["integer", "null"], "minimum": 0 },
+                                { "const": "unbounded" },
+                                {
+                                    "type": "object",
+                                    "required": ["bounded"],
+                                    "properties": { "bounded": { "type": "integer", "minimum": 0 } }
+                                }
+                            ]
+                        },
+                        "x": { "type": "integer" },
+                        "y": { "type": "integer" },
+                        "unit": { "type": "string" },
+                        "label": { "type": "string" },
+                        "labels": { "type": "object", "additionalProperties": { "type": "string" } },
+                        "description": { "type": "string" },
+                        "attributes": { "type": "object" }
+                    }
+                }
+            },
+            "transitions": {
+                "type": "object",
+                "additionalProperties": {
+                    "type": "object",
+                    "required": ["x", "y"],
+                    "properties": {
+                        "role": { "type": ["string", "null"] },
+                        "x": { "type": "integer" },
+                        "y": { "type": "integer" },
+                        "subnet": {
+                            "type": "object",
+                            "required": ["net", "ports"],
+                            "properties": {
+                                "net": { "$ref": "#" },
+                                "ports": { "type": "object", "additionalProperties": { "type": "string" } }
+                            }
+                        },
+                        "allowReentry": { "type": "boolean" },
+                        "priority": { "type": "integer" },
+                        "rate": { "type": "integer", "minimum": 0 },
+                        "delay": { "type": "integer", "minimum": 0 },
+                        "timeout": {
+                            "type": "object",
+                            "required": ["after"],
+                            "properties": {
+                                "after": { "type": "integer", "minimum": 0 },
+                                "escalate": { "type": "string" }
+                            }
+                        },
+                        "condition": { "type": "string" },
+                        "label": { "type": "string" },
+                        "labels": { "type": "object", "additionalProperties": { "type": "string" } },
+                        "description": { "type": "string" },
+                        "attributes": { "type": "object" }
+                    }
+                }
+            },
+            "attributes": { "type": "object" },
+            "roles": {
+                "type": "object",
+                "additionalProperties": {
+                    "type": "object",
+                    "properties": {
+                        "description": { "type": "string" },
+                        "implies": { "type": "array", "items": { "type": "string" } }
+                    }
+                }
+            },
+            "defaultRole": { "type": "string" },
+            "arcs": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["source", "target"],
+                    "properties": {
+                        "source": { "type": "string" },
+                        "target": { "type": "string" },
+                        "weight": { "type": ["integer", "null"], "exclusiveMinimum": 0 },
+                        "consume": { "type": ["boolean", "null"] },
+                        "produce": { "type": ["boolean", "null"] },
+                        "inhibit": { "type": ["boolean", "null"] },
+                        "read": { "type": ["boolean", "null"] },
+                        "reset": { "type": "boolean" },
+                        "transfer": { "type": "string" },
+                        "markingWeight": { "type": "string" }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Upgrades a document without a version, as written by early editors, to `v0`.
+///
+/// Missing collections and coordinates default to empty and zero, the model type to `petriNet`,
+/// and places without an offset are numbered after the others in label order.
+fn unversioned_to_v0(doc: &mut Map<String, Value>) {
+    doc.entry("modelType").or_insert(json!("petriNet"));
+    for key in ["places", "transitions"] {
+        if !doc.get(key).is_some_and(Value::is_object) {
+            doc.insert(key.to_string(), json!({}));
+        }
+    }
+    if !doc.get("arcs").is_some_and(Value::is_array) {
+        doc.insert("arcs".to_string(), json!([]));
+    }
+
+    let places = doc["places"].as_object_mut().unwrap();
+    let mut next = places.values().filter_map(|p| p.get("offset")?.as_i64()).max().map_or(0, |o| o + 1);
+    let mut labels: Vec<String> = places.keys().cloned().collect();
+    labels.sort();
+    for label in labels {
+        let place = places.get_mut(&label).unwrap();
+        if !place.is_object() {
+            *place = json!({});
+        }
+        let place = place.as_object_mut().unwrap();
+        if !place.contains_key("offset") {
+            place.insert("offset".to_string(), json!(next));
+            next += 1;
+        }
+        place.entry("x").or_insert(json!(0));
+        place.entry("y").or_insert(json!(0));
+    }
+    for transition in doc["transitions"].as_object_mut().unwrap().values_mut() {
+        if !transition.is_object() {
+            *transition = json!({});
+        }
+        let transition = transition.as_object_mut().unwrap();
+        transition.entry("role").or_insert(json!("default"));
+        transition.entry("x").or_insert(json!(0));
+        transition.entry("y").or_insert(json!(0));
+    }
+}
+
+/// Upgrades a document to `CURRENT_VERSION`, applying each migration from its declared version in turn.
+pub fn migrate(mut doc: Value) -> Result<Value, SchemaError> {
+    let map = doc.as_object_mut().ok_or(SchemaError::NotAnObject)?;
+    loop {
+        match map.get("version").and_then(Value::as_str) {
+            None => {
+                unversioned_to_v0(map);
+                map.insert("version".to_string(), json!("v0"));
+            }
+            Some(CURRENT_VERSION) => return Ok(doc),
+            Some(v) => return Err(SchemaError::UnsupportedVersion(v.to_string())),
+        }
+    }
+}
+
+/// Loads a `PetriNet` from a JSON document of any known version.
+pub fn load(contents: &str) -> Result<PetriNet, SchemaError> {
+    let doc = migrate(serde_json::from_str(contents)?)?;
+    let mut net: PetriNet = serde_json::from_value(doc)?;
+    net.populate_arc_attributes();
+    Ok(net)
+}
+
+/// Reads a `PetriNet` from a JSON document of the current version, rejecting anything `json_schema` does not allow.
+///
+/// Unlike `load` and `PetriNet::from_json` nothing is migrated, defaulted or ignored: unknown fields, duplicate keys,
+/// numbers that are not integers and values out of range are all reported as a `Violation` with its JSON Pointer.
+/// Integers must also fit in 32 bits. Syntax errors are reported as `SchemaError::Json`.
+///
+/// # Example
+///
+/// ```
+/// use pflow_metamodel::schema::{read_petri_net_from_string_strict, SchemaError};
+///
+/// let doc = r#"{"modelType": "petriNet", "version": "v0", "places": {}, "transitions": {},
+///     "arcs": [{"source": "a", "target": "b", "weight": 1.5, "colour": "red"}]}"#;
+/// match read_petri_net_from_string_strict(doc) {
+///     Err(SchemaError::Invalid(violations)) => {
+///         assert_eq!(violations[0].path, "/arcs/0/colour");
+///         assert_eq!(violations[1].path, "/arcs/0/weight");
+///     }
+///     other => panic!("unexpected {:?}", other),
+/// }
+/// ```
+pub fn read_petri_net_from_string_strict(contents: &str) -> Result<PetriNet, SchemaError> {
+    let duplicates = RefCell::new(Vec::new());
+    let mut deserializer = serde_json::Deserializer::from_str(contents);
+    let doc = StrictValue {
+        path: String::new(),
+        duplicates: &duplicates,
+    }
+    .deserialize(&mut deserializer)?;
+    deserializer.end()?;
+
+    let mut violations = duplicates.into_inner();
+    let schema = json_schema();
+    validate(&schema, &schema, &doc, "", &mut violations);
+    if !violations.is_empty() {
+        violations.sort_by(|a, b| a.path.cmp(&b.path));
+        return Err(SchemaError::Invalid(violations));
+    }
+    let mut net: PetriNet = serde_json::from_value(doc)?;
+    net.populate_arc_attributes();
+    Ok(net)
+}
+
+/// Appends a key or index to a JSON Pointer, escaping `~` and `/`.
+fn pointer(path: &str, key: &str) -> String {
+    format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// `StrictValue` deserializes a `Value` like serde_json, but records the keys repeated in an object,
+/// which serde_json silently overwrites.
+struct StrictValue<'a> {
+    path: String,
+    duplicates: &'a RefCell<Vec<Violation>>,
+}
+
+impl<'de> DeserializeSeed<'de> for StrictValue<'_> {
+    type Value = Value;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for StrictValue<'_> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Number::from_f64(v).map_or(Value::Null, Value::Number))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element_seed(StrictValue {
+            path: pointer(&self.path, &items.len().to_string()),
+            duplicates: self.duplicates,
+        })? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut object = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let path = pointer(&self.path, &key);
+            let value = map.next_value_seed(StrictValue {
+                path: path.clone(),
+                duplicates: self.duplicates,
+            })?;
+            if object.insert(key, value).is_some() {
+                self.duplicates.borrow_mut().push(Violation {
+                    path,
+                    message: "duplicate key".to_string(),
+                });
+            }
+        }
+        Ok(Value::Object(object))
+    }
+}
+
+/// Checks the value against the subset of JSON Schema used by `json_schema`, where objects declaring
+/// `properties` admit no other keys.
+fn validate(root: &Value, schema: &Value, value: &Value, path: &str, violations: &mut Vec<Violation>) {
+    let mut violation = |message: String| {
+        violations.push(Violation {
+            path: path.to_string(),
+            message,
+        })
+    };
+    if schema.get("$ref").and_then(Value::as_str) == Some("#") {
+        return validate(root, root, value, path, violations);
+    }
+    if let Some(choices) = schema.get("oneOf").and_then(Value::as_array) {
+        let matching = choices
+            .iter()
+            .filter(|choice| {
+                let mut nested = Vec::new();
+                validate(root, choice, value, path, &mut nested);
+                nested.is_empty()
+            })
+            .count();
+        if matching != 1 {
+            violation(format!("{} does not match exactly one of the allowed forms", value));
+        }
+        return;
+    }
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            violation(format!("expected {}, got {}", expected, value));
+        }
+        return;
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            violation(format!("expected one of {}, got {}", Value::Array(allowed.clone()), value));
+        }
+        return;
+    }
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => types.as_str().into_iter().collect(),
+        };
+        if !types.iter().any(|t| has_type(value, t)) {
+            violation(format!("expected {}, got {}", types.join(" or "), value));
+            return;
+        }
+    }
+    if let Some(n) = value.as_i64().or_else(|| value.as_u64().map(|_| i64::MAX)) {
+        if i32::try_from(n).is_err() {
+            violation(format!("{} is out of range", value));
+        } else if schema.get("minimum").and_then(Value::as_i64).is_some_and(|min| n < min) {
+            violation(format!("{} is less than {}", n, schema["minimum"]));
+        } else if schema.get("exclusiveMinimum").and_then(Value::as_i64).is_some_and(|min| n <= min) {
+            violation(format!("{} must be greater than {}", n, schema["exclusiveMinimum"]));
+        }
+    }
+    if let Some(items) = value.as_array() {
+        if let Some(item_schema) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                validate(root, item_schema, item, &pointer(path, &i.to_string()), violations);
+            }
+        }
+        return;
+    }
+    let Some(object) = value.as_object() else {
+        return;
+    };
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for key in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(key) {
+                violation(format!("missing required field {}", key));
+            }
+        }
+    }
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let additional = schema.get("additionalProperties");
+    for (key, item) in object {
+        let item_path = pointer(path, key);
+        match (properties.and_then(|p| p.get(key)), additional) {
+            (Some(item_schema), _) | (None, Some(item_schema)) => {
+                validate(root, item_schema, item, &item_path, violations)
+            }
+            (None, None) if properties.is_some() => violations.push(Violation {
+                path: item_path,
+                message: "unknown field".to_string(),
+            }),
+            (None, None) => {}
+        }
+    }
+}
+
+fn has_type(value: &Value, t: &str) -> bool {
+    match t {
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    #[test]
+    fn test_current_documents_are_unchanged() {
+        let doc: Value = serde_json::from_str(DINING_PHILOSOPHERS).unwrap();
+        assert_eq!(migrate(doc.clone()).unwrap(), doc);
+        assert_eq!(load(DINING_PHILOSOPHERS).unwrap().places.len(), 15);
+    }
+
+    #[test]
+    fn test_unversioned_document_is_upgraded() {
+        let net = load(r#"{
+            "places": { "b": { "initial": 1 }, "a": { "offset": 0 } },
+            "transitions": { "t": {} },
+            "arcs": [{ "source": "a", "target": "t" }]
+        }"#)
+        .unwrap();
+        assert_eq!(net.version, "v0");
+        assert_eq!(net.model_type, "petriNet");
+        assert_eq!(net.places["b"].offset, 1);
+        assert_eq!(net.transitions["t"].role.as_deref(), Some("default"));
+        assert_eq!(net.arcs[0].consume, Some(true));
+    }
+
+    #[test]
+    fn test_rejects_unknown_versions() {
+        assert!(matches!(load(r#"{ "version": "v9" }"#), Err(SchemaError::UnsupportedVersion(v)) if v == "v9"));
+        assert!(matches!(load("[]"), Err(SchemaError::NotAnObject)));
+    }
+
+    #[test]
+    fn test_schema_describes_every_field() {
+        let schema = json_schema();
+        let net: Value = serde_json::from_str(DINING_PHILOSOPHERS).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+        assert!(net.as_object().unwrap().keys().all(|k| properties.contains_key(k)));
+        assert_eq!(schema["properties"]["version"]["const"], CURRENT_VERSION);
+    }
+
+    #[test]
+    fn test_strict_accepts_current_documents() {
+        let net = read_petri_net_from_string_strict(DINING_PHILOSOPHERS).unwrap();
+        assert_eq!(net.places.len(), 15);
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("a", Option::from(1), Option::from(2), 0, 0);
+            p.func("t", "user", 0, 0);
+            p.arrow("a", "t", 1);
+            p.timeout("t", 5, None);
+        });
+        assert!(read_petri_net_from_string_strict(&net.to_json().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_strict_reports_violations() {
+        let doc = r#"{
+            "modelType": "petriNet",
+            "version": "v0",
+            "places": {
+                "a/b": { "offset": 0, "x": 0, "y": 0, "initial": -1 },
+                "c": { "offset": 1, "x": 4294967296, "y": 0, "capacity": "lots" }
+            },
+            "transitions": { "t": { "x": 0, "y": 0, "x": 1, "rate": 2.0 } },
+            "arcs": [{ "source": "a/b", "target": "t", "weight": 0 }, { "target": "t" }],
+            "extra": true
+        }"#;
+        let Err(SchemaError::Invalid(violations)) = read_petri_net_from_string_strict(doc) else {
+            panic!("expected violations");
+        };
+        let found: Vec<(&str, &str)> = violations.iter().map(|v| (v.path.as_str(), v.message.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("/arcs/0/weight", "0 must be greater than 0"),
+                ("/arcs/1", "missing required field source"),
+                ("/extra", "unknown field"),
+                ("/places/a~1b/initial", "-1 is less than 0"),
+                ("/places/c/capacity", "\"lots\" does not match exactly one of the allowed forms"),
+                ("/places/c/x", "4294967296 is out of range"),
+                ("/transitions/t/rate", "expected integer, got 2.0"),
+                ("/transitions/t/x", "duplicate key"),
+            ]
+        );
+        assert!(matches!(read_petri_net_from_string_strict("{"), Err(SchemaError::Json(_))));
+        assert!(read_petri_net_from_string_strict(r#"{ "places": {} }"#)
+            .unwrap_err()
+            .to_string()
+            .contains("/: missing required field arcs"));
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+
+use crate::vasm::{StateMachine, Transaction, Transition, Vector};
+
+/// `FiringSemantics` fires the transitions of a `StateMachine` in place of the semantics of its model type.
+///
+/// Attach an implementation with `StateMachine::set_firing_semantics` to give domain-specific variants, such as
+/// capacity shared between places or tokens that age, to every transformation, enabledness check and analysis
+/// of the state machine. `StateMachine::to_machine` cannot carry it and keeps the semantics of the model type.
+///
+/// The state machine rejects unknown actions, empty models and states of the wrong dimension before calling
+/// `fire`. Implementations may delegate to the built-in firing rules, such as `StateMachine::petri_net_fire`,
+/// but must not call `Vasm::transform`, which would call them back.
+pub trait FiringSemantics: fmt::Debug + Send + Sync {
+    /// Fires the transition `multiple` times in the state.
+    fn fire(&self, sm: &StateMachine, state: &Vector, transition: &Transition, multiple: i32) -> Transaction;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl::FlowDsl;
+    use crate::vasm::Vasm;
+
+    /// Lets the places share a capacity, so that a firing may not raise the total above it.
+    #[derive(Debug)]
+    struct SharedCapacity(i32);
+
+    impl FiringSemantics for SharedCapacity {
+        fn fire(&self, sm: &StateMachine, state: &Vector, transition: &Transition, multiple: i32) -> Transaction {
+            let mut tx = sm.petri_net_fire(state, transition, multiple);
+            if tx.is_ok() && tx.output.iter().sum::<i32>() > self.0 {
+                tx.ok = false;
+                tx.overflow = true;
+                tx.output.clone_from(state);
+            }
+            tx
+        }
+    }
+
+    /// Fires like a petri-net but ignores read and inhibitor arcs.
+    #[derive(Debug)]
+    struct IgnoreGuards;
+
+    impl FiringSemantics for IgnoreGuards {
+        fn fire(&self, sm: &StateMachine, state: &Vector, transition: &Transition, multiple: i32) -> Transaction {
+            let mut unguarded = transition.clone();
+            unguarded.guards.clear();
+            sm.petri_net_fire(state, &unguarded, multiple)
+        }
+    }
+
+    fn warehouse(p: &mut dyn FlowDsl) {
+        p.cell("dock", Option::from(2), None, 0, 0);
+        p.cell("shelf", None, None, 0, 0);
+        p.func("receive", "default", 0, 0);
+        p.func("store", "default", 0, 0);
+        p.arrow("receive", "dock", 1);
+        p.arrow("dock", "store", 1);
+        p.arrow("store", "shelf", 1);
+    }
+
+    #[test]
+    fn test_shared_capacity() {
+        let mut sm = StateMachine::new(warehouse);
+        assert!(sm.transform(&vec![2, 1], "receive", 1).is_ok());
+
+        sm.set_firing_semantics(SharedCapacity(3));
+        let tx = sm.transform(&vec![2, 1], "receive", 1);
+        assert!(tx.overflow && !tx.is_ok());
+        assert_eq!(tx.output, vec![2, 1]);
+        assert!(sm.transform(&vec![2, 0], "receive", 1).is_ok());
+        assert!(sm.is_enabled(&vec![2, 1], "store", 1));
+        assert!(!sm.is_enabled(&vec![2, 1], "receive", 1));
+
+        let compiled = sm.compile();
+        let receive = compiled.id("receive").unwrap();
+        assert!(!compiled.fire(&vec![2, 1], receive, 1).ok);
+        assert!(sm.clone().firing_semantics().is_some());
+    }
+
+    #[test]
+    fn test_semantics_decide_before_guards() {
+        let mut sm = StateMachine::new(|p| {
+            p.cell("dock", Option::from(1), None, 0, 0);
+            p.cell("shelf", None, None, 0, 0);
+            p.cell("closed", Option::from(1), None, 0, 0);
+            p.func("store", "default", 0, 0);
+            p.arrow("dock", "store", 1);
+            p.arrow("store", "shelf", 1);
+            p.guard("closed", "store", 1);
+        });
+        let state = sm.initial_vector();
+        assert!(!sm.is_enabled(&state, "store", 1));
+
+        sm.set_firing_semantics(IgnoreGuards);
+        assert!(sm.transform(&state, "store", 1).is_ok());
+        assert!(sm.is_enabled(&state, "store", 1));
+        let compiled = sm.compile();
+        let store = compiled.id("store").unwrap();
+        assert!(compiled.is_enabled(&state, store, 1) && compiled.fire(&state, store, 1).ok);
+        assert!(sm.preview(&state, "store", 1).enabled);
+    }
+}
@@ -0,0 +1,541 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::compression::DecodeLimits;
+use crate::petri_net::PetriNet;
+use crate::render::to_svg;
+use crate::vasm::{StateMachine, Transaction, Vasm, Vector};
+use crate::zblob::Zblob;
+
+/// `Repository` keeps the zblobs served by the router, keyed by CID.
+pub trait Repository: Send + Sync + 'static {
+    /// Stores the zblob, returns the stored zblob, which may have been normalized by the repository.
+    fn put(&self, zblob: Zblob) -> Result<Zblob, String>;
+    /// Returns the zblob with the given CID.
+    fn get(&self, cid: &str) -> Result<Option<Zblob>, String>;
+}
+
+/// `MemoryRepository` keeps the zblobs in memory, for tests and single-process servers.
+#[derive(Default)]
+pub struct MemoryRepository {
+    zblobs: RwLock<HashMap<String, Zblob>>,
+}
+
+impl Repository for MemoryRepository {
+    fn put(&self, zblob: Zblob) -> Result<Zblob, String> {
+        let mut zblobs = self.zblobs.write().unwrap();
+        Ok(zblobs.entry(zblob.ipfs_cid.clone()).or_insert(zblob).clone())
+    }
+
+    fn get(&self, cid: &str) -> Result<Option<Zblob>, String> {
+        Ok(self.zblobs.read().unwrap().get(cid).cloned())
+    }
+}
+
+#[cfg(feature = "storage")]
+impl Repository for std::sync::Mutex<crate::storage::BlobStore> {
+    fn put(&self, zblob: Zblob) -> Result<Zblob, String> {
+        let mut store = self.lock().unwrap();
+        store.insert(&zblob).map_err(|err| err.to_string())?;
+        store
+            .get(&zblob.ipfs_cid)
+            .map_err(|err| err.to_string())?
+            .ok_or_else(|| "zblob was not stored".to_string())
+    }
+
+    fn get(&self, cid: &str) -> Result<Option<Zblob>, String> {
+        self.lock().unwrap().get(cid).map_err(|err| err.to_string())
+    }
+}
+
+/// `ApiError` is an error response with a JSON body such as `{"error": "no model with cid ..."}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    fn internal(message: String) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.message }))).into_response()
+    }
+}
+
+/// The body of `POST /models`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UploadRequest {
+    /// The model or bundle, compressed and base64 encoded as in `Zblob::base64_zipped`.
+    pub base64_zipped: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub keywords: String,
+    #[serde(default)]
+    pub referrer: String,
+}
+
+/// The body of `POST /models/{cid}/fire`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FireRequest {
+    pub action: String,
+    /// The marking to fire in, the initial marking if absent.
+    #[serde(default)]
+    pub state: Option<Vector>,
+    /// The number of times to fire, once if absent.
+    #[serde(default)]
+    pub multiple: Option<i32>,
+}
+
+/// The most times a transition is fired at once.
+pub const MAX_MULTIPLE: i32 = 1_000;
+
+/// The most state machines kept decoded, the cache starts over once it is full.
+pub const MAX_CACHED_MACHINES: usize = 1_024;
+
+/// `Api` is the state shared by the handlers: the repository and the state machines of the models fired,
+/// which never go stale as models are keyed by the CID of their content.
+pub struct Api<R> {
+    repository: R,
+    machines: RwLock<HashMap<String, Arc<StateMachine>>>,
+}
+
+impl<R: Repository> Api<R> {
+    pub fn new(repository: R) -> Self {
+        Self {
+            repository,
+            machines: RwLock::default(),
+        }
+    }
+
+    /// Returns the state machine of the model with the given CID, decoding the model on a cache miss.
+    fn machine(&self, cid: &str) -> Result<Arc<StateMachine>, ApiError> {
+        if let Some(sm) = self.machines.read().unwrap().get(cid) {
+            return Ok(Arc::clone(sm));
+        }
+        let mut net = model(&find(&self.repository, cid)?)?;
+        let sm = StateMachine::try_from_model(&mut net)
+            .map_err(|err| ApiError::internal(format!("zblob {} holds an invalid model: {}", cid, err)))?;
+        let sm = Arc::new(sm);
+        let mut machines = self.machines.write().unwrap();
+        if machines.len() >= MAX_CACHED_MACHINES {
+            machines.clear();
+        }
+        machines.insert(cid.to_string(), Arc::clone(&sm));
+        Ok(sm)
+    }
+}
+
+type Shared<R> = State<Arc<Api<R>>>;
+
+/// Runs `f` on the blocking thread pool, as repositories such as SQLite databases and the decoding of
+/// models block.
+async fn blocking<R, T, F>(api: &Arc<Api<R>>, f: F) -> Result<T, ApiError>
+where
+    R: Repository,
+    T: Send + 'static,
+    F: FnOnce(&Api<R>) -> Result<T, ApiError> + Send + 'static,
+{
+    let api = Arc::clone(api);
+    tokio::task::spawn_blocking(move || f(&api))
+        .await
+        .map_err(|err| ApiError::internal(err.to_string()))?
+}
+
+fn find<R: Repository>(repository: &R, cid: &str) -> Result<Zblob, ApiError> {
+    repository
+        .get(cid)
+        .map_err(ApiError::internal)?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("no model with cid {}", cid)))
+}
+
+fn model(zblob: &Zblob) -> Result<PetriNet, ApiError> {
+    zblob
+        .bundle()
+        .and_then(|bundle| bundle.model())
+        .ok_or_else(|| ApiError::internal(format!("zblob {} holds no model", zblob.ipfs_cid)))
+}
+
+/// `POST /models` stores an uploaded zblob and answers `201 Created` with the stored zblob, or `400 Bad Request`
+/// if its model cannot be loaded as a state machine.
+pub async fn upload<R: Repository>(
+    State(api): Shared<R>,
+    Json(request): Json<UploadRequest>,
+) -> Result<(StatusCode, Json<Zblob>), ApiError> {
+    let stored = blocking(&api, move |api| {
+        let mut zblob = Zblob {
+            title: request.title,
+            description: request.description,
+            keywords: request.keywords,
+            referrer: request.referrer,
+            ..Zblob::from_string(Some(&request.base64_zipped))
+        };
+        let bundle = zblob
+            .bundle_within(DecodeLimits::default())
+            .map_err(|err| ApiError::bad_request(err.to_string()))?;
+        let mut net = bundle.model().ok_or_else(|| ApiError::bad_request("payload holds no model"))?;
+        StateMachine::try_from_model(&mut net).map_err(|err| ApiError::bad_request(err.to_string()))?;
+        zblob.normalize();
+        zblob.validate().map_err(|err| ApiError::bad_request(err.to_string()))?;
+        api.repository.put(zblob).map_err(ApiError::internal)
+    })
+    .await?;
+    Ok((StatusCode::CREATED, Json(stored)))
+}
+
+/// `GET /models/{cid}` returns the zblob.
+pub async fn get_model<R: Repository>(State(api): Shared<R>, Path(cid): Path<String>) -> Result<Json<Zblob>, ApiError> {
+    Ok(Json(blocking(&api, move |api| find(&api.repository, &cid)).await?))
+}
+
+/// `GET /models/{cid}/svg` draws the model in its initial marking.
+pub async fn get_svg<R: Repository>(
+    State(api): Shared<R>,
+    Path(cid): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let svg = blocking(&api, move |api| Ok(to_svg(&model(&find(&api.repository, &cid)?)?, None))).await?;
+    Ok(([(header::CONTENT_TYPE, "image/svg+xml")], svg))
+}
+
+/// `POST /models/{cid}/fire` fires a transition and returns the `Transaction`, which is not ok
+/// if the transition is unknown or not enabled, or `400 Bad Request` if the multiple is not between 1 and `MAX_MULTIPLE`.
+pub async fn fire<R: Repository>(
+    State(api): Shared<R>,
+    Path(cid): Path<String>,
+    Json(request): Json<FireRequest>,
+) -> Result<Json<Transaction>, ApiError> {
+    let multiple = request.multiple.unwrap_or(1);
+    if !(1..=MAX_MULTIPLE).contains(&multiple) {
+        return Err(ApiError::bad_request(format!(
+            "multiple {} is not between 1 and {}",
+            multiple, MAX_MULTIPLE
+        )));
+    }
+    let sm = blocking(&api, {
+        let cid = cid.clone();
+        move |api| api.machine(&cid)
+    })
+    .await?;
+    let state = request.state.unwrap_or_else(|| sm.initial_vector());
+    if state.len() != sm.places.len() {
+        return Err(ApiError::bad_request(format!(
+            "state has {} places, the model has {}",
+            state.len(),
+            sm.places.len()
+        )));
+    }
+    let tx = sm.transform(&state, &request.action, multiple);
+    #[cfg(feature = "metrics")]
+    crate::metrics::record_transaction(&cid, &sm, &request.action, &tx);
+    Ok(Json(tx))
+}
+
+/// Records every request with `metrics::record_request`, labelled by its route rather than its path
+/// so the CIDs do not become labels.
+#[cfg(feature = "metrics")]
+async fn track<B>(request: axum::http::Request<B>, next: axum::middleware::Next<B>) -> Response {
+    let route = request
+        .extensions()
+        .get::<axum::extract::MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+    let started = std::time::Instant::now();
+    let response = next.run(request).await;
+    crate::metrics::record_request(&route, response.status().as_u16(), started.elapsed());
+    response
+}
+
+/// `GET /openapi.json` returns the `openapi` document.
+pub async fn get_openapi() -> Json<Value> {
+    Json(openapi())
+}
+
+/// Returns the OpenAPI 3 document describing the routes of `router`.
+pub fn openapi() -> Value {
+    let cid = json!({ "name": "cid", "in": "path", "required": true, "schema": { "type": "string" } });
+    let error = json!({
+        "description": "The error",
+        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } }
+    });
+    let zblob = json!({ "application/json": { "schema": { "$ref": "#/components/schemas/Zblob" } } });
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "pflow-metamodel",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Stores Petri-net models as zblobs, draws them and fires their transitions."
+        },
+        "paths": {
+            "/models": {
+                "post": {
+                    "summary": "Upload a zblob",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/UploadRequest" } } }
+                    },
+                    "responses": {
+                        "201": { "description": "The stored zblob", "content": zblob },
+                        "400": error
+                    }
+                }
+            },
+            "/models/{cid}": {
+                "get": {
+                    "summary": "Get a zblob",
+                    "parameters": [cid],
+                    "responses": {
+                        "200": { "description": "The zblob", "content": zblob },
+                        "404": error
+                    }
+                }
+            },
+            "/models/{cid}/svg": {
+                "get": {
+                    "summary": "Draw the model in its initial marking",
+                    "parameters": [cid],
+                    "responses": {
+                        "200": {
+                            "description": "The drawing",
+                            "content": { "image/svg+xml": { "schema": { "type": "string" } } }
+                        },
+                        "404": error
+                    }
+                }
+            },
+            "/models/{cid}/fire": {
+                "post": {
+                    "summary": "Fire a transition",
+                    "parameters": [cid],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/FireRequest" } } }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "The transaction, not ok if the transition is unknown or not enabled",
+                            "content": {
+                                "application/json": { "schema": { "$ref": "#/components/schemas/Transaction" } }
+                            }
+                        },
+                        "400": error,
+                        "404": error
+                    }
+                }
+            }
+        },
+        "components": {
+            "schemas": {
+                "Error": {
+                    "type": "object",
+                    "required": ["error"],
+                    "properties": { "error": { "type": "string" } }
+                },
+                "UploadRequest": {
+                    "type": "object",
+                    "required": ["base64_zipped"],
+                    "properties": {
+                        "base64_zipped": { "type": "string", "description": "The compressed, base64 encoded model or bundle" },
+                        "title": { "type": "string" },
+                        "description": { "type": "string" },
+                        "keywords": { "type": "string" },
+                        "referrer": { "type": "string" }
+                    }
+                },
+                "Zblob": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "integer" },
+                        "ipfs_cid": { "type": "string" },
+                        "base64_zipped": { "type": "string" },
+                        "title": { "type": "string" },
+                        "description": { "type": "string" },
+                        "keywords": { "type": "string" },
+                        "referrer": { "type": "string" },
+                        "created_at": { "type": "string" },
+                        "previous_cid": { "type": "string" },
+                        "version": { "type": "integer" },
+                        "encryption": { "type": "string" }
+                    }
+                },
+                "FireRequest": {
+                    "type": "object",
+                    "required": ["action"],
+                    "properties": {
+                        "action": { "type": "string" },
+                        "state": { "type": "array", "items": { "type": "integer" } },
+                        "multiple": { "type": "integer", "default": 1, "minimum": 1, "maximum": MAX_MULTIPLE }
+                    }
+                },
+                "Transaction": {
+                    "type": "object",
+                    "properties": {
+                        "ok": { "type": "boolean" },
+                        "output": { "type": "array", "items": { "type": "integer" } },
+                        "role": { "type": "string" },
+                        "inhibited": { "type": "boolean" },
+                        "overflow": { "type": "boolean" },
+                        "underflow": { "type": "boolean" },
+                        "error": { "type": "object", "nullable": true }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Returns the router serving the REST API over the repository:
+///
+/// * `POST /models` uploads a zblob.
+/// * `GET /models/{cid}` returns a zblob.
+/// * `GET /models/{cid}/svg` draws a model.
+/// * `POST /models/{cid}/fire` fires a transition of a model.
+/// * `GET /openapi.json` describes these routes.
+///
+/// With the `metrics` feature every request and firing is recorded, see the `metrics` module.
+pub fn router<R: Repository>(repository: R) -> Router {
+    let router = Router::new()
+        .route("/models", post(upload::<R>))
+        .route("/models/:cid", get(get_model::<R>))
+        .route("/models/:cid/svg", get(get_svg::<R>))
+        .route("/models/:cid/fire", post(fire::<R>))
+        .route("/openapi.json", get(get_openapi));
+    #[cfg(feature = "metrics")]
+    let router = router.route_layer(axum::middleware::from_fn(track));
+    router.with_state(Arc::new(Api::new(repository)))
+}
+
+/// Serves the router on the address until the server fails.
+pub async fn serve(addr: SocketAddr, router: Router) -> Result<(), axum::Error> {
+    axum::Server::bind(&addr)
+        .serve(router.into_make_service())
+        .await
+        .map_err(axum::Error::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    async fn call(router: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, String) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json");
+        let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+        let response = router.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_routes() {
+        let router = router(MemoryRepository::default());
+        let zblob = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap().to_zblob();
+        let upload = json!({ "base64_zipped": zblob.base64_zipped, "title": " philosophers ", "keywords": "A, a,b" });
+        let (status, body) = call(&router, "POST", "/models", Some(upload)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let stored: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(stored["ipfs_cid"], zblob.ipfs_cid);
+        assert_eq!(
+            (stored["title"].as_str(), stored["keywords"].as_str()),
+            (Some("philosophers"), Some("a,b"))
+        );
+
+        let uri = format!("/models/{}", zblob.ipfs_cid);
+        let (status, body) = call(&router, "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), stored);
+
+        let (status, svg) = call(&router, "GET", &format!("{}/svg", uri), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(svg.starts_with("<svg") && svg.contains(">chopstick1</text>"));
+
+        let fire = format!("{}/fire", uri);
+        let (status, body) = call(&router, "POST", &fire, Some(json!({ "action": "eat1" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["ok"], true);
+        let (status, body) = call(&router, "POST", &fire, Some(json!({ "action": "missing" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["ok"], false);
+        let (status, _) = call(&router, "POST", &fire, Some(json!({ "action": "eat1", "state": [1] }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        for multiple in [0, -1, MAX_MULTIPLE + 1, i32::MAX] {
+            let request = json!({ "action": "eat1", "multiple": multiple });
+            let (status, body) = call(&router, "POST", &fire, Some(request)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(body.contains("is not between 1 and 1000"));
+        }
+
+        let (status, body) = call(&router, "GET", "/models/missing", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body, r#"{"error":"no model with cid missing"}"#);
+        let (status, _) = call(
+            &router,
+            "POST",
+            "/models",
+            Some(json!({ "base64_zipped": "not base64!" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let mut dangling = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        dangling.add_arc("eat1", "nowhere", None, None, None, None, None);
+        let upload = json!({ "base64_zipped": dangling.to_zblob().base64_zipped });
+        let (status, body) = call(&router, "POST", "/models", Some(upload)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, r#"{"error":"arc eat1 -> nowhere does not connect a place and a transition"}"#);
+        let (status, _) = call(&router, "GET", &format!("/models/{}", dangling.to_zblob().ipfs_cid), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, body) = call(&router, "GET", "/openapi.json", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let document: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(document["paths"].as_object().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_machine_cache() {
+        let api = Api::new(MemoryRepository::default());
+        let zblob = api
+            .repository
+            .put(PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap().to_zblob())
+            .unwrap();
+        let sm = api.machine(&zblob.ipfs_cid).unwrap();
+        assert!(Arc::ptr_eq(&sm, &api.machine(&zblob.ipfs_cid).unwrap()));
+        assert_eq!(api.machine("missing").unwrap_err().status, StatusCode::NOT_FOUND);
+    }
+}
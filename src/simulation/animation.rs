@@ -0,0 +1,109 @@
+use serde::Serialize;
+
+use crate::simulation::simulator::FireRecord;
+use crate::vasm::{StateMachine, Vasm, Vector};
+
+/// `TokenMove` hints the front-end to animate tokens along an arc.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TokenMove {
+    /// The place the tokens leave, or the transition that fired for produced tokens.
+    pub source: String,
+    /// The transition that fired for consumed tokens, or the place receiving the tokens.
+    pub target: String,
+    pub tokens: i32,
+}
+
+/// `Frame` is a single step of the token game animation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Frame {
+    pub step: usize,
+    /// The transition that fired to reach this frame, `None` for the initial frame.
+    pub action: Option<String>,
+    /// The marking shown in this frame.
+    pub marking: Vector,
+    /// The tokens moved from the previous frame, consumed tokens come first.
+    pub moves: Vec<TokenMove>,
+}
+
+fn token_moves(sm: &StateMachine, record: &FireRecord) -> Vec<TokenMove> {
+    let delta = match sm.transitions.get(&record.action) {
+        Some(t) => &t.delta,
+        None => return Vec::new(),
+    };
+    let consumed = delta.iter().enumerate().filter(|(_, d)| **d < 0).map(|(i, d)| TokenMove {
+        source: sm.places[i].clone(),
+        target: record.action.clone(),
+        tokens: -d * record.multiple,
+    });
+    let produced = delta.iter().enumerate().filter(|(_, d)| **d > 0).map(|(i, d)| TokenMove {
+        source: record.action.clone(),
+        target: sm.places[i].clone(),
+        tokens: d * record.multiple,
+    });
+    consumed.chain(produced).collect()
+}
+
+/// Builds the animation frames of a trace, starting with a frame of the initial marking.
+pub fn frames(sm: &StateMachine, trace: &[FireRecord]) -> Vec<Frame> {
+    let initial = Frame {
+        step: 0,
+        action: None,
+        marking: sm.initial_vector(),
+        moves: Vec::new(),
+    };
+    std::iter::once(initial)
+        .chain(trace.iter().enumerate().map(|(i, record)| Frame {
+            step: i + 1,
+            action: Some(record.action.clone()),
+            marking: record.output.clone(),
+            moves: token_moves(sm, record),
+        }))
+        .collect()
+}
+
+/// Serializes the frames as newline delimited JSON, one frame per line.
+pub fn to_ndjson(frames: &[Frame]) -> Result<String, serde_json::Error> {
+    let mut out = String::new();
+    for frame in frames {
+        out.push_str(&serde_json::to_string(frame)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulation::simulator::Simulator;
+
+    use super::*;
+
+    #[test]
+    fn test_frames_from_trace() {
+        let mut sim = Simulator::new(StateMachine::new(|p| {
+            p.cell("foo", Option::from(2), None, 0, 0);
+            p.cell("bar", None, None, 0, 0);
+            p.func("move", "default", 0, 0);
+            p.arrow("foo", "move", 1);
+            p.arrow("move", "bar", 1);
+        }));
+        sim.fire("move", 2);
+
+        let frames = frames(&sim.sm, sim.trace());
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].marking, vec![2, 0]);
+        assert_eq!(frames[1].action.as_deref(), Some("move"));
+        assert_eq!(frames[1].marking, vec![0, 2]);
+        assert_eq!(
+            frames[1].moves,
+            vec![
+                TokenMove { source: "foo".to_string(), target: "move".to_string(), tokens: 2 },
+                TokenMove { source: "move".to_string(), target: "bar".to_string(), tokens: 2 },
+            ]
+        );
+
+        let ndjson = to_ndjson(&frames).unwrap();
+        let lines: Vec<&str> = ndjson.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], r#"{"step":0,"action":null,"marking":[2,0],"moves":[]}"#);
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::policy::RandomChoice;
+use crate::simulation::simulator::Simulator;
+use crate::simulation::watch::StopReason;
+use crate::vasm::{StateMachine, Vector};
+
+/// `ExperimentConfig` describes a batch of independent simulation runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExperimentConfig {
+    /// The number of independent runs.
+    pub runs: usize,
+    /// The maximum number of firings per run.
+    pub max_steps: usize,
+    /// The seed of the first run, run `i` is seeded with `seed + i`.
+    pub seed: u64,
+    /// Runs the simulations on the rayon thread pool, runs them in turn without the `parallel` feature.
+    pub parallel: bool,
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        Self {
+            runs: 100,
+            max_steps: 1_000,
+            seed: 0,
+            parallel: true,
+        }
+    }
+}
+
+/// `Summary` describes the distribution of a sample with a 95% confidence interval of its mean.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub ci_low: f64,
+    pub ci_high: f64,
+}
+
+impl Summary {
+    /// Summarizes the sample, the confidence interval uses the normal approximation.
+    pub fn from_sample(sample: &[f64]) -> Self {
+        if sample.is_empty() {
+            return Self::default();
+        }
+        let n = sample.len() as f64;
+        let mean = sample.iter().sum::<f64>() / n;
+        let variance = match sample.len() {
+            1 => 0.0,
+            _ => sample.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0),
+        };
+        let std_dev = variance.sqrt();
+        let margin = 1.96 * std_dev / n.sqrt();
+        Self {
+            count: sample.len(),
+            mean,
+            std_dev,
+            min: sample.iter().copied().fold(f64::INFINITY, f64::min),
+            max: sample.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            ci_low: mean - margin,
+            ci_high: mean + margin,
+        }
+    }
+}
+
+/// `ExperimentReport` aggregates the outcome of all runs of an experiment.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExperimentReport {
+    pub runs: usize,
+    /// The distribution of the final token count of each place.
+    pub tokens: BTreeMap<String, Summary>,
+    /// The distribution of the number of firings of each transition per run.
+    pub firings: BTreeMap<String, Summary>,
+    /// The number of runs that reached a state without enabled transitions.
+    pub absorbed: usize,
+    /// The distribution of the number of steps until absorption over the absorbed runs.
+    pub time_to_absorption: Summary,
+}
+
+struct RunOutcome {
+    state: Vector,
+    firings: BTreeMap<String, usize>,
+    absorbed_after: Option<usize>,
+}
+
+fn simulate(sm: &StateMachine, config: &ExperimentConfig, run: usize) -> RunOutcome {
+    let mut sim = Simulator::new(sm.clone());
+    sim.set_policy(RandomChoice::new(config.seed.wrapping_add(run as u64)));
+    let report = sim.run(config.max_steps);
+    let mut firings: BTreeMap<String, usize> = BTreeMap::new();
+    report.trace.iter().for_each(|r| *firings.entry(r.action.clone()).or_insert(0) += 1);
+    RunOutcome {
+        state: sim.state().clone(),
+        firings,
+        absorbed_after: (report.reason == StopReason::Deadlock).then_some(report.steps),
+    }
+}
+
+/// Runs independent simulations choosing uniformly at random among the enabled transitions,
+/// and aggregates their outcomes. Runs are seeded individually, so the report does not depend
+/// on whether they ran in parallel.
+pub fn experiment(sm: &StateMachine, config: &ExperimentConfig) -> ExperimentReport {
+    let outcomes: Vec<RunOutcome> = match config.parallel {
+        #[cfg(feature = "parallel")]
+        true => (0..config.runs).into_par_iter().map(|i| simulate(sm, config, i)).collect(),
+        _ => (0..config.runs).map(|i| simulate(sm, config, i)).collect(),
+    };
+
+    let tokens = sm
+        .places
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let sample: Vec<f64> = outcomes.iter().map(|o| o.state[i] as f64).collect();
+            (p.clone(), Summary::from_sample(&sample))
+        })
+        .collect();
+
+    let mut actions: Vec<&String> = sm.transitions.keys().collect();
+    actions.sort();
+    let firings = actions
+        .into_iter()
+        .map(|a| {
+            let sample: Vec<f64> = outcomes
+                .iter()
+                .map(|o| *o.firings.get(a).unwrap_or(&0) as f64)
+                .collect();
+            (a.clone(), Summary::from_sample(&sample))
+        })
+        .collect();
+
+    let absorption: Vec<f64> = outcomes.iter().filter_map(|o| o.absorbed_after).map(|s| s as f64).collect();
+    ExperimentReport {
+        runs: config.runs,
+        tokens,
+        firings,
+        absorbed: absorption.len(),
+        time_to_absorption: Summary::from_sample(&absorption),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+
+    use super::*;
+
+    fn race(p: &mut dyn FlowDsl) {
+        p.cell("start", Option::from(1), None, 0, 0);
+        p.cell("left", None, None, 0, 0);
+        p.cell("right", None, None, 0, 0);
+        p.func("go_left", "default", 0, 0);
+        p.func("go_right", "default", 0, 0);
+        p.arrow("start", "go_left", 1);
+        p.arrow("go_left", "left", 1);
+        p.arrow("start", "go_right", 1);
+        p.arrow("go_right", "right", 1);
+    }
+
+    #[test]
+    fn test_summary() {
+        let s = Summary::from_sample(&[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(s.mean, 2.5);
+        assert_eq!(s.min, 1.0);
+        assert_eq!(s.max, 4.0);
+        assert!((s.std_dev - 1.290_994).abs() < 1e-6);
+        assert!(s.ci_low < s.mean && s.mean < s.ci_high);
+    }
+
+    #[test]
+    fn test_experiment_is_reproducible() {
+        let sm = StateMachine::new(race);
+        let config = ExperimentConfig { runs: 200, seed: 7, ..Default::default() };
+        let parallel = experiment(&sm, &config);
+        let serial = experiment(&sm, &ExperimentConfig { parallel: false, ..config });
+        assert_eq!(parallel.tokens["left"], serial.tokens["left"]);
+
+        assert_eq!(parallel.absorbed, 200);
+        assert_eq!(parallel.time_to_absorption.mean, 1.0);
+        let left = parallel.tokens["left"].mean;
+        assert!(left > 0.3 && left < 0.7, "left {}", left);
+        assert_eq!(parallel.tokens["start"].max, 0.0);
+        assert_eq!(parallel.firings["go_left"].mean + parallel.firings["go_right"].mean, 1.0);
+        assert!(serde_json::to_string(&parallel).is_ok());
+    }
+
+    #[test]
+    fn test_step_limit_is_not_absorption() {
+        let sm = StateMachine::new(|p| {
+            p.cell("count", None, None, 0, 0);
+            p.func("tick", "default", 0, 0);
+            p.arrow("tick", "count", 1);
+        });
+        let report = experiment(&sm, &ExperimentConfig { runs: 3, max_steps: 5, ..Default::default() });
+        assert_eq!(report.absorbed, 0);
+        assert_eq!(report.tokens["count"].mean, 5.0);
+    }
+}
@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::watch::MarkingView;
+use crate::vasm::{StateMachine, Transaction, Vasm, Vector};
+
+/// The number of events between two markings kept by a `Journal` unless configured otherwise.
+pub const DEFAULT_CHECKPOINT_INTERVAL: usize = 64;
+
+/// `JournalEvent` is a successful firing appended to a `Journal`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEvent {
+    pub action: String,
+    pub multiple: i32,
+}
+
+/// `Journal` is the append-only log of the successful firings of a single instance, the source of truth
+/// from which the marking at any offset is rebuilt.
+///
+/// The marking is kept every `checkpoint_interval` events, so rebuilding a past state replays at most
+/// that many events. Only the events need to be persisted, see `from_events`.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    pub sm: StateMachine,
+    events: Vec<JournalEvent>,
+    checkpoints: Vec<Vector>,
+    checkpoint_interval: usize,
+    state: Vector,
+}
+
+impl Journal {
+    /// Creates an empty journal starting in the initial state of the state machine.
+    pub fn new(sm: StateMachine) -> Self {
+        Self::with_checkpoint_interval(sm, DEFAULT_CHECKPOINT_INTERVAL)
+    }
+
+    /// Creates an empty journal keeping the marking every `interval` events, an interval of zero is treated as one.
+    pub fn with_checkpoint_interval(sm: StateMachine, interval: usize) -> Self {
+        let state = sm.initial_vector();
+        Self {
+            sm,
+            events: Vec::new(),
+            checkpoints: vec![state.clone()],
+            checkpoint_interval: interval.max(1),
+            state,
+        }
+    }
+
+    /// Rebuilds a journal from persisted events, failing with the index of the first event that is not enabled.
+    pub fn from_events(sm: StateMachine, events: Vec<JournalEvent>) -> Result<Self, usize> {
+        let mut journal = Self::new(sm);
+        for (index, event) in events.iter().enumerate() {
+            if journal.append(&event.action, event.multiple).is_err() {
+                return Err(index);
+            }
+        }
+        Ok(journal)
+    }
+
+    /// Returns the events in the order they were appended.
+    pub fn events(&self) -> &[JournalEvent] {
+        &self.events
+    }
+
+    /// Returns the number of events.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Checks if no event was appended.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Returns the marking after the last event.
+    pub fn state(&self) -> &Vector {
+        &self.state
+    }
+
+    /// Fires the action in the current state, appending an event only if the transformation succeeds.
+    pub fn append(&mut self, action: &str, multiple: i32) -> Transaction {
+        let res = self.sm.transform(&self.state, action, multiple);
+        if res.is_err() {
+            return res;
+        }
+        self.state.clone_from(&res.output);
+        self.events.push(JournalEvent {
+            action: action.to_string(),
+            multiple,
+        });
+        if self.events.len() % self.checkpoint_interval == 0 {
+            self.checkpoints.push(self.state.clone());
+        }
+        res
+    }
+
+    /// Returns the marking after the first `offset` events, the initial state at offset zero,
+    /// or None if the journal has fewer events.
+    pub fn state_at(&self, offset: usize) -> Option<Vector> {
+        if offset > self.events.len() {
+            return None;
+        }
+        let checkpoint = offset / self.checkpoint_interval;
+        let mut state = self.checkpoints[checkpoint].clone();
+        for event in &self.events[checkpoint * self.checkpoint_interval..offset] {
+            state = self.sm.transform(&state, &event.action, event.multiple).output;
+        }
+        Some(state)
+    }
+
+    /// Returns the first offset at which the condition holds, or None if it does not hold after the last event.
+    ///
+    /// Like `git bisect` the offsets are searched in halves, so the condition must keep holding once it
+    /// held, such as a case being stuck or a counter passing a threshold. Offset zero is the initial state.
+    pub fn bisect<F>(&self, condition: F) -> Option<usize>
+    where
+        F: Fn(&MarkingView) -> bool,
+    {
+        let holds = |offset: usize| {
+            let state = self.state_at(offset).unwrap();
+            condition(&MarkingView {
+                places: &self.sm.places,
+                state: &state,
+            })
+        };
+        let (mut low, mut high) = (0, self.events.len());
+        if !holds(high) {
+            return None;
+        }
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if holds(mid) {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        Some(low)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+
+    use super::*;
+
+    fn counter(p: &mut dyn FlowDsl) {
+        p.cell("source", Option::from(100), None, 0, 0);
+        p.cell("sink", None, None, 0, 0);
+        p.func("move", "default", 0, 0);
+        p.arrow("source", "move", 1);
+        p.arrow("move", "sink", 1);
+    }
+
+    #[test]
+    fn test_state_at() {
+        let mut journal = Journal::with_checkpoint_interval(StateMachine::new(counter), 4);
+        for multiple in [1, 2, 3, 4, 5, 6, 7, 8, 9, 10] {
+            assert!(journal.append("move", multiple).is_ok());
+        }
+        assert!(journal.append("move", 100).is_err());
+        assert_eq!(journal.len(), 10);
+        assert_eq!(journal.state(), &vec![45, 55]);
+
+        for offset in 0..=10 {
+            let moved = (1..=offset as i32).sum::<i32>();
+            assert_eq!(
+                journal.state_at(offset),
+                Some(vec![100 - moved, moved]),
+                "offset {}",
+                offset
+            );
+        }
+        assert_eq!(journal.state_at(11), None);
+
+        let rebuilt = Journal::from_events(StateMachine::new(counter), journal.events().to_vec()).unwrap();
+        assert_eq!(rebuilt.state(), journal.state());
+    }
+
+    #[test]
+    fn test_bisect() {
+        let mut journal = Journal::new(StateMachine::new(counter));
+        for _ in 0..100 {
+            journal.append("move", 1);
+        }
+        assert_eq!(journal.bisect(|m| m.tokens("sink") >= 37), Some(37));
+        assert_eq!(journal.bisect(|m| m.tokens("source") <= 100), Some(0));
+        assert_eq!(journal.bisect(|m| m.tokens("sink") > 100), None);
+    }
+
+    #[test]
+    fn test_from_events_rejects_disabled_event() {
+        let events = vec![
+            JournalEvent {
+                action: "move".to_string(),
+                multiple: 60,
+            },
+            JournalEvent {
+                action: "move".to_string(),
+                multiple: 60,
+            },
+        ];
+        assert_eq!(Journal::from_events(StateMachine::new(counter), events).unwrap_err(), 1);
+    }
+}
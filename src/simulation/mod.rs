@@ -0,0 +1,52 @@
+/// The `animation` module exports simulator traces as frames for the token game animation.
+pub mod animation;
+
+/// The `experiment` module runs seeded Monte Carlo simulations and aggregates their outcomes behind the `random` feature.
+#[cfg(feature = "random")]
+pub mod experiment;
+
+/// The `journal` module keeps the event log of an instance and rebuilds its marking at any offset.
+pub mod journal;
+
+/// The `policy` module contains the conflict-resolution policies choosing which enabled transition fires.
+pub mod policy;
+
+/// The `replay` module re-executes recorded traces and reports the first divergence.
+pub mod replay;
+
+/// The `simulator` module runs a single instance of a state machine and records its trace.
+pub mod simulator;
+
+/// The `snapshot` module persists the marking of a simulator tied to the version of its model behind the `zblob` feature.
+#[cfg(feature = "zblob")]
+pub mod snapshot;
+
+/// The `stream` module drives state machines from async streams of commands behind the `stream` feature.
+#[cfg(feature = "stream")]
+pub mod stream;
+
+/// The `timers` module schedules the timeouts of enabled transitions on a timer wheel.
+pub mod timers;
+
+/// The `updates` module describes the marking updates published to simulator subscribers.
+pub mod updates;
+
+/// The `watch` module describes the watch conditions and stop reasons of simulation runs.
+pub mod watch;
+
+pub use animation::{frames, to_ndjson, Frame, TokenMove};
+#[cfg(feature = "random")]
+pub use experiment::{experiment, ExperimentConfig, ExperimentReport, Summary};
+pub use journal::{Journal, JournalEvent};
+pub use policy::{ConflictPolicy, FirstEnabled, Priority, RoundRobin};
+#[cfg(feature = "random")]
+pub use policy::{RandomChoice, RateWeighted};
+pub use replay::{replay, Divergence, ReplayReport};
+pub use simulator::{FireRecord, Simulator};
+#[cfg(feature = "zblob")]
+pub use snapshot::{RestoreError, StateSnapshot};
+#[cfg(feature = "stream")]
+pub use stream::{Command, Driver};
+pub use timers::{TimeoutEvent, TimerWheel, Timers};
+pub use updates::{marking_delta, MarkingChange, MarkingUpdate, UpdateMode};
+pub use watch::{MarkingView, RunReport, StopReason, Watch};
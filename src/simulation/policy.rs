@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "random")]
+use rand::distributions::{Distribution, WeightedIndex};
+#[cfg(feature = "random")]
+use rand::rngs::StdRng;
+#[cfg(feature = "random")]
+use rand::{Rng, SeedableRng};
+
+use crate::vasm::{StateMachine, Vector};
+
+/// `ConflictPolicy` decides which transition fires when several are enabled during a simulation run.
+///
+/// Closures taking the state machine, the current state and the enabled transitions implement the trait.
+pub trait ConflictPolicy: Send {
+    /// Returns the index into `enabled` of the transition to fire.
+    ///
+    /// `enabled` is never empty and lists the enabled transitions in label order.
+    fn choose(&mut self, sm: &StateMachine, state: &Vector, enabled: &[String]) -> usize;
+}
+
+impl<F> ConflictPolicy for F
+where
+    F: FnMut(&StateMachine, &Vector, &[String]) -> usize + Send,
+{
+    fn choose(&mut self, sm: &StateMachine, state: &Vector, enabled: &[String]) -> usize {
+        self(sm, state, enabled)
+    }
+}
+
+/// `FirstEnabled` always fires the enabled transition with the smallest label.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FirstEnabled;
+
+impl ConflictPolicy for FirstEnabled {
+    fn choose(&mut self, _: &StateMachine, _: &Vector, _: &[String]) -> usize {
+        0
+    }
+}
+
+/// `RandomChoice` fires an enabled transition chosen uniformly at random.
+#[cfg(feature = "random")]
+#[derive(Debug, Clone)]
+pub struct RandomChoice {
+    rng: StdRng,
+}
+
+#[cfg(feature = "random")]
+impl RandomChoice {
+    /// Creates a seeded `RandomChoice` policy.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+#[cfg(feature = "random")]
+impl ConflictPolicy for RandomChoice {
+    fn choose(&mut self, _: &StateMachine, _: &Vector, enabled: &[String]) -> usize {
+        self.rng.gen_range(0..enabled.len())
+    }
+}
+
+/// `Priority` fires the enabled transition with the highest priority, ties go to the smallest label.
+/// Transitions without a priority have a priority of zero.
+#[derive(Debug, Clone, Default)]
+pub struct Priority {
+    pub priorities: BTreeMap<String, i32>,
+}
+
+impl Priority {
+    /// Creates a `Priority` policy from (action, priority) pairs.
+    pub fn new(priorities: &[(&str, i32)]) -> Self {
+        Self {
+            priorities: priorities.iter().map(|(a, p)| (a.to_string(), *p)).collect(),
+        }
+    }
+}
+
+impl ConflictPolicy for Priority {
+    fn choose(&mut self, _: &StateMachine, _: &Vector, enabled: &[String]) -> usize {
+        let priority = |i: usize| *self.priorities.get(&enabled[i]).unwrap_or(&0);
+        (0..enabled.len()).rev().max_by_key(|i| priority(*i)).unwrap_or(0)
+    }
+}
+
+/// `RoundRobin` cycles through the transitions in label order, firing the next enabled one
+/// after the transition it fired last.
+#[derive(Debug, Clone, Default)]
+pub struct RoundRobin {
+    last: Option<String>,
+}
+
+impl ConflictPolicy for RoundRobin {
+    fn choose(&mut self, _: &StateMachine, _: &Vector, enabled: &[String]) -> usize {
+        let i = match &self.last {
+            Some(last) => enabled.iter().position(|a| a > last).unwrap_or(0),
+            None => 0,
+        };
+        self.last = Some(enabled[i].clone());
+        i
+    }
+}
+
+/// `RateWeighted` fires an enabled transition chosen at random with a probability proportional to its rate.
+/// Transitions without a rate have a rate of one.
+#[cfg(feature = "random")]
+#[derive(Debug, Clone)]
+pub struct RateWeighted {
+    pub rates: BTreeMap<String, f64>,
+    rng: StdRng,
+}
+
+#[cfg(feature = "random")]
+impl RateWeighted {
+    /// Creates a seeded `RateWeighted` policy from (action, rate) pairs.
+    pub fn new(rates: &[(&str, f64)], seed: u64) -> Self {
+        Self {
+            rates: rates.iter().map(|(a, r)| (a.to_string(), *r)).collect(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+#[cfg(feature = "random")]
+impl ConflictPolicy for RateWeighted {
+    fn choose(&mut self, _: &StateMachine, _: &Vector, enabled: &[String]) -> usize {
+        let weights = enabled.iter().map(|a| self.rates.get(a).copied().unwrap_or(1.0).max(0.0));
+        match WeightedIndex::new(weights) {
+            Ok(dist) => dist.sample(&mut self.rng),
+            Err(_) => self.rng.gen_range(0..enabled.len()), // every rate is zero
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn choose(policy: &mut dyn ConflictPolicy, enabled: &[&str]) -> String {
+        let sm = StateMachine::new(|_| {});
+        let enabled: Vec<String> = enabled.iter().map(|a| a.to_string()).collect();
+        enabled[policy.choose(&sm, &vec![], &enabled)].clone()
+    }
+
+    #[test]
+    fn test_priority() {
+        let mut policy = Priority::new(&[("b", 2), ("c", 2), ("d", -1)]);
+        assert_eq!(choose(&mut policy, &["a", "b", "c"]), "b");
+        assert_eq!(choose(&mut policy, &["a", "d"]), "a");
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut policy = RoundRobin::default();
+        let picks: Vec<String> = (0..4).map(|_| choose(&mut policy, &["a", "b", "c"])).collect();
+        assert_eq!(picks, vec!["a", "b", "c", "a"]);
+        assert_eq!(choose(&mut policy, &["a", "c"]), "c");
+    }
+
+    #[test]
+    #[cfg(feature = "random")]
+    fn test_rate_weighted() {
+        let mut policy = RateWeighted::new(&[("slow", 0.0)], 1);
+        assert!((0..50).all(|_| choose(&mut policy, &["fast", "slow"]) == "fast"));
+
+        let mut a = RandomChoice::new(3);
+        let mut b = RandomChoice::new(3);
+        let enabled = ["a", "b", "c", "d"];
+        assert!((0..20).all(|_| choose(&mut a, &enabled) == choose(&mut b, &enabled)));
+    }
+
+    #[test]
+    fn test_closure_policy() {
+        let mut last = |_: &StateMachine, _: &Vector, enabled: &[String]| enabled.len() - 1;
+        assert_eq!(choose(&mut last, &["a", "b"]), "b");
+    }
+}
@@ -0,0 +1,142 @@
+use serde::Serialize;
+
+use crate::simulation::simulator::FireRecord;
+use crate::vasm::{StateMachine, Transaction, Vasm, Vector};
+
+/// `Divergence` describes the first recorded firing that could not be reproduced.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum Divergence {
+    /// The recorded action failed in the replayed state.
+    NotEnabled { index: usize, action: String, transaction: Transaction },
+    /// The recorded action succeeded but produced a different marking.
+    StateMismatch { index: usize, action: String, expected: Vector, actual: Vector },
+}
+
+impl Divergence {
+    /// Returns the position of the diverging record in the trace.
+    pub fn index(&self) -> usize {
+        match self {
+            Divergence::NotEnabled { index, .. } => *index,
+            Divergence::StateMismatch { index, .. } => *index,
+        }
+    }
+}
+
+/// `ReplayReport` is the outcome of re-executing a recorded trace.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayReport {
+    /// The number of records reproduced before the replay stopped.
+    pub replayed: usize,
+    /// The state reached by the replay.
+    pub state: Vector,
+    /// The first record that could not be reproduced.
+    pub divergence: Option<Divergence>,
+}
+
+impl ReplayReport {
+    /// Checks if every record was reproduced with the recorded marking.
+    pub fn is_faithful(&self) -> bool {
+        self.divergence.is_none()
+    }
+}
+
+/// Re-executes the recorded firings from the initial state, stopping at the first record that
+/// is not enabled or does not produce the recorded marking.
+pub fn replay(sm: &StateMachine, trace: &[FireRecord]) -> ReplayReport {
+    let mut state = sm.initial_vector();
+    for (index, record) in trace.iter().enumerate() {
+        let transaction = sm.transform(&state, &record.action, record.multiple);
+        let divergence = if transaction.is_err() {
+            Some(Divergence::NotEnabled {
+                index,
+                action: record.action.clone(),
+                transaction,
+            })
+        } else if transaction.output != record.output {
+            Some(Divergence::StateMismatch {
+                index,
+                action: record.action.clone(),
+                expected: record.output.clone(),
+                actual: transaction.output,
+            })
+        } else {
+            state = transaction.output;
+            None
+        };
+        if divergence.is_some() {
+            return ReplayReport {
+                replayed: index,
+                state,
+                divergence,
+            };
+        }
+    }
+    ReplayReport {
+        replayed: trace.len(),
+        state,
+        divergence: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+    use crate::simulation::simulator::Simulator;
+
+    use super::*;
+
+    fn order(p: &mut dyn FlowDsl) {
+        p.model_type("workflow");
+        p.cell("open", Option::from(1), None, 0, 0);
+        p.cell("paid", None, None, 0, 0);
+        p.cell("shipped", None, None, 0, 0);
+        p.func("pay", "default", 0, 0);
+        p.func("ship", "default", 0, 0);
+        p.arrow("open", "pay", 1);
+        p.arrow("pay", "paid", 1);
+        p.arrow("paid", "ship", 1);
+        p.arrow("ship", "shipped", 1);
+    }
+
+    fn recorded() -> Vec<FireRecord> {
+        let mut sim = Simulator::new(StateMachine::new(order));
+        sim.run(10);
+        sim.trace().to_vec()
+    }
+
+    #[test]
+    fn test_replay_is_faithful() {
+        let report = replay(&StateMachine::new(order), &recorded());
+        assert!(report.is_faithful());
+        assert_eq!(report.replayed, 2);
+        assert_eq!(report.state, vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn test_replay_against_changed_model() {
+        let changed = StateMachine::new(|p| {
+            order(p);
+            p.guard("ship", "open", 1);
+        });
+        let report = replay(&changed, &recorded());
+        assert_eq!(report.replayed, 1);
+        match report.divergence.unwrap() {
+            Divergence::NotEnabled { index, action, transaction } => {
+                assert_eq!((index, action.as_str()), (1, "ship"));
+                assert!(transaction.inhibited);
+            }
+            d => panic!("unexpected divergence {:?}", d),
+        }
+    }
+
+    #[test]
+    fn test_replay_detects_state_mismatch() {
+        let mut trace = recorded();
+        trace[0].output = vec![0, 0, 1];
+        let report = replay(&StateMachine::new(order), &trace);
+        assert_eq!(report.divergence.unwrap().index(), 0);
+        assert_eq!(report.state, vec![1, 0, 0]);
+    }
+}
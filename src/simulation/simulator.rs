@@ -0,0 +1,513 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::mpsc::{channel, Receiver, Sender};
+#[cfg(feature = "zblob")]
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::expr::Variables;
+use crate::quota::QuotaPolicy;
+use crate::simulation::policy::{ConflictPolicy, FirstEnabled};
+#[cfg(feature = "zblob")]
+use crate::simulation::snapshot::{model_cid, RestoreError, StateSnapshot};
+use crate::simulation::timers::{TimeoutEvent, Timers};
+use crate::simulation::updates::{MarkingUpdate, UpdateMode};
+use crate::simulation::watch::{MarkingView, RunReport, StopReason, Watch};
+use crate::vasm::{StateMachine, Transaction, Vasm, Vector};
+
+/// `FireRecord` is an entry in the trace of a `Simulator`, one per successful firing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FireRecord {
+    pub action: String,
+    pub multiple: i32,
+    /// The marking after the firing.
+    pub output: Vector,
+}
+
+/// `Simulator` runs a single instance of a `StateMachine`, recording a trace of the successful firings.
+pub struct Simulator {
+    pub sm: StateMachine,
+    state: Vector,
+    variables: Variables,
+    trace: Vec<FireRecord>,
+    subscribers: Vec<(UpdateMode, Sender<MarkingUpdate>)>,
+    breakpoints: BTreeSet<String>,
+    watches: Vec<Watch>,
+    policy: Box<dyn ConflictPolicy>,
+    quotas: QuotaPolicy,
+    timers: Timers,
+    /// The CID of the model, computed on first use.
+    #[cfg(feature = "zblob")]
+    model_cid: OnceLock<String>,
+}
+
+impl fmt::Debug for Simulator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Simulator")
+            .field("sm", &self.sm)
+            .field("state", &self.state)
+            .field("variables", &self.variables)
+            .field("trace", &self.trace)
+            .field("breakpoints", &self.breakpoints)
+            .field("watches", &self.watches)
+            .field("quotas", &self.quotas)
+            .field("timers", &self.timers)
+            .finish()
+    }
+}
+
+impl Simulator {
+    /// Creates a new `Simulator` in the initial state of the state machine.
+    pub fn new(sm: StateMachine) -> Self {
+        let state = sm.initial_vector();
+        let mut timers = Timers::new();
+        timers.update(&sm, "", &state, None);
+        Self {
+            sm,
+            state,
+            variables: Variables::new(),
+            trace: Vec::new(),
+            subscribers: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            watches: Vec::new(),
+            policy: Box::new(FirstEnabled),
+            quotas: QuotaPolicy::new(),
+            timers,
+            #[cfg(feature = "zblob")]
+            model_cid: OnceLock::new(),
+        }
+    }
+
+    /// Replaces the policy deciding which enabled transition fires during a run, `FirstEnabled` by default.
+    pub fn set_policy<P: ConflictPolicy + 'static>(&mut self, policy: P) {
+        self.policy = Box::new(policy);
+    }
+
+    /// Replaces the quotas limiting how many times a role may fire a transition, none by default.
+    ///
+    /// The simulator runs a single case, so `QuotaWindow::PerCase` quotas count every firing of the simulator.
+    pub fn set_quotas(&mut self, quotas: QuotaPolicy) {
+        self.quotas = quotas;
+    }
+
+    /// Returns the current marking.
+    pub fn state(&self) -> &Vector {
+        &self.state
+    }
+
+    /// Returns the variables the conditions of the transitions are evaluated over.
+    pub fn variables(&self) -> &Variables {
+        &self.variables
+    }
+
+    /// Sets a variable, carried alongside the marking.
+    pub fn set_variable(&mut self, name: &str, value: Value) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    /// Returns the current tick of the clock driving the timeouts of the transitions, see `advance`.
+    pub fn now(&self) -> u64 {
+        self.timers.now()
+    }
+
+    /// Returns the successful firings in the order they happened.
+    pub fn trace(&self) -> &[FireRecord] {
+        &self.trace
+    }
+
+    /// Subscribes to the marking updates published after each successful firing.
+    ///
+    /// Subscribers that dropped their receiver are removed on the next update.
+    pub fn subscribe(&mut self, mode: UpdateMode) -> Receiver<MarkingUpdate> {
+        let (sender, receiver) = channel();
+        self.subscribers.push((mode, sender));
+        receiver
+    }
+
+    /// Pauses runs right after the given transition fires.
+    pub fn add_breakpoint(&mut self, action: &str) {
+        self.breakpoints.insert(action.to_string());
+    }
+
+    /// Removes a breakpoint, returns false if there was none.
+    pub fn remove_breakpoint(&mut self, action: &str) -> bool {
+        self.breakpoints.remove(action)
+    }
+
+    /// Pauses runs as soon as the condition holds after a firing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pflow_metamodel::simulation::Simulator;
+    /// use pflow_metamodel::vasm::StateMachine;
+    ///
+    /// let mut sim = Simulator::new(StateMachine::new(|p| {
+    ///     p.cell("queue", None, None, 0, 0);
+    ///     p.func("arrive", "default", 0, 0);
+    ///     p.arrow("arrive", "queue", 1);
+    /// }));
+    /// sim.watch("backlog", |m| m.tokens("queue") > 10);
+    /// assert_eq!(sim.run(100).steps, 11);
+    /// ```
+    pub fn watch<F>(&mut self, name: &str, condition: F)
+    where
+        F: Fn(&MarkingView) -> bool + Send + Sync + 'static,
+    {
+        self.watches.push(Watch {
+            name: name.to_string(),
+            condition: Box::new(condition),
+        });
+    }
+
+    /// Lists the transitions enabled in the current state in label order, leaving out those whose condition
+    /// does not hold over the variables or whose role used up a quota.
+    pub fn enabled_actions(&self) -> Vec<String> {
+        let now = Instant::now();
+        let mut actions: Vec<(&String, &String)> = self
+            .sm
+            .transitions
+            .iter()
+            .filter(|(_, t)| t.condition_holds(&self.variables))
+            .map(|(action, t)| (action, &t.role))
+            .collect();
+        actions.sort();
+        actions
+            .into_iter()
+            .filter(|(a, role)| self.sm.is_enabled(&self.state, a, 1) && self.quotas.check("", role, a, now).is_ok())
+            .map(|(a, _)| a.clone())
+            .collect()
+    }
+
+    /// Fires enabled transitions chosen by the conflict policy until a breakpoint or watch pauses the run,
+    /// no transition is enabled, or `max_steps` transitions fired. Calling `run` again resumes after a pause.
+    ///
+    /// A deadlock is reported even when it is reached after exactly `max_steps` firings, and a run stops with
+    /// `StopReason::InvalidChoice` if the policy chooses an index outside the enabled transitions.
+    pub fn run(&mut self, max_steps: usize) -> RunReport {
+        let mut steps = 0;
+        let reason = loop {
+            let enabled = self.enabled_actions();
+            if enabled.is_empty() {
+                break StopReason::Deadlock;
+            }
+            if steps == max_steps {
+                break StopReason::StepLimit;
+            }
+            #[cfg(feature = "metrics")]
+            let started = std::time::Instant::now();
+            let index = self.policy.choose(&self.sm, &self.state, &enabled);
+            let Some(action) = enabled.get(index).cloned() else {
+                break StopReason::InvalidChoice {
+                    index,
+                    enabled: enabled.len(),
+                };
+            };
+            self.fire(&action, 1);
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_step(started.elapsed());
+            steps += 1;
+            if self.breakpoints.contains(&action) {
+                break StopReason::Breakpoint { action };
+            }
+            let view = MarkingView {
+                places: &self.sm.places,
+                state: &self.state,
+            };
+            if let Some(w) = self.watches.iter().find(|w| (w.condition)(&view)) {
+                break StopReason::Watch { name: w.name.clone() };
+            }
+        };
+        RunReport {
+            steps,
+            reason,
+            trace: self.trace.clone(),
+        }
+    }
+
+    /// Fires the action, moving to the resulting state and notifying subscribers if the transformation succeeds.
+    ///
+    /// The action is fired on behalf of the role of its transition, so its quotas apply, see `fire_as`.
+    /// With the `metrics` feature every firing is recorded under the model CID, see `metrics::record_transaction`.
+    pub fn fire(&mut self, action: &str, multiple: i32) -> Transaction {
+        let role = self.sm.transitions.get(action).map(|t| t.role.clone()).unwrap_or_default();
+        self.fire_as(action, multiple, &role)
+    }
+
+    /// Fires the action on behalf of the role with the variables like `StateMachine::transform_with`, rejecting
+    /// it with `TransformError::QuotaExceeded` if the role used up one of its quotas for the transition.
+    pub fn fire_as(&mut self, action: &str, multiple: i32, role: &str) -> Transaction {
+        let now = Instant::now();
+        let res = match self.quotas.check("", role, action, now) {
+            Ok(()) => self.sm.transform_with(&self.state, action, multiple, role, &self.variables),
+            Err(error) => Transaction::rejected(&self.state, role, error).with_action(&self.state, action, multiple),
+        };
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_transaction(self.model_cid(), &self.sm, action, &res);
+        if res.is_err() {
+            return res;
+        }
+        self.quotas.record("", role, action, now);
+        self.timers.update(&self.sm, "", &res.output, Some(action));
+        let previous = std::mem::replace(&mut self.state, res.output.clone());
+        self.subscribers.retain(|(mode, sender)| {
+            sender
+                .send(MarkingUpdate::new(*mode, action, &previous, &res.output))
+                .is_ok()
+        });
+        self.trace.push(FireRecord {
+            action: action.to_string(),
+            multiple,
+            output: res.output.clone(),
+        });
+        res
+    }
+
+    /// Advances the clock by the given number of ticks, one per time unit of the model.
+    ///
+    /// A transition enabled for longer than its `Timeout` times out once, firing its escalation transition
+    /// if it declares one, see `timers::Timers`.
+    pub fn advance(&mut self, ticks: u64) -> Vec<TimeoutEvent> {
+        let mut events = Vec::new();
+        for _ in 0..ticks {
+            for (case, action) in self.timers.tick() {
+                let escalate = self.sm.transitions[&action].timeout().and_then(|t| t.escalate.clone());
+                let transaction = escalate.as_deref().map(|e| self.fire(e, 1));
+                events.push(TimeoutEvent {
+                    case,
+                    action,
+                    at: self.timers.now(),
+                    escalate,
+                    transaction,
+                });
+            }
+        }
+        events
+    }
+
+    /// Takes a snapshot of the current marking, tied to the version of the model, see `snapshot::model_cid`.
+    ///
+    /// The model CID is computed once, so `sm` should not be edited after the first snapshot, restore or,
+    /// with the `metrics` feature, firing.
+    #[cfg(feature = "zblob")]
+    pub fn snapshot(&self) -> StateSnapshot {
+        StateSnapshot::new(self.model_cid(), self.state.clone())
+    }
+
+    #[cfg(feature = "zblob")]
+    fn model_cid(&self) -> &str {
+        self.model_cid.get_or_init(|| model_cid(&self.sm))
+    }
+
+    /// Moves to the marking of the snapshot, rejecting snapshots whose id does not match their content, that were
+    /// taken from another version of the model, or whose marking does not fit the places. The trace starts over
+    /// from the restored marking.
+    ///
+    /// The id is an unkeyed hash, so it catches accidental corruption but not a deliberately edited snapshot
+    /// with a recomputed id; the marking is checked against the places either way.
+    #[cfg(feature = "zblob")]
+    pub fn restore(&mut self, snapshot: &StateSnapshot) -> Result<(), RestoreError> {
+        if !snapshot.verify() {
+            return Err(RestoreError::Corrupted {
+                id: snapshot.id.clone(),
+            });
+        }
+        let expected = self.model_cid();
+        if snapshot.model_cid != expected {
+            return Err(RestoreError::ModelMismatch {
+                expected: expected.to_string(),
+                actual: snapshot.model_cid.clone(),
+            });
+        }
+        if snapshot.marking.len() != self.sm.places.len() {
+            return Err(RestoreError::DimensionMismatch {
+                expected: self.sm.places.len(),
+                actual: snapshot.marking.len(),
+            });
+        }
+        let invalid = snapshot
+            .marking
+            .iter()
+            .enumerate()
+            .position(|(i, &tokens)| tokens < 0 || !self.sm.place_capacity(i).allows(tokens));
+        if let Some(offset) = invalid {
+            return Err(RestoreError::InvalidMarking {
+                place: self.sm.places[offset].clone(),
+                tokens: snapshot.marking[offset],
+            });
+        }
+        self.state = snapshot.marking.clone();
+        self.trace.clear();
+        self.timers.forget("");
+        self.timers.update(&self.sm, "", &self.state, None);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+    use crate::quota::QuotaWindow;
+    use crate::simulation::policy::Priority;
+    use crate::simulation::updates::MarkingChange;
+    use crate::vasm::TransformError;
+
+    use super::*;
+
+    fn counter(p: &mut dyn FlowDsl) {
+        p.cell("foo", Option::from(1), None, 0, 0);
+        p.cell("bar", None, None, 0, 0);
+        p.cell("baz", Option::from(5), None, 0, 0);
+        p.func("move", "default", 0, 0);
+        p.arrow("foo", "move", 1);
+        p.arrow("move", "bar", 1);
+    }
+
+    #[test]
+    fn test_fire_records_trace() {
+        let mut sim = Simulator::new(StateMachine::new(counter));
+        assert!(sim.fire("move", 1).is_ok());
+        assert!(sim.fire("move", 1).is_err());
+        assert_eq!(sim.state(), &vec![0, 1, 5]);
+        assert_eq!(sim.trace().len(), 1);
+        assert_eq!(sim.trace()[0].action, "move");
+    }
+
+    fn pipeline(p: &mut dyn FlowDsl) {
+        p.cell("source", Option::from(3), None, 0, 0);
+        p.cell("queue", None, None, 0, 0);
+        p.cell("done", None, None, 0, 0);
+        p.func("arrive", "default", 0, 0);
+        p.func("serve", "default", 0, 0);
+        p.arrow("source", "arrive", 1);
+        p.arrow("arrive", "queue", 1);
+        p.arrow("queue", "serve", 1);
+        p.arrow("serve", "done", 1);
+    }
+
+    #[test]
+    fn test_run_until_breakpoint_and_resume() {
+        let mut sim = Simulator::new(StateMachine::new(pipeline));
+        sim.add_breakpoint("serve");
+        let report = sim.run(100);
+        assert!(report.is_paused());
+        assert_eq!(report.reason, StopReason::Breakpoint { action: "serve".to_string() });
+        assert_eq!(report.steps, 4);
+        assert_eq!(report.trace.last().unwrap().output, vec![0, 2, 1]);
+
+        assert!(sim.remove_breakpoint("serve"));
+        let report = sim.run(100);
+        assert_eq!(report.reason, StopReason::Deadlock);
+        assert_eq!(report.steps, 2);
+        assert_eq!(report.trace.len(), 6);
+    }
+
+    #[test]
+    fn test_run_until_watch() {
+        let mut sim = Simulator::new(StateMachine::new(pipeline));
+        sim.watch("backlog", |m| m.tokens("queue") > 1);
+        let report = sim.run(100);
+        assert_eq!(report.reason, StopReason::Watch { name: "backlog".to_string() });
+        assert_eq!(sim.state(), &vec![1, 2, 0]);
+
+        sim.watches.clear();
+        assert_eq!(sim.run(1).reason, StopReason::StepLimit);
+    }
+
+    #[test]
+    fn test_run_with_policy() {
+        let mut sim = Simulator::new(StateMachine::new(pipeline));
+        sim.set_policy(Priority::new(&[("serve", 1)]));
+        sim.run(100);
+        let actions: Vec<&str> = sim.trace().iter().map(|r| r.action.as_str()).collect();
+        assert_eq!(actions, vec!["arrive", "serve", "arrive", "serve", "arrive", "serve"]);
+
+        let mut sim = Simulator::new(StateMachine::new(pipeline));
+        sim.set_policy(|_: &StateMachine, _: &Vector, enabled: &[String]| enabled.len());
+        let report = sim.run(10);
+        assert_eq!(report.reason, StopReason::InvalidChoice { index: 1, enabled: 1 });
+        assert_eq!(report.steps, 0);
+    }
+
+    #[test]
+    fn test_deadlock_at_step_limit() {
+        let mut sim = Simulator::new(StateMachine::new(|p| {
+            p.cell("a", Some(1), None, 0, 0);
+            p.func("consume", "default", 0, 0);
+            p.arrow("a", "consume", 1);
+        }));
+        let report = sim.run(1);
+        assert_eq!(report.steps, 1);
+        assert_eq!(report.reason, StopReason::Deadlock);
+    }
+
+    #[test]
+    fn test_quotas_reject_and_stop_runs() {
+        let mut sim = Simulator::new(StateMachine::new(pipeline));
+        sim.set_quotas(QuotaPolicy::new().with_quota("default", "arrive", 2, QuotaWindow::PerCase));
+        assert!(sim.fire("arrive", 1).is_ok());
+        assert!(sim.fire_as("arrive", 1, "guest").error.is_some_and(|e| matches!(e, TransformError::RoleMismatch { .. })));
+        assert!(sim.fire_as("arrive", 1, "default").is_ok());
+        let rejected = sim.fire("arrive", 1);
+        assert_eq!(
+            rejected.error,
+            Some(TransformError::QuotaExceeded {
+                role: "default".to_string(),
+                action: "arrive".to_string(),
+                limit: 2
+            })
+        );
+        assert_eq!(sim.state(), &vec![1, 2, 0]);
+        assert_eq!(sim.enabled_actions(), vec!["serve"]);
+        assert_eq!(sim.run(100).reason, StopReason::Deadlock);
+        assert_eq!(sim.state(), &vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn test_conditions_over_variables() {
+        let mut sim = Simulator::new(StateMachine::new(|p| {
+            pipeline(p);
+            p.condition("serve", "staff > 0 && closed != true");
+        }));
+        sim.set_variable("staff", serde_json::json!(0));
+        assert_eq!(sim.run(100).reason, StopReason::Deadlock);
+        assert_eq!(sim.state(), &vec![0, 3, 0]);
+        assert!(sim.fire("serve", 1).inhibited);
+
+        sim.set_variable("staff", serde_json::json!(2));
+        assert_eq!(sim.enabled_actions(), vec!["serve"]);
+        assert!(sim.fire("serve", 1).is_ok());
+        sim.set_variable("closed", serde_json::json!(true));
+        assert!(sim.enabled_actions().is_empty());
+        assert_eq!(sim.variables().len(), 2);
+        assert!(sim.sm.transform(sim.state(), "serve", 1).is_ok());
+    }
+
+    #[test]
+    fn test_subscribers_receive_updates() {
+        let mut sim = Simulator::new(StateMachine::new(counter));
+        let full = sim.subscribe(UpdateMode::Full);
+        let delta = sim.subscribe(UpdateMode::Delta);
+        drop(sim.subscribe(UpdateMode::Delta));
+        sim.fire("move", 1);
+
+        assert_eq!(
+            full.try_recv().unwrap(),
+            MarkingUpdate::Full { action: "move".to_string(), state: vec![0, 1, 5] }
+        );
+        match delta.try_recv().unwrap() {
+            MarkingUpdate::Delta { changes, .. } => assert_eq!(
+                changes,
+                vec![
+                    MarkingChange { place: 0, old: 1, new: 0 },
+                    MarkingChange { place: 1, old: 0, new: 1 },
+                ]
+            ),
+            update => panic!("expected delta, got {:?}", update),
+        }
+        assert_eq!(sim.subscribers.len(), 2);
+    }
+}
@@ -0,0 +1,169 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::oid::Oid;
+use crate::vasm::{StateMachine, Vector};
+
+/// `StateSnapshot` is the marking of a running instance, tied to the model it was taken from.
+///
+/// The id is the CID of the model CID and the marking, so equal states of the same model share an id
+/// and a snapshot corrupted after it was taken no longer matches its id. The id is not keyed, anyone
+/// editing the marking can recompute it, so it is no protection against tampering.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateSnapshot {
+    pub id: String,
+    pub model_cid: String,
+    pub marking: Vector,
+}
+
+impl StateSnapshot {
+    /// Creates a snapshot of the marking of the model with the given CID.
+    pub fn new(model_cid: &str, marking: Vector) -> Self {
+        Self {
+            id: state_id(model_cid, &marking),
+            model_cid: model_cid.to_string(),
+            marking,
+        }
+    }
+
+    /// Checks if the id still matches the model CID and the marking, see the note on tampering above.
+    pub fn verify(&self) -> bool {
+        self.id == state_id(&self.model_cid, &self.marking)
+    }
+}
+
+/// Returns the content-addressed id of the marking of the model with the given CID.
+pub fn state_id(model_cid: &str, marking: &Vector) -> String {
+    let content = serde_json::json!({ "modelCid": model_cid, "marking": marking });
+    Oid::new(content.to_string().as_bytes()).unwrap().to_string()
+}
+
+/// Returns the CID identifying the version of the state machine snapshots are tied to.
+///
+/// It is the CID of the zblob of `StateMachine::to_model`, which has no layout, so moving the
+/// nodes of a net in the editor does not invalidate the snapshots of its running instances.
+/// Attributes edited into the state machine that are not integers are hashed as text.
+pub fn model_cid(sm: &StateMachine) -> String {
+    let mut model = sm.to_model();
+    model.stringify_non_integer_attributes();
+    model.to_zblob().ipfs_cid
+}
+
+/// `RestoreError` describes why a snapshot could not be restored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RestoreError {
+    /// The id of the snapshot does not match its content.
+    Corrupted { id: String },
+    /// The snapshot was taken from another version of the model.
+    ModelMismatch { expected: String, actual: String },
+    /// The marking does not have one entry per place of the state machine.
+    DimensionMismatch { expected: usize, actual: usize },
+    /// The marking puts a negative number of tokens, or more than its capacity, on a place.
+    InvalidMarking { place: String, tokens: i32 },
+}
+
+impl fmt::Display for RestoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestoreError::Corrupted { id } => write!(f, "snapshot {} does not match its content", id),
+            RestoreError::ModelMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "snapshot of model {} cannot be restored into model {}",
+                    actual, expected
+                )
+            }
+            RestoreError::DimensionMismatch { expected, actual } => {
+                write!(f, "expected a marking of {} places, got {}", expected, actual)
+            }
+            RestoreError::InvalidMarking { place, tokens } => {
+                write!(f, "place {} cannot hold {} tokens", place, tokens)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RestoreError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+    use crate::simulation::Simulator;
+
+    use super::*;
+
+    fn approval(p: &mut dyn FlowDsl) {
+        p.model_type("workflow");
+        p.cell("submitted", Option::from(1), Option::from(1), 0, 0);
+        p.cell("approved", None, Option::from(1), 0, 0);
+        p.func("approve", "manager", 0, 0);
+        p.arrow("submitted", "approve", 1);
+        p.arrow("approve", "approved", 1);
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut sim = Simulator::new(StateMachine::new(approval));
+        let initial = sim.snapshot();
+        assert!(sim.fire("approve", 1).is_ok());
+        let snapshot = sim.snapshot();
+        assert!(snapshot.verify());
+        assert_eq!(snapshot.model_cid, initial.model_cid);
+        assert_ne!(snapshot.id, initial.id);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(json.contains("\"modelCid\""));
+        let mut resumed = Simulator::new(StateMachine::new(approval));
+        resumed.restore(&serde_json::from_str(&json).unwrap()).unwrap();
+        assert_eq!(resumed.state(), &vec![0, 1]);
+        assert_eq!(resumed.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn test_restore_errors() {
+        let mut sim = Simulator::new(StateMachine::new(approval));
+        let mut snapshot = sim.snapshot();
+        snapshot.marking = vec![5, 5];
+        assert_eq!(
+            sim.restore(&snapshot),
+            Err(RestoreError::Corrupted {
+                id: snapshot.id.clone()
+            })
+        );
+
+        let other = StateSnapshot::new("zb2other", vec![0, 1]);
+        assert_eq!(
+            sim.restore(&other),
+            Err(RestoreError::ModelMismatch {
+                expected: model_cid(&sim.sm),
+                actual: "zb2other".to_string()
+            })
+        );
+
+        let short = StateSnapshot::new(&model_cid(&sim.sm), vec![1]);
+        assert_eq!(
+            sim.restore(&short),
+            Err(RestoreError::DimensionMismatch { expected: 2, actual: 1 })
+        );
+
+        let negative = StateSnapshot::new(&model_cid(&sim.sm), vec![-1, 0]);
+        assert_eq!(
+            sim.restore(&negative),
+            Err(RestoreError::InvalidMarking {
+                place: "submitted".to_string(),
+                tokens: -1
+            })
+        );
+        let overflowing = StateSnapshot::new(&model_cid(&sim.sm), vec![0, 2]);
+        assert_eq!(
+            sim.restore(&overflowing),
+            Err(RestoreError::InvalidMarking {
+                place: "approved".to_string(),
+                tokens: 2
+            })
+        );
+        assert_eq!(sim.state(), &vec![1, 0]);
+    }
+}
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::vasm::{StateMachine, Transaction, Vasm, Vector};
+
+/// `Command` asks a driven state machine to fire an action.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Command {
+    pub action: String,
+    pub multiple: i32,
+    /// The role firing the action, checked with `StateMachine::transform_as`, or None to skip the check.
+    #[serde(default)]
+    pub role: Option<String>,
+}
+
+impl Command {
+    /// Fires the action once without a role check.
+    pub fn fire(action: &str) -> Self {
+        Self {
+            action: action.to_string(),
+            multiple: 1,
+            role: None,
+        }
+    }
+
+    /// Fires the action once on behalf of the role.
+    pub fn fire_as(action: &str, role: &str) -> Self {
+        Self {
+            role: Some(role.to_string()),
+            ..Self::fire(action)
+        }
+    }
+}
+
+/// `Driver` applies commands to a marking, keeping the output of every successful firing.
+#[derive(Debug, Clone)]
+pub struct Driver {
+    sm: Arc<StateMachine>,
+    state: Vector,
+}
+
+impl Driver {
+    /// Starts from the initial marking of the state machine.
+    pub fn new(sm: Arc<StateMachine>) -> Self {
+        let state = sm.initial_vector();
+        Self { sm, state }
+    }
+
+    /// Starts from the given marking.
+    pub fn with_state(sm: Arc<StateMachine>, state: Vector) -> Self {
+        Self { sm, state }
+    }
+
+    pub fn state(&self) -> &Vector {
+        &self.state
+    }
+
+    /// Fires the command, the marking only changes if the transaction is ok.
+    pub fn apply(&mut self, command: &Command) -> Transaction {
+        let tx = match &command.role {
+            Some(role) => self
+                .sm
+                .transform_as(&self.state, &command.action, command.multiple, role),
+            None => self.sm.transform(&self.state, &command.action, command.multiple),
+        };
+        if tx.is_ok() {
+            self.state.clone_from(&tx.output);
+        }
+        tx
+    }
+
+    /// Turns a stream of commands into the stream of their transactions.
+    ///
+    /// A command is only read from `commands` when its transaction is polled, so a slow consumer
+    /// holds back the producer of the commands.
+    pub fn drive<S>(mut self, commands: S) -> impl Stream<Item = Transaction>
+    where
+        S: Stream<Item = Command>,
+    {
+        commands.map(move |command| self.apply(&command))
+    }
+
+    /// Runs the driver on a task of the tokio runtime, returns the sender of its commands and the stream
+    /// of their transactions. Both channels hold at most `buffer` messages, so a client that stops reading
+    /// transactions, such as a stalled websocket, eventually blocks the senders of commands.
+    ///
+    /// The task ends when every command sender is dropped or the transaction stream is dropped.
+    pub fn spawn(self, buffer: usize) -> (mpsc::Sender<Command>, ReceiverStream<Transaction>) {
+        let (command_tx, command_rx) = mpsc::channel(buffer);
+        let (transaction_tx, transaction_rx) = mpsc::channel(buffer);
+        let transactions = self.drive(ReceiverStream::new(command_rx));
+        tokio::spawn(async move {
+            tokio::pin!(transactions);
+            while let Some(tx) = transactions.next().await {
+                if transaction_tx.send(tx).await.is_err() {
+                    break;
+                }
+            }
+        });
+        (command_tx, ReceiverStream::new(transaction_rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::petri_net::PetriNet;
+    use crate::vasm::TransformError;
+
+    use super::*;
+
+    fn machine() -> Arc<StateMachine> {
+        let mut net = PetriNet::new();
+        net.add_place("ready", 0, Some(2), None, 0, 0);
+        net.add_place("done", 1, None, None, 0, 0);
+        net.add_transition("work", "worker", 0, 0);
+        net.add_arc("ready", "work", None, None, None, None, None);
+        net.add_arc("work", "done", None, None, None, None, None);
+        Arc::new(StateMachine::from_model(&mut net))
+    }
+
+    #[tokio::test]
+    async fn test_drive() {
+        let commands = tokio_stream::iter(vec![
+            Command::fire("work"),
+            Command::fire_as("work", "guest"),
+            Command::fire_as("work", "worker"),
+            Command::fire("work"),
+        ]);
+        let transactions: Vec<Transaction> = Driver::new(machine()).drive(commands).collect().await;
+        let ok: Vec<bool> = transactions.iter().map(|tx| tx.ok).collect();
+        assert_eq!(ok, vec![true, false, true, false]);
+        assert_eq!(transactions[2].output, vec![0, 2]);
+        let mismatch = TransformError::RoleMismatch {
+            expected: "worker".to_string(),
+            actual: "guest".to_string(),
+        };
+        assert_eq!(transactions[1].error, Some(mismatch));
+        assert!(transactions[3].underflow);
+    }
+
+    #[tokio::test]
+    async fn test_spawn_with_backpressure() {
+        let (commands, mut transactions) = Driver::new(machine()).spawn(1);
+        let producer = tokio::spawn(async move {
+            for _ in 0..4 {
+                commands.send(Command::fire("work")).await.unwrap();
+            }
+        });
+        let mut received = Vec::new();
+        while let Some(tx) = transactions.next().await {
+            received.push(tx);
+        }
+        producer.await.unwrap();
+        let ok: Vec<bool> = received.iter().map(|tx| tx.ok).collect();
+        assert_eq!(ok, vec![true, true, false, false]);
+        assert_eq!(received[1].output, vec![0, 2]);
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cases::CaseId;
+use crate::vasm::{StateMachine, Transaction, Vector};
+
+/// The number of slots of the wheel of `Timers`, deadlines further away take more than one turn.
+pub const DEFAULT_WHEEL_SLOTS: usize = 256;
+
+/// `TimerWheel` keeps items in the slot of the tick they are due at modulo the number of slots,
+/// so advancing the clock by one tick only visits a single slot however many timers are pending.
+#[derive(Debug, Clone)]
+pub struct TimerWheel<T> {
+    slots: Vec<Vec<(u64, T)>>,
+    now: u64,
+}
+
+impl<T> TimerWheel<T> {
+    /// Creates a wheel at tick zero with the given number of slots, at least one.
+    pub fn new(slots: usize) -> Self {
+        Self {
+            slots: (0..slots.max(1)).map(|_| Vec::new()).collect(),
+            now: 0,
+        }
+    }
+
+    /// Returns the current tick.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Schedules the item at the given tick, an item scheduled in the past is due on the next tick.
+    pub fn schedule(&mut self, at: u64, item: T) {
+        let at = at.max(self.now + 1);
+        let slot = (at % self.slots.len() as u64) as usize;
+        self.slots[slot].push((at, item));
+    }
+
+    /// Advances the clock by one tick and returns the items due, with the tick they were scheduled at.
+    pub fn tick(&mut self) -> Vec<(u64, T)> {
+        self.now += 1;
+        let slot = (self.now % self.slots.len() as u64) as usize;
+        let (due, pending) = std::mem::take(&mut self.slots[slot])
+            .into_iter()
+            .partition(|(at, _)| *at <= self.now);
+        self.slots[slot] = pending;
+        due
+    }
+}
+
+/// `Timers` tracks the deadlines of the enabled transitions declaring a `Timeout`, for one or more
+/// cases of a state machine. Time is counted in the time units of the model, one per tick.
+///
+/// A deadline is set when a transition becomes enabled and cleared when it fires or is disabled.
+/// A transition times out at most once while it stays enabled.
+#[derive(Debug, Clone)]
+pub struct Timers {
+    wheel: TimerWheel<(CaseId, String)>,
+    deadlines: BTreeMap<(CaseId, String), u64>,
+    expired: BTreeSet<(CaseId, String)>,
+}
+
+impl Default for Timers {
+    fn default() -> Self {
+        Self {
+            wheel: TimerWheel::new(DEFAULT_WHEEL_SLOTS),
+            deadlines: BTreeMap::new(),
+            expired: BTreeSet::new(),
+        }
+    }
+}
+
+impl Timers {
+    /// Creates timers at tick zero without any deadline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current tick.
+    pub fn now(&self) -> u64 {
+        self.wheel.now()
+    }
+
+    /// Returns the tick at which the transition of the case times out, if it is pending.
+    pub fn deadline(&self, case: &str, action: &str) -> Option<u64> {
+        self.deadlines.get(&(case.to_string(), action.to_string())).copied()
+    }
+
+    /// Sets or clears the deadlines of the case after it moved to `state`, by firing `fired` if any.
+    pub fn update(&mut self, sm: &StateMachine, case: &str, state: &Vector, fired: Option<&str>) {
+        for (action, transition) in &sm.transitions {
+            let Some(timeout) = transition.timeout() else {
+                continue;
+            };
+            let key = (case.to_string(), action.clone());
+            if fired == Some(action.as_str()) || !sm.is_enabled(state, action, 1) {
+                self.deadlines.remove(&key);
+                self.expired.remove(&key);
+            } else if !self.deadlines.contains_key(&key) && !self.expired.contains(&key) {
+                // The wheel moves a deadline due now to the next tick, the deadline must match it.
+                let at = self.now() + (timeout.after.max(0) as u64).max(1);
+                self.deadlines.insert(key.clone(), at);
+                self.wheel.schedule(at, key);
+            }
+        }
+    }
+
+    /// Clears the deadlines of a case, once it is removed.
+    pub fn forget(&mut self, case: &str) {
+        self.deadlines.retain(|(c, _), _| c != case);
+        self.expired.retain(|(c, _)| c != case);
+    }
+
+    /// Advances the clock by one tick and returns the (case, action) pairs timing out, in order.
+    pub fn tick(&mut self) -> Vec<(CaseId, String)> {
+        let mut due: Vec<(CaseId, String)> = self
+            .wheel
+            .tick()
+            .into_iter()
+            .filter(|(at, key)| self.deadlines.get(key) == Some(at))
+            .map(|(_, key)| key)
+            .collect();
+        // A deadline cleared and set again within a tick leaves two identical entries in the wheel.
+        due.sort();
+        due.dedup();
+        for key in &due {
+            self.deadlines.remove(key);
+            self.expired.insert(key.clone());
+        }
+        due
+    }
+}
+
+/// `TimeoutEvent` reports a transition that stayed enabled past its deadline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeoutEvent {
+    /// The case of the transition, empty for a `Simulator`.
+    pub case: CaseId,
+    pub action: String,
+    /// The tick at which the transition timed out.
+    pub at: u64,
+    /// The escalation transition declared by the timeout, if any.
+    pub escalate: Option<String>,
+    /// The transaction of the escalation transition fired automatically.
+    pub transaction: Option<Transaction>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+    use crate::simulation::Simulator;
+    use crate::vasm::Vasm;
+
+    use super::*;
+
+    fn ticket(p: &mut dyn FlowDsl) {
+        p.model_type("workflow");
+        p.cell("open", Option::from(1), Option::from(1), 0, 0);
+        p.cell("escalated", None, Option::from(1), 0, 0);
+        p.cell("resolved", None, Option::from(1), 0, 0);
+        p.func("resolve", "agent", 0, 0);
+        p.func("escalate", "system", 0, 0);
+        p.func("resolve_escalated", "manager", 0, 0);
+        p.arrow("open", "resolve", 1);
+        p.arrow("resolve", "resolved", 1);
+        p.arrow("open", "escalate", 1);
+        p.arrow("escalate", "escalated", 1);
+        p.arrow("escalated", "resolve_escalated", 1);
+        p.arrow("resolve_escalated", "resolved", 1);
+        p.timeout("resolve", 10, Some("escalate"));
+        p.timeout("resolve_escalated", 5, None);
+    }
+
+    #[test]
+    fn test_timer_wheel() {
+        let mut wheel = TimerWheel::new(4);
+        wheel.schedule(6, "late");
+        wheel.schedule(2, "early");
+        wheel.schedule(0, "overdue");
+        assert_eq!(wheel.tick(), vec![(1, "overdue")]);
+        assert_eq!(wheel.tick(), vec![(2, "early")]);
+        for _ in 3..6 {
+            assert!(wheel.tick().is_empty());
+        }
+        assert_eq!(wheel.tick(), vec![(6, "late")]);
+        assert_eq!(wheel.now(), 6);
+    }
+
+    #[test]
+    fn test_escalation() {
+        let mut sim = Simulator::new(StateMachine::new(ticket));
+        assert!(sim.advance(9).is_empty());
+        let events = sim.advance(1);
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].action.as_str(), events[0].at), ("resolve", 10));
+        assert_eq!(events[0].escalate.as_deref(), Some("escalate"));
+        assert!(events[0].transaction.as_ref().unwrap().is_ok());
+        assert_eq!(sim.state(), &vec![0, 1, 0]);
+
+        let events = sim.advance(100);
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].action.as_str(), events[0].at), ("resolve_escalated", 15));
+        assert!(events[0].transaction.is_none());
+        assert_eq!(sim.now(), 110);
+    }
+
+    #[test]
+    fn test_immediate_timeout() {
+        let mut sim = Simulator::new(StateMachine::new(|p| {
+            ticket(p);
+            p.timeout("resolve", 0, Some("escalate"));
+        }));
+        let events = sim.advance(1);
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].action.as_str(), events[0].at), ("resolve", 1));
+        assert_eq!(sim.state(), &vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn test_rearmed_deadline_times_out_once() {
+        let sm = StateMachine::new(ticket);
+        let mut timers = Timers::new();
+        let open = sm.initial_vector();
+        timers.update(&sm, "case", &open, None);
+        timers.update(&sm, "case", &vec![0, 0, 0], None);
+        timers.update(&sm, "case", &open, None);
+        assert_eq!(timers.deadline("case", "resolve"), Some(10));
+        for _ in 1..10 {
+            assert!(timers.tick().is_empty());
+        }
+        assert_eq!(timers.tick(), vec![("case".to_string(), "resolve".to_string())]);
+    }
+
+    #[test]
+    fn test_firing_clears_deadline() {
+        let mut sim = Simulator::new(StateMachine::new(ticket));
+        sim.advance(5);
+        assert!(sim.fire("resolve", 1).is_ok());
+        assert!(sim.advance(100).is_empty());
+        assert_eq!(sim.trace().len(), 1);
+    }
+}
@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+
+use crate::vasm::Vector;
+
+/// `UpdateMode` selects how marking changes are published to a subscriber.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdateMode {
+    /// Every update carries the complete marking.
+    Full,
+    /// Every update only carries the places whose token count changed.
+    Delta,
+}
+
+/// `MarkingChange` records the token count of a single place before and after a firing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkingChange {
+    /// The offset of the place in the state vector.
+    pub place: usize,
+    pub old: i32,
+    pub new: i32,
+}
+
+/// `MarkingUpdate` is published to subscribers after each successful firing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum MarkingUpdate {
+    /// The complete marking after `action` fired.
+    Full { action: String, state: Vector },
+    /// The places changed by `action`.
+    Delta { action: String, changes: Vec<MarkingChange> },
+}
+
+impl MarkingUpdate {
+    /// Creates the update for the given mode from the markings before and after the firing.
+    pub fn new(mode: UpdateMode, action: &str, old: &Vector, new: &Vector) -> Self {
+        match mode {
+            UpdateMode::Full => MarkingUpdate::Full {
+                action: action.to_string(),
+                state: new.clone(),
+            },
+            UpdateMode::Delta => MarkingUpdate::Delta {
+                action: action.to_string(),
+                changes: marking_delta(old, new),
+            },
+        }
+    }
+
+    /// Applies the update to a marking kept by the subscriber.
+    pub fn apply(&self, state: &mut Vector) {
+        match self {
+            MarkingUpdate::Full { state: s, .. } => state.clone_from(s),
+            MarkingUpdate::Delta { changes, .. } => {
+                changes.iter().for_each(|c| state[c.place] = c.new);
+            }
+        }
+    }
+}
+
+/// Lists the places whose token count differs between two markings of the same length.
+pub fn marking_delta(old: &Vector, new: &Vector) -> Vec<MarkingChange> {
+    old.iter()
+        .zip(new)
+        .enumerate()
+        .filter(|(_, (o, n))| o != n)
+        .map(|(place, (old, new))| MarkingChange {
+            place,
+            old: *old,
+            new: *new,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_round_trip() {
+        let old = vec![1, 0, 4, 2];
+        let new = vec![0, 1, 4, 2];
+        let update = MarkingUpdate::new(UpdateMode::Delta, "move", &old, &new);
+        assert_eq!(
+            update,
+            MarkingUpdate::Delta {
+                action: "move".to_string(),
+                changes: vec![
+                    MarkingChange { place: 0, old: 1, new: 0 },
+                    MarkingChange { place: 1, old: 0, new: 1 },
+                ],
+            }
+        );
+        let mut state = old.clone();
+        update.apply(&mut state);
+        assert_eq!(state, new);
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::simulation::simulator::FireRecord;
+use crate::vasm::Vector;
+
+/// `MarkingView` gives watch conditions access to token counts by place label.
+pub struct MarkingView<'a> {
+    pub places: &'a [String],
+    pub state: &'a Vector,
+}
+
+impl<'a> MarkingView<'a> {
+    /// Returns the number of tokens in the place, or zero if there is no such place.
+    pub fn tokens(&self, place: &str) -> i32 {
+        self.places
+            .iter()
+            .position(|p| p == place)
+            .and_then(|i| self.state.get(i))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// WatchCondition is a type alias for a predicate over the marking of a simulation.
+pub type WatchCondition = Box<dyn Fn(&MarkingView) -> bool + Send + Sync>;
+
+/// `Watch` is a named condition that pauses a run as soon as it holds after a firing.
+pub struct Watch {
+    pub name: String,
+    pub condition: WatchCondition,
+}
+
+impl fmt::Debug for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watch").field("name", &self.name).finish()
+    }
+}
+
+/// `StopReason` tells why a simulation run stopped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum StopReason {
+    /// A transition with a breakpoint fired.
+    Breakpoint { action: String },
+    /// A watch condition held after a firing.
+    Watch { name: String },
+    /// No transition was enabled.
+    Deadlock,
+    /// The maximum number of steps was fired.
+    StepLimit,
+    /// The conflict policy chose an index outside the enabled transitions.
+    InvalidChoice { index: usize, enabled: usize },
+}
+
+/// `RunReport` describes how a simulation run ended.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    /// The number of firings during the run.
+    pub steps: usize,
+    pub reason: StopReason,
+    /// The complete trace of the simulator when the run stopped.
+    pub trace: Vec<FireRecord>,
+}
+
+impl RunReport {
+    /// Checks if the run was paused by a breakpoint or a watch.
+    pub fn is_paused(&self) -> bool {
+        matches!(self.reason, StopReason::Breakpoint { .. } | StopReason::Watch { .. })
+    }
+}
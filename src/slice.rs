@@ -0,0 +1,173 @@
+use std::collections::BTreeSet;
+
+use crate::analysis::NetStructure;
+use crate::petri_net::PetriNet;
+
+/// `SliceDirection` decides whether a slice follows the causes or the effects of its seeds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SliceDirection {
+    /// Keeps the nodes that can change the marking of the seeds: producers and consumers of
+    /// each place, and the input and guard places of each transition.
+    #[default]
+    Backward,
+    /// Keeps the nodes whose marking or enabling the seeds can change: consumers and guarded
+    /// transitions of each place, and the output places of each transition.
+    Forward,
+}
+
+/// `SliceCriterion` selects the seed nodes of a slice by label or by transition role.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SliceCriterion {
+    pub places: Vec<String>,
+    pub transitions: Vec<String>,
+    pub roles: Vec<String>,
+    pub direction: SliceDirection,
+}
+
+impl SliceCriterion {
+    /// Creates an empty backward criterion.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a seed place.
+    pub fn place(mut self, label: &str) -> Self {
+        self.places.push(label.to_string());
+        self
+    }
+
+    /// Adds a seed transition.
+    pub fn transition(mut self, label: &str) -> Self {
+        self.transitions.push(label.to_string());
+        self
+    }
+
+    /// Adds every transition with the given role as a seed.
+    pub fn role(mut self, role: &str) -> Self {
+        self.roles.push(role.to_string());
+        self
+    }
+
+    /// Sets the direction of the slice.
+    pub fn direction(mut self, direction: SliceDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+}
+
+/// Lists the places attached to `node` by an inhibitor or read arc, or the transitions if `node` is a place.
+fn guard_neighbours<'a>(net: &'a PetriNet, node: &'a str) -> impl Iterator<Item = &'a String> + 'a {
+    net.arcs.iter().filter(|a| a.inhibit.unwrap_or(false)).filter_map(move |a| {
+        if a.source == node {
+            Some(&a.target)
+        } else if a.target == node {
+            Some(&a.source)
+        } else {
+            None
+        }
+    })
+}
+
+/// Slices the net, see `PetriNet::slice`.
+pub fn slice(net: &PetriNet, criterion: &SliceCriterion) -> PetriNet {
+    let s = NetStructure::from_net(net);
+    let mut stack: Vec<String> = criterion
+        .places
+        .iter()
+        .chain(criterion.transitions.iter())
+        .filter(|l| net.places.contains_key(*l) || net.transitions.contains_key(*l))
+        .cloned()
+        .collect();
+    stack.extend(
+        net.transitions
+            .iter()
+            .filter(|(_, t)| criterion.roles.iter().any(|r| Some(r) == t.role.as_ref()))
+            .map(|(l, _)| l.clone()),
+    );
+
+    let mut kept: BTreeSet<String> = BTreeSet::new();
+    while let Some(node) = stack.pop() {
+        if !kept.insert(node.clone()) {
+            continue;
+        }
+        let is_place = s.places.contains(&node);
+        let next: Vec<&String> = match (criterion.direction, is_place) {
+            (SliceDirection::Backward, true) => s.inputs[&node].keys().chain(s.outputs[&node].keys()).collect(),
+            (SliceDirection::Backward, false) => s.pre[&node].keys().chain(guard_neighbours(net, &node)).collect(),
+            (SliceDirection::Forward, true) => s.outputs[&node].keys().chain(guard_neighbours(net, &node)).collect(),
+            (SliceDirection::Forward, false) => s.post[&node].keys().collect(),
+        };
+        stack.extend(next.into_iter().filter(|n| !kept.contains(*n)).cloned());
+    }
+
+    let mut sliced = net.clone();
+    sliced.places.retain(|l, _| kept.contains(l));
+    sliced.transitions.retain(|l, _| kept.contains(l));
+    sliced.arcs.retain(|a| kept.contains(&a.source) && kept.contains(&a.target));
+    sliced.reindex_offsets();
+    sliced
+}
+
+impl PetriNet {
+    /// Returns the subnet relevant to the seeds of `criterion`, with only the arcs between kept nodes.
+    ///
+    /// Unknown seed labels are ignored, the remaining places keep their relative order.
+    pub fn slice(&self, criterion: &SliceCriterion) -> PetriNet {
+        slice(self, criterion)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline() -> PetriNet {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("order", Option::from(1), None, 0, 0);
+            p.cell("paid", None, None, 0, 0);
+            p.cell("shipped", None, None, 0, 0);
+            p.cell("hold", None, None, 0, 0);
+            p.cell("audit", None, None, 0, 0);
+            p.func("pay", "customer", 0, 0);
+            p.func("ship", "warehouse", 0, 0);
+            p.func("review", "auditor", 0, 0);
+            p.arrow("order", "pay", 1);
+            p.arrow("pay", "paid", 1);
+            p.arrow("paid", "ship", 1);
+            p.arrow("ship", "shipped", 1);
+            p.guard("hold", "ship", 1);
+            p.arrow("review", "audit", 1);
+        });
+        net
+    }
+
+    fn labels(net: &PetriNet) -> Vec<String> {
+        let mut labels: Vec<String> = net.places.keys().chain(net.transitions.keys()).cloned().collect();
+        labels.sort();
+        labels
+    }
+
+    #[test]
+    fn test_backward_slice_of_place() {
+        let net = pipeline().slice(&SliceCriterion::new().place("shipped"));
+        assert_eq!(labels(&net), vec!["hold", "order", "paid", "pay", "ship", "shipped"]);
+        assert_eq!(net.arcs.len(), 5);
+        assert_eq!(net.places["paid"].offset, 1);
+        assert_eq!(net.places["hold"].offset, 3);
+    }
+
+    #[test]
+    fn test_forward_slice_of_place() {
+        let criterion = SliceCriterion::new().place("hold").direction(SliceDirection::Forward);
+        assert_eq!(labels(&pipeline().slice(&criterion)), vec!["hold", "ship", "shipped"]);
+    }
+
+    #[test]
+    fn test_slice_by_role() {
+        assert_eq!(labels(&pipeline().slice(&SliceCriterion::new().role("auditor"))), vec!["review"]);
+        let net = pipeline().slice(&SliceCriterion::new().role("auditor").direction(SliceDirection::Forward));
+        assert_eq!(labels(&net), vec!["audit", "review"]);
+        assert_eq!(net.places["audit"].offset, 0);
+    }
+}
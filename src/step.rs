@@ -0,0 +1,153 @@
+use serde::Serialize;
+
+use crate::vasm::{StateMachine, Transaction, Vasm, Vector};
+
+/// `Step` is a set of transitions fired concurrently in a single step.
+#[derive(Debug, Clone, Serialize)]
+pub struct Step {
+    /// The transitions fired in the step, in label order.
+    pub actions: Vec<String>,
+    /// The combined result of the step, its role lists the distinct roles of the actions separated by commas.
+    pub transaction: Transaction,
+}
+
+impl StateMachine {
+    /// Fires a maximal set of mutually non-conflicting enabled transitions in one step.
+    ///
+    /// Transitions are considered in label order and added to the step while the state still holds
+    /// the tokens consumed by every transition in the step and the combined output respects the capacities.
+    /// Guards are evaluated against the state before the step.
+    /// Elementary and workflow models hold a single token, so their steps fire at most one transition.
+    /// A transition with reset, transfer or marking weighted arcs depends on the content of places, so it fires alone.
+    ///
+    /// # Returns
+    ///
+    /// * The chosen `Step`, which is empty with a failed transaction when no transition is enabled.
+    ///
+    pub fn fire_maximal_step(&self, state: &Vector) -> Step {
+        let mut actions: Vec<&String> = self.transitions.keys().collect();
+        actions.sort();
+
+        let mut chosen: Vec<String> = Vec::new();
+        let mut roles: Vec<&str> = Vec::new();
+        let mut consumed = vec![0; state.len()];
+        let mut output = state.clone();
+
+        for action in actions {
+            if !chosen.is_empty() && !self.semantics_config().is_concurrent() {
+                break;
+            }
+            if !self.is_enabled(state, action, 1) {
+                continue;
+            }
+            let transition = &self.transitions[action];
+            let transfers = !transition.transfers().is_empty() || !transition.marking_arcs().is_empty();
+            if transfers && !chosen.is_empty() {
+                continue;
+            }
+            let delta = &self.transitions[action].delta;
+            let fits = (0..state.len()).all(|i| {
+                let d = *delta.get(i).unwrap_or(&0);
+                let cap = self.place_capacity(i);
+                consumed[i] - d.min(0) <= state[i] && cap.allows(output[i] + d)
+            });
+            if !fits {
+                continue;
+            }
+            for (i, d) in delta.iter().enumerate() {
+                consumed[i] -= d.min(&0);
+                output[i] += d;
+            }
+            let role = self.transitions[action].role.as_str();
+            if !roles.contains(&role) {
+                roles.push(role);
+            }
+            chosen.push(action.clone());
+            if transfers {
+                break;
+            }
+        }
+
+        let ok = !chosen.is_empty();
+        if !ok {
+            output = state.clone();
+        } else if chosen.len() == 1 {
+            // keep the model specific output of a single firing, such as clamped workflow reentry
+            output = self.transform(state, &chosen[0], 1).output;
+        }
+        Step {
+            transaction: Transaction {
+                ok,
+                output,
+                role: roles.join(","),
+                inhibited: false,
+                overflow: false,
+                underflow: false,
+                error: None,
+                action: String::new(),
+                multiple: 0,
+                net_consumed: Vector::new(),
+                net_produced: Vector::new(),
+            }
+            .with_action(state, &chosen.join(","), 1),
+            actions: chosen,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::FlowDsl;
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::petri_net::PetriNet;
+
+    use super::*;
+
+    fn fork(p: &mut dyn FlowDsl) {
+        p.cell("a", Option::from(1), None, 0, 0);
+        p.cell("b", Option::from(1), None, 0, 0);
+        p.cell("shared", Option::from(1), None, 0, 0);
+        p.cell("out", None, Option::from(3), 0, 0);
+        p.func("ta", "clock", 0, 0);
+        p.func("tb", "clock", 0, 0);
+        p.func("tc", "reset", 0, 0);
+        p.func("td", "reset", 0, 0);
+        p.arrow("a", "ta", 1);
+        p.arrow("ta", "out", 1);
+        p.arrow("b", "tb", 1);
+        p.arrow("tb", "out", 1);
+        p.arrow("shared", "tc", 1);
+        p.arrow("tc", "out", 1);
+        p.arrow("shared", "td", 1);
+    }
+
+    #[test]
+    fn test_maximal_step_skips_conflicts() {
+        let sm = StateMachine::new(fork);
+        let step = sm.fire_maximal_step(&sm.initial_vector());
+        assert_eq!(step.actions, vec!["ta", "tb", "tc"]);
+        assert!(step.transaction.is_ok());
+        assert_eq!(step.transaction.output, vec![0, 0, 0, 3]);
+        assert_eq!(step.transaction.role, "clock,reset");
+
+        let empty = sm.fire_maximal_step(&step.transaction.output);
+        assert!(empty.actions.is_empty());
+        assert!(empty.transaction.is_err());
+    }
+
+    #[test]
+    fn test_maximal_step_respects_capacity() {
+        let sm = StateMachine::new(fork);
+        let step = sm.fire_maximal_step(&vec![1, 1, 1, 2]);
+        assert_eq!(step.actions, vec!["ta", "td"]);
+        assert_eq!(step.transaction.output, vec![0, 1, 0, 3]);
+    }
+
+    #[test]
+    fn test_philosophers_eat_concurrently() {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let sm = StateMachine::from_model(&mut net);
+        let step = sm.fire_maximal_step(&sm.initial_vector());
+        assert_eq!(step.actions, vec!["eat1", "eat3"]);
+    }
+}
@@ -0,0 +1,282 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::zblob::Zblob;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS zblobs (
+    id INTEGER PRIMARY KEY,
+    ipfs_cid TEXT NOT NULL UNIQUE,
+    base64_zipped TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    keywords TEXT NOT NULL,
+    referrer TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    previous_cid TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    encryption TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS keywords (
+    keyword TEXT NOT NULL,
+    zblob_id INTEGER NOT NULL REFERENCES zblobs (id),
+    PRIMARY KEY (keyword, zblob_id)
+);
+CREATE VIRTUAL TABLE IF NOT EXISTS zblob_text USING fts5 (title, description);
+";
+
+const COLUMNS: &str = "z.id, z.ipfs_cid, z.base64_zipped, z.title, z.description, z.keywords, z.referrer, z.created_at,
+    z.previous_cid, z.version, z.encryption";
+
+/// `SearchHit` is a zblob matching a full-text search, lower ranks match better.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub zblob: Zblob,
+    pub rank: f64,
+}
+
+/// `BlobStore` keeps zblobs in SQLite, indexed by CID, by keyword, and by the words of their title and description.
+pub struct BlobStore {
+    conn: Connection,
+}
+
+impl BlobStore {
+    /// Opens the store in the given database file, creating the tables if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> rusqlite::Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Opens a store that lives in memory, for tests and short-lived servers.
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> rusqlite::Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Normalizes, validates, stores and indexes the zblob, returns its id. A zblob whose CID is already
+    /// stored is not stored again, the id of the stored one is returned instead.
+    ///
+    /// A zblob failing `Zblob::validate` is rejected with a `ToSqlConversionFailure` holding the `MetadataError`.
+    pub fn insert(&mut self, zblob: &Zblob) -> rusqlite::Result<i64> {
+        if let Some(id) = self.id_of(&zblob.ipfs_cid)? {
+            return Ok(id);
+        }
+        let mut zblob = zblob.clone();
+        zblob.normalize();
+        zblob
+            .validate()
+            .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO zblobs (ipfs_cid, base64_zipped, title, description, keywords, referrer, created_at,
+                previous_cid, version, encryption)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                zblob.ipfs_cid,
+                zblob.base64_zipped,
+                zblob.title,
+                zblob.description,
+                zblob.keywords,
+                zblob.referrer,
+                zblob.created_at,
+                zblob.previous_cid,
+                zblob.version,
+                zblob.encryption
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        for keyword in zblob.keywords.split(',').filter(|k| !k.is_empty()) {
+            tx.execute(
+                "INSERT INTO keywords (keyword, zblob_id) VALUES (?1, ?2)",
+                params![keyword, id],
+            )?;
+        }
+        tx.execute(
+            "INSERT INTO zblob_text (rowid, title, description) VALUES (?1, ?2, ?3)",
+            params![id, zblob.title, zblob.description],
+        )?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    fn id_of(&self, cid: &str) -> rusqlite::Result<Option<i64>> {
+        self.conn
+            .query_row("SELECT id FROM zblobs WHERE ipfs_cid = ?1", [cid], |row| row.get(0))
+            .optional()
+    }
+
+    /// Returns the zblob with the given CID.
+    pub fn get(&self, cid: &str) -> rusqlite::Result<Option<Zblob>> {
+        let sql = format!("SELECT {} FROM zblobs z WHERE z.ipfs_cid = ?1", COLUMNS);
+        self.conn.query_row(&sql, [cid], from_row).optional()
+    }
+
+    /// Returns the zblob with the given CID followed by the versions it was derived from, newest first.
+    pub fn history(&self, cid: &str) -> rusqlite::Result<Vec<Zblob>> {
+        match self.get(cid)? {
+            Some(zblob) => zblob.try_history(|cid| self.get(cid)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns the zblobs derived directly from the zblob with the given CID, oldest first.
+    pub fn derived_from(&self, cid: &str) -> rusqlite::Result<Vec<Zblob>> {
+        let sql = format!("SELECT {} FROM zblobs z WHERE z.previous_cid = ?1 ORDER BY z.id", COLUMNS);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([cid], from_row)?;
+        rows.collect()
+    }
+
+    /// Returns the zblobs tagged with the keyword, newest first. Keywords are matched case-insensitively.
+    pub fn with_keyword(&self, keyword: &str) -> rusqlite::Result<Vec<Zblob>> {
+        let sql = format!(
+            "SELECT {} FROM keywords k JOIN zblobs z ON z.id = k.zblob_id WHERE k.keyword = ?1 ORDER BY z.id DESC",
+            COLUMNS
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([keyword.to_lowercase()], from_row)?;
+        rows.collect()
+    }
+
+    /// Searches the titles and descriptions for zblobs containing every word of the query,
+    /// best matches first. Words match by prefix, so `phil` finds `philosophers`.
+    pub fn search(&self, query: &str, limit: usize) -> rusqlite::Result<Vec<SearchHit>> {
+        // Every word is quoted, so the query cannot use or break the FTS5 query syntax.
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|w| format!("\"{}\"*", w.replace('"', "\"\"")))
+            .collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT {}, bm25(zblob_text) AS rank FROM zblob_text JOIN zblobs z ON z.id = zblob_text.rowid
+             WHERE zblob_text MATCH ?1 ORDER BY rank LIMIT ?2",
+            COLUMNS
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![terms.join(" "), limit as i64], |row| {
+            Ok(SearchHit {
+                zblob: from_row(row)?,
+                rank: row.get(11)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+fn from_row(row: &Row) -> rusqlite::Result<Zblob> {
+    Ok(Zblob {
+        id: row.get(0)?,
+        ipfs_cid: row.get(1)?,
+        base64_zipped: row.get(2)?,
+        title: row.get(3)?,
+        description: row.get(4)?,
+        keywords: row.get(5)?,
+        referrer: row.get(6)?,
+        created_at: row.get(7)?,
+        previous_cid: row.get(8)?,
+        version: row.get(9)?,
+        encryption: row.get(10)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::petri_net::PetriNet;
+
+    use crate::zblob::MAX_TITLE_LEN;
+
+    use super::*;
+
+    fn blob(title: &str, description: &str, keywords: &str) -> Zblob {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        net.add_place(title, 15, None, None, 0, 0);
+        Zblob {
+            title: title.to_string(),
+            description: description.to_string(),
+            keywords: keywords.to_string(),
+            ..net.to_zblob()
+        }
+    }
+
+    #[test]
+    fn test_insert_and_get() {
+        let mut store = BlobStore::open_in_memory().unwrap();
+        let zblob = blob("philosophers", "five philosophers", "classic");
+        let id = store.insert(&zblob).unwrap();
+        assert_eq!(store.insert(&zblob).unwrap(), id);
+        let stored = store.get(&zblob.ipfs_cid).unwrap().unwrap();
+        assert_eq!((stored.id, stored.title.as_str()), (id, "philosophers"));
+        assert_eq!(stored.to_net().places.len(), 16);
+        assert!(store.get("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_history() {
+        let mut store = BlobStore::open_in_memory().unwrap();
+        let original = blob("philosophers", "", "");
+        let mut net = original.to_net();
+        net.add_place("napkin", 1, None, None, 0, 0);
+        let edited = Zblob::derive_from(&original, &net);
+        store.insert(&original).unwrap();
+        store.insert(&edited).unwrap();
+
+        let history = store.history(&edited.ipfs_cid).unwrap();
+        let versions: Vec<_> = history.iter().map(|z| (z.version, z.previous_cid.as_str())).collect();
+        assert_eq!(versions, vec![(2, original.ipfs_cid.as_str()), (1, "")]);
+        assert_eq!(store.derived_from(&original.ipfs_cid).unwrap()[0].ipfs_cid, edited.ipfs_cid);
+        assert!(store.history("missing").unwrap().is_empty());
+
+        let reverted = Zblob::derive_from(&edited, &original.to_net());
+        store.insert(&reverted).unwrap();
+        let versions: Vec<_> = store.history(&reverted.ipfs_cid).unwrap().iter().map(|z| z.version).collect();
+        assert_eq!(versions, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_keywords_and_search() {
+        let mut store = BlobStore::open_in_memory().unwrap();
+        store
+            .insert(&blob(
+                "philosophers",
+                "dining philosophers sharing chopsticks",
+                "Classic, deadlock",
+            ))
+            .unwrap();
+        store
+            .insert(&blob("order", "an order workflow with payment", "workflow, classic"))
+            .unwrap();
+        store.insert(&blob("payment", "payment retries", "workflow")).unwrap();
+
+        let long = Zblob {
+            title: "x".repeat(MAX_TITLE_LEN + 1),
+            ..blob("long", "", "")
+        };
+        assert!(matches!(store.insert(&long), Err(rusqlite::Error::ToSqlConversionFailure(_))));
+        assert!(store.get(&long.ipfs_cid).unwrap().is_none());
+        let stored = store.get(&blob("order", "", "").ipfs_cid).unwrap().unwrap();
+        assert_eq!(stored.keywords, "workflow,classic");
+
+        let titles = |blobs: Vec<Zblob>| blobs.into_iter().map(|z| z.title).collect::<Vec<_>>();
+        assert_eq!(
+            titles(store.with_keyword("classic").unwrap()),
+            vec!["order", "philosophers"]
+        );
+        assert_eq!(titles(store.with_keyword("DEADLOCK").unwrap()), vec!["philosophers"]);
+
+        let hits = store.search("payment", 10).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].zblob.title, "payment");
+        assert!(hits[0].rank <= hits[1].rank);
+        assert_eq!(store.search("phil chop", 10).unwrap()[0].zblob.title, "philosophers");
+        assert!(store.search("\"unbalanced OR", 10).unwrap().is_empty());
+        assert!(store.search("  ", 10).unwrap().is_empty());
+        assert_eq!(store.search("payment", 1).unwrap().len(), 1);
+    }
+}
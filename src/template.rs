@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::capacity::Capacity;
+use crate::dsl::FlowDsl;
+use crate::expr::MarkingWeight;
+
+/// `Template` is a reusable net fragment declared once and instantiated many times under a name prefix.
+///
+/// The fragment refers to the surrounding net through ports, which are bound to existing nodes
+/// when the template is instantiated. Every other label in the fragment is renamed to `prefix.label`.
+///
+/// # Example
+///
+/// ```
+/// use pflow_metamodel::dsl::FlowDsl;
+/// use pflow_metamodel::template::Template;
+/// use pflow_metamodel::vasm::StateMachine;
+///
+/// fn approval(p: &mut dyn FlowDsl) {
+///     p.cell("pending", None, None, 0, 0);
+///     p.func("request", "default", 0, 0);
+///     p.func("approve", "manager", 0, 0);
+///     p.arrow("in", "request", 1);
+///     p.arrow("request", "pending", 1);
+///     p.arrow("pending", "approve", 1);
+///     p.arrow("approve", "out", 1);
+/// }
+///
+/// let sm = StateMachine::new(|p| {
+///     let step = Template::new(&["in", "out"], approval);
+///     p.cell("start", Option::from(1), None, 0, 0);
+///     p.cell("middle", None, None, 0, 0);
+///     p.cell("end", None, None, 0, 0);
+///     step.instantiate(p, "first", &[("in", "start"), ("out", "middle")]);
+///     step.instantiate(p, "second", &[("in", "middle"), ("out", "end")]);
+/// });
+/// assert!(sm.transitions.contains_key("second.approve"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Template {
+    ports: Vec<String>,
+    body: fn(&mut dyn FlowDsl),
+}
+
+impl Template {
+    /// Creates a template from its port names and the declaration of its body.
+    pub fn new(ports: &[&str], body: fn(&mut dyn FlowDsl)) -> Self {
+        Self {
+            ports: ports.iter().map(|p| p.to_string()).collect(),
+            body,
+        }
+    }
+
+    /// Returns the names of the ports of the template.
+    pub fn ports(&self) -> &[String] {
+        &self.ports
+    }
+
+    /// Declares a copy of the body in `p`, prefixing its labels and wiring each port to the bound node.
+    ///
+    /// Panics if a port is left unbound or a binding names an unknown port.
+    pub fn instantiate(&self, p: &mut dyn FlowDsl, prefix: &str, bindings: &[(&str, &str)]) {
+        let bindings: HashMap<String, String> = bindings
+            .iter()
+            .map(|(port, node)| {
+                assert!(self.ports.iter().any(|p| p == port), "unknown port {}", port);
+                (port.to_string(), node.to_string())
+            })
+            .collect();
+        for port in &self.ports {
+            assert!(bindings.contains_key(port), "port {} is not bound", port);
+        }
+        (self.body)(&mut Instance {
+            inner: p,
+            prefix,
+            bindings,
+        });
+    }
+}
+
+/// `Instance` forwards the declarations of a template body to the enclosing net with renamed labels.
+struct Instance<'a> {
+    inner: &'a mut dyn FlowDsl,
+    prefix: &'a str,
+    bindings: HashMap<String, String>,
+}
+
+impl<'a> Instance<'a> {
+    fn resolve(&self, label: &str) -> String {
+        match self.bindings.get(label) {
+            Some(node) => node.clone(),
+            None => format!("{}.{}", self.prefix, label),
+        }
+    }
+
+    fn declared(&self, label: &str) -> String {
+        assert!(!self.bindings.contains_key(label), "port {} cannot be declared in a template", label);
+        self.resolve(label)
+    }
+}
+
+impl<'a> FlowDsl for Instance<'a> {
+    /// The model type belongs to the enclosing net, templates cannot change it.
+    fn model_type(&mut self, _model_type: &str) {}
+
+    /// Roles are shared by the whole net, so they are declared without the prefix of the instance.
+    fn role(&mut self, name: &str, description: Option<&str>, implies: &[&str]) {
+        self.inner.role(name, description, implies);
+    }
+
+    /// The default role belongs to the enclosing net, templates cannot change it.
+    fn default_role(&mut self, _role: &str) {}
+
+    fn cell<'b>(&mut self, label: &'b str, initial: Option<i32>, capacity: Option<i32>, x: i32, y: i32) -> &'b str {
+        self.inner.cell(&self.declared(label), initial, capacity, x, y);
+        label
+    }
+
+    fn func<'b>(&mut self, label: &'b str, role: &str, x: i32, y: i32) -> &'b str {
+        self.inner.func(&self.declared(label), role, x, y);
+        label
+    }
+
+    fn arrow(&mut self, source: &str, target: &str, weight: i32) {
+        self.inner.arrow(&self.resolve(source), &self.resolve(target), weight);
+    }
+
+    fn guard(&mut self, source: &str, target: &str, weight: i32) {
+        self.inner.guard(&self.resolve(source), &self.resolve(target), weight);
+    }
+
+    fn reset(&mut self, cell: &str, func: &str) {
+        self.inner.reset(&self.resolve(cell), &self.resolve(func));
+    }
+
+    fn transfer(&mut self, source: &str, func: &str, target: &str, weight: i32) {
+        self.inner.transfer(&self.resolve(source), &self.resolve(func), &self.resolve(target), weight);
+    }
+
+    fn marking_arrow(&mut self, source: &str, target: &str, weight: &str) {
+        let weight = MarkingWeight::rename_place(weight, |place| self.resolve(place));
+        self.inner.marking_arrow(&self.resolve(source), &self.resolve(target), &weight);
+    }
+
+    fn unit(&mut self, cell: &str, unit: &str) {
+        self.inner.unit(&self.resolve(cell), unit);
+    }
+
+    fn capacity(&mut self, cell: &str, capacity: Capacity) {
+        self.inner.capacity(&self.resolve(cell), capacity);
+    }
+
+    fn allow_reentry(&mut self, func: &str) {
+        self.inner.allow_reentry(&self.resolve(func));
+    }
+
+    fn priority(&mut self, func: &str, priority: i32) {
+        self.inner.priority(&self.resolve(func), priority);
+    }
+
+    fn rate(&mut self, func: &str, rate: i32) {
+        self.inner.rate(&self.resolve(func), rate);
+    }
+
+    fn delay(&mut self, func: &str, delay: i32) {
+        self.inner.delay(&self.resolve(func), delay);
+    }
+
+    fn timeout(&mut self, func: &str, after: i32, escalate: Option<&str>) {
+        let escalate = escalate.map(|e| self.resolve(e));
+        self.inner.timeout(&self.resolve(func), after, escalate.as_deref());
+    }
+
+    fn condition(&mut self, func: &str, expression: &str) {
+        self.inner.condition(&self.resolve(func), expression);
+    }
+
+    fn label(&mut self, node: &str, label: &str) {
+        self.inner.label(&self.resolve(node), label);
+    }
+
+    fn localize(&mut self, node: &str, locale: &str, label: &str) {
+        self.inner.localize(&self.resolve(node), locale, label);
+    }
+
+    fn describe(&mut self, node: &str, description: &str) {
+        self.inner.describe(&self.resolve(node), description);
+    }
+
+    fn attribute(&mut self, node: &str, key: &str, value: Value) {
+        self.inner.attribute(&self.resolve(node), key, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vasm::{StateMachine, Vasm};
+
+    use super::*;
+
+    fn retry(p: &mut dyn FlowDsl) {
+        p.cell("attempts", Option::from(3), None, 0, 0);
+        p.func("try", "default", 0, 0);
+        p.func("fail", "default", 0, 0);
+        p.cell("trying", None, None, 0, 0);
+        p.arrow("in", "try", 1);
+        p.arrow("attempts", "try", 1);
+        p.arrow("try", "trying", 1);
+        p.arrow("trying", "fail", 1);
+        p.arrow("fail", "in", 1);
+        p.guard("out", "try", 1);
+    }
+
+    fn workflow(p: &mut dyn FlowDsl) {
+        let t = Template::new(&["in", "out"], retry);
+        p.cell("a", Option::from(1), None, 0, 0);
+        p.cell("b", Option::from(1), None, 0, 0);
+        p.cell("done", None, None, 0, 0);
+        t.instantiate(p, "ra", &[("in", "a"), ("out", "done")]);
+        t.instantiate(p, "rb", &[("in", "b"), ("out", "done")]);
+    }
+
+    fn index(sm: &StateMachine, place: &str) -> usize {
+        sm.places.iter().position(|p| p == place).unwrap()
+    }
+
+    #[test]
+    fn test_instances_are_renamed_and_wired() {
+        let sm = StateMachine::new(workflow);
+        assert_eq!(sm.places.len(), 7);
+        assert_eq!(sm.transitions.len(), 4);
+        assert_eq!(sm.initial[index(&sm, "rb.attempts")], 3);
+
+        let res = sm.transform(&sm.initial_vector(), "ra.try", 1);
+        assert!(res.is_ok());
+        assert_eq!(res.output[index(&sm, "a")], 0);
+        assert_eq!(res.output[index(&sm, "ra.trying")], 1);
+        assert_eq!(res.output[index(&sm, "b")], 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "port out is not bound")]
+    fn test_unbound_port_panics() {
+        StateMachine::new(|p| {
+            p.cell("a", None, None, 0, 0);
+            Template::new(&["in", "out"], retry).instantiate(p, "r", &[("in", "a")]);
+        });
+    }
+}
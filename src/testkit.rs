@@ -0,0 +1,372 @@
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::capacity::Capacity;
+use crate::petri_net::PetriNet;
+use crate::vasm::{StateMachine, Vasm, Vector};
+
+/// The largest number of places and of transitions in a generated net.
+pub const MAX_NODES: usize = 8;
+
+/// Generates valid place/transition nets with `p0, p1, ..` places and `t0, t1, ..` transitions.
+///
+/// Arcs have weights from one to three and some of them are inhibitor or read arcs,
+/// places may have an initial marking and a capacity.
+impl<'a> Arbitrary<'a> for PetriNet {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut net = PetriNet::new();
+        let places = u.int_in_range(1..=MAX_NODES)?;
+        let transitions = u.int_in_range(1..=MAX_NODES)?;
+        for i in 0..places {
+            let initial = u.int_in_range(0..=3)?;
+            net.add_place(&format!("p{}", i), i as i32, Some(initial), None, 0, 0);
+            if u.ratio(1, 4)? {
+                let capacity = Capacity::Bounded(u.int_in_range(initial as u32..=initial as u32 + 3)?);
+                net.set_capacity(&format!("p{}", i), capacity);
+            }
+        }
+        for t in 0..transitions {
+            net.add_transition(&format!("t{}", t), "default", 0, 0);
+        }
+        let arcs = u.int_in_range(0..=places * transitions * 2)?;
+        for _ in 0..arcs {
+            let place = format!("p{}", u.choose_index(places)?);
+            let transition = format!("t{}", u.choose_index(transitions)?);
+            let weight = Some(u.int_in_range(1..=3)?);
+            match u.int_in_range(0..=9)? {
+                0 => net.add_arc(&place, &transition, weight, None, None, Some(true), None),
+                1 => net.add_arc(&transition, &place, weight, None, None, Some(true), None),
+                2..=5 => net.add_arc(&place, &transition, weight, None, None, None, None),
+                _ => net.add_arc(&transition, &place, weight, None, None, None, None),
+            }
+        }
+        Ok(net)
+    }
+}
+
+/// Generates a marking of the state machine with at most `max_tokens` tokens in each place, respecting capacities.
+pub fn marking(u: &mut Unstructured, sm: &StateMachine, max_tokens: i32) -> Result<Vector> {
+    (0..sm.places.len())
+        .map(|i| sm.place_capacity(i))
+        .map(|c| u.int_in_range(0..=c.limit().map_or(max_tokens, |l| l.min(max_tokens))))
+        .collect()
+}
+
+/// Generates a firing sequence of at most `max_len` actions from the initial state,
+/// every action is enabled in the state the previous ones lead to.
+pub fn firing_sequence(u: &mut Unstructured, sm: &StateMachine, max_len: usize) -> Result<Vec<String>> {
+    let mut actions: Vec<&String> = sm.transitions.keys().collect();
+    actions.sort();
+    let mut state = sm.initial_vector();
+    let mut sequence = Vec::new();
+    while sequence.len() < max_len && !u.is_empty() {
+        let enabled: Vec<&String> = actions
+            .iter()
+            .copied()
+            .filter(|a| sm.is_enabled(&state, a, 1))
+            .collect();
+        if enabled.is_empty() {
+            break;
+        }
+        let action = *u.choose(&enabled)?;
+        state = sm.transform(&state, action, 1).output;
+        sequence.push(action.clone());
+    }
+    Ok(sequence)
+}
+
+/// Returns the nets one step smaller than `net`: without one of its arcs, transitions or places,
+/// or with one token less in a place. Candidates are yielded roughly from the largest reduction down.
+pub fn shrink(net: &PetriNet) -> impl Iterator<Item = PetriNet> + '_ {
+    let mut places: Vec<&String> = net.places.keys().collect();
+    places.sort();
+    let mut transitions: Vec<&String> = net.transitions.keys().collect();
+    transitions.sort();
+
+    let without_places = places.clone().into_iter().filter(|_| net.places.len() > 1).map(|p| {
+        let mut smaller = net.clone();
+        smaller.remove_place(p);
+        smaller
+    });
+    let without_transitions = transitions.into_iter().filter(|_| net.transitions.len() > 1).map(|t| {
+        let mut smaller = net.clone();
+        smaller.remove_transition(t);
+        smaller
+    });
+    let without_arcs = (0..net.arcs.len()).map(|i| {
+        let mut smaller = net.clone();
+        smaller.arcs.remove(i);
+        smaller
+    });
+    let fewer_tokens = places
+        .into_iter()
+        .filter(|p| net.places[*p].initial.unwrap_or(0) > 0)
+        .map(|p| {
+            let mut smaller = net.clone();
+            let place = smaller.places.get_mut(p).unwrap();
+            place.initial = place.initial.map(|n| n - 1);
+            smaller
+        });
+    without_places
+        .chain(without_transitions)
+        .chain(without_arcs)
+        .chain(fewer_tokens)
+}
+
+/// Returns the sequences one action shorter than `sequence`.
+pub fn shrink_sequence(sequence: &[String]) -> impl Iterator<Item = Vec<String>> + '_ {
+    (0..sequence.len())
+        .rev()
+        .map(|i| [&sequence[..i], &sequence[i + 1..]].concat())
+}
+
+/// Shrinks a net failing `property` greedily until none of its `shrink` candidates fails it any more.
+pub fn minimize<F>(net: PetriNet, property: F) -> PetriNet
+where
+    F: Fn(&PetriNet) -> bool,
+{
+    let mut smallest = net;
+    loop {
+        let smaller = shrink(&smallest).find(|candidate| !property(candidate));
+        match smaller {
+            Some(smaller) => smallest = smaller,
+            None => return smallest,
+        }
+    }
+}
+
+/// `Mutation` is a standard operator changing a single arc of a net.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Mutation {
+    /// Removes the arc.
+    DropArc,
+    /// Turns a regular arc into an inhibitor or read arc, or back.
+    FlipInhibitor,
+    /// Raises the weight by one, and lowers it by one when it is above one.
+    ChangeWeight,
+    /// Reverses the arc, so an input becomes an output and the other way round.
+    SwapDirection,
+}
+
+impl Mutation {
+    /// All mutation operators.
+    pub const ALL: [Mutation; 4] = [
+        Mutation::DropArc,
+        Mutation::FlipInhibitor,
+        Mutation::ChangeWeight,
+        Mutation::SwapDirection,
+    ];
+}
+
+/// `Mutant` is a variant of a net produced by applying one mutation operator to one arc.
+#[derive(Debug, Clone)]
+pub struct Mutant {
+    pub operator: Mutation,
+    /// The index of the mutated arc in the arcs of the original net.
+    pub arc: usize,
+    pub net: PetriNet,
+}
+
+/// Applies each operator to each arc of the net and returns the resulting variants, arc by arc
+/// in the order of `operators`. The arc attributes of the variants are populated again after mutating.
+pub fn mutate(net: &PetriNet, operators: &[Mutation]) -> Vec<Mutant> {
+    let mut mutants = Vec::new();
+    for arc in 0..net.arcs.len() {
+        for operator in operators {
+            let weight = net.arcs[arc].weight.unwrap_or(1);
+            let weights: Vec<i32> = match operator {
+                Mutation::ChangeWeight if weight > 1 => vec![weight + 1, weight - 1],
+                Mutation::ChangeWeight => vec![weight + 1],
+                _ => vec![weight],
+            };
+            for weight in weights {
+                let mut variant = net.clone();
+                let mutated = &mut variant.arcs[arc];
+                match operator {
+                    Mutation::DropArc => {
+                        variant.arcs.remove(arc);
+                    }
+                    Mutation::FlipInhibitor => mutated.inhibit = Some(!mutated.inhibit.unwrap_or(false)),
+                    Mutation::ChangeWeight => mutated.weight = Some(weight),
+                    Mutation::SwapDirection => std::mem::swap(&mut mutated.source, &mut mutated.target),
+                }
+                if let Some(mutated) = variant.arcs.get_mut(arc).filter(|_| *operator != Mutation::DropArc) {
+                    mutated.consume = None;
+                    mutated.produce = None;
+                    mutated.read = None;
+                }
+                variant.populate_arc_attributes();
+                mutants.push(Mutant {
+                    operator: *operator,
+                    arc,
+                    net: variant,
+                });
+            }
+        }
+    }
+    mutants
+}
+
+/// `MutationReport` tells how many mutants a test suite detected.
+#[derive(Debug, Clone)]
+pub struct MutationReport {
+    pub total: usize,
+    pub killed: usize,
+    /// The mutants the suite accepted, a suite checking the behavior of the net should reject them.
+    pub survivors: Vec<Mutant>,
+}
+
+impl MutationReport {
+    /// Returns the share of killed mutants, one if there were none.
+    pub fn score(&self) -> f64 {
+        match self.total {
+            0 => 1.0,
+            _ => self.killed as f64 / self.total as f64,
+        }
+    }
+}
+
+/// Runs the test suite against every mutant of the net, a mutant is killed when the suite returns false for it.
+pub fn mutation_score<F>(net: &PetriNet, operators: &[Mutation], suite: F) -> MutationReport
+where
+    F: Fn(&PetriNet) -> bool,
+{
+    let mutants = mutate(net, operators);
+    let total = mutants.len();
+    let survivors: Vec<Mutant> = mutants.into_iter().filter(|m| suite(&m.net)).collect();
+    MutationReport {
+        total,
+        killed: total - survivors.len(),
+        survivors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{RngCore, SeedableRng};
+
+    use super::*;
+
+    /// Runs the check on nets generated from random bytes.
+    fn for_random_nets(seed: u64, check: impl Fn(&mut Unstructured, PetriNet)) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut bytes = vec![0u8; 1024];
+        for _ in 0..200 {
+            rng.fill_bytes(&mut bytes);
+            let mut u = Unstructured::new(&bytes);
+            let net = PetriNet::arbitrary(&mut u).unwrap();
+            check(&mut u, net);
+        }
+    }
+
+    #[test]
+    fn test_transform_never_outputs_negative_tokens() {
+        for_random_nets(871, |u, mut net| {
+            let sm = StateMachine::from_model(&mut net);
+            let state = marking(u, &sm, 4).unwrap();
+            for action in sm.transitions.keys() {
+                let res = sm.transform(&state, action, 1);
+                assert!(
+                    !res.is_ok() || res.output.iter().all(|t| *t >= 0),
+                    "{} in {:?}",
+                    action,
+                    net
+                );
+                assert_eq!(res.is_ok(), sm.is_enabled(&state, action, 1));
+            }
+        });
+    }
+
+    #[test]
+    fn test_firing_sequences_replay() {
+        for_random_nets(872, |u, mut net| {
+            let sm = StateMachine::from_model(&mut net);
+            let sequence = firing_sequence(u, &sm, 10).unwrap();
+            let actions: Vec<(&str, i32)> = sequence.iter().map(|a| (a.as_str(), 1)).collect();
+            assert!(sm.transform_seq(&sm.initial_vector(), &actions).is_ok());
+            for shorter in shrink_sequence(&sequence) {
+                assert_eq!(shorter.len() + 1, sequence.len());
+            }
+        });
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        for_random_nets(873, |_, net| {
+            // Loading populates the arc attributes, so the first load normalizes the document.
+            let json = PetriNet::from_json(net.to_json().unwrap()).unwrap().to_json().unwrap();
+            assert_eq!(PetriNet::from_json(json.clone()).unwrap().to_json().unwrap(), json);
+        });
+    }
+
+    #[test]
+    fn test_mutate() {
+        let mut net = PetriNet::new();
+        net.add_place("start", 0, Some(2), None, 0, 0);
+        net.add_place("end", 1, None, None, 0, 0);
+        net.add_transition("go", "default", 0, 0);
+        net.add_arc("start", "go", Some(2), None, None, None, None);
+        net.add_arc("go", "end", Some(1), None, None, None, None);
+        net.populate_arc_attributes();
+
+        let mutants = mutate(&net, &Mutation::ALL);
+        // Both arcs are dropped, flipped and swapped, the first weight is raised and lowered, the second only raised.
+        assert_eq!(mutants.len(), 9);
+        let swapped = &mutants
+            .iter()
+            .find(|m| m.operator == Mutation::SwapDirection && m.arc == 0)
+            .unwrap()
+            .net;
+        assert_eq!(
+            (swapped.arcs[0].source.as_str(), swapped.arcs[0].produce),
+            ("go", Some(true))
+        );
+        let flipped = &mutants
+            .iter()
+            .find(|m| m.operator == Mutation::FlipInhibitor && m.arc == 1)
+            .unwrap()
+            .net;
+        assert_eq!(
+            (flipped.arcs[1].inhibit, flipped.arcs[1].read),
+            (Some(true), Some(true))
+        );
+        assert_eq!(mutate(&net, &[Mutation::DropArc]).len(), 2);
+
+        // The suite only checks that the transition is enabled, so it misses most mutants.
+        let enabled = |n: &PetriNet| {
+            let sm = StateMachine::from_model(&mut n.clone());
+            sm.is_enabled(&sm.initial_vector(), "go", 1)
+        };
+        let report = mutation_score(&net, &Mutation::ALL, enabled);
+        assert_eq!(report.total, 9);
+        let mut killed: Vec<(Mutation, usize)> = mutate(&net, &Mutation::ALL)
+            .into_iter()
+            .filter(|m| !enabled(&m.net))
+            .map(|m| (m.operator, m.arc))
+            .collect();
+        killed.sort();
+        assert_eq!(
+            killed,
+            vec![
+                (Mutation::FlipInhibitor, 0),
+                (Mutation::FlipInhibitor, 1),
+                (Mutation::ChangeWeight, 0),
+                (Mutation::SwapDirection, 1)
+            ]
+        );
+        assert_eq!((report.killed, report.survivors.len()), (4, 5));
+        assert!((report.score() - 4.0 / 9.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_minimize() {
+        let mut bytes = vec![0u8; 512];
+        StdRng::seed_from_u64(874).fill_bytes(&mut bytes);
+        let net = PetriNet::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+        assert!(net.arcs.len() > 2);
+        // The property fails for every net with two or more arcs, so the smallest failing net has exactly two.
+        let smallest = minimize(net, |n| n.arcs.len() < 2);
+        assert_eq!(smallest.arcs.len(), 2);
+        assert!(smallest.places.values().all(|p| p.initial == Some(0)));
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::dsl::{Builder, FlowDsl};
+use crate::petri_net::{PetriNet, DEFAULT_ROLE};
+
+/// `ParseError` reports a syntax or declaration error in a textual model with its 1-based position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Int(i32),
+    Punct(&'static str),
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(s) => write!(f, "`{}`", s),
+            Token::Int(i) => write!(f, "`{}`", i),
+            Token::Punct(p) => write!(f, "`{}`", p),
+            Token::End => write!(f, "end of input"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Spanned {
+    token: Token,
+    line: usize,
+    column: usize,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Spanned>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let (mut line, mut column) = (1, 1);
+
+    while let Some(&c) = chars.peek() {
+        let (start_line, start_column) = (line, column);
+        let mut bump = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+            let c = chars.next();
+            if c == Some('\n') {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+            c
+        };
+        let token = if c.is_whitespace() {
+            bump(&mut chars);
+            continue;
+        } else if c == '#' {
+            while chars.peek().is_some_and(|c| *c != '\n') {
+                bump(&mut chars);
+            }
+            continue;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while chars
+                .peek()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_')
+            {
+                ident.push(bump(&mut chars).unwrap());
+            }
+            Token::Ident(ident)
+        } else if c.is_ascii_digit() {
+            let mut digits = String::new();
+            while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                digits.push(bump(&mut chars).unwrap());
+            }
+            let value = digits.parse().map_err(|_| ParseError {
+                line: start_line,
+                column: start_column,
+                message: format!("number `{}` is too large", digits),
+            })?;
+            Token::Int(value)
+        } else {
+            bump(&mut chars);
+            match c {
+                '{' => Token::Punct("{"),
+                '}' => Token::Punct("}"),
+                '(' => Token::Punct("("),
+                ')' => Token::Punct(")"),
+                ',' => Token::Punct(","),
+                ':' => Token::Punct(":"),
+                '-' if chars.peek() == Some(&'>') => {
+                    bump(&mut chars);
+                    Token::Punct("->")
+                }
+                '-' => Token::Punct("-"),
+                _ => {
+                    return Err(ParseError {
+                        line: start_line,
+                        column: start_column,
+                        message: format!("unexpected character `{}`", c),
+                    })
+                }
+            }
+        };
+        tokens.push(Spanned {
+            token,
+            line: start_line,
+            column: start_column,
+        });
+    }
+    tokens.push(Spanned {
+        token: Token::End,
+        line,
+        column,
+    });
+    Ok(tokens)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Node {
+    Place,
+    Transition,
+}
+
+struct Parser<'a> {
+    tokens: Vec<Spanned>,
+    pos: usize,
+    nodes: HashMap<String, Node>,
+    builder: Builder<'a>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> &Spanned {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Spanned {
+        let t = self.tokens[self.pos].clone();
+        if t.token != Token::End {
+            self.pos += 1;
+        }
+        t
+    }
+
+    fn error_at(&self, at: &Spanned, message: String) -> ParseError {
+        ParseError {
+            line: at.line,
+            column: at.column,
+            message,
+        }
+    }
+
+    fn eat(&mut self, punct: &'static str) -> bool {
+        if self.peek().token == Token::Punct(punct) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, punct: &'static str) -> Result<(), ParseError> {
+        if self.eat(punct) {
+            return Ok(());
+        }
+        let t = self.peek().clone();
+        Err(self.error_at(&t, format!("expected `{}`, found {}", punct, t.token)))
+    }
+
+    fn ident(&mut self) -> Result<(String, Spanned), ParseError> {
+        let t = self.next();
+        match &t.token {
+            Token::Ident(s) => Ok((s.clone(), t)),
+            other => Err(self.error_at(&t, format!("expected a name, found {}", other))),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        let t = self.next();
+        if t.token != Token::Ident(keyword.to_string()) {
+            return Err(self.error_at(&t, format!("expected `{}`, found {}", keyword, t.token)));
+        }
+        Ok(())
+    }
+
+    fn int(&mut self) -> Result<i32, ParseError> {
+        let t = self.next();
+        match &t.token {
+            Token::Int(i) => Ok(*i),
+            other => Err(self.error_at(&t, format!("expected a number, found {}", other))),
+        }
+    }
+
+    fn declare(&mut self, name: &str, at: &Spanned, node: Node) -> Result<(), ParseError> {
+        if self.nodes.insert(name.to_string(), node).is_some() {
+            return Err(self.error_at(at, format!("duplicate node `{}`", name)));
+        }
+        Ok(())
+    }
+
+    /// Parses `name { item, ... }` calling `item` for each entry.
+    fn section<F>(&mut self, keyword: &str, mut item: F) -> Result<(), ParseError>
+    where
+        F: FnMut(&mut Self) -> Result<(), ParseError>,
+    {
+        self.keyword(keyword)?;
+        self.expect("{")?;
+        while !self.eat("}") {
+            item(self)?;
+            if !self.eat(",") {
+                self.expect("}")?;
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn place(&mut self) -> Result<(), ParseError> {
+        let (name, at) = self.ident()?;
+        self.declare(&name, &at, Node::Place)?;
+        let (mut initial, mut capacity) = (None, None);
+        if self.eat("(") {
+            initial = Some(self.int()?);
+            if self.eat(",") {
+                capacity = Some(self.int()?);
+            }
+            self.expect(")")?;
+        }
+        self.builder.cell(&name, initial, capacity, 0, 0);
+        Ok(())
+    }
+
+    fn transition(&mut self) -> Result<(), ParseError> {
+        let (name, at) = self.ident()?;
+        self.declare(&name, &at, Node::Transition)?;
+        let mut role = DEFAULT_ROLE.to_string();
+        if self.eat(":") {
+            self.keyword("role")?;
+            self.expect("(")?;
+            role = self.ident()?.0;
+            self.expect(")")?;
+        }
+        self.builder.func(&name, &role, 0, 0);
+        Ok(())
+    }
+
+    fn node(&mut self) -> Result<(String, Node, Spanned), ParseError> {
+        let (name, at) = self.ident()?;
+        match self.nodes.get(&name) {
+            Some(node) => Ok((name, *node, at)),
+            None => Err(self.error_at(&at, format!("undeclared node `{}`", name))),
+        }
+    }
+
+    fn arc_chain(&mut self) -> Result<(), ParseError> {
+        let (mut source, mut source_kind, _) = self.node()?;
+        let mut links = 0;
+        loop {
+            let at = self.peek().clone();
+            let weight = if self.eat("->") {
+                1
+            } else if self.eat("-") {
+                let w = self.int()?;
+                self.expect("->")?;
+                w
+            } else if links == 0 {
+                return Err(
+                    self.error_at(&at, format!("expected `->` or `-N->`, found {}", at.token))
+                );
+            } else {
+                return Ok(());
+            };
+            if weight <= 0 {
+                return Err(self.error_at(&at, "weight must be positive".to_string()));
+            }
+            let (target, target_kind, target_at) = self.node()?;
+            if source_kind == target_kind {
+                return Err(self.error_at(
+                    &target_at,
+                    "arcs must connect a place and a transition".to_string(),
+                ));
+            }
+            self.builder.arrow(&source, &target, weight);
+            (source, source_kind) = (target, target_kind);
+            links += 1;
+        }
+    }
+
+    fn parse(&mut self) -> Result<(), ParseError> {
+        if self.peek().token == Token::Ident("type".to_string()) {
+            self.next();
+            let model_type = self.ident()?.0;
+            self.builder.model_type(&model_type);
+        }
+        self.section("places", Self::place)?;
+        self.section("transitions", Self::transition)?;
+        self.section("arcs", Self::arc_chain)?;
+        let t = self.peek().clone();
+        if t.token != Token::End {
+            return Err(self.error_at(&t, format!("expected end of input, found {}", t.token)));
+        }
+        Ok(())
+    }
+}
+
+/// Parses the textual model format into a `PetriNet`.
+///
+/// The format mirrors the `petri_net!` macro with an optional model type, `#` starts a comment:
+///
+/// ```text
+/// type workflow
+/// places { p1(1), p2, p3(0, 1) }
+/// transitions { t1: role(user), t2 }
+/// arcs { p1 -1-> t1 -> p2, p2 -> t2 -> p3 }
+/// ```
+pub fn parse(source: &str) -> Result<PetriNet, ParseError> {
+    let mut net = PetriNet::new();
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        pos: 0,
+        nodes: HashMap::new(),
+        builder: Builder::new(&mut net),
+    };
+    parser.parse()?;
+    net.populate_arc_attributes();
+    Ok(net)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::capacity::Capacity;
+    use crate::vasm::{StateMachine, Vasm};
+
+    use super::*;
+
+    const SOURCE: &str = "
+# a two step workflow
+type workflow
+places { p1(1), p2, p3(0, 1) }
+transitions { t1: role(user), t2 }
+arcs { p1 -2-> t1 -> p2, p2 -> t2 -> p3 }
+";
+
+    fn error(source: &str) -> ParseError {
+        PetriNet::from_dsl_str(source).unwrap_err()
+    }
+
+    #[test]
+    fn test_parse_model() {
+        let net = PetriNet::from_dsl_str(SOURCE).unwrap();
+        assert_eq!(net.model_type, "workflow");
+        assert_eq!(net.places["p1"].initial, Some(1));
+        assert_eq!(net.places["p3"].capacity, Some(Capacity::Bounded(1)));
+        assert_eq!(net.places["p2"].offset, 1);
+        assert_eq!(net.transitions["t1"].role, Some("user".to_string()));
+        assert_eq!(net.transitions["t2"].role, Some("default".to_string()));
+        assert_eq!(net.arcs.len(), 4);
+        assert_eq!(net.arcs[0].weight, Some(2));
+
+        let sm = StateMachine::from_model(&mut net.clone());
+        assert_eq!(sm.initial_vector(), vec![1, 0, 0]);
+    }
+
+    #[test]
+    fn test_empty_sections_and_trailing_commas() {
+        let net = PetriNet::from_dsl_str("places { p1, } transitions {} arcs {}").unwrap();
+        assert_eq!(net.model_type, "petriNet");
+        assert_eq!(net.places.len(), 1);
+    }
+
+    #[test]
+    fn test_errors_report_position() {
+        let e = error("places { p1 }\ntransitions { t1 }\narcs { p1 -> t2 }");
+        assert_eq!((e.line, e.column), (3, 14));
+        assert_eq!(e.to_string(), "3:14: undeclared node `t2`");
+
+        let e = error("places { p1, p1 }");
+        assert_eq!(
+            (e.line, e.column, e.message.as_str()),
+            (1, 14, "duplicate node `p1`")
+        );
+
+        let e = error("places { p1, p2 } transitions {} arcs { p1 -> p2 }");
+        assert_eq!(e.message, "arcs must connect a place and a transition");
+
+        let e = error("places { p1 } transitions { t1 } arcs { p1 -0-> t1 }");
+        assert_eq!(
+            (e.column, e.message.as_str()),
+            (44, "weight must be positive")
+        );
+
+        let e = error("places { p1 ! }");
+        assert_eq!((e.line, e.column), (1, 13));
+
+        let e = error("places { p1 }");
+        assert_eq!(e.message, "expected `transitions`, found end of input");
+    }
+}
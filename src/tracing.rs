@@ -0,0 +1,152 @@
+use crate::petri_net::PetriNet;
+use crate::vasm::Vector;
+use crate::zblob::Zblob;
+
+/// The prefix of the targets of the spans, to filter them apart from those of the embedding service.
+pub const TARGET: &str = "pflow_metamodel";
+
+/// Returns the FNV-1a hash of the state, stable across runs and platforms so spans of
+/// different processes can be correlated by the marking they fired from.
+pub fn state_hash(state: &Vector) -> u64 {
+    state
+        .iter()
+        .flat_map(|tokens| tokens.to_le_bytes())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Returns the CID of the net as shared in a zblob, only computed when the span recording it is enabled.
+/// Attributes that are not integers, which `StateMachine::try_from_model` rejects, are hashed as text.
+pub fn model_cid(net: &PetriNet) -> String {
+    if net.non_integer_attribute().is_none() {
+        return Zblob::from_net(net).ipfs_cid;
+    }
+    let mut net = net.clone();
+    net.stringify_non_integer_attributes();
+    Zblob::from_net(&net).ipfs_cid
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fmt::Debug;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use ::tracing::field::{Field, Visit};
+    use ::tracing::span::{Attributes, Id, Record};
+    use ::tracing::{Event, Metadata, Subscriber};
+
+    use crate::analysis::reachability::{explore, Limits};
+    use crate::compression::compress_brotli_encode;
+    use crate::fixtures::DINING_PHILOSOPHERS;
+    use crate::vasm::{StateMachine, Vasm};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Fields(BTreeMap<String, String>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.insert(field.name().to_string(), format!("{:?}", value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    /// Keeps the fields of every span by its name and of every event, in the order they were recorded.
+    #[derive(Default)]
+    struct TestSubscriber {
+        next_id: AtomicU64,
+        spans: Mutex<Vec<(&'static str, Fields)>>,
+        events: Mutex<Vec<Fields>>,
+    }
+
+    impl TestSubscriber {
+        fn spans(&self, name: &str) -> Vec<BTreeMap<String, String>> {
+            let spans = self.spans.lock().unwrap();
+            spans
+                .iter()
+                .filter(|(n, _)| *n == name)
+                .map(|(_, fields)| fields.0.clone())
+                .collect()
+        }
+    }
+
+    impl Subscriber for TestSubscriber {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target().starts_with(TARGET)
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = Fields::default();
+            span.record(&mut fields);
+            self.spans.lock().unwrap().push((span.metadata().name(), fields));
+            Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            values.record(&mut spans[span.into_u64() as usize - 1].1);
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            self.events.lock().unwrap().push(fields);
+        }
+    }
+
+    #[test]
+    fn test_spans() {
+        let subscriber = Arc::new(TestSubscriber::default());
+        ::tracing::subscriber::with_default(subscriber.clone(), || {
+            let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+            let cid = model_cid(&net);
+            let sm = StateMachine::from_model(&mut net);
+            let state = sm.initial_vector();
+            sm.transform(&state, "eat1", 1);
+            explore(&sm, Limits::new(10));
+            compress_brotli_encode(DINING_PHILOSOPHERS);
+
+            let from_model = subscriber.spans("from_model");
+            assert_eq!(from_model[0]["model_cid"], cid);
+            assert_eq!(from_model[0]["places"], net.places.len().to_string());
+
+            let transform = subscriber.spans("transform");
+            assert_eq!(transform[0]["action"], "eat1");
+            assert_eq!(transform[0]["multiple"], "1");
+            assert_eq!(transform[0]["state_hash"], state_hash(&state).to_string());
+
+            let explore = subscriber.spans("explore");
+            assert_eq!(explore[0]["state_hash"], state_hash(&state).to_string());
+            let events = subscriber.events.lock().unwrap();
+            let explored = events
+                .iter()
+                .find(|e| e.0.get("message").is_some_and(|m| m == "explored"))
+                .unwrap();
+            assert_eq!(
+                (explored.0["states"].as_str(), explored.0["complete"].as_str()),
+                ("10", "false")
+            );
+
+            let compress = subscriber.spans("compress_stream");
+            assert_eq!(compress[0]["codec"], "Brotli { quality: 5 }");
+        });
+    }
+
+    #[test]
+    fn test_state_hash() {
+        assert_eq!(state_hash(&vec![]), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(state_hash(&vec![1, 0]), state_hash(&vec![1, 0]));
+        assert_ne!(state_hash(&vec![1, 0]), state_hash(&vec![0, 1]));
+    }
+}
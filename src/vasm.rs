@@ -1,36 +1,58 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
+use crate::capacity::Capacity;
 use crate::dsl::FlowDsl;
-use crate::petri_net::PetriNet;
+use crate::engine::{
+    self, configured_outcome, elementary_outcome, marking_delta, petri_net_outcome, workflow_outcome, Machine, MarkingArc,
+    Outcome, Threshold, Transfer,
+};
+use crate::guard::guards_block;
+use crate::layout;
+use crate::expr::{Condition, MarkingWeight, Variables};
+use crate::petri_net::{localized, PetriNet, RoleError, Timeout};
+use crate::roles::RoleRegistry;
+use crate::semantics::FiringSemantics;
+use crate::vector;
+
+pub use crate::engine::{ModelType, ReentryPolicy, SemanticsConfig, Vector};
 
 /// RoleMap is a type alias for a HashMap that maps a string to a boolean.
 pub type RoleMap = HashMap<String, bool>;
 
-/// Vector is a type alias for a vector of 32-bit integers.
-/// It is used to represent the state of a state machine and the delta of each transition or inhibitor.
-pub type Vector = Vec<i32>;
-
-/// ModelType is an enum that represents the type of model.
-/// It is used to determine the type of state machine to use.
-/// The possible values are `PetriNet`, `Elementary`, and `Workflow`.
-/// The default value is `PetriNet`.
-/// The `Elementary` model is a simplified version of the `PetriNet` model.
-/// The `Workflow` model is a simplified version of the `Elementary` model.
-/// The `PetriNet` model is the most complex and general model.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ModelType {
-    PetriNet,
-    Elementary,
-    Workflow,
-}
-
 /// Guard is a struct that represents a guard in a state machine.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Guard {
-    delta: Vector,
-    read: bool,
+    pub(crate) delta: Vector,
+    pub(crate) read: bool,
+    /// The offset of the guarded place and the arc weight, computed from `delta` on first use.
+    #[serde(skip)]
+    pub(crate) cached: OnceLock<(Option<usize>, i32)>,
+}
+
+impl Guard {
+    pub(crate) fn new(delta: Vector, read: bool) -> Self {
+        Self {
+            delta,
+            read,
+            cached: OnceLock::new(),
+        }
+    }
+
+    /// Returns the negated threshold of the guard, non-zero only at the offset of the guarded place.
+    pub fn delta(&self) -> &Vector {
+        &self.delta
+    }
+
+    /// Checks if the guard is a read arc, which requires the threshold instead of forbidding it.
+    pub fn read(&self) -> bool {
+        self.read
+    }
 }
 
 /// GuardMap is a type alias for a HashMap that maps a string to a `Guard`.
@@ -39,11 +61,25 @@ pub type GuardMap = HashMap<String, Guard>;
 /// Transition is a struct that represents a transition in a state machine.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transition {
-    label: String,
-    role: String,
-    delta: Vector,
-    guards: GuardMap,
-    allow_reentry: bool,
+    pub(crate) label: String,
+    pub(crate) role: String,
+    pub(crate) delta: Vector,
+    pub(crate) guards: GuardMap,
+    pub(crate) allow_reentry: bool,
+    #[serde(default)]
+    pub(crate) attributes: HashMap<String, Value>,
+    #[serde(default)]
+    pub(crate) display: Option<String>,
+    #[serde(default)]
+    pub(crate) labels: HashMap<String, String>,
+    #[serde(default)]
+    pub(crate) timeout: Option<Timeout>,
+    #[serde(default)]
+    pub(crate) condition: Option<Condition>,
+    #[serde(default)]
+    pub(crate) transfers: Vec<Transfer>,
+    #[serde(default)]
+    pub(crate) marking_arcs: Vec<MarkingArc>,
 }
 
 impl Default for Transition {
@@ -54,7 +90,119 @@ impl Default for Transition {
             delta: vec![],
             guards: GuardMap::new(),
             allow_reentry: false,
+            attributes: HashMap::new(),
+            display: None,
+            labels: HashMap::new(),
+            timeout: None,
+            condition: None,
+            transfers: Vec::new(),
+            marking_arcs: Vec::new(),
+        }
+    }
+}
+
+impl Transition {
+    /// Returns the identifier of the transition, the key in `StateMachine::transitions`.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the role allowed to fire the transition.
+    pub fn role(&self) -> &str {
+        &self.role
+    }
+
+    /// Returns the change of each place when the transition fires once, indexed like `StateMachine::places`.
+    pub fn delta(&self) -> &Vector {
+        &self.delta
+    }
+
+    /// Returns the inhibitor and read guards of the transition by place label.
+    pub fn guards(&self) -> &GuardMap {
+        &self.guards
+    }
+
+    /// Checks if the transition may fire into an already marked place under `ReentryPolicy::PerTransition`.
+    pub fn allow_reentry(&self) -> bool {
+        self.allow_reentry
+    }
+
+    /// Returns the deadline of the transition once it is enabled, if it has one.
+    pub fn timeout(&self) -> Option<&Timeout> {
+        self.timeout.as_ref()
+    }
+
+    /// Returns the condition over the case variables the transition requires to fire, if it has one.
+    pub fn condition(&self) -> Option<&Condition> {
+        self.condition.as_ref()
+    }
+
+    /// Returns the reset and transfer arcs of the transition in the order they apply.
+    pub fn transfers(&self) -> &[Transfer] {
+        &self.transfers
+    }
+
+    /// Returns the arcs of the transition whose weight is computed from the marking.
+    pub fn marking_arcs(&self) -> &[MarkingArc] {
+        &self.marking_arcs
+    }
+
+    /// Checks if the transition has no condition or its condition holds over the variables.
+    pub fn condition_holds(&self, variables: &Variables) -> bool {
+        self.condition.as_ref().map_or(true, |c| c.holds(variables))
+    }
+
+    /// Returns the name to show for the transition, see `PetriNet::display_label`.
+    /// Transformations always refer to the transition by its identifier.
+    pub fn display_label(&self, locale: Option<&str>) -> &str {
+        localized(&self.label, &self.display, &self.labels, locale)
+    }
+
+    /// Returns the custom attributes declared on the transition of the net.
+    pub fn attributes(&self) -> &HashMap<String, Value> {
+        &self.attributes
+    }
+}
+
+impl Transition {
+    /// Formats the transition with the effect of firing it once on each place, named by its label, such as
+    /// `sell by default: stock -2, sold +1, unless jammed ≥ 1`.
+    pub fn pretty(&self, sm: &StateMachine) -> String {
+        let place = |i: usize| sm.places.get(i).cloned().unwrap_or_else(|| format!("#{}", i));
+        let mut effects: Vec<String> = self
+            .delta
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| **d != 0)
+            .map(|(i, d)| format!("{} {:+}", place(i), d))
+            .collect();
+        effects.extend(self.marking_arcs.iter().map(|arc| {
+            let sign = if arc.consume { '-' } else { '+' };
+            let max = arc.max.map(|max| format!(" max {}", max)).unwrap_or_default();
+            let weight = format!("{}/{} of {}{}", arc.numerator, arc.denominator, place(arc.of), max);
+            format!("{} {}{}", place(arc.place), sign, weight)
+        }));
+        effects.extend(self.transfers.iter().map(|t| match t.to {
+            Some(to) => format!("{} => {} x{}", place(t.from), place(to), t.weight),
+            None => format!("reset {}", place(t.from)),
+        }));
+        let mut guards: Vec<&Guard> = self.guards.values().collect();
+        guards.sort_by_key(|g| g.place());
+        effects.extend(guards.into_iter().map(|g| {
+            let condition = if g.read() { "if" } else { "unless" };
+            format!("{} {} ≥ {}", condition, place(g.place().unwrap_or(usize::MAX)), g.weight())
+        }));
+        if effects.is_empty() {
+            return self.to_string();
         }
+        format!("{}: {}", self, effects.join(", "))
+    }
+}
+
+impl fmt::Display for Transition {
+    /// Formats the label and role of the transition, such as `sell by default`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} by {}", self.label, self.role)
     }
 }
 
@@ -66,13 +214,71 @@ pub type TransitionMap = HashMap<String, Transition>;
 pub struct StateMachine {
     pub model_type: ModelType,
     pub initial: Vector,
-    pub capacity: Vector,
+    /// The capacity of each place, indexed like `places`.
+    pub capacity: Vec<Capacity>,
     pub places: Vec<String>,
     pub transitions: TransitionMap,
     pub roles: RoleMap,
+    /// The roles declared by the net and the default role of transitions declared without one.
+    #[serde(default)]
+    pub registry: RoleRegistry,
+    /// The unit of the tokens held by each place, indexed like `places`.
+    #[serde(default)]
+    pub units: Vec<Option<String>>,
+    /// Decides when workflow transitions may fire into the marked place.
+    #[serde(default)]
+    pub reentry: ReentryPolicy,
+    /// The custom attributes of the net.
+    #[serde(default)]
+    pub attributes: HashMap<String, Value>,
+    /// The custom attributes of each place, indexed like `places`.
+    #[serde(default)]
+    pub place_attributes: Vec<HashMap<String, Value>>,
+    /// The semantics replacing those of the model type and the reentry policy, see `set_semantics`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub semantics: Option<SemanticsConfig>,
+    /// The custom semantics replacing every other firing rule, see `set_firing_semantics`.
+    #[serde(skip)]
+    pub(crate) firing: Option<Arc<dyn FiringSemantics>>,
+}
+
+impl StateMachine {
+    /// Formats the model type, the places with their initial tokens and bounded capacities, and the transitions
+    /// with their effects, one per line in label order, see `Transition::pretty`.
+    pub fn pretty(&self) -> String {
+        let mut lines = vec![model_type_to_string(&self.model_type).to_string(), "places:".to_string()];
+        lines.extend(self.places.iter().enumerate().map(|(i, label)| {
+            let tokens = self.format_tokens(i, self.initial.get(i).copied().unwrap_or(0));
+            match self.capacity.get(i).filter(|c| c.is_bounded()) {
+                Some(capacity) => format!("  {}: {} (capacity {})", label, tokens, capacity),
+                None => format!("  {}: {}", label, tokens),
+            }
+        }));
+        lines.push("transitions:".to_string());
+        let mut labels: Vec<&String> = self.transitions.keys().collect();
+        labels.sort();
+        lines.extend(labels.into_iter().map(|label| format!("  {}", self.transitions[label].pretty(self))));
+        lines.join("\n")
+    }
 }
 
-fn model_type_from_string(model_type: &str) -> ModelType {
+impl fmt::Display for StateMachine {
+    /// Formats the model type with the labels of the places and of the transitions in label order, such as
+    /// `petriNet with places stock, sold and transitions sell`, see `pretty` for the details.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut transitions: Vec<&str> = self.transitions.keys().map(String::as_str).collect();
+        transitions.sort();
+        write!(
+            f,
+            "{} with places {} and transitions {}",
+            model_type_to_string(&self.model_type),
+            self.places.join(", "),
+            transitions.join(", ")
+        )
+    }
+}
+
+pub(crate) fn model_type_from_string(model_type: &str) -> ModelType {
     match model_type {
         "elementary" => ModelType::Elementary,
         "workflow" => ModelType::Workflow,
@@ -80,22 +286,12 @@ fn model_type_from_string(model_type: &str) -> ModelType {
     }
 }
 
-fn vector_add(capacity: &Vector, state: &Vector, delta: &Vector, multiple: i32) -> (Vector, bool, bool, bool) {
-    let mut overflow = false;
-    let mut underflow = false;
-    let mut output: Vector = Vec::new();
-    let mut ok = true;
-    for i in 0..state.len() {
-        output.push(state[i] + delta.get(i).unwrap_or(&0) * multiple);
-        if output[i] < 0 {
-            underflow = true;
-            ok = false; // underflow: contains negative
-        } else if capacity[i] > 0 && capacity[i] - output[i] < 0 {
-            overflow = true;
-            ok = false; // overflow: exceeds capacity
-        }
+fn model_type_to_string(model_type: &ModelType) -> &'static str {
+    match model_type {
+        ModelType::PetriNet => "petriNet",
+        ModelType::Elementary => "elementary",
+        ModelType::Workflow => "workflow",
     }
-    (output, ok, overflow, underflow)
 }
 
 impl StateMachine {
@@ -104,38 +300,96 @@ impl StateMachine {
         let net = &mut PetriNet::new();
         let mut sm = net.declare(declaration).as_vasm();
         sm.model_type = model_type_from_string(&net.model_type);
-        return sm;
+        sm
     }
 
-    /// Creates a new `StateMachine` object from the given `PetriNet`.
+    /// Creates a new `StateMachine` object from the given `PetriNet`, flattening its substitution transitions.
+    ///
+    /// # Panics
+    ///
+    /// * If the model is invalid, see `StateMachine::try_from_model`.
+    ///
     pub fn from_model(model: &mut PetriNet) -> Self {
+        Self::try_from_model(model).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a new `StateMachine` object from the given `PetriNet` like `from_model`, for models that were not
+    /// checked yet, such as those uploaded by clients.
+    ///
+    /// # Returns
+    ///
+    /// * The state machine, or a `ModelError` describing the first invalid element of the model.
+    ///
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "from_model", level = "debug", skip_all, fields(
+        model_cid = crate::tracing::model_cid(model),
+        places = model.places.len(),
+        transitions = model.transitions.len(),
+    )))]
+    pub fn try_from_model(model: &mut PetriNet) -> Result<Self, ModelError> {
+        if model.has_subnets() {
+            return Self::try_from_model(&mut model.flatten());
+        }
+        if let Some((node, key)) = model.non_integer_attribute() {
+            return Err(ModelError::NonIntegerAttribute {
+                node: node.to_string(),
+                key: key.to_string(),
+            });
+        }
+        model.validate_roles().map_err(ModelError::InvalidRoles)?;
         let model_type = model_type_from_string(&model.model_type);
         model.populate_arc_attributes();
+        let default_role = model.default_role().to_string();
         let mut roles = RoleMap::new();
         model.transitions.iter().for_each(|(_, v)| {
-            roles.insert(v.role.clone().unwrap_or("default".to_string()), true);
+            roles.insert(v.role.clone().unwrap_or(default_role.clone()), true);
         });
 
         let vector_size = model.places.len();
+        let mut offsets = vec![false; vector_size];
+        for (label, place) in &model.places {
+            match usize::try_from(place.offset).ok().and_then(|i| offsets.get_mut(i)) {
+                Some(taken) if !*taken => *taken = true,
+                _ => {
+                    return Err(ModelError::InvalidOffset {
+                        place: label.clone(),
+                        offset: place.offset,
+                    })
+                }
+            }
+        }
+        let offset = |label: &str| model.places.get(label).map(|p| p.offset as usize);
 
         let mut transitions: TransitionMap = model
             .transitions
             .iter()
             .map(|(k, v)| {
-                (
+                let condition = v.condition.as_deref().map(Condition::parse).transpose().map_err(|err| {
+                    ModelError::InvalidCondition {
+                        transition: k.clone(),
+                        reason: err.to_string(),
+                    }
+                })?;
+                Ok((
                     k.clone(),
                     Transition {
                         label: k.clone(),
-                        role: v.role.clone().unwrap_or("default".to_string()),
+                        role: v.role.clone().unwrap_or(default_role.clone()),
                         delta: vec![0; vector_size],
                         guards: GuardMap::new(),
-                        allow_reentry: false,
+                        allow_reentry: v.allow_reentry,
+                        attributes: v.attributes.clone(),
+                        display: v.label.clone(),
+                        labels: v.labels.clone(),
+                        timeout: v.timeout.clone(),
+                        condition,
+                        transfers: Vec::new(),
+                        marking_arcs: Vec::new(),
                     },
-                )
+                ))
             })
-            .collect();
+            .collect::<Result<_, _>>()?;
 
-        model.arcs.iter().for_each(|arc| {
+        for arc in &model.arcs {
             let source = arc.source.clone();
             let target = arc.target.clone();
             let weight = arc.weight.unwrap_or(1);
@@ -143,43 +397,86 @@ impl StateMachine {
             let produce = arc.produce.unwrap_or(false);
             let inhibit = arc.inhibit.unwrap_or(false);
             let read = arc.read.unwrap_or(false);
+            let dangling = || ModelError::DanglingArc {
+                source: source.clone(),
+                target: target.clone(),
+            };
+
+            if arc.reset.unwrap_or(false) || arc.transfer.is_some() {
+                let to = arc.transfer.as_ref().map(|place| offset(place).ok_or_else(dangling)).transpose()?;
+                let from = offset(&source).ok_or_else(dangling)?;
+                let transition = transitions.get_mut(&target).ok_or_else(dangling)?;
+                transition.transfers.push(Transfer { from, to, weight });
+                continue;
+            }
+
+            if let (Some(marking_weight), false) = (&arc.marking_weight, inhibit) {
+                let w = MarkingWeight::parse(marking_weight).map_err(|err| ModelError::InvalidMarkingWeight {
+                    source: source.clone(),
+                    target: target.clone(),
+                    reason: err.to_string(),
+                })?;
+                let (place, transition) = if consume { (&source, &target) } else { (&target, &source) };
+                let place = offset(place).ok_or_else(dangling)?;
+                let of = match w.of {
+                    Some(of) => offset(&of).ok_or_else(dangling)?,
+                    None => place,
+                };
+                transitions.get_mut(transition).ok_or_else(dangling)?.marking_arcs.push(MarkingArc {
+                    place,
+                    consume,
+                    of,
+                    numerator: w.numerator,
+                    denominator: w.denominator,
+                    max: w.max,
+                });
+                continue;
+            }
 
             let p = if read || produce {
-                model.places.get(&target)
+                offset(&target)
             } else {
-                model.places.get(&source)
-            }.unwrap();
+                offset(&source)
+            }.ok_or_else(dangling)?;
 
             let t = if read || produce {
                 transitions.get_mut(&source)
             } else {
                 transitions.get_mut(&target)
-            }.unwrap();
+            }.ok_or_else(dangling)?;
 
             let delta = &mut vec![0; vector_size];
-            delta[p.offset as usize] = 0 - weight;
+            delta[p] = 0 - weight;
 
             if inhibit {
+                let place = if read || produce { &target } else { &source };
                 t.guards.insert(
-                    target.clone(),
-                    Guard { delta: delta.clone(), read },
+                    place.clone(),
+                    Guard::new(delta.clone(), read),
                 );
             } else {
                 if consume {
-                    t.delta[model.places.get(&source).unwrap().offset as usize] = 0 - weight;
+                    t.delta[offset(&source).ok_or_else(dangling)?] = 0 - weight;
                 } else {
-                    t.delta[model.places.get(&target).unwrap().offset as usize] = weight;
+                    t.delta[offset(&target).ok_or_else(dangling)?] = weight;
                 }
             }
-        });
+        }
 
         let mut initial = vec![0; vector_size];
-        let mut capacity = vec![0; vector_size];
+        let mut capacity = vec![Capacity::Unbounded; vector_size];
         let mut places = vec!["".to_string(); vector_size];
+        let mut units = vec![None; vector_size];
+        let mut place_attributes = vec![HashMap::new(); vector_size];
 
-        model.places.iter().for_each(|(k, v)| {
+        for (k, v) in &model.places {
             let i = v.initial.unwrap_or(0);
-            assert!(i >= 0, "initial must be non-negative");
+            if i < 0 {
+                return Err(ModelError::NegativeInitial {
+                    place: k.clone(),
+                    initial: i,
+                });
+            }
 
             initial[v.offset as usize] = match model_type {
                 ModelType::PetriNet => i,
@@ -194,99 +491,416 @@ impl StateMachine {
             };
 
             capacity[v.offset as usize] = match model_type {
-                ModelType::PetriNet => v.capacity.unwrap_or(0),
-                ModelType::Elementary => 1,
-                ModelType::Workflow => 1,
+                ModelType::PetriNet => v.capacity.unwrap_or_default(),
+                ModelType::Elementary => Capacity::Bounded(1),
+                ModelType::Workflow => Capacity::Bounded(1),
             };
             places[v.offset as usize] = k.clone();
-        });
+            units[v.offset as usize] = v.unit.clone();
+            place_attributes[v.offset as usize] = v.attributes.clone();
+        }
 
-        Self {
+        Ok(Self {
             model_type: model_type_from_string(&model.model_type),
             initial,
             capacity,
             places,
             transitions,
             roles,
+            registry: RoleRegistry::from_net(model),
+            units,
+            reentry: ReentryPolicy::default(),
+            attributes: model.attributes.clone(),
+            place_attributes,
+            semantics: None,
+            firing: None,
+        })
+    }
+
+    /// Fires the transitions according to the given semantics instead of those of the model type.
+    ///
+    /// A place capacity forced by the config replaces the capacity of every place when firing, the declared
+    /// capacities and initial marking are kept, see `place_capacity`.
+    pub fn set_semantics(&mut self, config: SemanticsConfig) -> &mut Self {
+        self.semantics = Some(config);
+        self
+    }
+
+    /// Returns the capacity the place at the offset fires with, the one forced by the semantics if any.
+    pub fn place_capacity(&self, offset: usize) -> Capacity {
+        self.semantics
+            .and_then(|config| config.place_capacity)
+            .unwrap_or_else(|| self.capacity.get(offset).copied().unwrap_or_default())
+    }
+
+    /// Returns the semantics the transitions fire with, the preset of the model type with the reentry
+    /// policy unless `set_semantics` replaced them.
+    pub fn semantics_config(&self) -> SemanticsConfig {
+        self.semantics
+            .unwrap_or_else(|| SemanticsConfig::preset(&self.model_type).with_reentry(self.reentry))
+    }
+
+    /// Fires the transitions with custom semantics instead of any built-in rules, see `FiringSemantics`.
+    pub fn set_firing_semantics(&mut self, semantics: impl FiringSemantics + 'static) -> &mut Self {
+        self.firing = Some(Arc::new(semantics));
+        self
+    }
+
+    /// Returns the custom semantics the transitions fire with, if any.
+    pub fn firing_semantics(&self) -> Option<&dyn FiringSemantics> {
+        self.firing.as_deref()
+    }
+
+    /// Reconstructs a `PetriNet` from the vectorized form, laid out with `layout::auto`.
+    ///
+    /// Arcs are derived from the transition deltas and guards, so a place both consumed and produced
+    /// by the same transition has no net change and gets no arcs. Coordinates and the display labels
+    /// of places are not part of the vectorized form and are lost.
+    pub fn to_model(&self) -> PetriNet {
+        let mut net = PetriNet::new();
+        net.model_type = model_type_to_string(&self.model_type).to_string();
+        net.attributes = self.attributes.clone();
+        self.registry.write_to(&mut net);
+        for (offset, label) in self.places.iter().enumerate() {
+            net.add_place(label, offset as i32, Some(self.initial[offset]), None, 0, 0);
+            let place = net.places.get_mut(label).unwrap();
+            place.capacity = Some(self.capacity[offset]);
+            place.unit = self.units.get(offset).cloned().flatten();
+            place.attributes = self.place_attributes.get(offset).cloned().unwrap_or_default();
+        }
+
+        let mut actions: Vec<&String> = self.transitions.keys().collect();
+        actions.sort();
+        for action in actions {
+            let t = &self.transitions[action];
+            net.add_transition(action, &t.role, 0, 0);
+            let transition = net.transitions.get_mut(action).unwrap();
+            transition.allow_reentry = t.allow_reentry;
+            transition.label.clone_from(&t.display);
+            transition.labels.clone_from(&t.labels);
+            transition.attributes.clone_from(&t.attributes);
+            transition.timeout.clone_from(&t.timeout);
+            transition.condition = t.condition.as_ref().map(|c| c.source().to_string());
+
+            for (offset, weight) in t.delta.iter().enumerate().filter(|(_, w)| **w != 0) {
+                let place = &self.places[offset];
+                if *weight < 0 {
+                    net.add_arc(place, action, Some(-weight), None, None, None, None);
+                } else {
+                    net.add_arc(action, place, Some(*weight), None, None, None, None);
+                }
+            }
+            let mut guarded: Vec<&String> = t.guards.keys().collect();
+            guarded.sort();
+            for place in guarded {
+                let guard = &t.guards[place];
+                let weight = -guard.delta.iter().sum::<i32>();
+                if guard.read {
+                    net.add_arc(action, place, Some(weight), None, None, Some(true), None);
+                } else {
+                    net.add_arc(place, action, Some(weight), None, None, Some(true), None);
+                }
+            }
+            for transfer in &t.transfers {
+                let from = &self.places[transfer.from];
+                match transfer.to {
+                    Some(to) => net.add_transfer_arc(from, action, &self.places[to], transfer.weight),
+                    None => net.add_reset_arc(from, action),
+                }
+            }
+            for arc in &t.marking_arcs {
+                let place = &self.places[arc.place];
+                let weight = MarkingWeight {
+                    numerator: arc.numerator,
+                    denominator: arc.denominator,
+                    of: (arc.of != arc.place).then(|| self.places[arc.of].clone()),
+                    max: arc.max,
+                }
+                .to_string();
+                if arc.consume {
+                    net.add_marking_arc(place, action, &weight);
+                } else {
+                    net.add_marking_arc(action, place, &weight);
+                }
+            }
         }
+        net.populate_arc_attributes();
+        layout::auto(&mut net);
+        net
     }
 
-    /// Checks if any guard fails for the given state and transition.
-    fn guard_fails(&self, state: &Vector, transition: &Transition, multiple: i32) -> Result<bool, &'static str> {
-        for (_, guard) in &transition.guards {
-            let (_, threshold_met, _, _) = vector_add(&self.capacity, state, &guard.delta, multiple);
-            return if guard.read {
-                Ok(!threshold_met) // read arc enables after a threshold
-            } else {
-                Ok(threshold_met) // guard inhibits until a threshold
-            };
+    /// Returns the (place, weight) pairs of the places changed by firing the action once,
+    /// negative weights are consumed and positive ones produced, or None if there is no such action.
+    pub fn weights<'a>(&'a self, action: &str) -> Option<impl Iterator<Item = (&'a str, i32)> + 'a> {
+        let transition = self.transitions.get(action)?;
+        Some(
+            self.places
+                .iter()
+                .zip(&transition.delta)
+                .filter(|(_, w)| **w != 0)
+                .map(|(p, w)| (p.as_str(), *w)),
+        )
+    }
+
+    /// Checks if any guard blocks the transition in the given state, see the `guard` module for the semantics.
+    pub fn is_inhibited(&self, state: &Vector, transition: &Transition, multiple: i32) -> bool {
+        self.guard_fails(state, transition, multiple)
+    }
+
+    fn guard_fails(&self, state: &Vector, transition: &Transition, multiple: i32) -> bool {
+        guards_block(&transition.guards, state, multiple)
+    }
+
+    /// Fires the action on behalf of `role` like `Vasm::transform`, rejecting it with `TransformError::RoleMismatch`
+    /// if the transition belongs to another role that `role` does not imply, see `RoleRegistry::may_fire`.
+    pub fn transform_as(&self, state: &Vector, action: &str, multiple: i32, role: &str) -> Transaction {
+        match self.transitions.get(action) {
+            Some(t) if !self.registry.may_fire(role, &t.role) => {
+                let error = TransformError::RoleMismatch {
+                    expected: t.role.clone(),
+                    actual: role.to_string(),
+                };
+                Transaction::rejected(state, &t.role, error).with_action(state, action, multiple)
+            }
+            _ => self.transform(state, action, multiple),
         }
-        Ok(false)
     }
-    pub fn petri_net_fire(&self, state: &Vector, transition: &Transition, multiple: i32) -> Transaction {
-        let role = transition.role.clone();
-        let (output, ok, overflow, underflow) = vector_add(&self.capacity, state, &transition.delta, multiple);
-        let inhibited = self.guard_fails(state, transition, multiple).unwrap();
 
-        Transaction {
-            output,
-            ok: ok && !inhibited,
-            role,
-            inhibited,
-            overflow,
-            underflow,
+    /// Fires the action on behalf of `role` like `transform_as`, additionally requiring the condition of the
+    /// transition to hold over the case variables, the transaction is inhibited otherwise.
+    ///
+    /// `Vasm::transform` ignores conditions, so analyses of the token game treat every condition as satisfiable.
+    pub fn transform_with(
+        &self,
+        state: &Vector,
+        action: &str,
+        multiple: i32,
+        role: &str,
+        variables: &Variables,
+    ) -> Transaction {
+        let res = self.transform_as(state, action, multiple, role);
+        match self.transitions.get(action) {
+            Some(t) if res.is_ok() && !t.condition_holds(variables) => {
+                Transaction::inhibited(state, &t.role).with_action(state, action, multiple)
+            }
+            _ => res,
         }
     }
 
-    pub fn elementary_fire(&self, state: &Vector, transition: &Transition, multiple: i32) -> Transaction {
-        let role = transition.role.clone();
-        let (output, ok, overflow, underflow) = vector_add(&self.capacity, state, &transition.delta, multiple);
-        let inhibited = self.guard_fails(state, transition, multiple).unwrap_or(false);
-        let output_state_count = output.iter().filter(|&x| *x > 0).count();
-        let elementary_ok = ok && output_state_count == 1 && !inhibited;
-        Transaction {
-            output,
-            ok: elementary_ok,
-            role,
-            inhibited,
-            overflow,
-            underflow,
+    /// Applies a sequence of (action, multiple) pairs atomically.
+    ///
+    /// # Returns
+    ///
+    /// * The final state and the transaction of every action, or a `SequenceError` for the first
+    ///   failing action, in which case the input state is left as it was.
+    ///
+    #[allow(clippy::result_large_err)]
+    pub fn transform_seq(&self, state: &Vector, actions: &[(&str, i32)]) -> Result<(Vector, Vec<Transaction>), SequenceError> {
+        let mut current = state.clone();
+        let mut transactions = Vec::with_capacity(actions.len());
+        for (index, (action, multiple)) in actions.iter().enumerate() {
+            let transaction = self.transform(&current, action, *multiple);
+            if transaction.is_err() {
+                return Err(SequenceError { index, transaction });
+            }
+            current.clone_from(&transaction.output);
+            transactions.push(transaction);
+        }
+        Ok((current, transactions))
+    }
+
+    /// Checks if the state machine has no places, in which case every transformation is rejected.
+    pub fn is_empty(&self) -> bool {
+        self.places.is_empty()
+    }
+
+    /// Formats a token count of the place at the given offset with the unit of the place.
+    pub fn format_tokens(&self, offset: usize, count: i32) -> String {
+        match self.units.get(offset).and_then(|u| u.as_deref()) {
+            Some(unit) => format!("{} {}", count, unit),
+            None => count.to_string(),
+        }
+    }
+
+    /// Formats a state as a comma separated list of place labels and token counts with their units.
+    pub fn format_marking(&self, state: &Vector) -> String {
+        self.places
+            .iter()
+            .zip(state)
+            .enumerate()
+            .map(|(i, (label, count))| format!("{}: {}", label, self.format_tokens(i, *count)))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Exports the state machine as a `Machine`, which fires the same transitions without the standard library.
+    ///
+    /// Transitions are numbered in label order like `CompiledVasm`, roles and attributes are dropped.
+    pub fn to_machine(&self) -> Machine {
+        let mut labels: Vec<&String> = self.transitions.keys().collect();
+        labels.sort();
+        let transitions = labels
+            .into_iter()
+            .map(|label| {
+                let transition = &self.transitions[label];
+                let guards = transition.guards.values().map(|g| Threshold {
+                    // A guard on no place compares the threshold against zero tokens, as does an offset past the state.
+                    place: g.place().unwrap_or(usize::MAX),
+                    weight: g.weight(),
+                    kind: g.kind(),
+                });
+                engine::Transition {
+                    label: label.clone(),
+                    delta: transition.delta.clone(),
+                    guards: guards.collect(),
+                    allow_reentry: transition.allow_reentry,
+                    transfers: transition.transfers.clone(),
+                    marking_arcs: transition.marking_arcs.clone(),
+                }
+            })
+            .collect();
+        Machine {
+            model_type: self.model_type.clone(),
+            initial: self.initial.clone(),
+            capacity: self.capacity.clone(),
+            transitions,
+            reentry: self.reentry,
+            semantics: self.semantics,
         }
     }
 
+    /// Checks if the action can fire in the given state without building its output state.
+    ///
+    /// Guards are checked first and the check stops at the first failing one, so this is the cheap way
+    /// to find the enabled transitions. For petri-nets the token counts are then checked place by place
+    /// without allocating, elementary and workflow models fall back to a full transformation.
+    pub fn is_enabled(&self, state: &Vector, action: &str, multiple: i32) -> bool {
+        match self.transitions.get(action) {
+            Some(transition) => self.transition_enabled(state, transition, multiple),
+            None => false,
+        }
+    }
+
+    pub(crate) fn transition_enabled(&self, state: &Vector, transition: &Transition, multiple: i32) -> bool {
+        if self.is_empty() || state.len() != self.places.len() {
+            return false;
+        }
+        if let Some(semantics) = &self.firing {
+            return semantics.fire(self, state, transition, multiple).is_ok();
+        }
+        if self.guard_fails(state, transition, multiple) {
+            return false;
+        }
+        let plain = transition.transfers.is_empty() && transition.marking_arcs.is_empty();
+        if !plain || !self.semantics_config().is_concurrent() {
+            return self.outcome(state, transition, multiple).ok;
+        }
+        state.iter().enumerate().all(|(i, tokens)| {
+            let output = vector::fired(*tokens, *transition.delta.get(i).unwrap_or(&0), multiple);
+            let capacity = self.place_capacity(i);
+            output >= 0 && i32::try_from(output).is_ok_and(|output| capacity.allows(output))
+        })
+    }
+
+    /// Fires a petri-net transition, an inhibited transition is rejected before any arithmetic
+    /// and returns the input state unchanged.
+    pub fn petri_net_fire(&self, state: &Vector, transition: &Transition, multiple: i32) -> Transaction {
+        self.petri_net_outcome(state, transition, multiple).with_role(&transition.role)
+    }
+
+    /// Fires an elementary transition, an inhibited transition is rejected before any arithmetic
+    /// and returns the input state unchanged.
+    pub fn elementary_fire(&self, state: &Vector, transition: &Transition, multiple: i32) -> Transaction {
+        self.elementary_outcome(state, transition, multiple).with_role(&transition.role)
+    }
+
+    /// Checks if the reentry policy lets the transition fire into the marked place.
+    pub fn allows_reentry(&self, transition: &Transition) -> bool {
+        self.reentry.allows(transition.allow_reentry)
+    }
+
+    /// Fires a workflow transition, which behaves like an elementary one unless it reenters.
+    ///
+    /// A reentering firing overflows the marked place, it is accepted when it is not inhibited,
+    /// leaves exactly one place marked once every count is clamped to zero or one,
+    /// and the reentry policy allows it. An inhibited transition is rejected before any arithmetic.
     pub fn workflow_fire(&self, state: &Vector, transition: &Transition, multiple: i32) -> Transaction {
-        let role = transition.role.clone();
-        let (output, ok, overflow, underflow) = vector_add(&self.capacity, state, &transition.delta, multiple);
-        let inhibited = self.guard_fails(state, transition, multiple).unwrap();
-        let workflow_output = output.iter().map(|x| {
-            match x {
-                -1 => 0, // allow retry / reentry
-                0 => 0,
-                1 => 1,
-                2 => 1, // allow reentry
-                _ => 1, // no other values allowed
+        self.workflow_outcome(state, transition, multiple).with_role(&transition.role)
+    }
+
+    /// Fires the transition according to the semantics, the caller checks the action and the state dimension.
+    pub(crate) fn outcome(&self, state: &Vector, transition: &Transition, multiple: i32) -> Outcome {
+        if let Some(config) = &self.semantics {
+            if self.guard_fails(state, transition, multiple) {
+                return Outcome::inhibited(state);
             }
-        }).collect::<Vec<i32>>();
-        let output_state_count = workflow_output.iter().filter(|&x| *x > 0).count();
-        if !inhibited && overflow && output_state_count == 1 && transition.allow_reentry {
-            return Transaction {
-                output: workflow_output,
-                ok: true,
-                role,
-                inhibited,
-                overflow: false,
-                underflow,
+            let Some((delta, multiple)) = firing_delta(state, transition, multiple) else {
+                return Outcome::overflowed(state);
             };
+            let allow_reentry = config.reentry.allows(transition.allow_reentry);
+            return configured_outcome(config, &self.capacity, state, &delta, multiple, allow_reentry);
+        }
+        match self.model_type {
+            ModelType::PetriNet => self.petri_net_outcome(state, transition, multiple),
+            ModelType::Elementary => self.elementary_outcome(state, transition, multiple),
+            ModelType::Workflow => self.workflow_outcome(state, transition, multiple),
         }
-        let workflow_ok = ok && output_state_count == 1 && !inhibited;
+    }
 
+    fn petri_net_outcome(&self, state: &Vector, transition: &Transition, multiple: i32) -> Outcome {
+        if self.guard_fails(state, transition, multiple) {
+            return Outcome::inhibited(state);
+        }
+        let Some((delta, multiple)) = firing_delta(state, transition, multiple) else {
+            return Outcome::overflowed(state);
+        };
+        petri_net_outcome(&self.capacity, state, &delta, multiple)
+    }
+
+    fn elementary_outcome(&self, state: &Vector, transition: &Transition, multiple: i32) -> Outcome {
+        if self.guard_fails(state, transition, multiple) {
+            return Outcome::inhibited(state);
+        }
+        let Some((delta, multiple)) = firing_delta(state, transition, multiple) else {
+            return Outcome::overflowed(state);
+        };
+        elementary_outcome(&self.capacity, state, &delta, multiple)
+    }
+
+    fn workflow_outcome(&self, state: &Vector, transition: &Transition, multiple: i32) -> Outcome {
+        if self.guard_fails(state, transition, multiple) {
+            return Outcome::inhibited(state);
+        }
+        let Some((delta, multiple)) = firing_delta(state, transition, multiple) else {
+            return Outcome::overflowed(state);
+        };
+        workflow_outcome(&self.capacity, state, &delta, multiple, self.allows_reentry(transition))
+    }
+}
+
+/// Returns the delta to add `multiple` times to the state, with the marking arcs and transfers of the
+/// transition folded in, or None if the change of a place does not fit in an i32.
+fn firing_delta<'a>(state: &Vector, transition: &'a Transition, multiple: i32) -> Option<(Cow<'a, Vector>, i32)> {
+    match marking_delta(state, &transition.delta, multiple, &transition.marking_arcs, &transition.transfers).ok()? {
+        Some((delta, multiple)) => Some((Cow::Owned(delta), multiple)),
+        None => Some((Cow::Borrowed(&transition.delta), multiple)),
+    }
+}
+
+impl Outcome {
+    pub(crate) fn with_role(self, role: &str) -> Transaction {
         Transaction {
-            output,
-            ok: workflow_ok,
-            role,
-            inhibited,
-            overflow,
-            underflow,
+            ok: self.ok,
+            output: self.output,
+            role: role.to_string(),
+            inhibited: self.inhibited,
+            overflow: self.overflow,
+            underflow: self.underflow,
+            error: None,
+            action: String::new(),
+            multiple: 0,
+            net_consumed: Vector::new(),
+            net_produced: Vector::new(),
         }
     }
 }
@@ -304,12 +918,231 @@ pub struct Transaction {
     /// An optional boolean indicating whether the transformation was inhibited.
     pub inhibited: bool,
     /// An optional boolean indicating whether an overflow occurred during the transformation.
+    ///
+    /// Inhibited transformations are rejected before any arithmetic, so they never report an overflow
+    /// or an underflow and their output is the input state.
     pub overflow: bool,
-    /// An optional boolean indicating whether an underflow occurred during the transformation.
+    /// An optional boolean indicating whether an underflow occurred during the transformation, see `overflow`.
     pub underflow: bool,
+    /// The reason the transformation was rejected before any arithmetic was attempted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<TransformError>,
+    /// The fired action, the actions of a step separated by commas.
+    #[serde(default)]
+    pub action: String,
+    /// The number of times the action was fired.
+    #[serde(default)]
+    pub multiple: i32,
+    /// The net decrease of each place, indexed like the places, empty if the transformation was rejected
+    /// or inhibited before any arithmetic. For an overflow or underflow it is the change that was refused.
+    /// Places a transition only reads, or consumes and produces alike, have no net change.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub net_consumed: Vector,
+    /// The net increase of each place, indexed like the places, empty like `net_consumed`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub net_produced: Vector,
+}
+
+/// `PlaceChange` is the change of the tokens held by a place between two states, see `diff_states`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaceChange {
+    /// The label of the place, or its offset prefixed with `#` if it has none.
+    pub place: String,
+    pub before: i32,
+    pub after: i32,
+}
+
+impl PlaceChange {
+    /// Returns the number of tokens added to the place, negative if tokens were removed.
+    pub fn delta(&self) -> i32 {
+        self.after - self.before
+    }
+}
+
+impl fmt::Display for PlaceChange {
+    /// Formats the change as `queue: 3 → 2`, flagging a negative count such as `queue: 0 → -1 (negative)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} → {}", self.place, self.before, self.after)?;
+        if self.before < 0 || self.after < 0 {
+            write!(f, " (negative)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the places holding a different number of tokens in `b` than in `a`, in offset order.
+///
+/// A missing entry counts as zero tokens and negative counts are compared like any other, so the output
+/// of a transaction that underflowed can be diffed against its input to debug a bad delta. Places without
+/// a label in `places` are named by their offset, such as `#3`.
+pub fn diff_states(a: &Vector, b: &Vector, places: &[String]) -> Vec<PlaceChange> {
+    (0..a.len().max(b.len()))
+        .filter_map(|i| {
+            let (before, after) = (*a.get(i).unwrap_or(&0), *b.get(i).unwrap_or(&0));
+            (before != after).then(|| PlaceChange {
+                place: places.get(i).cloned().unwrap_or_else(|| format!("#{}", i)),
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+/// `ModelError` describes why a `PetriNet` cannot be loaded as a state machine, see `StateMachine::try_from_model`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelError {
+    /// The arc, or the place it transfers to or weighs by, does not connect a place and a transition of the model.
+    DanglingArc { source: String, target: String },
+    /// The offset of the place is out of range or shared with another place.
+    InvalidOffset { place: String, offset: i32 },
+    /// The initial tokens of the place are negative.
+    NegativeInitial { place: String, initial: i32 },
+    /// The condition of the transition does not parse.
+    InvalidCondition { transition: String, reason: String },
+    /// The marking weight of the arc does not parse.
+    InvalidMarkingWeight {
+        source: String,
+        target: String,
+        reason: String,
+    },
+    /// An attribute of the node, or of the net if `node` is empty, holds a number that is not an integer.
+    NonIntegerAttribute { node: String, key: String },
+    /// The net declares roles but uses one it does not declare, see `PetriNet::validate_roles`.
+    InvalidRoles(RoleError),
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::DanglingArc { source, target } => {
+                write!(f, "arc {} -> {} does not connect a place and a transition", source, target)
+            }
+            ModelError::InvalidOffset { place, offset } => write!(f, "place {} has an invalid offset {}", place, offset),
+            ModelError::NegativeInitial { place, initial } => {
+                write!(f, "place {} starts with {} tokens", place, initial)
+            }
+            ModelError::InvalidCondition { transition, reason } => {
+                write!(f, "invalid condition of {}: {}", transition, reason)
+            }
+            ModelError::InvalidMarkingWeight { source, target, reason } => {
+                write!(f, "invalid marking weight of arc {} -> {}: {}", source, target, reason)
+            }
+            ModelError::NonIntegerAttribute { node, key } => {
+                let node = if node.is_empty() { "the net" } else { node };
+                write!(f, "attribute {} of {} is not an integer", key, node)
+            }
+            ModelError::InvalidRoles(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+/// `TransformError` describes a transformation that could not be evaluated against the state machine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum TransformError {
+    /// The action does not name a transition of the state machine.
+    UnknownAction { action: String },
+    /// The state machine has no places, so there is no state to transform.
+    EmptyModel,
+    /// The state vector does not have one entry per place of the state machine.
+    DimensionMismatch { expected: usize, actual: usize },
+    /// The transition belongs to the `expected` role but was fired on behalf of `actual`.
+    RoleMismatch { expected: String, actual: String },
+    /// The role already fired the transition `limit` times in the window of one of its quotas, see `quota::QuotaPolicy`.
+    QuotaExceeded { role: String, action: String, limit: u32 },
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransformError::UnknownAction { action } => write!(f, "no transition for {}", action),
+            TransformError::EmptyModel => write!(f, "model has no places"),
+            TransformError::DimensionMismatch { expected, actual } => {
+                write!(f, "expected a state of {} places, got {}", expected, actual)
+            }
+            TransformError::RoleMismatch { expected, actual } => {
+                write!(f, "transition belongs to role {}, not {}", expected, actual)
+            }
+            TransformError::QuotaExceeded { role, action, limit } => {
+                write!(f, "role {} may fire {} at most {} times", role, action, limit)
+            }
+        }
+    }
 }
 
+impl std::error::Error for TransformError {}
+
+/// `SequenceError` reports the step at which an atomic sequence of actions failed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceError {
+    /// The position of the failed action in the sequence.
+    pub index: usize,
+    /// The failed transaction.
+    pub transaction: Transaction,
+}
+
+impl fmt::Display for SequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "action {} of the sequence failed", self.index)?;
+        match &self.transaction.error {
+            Some(error) => write!(f, ": {}", error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for SequenceError {}
+
 impl Transaction {
+    /// Creates a failed transaction that leaves the state unchanged.
+    pub fn rejected(state: &Vector, role: &str, error: TransformError) -> Self {
+        Self {
+            ok: false,
+            output: state.clone(),
+            role: role.to_string(),
+            inhibited: false,
+            overflow: false,
+            underflow: false,
+            error: Some(error),
+            action: String::new(),
+            multiple: 0,
+            net_consumed: Vector::new(),
+            net_produced: Vector::new(),
+        }
+    }
+
+    /// Creates a failed transaction for a transition blocked by one of its guards, the state is left unchanged
+    /// and the overflow and underflow flags are not computed.
+    pub fn inhibited(state: &Vector, role: &str) -> Self {
+        Self {
+            ok: false,
+            output: state.clone(),
+            role: role.to_string(),
+            inhibited: true,
+            overflow: false,
+            underflow: false,
+            error: None,
+            action: String::new(),
+            multiple: 0,
+            net_consumed: Vector::new(),
+            net_produced: Vector::new(),
+        }
+    }
+
+    /// Records the fired action and multiple, and unless the transaction was rejected or inhibited before
+    /// any arithmetic, the net change of each place from the input state split into `net_consumed` and `net_produced`.
+    pub fn with_action(mut self, state: &Vector, action: &str, multiple: i32) -> Self {
+        self.action = action.to_string();
+        self.multiple = multiple;
+        if self.error.is_none() && !self.inhibited && state.len() == self.output.len() {
+            let change = state.iter().zip(&self.output).map(|(before, after)| after - before);
+            (self.net_consumed, self.net_produced) = change.map(|d| ((-d).max(0), d.max(0))).unzip();
+        }
+        self
+    }
+
     /// Checks if the transaction was successful.
     ///
     /// # Returns
@@ -323,6 +1156,135 @@ impl Transaction {
     pub fn is_err(&self) -> bool {
         !self.ok
     }
+
+    /// Returns the state the transaction was fired in, known once its breakdown was recorded, see `with_action`.
+    pub fn input(&self) -> Option<Vector> {
+        if self.net_consumed.len() != self.output.len() || self.net_produced.len() != self.output.len() {
+            return None;
+        }
+        let input = self.output.iter().zip(&self.net_consumed).zip(&self.net_produced);
+        Some(input.map(|((output, consumed), produced)| output + consumed - produced).collect())
+    }
+}
+
+impl Transaction {
+    /// Formats the transaction like its `Display` output, with the places named by their labels.
+    pub fn pretty(&self, sm: &StateMachine) -> String {
+        let mut out = String::new();
+        let _ = self.write_to(&mut out, &sm.places);
+        out
+    }
+
+    fn write_to(&self, f: &mut dyn fmt::Write, places: &[String]) -> fmt::Result {
+        write!(f, "{} x{} by {}", self.action, self.multiple, self.role)?;
+        if let Some(error) = &self.error {
+            return write!(f, " rejected: {}", error);
+        }
+        let reason = match (self.ok, self.inhibited, self.overflow, self.underflow) {
+            (true, ..) => None,
+            (_, true, ..) => Some("inhibited"),
+            (_, _, true, _) => Some("overflow"),
+            (_, _, _, true) => Some("underflow"),
+            _ => Some("failed"),
+        };
+        if let Some(reason) = reason {
+            write!(f, " {}", reason)?;
+        }
+        let changes = match self.input() {
+            Some(input) => diff_states(&input, &self.output, places),
+            None => Vec::new(),
+        };
+        if changes.is_empty() {
+            return match reason {
+                Some(_) => Ok(()),
+                None => write!(f, ": no change"),
+            };
+        }
+        let changes: Vec<String> = changes.iter().map(ToString::to_string).collect();
+        write!(f, ": {}", changes.join(", "))
+    }
+}
+
+impl fmt::Display for Transaction {
+    /// Formats the fired action and its changes by place offset, such as `sell x2 by default: #0: 5 → 1`,
+    /// or the reason it failed followed by the refused changes, such as `sell x3 by default underflow: #0: 5 → -1`.
+    /// See `pretty` for the labels of the places.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_to(f, &[])
+    }
+}
+
+/// `Marking` is a state vector known to have one entry per place of the state machine it was made for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Marking(Vector);
+
+impl Marking {
+    /// Creates a `Marking` holding the initial state of the given state machine.
+    pub fn for_machine(sm: &StateMachine) -> Self {
+        Self(sm.initial_vector())
+    }
+
+    /// Creates a `Marking` from a state vector after checking it matches the places of the state machine.
+    pub fn from_vector(sm: &StateMachine, state: Vector) -> Result<Self, TransformError> {
+        if state.len() != sm.places.len() {
+            return Err(TransformError::DimensionMismatch {
+                expected: sm.places.len(),
+                actual: state.len(),
+            });
+        }
+        Ok(Self(state))
+    }
+
+    /// Returns the underlying state vector.
+    pub fn as_vector(&self) -> &Vector {
+        &self.0
+    }
+
+    /// Fires the action and moves to the resulting state if the transformation succeeds.
+    pub fn apply(&mut self, sm: &StateMachine, action: &str, multiple: i32) -> Transaction {
+        let res = sm.transform(&self.0, action, multiple);
+        if res.is_ok() {
+            self.0 = res.output.clone();
+        }
+        res
+    }
+
+    /// Fires the action on behalf of the role with the case variables like `StateMachine::transform_with`,
+    /// moving to the resulting state if the transformation succeeds.
+    pub fn apply_with(
+        &mut self,
+        sm: &StateMachine,
+        action: &str,
+        multiple: i32,
+        role: &str,
+        variables: &Variables,
+    ) -> Transaction {
+        let res = sm.transform_with(&self.0, action, multiple, role, variables);
+        if res.is_ok() {
+            self.0 = res.output.clone();
+        }
+        res
+    }
+}
+
+impl Marking {
+    /// Formats the marking with the labels and units of the places, see `StateMachine::format_marking`.
+    pub fn pretty(&self, sm: &StateMachine) -> String {
+        sm.format_marking(&self.0)
+    }
+}
+
+impl fmt::Display for Marking {
+    /// Formats the token counts in offset order, such as `[3, 0]`, see `pretty` for the labels of the places.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl From<Marking> for Vector {
+    fn from(marking: Marking) -> Self {
+        marking.0
+    }
 }
 
 /// `Vasm` is a trait that represents a [vector addition state machine](https://en.wikipedia.org/wiki/Vector_addition_system).
@@ -373,18 +1335,38 @@ impl Vasm for StateMachine {
         self.initial.clone()
     }
 
-    // REVIEW: test that this works properly
+    /// Unknown actions, models without places and states that do not have one entry per place
+    /// are rejected with a `TransformError` and the input state is returned unchanged.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(
+        action = %action,
+        multiple = multiple,
+        state_hash = crate::tracing::state_hash(state),
+    )))]
     fn transform(&self, state: &Vector, action: &str, multiple: i32) -> Transaction {
-        let transition = self
-            .transitions
-            .get(action)
-            .unwrap_or_else(|| panic!("no transition for {}", action));
-
-        match self.model_type {
-            ModelType::PetriNet => self.petri_net_fire(state, transition, multiple),
-            ModelType::Elementary => self.elementary_fire(state, transition, multiple),
-            ModelType::Workflow => self.workflow_fire(state, transition, multiple),
+        let transition = match self.transitions.get(action) {
+            Some(t) => t,
+            None => {
+                let error = TransformError::UnknownAction { action: action.to_string() };
+                return Transaction::rejected(state, "", error).with_action(state, action, multiple);
+            }
+        };
+        if self.is_empty() {
+            let tx = Transaction::rejected(state, &transition.role, TransformError::EmptyModel);
+            return tx.with_action(state, action, multiple);
         }
+        if state.len() != self.places.len() {
+            let error = TransformError::DimensionMismatch {
+                expected: self.places.len(),
+                actual: state.len(),
+            };
+            return Transaction::rejected(state, &transition.role, error).with_action(state, action, multiple);
+        }
+
+        let tx = match &self.firing {
+            Some(semantics) => semantics.fire(self, state, transition, multiple),
+            None => self.outcome(state, transition, multiple).with_role(&transition.role),
+        };
+        tx.with_action(state, action, multiple)
     }
 }
 
@@ -396,5 +1378,656 @@ fn test_default_net() {
     });
     let vasm = mm.as_vasm();
     let state = vasm.initial_vector();
-    assert!(state.len() == 0);
+    assert!(state.is_empty());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{dead_transitions, explore, reduce, structurally_dead_transitions, Limits};
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    fn empty_model(p: &mut dyn FlowDsl) {
+        p.model_type("petriNet");
+        p.func("noop", "default", 0, 0);
+    }
+
+    #[test]
+    fn test_empty_model_rejects_transform() {
+        let sm = StateMachine::new(empty_model);
+        assert!(sm.is_empty());
+        assert!(sm.empty_vector().is_empty());
+
+        let res = sm.transform(&sm.initial_vector(), "noop", 1);
+        assert!(res.is_err());
+        assert_eq!(res.role, "default");
+        assert_eq!(res.error, Some(TransformError::EmptyModel));
+    }
+
+    #[test]
+    fn test_unknown_action_is_rejected() {
+        let sm = StateMachine::new(|p| {
+            p.cell("foo", Option::from(1), None, 0, 0);
+        });
+        let res = sm.transform(&vec![1], "missing", 1);
+        assert!(res.is_err());
+        assert_eq!(res.output, vec![1]);
+        assert_eq!(
+            res.error,
+            Some(TransformError::UnknownAction { action: "missing".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_transition_without_arcs_is_a_noop() {
+        let sm = StateMachine::new(|p| {
+            p.cell("foo", Option::from(1), None, 0, 0);
+            p.func("noop", "default", 0, 0);
+        });
+        let res = sm.transform(&sm.initial_vector(), "noop", 1);
+        assert!(res.is_ok());
+        assert_eq!(res.output, vec![1]);
+        assert_eq!(res.error, None);
+    }
+
+    #[test]
+    fn test_empty_delta_is_tolerated() {
+        let mut sm = StateMachine::new(|p| {
+            p.cell("foo", Option::from(1), None, 0, 0);
+            p.func("noop", "default", 0, 0);
+        });
+        sm.transitions.get_mut("noop").unwrap().delta = vec![];
+        assert!(sm.transform(&sm.initial_vector(), "noop", 1).is_ok());
+    }
+
+    #[test]
+    fn test_dimension_mismatch_is_rejected() {
+        let sm = StateMachine::new(|p| {
+            p.cell("foo", Option::from(1), None, 0, 0);
+            p.cell("bar", None, None, 0, 0);
+            p.func("move", "default", 0, 0);
+            p.arrow("foo", "move", 1);
+            p.arrow("move", "bar", 1);
+        });
+        for state in [vec![], vec![1], vec![1, 0, 0]] {
+            let res = sm.transform(&state, "move", 1);
+            assert!(res.is_err());
+            assert_eq!(res.output, state);
+            assert_eq!(
+                res.error,
+                Some(TransformError::DimensionMismatch { expected: 2, actual: state.len() })
+            );
+        }
+        assert!(Marking::from_vector(&sm, vec![1]).is_err());
+
+        let mut marking = Marking::for_machine(&sm);
+        assert!(marking.apply(&sm, "move", 1).is_ok());
+        assert_eq!(marking.as_vector(), &vec![0, 1]);
+        assert!(marking.apply(&sm, "move", 1).is_err());
+        assert_eq!(Vector::from(marking), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_format_marking_with_units() {
+        let sm = StateMachine::new(|p| {
+            p.cell("stock", Option::from(3), None, 0, 0);
+            p.cell("price", Option::from(250), None, 0, 0);
+            p.cell("open", Option::from(1), None, 0, 0);
+            p.unit("stock", "items");
+            p.unit("price", "€ cents");
+        });
+        assert_eq!(sm.format_tokens(0, 1), "1 items");
+        assert_eq!(
+            sm.format_marking(&sm.initial_vector()),
+            "stock: 3 items, price: 250 € cents, open: 1"
+        );
+    }
+
+    fn stages(p: &mut dyn FlowDsl) {
+        p.cell("a", Option::from(1), None, 0, 0);
+        p.cell("b", None, None, 0, 0);
+        p.cell("c", None, None, 0, 0);
+        p.func("ab", "default", 0, 0);
+        p.func("bc", "default", 0, 0);
+        p.func("ca", "default", 0, 0);
+        p.func("restart", "default", 0, 0);
+        p.arrow("a", "ab", 1);
+        p.arrow("ab", "b", 1);
+        p.arrow("b", "bc", 1);
+        p.arrow("bc", "c", 1);
+        p.arrow("c", "ca", 1);
+        p.arrow("ca", "a", 1);
+        p.arrow("restart", "a", 1);
+    }
+
+    fn elementary_stages() -> StateMachine {
+        StateMachine::new(|p| {
+            p.model_type("elementary");
+            stages(p);
+        })
+    }
+
+    fn workflow_stages(reentry: ReentryPolicy) -> StateMachine {
+        let mut sm = StateMachine::new(|p| {
+            p.model_type("workflow");
+            stages(p);
+        });
+        sm.reentry = reentry;
+        sm
+    }
+
+    fn binary_states(n: usize) -> Vec<Vector> {
+        (0..1 << n).map(|bits: usize| (0..n).map(|i| (bits >> i & 1) as i32).collect()).collect()
+    }
+
+    #[test]
+    fn test_workflow_without_reentry_matches_elementary() {
+        let elementary = elementary_stages();
+        let workflow = workflow_stages(ReentryPolicy::Never);
+        for state in binary_states(3) {
+            for action in ["ab", "bc", "ca", "restart"] {
+                for multiple in 1..=2 {
+                    let e = elementary.transform(&state, action, multiple);
+                    let w = workflow.transform(&state, action, multiple);
+                    assert_eq!(e.ok, w.ok, "{} from {:?} x{}", action, state, multiple);
+                    assert_eq!(e.output, w.output, "{} from {:?} x{}", action, state, multiple);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_workflow_semantics_on_petri_net() {
+        let workflow = workflow_stages(ReentryPolicy::Always);
+        let mut sm = StateMachine::new(stages);
+        sm.initial[0] = 2;
+        sm.set_semantics(SemanticsConfig::WORKFLOW.with_reentry(ReentryPolicy::Always));
+        assert_eq!((sm.initial_vector(), sm.capacity.clone()), (vec![2, 0, 0], StateMachine::new(stages).capacity));
+        assert!((0..3).all(|i| sm.place_capacity(i) == workflow.capacity[i]));
+        assert!(!sm.transform(&sm.initial_vector(), "ab", 1).is_ok());
+        for state in binary_states(3) {
+            for action in ["ab", "bc", "ca", "restart"] {
+                let w = workflow.transform(&state, action, 1);
+                let tx = sm.transform(&state, action, 1);
+                assert_eq!((tx.ok, tx.output), (w.ok, w.output), "{} from {:?}", action, state);
+            }
+        }
+        let machine = sm.to_machine();
+        assert!(machine.is_enabled(&sm.initial_vector(), machine.id("restart").unwrap(), 1));
+    }
+
+    #[test]
+    fn test_multi_token_single_active_place() {
+        let mut sm = StateMachine::new(stages);
+        sm.initial[0] = 3;
+        sm.set_semantics(SemanticsConfig {
+            single_active_place: true,
+            ..SemanticsConfig::PETRI_NET
+        });
+        assert!(!sm.is_enabled(&sm.initial_vector(), "ab", 1));
+        let tx = sm.transform(&sm.initial_vector(), "ab", 3);
+        assert_eq!((tx.ok, tx.output.clone()), (true, vec![0, 3, 0]));
+        assert!(!sm.is_enabled(&tx.output, "restart", 1));
+        assert!(sm.fire_maximal_step(&vec![1, 1, 1]).actions.is_empty());
+    }
+
+    #[test]
+    fn test_is_enabled_agrees_with_transform() {
+        let guarded = StateMachine::new(|p| {
+            p.cell("a", Option::from(1), Option::from(2), 0, 0);
+            p.cell("b", None, Option::from(1), 0, 0);
+            p.cell("c", None, None, 0, 0);
+            p.func("ab", "default", 0, 0);
+            p.func("bc", "default", 0, 0);
+            p.arrow("a", "ab", 1);
+            p.arrow("ab", "b", 1);
+            p.arrow("b", "bc", 1);
+            p.arrow("bc", "c", 1);
+            p.guard("c", "ab", 1);
+            p.guard("bc", "a", 1);
+        });
+        let models = [
+            guarded,
+            elementary_stages(),
+            workflow_stages(ReentryPolicy::Always),
+        ];
+        for sm in &models {
+            for state in binary_states(3) {
+                for action in ["ab", "bc", "ca", "restart", "missing"] {
+                    for multiple in 1..=2 {
+                        let enabled = sm.is_enabled(&state, action, multiple);
+                        assert_eq!(enabled, sm.transform(&state, action, multiple).is_ok(), "{} from {:?}", action, state);
+                    }
+                }
+            }
+        }
+        assert!(!models[0].is_enabled(&vec![1, 0], "ab", 1));
+    }
+
+    #[test]
+    fn test_inhibited_firing_leaves_state_unchanged() {
+        let sm = StateMachine::new(|p| {
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.cell("b", Option::from(2), None, 0, 0);
+            p.func("t", "default", 0, 0);
+            p.arrow("a", "t", 1);
+            p.guard("b", "t", 1);
+        });
+        let res = sm.transform(&sm.initial_vector(), "t", 1);
+        assert!(res.is_err() && res.inhibited);
+        assert!(!res.underflow && !res.overflow);
+        assert_eq!(res.output, sm.initial_vector());
+
+        // Firing twice would also underflow `a`, but an inhibited firing stops before the arithmetic.
+        let res = sm.transform(&sm.initial_vector(), "t", 2);
+        assert!(res.inhibited && !res.underflow);
+        assert_eq!(res.output, sm.initial_vector());
+    }
+
+    #[test]
+    fn test_reentry_policies() {
+        let reentered = vec![1, 0, 0];
+        assert!(elementary_stages().transform(&reentered, "restart", 1).is_err());
+        assert!(workflow_stages(ReentryPolicy::Never).transform(&reentered, "restart", 1).is_err());
+        assert!(workflow_stages(ReentryPolicy::PerTransition).transform(&reentered, "restart", 1).is_err());
+
+        let res = workflow_stages(ReentryPolicy::Always).transform(&reentered, "restart", 1);
+        assert!(res.is_ok());
+        assert!(!res.overflow);
+        assert_eq!(res.output, reentered);
+
+        let sm = StateMachine::new(|p| {
+            p.model_type("workflow");
+            stages(p);
+            p.allow_reentry("restart");
+        });
+        assert_eq!(sm.reentry, ReentryPolicy::PerTransition);
+        assert!(sm.transform(&reentered, "restart", 1).is_ok());
+        assert!(sm.transform(&vec![0, 1, 0], "restart", 1).is_err());
+        let json = sm.to_model().to_json().unwrap();
+        assert!(json.contains("\"allowReentry\":true"));
+        let restored = StateMachine::from_model(&mut PetriNet::from_json(json).unwrap());
+        assert!(restored.transitions["restart"].allow_reentry);
+    }
+
+    #[test]
+    fn test_transform_seq_is_atomic() {
+        let sm = StateMachine::new(|p| {
+            p.cell("foo", Option::from(2), None, 0, 0);
+            p.cell("bar", None, None, 0, 0);
+            p.func("move", "default", 0, 0);
+            p.arrow("foo", "move", 1);
+            p.arrow("move", "bar", 1);
+        });
+        let state = sm.initial_vector();
+        let (output, transactions) = sm.transform_seq(&state, &[("move", 1), ("move", 1)]).unwrap();
+        assert_eq!(output, vec![0, 2]);
+        assert_eq!(transactions.len(), 2);
+
+        let err = sm.transform_seq(&state, &[("move", 1), ("move", 2)]).unwrap_err();
+        assert_eq!(err.index, 1);
+        assert!(err.transaction.underflow);
+        assert_eq!(state, vec![2, 0]);
+
+        let err = sm.transform_seq(&state, &[("missing", 1)]).unwrap_err();
+        assert_eq!(err.to_string(), "action 0 of the sequence failed: no transition for missing");
+    }
+
+    #[test]
+    fn test_empty_model_analysis() {
+        let sm = StateMachine::new(empty_model);
+        let graph = explore(&sm, Limits::default());
+        assert!(graph.complete);
+        assert_eq!(graph.states, vec![Vector::new()]);
+        assert_eq!(graph.edge_count(), 0);
+        assert!(structurally_dead_transitions(&sm).is_empty());
+        assert_eq!(dead_transitions(&sm, Limits::default()), vec!["noop"]);
+
+        let (reduced, log) = reduce(&PetriNet::new());
+        assert!(log.is_empty());
+        assert!(reduced.places.is_empty());
+    }
+
+    #[test]
+    fn test_introspection() {
+        let sm = StateMachine::new(|p| {
+            p.cell("a", Option::from(2), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.cell("c", None, None, 0, 0);
+            p.func("move", "mover", 0, 0);
+            p.arrow("a", "move", 2);
+            p.arrow("move", "b", 1);
+            p.guard("c", "move", 1);
+        });
+        let t = &sm.transitions["move"];
+        assert_eq!((t.label(), t.role()), ("move", "mover"));
+        assert_eq!(t.delta(), &vec![-2, 1, 0]);
+        assert!(!t.allow_reentry());
+        assert_eq!(t.guards()["c"].delta(), &vec![0, 0, -1]);
+        assert!(!t.guards()["c"].read());
+        assert_eq!(sm.weights("move").unwrap().collect::<Vec<_>>(), vec![("a", -2), ("b", 1)]);
+        assert!(sm.weights("missing").is_none());
+    }
+
+    #[test]
+    fn test_to_model_round_trip() {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        net.set_attribute("", "owner", serde_json::json!("kitchen"));
+        let sm = StateMachine::from_model(&mut net);
+        let mut model = sm.to_model();
+        assert!(model.places.values().any(|p| p.x != 0));
+        assert_eq!(model.attributes["owner"], "kitchen");
+
+        let rebuilt = StateMachine::from_model(&mut model);
+        assert_eq!(rebuilt.places, sm.places);
+        assert_eq!(rebuilt.initial, sm.initial);
+        assert_eq!(rebuilt.capacity, sm.capacity);
+        for (label, t) in &sm.transitions {
+            assert_eq!(rebuilt.transitions[label].delta, t.delta);
+            assert_eq!(rebuilt.transitions[label].role, t.role);
+        }
+    }
+
+    #[test]
+    fn test_to_model_guards() {
+        let sm = StateMachine::new(|p| {
+            p.cell("a", Option::from(1), None, 0, 0);
+            p.cell("b", None, None, 0, 0);
+            p.func("t", "default", 0, 0);
+            p.arrow("a", "t", 1);
+            p.guard("b", "t", 2);
+            p.guard("t", "a", 1);
+        });
+        let mut model = sm.to_model();
+        let guards: Vec<_> = model.arcs.iter().filter(|a| a.inhibit == Some(true)).collect();
+        assert_eq!(guards.len(), 2);
+        assert!(guards.iter().any(|a| a.source == "b" && a.weight == Some(2) && a.read == Some(false)));
+        assert!(guards.iter().any(|a| a.source == "t" && a.target == "a" && a.read == Some(true)));
+
+        let rebuilt = StateMachine::from_model(&mut model);
+        assert_eq!(rebuilt.transitions["t"].guards["a"].delta, sm.transitions["t"].guards["a"].delta);
+        assert!(rebuilt.transitions["t"].guards["a"].read);
+    }
+
+    #[test]
+    fn test_to_machine() {
+        let guarded = StateMachine::new(|p| {
+            p.cell("a", Option::from(2), None, 0, 0);
+            p.cell("b", None, Option::from(1), 0, 0);
+            p.func("t", "default", 0, 0);
+            p.func("u", "default", 0, 0);
+            p.arrow("a", "t", 1);
+            p.arrow("t", "b", 1);
+            p.guard("b", "t", 1);
+            p.guard("t", "a", 2);
+            p.arrow("b", "u", 1);
+        });
+        let philosophers = StateMachine::from_model(&mut PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap());
+        for sm in [guarded, philosophers] {
+            let machine = sm.to_machine();
+            let compiled = sm.compile();
+            let states = explore(&sm, Limits::default()).states.into_states();
+            for state in &states {
+                for (id, action) in compiled.labels().iter().enumerate() {
+                    assert_eq!(machine.id(action), Some(id));
+                    let outcome = machine.fire(state, id, 1).unwrap();
+                    let tx = sm.transform(state, action, 1);
+                    assert_eq!((outcome.ok, outcome.inhibited, outcome.output), (tx.ok, tx.inhibited, tx.output));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_reset_and_transfer_arcs() {
+        let sm = StateMachine::new(|p| {
+            p.cell("inbox", Option::from(4), None, 0, 0);
+            p.cell("trigger", Option::from(1), None, 0, 0);
+            p.cell("archive", None, None, 0, 0);
+            p.cell("spam", Option::from(3), None, 0, 0);
+            p.func("flush", "default", 0, 0);
+            p.arrow("trigger", "flush", 1);
+            p.transfer("inbox", "flush", "archive", 2);
+            p.reset("spam", "flush");
+        });
+        assert_eq!(sm.transitions["flush"].transfers().len(), 2);
+        let tx = sm.transform(&sm.initial_vector(), "flush", 1);
+        assert!(tx.is_ok());
+        assert_eq!(tx.output, vec![0, 0, 8, 0]);
+        assert!(sm.is_enabled(&vec![0, 1, 0, 0], "flush", 1));
+        assert!(!sm.is_enabled(&vec![4, 0, 0, 3], "flush", 1));
+
+        let rebuilt = StateMachine::from_model(&mut sm.to_model());
+        assert_eq!(rebuilt.transitions["flush"].transfers(), sm.transitions["flush"].transfers());
+        let machine = sm.to_machine();
+        let outcome = machine.fire(&sm.initial_vector(), machine.id("flush").unwrap(), 1).unwrap();
+        assert_eq!(outcome.output, tx.output);
+    }
+
+    #[test]
+    fn test_transaction_breakdown() {
+        let sm = StateMachine::new(|p| {
+            p.cell("stock", Option::from(5), None, 0, 0);
+            p.cell("sold", None, None, 0, 0);
+            p.func("sell", "default", 0, 0);
+            p.arrow("stock", "sell", 2);
+            p.arrow("sell", "sold", 1);
+        });
+        let tx = sm.transform(&sm.initial_vector(), "sell", 2);
+        assert_eq!((tx.action.as_str(), tx.multiple), ("sell", 2));
+        assert_eq!((tx.net_consumed, tx.net_produced), (vec![4, 0], vec![0, 2]));
+
+        let failed = sm.transform(&sm.initial_vector(), "sell", 3);
+        assert_eq!((failed.action.as_str(), failed.multiple), ("sell", 3));
+        assert!(failed.is_err() && failed.input() == Some(sm.initial_vector()));
+        assert_eq!((failed.net_consumed, failed.net_produced), (vec![6, 0], vec![0, 3]));
+        assert_eq!(sm.transform(&vec![0], "sell", 1).action, "sell");
+
+        let sm = StateMachine::new(|p| {
+            p.cell("open", Option::from(1), None, 0, 0);
+            p.cell("done", None, None, 0, 0);
+            p.func("check", "default", 0, 0);
+            p.guard("check", "open", 1);
+            p.arrow("check", "done", 1);
+        });
+        let tx = sm.transform(&sm.initial_vector(), "check", 1);
+        assert!(tx.is_ok());
+        assert_eq!((tx.net_consumed, tx.net_produced), (vec![0, 0], vec![0, 1]));
+    }
+
+    #[test]
+    fn test_diff_states() {
+        let places = vec!["queue".to_string(), "done".to_string()];
+        let changes = diff_states(&vec![3, 0, 1], &vec![2, 0, -1], &places);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].to_string(), "queue: 3 → 2");
+        assert_eq!((changes[1].place.as_str(), changes[1].delta()), ("#2", -2));
+        assert_eq!(changes[1].to_string(), "#2: 1 → -1 (negative)");
+        assert_eq!(diff_states(&vec![1], &vec![1, 2], &places)[0].to_string(), "done: 0 → 2");
+
+        let sm = StateMachine::new(|p| {
+            p.cell("stock", Option::from(5), None, 0, 0);
+            p.cell("sold", None, None, 0, 0);
+            p.func("sell", "default", 0, 0);
+            p.arrow("stock", "sell", 2);
+            p.arrow("sell", "sold", 1);
+        });
+        let tx = sm.transform(&sm.initial_vector(), "sell", 2);
+        assert_eq!(tx.input(), Some(sm.initial_vector()));
+        assert_eq!(tx.to_string(), "sell x2 by default: #0: 5 → 1, #1: 0 → 2");
+        assert_eq!(
+            sm.transform(&sm.initial_vector(), "sell", 3).to_string(),
+            "sell x3 by default underflow: #0: 5 → -1 (negative), #1: 0 → 3"
+        );
+        assert_eq!(
+            sm.transform(&vec![0], "sell", 1).to_string(),
+            "sell x1 by default rejected: expected a state of 2 places, got 1"
+        );
+    }
+
+    #[test]
+    fn test_pretty_print() {
+        let sm = StateMachine::new(|p| {
+            p.cell("stock", Option::from(5), Option::from(10), 0, 0);
+            p.cell("sold", None, None, 0, 0);
+            p.cell("jammed", None, None, 0, 0);
+            p.func("sell", "clerk", 0, 0);
+            p.func("restock", "clerk", 0, 0);
+            p.arrow("stock", "sell", 2);
+            p.arrow("sell", "sold", 1);
+            p.guard("jammed", "sell", 1);
+            p.arrow("restock", "stock", 1);
+            p.unit("sold", "cans");
+        });
+        assert_eq!(sm.to_string(), "petriNet with places stock, sold, jammed and transitions restock, sell");
+        assert_eq!(sm.transitions["sell"].to_string(), "sell by clerk");
+        assert_eq!(
+            sm.pretty(),
+            [
+                "petriNet",
+                "places:",
+                "  stock: 5 (capacity 10)",
+                "  sold: 0 cans",
+                "  jammed: 0",
+                "transitions:",
+                "  restock by clerk: stock +1",
+                "  sell by clerk: stock -2, sold +1, unless jammed ≥ 1",
+            ]
+            .join("\n")
+        );
+
+        let mut marking = Marking::for_machine(&sm);
+        let tx = marking.apply(&sm, "sell", 1);
+        assert_eq!(tx.pretty(&sm), "sell x1 by clerk: stock: 5 → 3, sold: 0 → 1");
+        assert_eq!(marking.to_string(), "[3, 1, 0]");
+        assert_eq!(marking.pretty(&sm), "stock: 3, sold: 1 cans, jammed: 0");
+    }
+
+    #[test]
+    fn test_marking_weighted_arcs() {
+        let sm = StateMachine::new(|p| {
+            p.cell("dock", Option::from(25), None, 0, 0);
+            p.cell("truck", None, None, 0, 0);
+            p.cell("log", None, None, 0, 0);
+            p.func("load", "default", 0, 0);
+            p.marking_arrow("dock", "load", "all max 10");
+            p.marking_arrow("load", "truck", "all of dock max 10");
+            p.marking_arrow("load", "log", "half of dock");
+        });
+        let mut state = sm.initial_vector();
+        for (dock, truck, log) in [(15, 10, 12), (5, 20, 19), (0, 25, 21)] {
+            let tx = sm.transform(&state, "load", 1);
+            assert!(tx.is_ok());
+            assert_eq!(tx.output, vec![dock, truck, log]);
+            state = tx.output;
+        }
+        assert!(!sm.is_enabled(&vec![10, 0, 0], "load", 2));
+
+        let rebuilt = StateMachine::from_model(&mut sm.to_model());
+        assert_eq!(rebuilt.transitions["load"].marking_arcs(), sm.transitions["load"].marking_arcs());
+        let json = serde_json::to_string(&sm).unwrap();
+        let back: StateMachine = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.transitions["load"].marking_arcs().len(), 3);
+
+        let mut net = sm.to_model();
+        net.add_marking_arc("dock", "load", "twice of dock");
+        let err = StateMachine::try_from_model(&mut net).unwrap_err();
+        assert!(matches!(
+            &err,
+            ModelError::InvalidMarkingWeight { source, target, .. } if source == "dock" && target == "load"
+        ));
+        assert!(err.to_string().starts_with("invalid marking weight of arc dock -> load: "));
+    }
+
+    #[test]
+    fn test_try_from_model_rejects_invalid_nets() {
+        let declare = || {
+            let mut net = PetriNet::new();
+            net.declare(|p| {
+                p.cell("ready", Option::from(1), None, 0, 0);
+                p.func("go", "default", 0, 0);
+                p.arrow("ready", "go", 1);
+            });
+            net
+        };
+        assert!(StateMachine::try_from_model(&mut declare()).is_ok());
+
+        let mut net = declare();
+        net.add_arc("go", "nowhere", None, None, None, None, None);
+        assert_eq!(
+            StateMachine::try_from_model(&mut net).unwrap_err(),
+            ModelError::DanglingArc {
+                source: "go".to_string(),
+                target: "nowhere".to_string()
+            }
+        );
+        let mut net = declare();
+        net.add_transfer_arc("ready", "go", "nowhere", 1);
+        assert!(matches!(StateMachine::try_from_model(&mut net), Err(ModelError::DanglingArc { .. })));
+
+        let mut net = declare();
+        net.places.get_mut("ready").unwrap().initial = Some(-1);
+        assert_eq!(
+            StateMachine::try_from_model(&mut net).unwrap_err().to_string(),
+            "place ready starts with -1 tokens"
+        );
+        let mut net = declare();
+        net.places.get_mut("ready").unwrap().offset = 1;
+        assert!(matches!(StateMachine::try_from_model(&mut net), Err(ModelError::InvalidOffset { offset: 1, .. })));
+
+        let mut net = declare();
+        net.declare(|p| p.role("admin", None, &[]));
+        assert_eq!(
+            StateMachine::try_from_model(&mut net).unwrap_err().to_string(),
+            "transition go belongs to undeclared role default"
+        );
+    }
+
+    #[test]
+    fn test_invalid_condition_is_rejected() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.cell("claim", Option::from(1), None, 0, 0);
+            p.func("approve", "default", 0, 0);
+            p.arrow("claim", "approve", 1);
+        });
+        net.set_condition("approve", "amount >");
+        let err = StateMachine::try_from_model(&mut net).unwrap_err();
+        assert!(matches!(&err, ModelError::InvalidCondition { transition, .. } if transition == "approve"));
+        assert!(err.to_string().starts_with("invalid condition of approve: "));
+
+        net.set_condition("approve", "amount > 1000");
+        assert!(StateMachine::try_from_model(&mut net).unwrap().transitions["approve"].condition.is_some());
+    }
+
+    #[test]
+    fn test_implied_roles() {
+        let mut net = PetriNet::new();
+        net.declare(|p| {
+            p.role("clerk", None, &[]);
+            p.role("manager", Some("approves refunds"), &["clerk"]);
+            p.default_role("clerk");
+            p.cell("requested", Option::from(1), None, 0, 0);
+            p.cell("refunded", None, None, 0, 0);
+            p.func("refund", "clerk", 0, 0);
+            p.arrow("requested", "refund", 1);
+            p.arrow("refund", "refunded", 1);
+        });
+        net.transitions.get_mut("refund").unwrap().role = None;
+        let sm = StateMachine::from_model(&mut net);
+        assert_eq!(sm.transitions["refund"].role(), "clerk");
+        assert_eq!(sm.registry.default_role(), "clerk");
+        assert!(sm.roles.contains_key("clerk"));
+
+        let state = sm.initial_vector();
+        assert!(sm.transform_as(&state, "refund", 1, "manager").is_ok());
+        assert!(sm.transform_as(&state, "refund", 1, "clerk").is_ok());
+        let tx = sm.transform_as(&state, "refund", 1, "guest");
+        assert!(matches!(tx.error, Some(TransformError::RoleMismatch { .. })));
+
+        let model = sm.to_model();
+        assert_eq!(model.default_role.as_deref(), Some("clerk"));
+        assert_eq!(model.roles["manager"].implies, vec!["clerk"]);
+    }
 }
@@ -0,0 +1,158 @@
+use core::cmp::Ordering;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::capacity::Capacity;
+
+pub use crate::engine::Vector;
+
+/// Returns a state with no token in any of `len` places.
+pub fn zero(len: usize) -> Vector {
+    vec![0; len]
+}
+
+/// Checks if no entry of the vector is set.
+pub fn is_zero(v: &[i32]) -> bool {
+    v.iter().all(|x| *x == 0)
+}
+
+/// Adds `multiple` times `delta` to `state` and checks the result against the capacities, as transitions fire.
+///
+/// A missing entry of `delta` counts as zero and a missing capacity as unbounded. The sum is computed without
+/// wrapping, a place whose count would not fit in an i32 overflows and its output saturates.
+///
+/// # Returns
+///
+/// * The output state, whether it is valid, and whether any place overflowed or underflowed.
+///
+pub fn add(capacity: &[Capacity], state: &Vector, delta: &Vector, multiple: i32) -> (Vector, bool, bool, bool) {
+    #[cfg(feature = "simd")]
+    if delta.len() == state.len() && capacity.len() == state.len() {
+        return crate::simd::vector_add(capacity, state, delta, multiple);
+    }
+    scalar_add(capacity, state, delta, multiple)
+}
+
+/// Subtracts `multiple` times `delta` from `state` and checks the result against the capacities, see `add`.
+pub fn sub(capacity: &[Capacity], state: &Vector, delta: &Vector, multiple: i32) -> (Vector, bool, bool, bool) {
+    add(capacity, state, delta, -multiple)
+}
+
+pub(crate) fn scalar_add(
+    capacity: &[Capacity],
+    state: &Vector,
+    delta: &Vector,
+    multiple: i32,
+) -> (Vector, bool, bool, bool) {
+    let mut overflow = false;
+    let mut underflow = false;
+    let mut output: Vector = Vec::new();
+    let mut ok = true;
+    for i in 0..state.len() {
+        let tokens = fired(state[i], *delta.get(i).unwrap_or(&0), multiple);
+        output.push(saturate(tokens));
+        let cap = capacity.get(i).copied().unwrap_or_default();
+        if tokens < 0 {
+            underflow = true;
+            ok = false; // underflow: contains negative
+        } else if tokens > i32::MAX as i64 || !cap.allows(output[i]) {
+            overflow = true;
+            ok = false; // overflow: exceeds capacity
+        }
+    }
+    (output, ok, overflow, underflow)
+}
+
+/// Returns the tokens of a place after adding `multiple` times `delta`, in a width where it cannot wrap.
+pub(crate) fn fired(tokens: i32, delta: i32, multiple: i32) -> i64 {
+    tokens as i64 + delta as i64 * multiple as i64
+}
+
+/// Clamps a token count to the range of an i32.
+pub(crate) fn saturate(tokens: i64) -> i32 {
+    tokens.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+/// Compares two vectors entry by entry, a missing entry counting as zero.
+///
+/// # Returns
+///
+/// * `Less` or `Greater` if every entry of `a` is at most or at least that of `b` and they differ,
+///   `Equal` if they do not differ, and None if they are incomparable.
+///
+pub fn compare(a: &[i32], b: &[i32]) -> Option<Ordering> {
+    let mut ordering = Ordering::Equal;
+    for i in 0..a.len().max(b.len()) {
+        match (a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)), ordering) {
+            (Ordering::Equal, _) => {}
+            (entry, Ordering::Equal) => ordering = entry,
+            (entry, _) if entry != ordering => return None,
+            _ => {}
+        }
+    }
+    Some(ordering)
+}
+
+/// Checks if every entry of `a` is at least that of `b`, which is how a marking covers another.
+pub fn ge(a: &[i32], b: &[i32]) -> bool {
+    matches!(compare(a, b), Some(Ordering::Greater | Ordering::Equal))
+}
+
+/// Checks if every entry of `a` is at most that of `b`.
+pub fn le(a: &[i32], b: &[i32]) -> bool {
+    ge(b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_sub() {
+        let capacity = [Capacity::Unbounded, Capacity::Bounded(2)];
+        assert_eq!(
+            add(&capacity, &vec![1, 1], &vec![-1, 1], 1),
+            (vec![0, 2], true, false, false)
+        );
+        assert_eq!(
+            add(&capacity, &vec![1, 1], &vec![-1, 1], 2),
+            (vec![-1, 3], false, true, true)
+        );
+        assert_eq!(
+            sub(&capacity, &vec![0, 2], &vec![-1, 1], 1),
+            (vec![1, 1], true, false, false)
+        );
+        assert_eq!(add(&[], &vec![1, 1], &vec![1], 1), (vec![2, 1], true, false, false));
+        assert_eq!(zero(3), vec![0, 0, 0]);
+        assert!(is_zero(&zero(3)) && !is_zero(&[0, 1]));
+    }
+
+    #[test]
+    fn test_add_does_not_wrap() {
+        let unbounded = [Capacity::Unbounded; 2];
+        assert_eq!(
+            add(&unbounded, &vec![i32::MAX, 0], &vec![1, 0], 1),
+            (vec![i32::MAX, 0], false, true, false)
+        );
+        assert_eq!(
+            add(&unbounded, &vec![0, 0], &vec![0, i32::MAX], 2),
+            (vec![0, i32::MAX], false, true, false)
+        );
+        assert_eq!(
+            add(&unbounded, &vec![1, 0], &vec![i32::MIN, 0], 3),
+            (vec![i32::MIN, 0], false, false, true)
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare(&[1, 2], &[1, 2]), Some(Ordering::Equal));
+        assert_eq!(compare(&[1, 3], &[1, 2]), Some(Ordering::Greater));
+        assert_eq!(compare(&[0, 2], &[1, 2]), Some(Ordering::Less));
+        assert_eq!(compare(&[0, 3], &[1, 2]), None);
+        assert_eq!(compare(&[1], &[1, 0]), Some(Ordering::Equal));
+        assert!(ge(&[2, 2], &[1, 2]) && !ge(&[2], &[1, 2]));
+        assert!(le(&[1, 2], &[2, 2]) && !le(&[0, 3], &[1, 2]));
+    }
+}
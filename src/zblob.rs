@@ -1,6 +1,10 @@
-use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::convert::Infallible;
+use std::fmt;
 
-use crate::compression::{compress_brotli_encode, decompress_brotli_decode};
+use serde::{Deserialize, Serialize};
+
+use crate::compression::{compress_brotli_encode, decode_within, DecodeError, DecodeLimits};
 use crate::oid::Oid;
 use crate::petri_net::PetriNet;
 
@@ -23,6 +27,167 @@ pub struct Zblob {
     pub referrer: String,
     /// The creation time of the zblob.
     pub created_at: String,
+    /// The CID of the zblob this one was derived from, empty for an original model.
+    pub previous_cid: String,
+    /// The position of the zblob in its history, 1 for an original model.
+    pub version: u32,
+    /// The key-wrapping metadata of an encrypted zblob as JSON, empty for a plain one.
+    pub encryption: String,
+}
+
+/// The maximum length of a title, in characters.
+pub const MAX_TITLE_LEN: usize = 256;
+/// The maximum length of a description, in characters.
+pub const MAX_DESCRIPTION_LEN: usize = 4096;
+/// The maximum length of the normalized keywords, in characters.
+pub const MAX_KEYWORDS_LEN: usize = 512;
+/// The maximum length of a referrer, in characters.
+pub const MAX_REFERRER_LEN: usize = 2048;
+
+/// `MetadataError` describes a zblob field that cannot be stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetadataError {
+    /// The field is longer than the allowed number of characters.
+    TooLong { field: &'static str, limit: usize },
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataError::TooLong { field, limit } => write!(f, "{} is longer than {} characters", field, limit),
+        }
+    }
+}
+
+impl std::error::Error for MetadataError {}
+
+/// Splits keywords on commas, trims and lowercases them, collapses inner whitespace so multi-word keywords
+/// such as `dining philosophers` survive, and drops duplicates, keeping the first occurrence of each.
+/// The result is joined with commas, such as `classic,deadlock`.
+pub fn normalize_keywords(keywords: &str) -> String {
+    let mut seen = HashSet::new();
+    keywords
+        .split(',')
+        .map(|k| k.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase())
+        .filter(|k| !k.is_empty())
+        .filter(|k| seen.insert(k.clone()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Returns the current time in RFC 3339 format, such as `2024-02-25T14:58:27.123Z`.
+#[cfg(feature = "timestamps")]
+pub fn timestamp() -> String {
+    time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap()
+}
+
+/// The bundle entry holding the model as JSON.
+pub const MODEL_ENTRY: &str = "model.json";
+/// The bundle entry holding the position of every node, see `Layout`.
+pub const LAYOUT_ENTRY: &str = "layout.json";
+/// The bundle entry holding the documentation of the model.
+pub const README_ENTRY: &str = "README.md";
+/// The bundle entry holding the model in the text DSL, as it was declared.
+pub const DECLARATION_ENTRY: &str = "declaration.pflow";
+
+/// `Layout` maps the label of each place and transition to its (x, y) position.
+pub type Layout = BTreeMap<String, (i32, i32)>;
+
+/// `Bundle` is a set of named files packed into a single zblob, such as a model with its layout and readme.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bundle {
+    pub entries: BTreeMap<String, String>,
+}
+
+/// The document a bundle is encoded as, the `bundle` key tells it apart from a plain model.
+#[derive(Serialize, Deserialize)]
+struct BundleDocument {
+    bundle: BTreeMap<String, String>,
+}
+
+impl Bundle {
+    /// Creates a bundle holding the model of the net.
+    pub fn from_net(net: &PetriNet) -> Self {
+        Self::default().with_entry(MODEL_ENTRY, &net.to_json().unwrap())
+    }
+
+    /// Adds or replaces an entry.
+    pub fn with_entry(mut self, name: &str, content: &str) -> Self {
+        self.entries.insert(name.to_string(), content.to_string());
+        self
+    }
+
+    /// Adds the position of every node of the net as the layout entry.
+    pub fn with_layout(self, net: &PetriNet) -> Self {
+        let layout: Layout = net
+            .places
+            .iter()
+            .map(|(label, p)| (label.clone(), (p.x, p.y)))
+            .chain(net.transitions.iter().map(|(label, t)| (label.clone(), (t.x, t.y))))
+            .collect();
+        self.with_entry(LAYOUT_ENTRY, &serde_json::to_string(&layout).unwrap())
+    }
+
+    /// Adds the readme entry.
+    pub fn with_readme(self, readme: &str) -> Self {
+        self.with_entry(README_ENTRY, readme)
+    }
+
+    /// Adds the net in the text DSL as the declaration entry.
+    pub fn with_declaration(self, net: &PetriNet) -> Self {
+        self.with_entry(DECLARATION_ENTRY, &net.to_dsl_source())
+    }
+
+    /// Returns the content of the entry with the given name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries.get(name).map(|e| e.as_str())
+    }
+
+    /// Parses the model entry.
+    pub fn model(&self) -> Option<PetriNet> {
+        PetriNet::from_json(self.get(MODEL_ENTRY)?.to_string()).ok()
+    }
+
+    /// Parses the layout entry.
+    pub fn layout(&self) -> Option<Layout> {
+        serde_json::from_str(self.get(LAYOUT_ENTRY)?).ok()
+    }
+
+    /// Returns the readme entry.
+    pub fn readme(&self) -> Option<&str> {
+        self.get(README_ENTRY)
+    }
+
+    /// Returns the declaration entry.
+    pub fn declaration(&self) -> Option<&str> {
+        self.get(DECLARATION_ENTRY)
+    }
+
+    fn encode(&self) -> String {
+        let document = BundleDocument {
+            bundle: self.entries.clone(),
+        };
+        compress_brotli_encode(&serde_json::to_string(&document).unwrap())
+    }
+
+    /// Decodes the compressed content of a zblob, a plain model becomes a bundle with only the model entry.
+    fn decode(base64_zipped: &str, limits: DecodeLimits) -> Result<Self, DecodeError> {
+        let decoded = decode_within(base64_zipped, limits)?;
+        let bundle = match serde_json::from_str::<BundleDocument>(&decoded) {
+            Ok(document) => Self {
+                entries: document.bundle,
+            },
+            Err(_) => Self::default().with_entry(MODEL_ENTRY, &decoded),
+        };
+        if bundle.entries.len() > limits.max_entries {
+            return Err(DecodeError::TooManyEntries {
+                limit: limits.max_entries,
+            });
+        }
+        Ok(bundle)
+    }
 }
 
 const EMPTY_NET: &str = "UEsDBAoAAAAAAER3WVjjbbhPbAAAAGwAAAAKAAAAbW9kZWwuanNvbnsKICAibW9kZWxUeXBlIjogInBldHJpTmV0IiwKICAidmVyc2lvbiI6ICJ2MCIsCiAgInBsYWNlcyI6IHsKICB9LAogICJ0cmFuc2l0aW9ucyI6IHsKICB9LAogICJhcmNzIjogWwogIF0KfVBLAQIUAAoAAAAAAER3WVjjbbhPbAAAAGwAAAAKAAAAAAAAAAAAAAAAAAAAAABtb2RlbC5qc29uUEsFBgAAAAABAAEAOAAAAJQAAAAAAA==";
@@ -38,6 +203,9 @@ impl Default for Zblob {
             keywords: "new".to_string(),
             referrer: "".to_string(),
             created_at: "".to_string(),
+            previous_cid: "".to_string(),
+            version: 1,
+            encryption: "".to_string(),
         }
     }
 }
@@ -45,23 +213,278 @@ impl Default for Zblob {
 impl Zblob {
     pub fn from_string(encoded_zip: Option<&str>) -> Self {
         let mut zblob = Zblob::default();
-        if encoded_zip.is_some() {
-            zblob.base64_zipped = encoded_zip.unwrap().to_string();
-            zblob.ipfs_cid = Oid::new(encoded_zip.unwrap().as_bytes())
+        if let Some(encoded_zip) = encoded_zip {
+            zblob.base64_zipped = encoded_zip.to_string();
+            zblob.ipfs_cid = Oid::new(encoded_zip.as_bytes())
                 .unwrap()
                 .to_string();
             zblob.keywords = "".to_string();
+            #[cfg(feature = "timestamps")]
+            {
+                zblob.created_at = timestamp();
+            }
         }
         zblob
     }
+
+    /// Trims the title, description and referrer, normalizes the keywords with `normalize_keywords`,
+    /// and stamps `created_at` with the current time if it is empty and the `timestamps` feature is enabled.
+    pub fn normalize(&mut self) {
+        for field in [&mut self.title, &mut self.description, &mut self.referrer] {
+            *field = field.trim().to_string();
+        }
+        self.keywords = normalize_keywords(&self.keywords);
+        #[cfg(feature = "timestamps")]
+        if self.created_at.is_empty() {
+            self.created_at = timestamp();
+        }
+    }
+
+    /// Checks the length of the title, description, keywords and referrer.
+    pub fn validate(&self) -> Result<(), MetadataError> {
+        let fields = [
+            ("title", &self.title, MAX_TITLE_LEN),
+            ("description", &self.description, MAX_DESCRIPTION_LEN),
+            ("keywords", &self.keywords, MAX_KEYWORDS_LEN),
+            ("referrer", &self.referrer, MAX_REFERRER_LEN),
+        ];
+        match fields.into_iter().find(|(_, value, limit)| value.chars().count() > *limit) {
+            Some((field, _, limit)) => Err(MetadataError::TooLong { field, limit }),
+            None => Ok(()),
+        }
+    }
     pub fn from_net(net: &PetriNet) -> Self {
         let net_json = net.to_json().unwrap();
         let data = compress_brotli_encode(&net_json);
-        return Self::from_string(Some(&data));
+        Self::from_string(Some(&data))
+    }
+
+    /// Packs the edited net as the next version of `parent`, keeping its title, description and keywords.
+    ///
+    /// The CID of a derived zblob also covers its parent and version, see `content_cid`, so reverting to an
+    /// earlier net still makes a new zblob instead of one with the CID of that earlier version.
+    pub fn derive_from(parent: &Zblob, net: &PetriNet) -> Self {
+        let mut zblob = Self {
+            title: parent.title.clone(),
+            description: parent.description.clone(),
+            keywords: parent.keywords.clone(),
+            previous_cid: parent.ipfs_cid.clone(),
+            version: parent.version + 1,
+            ..Self::from_net(net)
+        };
+        zblob.ipfs_cid = zblob.content_cid();
+        zblob
+    }
+
+    /// Computes the CID of the compressed content, prefixed with the parent CID and version for derived zblobs.
+    pub fn content_cid(&self) -> String {
+        let content = match self.parent_cid() {
+            Some(parent) => format!("{}\n{}\n{}", parent, self.version, self.base64_zipped),
+            None => self.base64_zipped.clone(),
+        };
+        Oid::new(content.as_bytes()).unwrap().to_string()
+    }
+
+    /// Returns the CID of the zblob this one was derived from.
+    pub fn parent_cid(&self) -> Option<&str> {
+        Some(self.previous_cid.as_str()).filter(|cid| !cid.is_empty())
+    }
+
+    /// Returns this zblob followed by its ancestors, newest first, looking up each parent by CID.
+    /// The walk stops at an original model, at a parent `lookup` does not know, or where the chain loops.
+    pub fn history<F: FnMut(&str) -> Option<Zblob>>(&self, mut lookup: F) -> Vec<Zblob> {
+        self.try_history(|cid| Ok::<_, Infallible>(lookup(cid)))
+            .unwrap_or_else(|never| match never {})
+    }
+
+    /// Like `history`, for lookups that can fail, such as database queries.
+    pub fn try_history<E, F>(&self, mut lookup: F) -> Result<Vec<Zblob>, E>
+    where
+        F: FnMut(&str) -> Result<Option<Zblob>, E>,
+    {
+        let mut seen = HashSet::from([self.ipfs_cid.clone()]);
+        let mut history = vec![self.clone()];
+        while let Some(cid) = history.last().unwrap().parent_cid() {
+            if !seen.insert(cid.to_string()) {
+                break;
+            }
+            match lookup(cid)? {
+                Some(parent) => history.push(parent),
+                None => break,
+            }
+        }
+        Ok(history)
+    }
+
+    /// Packs every entry of the bundle into a zblob, a bundle holding only a model is packed like `from_net`.
+    pub fn from_bundle(bundle: &Bundle) -> Self {
+        match bundle.model() {
+            Some(net) if bundle.entries.len() == 1 => Self::from_net(&net),
+            _ => Self::from_string(Some(&bundle.encode())),
+        }
+    }
+
+    /// Unpacks the entries of the zblob, a zblob made from a single net holds only the model entry.
+    ///
+    /// Encrypted zblobs hold ciphertext and have no entries until they are decrypted.
+    pub fn bundle(&self) -> Option<Bundle> {
+        self.bundle_within(DecodeLimits::default()).ok()
+    }
+
+    /// Unpacks the entries of the zblob, failing if it decompresses to more bytes or entries than the limits allow.
+    pub fn bundle_within(&self, limits: DecodeLimits) -> Result<Bundle, DecodeError> {
+        if !self.encryption.is_empty() {
+            return Err(DecodeError::Encrypted);
+        }
+        Bundle::decode(&self.base64_zipped, limits)
+    }
+
+    /// Returns the layout entry of a bundled zblob.
+    pub fn layout(&self) -> Option<Layout> {
+        self.bundle()?.layout()
+    }
+
+    /// Returns the readme entry of a bundled zblob.
+    pub fn readme(&self) -> Option<String> {
+        self.bundle()?.readme().map(|r| r.to_string())
+    }
+
+    /// Returns the declaration entry of a bundled zblob.
+    pub fn declaration(&self) -> Option<String> {
+        self.bundle()?.declaration().map(|d| d.to_string())
     }
 
     pub fn to_net(&self) -> PetriNet {
-        let decoded = decompress_brotli_decode(&self.base64_zipped).unwrap();
-        return serde_json::from_str(&decoded).unwrap();
+        let bundle = self.bundle().unwrap();
+        serde_json::from_str(bundle.get(MODEL_ENTRY).unwrap()).unwrap()
+    }
+
+    /// Like `to_net`, returning None if the zblob is encrypted or holds no valid model instead of panicking.
+    pub fn try_to_net(&self) -> Option<PetriNet> {
+        self.bundle()?.model()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::DINING_PHILOSOPHERS;
+
+    use super::*;
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let bundle = Bundle::from_net(&net)
+            .with_layout(&net)
+            .with_readme("# Dining philosophers")
+            .with_declaration(&net)
+            .with_entry("notes.txt", "five forks");
+        let zblob = Zblob::from_bundle(&bundle);
+        assert_ne!(zblob.ipfs_cid, net.to_zblob().ipfs_cid);
+        assert_eq!(zblob.bundle().unwrap(), bundle);
+        assert_eq!(zblob.to_net().to_json().unwrap(), net.to_json().unwrap());
+        assert_eq!(zblob.layout().unwrap()["chopstick1"], (811, 426));
+        assert_eq!(zblob.layout().unwrap().len(), 25);
+        assert_eq!(zblob.readme().unwrap(), "# Dining philosophers");
+        assert_eq!(zblob.declaration().unwrap(), net.to_dsl_source());
+        assert_eq!(zblob.bundle().unwrap().get("notes.txt"), Some("five forks"));
+    }
+
+    #[test]
+    fn test_bundle_limits() {
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let bundle = (0..10).fold(Bundle::from_net(&net), |b, i| b.with_entry(&format!("{}.txt", i), "x"));
+        let zblob = Zblob::from_bundle(&bundle);
+        let limits = DecodeLimits {
+            max_entries: 5,
+            ..Default::default()
+        };
+        assert_eq!(zblob.bundle_within(limits), Err(DecodeError::TooManyEntries { limit: 5 }));
+        let limits = DecodeLimits {
+            max_size: 100,
+            ..Default::default()
+        };
+        assert_eq!(zblob.bundle_within(limits), Err(DecodeError::TooLarge { limit: 100 }));
+        assert_eq!(zblob.bundle_within(DecodeLimits::default()).unwrap(), bundle);
+    }
+
+    #[test]
+    fn test_history() {
+        let mut net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let original = Zblob {
+            title: "philosophers".to_string(),
+            ..net.to_zblob()
+        };
+        assert_eq!((original.parent_cid(), original.version), (None, 1));
+        net.add_place("napkin", 1, None, None, 0, 0);
+        let edited = Zblob::derive_from(&original, &net);
+        assert_eq!(edited.parent_cid(), Some(original.ipfs_cid.as_str()));
+        assert_eq!((edited.version, edited.title.as_str()), (2, "philosophers"));
+        assert_ne!(edited.ipfs_cid, net.to_zblob().ipfs_cid);
+        assert_eq!(edited.ipfs_cid, edited.content_cid());
+        net.add_place("bowl", 1, None, None, 0, 0);
+        let latest = Zblob::derive_from(&edited, &net);
+
+        let blobs: BTreeMap<String, Zblob> = [&original, &edited, &latest]
+            .iter()
+            .map(|z| (z.ipfs_cid.clone(), (*z).clone()))
+            .collect();
+        let lookup = |cid: &str| blobs.get(cid).cloned();
+        let versions = |h: Vec<Zblob>| h.iter().map(|z| z.version).collect::<Vec<_>>();
+        assert_eq!(versions(latest.history(lookup)), vec![3, 2, 1]);
+        assert_eq!(versions(edited.history(lookup)), vec![2, 1]);
+        assert_eq!(versions(latest.history(|_| None)), vec![3]);
+
+        let looped = Zblob::derive_from(&latest, &original.to_net());
+        assert_eq!(looped.base64_zipped, original.base64_zipped);
+        assert_ne!(looped.ipfs_cid, original.ipfs_cid);
+        assert_eq!(versions(looped.history(lookup)), vec![4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize_keywords(" Classic, deadlock , classic,,DEADLOCK, dining "), "classic,deadlock,dining");
+        assert_eq!(normalize_keywords("Dining  Philosophers,classic"), "dining philosophers,classic");
+        assert_eq!(normalize_keywords(""), "");
+
+        let mut zblob = Zblob {
+            title: "  philosophers ".to_string(),
+            keywords: "Classic, classic".to_string(),
+            created_at: "".to_string(),
+            ..Zblob::default()
+        };
+        zblob.normalize();
+        assert_eq!((zblob.title.as_str(), zblob.keywords.as_str()), ("philosophers", "classic"));
+        assert_eq!(zblob.created_at.is_empty(), cfg!(not(feature = "timestamps")));
+        assert_eq!(zblob.validate(), Ok(()));
+
+        zblob.description = "x".repeat(MAX_DESCRIPTION_LEN + 1);
+        let err = MetadataError::TooLong {
+            field: "description",
+            limit: MAX_DESCRIPTION_LEN,
+        };
+        assert_eq!(zblob.validate(), Err(err));
+        zblob.description = "é".repeat(MAX_DESCRIPTION_LEN);
+        assert_eq!(zblob.validate(), Ok(()));
+    }
+
+    #[cfg(feature = "timestamps")]
+    #[test]
+    fn test_created_at() {
+        let created_at = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap().to_zblob().created_at;
+        let (date, time) = created_at.split_once('T').unwrap();
+        assert_eq!(date.len(), 10, "{}", created_at);
+        assert!(time.ends_with('Z') && date.starts_with("20"), "{}", created_at);
+    }
+
+    #[test]
+    fn test_single_model_zblob() {
+        let net = PetriNet::from_json(DINING_PHILOSOPHERS.to_string()).unwrap();
+        let zblob = net.to_zblob();
+        assert_eq!(Zblob::from_bundle(&Bundle::from_net(&net)).ipfs_cid, zblob.ipfs_cid);
+        let bundle = zblob.bundle().unwrap();
+        assert_eq!(bundle.entries.keys().collect::<Vec<_>>(), vec![MODEL_ENTRY]);
+        assert_eq!(bundle.model().unwrap().places.len(), 15);
+        assert!(zblob.layout().is_none() && zblob.readme().is_none());
     }
 }